@@ -0,0 +1,124 @@
+//! Forward pass throughput of the YOLOX variants on the CPU (NdArray backend).
+//!
+//! Run with `cargo bench --bench model_inference`. The peak memory allocated during the forward
+//! passes of each benchmark is reported after it, through a counting global allocator.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use burn::{
+    backend::NdArray,
+    tensor::{Distribution, Tensor},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use yolox_burn::model::yolox::Yolox;
+
+type Backend = NdArray<f32>;
+
+const HEIGHT: usize = 640;
+const WIDTH: usize = 640;
+const NUM_CLASSES: usize = 80;
+const BATCH_SIZES: [usize; 3] = [1, 4, 8];
+
+/// System allocator which keeps track of the peak allocated memory.
+struct PeakAllocator {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl PeakAllocator {
+    /// Reset the peak to the currently allocated memory.
+    fn reset_peak(&self) {
+        self.peak
+            .store(self.current.load(Ordering::SeqCst), Ordering::SeqCst);
+    }
+
+    /// Peak allocated memory (in bytes) since the last reset.
+    fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = self.current.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            self.peak.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        self.current.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator {
+    current: AtomicUsize::new(0),
+    peak: AtomicUsize::new(0),
+};
+
+/// Benchmarked YOLOX variants.
+#[derive(Debug, Clone, Copy)]
+enum YoloxVariant {
+    Nano,
+    Tiny,
+}
+
+impl YoloxVariant {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Nano => "yolox-nano",
+            Self::Tiny => "yolox-tiny",
+        }
+    }
+}
+
+/// Create a new (randomly initialized) model on the default device.
+fn setup_yolox(variant: YoloxVariant) -> Yolox<Backend> {
+    let device = Default::default();
+    match variant {
+        YoloxVariant::Nano => Yolox::yolox_nano(NUM_CLASSES, &device),
+        YoloxVariant::Tiny => Yolox::yolox_tiny(NUM_CLASSES, &device),
+    }
+}
+
+fn forward_throughput(c: &mut Criterion) {
+    let device = Default::default();
+    let mut group = c.benchmark_group("yolox_forward");
+    group.sample_size(10);
+
+    for variant in [YoloxVariant::Nano, YoloxVariant::Tiny] {
+        let model = setup_yolox(variant);
+
+        for batch_size in BATCH_SIZES {
+            let input = Tensor::<Backend, 4>::random(
+                [batch_size, 3, HEIGHT, WIDTH],
+                Distribution::Uniform(0., 255.),
+                &device,
+            );
+
+            ALLOCATOR.reset_peak();
+            group.throughput(Throughput::Elements(batch_size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(variant.name(), batch_size),
+                &input,
+                |b, input| b.iter(|| model.forward(input.clone()).into_data()),
+            );
+            println!(
+                "{}/{batch_size}: peak memory {:.1} MiB",
+                variant.name(),
+                ALLOCATOR.peak() as f64 / (1024. * 1024.)
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, forward_throughput);
+criterion_main!(benches);
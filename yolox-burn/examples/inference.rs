@@ -1,7 +1,10 @@
 use std::path::Path;
 
 use image::{DynamicImage, ImageBuffer};
-use yolox_burn::model::{boxes::nms, weights, yolox::Yolox, BoundingBox};
+use yolox_burn::{
+    model::{weights, yolox::Yolox, DetectionModel},
+    types::Detection,
+};
 
 use burn::{
     backend::NdArray,
@@ -29,7 +32,7 @@ fn to_tensor<B: Backend, T: Element>(
 /// # Arguments
 ///
 /// * `image`: Original input image.
-/// * `boxes` - Bounding boxes, grouped per class.
+/// * `detections` - Detected objects.
 /// * `color` - [R, G, B] color values to draw the boxes.
 /// * `ratio` - [x, y] aspect ratio to scale the predicted boxes.
 ///
@@ -38,7 +41,7 @@ fn to_tensor<B: Backend, T: Element>(
 /// The image annotated with bounding boxes.
 fn draw_boxes(
     image: DynamicImage,
-    boxes: &[Vec<BoundingBox>],
+    detections: &[Detection],
     color: &[u8; 3],
     ratio: &[f32; 2], // (x, y) ratio
 ) -> DynamicImage {
@@ -68,27 +71,26 @@ fn draw_boxes(
     // Annotate the original image and print boxes information.
     let (image_h, image_w) = (image.height(), image.width());
     let mut image = image.to_rgb8();
-    for (class_index, bboxes_for_class) in boxes.iter().enumerate() {
-        for b in bboxes_for_class.iter() {
-            let xmin = (b.xmin * ratio[0]).clamp(0., image_w as f32 - 1.);
-            let ymin = (b.ymin * ratio[1]).clamp(0., image_h as f32 - 1.);
-            let xmax = (b.xmax * ratio[0]).clamp(0., image_w as f32 - 1.);
-            let ymax = (b.ymax * ratio[1]).clamp(0., image_h as f32 - 1.);
-
-            println!(
-                "Predicted {} ({:.2}) at [{:.2}, {:.2}, {:.2}, {:.2}]",
-                class_index, b.confidence, xmin, ymin, xmax, ymax,
-            );
-
-            draw_rect(
-                &mut image,
-                xmin as u32,
-                xmax as u32,
-                ymin as u32,
-                ymax as u32,
-                color,
-            );
-        }
+    for det in detections.iter() {
+        let [xmin, ymin, xmax, ymax] = det.box_xyxy;
+        let xmin = (xmin * ratio[0]).clamp(0., image_w as f32 - 1.);
+        let ymin = (ymin * ratio[1]).clamp(0., image_h as f32 - 1.);
+        let xmax = (xmax * ratio[0]).clamp(0., image_w as f32 - 1.);
+        let ymax = (ymax * ratio[1]).clamp(0., image_h as f32 - 1.);
+
+        println!(
+            "Predicted {} ({:.2}) at [{:.2}, {:.2}, {:.2}, {:.2}]",
+            det.class_id, det.score, xmin, ymin, xmax, ymax,
+        );
+
+        draw_rect(
+            &mut image,
+            xmin as u32,
+            xmax as u32,
+            ymin as u32,
+            ymax as u32,
+            color,
+        );
     }
     DynamicImage::ImageRgb8(image)
 }
@@ -123,22 +125,14 @@ pub fn main() {
     )
     .unsqueeze::<4>(); // [B, C, H, W]
 
-    // Forward pass
-    let out = model.forward(x);
-
-    // Post-processing
-    let [_, num_boxes, num_outputs] = out.dims();
-    let boxes = out.clone().slice([0..1, 0..num_boxes, 0..4]);
-    let obj_scores = out.clone().slice([0..1, 0..num_boxes, 4..5]);
-    let cls_scores = out.slice([0..1, 0..num_boxes, 5..num_outputs]);
-    let scores = cls_scores * obj_scores;
-    let boxes = nms(boxes, scores, 0.65, 0.5);
+    // Forward pass and post-processing
+    let detections = model.infer(x);
 
     // Draw outputs and save results
     let (h, w) = (img.height(), img.width());
     let img_out = draw_boxes(
         img,
-        &boxes[0],
+        &detections[0],
         &[239u8, 62u8, 5u8],
         &[w as f32 / WIDTH as f32, h as f32 / HEIGHT as f32],
     );
@@ -0,0 +1,154 @@
+use burn::tensor::{backend::Backend, Tensor};
+
+use super::{uniform, Sample, Transform};
+
+/// Maximum pixel value of the images.
+const MAX_PIXEL_VALUE: f32 = 255.;
+/// ITU-R 601-2 luma weights of the RGB channels.
+const LUMA: [f32; 3] = [0.299, 0.587, 0.114];
+/// RGB to YIQ color space transform.
+const RGB_TO_YIQ: [[f32; 3]; 3] = [
+    [0.299, 0.587, 0.114],
+    [0.596, -0.274, -0.322],
+    [0.211, -0.523, 0.312],
+];
+/// YIQ to RGB color space transform.
+const YIQ_TO_RGB: [[f32; 3]; 3] = [
+    [1.0, 0.956, 0.621],
+    [1.0, -0.272, -0.647],
+    [1.0, -1.106, 1.703],
+];
+
+/// Grayscale version of the image. Shape: `[1, H, W]`.
+fn grayscale<B: Backend>(image: Tensor<B, 3>) -> Tensor<B, 3> {
+    let weights = Tensor::<B, 1>::from_floats(LUMA, &image.device()).reshape([3, 1, 1]);
+
+    (image * weights).sum_dim(0)
+}
+
+/// Matrix rotating the hue of RGB colors by the given angle (in radians), as a rotation of the
+/// chroma in the YIQ color space.
+fn hue_rotation(angle: f32) -> [[f32; 3]; 3] {
+    let (sin, cos) = angle.sin_cos();
+    let rotation = [[1., 0., 0.], [0., cos, -sin], [0., sin, cos]];
+    let matmul = |a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]| -> [[f32; 3]; 3] {
+        core::array::from_fn(|i| {
+            core::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum::<f32>())
+        })
+    };
+
+    matmul(&YIQ_TO_RGB, &matmul(&rotation, &RGB_TO_YIQ))
+}
+
+/// Randomly change the brightness, contrast, saturation and hue of the image.
+///
+/// The brightness, contrast and saturation factors are uniformly sampled in
+/// `[max(0, 1 - x), 1 + x]` and the hue shift in `[-hue, hue]` (as a fraction of a full turn
+/// of the color wheel). The adjustments are applied in this order.
+pub struct ColorJitter {
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    hue: f32,
+}
+
+impl ColorJitter {
+    /// Create a new color jitter.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness`: How much to jitter the brightness.
+    /// * `contrast` - How much to jitter the contrast.
+    /// * `saturation` - How much to jitter the saturation.
+    /// * `hue` - How much to jitter the hue, in `[0, 0.5]`.
+    pub fn new(brightness: f32, contrast: f32, saturation: f32, hue: f32) -> Self {
+        assert!(
+            brightness >= 0. && contrast >= 0. && saturation >= 0.,
+            "jitter factors should be non-negative"
+        );
+        assert!((0. ..=0.5).contains(&hue), "hue should be in [0, 0.5]");
+
+        Self {
+            brightness,
+            contrast,
+            saturation,
+            hue,
+        }
+    }
+}
+
+impl<B: Backend> Transform<B> for ColorJitter {
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B> {
+        let device = image.device();
+        let [channels, height, width] = image.dims();
+        assert_eq!(channels, 3, "color jitter expects RGB images");
+
+        let [u_brightness, u_contrast, u_saturation, u_hue] = uniform::<B, 4>(&device);
+        let factor = |x: f32, u: f32| (1. + x * (2. * u - 1.)).max(0.);
+
+        // Brightness: scale the pixel values
+        let image = image
+            .mul_scalar(factor(self.brightness, u_brightness))
+            .clamp(0., MAX_PIXEL_VALUE);
+
+        // Contrast: blend with the mean gray level
+        let contrast = factor(self.contrast, u_contrast);
+        let mean = grayscale(image.clone()).mean().reshape([1, 1, 1]);
+        let image = (image.mul_scalar(contrast) + mean.mul_scalar(1. - contrast))
+            .clamp(0., MAX_PIXEL_VALUE);
+
+        // Saturation: blend with the grayscale image
+        let saturation = factor(self.saturation, u_saturation);
+        let gray = grayscale(image.clone());
+        let image = (image.mul_scalar(saturation) + gray.mul_scalar(1. - saturation))
+            .clamp(0., MAX_PIXEL_VALUE);
+
+        // Hue: rotate the chroma
+        let angle = self.hue * (2. * u_hue - 1.) * 2. * core::f32::consts::PI;
+        let rotation =
+            Tensor::<B, 1>::from_floats(hue_rotation(angle).concat().as_slice(), &device)
+                .reshape([3, 3]);
+        let image = rotation
+            .matmul(image.reshape([3, height * width]))
+            .reshape([3, height, width])
+            .clamp(0., MAX_PIXEL_VALUE);
+
+        (image, boxes, labels)
+    }
+}
+
+/// Normalize each channel of the image with the given mean and standard deviation.
+///
+/// The mean and standard deviation are expressed in the pixel value range of the images (e.g.,
+/// `[123.675, 116.28, 103.53]` and `[58.395, 57.12, 57.375]` for the ImageNet statistics).
+pub struct Normalize {
+    mean: [f32; 3],
+    std: [f32; 3],
+}
+
+impl Normalize {
+    /// Create a new normalization with the per-channel mean and standard deviation.
+    pub fn new(mean: [f32; 3], std: [f32; 3]) -> Self {
+        Self { mean, std }
+    }
+}
+
+impl<B: Backend> Transform<B> for Normalize {
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B> {
+        let device = image.device();
+        let mean = Tensor::<B, 1>::from_floats(self.mean, &device).reshape([3, 1, 1]);
+        let std = Tensor::<B, 1>::from_floats(self.std, &device).reshape([3, 1, 1]);
+
+        ((image - mean) / std, boxes, labels)
+    }
+}
@@ -0,0 +1,469 @@
+use alloc::vec;
+use burn::tensor::{
+    backend::Backend,
+    module::interpolate,
+    ops::{InterpolateMode, InterpolateOptions},
+    Tensor,
+};
+use rand::Rng;
+
+use super::{filter_boxes, uniform, Sample, Transform};
+use crate::transforms::BoxTransformer;
+
+/// Number of attempts to sample a valid crop before falling back to a center crop.
+const MAX_CROP_ATTEMPTS: usize = 10;
+
+/// Split the bounding boxes into their `xmin`, `ymin`, `xmax` and `ymax` columns.
+fn box_columns<B: Backend>(boxes: Tensor<B, 2>) -> [Tensor<B, 2>; 4] {
+    let [n, _] = boxes.dims();
+    core::array::from_fn(|i| boxes.clone().slice([0..n, i..i + 1]))
+}
+
+/// Horizontally flip the image with probability `p`.
+pub struct RandomHorizontalFlip {
+    p: f32,
+}
+
+impl RandomHorizontalFlip {
+    /// Create a new random horizontal flip with probability `p`.
+    pub fn new(p: f32) -> Self {
+        Self { p }
+    }
+}
+
+impl<B: Backend> Transform<B> for RandomHorizontalFlip {
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B> {
+        let [u] = uniform::<B, 1>(&image.device());
+        if u >= self.p {
+            return (image, boxes, labels);
+        }
+
+        let [_, _, width] = image.dims();
+        let boxes = boxes.map(|boxes| BoxTransformer::horizontal_flip(boxes, width as f32));
+
+        (image.flip([2]), boxes, labels)
+    }
+}
+
+/// Minimum fraction of the area of a box which should remain inside a crop for the box to be
+/// kept.
+const MIN_CROP_AREA_FRACTION: f32 = 0.01;
+
+/// [Random resized crop](RandomResizedCrop) configuration.
+#[derive(Debug, Clone)]
+pub struct RandomResizedCropConfig {
+    size: (usize, usize),
+    scale: (f32, f32),
+    ratio: (f32, f32),
+    interpolation: InterpolateMode,
+}
+
+impl RandomResizedCropConfig {
+    /// Create a new instance of the random resized crop [config](RandomResizedCropConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: Output size `(height, width)`.
+    /// * `scale` - Range of the area of the crop, relative to the area of the image (e.g.,
+    ///   `(0.08, 1.0)`).
+    /// * `ratio` - Range of the aspect ratio (width / height) of the crop (e.g., `(0.75, 1.33)`).
+    /// * `interpolation` - Interpolation mode of the resize.
+    pub fn new(
+        size: (usize, usize),
+        scale: (f32, f32),
+        ratio: (f32, f32),
+        interpolation: InterpolateMode,
+    ) -> Self {
+        assert!(
+            scale.0 > 0. && scale.0 <= scale.1,
+            "invalid crop scale range"
+        );
+        assert!(
+            ratio.0 > 0. && ratio.0 <= ratio.1,
+            "invalid crop aspect ratio range"
+        );
+
+        Self {
+            size,
+            scale,
+            ratio,
+            interpolation,
+        }
+    }
+
+    /// Initialize a new [random resized crop](RandomResizedCrop) transform.
+    pub fn init(&self) -> RandomResizedCrop {
+        RandomResizedCrop {
+            config: self.clone(),
+        }
+    }
+
+    /// Sample the crop `(top, left, height, width)` of an image with the given size, with the
+    /// algorithm of torchvision: up to 10 attempts to sample a crop with a valid scale and aspect
+    /// ratio, then a center crop with the aspect ratio clamped to the ratio range.
+    ///
+    /// `uniform` draws values from the uniform distribution on `[0, 1)`.
+    fn sample_crop(
+        &self,
+        [height, width]: [usize; 2],
+        mut uniform: impl FnMut() -> f32,
+    ) -> (usize, usize, usize, usize) {
+        let area = (height * width) as f32;
+        let (log_ratio_min, log_ratio_max) = (self.ratio.0.ln(), self.ratio.1.ln());
+
+        for _ in 0..MAX_CROP_ATTEMPTS {
+            let target_area = area * (self.scale.0 + uniform() * (self.scale.1 - self.scale.0));
+            let ratio = (log_ratio_min + uniform() * (log_ratio_max - log_ratio_min)).exp();
+
+            let w = (target_area * ratio).sqrt().round() as usize;
+            let h = (target_area / ratio).sqrt().round() as usize;
+            if w > 0 && w <= width && h > 0 && h <= height {
+                let top = (uniform() * (height - h + 1) as f32) as usize;
+                let left = (uniform() * (width - w + 1) as f32) as usize;
+                return (top.min(height - h), left.min(width - w), h, w);
+            }
+        }
+
+        // Fall back to a center crop
+        let in_ratio = width as f32 / height as f32;
+        let (h, w) = if in_ratio < self.ratio.0 {
+            ((width as f32 / self.ratio.0).round() as usize, width)
+        } else if in_ratio > self.ratio.1 {
+            (height, (height as f32 * self.ratio.1).round() as usize)
+        } else {
+            (height, width)
+        };
+        let (h, w) = (h.clamp(1, height), w.clamp(1, width));
+
+        ((height - h) / 2, (width - w) / 2, h, w)
+    }
+
+    /// Crop the image, resize it to the output size and update the boxes.
+    fn crop_and_resize<B: Backend>(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+        (top, left, h, w): (usize, usize, usize, usize),
+    ) -> Sample<B> {
+        let [channels, _, _] = image.dims();
+        let (out_h, out_w) = self.size;
+
+        let image = image.slice([0..channels, top..top + h, left..left + w]);
+        let image = interpolate(
+            image.unsqueeze::<4>(),
+            [out_h, out_w],
+            InterpolateOptions::new(self.interpolation.clone()),
+        )
+        .squeeze(0);
+
+        let (boxes, labels) = match boxes {
+            Some(boxes) => {
+                // Clip the boxes to the crop and rescale them to the output size
+                let (sx, sy) = (out_w as f32 / w as f32, out_h as f32 / h as f32);
+                let clip = |x: Tensor<B, 2>, offset: usize, max: usize| {
+                    x.sub_scalar(offset as f32).clamp(0., max as f32)
+                };
+                let [xmin, ymin, xmax, ymax] = box_columns(boxes);
+                let area = (xmax.clone() - xmin.clone()) * (ymax.clone() - ymin.clone());
+                let (xmin, xmax) = (clip(xmin, left, w), clip(xmax, left, w));
+                let (ymin, ymax) = (clip(ymin, top, h), clip(ymax, top, h));
+                let clipped_area = (xmax.clone() - xmin.clone()) * (ymax.clone() - ymin.clone());
+
+                // Remove the boxes (mostly) outside of the crop
+                let [n, _] = xmin.dims();
+                let keep = clipped_area
+                    .clone()
+                    .greater_elem(0.)
+                    .float()
+                    .mul(
+                        clipped_area
+                            .greater_equal(area.mul_scalar(MIN_CROP_AREA_FRACTION))
+                            .float(),
+                    )
+                    .reshape([n])
+                    .greater_elem(0.5);
+                let boxes = Tensor::cat(
+                    vec![
+                        xmin.mul_scalar(sx),
+                        ymin.mul_scalar(sy),
+                        xmax.mul_scalar(sx),
+                        ymax.mul_scalar(sy),
+                    ],
+                    1,
+                );
+                let (boxes, labels) = filter_boxes(boxes, labels, keep);
+
+                (Some(boxes), labels)
+            }
+            None => (None, labels),
+        };
+
+        (image, boxes, labels)
+    }
+}
+
+/// Crop a random region of the image and resize it to the configured size.
+///
+/// The boxes are clipped to the cropped region and rescaled, and the boxes with less than 1% of
+/// their area inside the region are removed (along with their labels).
+pub fn random_resized_crop<B: Backend>(
+    image: Tensor<B, 3>,
+    boxes: Option<Tensor<B, 2>>,
+    labels: Option<Tensor<B, 1>>,
+    config: &RandomResizedCropConfig,
+    rng: &mut impl Rng,
+) -> Sample<B> {
+    let [_, height, width] = image.dims();
+    let crop = config.sample_crop([height, width], || rng.gen());
+
+    config.crop_and_resize(image, boxes, labels, crop)
+}
+
+/// Crop a random region of the image and resize it to the given size, with the random numbers
+/// drawn from the backend generator (see [random_resized_crop]).
+pub struct RandomResizedCrop {
+    config: RandomResizedCropConfig,
+}
+
+impl RandomResizedCrop {
+    /// Create a new random resized crop with bilinear interpolation.
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: Output size `[height, width]`.
+    /// * `scale` - Range of the area of the crop, relative to the area of the image (e.g.,
+    ///   `(0.08, 1.0)`).
+    /// * `ratio` - Range of the aspect ratio (width / height) of the crop (e.g., `(0.75, 1.33)`).
+    pub fn new([height, width]: [usize; 2], scale: (f32, f32), ratio: (f32, f32)) -> Self {
+        RandomResizedCropConfig::new((height, width), scale, ratio, InterpolateMode::Bilinear)
+            .init()
+    }
+}
+
+impl<B: Backend> Transform<B> for RandomResizedCrop {
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B> {
+        let [_, height, width] = image.dims();
+        let device = image.device();
+        let crop = self
+            .config
+            .sample_crop([height, width], || uniform::<B, 1>(&device)[0]);
+
+        self.config.crop_and_resize(image, boxes, labels, crop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn horizontal_flip_boxes() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::from_floats([[[1., 2., 3., 4.]]; 3], &device);
+        let boxes = Tensor::from_floats([[0., 0., 1., 1.], [1., 0., 4., 1.]], &device);
+
+        let (image, boxes, _) = RandomHorizontalFlip::new(1.).apply(image, Some(boxes), None);
+
+        image
+            .into_data()
+            .assert_eq(&TensorData::from([[[4f32, 3., 2., 1.]]; 3]), false);
+        boxes.unwrap().into_data().assert_eq(
+            &TensorData::from([[3f32, 0., 4., 1.], [0., 0., 3., 1.]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn horizontal_flip_never() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::from_floats([[[1., 2., 3., 4.]]; 3], &device);
+        let boxes = Tensor::from_floats([[0., 0., 1., 1.]], &device);
+
+        let (image, boxes, _) = RandomHorizontalFlip::new(0.).apply(image, Some(boxes), None);
+
+        image
+            .into_data()
+            .assert_eq(&TensorData::from([[[1f32, 2., 3., 4.]]; 3]), false);
+        boxes
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([[0f32, 0., 1., 1.]]), false);
+    }
+
+    #[test]
+    fn resized_crop_full_image() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::ones([3, 8, 8], &device);
+        let boxes = Tensor::from_floats([[2., 2., 6., 4.]], &device);
+        let labels = Tensor::from_floats([1.], &device);
+
+        let (image, boxes, labels) = RandomResizedCrop::new([16, 4], (1., 1.), (1., 1.)).apply(
+            image,
+            Some(boxes),
+            Some(labels),
+        );
+
+        assert_eq!(image.dims(), [3, 16, 4]);
+        boxes
+            .unwrap()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32, 4., 3., 8.]]), 4);
+        assert_eq!(labels.unwrap().dims(), [1]);
+    }
+
+    #[test]
+    fn resized_crop_removes_boxes_outside() {
+        let config =
+            RandomResizedCropConfig::new((4, 4), (0.25, 0.25), (1., 1.), InterpolateMode::Nearest);
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::ones([3, 8, 8], &device);
+        let boxes = Tensor::from_floats([[0., 0., 2., 2.], [6., 6., 8., 8.]], &device);
+        let labels = Tensor::from_floats([1., 2.], &device);
+
+        // Top-left quarter of the image
+        let (image, boxes, labels) =
+            config.crop_and_resize(image, Some(boxes), Some(labels), (0, 0, 4, 4));
+
+        assert_eq!(image.dims(), [3, 4, 4]);
+        boxes
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([[0f32, 0., 2., 2.]]), false);
+        labels
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([1f32]), false);
+    }
+
+    #[test]
+    fn sampled_crop_within_image() {
+        let config = RandomResizedCropConfig::new(
+            (32, 32),
+            (0.08, 1.),
+            (0.75, 4. / 3.),
+            InterpolateMode::Bilinear,
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let (top, left, h, w) = config.sample_crop([48, 64], || rng.gen());
+            assert!(h > 0 && w > 0);
+            assert!(top + h <= 48 && left + w <= 64);
+        }
+    }
+
+    #[test]
+    fn random_resized_crop_output_size() {
+        let config = RandomResizedCropConfig::new(
+            (24, 40),
+            (0.08, 1.),
+            (0.75, 4. / 3.),
+            InterpolateMode::Bilinear,
+        );
+        let device = Default::default();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..10 {
+            let image = Tensor::<TestBackend, 3>::ones([3, 48, 64], &device);
+            let (image, boxes, labels) = random_resized_crop(image, None, None, &config, &mut rng);
+
+            assert_eq!(image.dims(), [3, 24, 40]);
+            assert!(boxes.is_none() && labels.is_none());
+        }
+    }
+
+    #[test]
+    fn random_resized_crop_full_scale_is_resize() {
+        let config =
+            RandomResizedCropConfig::new((32, 16), (1., 1.), (1., 1.), InterpolateMode::Nearest);
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::ones([3, 8, 8], &device);
+        let boxes = Tensor::from_floats([[1., 2., 5., 6.], [0., 0., 8., 8.]], &device);
+        let labels = Tensor::from_floats([1., 2.], &device);
+
+        let (image, boxes, labels) = random_resized_crop(
+            image,
+            Some(boxes),
+            Some(labels),
+            &config,
+            &mut StdRng::seed_from_u64(0),
+        );
+
+        // Scale of 2 horizontally and 4 vertically
+        assert_eq!(image.dims(), [3, 32, 16]);
+        boxes.unwrap().into_data().assert_approx_eq(
+            &TensorData::from([[2f32, 8., 10., 24.], [0., 0., 16., 32.]]),
+            4,
+        );
+        labels
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([1f32, 2.]), false);
+    }
+
+    #[test]
+    fn resized_crop_filters_degenerate_boxes() {
+        let config =
+            RandomResizedCropConfig::new((8, 8), (0.25, 0.25), (1., 1.), InterpolateMode::Nearest);
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::ones([3, 16, 16], &device);
+        let boxes = Tensor::from_floats(
+            [
+                // Inside the crop
+                [1., 1., 3., 3.],
+                // Less than 1% of the area inside the crop
+                [7.9, 7.9, 15.9, 15.9],
+                // Empty box
+                [2., 2., 2., 5.],
+                // Half of the area inside the crop
+                [4., 6., 8., 10.],
+            ],
+            &device,
+        );
+        let labels = Tensor::from_floats([1., 2., 3., 4.], &device);
+
+        let (_, boxes, labels) =
+            config.crop_and_resize(image, Some(boxes), Some(labels), (0, 0, 8, 8));
+
+        boxes
+            .unwrap()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32, 1., 3., 3.], [4., 6., 8., 8.]]), 4);
+        labels
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([1f32, 4.]), false);
+    }
+
+    #[test]
+    fn sampled_crop_falls_back_to_center() {
+        // A square crop with the area of the image never fits in a non-square image
+        let config =
+            RandomResizedCropConfig::new((8, 8), (1., 1.), (1., 1.), InterpolateMode::Nearest);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        assert_eq!(config.sample_crop([8, 16], || rng.gen()), (0, 4, 8, 8));
+        assert_eq!(config.sample_crop([20, 10], || rng.gen()), (5, 0, 10, 10));
+    }
+
+    #[test]
+    #[should_panic = "invalid crop scale range"]
+    fn invalid_crop_scale() {
+        let _ = RandomResizedCrop::new([8, 8], (0.5, 0.1), (1., 1.));
+    }
+}
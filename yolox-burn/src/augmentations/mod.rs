@@ -0,0 +1,193 @@
+//! Image augmentations for training, applied jointly to an image and its bounding boxes.
+//!
+//! Images have shape `[C, H, W]` with pixel values in the range `[0, 255]` (as returned by the
+//! datasets) and bounding boxes are `(xmin, ymin, xmax, ymax)` in pixel coordinates with shape
+//! `[num_boxes, 4]`. Random numbers are drawn from the backend random number generator, which can
+//! be seeded with [Backend::seed].
+mod color;
+mod geometric;
+
+pub use color::*;
+pub use geometric::*;
+
+use alloc::{boxed::Box, vec::Vec};
+use burn::tensor::{
+    backend::Backend, Bool, Device, Distribution, ElementConversion, Int, Tensor, TensorData,
+};
+
+/// Image, bounding boxes and labels of a training sample.
+pub type Sample<B> = (Tensor<B, 3>, Option<Tensor<B, 2>>, Option<Tensor<B, 1>>);
+
+/// Image transform, which also updates the bounding boxes and labels of the objects (e.g., when
+/// the image is flipped or cropped).
+pub trait Transform<B: Backend> {
+    /// Apply the transform to the image and its (optional) bounding boxes and labels.
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B>;
+}
+
+/// Apply a sequence of [transforms](Transform) in order.
+pub struct Compose<B: Backend> {
+    transforms: Vec<Box<dyn Transform<B>>>,
+}
+
+impl<B: Backend> Compose<B> {
+    /// Create a new composition of the given transforms.
+    pub fn new(transforms: Vec<Box<dyn Transform<B>>>) -> Self {
+        Self { transforms }
+    }
+}
+
+impl<B: Backend> Transform<B> for Compose<B> {
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B> {
+        self.transforms.iter().fold(
+            (image, boxes, labels),
+            |(image, boxes, labels), transform| transform.apply(image, boxes, labels),
+        )
+    }
+}
+
+/// Randomly apply a [transform](Transform) with probability `p`.
+pub struct RandomApply<B: Backend> {
+    transform: Box<dyn Transform<B>>,
+    p: f32,
+}
+
+impl<B: Backend> RandomApply<B> {
+    /// Create a new random transform applied with probability `p`.
+    pub fn new(transform: Box<dyn Transform<B>>, p: f32) -> Self {
+        Self { transform, p }
+    }
+}
+
+impl<B: Backend> Transform<B> for RandomApply<B> {
+    fn apply(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Option<Tensor<B, 2>>,
+        labels: Option<Tensor<B, 1>>,
+    ) -> Sample<B> {
+        let [u] = uniform::<B, 1>(&image.device());
+        if u < self.p {
+            self.transform.apply(image, boxes, labels)
+        } else {
+            (image, boxes, labels)
+        }
+    }
+}
+
+/// Draw `N` values from the uniform distribution on `[0, 1)`.
+fn uniform<B: Backend, const N: usize>(device: &Device<B>) -> [f32; N] {
+    let values: Vec<f32> = Tensor::<B, 1>::random([N], Distribution::Default, device)
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>())
+        .collect();
+
+    core::array::from_fn(|i| values[i])
+}
+
+/// Only keep the bounding boxes (and their labels) selected by the mask.
+fn filter_boxes<B: Backend>(
+    boxes: Tensor<B, 2>,
+    labels: Option<Tensor<B, 1>>,
+    keep: Tensor<B, 1, Bool>,
+) -> (Tensor<B, 2>, Option<Tensor<B, 1>>) {
+    let device = boxes.device();
+    let indices: Vec<i64> = keep
+        .into_data()
+        .iter::<bool>()
+        .enumerate()
+        .filter_map(|(i, keep)| keep.then_some(i as i64))
+        .collect();
+    let num_kept = indices.len();
+    let indices = Tensor::<B, 1, Int>::from_data(
+        TensorData::new(indices, [num_kept]).convert::<B::IntElem>(),
+        &device,
+    );
+
+    (
+        boxes.select(0, indices.clone()),
+        labels.map(|labels| labels.select(0, indices)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn compose_in_order() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::from_floats(
+            [[[0., 255.]], [[10., 20.]], [[100., 200.]]],
+            &device,
+        );
+        let boxes = Tensor::from_floats([[0., 0., 1., 1.]], &device);
+        let transforms: Vec<Box<dyn Transform<TestBackend>>> = vec![
+            Box::new(RandomHorizontalFlip::new(1.)),
+            Box::new(Normalize::new([0., 10., 100.], [255., 10., 100.])),
+        ];
+
+        let (image, boxes, labels) = Compose::new(transforms).apply(image, Some(boxes), None);
+
+        image
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[1f32, 0.]], [[1., 0.]], [[1., 0.]]]), 4);
+        boxes
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([[1f32, 0., 2., 1.]]), false);
+        assert!(labels.is_none());
+    }
+
+    #[test]
+    fn random_apply_probability() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::from_floats([[[1., 2.]]], &device);
+
+        let never = RandomApply::new(Box::new(RandomHorizontalFlip::new(1.)), 0.);
+        let (output, _, _) = never.apply(image.clone(), None, None);
+        output
+            .into_data()
+            .assert_eq(&TensorData::from([[[1f32, 2.]]]), false);
+
+        let always = RandomApply::new(Box::new(RandomHorizontalFlip::new(1.)), 1.);
+        let (output, _, _) = always.apply(image, None, None);
+        output
+            .into_data()
+            .assert_eq(&TensorData::from([[[2f32, 1.]]]), false);
+    }
+
+    #[test]
+    fn filter_boxes_and_labels() {
+        let device = Default::default();
+        let boxes =
+            Tensor::<TestBackend, 2>::from_floats([[0., 0., 1., 1.], [1., 1., 2., 2.]], &device);
+        let labels = Tensor::from_floats([3., 4.], &device);
+        let keep = Tensor::<TestBackend, 1>::from_floats([0., 1.], &device).greater_elem(0.5);
+
+        let (boxes, labels) = filter_boxes(boxes, Some(labels), keep);
+
+        boxes
+            .into_data()
+            .assert_eq(&TensorData::from([[1f32, 1., 2., 2.]]), false);
+        labels
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([4f32]), false);
+    }
+}
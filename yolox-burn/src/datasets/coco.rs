@@ -0,0 +1,368 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use burn::tensor::{backend::Backend, Device, Tensor, TensorData};
+use image::imageops::FilterType;
+use serde::Deserialize;
+
+/// Default (square) input size of the preprocessed images.
+const IMAGE_SIZE: usize = 640;
+
+/// Object annotation of a [COCO dataset](CocoDataset) image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Bounding box `[x, y, w, h]` in pixel coordinates of the preprocessed image.
+    pub box_xywh: [f32; 4],
+    /// Label index of the object category.
+    pub category_id: usize,
+}
+
+/// Error type for [COCO dataset](CocoDataset) loading.
+#[derive(Debug)]
+pub enum CocoError {
+    /// Failed to read a file.
+    Io(std::io::Error),
+    /// Failed to parse the annotation file.
+    Json(serde_json::Error),
+    /// Failed to load an image.
+    Image(image::ImageError),
+    /// A class name is not part of the dataset categories.
+    UnknownCategory(String),
+}
+
+impl fmt::Display for CocoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Failed to read file: {err}"),
+            Self::Json(err) => write!(f, "Failed to parse annotations: {err}"),
+            Self::Image(err) => write!(f, "Failed to load image: {err}"),
+            Self::UnknownCategory(name) => write!(f, "Unknown category '{name}'"),
+        }
+    }
+}
+
+impl std::error::Error for CocoError {}
+
+impl From<std::io::Error> for CocoError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for CocoError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<image::ImageError> for CocoError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Image(err)
+    }
+}
+
+/// COCO annotation file content (only the fields used for detection).
+#[derive(Deserialize, Debug, Clone)]
+struct CocoFile {
+    images: Vec<CocoImage>,
+    annotations: Vec<CocoAnnotation>,
+    categories: Vec<CocoCategory>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct CocoImage {
+    pub(crate) id: usize,
+    pub(crate) file_name: String,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct CocoAnnotation {
+    pub(crate) image_id: usize,
+    pub(crate) category_id: usize,
+    pub(crate) bbox: [f32; 4],
+    #[serde(default)]
+    pub(crate) iscrowd: u8,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct CocoCategory {
+    pub(crate) id: usize,
+    pub(crate) name: String,
+}
+
+/// [COCO](https://cocodataset.org) object detection dataset.
+///
+/// Only the annotation file is parsed on creation, images are loaded from disk lazily on each
+/// [get](CocoDataset::get) call.
+#[derive(Debug, Clone)]
+pub struct CocoDataset {
+    image_dir: PathBuf,
+    images: Vec<CocoImage>,
+    // Annotations grouped by image index
+    annotations: Vec<Vec<CocoAnnotation>>,
+    categories: Vec<CocoCategory>,
+    // Category id -> label index
+    labels: HashMap<usize, usize>,
+    image_size: [usize; 2],
+}
+
+impl CocoDataset {
+    /// Create a new COCO dataset from the images directory and the annotation file.
+    ///
+    /// By default, all categories are used and mapped to label indices in increasing order of
+    /// category ID (e.g., the 80 COCO categories with IDs in `[1, 90]` are mapped to `[0, 79]`).
+    ///
+    /// # Arguments
+    ///
+    /// * `image_dir`: Directory containing the images.
+    /// * `annotation_file` - Path to the COCO JSON annotation file (e.g., `instances_val2017.json`).
+    pub fn new(image_dir: &Path, annotation_file: &Path) -> Result<Self, CocoError> {
+        let reader = BufReader::new(File::open(annotation_file)?);
+        let content: CocoFile = serde_json::from_reader(reader)?;
+
+        Ok(Self::from_parts(
+            image_dir,
+            content.images,
+            content.annotations,
+            content.categories,
+        ))
+    }
+
+    /// Create a new COCO dataset from already parsed images, annotations and categories.
+    pub(crate) fn from_parts(
+        image_dir: &Path,
+        images: Vec<CocoImage>,
+        annotations: Vec<CocoAnnotation>,
+        mut categories: Vec<CocoCategory>,
+    ) -> Self {
+        let index: HashMap<usize, usize> = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| (image.id, i))
+            .collect();
+
+        let mut grouped = vec![Vec::new(); images.len()];
+        for ann in annotations {
+            // Crowd annotations are not used for detection
+            if ann.iscrowd != 0 || ann.bbox[2] <= 0. || ann.bbox[3] <= 0. {
+                continue;
+            }
+            if let Some(&i) = index.get(&ann.image_id) {
+                grouped[i].push(ann);
+            }
+        }
+
+        categories.sort_by_key(|c| c.id);
+        let labels = categories
+            .iter()
+            .enumerate()
+            .map(|(label, c)| (c.id, label))
+            .collect();
+
+        Self {
+            image_dir: image_dir.to_path_buf(),
+            images,
+            annotations: grouped,
+            categories,
+            labels,
+            image_size: [IMAGE_SIZE, IMAGE_SIZE],
+        }
+    }
+
+    /// Restrict the dataset to the given class names.
+    ///
+    /// Each class is mapped to its position in `classes` and annotations for categories not in the
+    /// list are ignored.
+    pub fn with_classes(mut self, classes: &[&str]) -> Result<Self, CocoError> {
+        self.labels = classes
+            .iter()
+            .enumerate()
+            .map(|(label, name)| {
+                self.categories
+                    .iter()
+                    .find(|c| c.name == *name)
+                    .map(|c| (c.id, label))
+                    .ok_or_else(|| CocoError::UnknownCategory(name.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(self)
+    }
+
+    /// Set the `[height, width]` of the preprocessed images.
+    pub fn with_image_size(mut self, height: usize, width: usize) -> Self {
+        self.image_size = [height, width];
+        self
+    }
+
+    /// The `[height, width]` of the preprocessed images.
+    pub fn image_size(&self) -> [usize; 2] {
+        self.image_size
+    }
+
+    /// Number of images in the dataset.
+    pub fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Number of classes used for the label mapping.
+    pub fn num_classes(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Label index for the given COCO category ID, if the category is used.
+    pub fn label(&self, category_id: usize) -> Option<usize> {
+        self.labels.get(&category_id).copied()
+    }
+
+    /// Annotations for the image at the given index, scaled to the preprocessed image size.
+    pub fn annotations(&self, index: usize) -> Vec<Annotation> {
+        let image = &self.images[index];
+        let [height, width] = self.image_size;
+        let ratio_x = width as f32 / image.width as f32;
+        let ratio_y = height as f32 / image.height as f32;
+
+        self.annotations[index]
+            .iter()
+            .filter_map(|ann| {
+                let [x, y, w, h] = ann.bbox;
+                self.label(ann.category_id).map(|category_id| Annotation {
+                    box_xywh: [x * ratio_x, y * ratio_y, w * ratio_x, h * ratio_y],
+                    category_id,
+                })
+            })
+            .collect()
+    }
+
+    /// Path to the image file at the given index.
+    pub fn image_path(&self, index: usize) -> PathBuf {
+        self.image_dir.join(&self.images[index].file_name)
+    }
+
+    /// Load and preprocess the image at the given index.
+    ///
+    /// The image is resized to the dataset image size and returned with shape `[C, H, W]` and
+    /// pixel values in the range `[0, 255]`, as expected by the YOLOX model.
+    pub fn load_image<B: Backend>(
+        &self,
+        index: usize,
+        device: &Device<B>,
+    ) -> Result<Tensor<B, 3>, CocoError> {
+        let [height, width] = self.image_size;
+        let image = image::open(self.image_path(index))?
+            .resize_exact(width as u32, height as u32, FilterType::Triangle)
+            .into_rgb8();
+
+        let data = TensorData::new(image.into_raw(), [height, width, 3]);
+        let tensor = Tensor::<B, 3>::from_data(data.convert::<B::FloatElem>(), device)
+            // [H, W, C] -> [C, H, W]
+            .permute([2, 0, 1]);
+
+        Ok(tensor)
+    }
+
+    /// Get the preprocessed image and its annotations at the given index.
+    ///
+    /// # Panics
+    ///
+    /// If the index is out of bounds or if the image cannot be loaded.
+    pub fn get<B: Backend>(
+        &self,
+        index: usize,
+        device: &Device<B>,
+    ) -> (Tensor<B, 3>, Vec<Annotation>) {
+        let image = self
+            .load_image(index, device)
+            .unwrap_or_else(|err| panic!("Failed to load image {index}.\nError: {err}"));
+
+        (image, self.annotations(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> CocoDataset {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        CocoDataset::new(&dir, &dir.join("coco_2images.json")).unwrap()
+    }
+
+    fn assert_box_eq(actual: [f32; 4], expected: [f32; 4]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() < 1e-3,
+                "expected {expected:?}, got {actual:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_images_and_categories() {
+        let dataset = fixture();
+
+        assert_eq!(dataset.num_images(), 2);
+        assert_eq!(dataset.num_classes(), 3);
+        // Labels follow the increasing category IDs
+        assert_eq!(dataset.label(1), Some(0));
+        assert_eq!(dataset.label(3), Some(1));
+        assert_eq!(dataset.label(18), Some(2));
+        assert_eq!(dataset.label(2), None);
+        assert!(dataset.image_path(1).ends_with("000000000285.jpg"));
+    }
+
+    #[test]
+    fn groups_annotations_by_image() {
+        let dataset = fixture();
+
+        // The crowd annotation of the first image is skipped
+        assert_eq!(dataset.annotations(0).len(), 2);
+        assert_eq!(dataset.annotations(1).len(), 1);
+        assert_eq!(dataset.annotations(1)[0].category_id, 1);
+    }
+
+    #[test]
+    fn scales_boxes_to_image_size() {
+        let dataset = fixture();
+
+        // 640x480 -> 640x640
+        let annotations = dataset.annotations(0);
+        assert_box_eq(annotations[0].box_xywh, [64., 64., 128., 128.]);
+        assert_eq!(annotations[0].category_id, 0);
+        assert_box_eq(annotations[1].box_xywh, [320., 320., 160., 160.]);
+        assert_eq!(annotations[1].category_id, 2);
+
+        // 320x320 -> 640x640
+        assert_box_eq(dataset.annotations(1)[0].box_xywh, [64., 32., 128., 320.]);
+
+        // 320x320 -> 160x320
+        let dataset = dataset.with_image_size(160, 320);
+        assert_box_eq(dataset.annotations(1)[0].box_xywh, [32., 8., 64., 80.]);
+    }
+
+    #[test]
+    fn restricts_classes() {
+        let dataset = fixture().with_classes(&["dog", "person"]).unwrap();
+
+        assert_eq!(dataset.num_classes(), 2);
+        let labels: Vec<_> = dataset
+            .annotations(0)
+            .iter()
+            .map(|ann| ann.category_id)
+            .collect();
+        assert_eq!(labels, vec![1, 0]);
+        assert!(dataset.annotations(1).is_empty());
+
+        assert!(matches!(
+            fixture().with_classes(&["zebra"]),
+            Err(CocoError::UnknownCategory(_))
+        ));
+    }
+}
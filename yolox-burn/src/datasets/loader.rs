@@ -0,0 +1,283 @@
+use burn::tensor::{backend::Backend, Device, Tensor};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+
+use super::DetectionDataset;
+
+/// Default seed of the shuffling random number generator.
+const DEFAULT_SEED: u64 = 42;
+
+/// Sample of an object detection dataset.
+pub struct DetectionSample<B: Backend> {
+    /// Image with shape `[C, H, W]`.
+    pub image: Tensor<B, 3>,
+    /// Bounding boxes `(xmin, ymin, xmax, ymax)` with shape `[num_boxes, 4]`.
+    pub boxes: Tensor<B, 2>,
+    /// Labels of the objects with shape `[num_boxes]`.
+    pub labels: Tensor<B, 1>,
+    /// Index of the image in the dataset.
+    pub image_id: usize,
+}
+
+/// Batch of object detection samples.
+///
+/// The images have the same size and are stacked, while the boxes and labels are kept per image
+/// since the number of objects varies between images.
+pub struct DetectionBatch<B: Backend> {
+    /// Images with shape `[batch_size, C, H, W]`.
+    pub images: Tensor<B, 4>,
+    /// Bounding boxes `(xmin, ymin, xmax, ymax)` of each image with shape `[num_boxes, 4]`.
+    pub boxes: Vec<Tensor<B, 2>>,
+    /// Labels of the objects of each image with shape `[num_boxes]`.
+    pub labels: Vec<Tensor<B, 1>>,
+    /// Index of each image in the dataset.
+    pub image_ids: Vec<usize>,
+}
+
+/// Collate the samples into a batch.
+///
+/// # Panics
+///
+/// If there are no samples or if the images do not have the same size.
+pub fn collate_detection_batch<B: Backend>(samples: Vec<DetectionSample<B>>) -> DetectionBatch<B> {
+    assert!(!samples.is_empty(), "cannot collate an empty batch");
+
+    let batch_size = samples.len();
+    let mut images = Vec::with_capacity(batch_size);
+    let mut boxes = Vec::with_capacity(batch_size);
+    let mut labels = Vec::with_capacity(batch_size);
+    let mut image_ids = Vec::with_capacity(batch_size);
+    for sample in samples {
+        images.push(sample.image);
+        boxes.push(sample.boxes);
+        labels.push(sample.labels);
+        image_ids.push(sample.image_id);
+    }
+
+    DetectionBatch {
+        images: Tensor::stack(images, 0),
+        boxes,
+        labels,
+        image_ids,
+    }
+}
+
+/// Data loader of an [object detection dataset](DetectionDataset), which yields
+/// [batches](DetectionBatch) of samples.
+///
+/// The samples of a batch are loaded in parallel by a pool of worker threads. When shuffling,
+/// the order of the samples is drawn from a seeded random number generator, so that the same
+/// seed produces the same sequence of epochs.
+pub struct ObjectDetectionDataLoader<D: DetectionDataset> {
+    dataset: D,
+    batch_size: usize,
+    shuffle: bool,
+    rng: StdRng,
+    pool: Option<ThreadPool>,
+}
+
+impl<D: DetectionDataset + Sync> ObjectDetectionDataLoader<D> {
+    /// Create a new data loader.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset`: Dataset to load.
+    /// * `batch_size` - Number of samples per batch. The last batch of an epoch may be smaller.
+    /// * `shuffle` - Whether to shuffle the samples at each epoch.
+    /// * `num_workers` - Number of threads which load the samples. The samples are loaded on the
+    ///   calling thread when zero.
+    pub fn new(dataset: D, batch_size: usize, shuffle: bool, num_workers: usize) -> Self {
+        assert!(batch_size > 0, "the batch size should be positive");
+
+        let pool = (num_workers > 0).then(|| {
+            ThreadPoolBuilder::new()
+                .num_threads(num_workers)
+                .build()
+                .expect("the worker threads should be created")
+        });
+
+        Self {
+            dataset,
+            batch_size,
+            shuffle,
+            rng: StdRng::seed_from_u64(DEFAULT_SEED),
+            pool,
+        }
+    }
+
+    /// Set the seed of the shuffling random number generator (defaults to 42).
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Number of batches per epoch.
+    pub fn num_batches(&self) -> usize {
+        self.dataset.len().div_ceil(self.batch_size)
+    }
+
+    /// Iterate over the batches of a new epoch.
+    pub fn iter<'a, B: Backend>(
+        &'a mut self,
+        device: &'a Device<B>,
+    ) -> impl Iterator<Item = DetectionBatch<B>> + 'a {
+        let mut indices: Vec<usize> = (0..self.dataset.len()).collect();
+        if self.shuffle {
+            indices.shuffle(&mut self.rng);
+        }
+
+        let this = &*self;
+        let batches: Vec<Vec<usize>> = indices
+            .chunks(this.batch_size)
+            .map(|batch| batch.to_vec())
+            .collect();
+
+        batches
+            .into_iter()
+            .map(move |batch| collate_detection_batch(this.load(&batch, device)))
+    }
+
+    /// Load the samples at the given indices.
+    fn load<B: Backend>(&self, indices: &[usize], device: &Device<B>) -> Vec<DetectionSample<B>> {
+        let load = |&index: &usize| {
+            let (image, boxes, labels) = self.dataset.get_sample(index, device);
+            DetectionSample {
+                image,
+                boxes,
+                labels,
+                image_id: index,
+            }
+        };
+
+        match &self.pool {
+            Some(pool) => pool.install(|| indices.par_iter().map(load).collect()),
+            None => indices.iter().map(load).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Dataset of 3 images of size 4x4, where the image `i` has `i + 1` boxes.
+    struct MiniDataset;
+
+    impl DetectionDataset for MiniDataset {
+        fn len(&self) -> usize {
+            3
+        }
+
+        fn image_size(&self, _index: usize) -> [usize; 2] {
+            [4, 4]
+        }
+
+        fn get_sample<B: Backend>(
+            &self,
+            index: usize,
+            device: &Device<B>,
+        ) -> (Tensor<B, 3>, Tensor<B, 2>, Tensor<B, 1>) {
+            let num_boxes = index + 1;
+
+            (
+                Tensor::full([3, 4, 4], index as f32, device),
+                Tensor::ones([num_boxes, 4], device),
+                Tensor::full([num_boxes], index as f32, device),
+            )
+        }
+    }
+
+    fn epoch_ids(loader: &mut ObjectDetectionDataLoader<MiniDataset>) -> Vec<usize> {
+        let device = Default::default();
+        loader
+            .iter::<TestBackend>(&device)
+            .flat_map(|batch| batch.image_ids)
+            .collect()
+    }
+
+    #[test]
+    fn batches_keep_boxes_per_image() {
+        let device = Default::default();
+        let mut loader = ObjectDetectionDataLoader::new(MiniDataset, 2, false, 0);
+        assert_eq!(loader.num_batches(), 2);
+
+        let batches: Vec<_> = loader.iter::<TestBackend>(&device).collect();
+        assert_eq!(batches.len(), 2);
+
+        let batch = &batches[0];
+        assert_eq!(batch.images.dims(), [2, 3, 4, 4]);
+        assert_eq!(batch.image_ids, vec![0, 1]);
+        // The boxes are not padded to the same number of objects
+        assert_eq!(batch.boxes.len(), 2);
+        assert_eq!(batch.boxes[0].dims(), [1, 4]);
+        assert_eq!(batch.boxes[1].dims(), [2, 4]);
+        assert_eq!(batch.labels[1].dims(), [2]);
+
+        // Last batch with the remaining sample
+        let batch = &batches[1];
+        assert_eq!(batch.images.dims(), [1, 3, 4, 4]);
+        assert_eq!(batch.boxes[0].dims(), [3, 4]);
+        assert_eq!(batch.image_ids, vec![2]);
+    }
+
+    #[test]
+    fn collate_stacks_images() {
+        let device = Default::default();
+        let samples = (0..3)
+            .map(|i| {
+                let (image, boxes, labels) = MiniDataset.get_sample::<TestBackend>(i, &device);
+                DetectionSample {
+                    image,
+                    boxes,
+                    labels,
+                    image_id: i,
+                }
+            })
+            .collect();
+
+        let batch = collate_detection_batch(samples);
+        assert_eq!(batch.images.dims(), [3, 3, 4, 4]);
+        batch
+            .images
+            .sum_dim(3)
+            .sum_dim(2)
+            .sum_dim(1)
+            .flatten::<1>(0, 3)
+            .into_data()
+            .assert_eq(&TensorData::from([0f32, 48., 96.]), false);
+        assert_eq!(batch.image_ids, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn shuffle_with_same_seed() {
+        let ids = |seed| {
+            let mut loader =
+                ObjectDetectionDataLoader::new(MiniDataset, 2, true, 0).with_seed(seed);
+            (epoch_ids(&mut loader), epoch_ids(&mut loader))
+        };
+
+        let (first, second) = ids(7);
+        assert_eq!(ids(7), (first.clone(), second));
+
+        let mut sorted = first;
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn workers_load_same_batches() {
+        let mut loader = ObjectDetectionDataLoader::new(MiniDataset, 2, true, 2).with_seed(3);
+        let mut reference = ObjectDetectionDataLoader::new(MiniDataset, 2, true, 0).with_seed(3);
+
+        assert_eq!(epoch_ids(&mut loader), epoch_ids(&mut reference));
+    }
+
+    #[test]
+    #[should_panic = "cannot collate an empty batch"]
+    fn collate_empty_batch() {
+        collate_detection_batch::<TestBackend>(Vec::new());
+    }
+}
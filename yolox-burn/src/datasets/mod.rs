@@ -0,0 +1,69 @@
+pub mod coco;
+pub mod loader;
+pub mod tiling;
+pub mod voc;
+
+use burn::tensor::{backend::Backend, Device, Tensor};
+
+use coco::CocoDataset;
+
+/// Object detection dataset, which can be [tiled](tiling::TiledDataset) and
+/// [batched](loader::ObjectDetectionDataLoader).
+pub trait DetectionDataset {
+    /// Number of images in the dataset.
+    fn len(&self) -> usize;
+
+    /// Whether the dataset is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size `[height, width]` of the image at the given index, without loading it.
+    fn image_size(&self, index: usize) -> [usize; 2];
+
+    /// Get the image with shape `[C, H, W]`, the bounding boxes `(xmin, ymin, xmax, ymax)` with
+    /// shape `[num_boxes, 4]` and the labels with shape `[num_boxes]` at the given index.
+    fn get_sample<B: Backend>(
+        &self,
+        index: usize,
+        device: &Device<B>,
+    ) -> (Tensor<B, 3>, Tensor<B, 2>, Tensor<B, 1>);
+}
+
+impl DetectionDataset for CocoDataset {
+    fn len(&self) -> usize {
+        self.num_images()
+    }
+
+    fn image_size(&self, _index: usize) -> [usize; 2] {
+        // All the images are resized to the same size
+        CocoDataset::image_size(self)
+    }
+
+    fn get_sample<B: Backend>(
+        &self,
+        index: usize,
+        device: &Device<B>,
+    ) -> (Tensor<B, 3>, Tensor<B, 2>, Tensor<B, 1>) {
+        let (image, annotations) = self.get(index, device);
+        let num_boxes = annotations.len();
+
+        let boxes: Vec<f32> = annotations
+            .iter()
+            .flat_map(|ann| {
+                let [x, y, w, h] = ann.box_xywh;
+                [x, y, x + w, y + h]
+            })
+            .collect();
+        let labels: Vec<f32> = annotations
+            .iter()
+            .map(|ann| ann.category_id as f32)
+            .collect();
+
+        (
+            image,
+            Tensor::<B, 1>::from_floats(boxes.as_slice(), device).reshape([num_boxes, 4]),
+            Tensor::from_floats(labels.as_slice(), device),
+        )
+    }
+}
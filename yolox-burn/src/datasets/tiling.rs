@@ -0,0 +1,300 @@
+use burn::tensor::{backend::Backend, Device, ElementConversion, Int, Tensor, TensorData};
+
+use super::DetectionDataset;
+
+/// Tile of an image, with the objects it contains.
+pub struct TiledSample<B: Backend> {
+    /// Tile image with shape `[C, tile_height, tile_width]`.
+    pub image: Tensor<B, 3>,
+    /// Bounding boxes `(xmin, ymin, xmax, ymax)` in tile coordinates, clipped to the tile.
+    /// Shape: `[num_boxes, 4]`.
+    pub boxes: Tensor<B, 2>,
+    /// Labels of the objects. Shape: `[num_boxes]`.
+    pub labels: Tensor<B, 1>,
+    /// Offset `(x, y)` of the tile in the image.
+    pub tile_offset: (usize, usize),
+}
+
+/// Split high-resolution images (e.g., satellite or medical images) and their annotations into
+/// overlapping tiles, so that the model can be trained on smaller inputs.
+#[derive(Debug, Clone)]
+pub struct DetectionTiler {
+    tile_size: usize,
+    overlap: usize,
+    min_box_area_fraction: f32,
+}
+
+impl DetectionTiler {
+    /// Create a new tiler.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_size`: Size of the (square) tiles.
+    /// * `overlap` - Minimum overlap between adjacent tiles, smaller than the tile size.
+    /// * `min_box_area_fraction` - A box is kept in a tile if the fraction of its area inside the
+    ///   tile is larger than this value.
+    pub fn new(tile_size: usize, overlap: usize, min_box_area_fraction: f32) -> Self {
+        assert!(
+            overlap < tile_size,
+            "the overlap should be smaller than the tile size"
+        );
+
+        Self {
+            tile_size,
+            overlap,
+            min_box_area_fraction,
+        }
+    }
+
+    /// Start positions of the tiles along an axis of the given size. The last tile is aligned
+    /// with the end of the axis, so it may overlap more with the previous tile.
+    fn starts(&self, size: usize) -> Vec<usize> {
+        if size <= self.tile_size {
+            return vec![0];
+        }
+
+        let stride = self.tile_size - self.overlap;
+        let mut starts: Vec<usize> = (0..)
+            .map(|i| i * stride)
+            .take_while(|&start| start + self.tile_size < size)
+            .collect();
+        starts.push(size - self.tile_size);
+
+        starts
+    }
+
+    /// Offsets `(x, y)` of the tiles of an image with the given size `[height, width]`, in
+    /// row-major order.
+    pub fn tile_offsets(&self, [height, width]: [usize; 2]) -> Vec<(usize, usize)> {
+        let xs = self.starts(width);
+        self.starts(height)
+            .into_iter()
+            .flat_map(|y| xs.iter().map(move |&x| (x, y)))
+            .collect()
+    }
+
+    /// Split an image and its annotations into tiles.
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: Image with shape `[C, H, W]`.
+    /// * `boxes` - Bounding boxes `(xmin, ymin, xmax, ymax)` with shape `[num_boxes, 4]`.
+    /// * `labels` - Labels of the objects with shape `[num_boxes]`.
+    pub fn tile_image_and_annotations<B: Backend>(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Tensor<B, 2>,
+        labels: Tensor<B, 1>,
+    ) -> Vec<TiledSample<B>> {
+        let [_, height, width] = image.dims();
+
+        self.tile_offsets([height, width])
+            .into_iter()
+            .map(|offset| self.tile(image.clone(), boxes.clone(), labels.clone(), offset))
+            .collect()
+    }
+
+    /// Extract the tile at the given offset `(x, y)` of an image and its annotations.
+    pub fn tile<B: Backend>(
+        &self,
+        image: Tensor<B, 3>,
+        boxes: Tensor<B, 2>,
+        labels: Tensor<B, 1>,
+        tile_offset: (usize, usize),
+    ) -> TiledSample<B> {
+        let [channels, height, width] = image.dims();
+        let (x, y) = tile_offset;
+        let (tile_w, tile_h) = (
+            self.tile_size.min(width - x),
+            self.tile_size.min(height - y),
+        );
+        let image = image.slice([0..channels, y..y + tile_h, x..x + tile_w]);
+
+        // Clip the boxes to the tile, in tile coordinates
+        let [n, _] = boxes.dims();
+        let device = boxes.device();
+        let column = |i: usize| boxes.clone().slice([0..n, i..i + 1]);
+        let (xmin, ymin) = (
+            column(0).sub_scalar(x as f32),
+            column(1).sub_scalar(y as f32),
+        );
+        let (xmax, ymax) = (
+            column(2).sub_scalar(x as f32),
+            column(3).sub_scalar(y as f32),
+        );
+        let area = (xmax.clone() - xmin.clone()) * (ymax.clone() - ymin.clone());
+        let (xmin, xmax) = (xmin.clamp(0., tile_w as f32), xmax.clamp(0., tile_w as f32));
+        let (ymin, ymax) = (ymin.clamp(0., tile_h as f32), ymax.clamp(0., tile_h as f32));
+        let inside = (xmax.clone() - xmin.clone()) * (ymax.clone() - ymin.clone());
+
+        // Keep the boxes mostly inside the tile
+        let fraction = (inside / area.clamp_min(f32::EPSILON)).reshape([n]);
+        let indices: Vec<i64> = fraction
+            .into_data()
+            .iter::<B::FloatElem>()
+            .enumerate()
+            .filter(|(_, fraction)| fraction.elem::<f32>() > self.min_box_area_fraction)
+            .map(|(i, _)| i as i64)
+            .collect();
+        let num_kept = indices.len();
+        let indices = Tensor::<B, 1, Int>::from_data(
+            TensorData::new(indices, [num_kept]).convert::<B::IntElem>(),
+            &device,
+        );
+
+        TiledSample {
+            image,
+            boxes: Tensor::cat(vec![xmin, ymin, xmax, ymax], 1).select(0, indices.clone()),
+            labels: labels.select(0, indices),
+            tile_offset,
+        }
+    }
+}
+
+/// Dataset of the tiles of the images of a [detection dataset](DetectionDataset).
+///
+/// The tiles are extracted lazily: each [get](TiledDataset::get) call loads the image of the
+/// tile and crops it.
+pub struct TiledDataset<D: DetectionDataset> {
+    dataset: D,
+    tiler: DetectionTiler,
+    /// Image index and tile offset of each tile.
+    tiles: Vec<(usize, (usize, usize))>,
+}
+
+impl<D: DetectionDataset> TiledDataset<D> {
+    /// Create a new tiled dataset.
+    pub fn new(dataset: D, tiler: DetectionTiler) -> Self {
+        let tiles = (0..dataset.len())
+            .flat_map(|index| {
+                tiler
+                    .tile_offsets(dataset.image_size(index))
+                    .into_iter()
+                    .map(move |offset| (index, offset))
+            })
+            .collect();
+
+        Self {
+            dataset,
+            tiler,
+            tiles,
+        }
+    }
+
+    /// Number of tiles in the dataset.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Whether the dataset has no tile.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Get the tile at the given index.
+    ///
+    /// # Panics
+    ///
+    /// If the index is out of bounds.
+    pub fn get<B: Backend>(&self, index: usize, device: &Device<B>) -> TiledSample<B> {
+        let (image_index, offset) = self.tiles[index];
+        let (image, boxes, labels) = self.dataset.get_sample(image_index, device);
+
+        self.tiler.tile(image, boxes, labels, offset)
+    }
+}
+
+impl<D: DetectionDataset> DetectionDataset for TiledDataset<D> {
+    fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn image_size(&self, index: usize) -> [usize; 2] {
+        let (image_index, (x, y)) = self.tiles[index];
+        let [height, width] = self.dataset.image_size(image_index);
+        let tile_size = self.tiler.tile_size;
+
+        [tile_size.min(height - y), tile_size.min(width - x)]
+    }
+
+    fn get_sample<B: Backend>(
+        &self,
+        index: usize,
+        device: &Device<B>,
+    ) -> (Tensor<B, 3>, Tensor<B, 2>, Tensor<B, 1>) {
+        let tile = self.get(index, device);
+
+        (tile.image, tile.boxes, tile.labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn tile_offsets_cover_image() {
+        let tiler = DetectionTiler::new(320, 32, 0.5);
+
+        // Stride of 288, the last tile is aligned with the end of the image
+        let offsets = tiler.tile_offsets([640, 640]);
+        assert_eq!(offsets.len(), 9);
+        assert_eq!(offsets[..3], [(0, 0), (288, 0), (320, 0)]);
+        assert_eq!(offsets[8], (320, 320));
+    }
+
+    #[test]
+    fn small_image_single_tile() {
+        let tiler = DetectionTiler::new(320, 32, 0.5);
+
+        assert_eq!(tiler.tile_offsets([200, 300]), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn box_spanning_two_tiles() {
+        let device = Default::default();
+        let tiler = DetectionTiler::new(320, 32, 0.5);
+        let image = Tensor::<TestBackend, 3>::zeros([3, 640, 640], &device);
+        let boxes = Tensor::<TestBackend, 2>::from_floats([[250., 10., 350., 60.]], &device);
+        let labels = Tensor::<TestBackend, 1>::from_floats([7.], &device);
+
+        let tiles = tiler.tile_image_and_annotations(image, boxes, labels);
+        assert_eq!(tiles.len(), 9);
+        for tile in tiles.iter() {
+            assert_eq!(tile.image.dims(), [3, 320, 320]);
+        }
+
+        // 70% of the box is in the first tile, 62% in the second and 30% in the third
+        let kept: Vec<_> = tiles
+            .iter()
+            .filter(|tile| tile.boxes.dims()[0] > 0)
+            .map(|tile| tile.tile_offset)
+            .collect();
+        assert_eq!(kept, vec![(0, 0), (288, 0)]);
+
+        tiles[0]
+            .boxes
+            .clone()
+            .into_data()
+            .assert_eq(&TensorData::from([[250., 10., 320., 60.]]), false);
+        tiles[1]
+            .boxes
+            .clone()
+            .into_data()
+            .assert_eq(&TensorData::from([[0., 10., 62., 60.]]), false);
+        tiles[1]
+            .labels
+            .clone()
+            .into_data()
+            .assert_eq(&TensorData::from([7.]), false);
+    }
+
+    #[test]
+    #[should_panic = "the overlap should be smaller than the tile size"]
+    fn overlap_larger_than_tile() {
+        DetectionTiler::new(320, 320, 0.5);
+    }
+}
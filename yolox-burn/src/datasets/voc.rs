@@ -0,0 +1,355 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use super::coco::{CocoAnnotation, CocoCategory, CocoDataset, CocoImage};
+
+/// The 20 standard Pascal VOC classes.
+pub const VOC_CLASSES: [&str; 20] = [
+    "aeroplane",
+    "bicycle",
+    "bird",
+    "boat",
+    "bottle",
+    "bus",
+    "car",
+    "cat",
+    "chair",
+    "cow",
+    "diningtable",
+    "dog",
+    "horse",
+    "motorbike",
+    "person",
+    "pottedplant",
+    "sheep",
+    "sofa",
+    "train",
+    "tvmonitor",
+];
+
+/// Error type for [Pascal VOC dataset](VocDataset) loading.
+#[derive(Debug)]
+pub enum VocError {
+    /// Failed to read a file.
+    Io(std::io::Error),
+    /// Malformed annotation file.
+    InvalidAnnotation { file: PathBuf, reason: String },
+    /// An object class is not one of the [standard VOC classes](VOC_CLASSES).
+    UnknownClass(String),
+}
+
+impl fmt::Display for VocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Failed to read file: {err}"),
+            Self::InvalidAnnotation { file, reason } => {
+                write!(f, "Invalid annotation file {}: {reason}", file.display())
+            }
+            Self::UnknownClass(name) => write!(f, "Unknown class '{name}'"),
+        }
+    }
+}
+
+impl std::error::Error for VocError {}
+
+impl From<std::io::Error> for VocError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Pascal VOC object annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VocObject {
+    /// Class index in [VOC_CLASSES].
+    pub class_id: usize,
+    /// Bounding box `[xmin, ymin, xmax, ymax]` in pixel coordinates.
+    pub box_xyxy: [f32; 4],
+    /// Objects marked as difficult are usually ignored during evaluation.
+    pub difficult: bool,
+}
+
+/// Pascal VOC image annotations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VocSample {
+    /// Image identifier (file stem).
+    pub id: String,
+    pub width: usize,
+    pub height: usize,
+    pub objects: Vec<VocObject>,
+}
+
+/// [Pascal VOC](http://host.robots.ox.ac.uk/pascal/VOC/) object detection dataset.
+///
+/// The expected directory structure is the one of the official `VOCdevkit` archives:
+///
+/// ```text
+/// root
+/// └── VOC2012
+///     ├── Annotations/*.xml
+///     ├── ImageSets/Main/{train,val,trainval,test}.txt
+///     └── JPEGImages/*.jpg
+/// ```
+#[derive(Debug, Clone)]
+pub struct VocDataset {
+    image_dir: PathBuf,
+    samples: Vec<VocSample>,
+}
+
+impl VocDataset {
+    /// Create a new Pascal VOC dataset.
+    ///
+    /// # Arguments
+    ///
+    /// * `root`: Path to the `VOCdevkit` directory.
+    /// * `year` - Dataset year (e.g., `"2007"` or `"2012"`).
+    /// * `split` - Image set split (e.g., `"train"`, `"val"` or `"trainval"`).
+    pub fn new(root: &Path, year: &str, split: &str) -> Result<Self, VocError> {
+        let voc_dir = root.join(format!("VOC{year}"));
+        let split_file = voc_dir
+            .join("ImageSets")
+            .join("Main")
+            .join(split)
+            .with_extension("txt");
+
+        let samples = fs::read_to_string(split_file)?
+            .lines()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                let file = voc_dir.join("Annotations").join(id).with_extension("xml");
+                let content = fs::read_to_string(&file)?;
+                parse_annotation(id, &content).map_err(|reason| match reason {
+                    ParseError::UnknownClass(name) => VocError::UnknownClass(name),
+                    ParseError::Invalid(reason) => VocError::InvalidAnnotation { file, reason },
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            image_dir: voc_dir.join("JPEGImages"),
+            samples,
+        })
+    }
+
+    /// The 20 standard VOC class names.
+    pub fn class_names() -> &'static [&'static str] {
+        &VOC_CLASSES
+    }
+
+    /// Number of images in the dataset.
+    pub fn num_images(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Annotations of the image at the given index.
+    pub fn sample(&self, index: usize) -> Option<&VocSample> {
+        self.samples.get(index)
+    }
+
+    /// Path to the image file at the given index.
+    pub fn image_path(&self, index: usize) -> PathBuf {
+        self.image_dir
+            .join(&self.samples[index].id)
+            .with_extension("jpg")
+    }
+}
+
+impl From<VocDataset> for CocoDataset {
+    /// Convert to the COCO format for uniform training.
+    ///
+    /// VOC class indices are used as category IDs, so labels are preserved. Difficult objects are
+    /// marked as crowd annotations and thus ignored for training.
+    fn from(dataset: VocDataset) -> Self {
+        let categories = VOC_CLASSES
+            .iter()
+            .enumerate()
+            .map(|(id, name)| CocoCategory {
+                id,
+                name: name.to_string(),
+            })
+            .collect();
+
+        let mut images = Vec::with_capacity(dataset.samples.len());
+        let mut annotations = Vec::new();
+        for (image_id, sample) in dataset.samples.into_iter().enumerate() {
+            annotations.extend(sample.objects.iter().map(|obj| {
+                let [xmin, ymin, xmax, ymax] = obj.box_xyxy;
+                CocoAnnotation {
+                    image_id,
+                    category_id: obj.class_id,
+                    bbox: [xmin, ymin, xmax - xmin, ymax - ymin],
+                    iscrowd: obj.difficult as u8,
+                }
+            }));
+            images.push(CocoImage {
+                id: image_id,
+                file_name: format!("{}.jpg", sample.id),
+                width: sample.width,
+                height: sample.height,
+            });
+        }
+
+        CocoDataset::from_parts(&dataset.image_dir, images, annotations, categories)
+    }
+}
+
+enum ParseError {
+    UnknownClass(String),
+    Invalid(String),
+}
+
+/// Parse a VOC XML annotation file.
+fn parse_annotation(id: &str, content: &str) -> Result<VocSample, ParseError> {
+    fn number(content: &str, tag: &str) -> Result<f32, ParseError> {
+        let value = xml_element(content, tag)
+            .ok_or_else(|| ParseError::Invalid(format!("missing <{tag}>")))?;
+        value
+            .parse::<f32>()
+            .map_err(|_| ParseError::Invalid(format!("invalid <{tag}> value '{value}'")))
+    }
+
+    let size = xml_element(content, "size")
+        .ok_or_else(|| ParseError::Invalid("missing <size>".to_string()))?;
+    let width = number(size, "width")? as usize;
+    let height = number(size, "height")? as usize;
+
+    let objects = xml_elements(content, "object")
+        .map(|obj| {
+            let name = xml_element(obj, "name")
+                .ok_or_else(|| ParseError::Invalid("missing <name>".to_string()))?;
+            let class_id = VOC_CLASSES
+                .iter()
+                .position(|c| *c == name)
+                .ok_or_else(|| ParseError::UnknownClass(name.to_string()))?;
+            let difficult = xml_element(obj, "difficult").is_some_and(|d| d == "1");
+
+            let bndbox = xml_element(obj, "bndbox")
+                .ok_or_else(|| ParseError::Invalid("missing <bndbox>".to_string()))?;
+            // VOC coordinates are 1-based
+            let box_xyxy = [
+                number(bndbox, "xmin")? - 1.,
+                number(bndbox, "ymin")? - 1.,
+                number(bndbox, "xmax")? - 1.,
+                number(bndbox, "ymax")? - 1.,
+            ];
+
+            Ok(VocObject {
+                class_id,
+                box_xyxy,
+                difficult,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(VocSample {
+        id: id.to_string(),
+        width,
+        height,
+        objects,
+    })
+}
+
+/// Iterate over the (trimmed) content of all `<tag>...</tag>` elements.
+///
+/// This is a minimal parser for the simple XML structure of VOC annotations: attributes,
+/// self-closing tags and nested elements with the same tag name are not supported.
+fn xml_elements<'a>(content: &'a str, tag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut rest = content;
+
+    core::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let element = rest[start..end].trim();
+        rest = &rest[end + close.len()..];
+        Some(element)
+    })
+}
+
+/// Content of the first `<tag>...</tag>` element.
+fn xml_element<'a>(content: &'a str, tag: &'a str) -> Option<&'a str> {
+    xml_elements(content, tag).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> VocDataset {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/VOCdevkit");
+        VocDataset::new(&root, "2007", "val").unwrap()
+    }
+
+    #[test]
+    fn standard_classes() {
+        let classes = VocDataset::class_names();
+
+        assert_eq!(classes.len(), 20);
+        assert_eq!(classes[1], "bicycle");
+        assert_eq!(classes[14], "person");
+        assert_eq!(classes[19], "tvmonitor");
+    }
+
+    #[test]
+    fn parses_bicycle_annotation() {
+        let dataset = fixture();
+        assert_eq!(dataset.num_images(), 1);
+        assert!(dataset.image_path(0).ends_with("JPEGImages/000016.jpg"));
+
+        let sample = dataset.sample(0).unwrap();
+        assert_eq!(sample.id, "000016");
+        assert_eq!((sample.width, sample.height), (334, 500));
+        assert_eq!(sample.objects.len(), 2);
+
+        let bicycle = &sample.objects[0];
+        assert_eq!(VOC_CLASSES[bicycle.class_id], "bicycle");
+        assert!(!bicycle.difficult);
+        assert!(sample.objects[1].difficult);
+    }
+
+    #[test]
+    fn converts_to_zero_based_coordinates() {
+        let sample = fixture().sample(0).unwrap().clone();
+
+        assert_eq!(sample.objects[0].box_xyxy, [91., 71., 304., 472.]);
+        assert_eq!(sample.objects[1].box_xyxy, [0., 0., 39., 119.]);
+        for obj in &sample.objects {
+            let [xmin, ymin, xmax, ymax] = obj.box_xyxy;
+            assert!(0. <= xmin && xmin < xmax && xmax < sample.width as f32);
+            assert!(0. <= ymin && ymin < ymax && ymax < sample.height as f32);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_annotations() {
+        let unknown = "<annotation><size><width>10</width><height>10</height></size>\
+            <object><name>zebra</name><bndbox><xmin>1</xmin><ymin>1</ymin>\
+            <xmax>5</xmax><ymax>5</ymax></bndbox></object></annotation>";
+        assert!(matches!(
+            parse_annotation("0", unknown),
+            Err(ParseError::UnknownClass(name)) if name == "zebra"
+        ));
+
+        let missing_size = "<annotation><object><name>dog</name></object></annotation>";
+        assert!(matches!(
+            parse_annotation("0", missing_size),
+            Err(ParseError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn converts_to_coco() {
+        let dataset: CocoDataset = fixture().into();
+
+        assert_eq!(dataset.num_classes(), 20);
+        // The difficult person is ignored
+        let annotations = dataset.with_image_size(500, 334).annotations(0);
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].category_id, 1);
+        assert_eq!(annotations[0].box_xywh, [91., 71., 213., 401.]);
+    }
+}
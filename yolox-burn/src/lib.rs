@@ -1,3 +1,17 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+#[cfg(feature = "std")]
+pub mod augmentations;
+#[cfg(feature = "dataset")]
+pub mod datasets;
+pub mod loss;
+pub mod metrics;
 pub mod model;
+pub mod preprocess;
+pub mod pretraining;
+pub mod quantization;
+#[cfg(feature = "std")]
+pub mod training;
+pub mod transforms;
+pub mod types;
+pub mod utils;
 extern crate alloc;
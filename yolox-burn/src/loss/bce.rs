@@ -0,0 +1,40 @@
+use burn::tensor::{backend::Backend, Tensor};
+
+/// Binary cross-entropy loss on probabilities (e.g., sigmoid outputs).
+#[derive(Debug, Clone)]
+pub struct BceLoss {
+    eps: f32,
+}
+
+impl Default for BceLoss {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BceLoss {
+    /// Create a new binary cross-entropy loss.
+    pub fn new() -> Self {
+        Self { eps: 1e-7 }
+    }
+
+    /// Compute the element-wise loss.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred`: Predicted probabilities in the range `[0, 1]`.
+    /// * `target` - Target probabilities in the range `[0, 1]`.
+    pub fn forward<B: Backend, const D: usize>(
+        &self,
+        pred: Tensor<B, D>,
+        target: Tensor<B, D>,
+    ) -> Tensor<B, D> {
+        // Clamp to avoid log(0)
+        let pred = pred.clamp(self.eps, 1. - self.eps);
+
+        let pos = target.clone() * pred.clone().log();
+        let neg = target.neg().add_scalar(1.) * pred.neg().add_scalar(1.).log();
+
+        (pos + neg).neg()
+    }
+}
@@ -0,0 +1,238 @@
+use alloc::vec::Vec;
+use burn::tensor::{
+    activation::{log_softmax, softmax},
+    backend::Backend,
+    Tensor,
+};
+
+/// Default temperature of the student outputs.
+const STUDENT_TEMP: f64 = 0.1;
+/// Default momentum of the center update.
+const CENTER_MOMENTUM: f64 = 0.9;
+
+/// Self-distillation loss of [DINO](https://arxiv.org/abs/2104.14294).
+///
+/// Cross-entropy between the sharpened and centered teacher distribution of each global crop
+/// and the student distribution of every other crop. The center is an exponential moving average
+/// of the teacher outputs, which avoids the collapse to a single dimension, while the sharpening
+/// (low teacher temperature) avoids the collapse to the uniform distribution.
+#[derive(Debug, Clone)]
+pub struct DinoLoss<B: Backend> {
+    out_dim: usize,
+    num_crops: usize,
+    warmup_teacher_temp: f64,
+    teacher_temp: f64,
+    warmup_epochs: usize,
+    num_epochs: usize,
+    student_temp: f64,
+    center_momentum: f64,
+    /// Center of the teacher outputs. Shape: `[1, out_dim]`.
+    center: Option<Tensor<B, 2>>,
+}
+
+impl<B: Backend> DinoLoss<B> {
+    /// Create a new DINO loss.
+    ///
+    /// # Arguments
+    ///
+    /// * `out_dim`: Output dimension of the projection heads.
+    /// * `num_crops` - Total number of crops (global and local) seen by the student.
+    /// * `warmup_teacher_temp` - Initial teacher temperature (0.04 in the original paper).
+    /// * `teacher_temp` - Teacher temperature after the warmup (0.04 to 0.07).
+    /// * `warmup_epochs` - Number of epochs of the linear teacher temperature warmup.
+    /// * `num_epochs` - Total number of training epochs.
+    pub fn new(
+        out_dim: usize,
+        num_crops: usize,
+        warmup_teacher_temp: f64,
+        teacher_temp: f64,
+        warmup_epochs: usize,
+        num_epochs: usize,
+    ) -> Self {
+        assert!(
+            warmup_epochs <= num_epochs,
+            "the warmup should not be longer than the training"
+        );
+
+        Self {
+            out_dim,
+            num_crops,
+            warmup_teacher_temp,
+            teacher_temp,
+            warmup_epochs,
+            num_epochs,
+            student_temp: STUDENT_TEMP,
+            center_momentum: CENTER_MOMENTUM,
+            center: None,
+        }
+    }
+
+    /// Set the temperature of the student outputs (defaults to 0.1).
+    pub fn with_student_temp(mut self, student_temp: f64) -> Self {
+        self.student_temp = student_temp;
+        self
+    }
+
+    /// Set the momentum of the center update (defaults to 0.9).
+    pub fn with_center_momentum(mut self, center_momentum: f64) -> Self {
+        self.center_momentum = center_momentum;
+        self
+    }
+
+    /// Teacher temperature at the given epoch, linearly increased from `warmup_teacher_temp` to
+    /// `teacher_temp` during the warmup.
+    pub fn teacher_temp(&self, epoch: usize) -> f64 {
+        let epoch = epoch.min(self.num_epochs);
+        if epoch + 1 >= self.warmup_epochs {
+            return self.teacher_temp;
+        }
+
+        let progress = epoch as f64 / (self.warmup_epochs - 1) as f64;
+        self.warmup_teacher_temp + progress * (self.teacher_temp - self.warmup_teacher_temp)
+    }
+
+    /// Center of the teacher outputs, if it has been computed. Shape: `[1, out_dim]`.
+    pub fn center(&self) -> Option<Tensor<B, 2>> {
+        self.center.clone()
+    }
+
+    /// Compute the loss and update the center with the teacher outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `student_output`: Student outputs of the global crops, then of the local crops (see
+    ///   [DinoOutput](crate::pretraining::DinoOutput)). Shape: `[batch_size, out_dim]` each.
+    /// * `teacher_output` - Teacher outputs of the global crops. Shape: `[batch_size, out_dim]`
+    ///   each.
+    /// * `epoch` - Current training epoch (for the teacher temperature schedule).
+    ///
+    /// # Returns
+    ///
+    /// The mean cross-entropy over the (teacher, student) crop pairs, with shape `[1]`.
+    pub fn forward(
+        &mut self,
+        student_output: Vec<Tensor<B, 2>>,
+        teacher_output: Vec<Tensor<B, 2>>,
+        epoch: usize,
+    ) -> Tensor<B, 1> {
+        assert_eq!(
+            student_output.len(),
+            self.num_crops,
+            "expected one student output per crop"
+        );
+        let device = student_output[0].device();
+        let temp = self.teacher_temp(epoch);
+        let center = self
+            .center
+            .clone()
+            .unwrap_or_else(|| Tensor::zeros([1, self.out_dim], &device));
+
+        let teacher_output: Vec<_> = teacher_output.into_iter().map(|t| t.detach()).collect();
+        let teacher_probs: Vec<_> = teacher_output
+            .iter()
+            .map(|t| softmax((t.clone() - center.clone()).div_scalar(temp), 1))
+            .collect();
+        let student_log_probs: Vec<_> = student_output
+            .into_iter()
+            .map(|s| log_softmax(s.div_scalar(self.student_temp), 1))
+            .collect();
+
+        let mut loss = Tensor::zeros([1], &device);
+        let mut num_terms = 0;
+        for (iq, q) in teacher_probs.iter().enumerate() {
+            for (v, log_p) in student_log_probs.iter().enumerate() {
+                // The teacher and student see the same view
+                if v == iq {
+                    continue;
+                }
+                loss = loss + (q.clone() * log_p.clone()).sum_dim(1).neg().mean();
+                num_terms += 1;
+            }
+        }
+
+        self.update_center(teacher_output);
+
+        if num_terms == 0 {
+            loss
+        } else {
+            loss.div_scalar(num_terms as f32)
+        }
+    }
+
+    /// Update the center with the mean of the teacher outputs.
+    fn update_center(&mut self, teacher_output: Vec<Tensor<B, 2>>) {
+        if teacher_output.is_empty() {
+            return;
+        }
+
+        let batch_center = Tensor::cat(teacher_output, 0).mean_dim(0);
+        let center = match self.center.take() {
+            Some(center) => center,
+            None => Tensor::zeros([1, self.out_dim], &batch_center.device()),
+        };
+        self.center = Some(
+            center.mul_scalar(self.center_momentum)
+                + batch_center.mul_scalar(1. - self.center_momentum),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn teacher_temperature_warmup() {
+        let loss = DinoLoss::<TestBackend>::new(8, 4, 0.04, 0.07, 4, 10);
+
+        assert!((loss.teacher_temp(0) - 0.04).abs() < 1e-9);
+        assert!((loss.teacher_temp(1) - 0.05).abs() < 1e-9);
+        assert!((loss.teacher_temp(3) - 0.07).abs() < 1e-9);
+        assert!((loss.teacher_temp(20) - 0.07).abs() < 1e-9);
+    }
+
+    #[test]
+    fn center_update() {
+        let device = Default::default();
+        let mut loss = DinoLoss::<TestBackend>::new(2, 2, 0.04, 0.04, 0, 10);
+        let student = || Tensor::random([2, 2], Distribution::Default, &device);
+        let teacher = Tensor::<TestBackend, 2>::from_floats([[1., 3.], [3., 5.]], &device);
+
+        assert!(loss.center().is_none());
+        let _ = loss.forward(vec![student(), student()], vec![teacher.clone()], 0);
+        loss.center()
+            .unwrap()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[0.2f32, 0.4]]), 5);
+        let _ = loss.forward(vec![student(), student()], vec![teacher], 0);
+        loss.center()
+            .unwrap()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[0.38f32, 0.76]]), 5);
+    }
+
+    #[test]
+    fn matching_distributions() {
+        let device = Default::default();
+        let mut loss = DinoLoss::<TestBackend>::new(3, 2, 0.1, 0.1, 0, 10);
+        let logits = Tensor::<TestBackend, 2>::from_floats([[1., 0., -1.]], &device);
+
+        let value = loss.forward(
+            vec![logits.clone(), logits.clone()],
+            vec![logits.clone()],
+            0,
+        );
+
+        // Cross-entropy of the student and teacher distributions, equal to their entropy
+        let probs = softmax(logits.clone().div_scalar(0.1), 1);
+        let entropy = (probs.clone() * probs.log()).sum().neg();
+        value.into_data().assert_approx_eq(&entropy.into_data(), 4);
+    }
+}
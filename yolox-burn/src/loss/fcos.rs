@@ -0,0 +1,305 @@
+use alloc::{vec, vec::Vec};
+use burn::tensor::{backend::Backend, Bool, Int, Tensor};
+
+/// Cost of the anchor points outside of a ground truth box, larger than any box area.
+const INF: f32 = 1e8;
+
+/// Targets assigned to the anchor points by the [FCOS target assigner](FcosTargetAssigner).
+pub struct FcosTargets<B: Backend> {
+    /// Class index of the assigned box, `-1` for the background. Shape: `[num_anchors]`.
+    pub cls_targets: Tensor<B, 1, Int>,
+    /// Distances `(left, top, right, bottom)` in pixels from the anchor point to the sides of
+    /// the assigned box, zero for the background. Shape: `[num_anchors, 4]`.
+    pub box_targets_ltrb: Tensor<B, 2>,
+    /// Centerness of the anchor point in the assigned box, in the range `[0, 1]`, zero for the
+    /// background. Shape: `[num_anchors]`.
+    pub centerness_targets: Tensor<B, 1>,
+    /// Whether a box is assigned to the anchor point. Shape: `[num_anchors]`.
+    pub foreground_mask: Tensor<B, 1, Bool>,
+}
+
+/// Target assignment of [FCOS](https://arxiv.org/abs/1904.01355).
+///
+/// An anchor point is positive for a ground truth box when:
+///   - it lies inside the box, and within `center_sampling_radius` strides of the box center
+///     (center sampling is disabled when the radius is zero),
+///   - the largest distance to the sides of the box is within the regression range of the level
+///     of the anchor point, so that each box is predicted by the levels of the matching scale.
+///
+/// Anchor points which are positive for multiple boxes are assigned to the smallest box.
+#[derive(Debug, Clone)]
+pub struct FcosTargetAssigner {
+    strides: Vec<usize>,
+    regress_ranges: Vec<(f32, f32)>,
+    center_sampling_radius: f32,
+}
+
+impl FcosTargetAssigner {
+    /// Create a new FCOS target assigner.
+    ///
+    /// # Arguments
+    ///
+    /// * `strides`: Stride of each feature level.
+    /// * `regress_ranges` - Range `(min, max)` of the largest distance to the box sides for each
+    ///   feature level (e.g., `(0, 64)`, `(64, 128)`, ..., `(512, INF)`).
+    /// * `center_sampling_radius` - Radius of the center region, in strides (e.g., `1.5`).
+    pub fn new(
+        strides: Vec<usize>,
+        regress_ranges: Vec<(f32, f32)>,
+        center_sampling_radius: f32,
+    ) -> Self {
+        assert_eq!(
+            strides.len(),
+            regress_ranges.len(),
+            "expected one regression range per stride"
+        );
+
+        Self {
+            strides,
+            regress_ranges,
+            center_sampling_radius,
+        }
+    }
+
+    /// Assign the ground truth boxes of an image to the anchor points.
+    ///
+    /// # Arguments
+    ///
+    /// * `gt_boxes`: Ground truth boxes `(xmin, ymin, xmax, ymax)`. Shape: `[num_boxes, 4]`.
+    /// * `gt_labels` - Class index of each box. Shape: `[num_boxes]`.
+    /// * `anchor_points` - Anchor points `(x, y)` in pixels (e.g., from
+    ///   [make_anchor_grid](crate::model::decode_grid::make_anchor_grid)).
+    ///   Shape: `[num_anchors, 2]`.
+    /// * `strides_per_anchor` - Stride of the level of each anchor point. Shape: `[num_anchors]`.
+    pub fn assign<B: Backend>(
+        &self,
+        gt_boxes: Tensor<B, 2>,
+        gt_labels: Tensor<B, 1>,
+        anchor_points: Tensor<B, 2>,
+        strides_per_anchor: Tensor<B, 1>,
+    ) -> FcosTargets<B> {
+        let device = anchor_points.device();
+        let [num_anchors, _] = anchor_points.dims();
+        let [num_boxes, _] = gt_boxes.dims();
+        if num_boxes == 0 {
+            return FcosTargets {
+                cls_targets: Tensor::<B, 1, Int>::ones([num_anchors], &device).neg(),
+                box_targets_ltrb: Tensor::zeros([num_anchors, 4], &device),
+                centerness_targets: Tensor::zeros([num_anchors], &device),
+                foreground_mask: Tensor::<B, 1>::zeros([num_anchors], &device).greater_elem(0.),
+            };
+        }
+
+        // Distances from the anchor points to the box sides [A, G]
+        let px = anchor_points.clone().slice([0..num_anchors, 0..1]);
+        let py = anchor_points.slice([0..num_anchors, 1..2]);
+        let column = |i: usize| {
+            gt_boxes
+                .clone()
+                .slice([0..num_boxes, i..i + 1])
+                .reshape([1, num_boxes])
+        };
+        let (x0, y0, x1, y1) = (column(0), column(1), column(2), column(3));
+        let left = px.clone() - x0.clone();
+        let top = py.clone() - y0.clone();
+        let right = x1.clone() - px.clone();
+        let bottom = y1.clone() - py.clone();
+        let distances = Tensor::stack::<3>(
+            vec![left.clone(), top.clone(), right.clone(), bottom.clone()],
+            2,
+        );
+        let min_distance = distances
+            .clone()
+            .min_dim(2)
+            .reshape([num_anchors, num_boxes]);
+        let max_distance = distances.max_dim(2).reshape([num_anchors, num_boxes]);
+
+        // Inside the box (and its center region)
+        let mut positive = min_distance.greater_elem(0.).float();
+        if self.center_sampling_radius > 0. {
+            let radius = strides_per_anchor
+                .clone()
+                .reshape([num_anchors, 1])
+                .mul_scalar(self.center_sampling_radius);
+            let cx = (x0.clone() + x1.clone()).div_scalar(2.);
+            let cy = (y0.clone() + y1.clone()).div_scalar(2.);
+            let in_center = (px - cx).abs().lower(radius.clone()).float()
+                * (py - cy).abs().lower(radius).float();
+            positive = positive * in_center;
+        }
+
+        // Within the regression range of the level
+        let mut range_min = Tensor::<B, 2>::zeros([num_anchors, 1], &device);
+        let mut range_max = Tensor::<B, 2>::zeros([num_anchors, 1], &device);
+        let strides = strides_per_anchor.reshape([num_anchors, 1]);
+        for (&stride, &(min, max)) in self.strides.iter().zip(&self.regress_ranges) {
+            let level = strides.clone().equal_elem(stride as f32).float();
+            range_min = range_min + level.clone().mul_scalar(min);
+            range_max = range_max + level.mul_scalar(max.min(INF));
+        }
+        let positive = positive
+            * max_distance.clone().greater_equal(range_min).float()
+            * max_distance.lower_equal(range_max).float();
+
+        // Assign the smallest positive box
+        let areas = (x1 - x0) * (y1 - y0);
+        let cost = positive.clone() * areas + positive.neg().add_scalar(1.).mul_scalar(INF);
+        let (min_cost, assigned) = cost.min_dim_with_indices(1);
+        let foreground = min_cost.lower_elem(INF).reshape([num_anchors]);
+        let fg = foreground.clone().float();
+
+        let gather = |x: Tensor<B, 2>| x.gather(1, assigned.clone()).reshape([num_anchors]);
+        let (left, top) = (gather(left), gather(top));
+        let (right, bottom) = (gather(right), gather(bottom));
+
+        let ratio = |a: Tensor<B, 1>, b: Tensor<B, 1>| {
+            a.clone().min_pair(b.clone()) / a.max_pair(b).clamp_min(1e-6)
+        };
+        let centerness = (ratio(left.clone(), right.clone()) * ratio(top.clone(), bottom.clone()))
+            .clamp(0., 1.)
+            .sqrt()
+            * fg.clone();
+        let box_targets = Tensor::stack::<2>(vec![left, top, right, bottom], 1)
+            * fg.clone().reshape([num_anchors, 1]);
+
+        let labels = gt_labels
+            .reshape([1, num_boxes])
+            .repeat_dim(0, num_anchors)
+            .gather(1, assigned)
+            .reshape([num_anchors]);
+        // Background points are labeled -1
+        let cls_targets = (labels.add_scalar(1.) * fg).int().sub_scalar(1);
+
+        FcosTargets {
+            cls_targets,
+            box_targets_ltrb: box_targets,
+            centerness_targets: centerness,
+            foreground_mask: foreground,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Four anchor points of stride 8 and one of stride 16.
+    fn anchors() -> (Tensor<TestBackend, 2>, Tensor<TestBackend, 1>) {
+        let device = Default::default();
+        let points = Tensor::from_floats(
+            [[4., 4.], [12., 12.], [100., 100.], [28., 28.], [24., 24.]],
+            &device,
+        );
+        let strides = Tensor::from_floats([8., 8., 8., 8., 16.], &device);
+
+        (points, strides)
+    }
+
+    fn assign(center_sampling_radius: f32) -> FcosTargets<TestBackend> {
+        let device = Default::default();
+        let assigner = FcosTargetAssigner::new(
+            vec![8, 16],
+            vec![(0., 64.), (64., f32::INFINITY)],
+            center_sampling_radius,
+        );
+        let gt_boxes = Tensor::from_floats([[0., 0., 32., 32.], [8., 8., 16., 16.]], &device);
+        let gt_labels = Tensor::from_floats([2., 1.], &device);
+        let (points, strides) = anchors();
+
+        assigner.assign(gt_boxes, gt_labels, points, strides)
+    }
+
+    #[test]
+    fn fcos_assigns_smallest_box() {
+        let targets = assign(0.);
+
+        // The point outside of the boxes and the point of the wrong level are background
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([true, true, false, true, false]), false);
+        targets
+            .cls_targets
+            .into_data()
+            .assert_eq(&TensorData::from([2i64, 1, -1, 2, -1]), false);
+        targets.box_targets_ltrb.into_data().assert_approx_eq(
+            &TensorData::from([
+                [4., 4., 28., 28.],
+                [4., 4., 4., 4.],
+                [0., 0., 0., 0.],
+                [28., 28., 4., 4.],
+                [0., 0., 0., 0.],
+            ]),
+            5,
+        );
+    }
+
+    #[test]
+    fn fcos_positive_distances_and_centerness() {
+        let targets = assign(0.);
+        let fg = targets
+            .foreground_mask
+            .into_data()
+            .to_vec::<bool>()
+            .unwrap();
+        let ltrb = targets
+            .box_targets_ltrb
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap();
+        let centerness = targets
+            .centerness_targets
+            .clone()
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap();
+
+        for (i, &fg) in fg.iter().enumerate() {
+            if fg {
+                assert!(ltrb[4 * i..4 * i + 4].iter().all(|&d| d > 0.));
+            }
+            assert!((0. ..=1.).contains(&centerness[i]));
+        }
+        targets
+            .centerness_targets
+            .into_data()
+            .assert_approx_eq(&TensorData::from([4. / 28., 1., 0., 4. / 28., 0.]), 5);
+    }
+
+    #[test]
+    fn fcos_center_sampling() {
+        let targets = assign(1.5);
+
+        // Only the points within 12 pixels of the box centers are positive
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([false, true, false, false, false]), false);
+    }
+
+    #[test]
+    fn fcos_without_boxes() {
+        let device = Default::default();
+        let assigner = FcosTargetAssigner::new(vec![8, 16], vec![(0., 64.), (64., 1e8)], 1.5);
+        let (points, strides) = anchors();
+
+        let targets = assigner.assign(
+            Tensor::zeros([0, 4], &device),
+            Tensor::zeros([0], &device),
+            points,
+            strides,
+        );
+
+        targets
+            .cls_targets
+            .into_data()
+            .assert_eq(&TensorData::from([-1i64; 5]), false);
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([false; 5]), false);
+    }
+}
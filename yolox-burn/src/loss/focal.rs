@@ -0,0 +1,200 @@
+use burn::{
+    nn::loss::Reduction,
+    tensor::{activation::sigmoid, backend::Backend, Tensor},
+};
+
+use super::BceLoss;
+
+/// [Focal loss](https://arxiv.org/abs/1708.02002) on probabilities (e.g., sigmoid outputs).
+///
+/// Down-weights the loss of well classified examples to focus training on hard examples:
+/// `FL(p_t) = -alpha_t * (1 - p_t)^gamma * log(p_t)`.
+#[derive(Debug, Clone)]
+pub struct FocalLoss {
+    alpha: f32,
+    gamma: f32,
+    bce: BceLoss,
+}
+
+impl Default for FocalLoss {
+    fn default() -> Self {
+        Self::new(0.25, 2.0)
+    }
+}
+
+impl FocalLoss {
+    /// Create a new focal loss.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha`: Weighting factor of the positive examples in the range `[0, 1]`. Negative
+    ///   examples are weighted by `1 - alpha`.
+    /// * `gamma` - Focusing parameter (`gamma = 0` is equivalent to the weighted BCE).
+    pub fn new(alpha: f32, gamma: f32) -> Self {
+        Self {
+            alpha,
+            gamma,
+            bce: BceLoss::new(),
+        }
+    }
+
+    /// Compute the element-wise loss.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred`: Predicted probabilities in the range `[0, 1]`.
+    /// * `target` - Target probabilities in the range `[0, 1]`.
+    pub fn forward<B: Backend, const D: usize>(
+        &self,
+        pred: Tensor<B, D>,
+        target: Tensor<B, D>,
+    ) -> Tensor<B, D> {
+        let bce = self.bce.forward(pred.clone(), target.clone());
+
+        // p_t = p if t == 1 else 1 - p
+        let p_t = pred.clone() * target.clone()
+            + pred.neg().add_scalar(1.) * target.clone().neg().add_scalar(1.);
+        let alpha_t = target.clone().mul_scalar(self.alpha)
+            + target.neg().add_scalar(1.).mul_scalar(1. - self.alpha);
+        let modulating = p_t.neg().add_scalar(1.).powf_scalar(self.gamma);
+
+        alpha_t * modulating * bce
+    }
+}
+
+/// [Focal loss](https://arxiv.org/abs/1708.02002) of binary segmentation masks, on logits.
+///
+/// The binary cross-entropy is computed from the logits with the numerically stable
+/// `max(0, x) - x * t + log(1 + exp(-|x|))`, and weighted by `alpha_t * (1 - p_t)^gamma` as in
+/// the [focal loss](FocalLoss).
+///
+/// # Arguments
+///
+/// * `pred`: Predicted logits with shape `[batch_size, 1, height, width]`.
+/// * `target` - Binary target masks with shape `[batch_size, 1, height, width]`.
+/// * `alpha` - Weighting factor of the foreground pixels in the range `[0, 1]`. Background pixels
+///   are weighted by `1 - alpha`.
+/// * `gamma` - Focusing parameter (`gamma = 0` is equivalent to the weighted BCE).
+/// * `pos_weight` - Additional weight of the foreground pixels, for imbalanced masks.
+/// * `reduction` - Reduction of the pixel losses (the mean for [auto](Reduction::Auto)).
+pub fn binary_focal_loss<B: Backend>(
+    pred: Tensor<B, 4>,
+    target: Tensor<B, 4>,
+    alpha: f32,
+    gamma: f32,
+    pos_weight: Option<f32>,
+    reduction: Reduction,
+) -> Tensor<B, 1> {
+    let background = target.clone().neg().add_scalar(1.);
+    let bce = pred.clone().clamp_min(0.) - pred.clone() * target.clone()
+        + pred.clone().abs().neg().exp().add_scalar(1.).log();
+
+    // p_t = p if t == 1 else 1 - p
+    let p = sigmoid(pred);
+    let p_t = p.clone() * target.clone() + p.neg().add_scalar(1.) * background.clone();
+    let alpha_t = target.clone().mul_scalar(alpha) + background.clone().mul_scalar(1. - alpha);
+    // Clamped so that the gradient of the saturated predictions is finite for gamma < 1
+    let modulating = p_t.neg().add_scalar(1.).clamp_min(1e-7).powf_scalar(gamma);
+
+    let mut loss = alpha_t * modulating * bce;
+    if let Some(pos_weight) = pos_weight {
+        loss = loss * (target.mul_scalar(pos_weight) + background);
+    }
+
+    match reduction {
+        Reduction::Sum => loss.sum(),
+        Reduction::Mean | Reduction::Auto => loss.mean(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        tensor::TensorData,
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    /// Masks of shape `[1, 1, 1, N]`.
+    fn masks<B: Backend, const N: usize>(
+        pred: [f32; N],
+        target: [f32; N],
+        device: &B::Device,
+    ) -> (Tensor<B, 4>, Tensor<B, 4>) {
+        (
+            Tensor::<B, 1>::from_floats(pred, device).reshape([1, 1, 1, N]),
+            Tensor::<B, 1>::from_floats(target, device).reshape([1, 1, 1, N]),
+        )
+    }
+
+    #[test]
+    fn binary_focal_loss_without_focusing_is_bce() {
+        let device = Default::default();
+        let logits = [0., 2., -1., 3.];
+        let targets = [1., 0., 1., 1.];
+        let (pred, target) = masks::<TestBackend, 4>(logits, targets, &device);
+
+        let loss = binary_focal_loss(pred, target, 0.25, 0., None, Reduction::Mean);
+
+        // Weighted BCE: -log(sigmoid(x)) for the foreground, -log(1 - sigmoid(x)) otherwise
+        let bce = |x: f32, t: f32| match t == 1. {
+            true => 0.25 * (1. + (-x).exp()).ln(),
+            false => 0.75 * (1. + x.exp()).ln(),
+        };
+        let expected = logits
+            .iter()
+            .zip(targets)
+            .map(|(&x, t)| bce(x, t))
+            .sum::<f32>()
+            / 4.;
+        loss.into_data()
+            .assert_approx_eq(&TensorData::from([expected]), 5);
+    }
+
+    #[test]
+    fn binary_focal_loss_pos_weight() {
+        let device = Default::default();
+        let loss = |pred, target| {
+            let (pred, target) = masks::<TestBackend, 1>([pred], [target], &device);
+            binary_focal_loss(pred, target, 0.5, 2., Some(10.), Reduction::Sum).into_scalar()
+        };
+
+        // Foreground and background pixels with the same unweighted loss
+        let (foreground, background) = (loss(-1., 1.), loss(1., 0.));
+
+        assert!((foreground / background - 10.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn binary_focal_loss_finite_gradients() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+
+        for gamma in [0., 0.5, 2.] {
+            let pred = Tensor::<TrainingBackend, 1>::from_floats(
+                [-100., -50., -1., 0., 1., 50., 100., -100., 0., 100.],
+                &device,
+            )
+            .reshape([1, 1, 2, 5])
+            .require_grad();
+            let target = Tensor::<TrainingBackend, 1>::from_floats(
+                [1., 1., 1., 1., 1., 1., 1., 0., 0., 0.],
+                &device,
+            )
+            .reshape([1, 1, 2, 5]);
+
+            let loss =
+                binary_focal_loss(pred.clone(), target, 0.25, gamma, Some(2.), Reduction::Mean);
+            assert!(loss.clone().into_scalar().is_finite());
+
+            let grads = loss.backward();
+            let grad: Vec<f32> = pred.grad(&grads).unwrap().into_data().to_vec().unwrap();
+            assert!(
+                grad.iter().all(|g| g.is_finite()),
+                "gamma {gamma}: {grad:?}"
+            );
+        }
+    }
+}
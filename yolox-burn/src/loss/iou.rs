@@ -0,0 +1,87 @@
+use burn::tensor::{backend::Backend, Tensor};
+
+/// Type of [IoU loss](IouLoss).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IouLossType {
+    /// `1 - IoU^2`, as used in YOLOX.
+    Iou,
+    /// Generalized IoU loss `1 - GIoU` from [Rezatofighi et al.](https://arxiv.org/abs/1902.09630).
+    GIou,
+}
+
+/// Intersection over union (IoU) based box regression loss.
+#[derive(Debug, Clone)]
+pub struct IouLoss {
+    loss_type: IouLossType,
+}
+
+impl Default for IouLoss {
+    fn default() -> Self {
+        Self::new(IouLossType::Iou)
+    }
+}
+
+impl IouLoss {
+    /// Create a new IoU loss.
+    pub fn new(loss_type: IouLossType) -> Self {
+        Self { loss_type }
+    }
+
+    /// Compute the loss for each pair of boxes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred`: Predicted boxes `(cx, cy, w, h)`. Shape: `[num_boxes, 4]`.
+    /// * `target` - Target boxes `(cx, cy, w, h)`. Shape: `[num_boxes, 4]`.
+    ///
+    /// # Returns
+    ///
+    /// The loss for each box. Shape: `[num_boxes]`.
+    pub fn forward<B: Backend>(&self, pred: Tensor<B, 2>, target: Tensor<B, 2>) -> Tensor<B, 1> {
+        let (iou, enclosing_area, union) = iou_cxcywh(pred, target);
+
+        match self.loss_type {
+            IouLossType::Iou => iou.powf_scalar(2.).neg().add_scalar(1.),
+            IouLossType::GIou => {
+                let giou = iou - (enclosing_area.clone() - union) / enclosing_area.clamp_min(1e-16);
+                giou.neg().add_scalar(1.)
+            }
+        }
+    }
+}
+
+/// Element-wise IoU of two sets of `(cx, cy, w, h)` boxes with shape `[num_boxes, 4]`.
+///
+/// Returns the IoU, the area of the smallest enclosing box and the union area of each pair.
+pub(crate) fn iou_cxcywh<B: Backend>(
+    a: Tensor<B, 2>,
+    b: Tensor<B, 2>,
+) -> (Tensor<B, 1>, Tensor<B, 1>, Tensor<B, 1>) {
+    let [n, _] = a.dims();
+    let col = |t: &Tensor<B, 2>, i: usize| t.clone().slice([0..n, i..i + 1]).reshape([n]);
+
+    let (a_cx, a_cy, a_w, a_h) = (col(&a, 0), col(&a, 1), col(&a, 2), col(&a, 3));
+    let (b_cx, b_cy, b_w, b_h) = (col(&b, 0), col(&b, 1), col(&b, 2), col(&b, 3));
+
+    let a_x1 = a_cx.clone() - a_w.clone() / 2.;
+    let a_x2 = a_cx + a_w.clone() / 2.;
+    let a_y1 = a_cy.clone() - a_h.clone() / 2.;
+    let a_y2 = a_cy + a_h.clone() / 2.;
+    let b_x1 = b_cx.clone() - b_w.clone() / 2.;
+    let b_x2 = b_cx + b_w.clone() / 2.;
+    let b_y1 = b_cy.clone() - b_h.clone() / 2.;
+    let b_y2 = b_cy + b_h.clone() / 2.;
+
+    let i_w =
+        (a_x2.clone().min_pair(b_x2.clone()) - a_x1.clone().max_pair(b_x1.clone())).clamp_min(0.);
+    let i_h =
+        (a_y2.clone().min_pair(b_y2.clone()) - a_y1.clone().max_pair(b_y1.clone())).clamp_min(0.);
+    let intersection = i_w * i_h;
+    let union = a_w * a_h + b_w * b_h - intersection.clone();
+    let iou = intersection / union.clone().clamp_min(1e-16);
+
+    let e_w = a_x2.max_pair(b_x2) - a_x1.min_pair(b_x1);
+    let e_h = a_y2.max_pair(b_y2) - a_y1.min_pair(b_y1);
+
+    (iou, e_w * e_h, union)
+}
@@ -0,0 +1,23 @@
+mod bce;
+mod dino;
+mod fcos;
+mod focal;
+mod iou;
+mod ohem;
+mod simota;
+mod sparse_rcnn;
+mod tal;
+mod uncertainty;
+mod yolox;
+
+pub use bce::*;
+pub use dino::*;
+pub use fcos::*;
+pub use focal::*;
+pub use iou::*;
+pub use ohem::*;
+pub use simota::*;
+pub use sparse_rcnn::*;
+pub use tal::*;
+pub use uncertainty::*;
+pub use yolox::*;
@@ -0,0 +1,196 @@
+use burn::tensor::{backend::Backend, ElementConversion, Tensor};
+
+/// [Online hard example mining](https://arxiv.org/abs/1604.03540) (OHEM) loss reduction.
+///
+/// Instead of averaging the loss over all the examples, only the hardest examples (i.e., with the
+/// highest loss) are kept so that training focuses on the difficult samples. The wrapped loss is
+/// computed beforehand, element-wise (e.g., with [FocalLoss](super::FocalLoss)).
+#[derive(Debug, Clone)]
+pub struct OhemLoss {
+    ratio: f64,
+    min_kept: usize,
+}
+
+impl OhemLoss {
+    /// Number of examples kept among `num_valid` valid examples.
+    pub fn num_kept(&self, num_valid: usize) -> usize {
+        let kept = (self.ratio * num_valid as f64).ceil() as usize;
+        kept.max(self.min_kept).min(num_valid)
+    }
+
+    /// Compute the mean loss of the hardest examples.
+    ///
+    /// The examples are selected on the loss values without tracking the gradients, so the
+    /// gradient only flows through the selected examples.
+    ///
+    /// # Arguments
+    ///
+    /// * `per_element_loss`: Loss of each example. Shape: `[num_examples]`.
+    /// * `mask` - Optional mask of the valid examples (`1` for valid, `0` for ignored). Shape:
+    ///   `[num_examples]`.
+    ///
+    /// # Returns
+    ///
+    /// The mean loss of the selected examples with shape `[1]`, or zero if there is no valid
+    /// example.
+    pub fn forward<B: Backend>(
+        &self,
+        per_element_loss: Tensor<B, 1>,
+        mask: Option<Tensor<B, 1>>,
+    ) -> Tensor<B, 1> {
+        let [num_examples] = per_element_loss.dims();
+        let device = per_element_loss.device();
+
+        let (scores, num_valid) = match mask {
+            Some(mask) => {
+                let num_valid = mask.clone().sum().into_scalar().elem::<f32>().round() as usize;
+                // Ignored examples are ranked last
+                let scores = per_element_loss
+                    .clone()
+                    .detach()
+                    .mask_fill(mask.lower_equal_elem(0.), f32::NEG_INFINITY);
+                (scores, num_valid)
+            }
+            None => (per_element_loss.clone().detach(), num_examples),
+        };
+
+        let num_kept = self.num_kept(num_valid);
+        if num_kept == 0 {
+            return Tensor::zeros([1], &device);
+        }
+
+        let (_, indices) = scores.topk_with_indices(num_kept, 0);
+        per_element_loss.select(0, indices).mean()
+    }
+}
+
+/// [OHEM loss](OhemLoss) configuration.
+pub struct OhemLossConfig {
+    ratio: f64,
+    min_kept: usize,
+}
+
+impl OhemLossConfig {
+    /// Create a new instance of the OHEM loss [config](OhemLossConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `ratio`: Fraction of the valid examples to keep, in the range `[0, 1]`.
+    /// * `min_kept` - Minimum number of examples to keep (if there are enough valid examples).
+    pub fn new(ratio: f64, min_kept: usize) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&ratio),
+            "the OHEM ratio should be in the range [0, 1]"
+        );
+
+        Self { ratio, min_kept }
+    }
+
+    /// Initialize a new [OHEM loss](OhemLoss).
+    pub fn init(&self) -> OhemLoss {
+        OhemLoss {
+            ratio: self.ratio,
+            min_kept: self.min_kept,
+        }
+    }
+}
+
+/// Linear schedule of the [OHEM](OhemLoss) ratio, from `start_ratio` at the first epoch to
+/// `end_ratio` after `num_epochs` epochs (e.g., to progressively focus on harder examples).
+pub fn ohem_ratio_schedule(
+    epoch: usize,
+    start_ratio: f64,
+    end_ratio: f64,
+    num_epochs: usize,
+) -> f64 {
+    if num_epochs == 0 {
+        return end_ratio;
+    }
+
+    let progress = (epoch as f64 / num_epochs as f64).min(1.);
+    start_ratio + (end_ratio - start_ratio) * progress
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        tensor::TensorData,
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn losses() -> Tensor<TestBackend, 1> {
+        Tensor::from_floats([0.5, 3.0, 1.0, 2.0, 0.1], &Default::default())
+    }
+
+    #[test]
+    fn ohem_zero_ratio_keeps_min_kept() {
+        let ohem = OhemLossConfig::new(0., 2).init();
+
+        // Mean of the two hardest examples
+        ohem.forward(losses(), None)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([2.5]), 5);
+    }
+
+    #[test]
+    fn ohem_unit_ratio_keeps_all() {
+        let ohem = OhemLossConfig::new(1., 2).init();
+
+        ohem.forward(losses(), None)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([1.32]), 5);
+    }
+
+    #[test]
+    fn ohem_ignores_masked_examples() {
+        let device = Default::default();
+        let ohem = OhemLossConfig::new(1., 0).init();
+        let mask = Tensor::from_floats([1., 0., 1., 0., 1.], &device);
+
+        // Only the valid examples are averaged
+        ohem.forward(losses(), Some(mask))
+            .into_data()
+            .assert_approx_eq(&TensorData::from([0.5333333]), 5);
+        assert_eq!(
+            ohem.forward(losses(), Some(Tensor::zeros([5], &device)))
+                .into_scalar(),
+            0.
+        );
+    }
+
+    #[test]
+    fn ohem_gradient_selected_examples() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let ohem = OhemLossConfig::new(0.4, 0).init();
+        let loss = Tensor::<TrainingBackend, 1>::from_floats([0.5, 3.0, 1.0, 2.0, 0.1], &device)
+            .require_grad();
+
+        let grads = ohem.forward(loss.clone(), None).backward();
+
+        // The two hardest examples receive the gradient of the mean
+        loss.grad(&grads)
+            .unwrap()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([0., 0.5, 0., 0.5, 0.]), 5);
+    }
+
+    #[test]
+    fn ohem_num_kept() {
+        let ohem = OhemLossConfig::new(0.25, 3).init();
+
+        assert_eq!(ohem.num_kept(100), 25);
+        assert_eq!(ohem.num_kept(8), 3);
+        assert_eq!(ohem.num_kept(2), 2);
+    }
+
+    #[test]
+    fn ohem_ratio_schedule_linear() {
+        assert_eq!(ohem_ratio_schedule(0, 1., 0.5, 10), 1.);
+        assert!((ohem_ratio_schedule(5, 1., 0.5, 10) - 0.75).abs() < 1e-12);
+        assert_eq!(ohem_ratio_schedule(20, 1., 0.5, 10), 0.5);
+    }
+}
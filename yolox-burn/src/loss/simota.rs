@@ -0,0 +1,457 @@
+use alloc::{vec, vec::Vec};
+use burn::tensor::{backend::Backend, Bool, ElementConversion, Int, Tensor, TensorData};
+
+use super::iou_cxcywh;
+
+/// Default radius (in strides) around the object centers of the candidate anchors.
+const CENTER_RADIUS: f32 = 2.5;
+/// Default number of highest IoU candidates used to estimate the number of positives of a box.
+const CANDIDATE_TOP_K: usize = 10;
+/// Default weight of the IoU cost relative to the classification cost.
+const IOU_WEIGHT: f32 = 3.;
+/// Cost of the candidate anchors which are not both inside the box and its center region.
+const OUTSIDE_COST: f32 = 1e5;
+/// Small value to avoid `log(0)`.
+const EPSILON: f32 = 1e-7;
+
+/// Targets assigned to the predictions of an image by the [SimOTA assigner](SimOtaAssigner).
+pub struct SimOtaTargets<B: Backend> {
+    /// Matched ground truth boxes `(cx, cy, w, h)`, zero for the background.
+    /// Shape: `[num_anchors, 4]`.
+    pub box_targets: Tensor<B, 2>,
+    /// One-hot class of the matched box scaled by the IoU of the prediction with the box, all
+    /// zeros for the background. Shape: `[num_anchors, num_classes]`.
+    pub cls_targets: Tensor<B, 2>,
+    /// Objectness target, 1 for the foreground and 0 for the background. Shape: `[num_anchors]`.
+    pub obj_targets: Tensor<B, 1>,
+    /// Whether a box is matched to the prediction. Shape: `[num_anchors]`.
+    pub foreground_mask: Tensor<B, 1, Bool>,
+    /// Number of foreground predictions.
+    pub num_foreground: usize,
+}
+
+/// SimOTA dynamic label assignment of [YOLOX](https://arxiv.org/abs/2107.08430).
+///
+/// The candidate anchors of a ground truth box are inside the box or within
+/// `center_radius` strides of its center. The cost of assigning a candidate to a box is the
+/// binary cross-entropy of its class scores with the box class, plus `iou_weight` times
+/// `-log(IoU)` of the predicted box, plus a large constant if the anchor is not both inside the
+/// box and its center region.
+///
+/// Each box is assigned to its `k` lowest cost candidates, where `k` is the sum of the
+/// `candidate_top_k` highest IoUs of the candidates (at least 1). Anchors assigned to several
+/// boxes keep the lowest cost one.
+#[derive(Debug, Clone)]
+pub struct SimOtaAssigner {
+    center_radius: f32,
+    candidate_top_k: usize,
+    iou_weight: f32,
+}
+
+impl Default for SimOtaAssigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimOtaAssigner {
+    /// Create a new SimOTA assigner with the YOLOX settings.
+    pub fn new() -> Self {
+        Self {
+            center_radius: CENTER_RADIUS,
+            candidate_top_k: CANDIDATE_TOP_K,
+            iou_weight: IOU_WEIGHT,
+        }
+    }
+
+    /// Set the radius (in strides) of the center region of the boxes (defaults to 2.5).
+    pub fn with_center_radius(mut self, center_radius: f32) -> Self {
+        self.center_radius = center_radius;
+        self
+    }
+
+    /// Set the number of highest IoU candidates used to estimate the number of positives of
+    /// each box (defaults to 10).
+    pub fn with_candidate_top_k(mut self, candidate_top_k: usize) -> Self {
+        self.candidate_top_k = candidate_top_k;
+        self
+    }
+
+    /// Set the weight of the IoU cost (defaults to 3).
+    pub fn with_iou_weight(mut self, iou_weight: f32) -> Self {
+        self.iou_weight = iou_weight;
+        self
+    }
+
+    /// Assign the ground truth boxes of an image to the predictions.
+    ///
+    /// The predictions are only used to compute the assignment costs, and are detached.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred_boxes`: Predicted boxes `(cx, cy, w, h)`. Shape: `[num_anchors, 4]`.
+    /// * `pred_scores` - Predicted class probabilities, multiplied by the objectness.
+    ///   Shape: `[num_anchors, num_classes]`.
+    /// * `anchors` - Anchor points `(x, y)` and stride of each prediction.
+    ///   Shape: `[num_anchors, 3]`.
+    /// * `gt_boxes` - Ground truth boxes `(cx, cy, w, h)`. Shape: `[num_boxes, 4]`.
+    /// * `gt_labels` - Class index of each box. Shape: `[num_boxes]`.
+    pub fn assign<B: Backend>(
+        &self,
+        pred_boxes: Tensor<B, 2>,
+        pred_scores: Tensor<B, 2>,
+        anchors: Tensor<B, 2>,
+        gt_boxes: Tensor<B, 2>,
+        gt_labels: Tensor<B, 1, Int>,
+    ) -> SimOtaTargets<B> {
+        let device = pred_boxes.device();
+        let [num_anchors, num_classes] = pred_scores.dims();
+        let [num_boxes, _] = gt_boxes.dims();
+        if num_boxes == 0 {
+            return SimOtaTargets {
+                box_targets: Tensor::zeros([num_anchors, 4], &device),
+                cls_targets: Tensor::zeros([num_anchors, num_classes], &device),
+                obj_targets: Tensor::zeros([num_anchors], &device),
+                foreground_mask: Tensor::<B, 1>::zeros([num_anchors], &device).greater_elem(0.),
+                num_foreground: 0,
+            };
+        }
+        let (pred_boxes, pred_scores) = (pred_boxes.detach(), pred_scores.detach());
+
+        // Pairwise IoU [num_boxes, num_anchors]
+        let (ious, _, _) = iou_cxcywh(
+            pred_boxes
+                .reshape([1, num_anchors, 4])
+                .repeat_dim(0, num_boxes)
+                .reshape([num_boxes * num_anchors, 4]),
+            gt_boxes
+                .clone()
+                .reshape([num_boxes, 1, 4])
+                .repeat_dim(1, num_anchors)
+                .reshape([num_boxes * num_anchors, 4]),
+        );
+        let ious = ious.reshape([num_boxes, num_anchors]);
+
+        // Candidate anchors inside the boxes or their center regions
+        let (in_box, in_center) = self.candidate_masks(anchors, gt_boxes.clone());
+        let candidates = (in_box.clone() + in_center.clone()).clamp_max(1.);
+        let outside = in_box.mul(in_center).neg().add_scalar(1.);
+
+        // Binary cross-entropy of the class scores with the one-hot box classes
+        let scores = pred_scores.clamp(EPSILON, 1. - EPSILON);
+        let neg_cost = scores.clone().neg().add_scalar(1.).log().neg().sum_dim(1);
+        let box_scores = scores.transpose().select(0, gt_labels.clone());
+        let cls_cost = neg_cost.reshape([1, num_anchors]).repeat_dim(0, num_boxes)
+            - box_scores.clone().log()
+            + box_scores.neg().add_scalar(1.).log();
+
+        let iou_cost = ious.clone().add_scalar(EPSILON).log().neg();
+        let cost =
+            cls_cost + iou_cost.mul_scalar(self.iou_weight) + outside.mul_scalar(OUTSIDE_COST);
+
+        // Dynamic k matching on the host
+        let to_vec = |x: Tensor<B, 2>| -> Vec<f32> {
+            x.into_data()
+                .iter::<B::FloatElem>()
+                .map(|v| v.elem::<f32>())
+                .collect()
+        };
+        let cost = to_vec(cost);
+        let iou_values = to_vec(ious);
+        let candidates = to_vec(candidates);
+
+        // Matched box and its cost for each anchor
+        let mut matched: Vec<Option<(usize, f32)>> = vec![None; num_anchors];
+        for g in 0..num_boxes {
+            let offset = g * num_anchors;
+            let mut anchor_indices: Vec<usize> = (0..num_anchors)
+                .filter(|&a| candidates[offset + a] > 0.)
+                .collect();
+            if anchor_indices.is_empty() {
+                continue;
+            }
+
+            let mut top_ious: Vec<f32> = anchor_indices
+                .iter()
+                .map(|&a| iou_values[offset + a])
+                .collect();
+            top_ious.sort_by(|a, b| b.total_cmp(a));
+            let dynamic_k = (top_ious.iter().take(self.candidate_top_k).sum::<f32>() as usize)
+                .clamp(1, anchor_indices.len());
+
+            anchor_indices.sort_by(|&a, &b| cost[offset + a].total_cmp(&cost[offset + b]));
+            for &a in &anchor_indices[..dynamic_k] {
+                let c = cost[offset + a];
+                if matched[a].map_or(true, |(_, prev)| c < prev) {
+                    matched[a] = Some((g, c));
+                }
+            }
+        }
+
+        let assigned: Vec<i64> = matched
+            .iter()
+            .map(|m| m.map_or(-1, |(g, _)| g as i64))
+            .collect();
+        let num_foreground = assigned.iter().filter(|&&g| g >= 0).count();
+        let matched_ious: Vec<f32> = matched
+            .iter()
+            .enumerate()
+            .map(|(a, m)| m.map_or(0., |(g, _)| iou_values[g * num_anchors + a]))
+            .collect();
+
+        let assigned = Tensor::<B, 1, Int>::from_data(
+            TensorData::new(assigned, [num_anchors]).convert::<B::IntElem>(),
+            &device,
+        );
+        let matched_ious = Tensor::<B, 1>::from_data(
+            TensorData::new(matched_ious, [num_anchors]).convert::<B::FloatElem>(),
+            &device,
+        );
+        let foreground_mask = assigned.clone().greater_equal_elem(0);
+        let obj_targets = foreground_mask.clone().float();
+        let indices = assigned.clamp_min(0);
+        let foreground = obj_targets.clone().reshape([num_anchors, 1]);
+
+        let box_targets = gt_boxes.select(0, indices.clone()) * foreground.clone().repeat_dim(1, 4);
+        let one_hot = Tensor::<B, 1, Int>::arange(0..num_classes as i64, &device)
+            .reshape([1, num_classes])
+            .repeat_dim(0, num_anchors)
+            .equal(
+                gt_labels
+                    .select(0, indices)
+                    .reshape([num_anchors, 1])
+                    .repeat_dim(1, num_classes),
+            )
+            .float();
+        let cls_targets = one_hot
+            * (matched_ious.reshape([num_anchors, 1]) * foreground).repeat_dim(1, num_classes);
+
+        SimOtaTargets {
+            box_targets,
+            cls_targets,
+            obj_targets,
+            foreground_mask,
+            num_foreground,
+        }
+    }
+
+    /// Masks of the anchors inside each box and inside the center region of each box.
+    ///
+    /// # Shapes
+    ///   - anchors: `[num_anchors, 3]`
+    ///   - gt_boxes: `[num_boxes, 4]`
+    ///   - output: `[num_boxes, num_anchors]` for each mask, with values 0 or 1
+    fn candidate_masks<B: Backend>(
+        &self,
+        anchors: Tensor<B, 2>,
+        gt_boxes: Tensor<B, 2>,
+    ) -> (Tensor<B, 2>, Tensor<B, 2>) {
+        let [num_anchors, _] = anchors.dims();
+        let [num_boxes, _] = gt_boxes.dims();
+        // Anchor values repeated for each box, and box values repeated for each anchor
+        let anchor = |i: usize| {
+            anchors
+                .clone()
+                .slice([0..num_anchors, i..i + 1])
+                .reshape([1, num_anchors])
+                .repeat_dim(0, num_boxes)
+        };
+        let gt = |i: usize| {
+            gt_boxes
+                .clone()
+                .slice([0..num_boxes, i..i + 1])
+                .repeat_dim(1, num_anchors)
+        };
+        let (x, y, stride) = (anchor(0), anchor(1), anchor(2));
+        let (cx, cy, half_w, half_h) = (gt(0), gt(1), gt(2).div_scalar(2.), gt(3).div_scalar(2.));
+
+        // Distance of the anchor points to the center, compared with half the region size
+        let dx = (x - cx).abs();
+        let dy = (y - cy).abs();
+        let inside =
+            |dx: Tensor<B, 2>, dy: Tensor<B, 2>, half_w: Tensor<B, 2>, half_h: Tensor<B, 2>| {
+                dx.lower(half_w).float() * dy.lower(half_h).float()
+            };
+        let radius = stride.mul_scalar(self.center_radius);
+
+        (
+            inside(dx.clone(), dy.clone(), half_w, half_h),
+            inside(dx, dy, radius.clone(), radius),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    /// Box `(8, 8, 16, 16)` of class 0, with three candidate anchors inside it and an anchor far
+    /// away. The predictions of the first two anchors match the box (IoU 1) and the third one
+    /// has an IoU of 0.5, so that the sum of the IoUs is 2.5.
+    fn single_box_inputs() -> [Tensor<TestBackend, 2>; 4] {
+        let device = Default::default();
+        let pred_boxes = Tensor::from_floats(
+            [
+                [8., 8., 16., 16.],
+                [8., 8., 16., 16.],
+                [8., 8., 16., 8.],
+                [40., 40., 16., 16.],
+            ],
+            &device,
+        );
+        // Classification costs: -ln(0.5) = 0.693, -ln(0.9) = 0.105
+        let pred_scores = Tensor::from_floats([[0.5], [0.9], [0.9], [0.5]], &device);
+        let anchors = Tensor::from_floats(
+            [[8., 8., 8.], [4., 8., 8.], [12., 8., 8.], [40., 40., 8.]],
+            &device,
+        );
+        let gt_boxes = Tensor::from_floats([[8., 8., 16., 16.]], &device);
+
+        [pred_boxes, pred_scores, anchors, gt_boxes]
+    }
+
+    #[test]
+    fn dynamic_k_from_candidate_ious() {
+        let device = Default::default();
+        let [pred_boxes, pred_scores, anchors, gt_boxes] = single_box_inputs();
+
+        // k = floor(1 + 1 + 0.5) = 2 lowest costs: 0.105 (anchor 1) and 0.693 (anchor 0), the
+        // third candidate having an additional IoU cost of 3 * -ln(0.5) = 2.08
+        let targets = SimOtaAssigner::new().assign(
+            pred_boxes,
+            pred_scores,
+            anchors,
+            gt_boxes,
+            Tensor::from_ints([0], &device),
+        );
+
+        assert_eq!(targets.num_foreground, 2);
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([true, true, false, false]), false);
+        targets
+            .obj_targets
+            .into_data()
+            .assert_eq(&TensorData::from([1f32, 1., 0., 0.]), false);
+        targets.box_targets.into_data().assert_eq(
+            &TensorData::from([
+                [8f32, 8., 16., 16.],
+                [8., 8., 16., 16.],
+                [0., 0., 0., 0.],
+                [0., 0., 0., 0.],
+            ]),
+            false,
+        );
+        targets
+            .cls_targets
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32], [1.], [0.], [0.]]), 5);
+    }
+
+    #[test]
+    fn dynamic_k_top_candidates() {
+        let device = Default::default();
+        let [pred_boxes, pred_scores, anchors, gt_boxes] = single_box_inputs();
+
+        // k = floor(1) = 1 with the highest IoU only: the lowest cost candidate (anchor 1)
+        let targets = SimOtaAssigner::new().with_candidate_top_k(1).assign(
+            pred_boxes.clone(),
+            pred_scores.clone(),
+            anchors.clone(),
+            gt_boxes.clone(),
+            Tensor::from_ints([0], &device),
+        );
+        assert_eq!(targets.num_foreground, 1);
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([false, true, false, false]), false);
+
+        // Only the first anchor is within 0.25 stride of the center: the other candidates have
+        // the outside cost, and the first one has the lowest cost
+        let targets = SimOtaAssigner::new()
+            .with_candidate_top_k(1)
+            .with_center_radius(0.25)
+            .assign(
+                pred_boxes,
+                pred_scores,
+                anchors,
+                gt_boxes,
+                Tensor::from_ints([0], &device),
+            );
+        assert_eq!(targets.num_foreground, 1);
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([true, false, false, false]), false);
+    }
+
+    #[test]
+    fn conflicts_keep_lowest_cost() {
+        let device = Default::default();
+        // A single anchor, candidate of both boxes: IoU 1 with box 1 and 224 / 288 = 0.778 with
+        // box 0 (IoU cost 3 * -ln(0.778) = 0.754)
+        let pred_boxes = Tensor::<TestBackend, 2>::from_floats([[10., 8., 16., 16.]], &device);
+        let anchors = Tensor::from_floats([[8., 8., 8.]], &device);
+        let gt_boxes = Tensor::from_floats([[8., 8., 16., 16.], [10., 8., 16., 16.]], &device);
+        let gt_labels = Tensor::from_ints([0, 1], &device);
+        let assign = |scores: [f32; 2]| {
+            SimOtaAssigner::new().assign(
+                pred_boxes.clone(),
+                Tensor::from_floats([scores], &device),
+                anchors.clone(),
+                gt_boxes.clone(),
+                gt_labels.clone(),
+            )
+        };
+
+        // Same classification costs: the higher IoU (box 1) has the lowest cost
+        let targets = assign([0.5, 0.5]);
+        assert_eq!(targets.num_foreground, 1);
+        targets
+            .box_targets
+            .into_data()
+            .assert_eq(&TensorData::from([[10f32, 8., 16., 16.]]), false);
+        targets
+            .cls_targets
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[0f32, 1.]]), 5);
+
+        // Classification cost 0.02 for box 0 and 9.21 for box 1: box 0 has the lowest cost
+        let targets = assign([0.99, 0.01]);
+        assert_eq!(targets.num_foreground, 1);
+        targets
+            .box_targets
+            .into_data()
+            .assert_eq(&TensorData::from([[8f32, 8., 16., 16.]]), false);
+        targets
+            .cls_targets
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[224f32 / 288., 0.]]), 5);
+    }
+
+    #[test]
+    fn no_ground_truth_is_background() {
+        let device = Default::default();
+        let [pred_boxes, pred_scores, anchors, _] = single_box_inputs();
+
+        let targets = SimOtaAssigner::new().assign(
+            pred_boxes,
+            pred_scores,
+            anchors,
+            Tensor::zeros([0, 4], &device),
+            Tensor::zeros([0], &device),
+        );
+
+        assert_eq!(targets.num_foreground, 0);
+        targets
+            .obj_targets
+            .into_data()
+            .assert_eq(&TensorData::from([0f32; 4]), false);
+        assert_eq!(targets.cls_targets.dims(), [4, 1]);
+    }
+}
@@ -0,0 +1,264 @@
+use alloc::{vec, vec::Vec};
+use burn::tensor::{backend::Backend, Bool, ElementConversion, Int, Tensor, TensorData};
+
+use crate::types::IoUThreshold;
+
+/// Targets assigned to the proposals by the [Sparse R-CNN assigner](SparseRcnnAssigner).
+pub struct SparseRcnnTargets<B: Backend> {
+    /// Matched ground truth boxes `(xmin, ymin, xmax, ymax)`, zero for the background.
+    /// Shape: `[num_proposals, 4]`.
+    pub box_targets: Tensor<B, 2>,
+    /// Class index of the matched box, `-1` for the background. Shape: `[num_proposals]`.
+    pub cls_targets: Tensor<B, 1, Int>,
+    /// Whether a box is matched to the proposal. Shape: `[num_proposals]`.
+    pub foreground_mask: Tensor<B, 1, Bool>,
+}
+
+/// One-to-one target assignment of the learnable proposals of
+/// [Sparse R-CNN](https://arxiv.org/abs/2011.12450).
+///
+/// Each ground truth box is matched to at most one proposal and each proposal to at most one
+/// box. The (proposal, box) pairs are matched greedily by decreasing IoU, so that each box gets
+/// its highest IoU proposal which is not already taken. Pairs with an IoU below the matching
+/// threshold are not matched, and the unmatched proposals are background.
+///
+/// Unlike SimOTA, there is no dynamic number of positives per box.
+#[derive(Debug, Clone, Copy)]
+pub struct SparseRcnnAssigner {
+    match_iou_threshold: IoUThreshold,
+}
+
+impl SparseRcnnAssigner {
+    /// Create a new Sparse R-CNN assigner.
+    ///
+    /// # Arguments
+    ///
+    /// * `match_iou_threshold`: Minimum IoU between a proposal and its matched box.
+    pub fn new(match_iou_threshold: IoUThreshold) -> Self {
+        Self {
+            match_iou_threshold,
+        }
+    }
+
+    /// Assign the ground truth boxes of an image to the proposals.
+    ///
+    /// # Arguments
+    ///
+    /// * `proposals`: Proposal boxes `(xmin, ymin, xmax, ymax)`. Shape: `[num_proposals, 4]`.
+    /// * `gt_boxes` - Ground truth boxes `(xmin, ymin, xmax, ymax)`. Shape: `[num_boxes, 4]`.
+    /// * `gt_labels` - Class index of each box. Shape: `[num_boxes]`.
+    pub fn assign<B: Backend>(
+        &self,
+        proposals: Tensor<B, 2>,
+        gt_boxes: Tensor<B, 2>,
+        gt_labels: Tensor<B, 1>,
+    ) -> SparseRcnnTargets<B> {
+        let device = proposals.device();
+        let [num_proposals, _] = proposals.dims();
+        let [num_boxes, _] = gt_boxes.dims();
+        if num_boxes == 0 {
+            return SparseRcnnTargets {
+                box_targets: Tensor::zeros([num_proposals, 4], &device),
+                cls_targets: Tensor::<B, 1, Int>::ones([num_proposals], &device).neg(),
+                foreground_mask: Tensor::<B, 1>::zeros([num_proposals], &device).greater_elem(0.),
+            };
+        }
+
+        // Greedy matching on the host, by decreasing IoU
+        let ious: Vec<f32> = pairwise_iou(proposals, gt_boxes.clone())
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem())
+            .collect();
+        let mut pairs: Vec<(usize, f32)> = ious
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, iou)| iou >= self.match_iou_threshold.value())
+            .collect();
+        pairs.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let mut assigned = vec![-1i64; num_proposals];
+        let mut box_taken = vec![false; num_boxes];
+        for (index, _) in pairs {
+            let (p, g) = (index / num_boxes, index % num_boxes);
+            if assigned[p] < 0 && !box_taken[g] {
+                assigned[p] = g as i64;
+                box_taken[g] = true;
+            }
+        }
+
+        let assigned = Tensor::<B, 1, Int>::from_data(
+            TensorData::new(assigned, [num_proposals]).convert::<B::IntElem>(),
+            &device,
+        );
+        let foreground = assigned.clone().greater_equal_elem(0);
+        let background = foreground.clone().bool_not();
+        let indices = assigned.clamp_min(0);
+
+        let box_targets = gt_boxes.select(0, indices.clone()).mask_fill(
+            background
+                .clone()
+                .reshape([num_proposals, 1])
+                .repeat_dim(1, 4),
+            0.,
+        );
+        // Background proposals are labeled -1
+        let cls_targets = gt_labels.select(0, indices).int().mask_fill(background, -1);
+
+        SparseRcnnTargets {
+            box_targets,
+            cls_targets,
+            foreground_mask: foreground,
+        }
+    }
+}
+
+/// IoU of each pair of `(xmin, ymin, xmax, ymax)` boxes.
+///
+/// # Shapes
+///   - boxes1: `[n, 4]`
+///   - boxes2: `[m, 4]`
+///   - output: `[n, m]`
+fn pairwise_iou<B: Backend>(boxes1: Tensor<B, 2>, boxes2: Tensor<B, 2>) -> Tensor<B, 2> {
+    let [n, _] = boxes1.dims();
+    let [m, _] = boxes2.dims();
+    // Coordinates repeated to [N, M]
+    let column = |i: usize| boxes1.clone().slice([0..n, i..i + 1]).repeat_dim(1, m);
+    let row = |i: usize| {
+        boxes2
+            .clone()
+            .slice([0..m, i..i + 1])
+            .reshape([1, m])
+            .repeat_dim(0, n)
+    };
+    let (ax0, ay0, ax1, ay1) = (column(0), column(1), column(2), column(3));
+    let (bx0, by0, bx1, by1) = (row(0), row(1), row(2), row(3));
+
+    let inter_w =
+        (ax1.clone().min_pair(bx1.clone()) - ax0.clone().max_pair(bx0.clone())).clamp_min(0.);
+    let inter_h =
+        (ay1.clone().min_pair(by1.clone()) - ay0.clone().max_pair(by0.clone())).clamp_min(0.);
+    let inter = inter_w * inter_h;
+
+    let area1 = (ax1 - ax0) * (ay1 - ay0);
+    let area2 = (bx1 - bx0) * (by1 - by0);
+    let union = area1 + area2 - inter.clone();
+
+    inter / union.clamp_min(1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn assign(
+        proposals: Tensor<TestBackend, 2>,
+        gt_boxes: Tensor<TestBackend, 2>,
+        gt_labels: Tensor<TestBackend, 1>,
+    ) -> SparseRcnnTargets<TestBackend> {
+        SparseRcnnAssigner::new(IoUThreshold::default()).assign(proposals, gt_boxes, gt_labels)
+    }
+
+    #[test]
+    fn perfect_proposal_is_matched() {
+        let device = Default::default();
+        let proposals = Tensor::from_floats([[20., 20., 30., 30.], [0., 0., 10., 10.]], &device);
+        let gt_boxes = Tensor::from_floats([[0., 0., 10., 10.]], &device);
+        let gt_labels = Tensor::from_floats([3.], &device);
+
+        let targets = assign(proposals, gt_boxes, gt_labels);
+
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([false, true]), false);
+        targets
+            .cls_targets
+            .into_data()
+            .assert_eq(&TensorData::from([-1i64, 3]), false);
+        targets.box_targets.into_data().assert_eq(
+            &TensorData::from([[0f32, 0., 0., 0.], [0., 0., 10., 10.]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn low_iou_proposal_is_background() {
+        let device = Default::default();
+        // IoU of 25 / 175
+        let proposals = Tensor::from_floats([[0., 0., 10., 10.]], &device);
+        let gt_boxes = Tensor::from_floats([[5., 5., 15., 15.]], &device);
+        let gt_labels = Tensor::from_floats([1.], &device);
+
+        let targets = assign(proposals, gt_boxes, gt_labels);
+
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([false]), false);
+        targets
+            .cls_targets
+            .into_data()
+            .assert_eq(&TensorData::from([-1i64]), false);
+    }
+
+    #[test]
+    fn box_matched_to_single_proposal() {
+        let device = Default::default();
+        let gt_boxes = Tensor::from_floats([[0., 0., 10., 10.]], &device);
+        let gt_labels = Tensor::from_floats([2.], &device);
+
+        // IoU of 0.8 and 1
+        let proposals = Tensor::from_floats([[0., 0., 10., 8.], [0., 0., 10., 10.]], &device);
+        let targets = assign(proposals, gt_boxes.clone(), gt_labels.clone());
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([false, true]), false);
+
+        // Equal overlaps, the first proposal is matched
+        let proposals = Tensor::from_floats([[0., 0., 10., 8.], [0., 2., 10., 10.]], &device);
+        let targets = assign(proposals, gt_boxes, gt_labels);
+        targets
+            .foreground_mask
+            .into_data()
+            .assert_eq(&TensorData::from([true, false]), false);
+    }
+
+    #[test]
+    fn boxes_matched_greedily() {
+        let device = Default::default();
+        // Both boxes have their highest IoU with the first proposal
+        let proposals = Tensor::from_floats([[0., 0., 10., 10.], [0., 0., 10., 6.]], &device);
+        let gt_boxes = Tensor::from_floats([[0., 0., 10., 9.], [0., 0., 10., 10.]], &device);
+        let gt_labels = Tensor::from_floats([4., 5.], &device);
+
+        let targets = assign(proposals, gt_boxes, gt_labels);
+
+        targets
+            .cls_targets
+            .into_data()
+            .assert_eq(&TensorData::from([5i64, 4]), false);
+    }
+
+    #[test]
+    fn no_ground_truth() {
+        let device = Default::default();
+        let proposals = Tensor::<TestBackend, 2>::ones([3, 4], &device);
+
+        let targets = assign(
+            proposals,
+            Tensor::zeros([0, 4], &device),
+            Tensor::zeros([0], &device),
+        );
+
+        assert_eq!(targets.box_targets.dims(), [3, 4]);
+        targets
+            .cls_targets
+            .into_data()
+            .assert_eq(&TensorData::from([-1i64, -1, -1]), false);
+    }
+}
@@ -0,0 +1,131 @@
+use burn::tensor::{activation::sigmoid, backend::Backend, Tensor};
+
+use super::{iou_cxcywh, BceLoss, IouLoss, IouLossType};
+
+/// Task alignment metric `score^alpha * IoU^beta` of [TOOD](https://arxiv.org/abs/2108.07755),
+/// measuring how well the classification and localization of a prediction agree.
+///
+/// # Shapes
+///   - scores: `[num_boxes]`
+///   - ious: `[num_boxes]`
+///   - output: `[num_boxes]`
+pub fn task_aligned_metric<B: Backend>(
+    scores: Tensor<B, 1>,
+    ious: Tensor<B, 1>,
+    alpha: f32,
+    beta: f32,
+) -> Tensor<B, 1> {
+    scores.powf_scalar(alpha) * ious.powf_scalar(beta)
+}
+
+/// Task-aligned loss of the [TAL head](crate::model::heads::TalHead).
+///
+/// Unlike SimOTA, the positive predictions are weighted by their normalized [task alignment
+/// metric](task_aligned_metric), which is used as the soft target of the classification
+/// (binary cross-entropy) and as the weight of the GIoU loss.
+///
+/// # Arguments
+///
+/// * `cls_logits`: Predicted class logits. Shape: `[num_boxes, num_classes]`.
+/// * `pred_boxes` - Predicted boxes `(cx, cy, w, h)`. Shape: `[num_boxes, 4]`.
+/// * `target_boxes` - Assigned target boxes `(cx, cy, w, h)`. Shape: `[num_boxes, 4]`.
+/// * `target_classes` - One-hot assigned target classes, with all zeros for the background.
+///   Shape: `[num_boxes, num_classes]`.
+/// * `alpha` - Exponent of the classification score in the alignment metric.
+/// * `beta` - Exponent of the IoU in the alignment metric.
+///
+/// # Returns
+///
+/// The sum of the classification and box losses, normalized by the sum of the alignment targets.
+/// Shape: `[1]`.
+pub fn task_align_loss<B: Backend>(
+    cls_logits: Tensor<B, 2>,
+    pred_boxes: Tensor<B, 2>,
+    target_boxes: Tensor<B, 2>,
+    target_classes: Tensor<B, 2>,
+    alpha: f32,
+    beta: f32,
+) -> Tensor<B, 1> {
+    let [n, _] = cls_logits.dims();
+    let foreground = target_classes.clone().sum_dim(1).reshape([n]);
+    let probs = sigmoid(cls_logits);
+
+    // Alignment metric of the assigned class, normalized to the maximum IoU
+    let scores = (probs.clone() * target_classes.clone())
+        .sum_dim(1)
+        .reshape([n]);
+    let (ious, _, _) = iou_cxcywh(pred_boxes.clone().detach(), target_boxes.clone());
+    let ious = ious * foreground.clone();
+    let metric = task_aligned_metric(scores.detach(), ious.clone(), alpha, beta) * foreground;
+    let alignment = (metric.clone() / metric.max().clamp_min(1e-9) * ious.max()).detach();
+
+    let cls_targets = target_classes * alignment.clone().unsqueeze_dim(1);
+    let cls_loss = BceLoss::new().forward(probs, cls_targets).sum();
+    let box_loss = IouLoss::new(IouLossType::GIou).forward(pred_boxes, target_boxes);
+    let box_loss = (box_loss * alignment.clone()).sum();
+
+    (cls_loss + box_loss) / alignment.sum().clamp_min(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn aligned_metric_values() {
+        let device = Default::default();
+        let scores = Tensor::<TestBackend, 1>::from_floats([0.5, 0.5, 0.8], &device);
+        let ious = Tensor::from_floats([1., 0.5, 0.], &device);
+
+        let metric = task_aligned_metric(scores, ious, 1., 6.);
+
+        metric
+            .into_data()
+            .assert_approx_eq(&TensorData::from([0.5f32, 0.0078125, 0.]), 6);
+    }
+
+    #[test]
+    fn aligned_metric_higher_for_perfect_prediction() {
+        let device = Default::default();
+        let target = Tensor::<TestBackend, 2>::from_floats([[10., 10., 4., 4.]; 2], &device);
+        let pred = Tensor::from_floats([[10., 10., 4., 4.], [11., 10., 4., 4.]], &device);
+        let (ious, _, _) = iou_cxcywh(pred, target);
+        let scores = Tensor::from_floats([0.7, 0.7], &device);
+
+        let metric = task_aligned_metric(scores, ious, 1., 6.)
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap();
+
+        assert!((metric[0] - 0.7).abs() < 1e-5);
+        assert!(metric[0] > metric[1] && metric[1] > 0.);
+    }
+
+    #[test]
+    fn loss_lower_for_perfect_prediction() {
+        let device = Default::default();
+        let target_boxes = Tensor::<TestBackend, 2>::from_floats([[10., 10., 4., 4.]], &device);
+        let target_classes = Tensor::from_floats([[0., 1.]], &device);
+        let logits = Tensor::from_floats([[-10., 10.]], &device);
+        let loss = |pred: [[f32; 4]; 1]| -> f32 {
+            task_align_loss(
+                logits.clone(),
+                Tensor::from_floats(pred, &device),
+                target_boxes.clone(),
+                target_classes.clone(),
+                1.,
+                6.,
+            )
+            .into_scalar()
+        };
+
+        let perfect = loss([[10., 10., 4., 4.]]);
+        let partial = loss([[12., 11., 4., 4.]]);
+
+        assert!(perfect < 1e-3, "unexpected loss {perfect}");
+        assert!(partial > perfect);
+    }
+}
@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+use burn::{
+    module::{Module, Param},
+    tensor::{backend::Backend, Device, ElementConversion, Tensor},
+};
+
+/// Multi-task loss weighted by the learned homoscedastic uncertainty of each task, as introduced
+/// in [Multi-Task Learning Using Uncertainty to Weigh Losses](https://arxiv.org/abs/1705.07115).
+///
+/// Each task `i` has a learnable log-variance `s_i` and the total loss is
+/// `sum_i (0.5 * exp(-s_i) * loss_i + 0.5 * s_i)`, so that the weights of the tasks are learned
+/// jointly with the model (the second term prevents the variances from growing indefinitely).
+/// The module should be optimized along with the model parameters.
+#[derive(Module, Debug)]
+pub struct UncertaintyWeightedLoss<B: Backend> {
+    log_sigma_sq: Param<Tensor<B, 1>>,
+}
+
+impl<B: Backend> UncertaintyWeightedLoss<B> {
+    /// Create a new uncertainty weighted loss, with all log-variances initialized to zero.
+    pub fn new(num_tasks: usize, device: &Device<B>) -> Self {
+        assert!(num_tasks > 0, "at least one task is required");
+
+        Self {
+            log_sigma_sq: Param::from_tensor(Tensor::zeros([num_tasks], device)),
+        }
+    }
+
+    /// Compute the weighted sum of the task losses.
+    ///
+    /// # Arguments
+    ///
+    /// * `losses`: Scalar loss of each task, in the same order as the log-variances. Each loss
+    ///   has shape `[1]`.
+    ///
+    /// # Returns
+    ///
+    /// The total loss with shape `[1]`.
+    pub fn forward(&self, losses: Vec<Tensor<B, 1>>) -> Tensor<B, 1> {
+        let [num_tasks] = self.log_sigma_sq.dims();
+        assert_eq!(losses.len(), num_tasks, "expected one loss per task");
+
+        let losses = Tensor::cat(losses, 0);
+        let log_sigma_sq = self.log_sigma_sq.val();
+
+        (log_sigma_sq.clone().neg().exp() * losses + log_sigma_sq)
+            .mul_scalar(0.5)
+            .sum()
+    }
+
+    /// Effective weight `0.5 * exp(-s_i)` of each task loss.
+    pub fn task_weights(&self) -> Vec<f32> {
+        self.log_sigma_sq
+            .val()
+            .neg()
+            .exp()
+            .mul_scalar(0.5)
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|w| w.elem::<f32>())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        tensor::TensorData,
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn uncertainty_loss_zero_log_variance() {
+        let device = Default::default();
+        let loss_fn = UncertaintyWeightedLoss::<TestBackend>::new(3, &device);
+        let losses = vec![
+            Tensor::from_floats([2.0], &device),
+            Tensor::from_floats([0.5], &device),
+            Tensor::from_floats([1.5], &device),
+        ];
+
+        // 0.5 * (2.0 + 0.5 + 1.5)
+        loss_fn
+            .forward(losses)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([2.0]), 5);
+        assert_eq!(loss_fn.task_weights(), vec![0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn uncertainty_loss_gradient() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let loss_fn = UncertaintyWeightedLoss::<TrainingBackend>::new(2, &device);
+        let losses = vec![
+            Tensor::from_floats([3.0], &device),
+            Tensor::from_floats([1.0], &device),
+        ];
+
+        let grads = loss_fn.forward(losses).backward();
+        let grad = loss_fn.log_sigma_sq.grad(&grads).unwrap();
+
+        // d/ds 0.5 * (exp(-s) * loss + s) = 0.5 * (1 - loss) at s = 0
+        grad.into_data()
+            .assert_approx_eq(&TensorData::from([-1.0, 0.0]), 5);
+    }
+
+    #[test]
+    #[should_panic = "expected one loss per task"]
+    fn uncertainty_loss_wrong_number_of_losses() {
+        let device = Default::default();
+        let loss_fn = UncertaintyWeightedLoss::<TestBackend>::new(2, &device);
+
+        loss_fn.forward(vec![Tensor::from_floats([1.0], &device)]);
+    }
+}
@@ -0,0 +1,148 @@
+use alloc::vec::Vec;
+use burn::tensor::{backend::Backend, Int, Tensor};
+
+use super::{BceLoss, IouLoss, SimOtaAssigner};
+
+/// Default weight of the box regression loss.
+const REG_WEIGHT: f32 = 5.;
+
+/// Ground truth boxes of an image.
+#[derive(Debug, Clone)]
+pub struct GroundTruthBoxes<B: Backend> {
+    /// Boxes `(cx, cy, w, h)` in pixels. Shape: `[num_boxes, 4]`.
+    pub boxes: Tensor<B, 2>,
+    /// Class index of each box. Shape: `[num_boxes]`.
+    pub labels: Tensor<B, 1, Int>,
+}
+
+/// Terms of the [YOLOX loss](YoloxLoss).
+pub struct YoloxLossOutput<B: Backend> {
+    /// Total loss. Shape: `[1]`.
+    pub loss: Tensor<B, 1>,
+    /// IoU loss of the foreground boxes, before weighting. Shape: `[1]`.
+    pub iou_loss: Tensor<B, 1>,
+    /// Objectness loss of all the predictions. Shape: `[1]`.
+    pub obj_loss: Tensor<B, 1>,
+    /// Classification loss of the foreground predictions. Shape: `[1]`.
+    pub cls_loss: Tensor<B, 1>,
+    /// Number of foreground predictions of the batch.
+    pub num_foreground: usize,
+}
+
+/// Training loss of [YOLOX](https://arxiv.org/abs/2107.08430).
+///
+/// The predictions are assigned to the ground truth boxes by [SimOTA](SimOtaAssigner). The loss
+/// sums an [IoU loss](IouLoss) of the foreground boxes (weighted by 5), a
+/// [binary cross-entropy](BceLoss) of the objectness of all the predictions and a binary
+/// cross-entropy of the foreground class scores, with IoU-aware targets. The sum is normalized by
+/// the number of foreground predictions.
+#[derive(Debug, Clone)]
+pub struct YoloxLoss {
+    assigner: SimOtaAssigner,
+    iou: IouLoss,
+    bce: BceLoss,
+    reg_weight: f32,
+}
+
+impl Default for YoloxLoss {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl YoloxLoss {
+    /// Create a new YOLOX loss with the default [SimOTA assigner](SimOtaAssigner).
+    pub fn new() -> Self {
+        Self {
+            assigner: SimOtaAssigner::new(),
+            iou: IouLoss::default(),
+            bce: BceLoss::new(),
+            reg_weight: REG_WEIGHT,
+        }
+    }
+
+    /// Set the label assigner.
+    pub fn with_assigner(mut self, assigner: SimOtaAssigner) -> Self {
+        self.assigner = assigner;
+        self
+    }
+
+    /// Set the weight of the box regression loss (defaults to 5).
+    pub fn with_reg_weight(mut self, reg_weight: f32) -> Self {
+        self.reg_weight = reg_weight;
+        self
+    }
+
+    /// Compute the loss of a batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs`: Decoded `(cx, cy, w, h)` boxes, followed by the objectness and class
+    ///   probabilities. Shape: `[batch_size, num_anchors, 5 + num_classes]`.
+    /// * `anchors` - Anchor points `(x, y)` and stride of each prediction. Shape:
+    ///   `[num_anchors, 3]`.
+    /// * `targets` - Ground truth boxes of each image.
+    pub fn forward<B: Backend>(
+        &self,
+        outputs: Tensor<B, 3>,
+        anchors: Tensor<B, 2>,
+        targets: &[GroundTruthBoxes<B>],
+    ) -> YoloxLossOutput<B> {
+        let [batch_size, num_anchors, num_outputs] = outputs.dims();
+        assert_eq!(batch_size, targets.len(), "expected targets for each image");
+
+        let mut iou_losses = Vec::with_capacity(batch_size);
+        let mut obj_losses = Vec::with_capacity(batch_size);
+        let mut cls_losses = Vec::with_capacity(batch_size);
+        let mut num_foreground = 0;
+        for (out, target) in outputs.iter_dim(0).zip(targets) {
+            let out: Tensor<B, 2> = out.squeeze(0);
+            let pred_boxes = out.clone().slice([0..num_anchors, 0..4]);
+            let obj_scores = out.clone().slice([0..num_anchors, 4..5]);
+            let cls_scores = out.slice([0..num_anchors, 5..num_outputs]);
+
+            let assigned = self.assigner.assign(
+                pred_boxes.clone(),
+                cls_scores.clone() * obj_scores.clone(),
+                anchors.clone(),
+                target.boxes.clone(),
+                target.labels.clone(),
+            );
+            let foreground = assigned.obj_targets.clone();
+            num_foreground += assigned.num_foreground;
+
+            obj_losses.push(
+                self.bce
+                    .forward(obj_scores.reshape([num_anchors]), assigned.obj_targets)
+                    .sum(),
+            );
+            iou_losses.push(
+                (self.iou.forward(pred_boxes, assigned.box_targets) * foreground.clone()).sum(),
+            );
+            cls_losses.push(
+                (self
+                    .bce
+                    .forward(cls_scores, assigned.cls_targets)
+                    .sum_dim(1)
+                    .reshape([num_anchors])
+                    * foreground)
+                    .sum(),
+            );
+        }
+
+        let normalizer = num_foreground.max(1) as f32;
+        let iou_loss = Tensor::cat(iou_losses, 0).sum().div_scalar(normalizer);
+        let obj_loss = Tensor::cat(obj_losses, 0).sum().div_scalar(normalizer);
+        let cls_loss = Tensor::cat(cls_losses, 0).sum().div_scalar(normalizer);
+
+        YoloxLossOutput {
+            loss: iou_loss.clone().mul_scalar(self.reg_weight)
+                + obj_loss.clone()
+                + cls_loss.clone(),
+            iou_loss,
+            obj_loss,
+            cls_loss,
+            num_foreground,
+        }
+    }
+}
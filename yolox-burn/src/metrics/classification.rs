@@ -0,0 +1,77 @@
+use burn::tensor::{backend::Backend, ElementConversion, Int, Tensor};
+
+/// Top-k classification accuracy: the fraction of the samples whose label is among the `k`
+/// highest scoring classes.
+pub struct TopKAccuracy;
+
+impl TopKAccuracy {
+    /// Compute the top-k accuracy of a batch of predictions.
+    ///
+    /// # Arguments
+    ///
+    /// * `logits`: Class scores (or logits). Shape: `[batch_size, num_classes]`.
+    /// * `labels` - Ground-truth class indices. Shape: `[batch_size]`.
+    /// * `k` - Number of highest scoring classes considered for each sample.
+    pub fn compute<B: Backend>(logits: Tensor<B, 2>, labels: Tensor<B, 1, Int>, k: usize) -> f32 {
+        let [batch_size, num_classes] = logits.dims();
+        assert!(
+            k > 0 && k <= num_classes,
+            "k should be in range [1, {num_classes}]"
+        );
+        if batch_size == 0 {
+            return 0.;
+        }
+
+        // [batch_size, k]
+        let (_, top_classes) = logits.topk_with_indices(k, 1);
+        let labels = labels.reshape([batch_size, 1]).repeat_dim(1, k);
+        let correct = top_classes
+            .equal(labels)
+            .int()
+            .sum()
+            .into_scalar()
+            .elem::<f32>();
+
+        correct / batch_size as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn logits() -> Tensor<TestBackend, 2> {
+        Tensor::from_floats(
+            [[0.1, 0.7, 0.2], [0.8, 0.15, 0.05], [0.3, 0.3, 0.4]],
+            &Default::default(),
+        )
+    }
+
+    #[test]
+    fn top1_accuracy_perfect_predictions() {
+        let labels = Tensor::from_ints([1, 0, 2], &Default::default());
+
+        assert_eq!(TopKAccuracy::compute(logits(), labels, 1), 1.);
+    }
+
+    #[test]
+    fn topk_accuracy() {
+        // Second highest class of the first sample, lowest class of the second one
+        let labels = Tensor::from_ints([2, 2, 2], &Default::default());
+
+        assert!((TopKAccuracy::compute(logits(), labels.clone(), 1) - 1. / 3.).abs() < 1e-6);
+        assert!((TopKAccuracy::compute(logits(), labels.clone(), 2) - 2. / 3.).abs() < 1e-6);
+        assert_eq!(TopKAccuracy::compute(logits(), labels, 3), 1.);
+    }
+
+    #[test]
+    #[should_panic = "k should be in range [1, 3]"]
+    fn topk_accuracy_invalid_k() {
+        let labels = Tensor::from_ints([1, 0, 2], &Default::default());
+
+        TopKAccuracy::compute(logits(), labels, 4);
+    }
+}
@@ -0,0 +1,165 @@
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::types::{Detection, GroundTruth, IoUThreshold};
+
+/// IoU thresholds `[0.5:0.05:0.95]` used by the COCO evaluation.
+pub const COCO_IOU_THRESHOLDS: [f32; 10] = [0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8, 0.85, 0.9, 0.95];
+
+/// Number of recall points used to interpolate the precision-recall curve.
+const RECALL_POINTS: usize = 101;
+
+/// Intersection over union of two `[xmin, ymin, xmax, ymax]` boxes.
+pub fn box_iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let w = (a[2].min(b[2]) - a[0].max(b[0])).max(0.);
+    let h = (a[3].min(b[3]) - a[1].max(b[1])).max(0.);
+    let intersection = w * h;
+    let area_a = (a[2] - a[0]).max(0.) * (a[3] - a[1]).max(0.);
+    let area_b = (b[2] - b[0]).max(0.) * (b[3] - b[1]).max(0.);
+    let union = area_a + area_b - intersection;
+
+    if union > 0. {
+        intersection / union
+    } else {
+        0.
+    }
+}
+
+/// Average precision of a single class at the given IoU threshold, using the COCO 101-point
+/// interpolation.
+///
+/// Returns `None` when there is no (non-crowd) ground-truth object for the class.
+fn average_precision(
+    detections: &[&Detection],
+    ground_truths: &[&GroundTruth],
+    iou_threshold: IoUThreshold,
+) -> Option<f32> {
+    let num_positives = ground_truths.iter().filter(|gt| !gt.is_crowd).count();
+    if num_positives == 0 {
+        return None;
+    }
+
+    let mut detections = detections.to_vec();
+    detections.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+
+    let mut matched = vec![false; ground_truths.len()];
+    let mut tp = Vec::with_capacity(detections.len());
+    for det in detections {
+        // Best matching ground-truth in the same image, non-crowd objects first
+        let mut best: Option<(usize, f32)> = None;
+        for (i, gt) in ground_truths.iter().enumerate() {
+            if gt.image_id != det.image_id || (matched[i] && !gt.is_crowd) {
+                continue;
+            }
+            let iou = box_iou(&det.box_xyxy, &gt.box_xyxy);
+            if iou < iou_threshold.value() {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((j, best_iou)) => {
+                    (ground_truths[j].is_crowd && !gt.is_crowd)
+                        || (ground_truths[j].is_crowd == gt.is_crowd && iou > best_iou)
+                }
+            };
+            if better {
+                best = Some((i, iou));
+            }
+        }
+
+        match best {
+            // Detections matched to crowd regions are ignored
+            Some((i, _)) if ground_truths[i].is_crowd => {}
+            Some((i, _)) => {
+                matched[i] = true;
+                tp.push(true);
+            }
+            None => tp.push(false),
+        }
+    }
+
+    Some(interpolated_average_precision(&tp, num_positives))
+}
+
+/// Area under the precision-recall curve of the detections sorted by decreasing score, using the
+/// COCO 101-point interpolation.
+///
+/// # Arguments
+///
+/// * `tp`: Whether each detection is a true positive.
+/// * `num_positives` - Number of ground-truth objects.
+pub(super) fn interpolated_average_precision(tp: &[bool], num_positives: usize) -> f32 {
+    let mut precision = Vec::with_capacity(tp.len());
+    let mut recall = Vec::with_capacity(tp.len());
+    let mut tp_sum = 0;
+    for (i, is_tp) in tp.iter().enumerate() {
+        tp_sum += *is_tp as usize;
+        precision.push(tp_sum as f32 / (i + 1) as f32);
+        recall.push(tp_sum as f32 / num_positives as f32);
+    }
+
+    // Make precision monotonically decreasing
+    for i in (1..precision.len()).rev() {
+        precision[i - 1] = precision[i - 1].max(precision[i]);
+    }
+
+    (0..RECALL_POINTS)
+        .map(|i| {
+            let r = i as f32 / (RECALL_POINTS - 1) as f32;
+            let idx = recall.partition_point(|&v| v < r);
+            precision.get(idx).copied().unwrap_or(0.)
+        })
+        .sum::<f32>()
+        / RECALL_POINTS as f32
+}
+
+/// Mean average precision over all classes at the given IoU threshold (e.g., mAP@0.5).
+///
+/// Classes without ground-truth objects are ignored.
+///
+/// # Arguments
+///
+/// * `detections`: Detections for all images of the dataset.
+/// * `ground_truths` - Ground-truth objects for all images of the dataset.
+/// * `iou_threshold` - Minimum IoU for a detection to be matched with a ground-truth object.
+pub fn mean_average_precision(
+    detections: &[Detection],
+    ground_truths: &[GroundTruth],
+    iou_threshold: IoUThreshold,
+) -> f32 {
+    let num_classes = detections
+        .iter()
+        .map(|d| d.class_id + 1)
+        .chain(ground_truths.iter().map(|gt| gt.class_id + 1))
+        .max()
+        .unwrap_or(0);
+
+    let aps: Vec<f32> = (0..num_classes)
+        .filter_map(|class_id| {
+            let dets: Vec<_> = detections
+                .iter()
+                .filter(|d| d.class_id == class_id)
+                .collect();
+            let gts: Vec<_> = ground_truths
+                .iter()
+                .filter(|gt| gt.class_id == class_id)
+                .collect();
+            average_precision(&dets, &gts, iou_threshold)
+        })
+        .collect();
+
+    if aps.is_empty() {
+        0.
+    } else {
+        aps.iter().sum::<f32>() / aps.len() as f32
+    }
+}
+
+/// COCO mAP, averaged over the [IoU thresholds](COCO_IOU_THRESHOLDS) `[0.5:0.05:0.95]`.
+pub fn coco_map(detections: &[Detection], ground_truths: &[GroundTruth]) -> f32 {
+    COCO_IOU_THRESHOLDS
+        .iter()
+        .map(|&iou| mean_average_precision(detections, ground_truths, iou.into()))
+        .sum::<f32>()
+        / COCO_IOU_THRESHOLDS.len() as f32
+}
@@ -0,0 +1,7 @@
+mod classification;
+mod map;
+mod oks;
+
+pub use classification::*;
+pub use map::*;
+pub use oks::*;
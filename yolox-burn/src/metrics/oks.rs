@@ -0,0 +1,341 @@
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+use burn::tensor::{backend::Backend, Tensor};
+
+use super::{map::interpolated_average_precision, COCO_IOU_THRESHOLDS};
+use crate::types::IoUThreshold;
+
+/// Per-keypoint standard deviations of the COCO keypoints (nose, eyes, ears, shoulders, elbows,
+/// wrists, hips, knees and ankles), relative to the object scale.
+pub const COCO_KEYPOINT_SIGMAS: [f32; 17] = [
+    0.026, 0.025, 0.025, 0.035, 0.035, 0.079, 0.079, 0.072, 0.072, 0.062, 0.062, 0.107, 0.107,
+    0.087, 0.087, 0.089, 0.089,
+];
+
+/// Object keypoint similarity (OKS) between each pair of predicted and ground-truth skeletons.
+///
+/// The similarity of each visible keypoint is `exp(-d^2 / (2 * s^2 * k^2))`, with `d` the distance
+/// between the predicted and ground-truth keypoints, `s^2` the object area and `k = 2 * sigma` as
+/// in the COCO evaluation. The OKS is the mean similarity over the visible keypoints of the
+/// ground-truth (0 without visible keypoint).
+///
+/// # Arguments
+///
+/// * `pred_keypoints`: Predicted keypoints `(x, y)`. Shape: `[num_preds, num_keypoints, 2]`.
+/// * `gt_keypoints` - Ground-truth keypoints `(x, y)`. Shape: `[num_gts, num_keypoints, 2]`.
+/// * `gt_visibility` - Visibility of the ground-truth keypoints (1 for visible, 0 otherwise).
+///   Shape: `[num_gts, num_keypoints]`.
+/// * `area` - Area of the ground-truth objects. Shape: `[num_gts]`.
+/// * `sigmas` - Standard deviation of each keypoint (e.g., [COCO_KEYPOINT_SIGMAS]).
+///
+/// # Returns
+///
+/// The OKS with shape `[num_preds, num_gts]`.
+pub fn oks<B: Backend>(
+    pred_keypoints: Tensor<B, 3>,
+    gt_keypoints: Tensor<B, 3>,
+    gt_visibility: Tensor<B, 2>,
+    area: Tensor<B, 1>,
+    sigmas: &[f32],
+) -> Tensor<B, 2> {
+    let [n, k, _] = pred_keypoints.dims();
+    let [m, _, _] = gt_keypoints.dims();
+    assert_eq!(sigmas.len(), k, "expected one sigma per keypoint");
+    let device = pred_keypoints.device();
+
+    // Squared distances [N, M, K]
+    let d2 = (pred_keypoints.unsqueeze_dim::<4>(1) - gt_keypoints.unsqueeze_dim::<4>(0))
+        .powf_scalar(2.)
+        .sum_dim(3)
+        .reshape([n, m, k]);
+
+    // 2 * s^2 * k^2 [1, M, K]
+    let kappa2: Vec<f32> = sigmas.iter().map(|s| (2. * s).powi(2)).collect();
+    let kappa2 = Tensor::<B, 1>::from_floats(kappa2.as_slice(), &device).reshape([1, 1, k]);
+    let denom = area.reshape([1, m, 1]).mul_scalar(2.) * kappa2;
+
+    let visibility = gt_visibility.greater_elem(0.).float().reshape([1, m, k]);
+    let num_visible = visibility.clone().sum_dim(2).clamp_min(1.);
+    let similarity = (d2.neg() / denom).exp() * visibility;
+
+    (similarity.sum_dim(2) / num_visible).reshape([n, m])
+}
+
+/// Predicted skeleton of a person.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PosePrediction {
+    /// Index of the image in the dataset.
+    pub image_id: usize,
+    /// Keypoints `(x, y)` in image coordinates.
+    pub keypoints: Vec<[f32; 2]>,
+    /// Confidence score.
+    pub score: f32,
+}
+
+/// Ground-truth skeleton of a person.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseGroundTruth {
+    /// Index of the image in the dataset.
+    pub image_id: usize,
+    /// Keypoints `(x, y)` in image coordinates.
+    pub keypoints: Vec<[f32; 2]>,
+    /// Whether each keypoint is labeled and visible.
+    pub visible: Vec<bool>,
+    /// Object area (the squared scale of the OKS).
+    pub area: f32,
+}
+
+/// Pose estimation metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseMetrics {
+    /// Average precision at each of the requested OKS thresholds.
+    pub ap: Vec<f32>,
+    /// Average precision averaged over the COCO OKS thresholds `[0.5:0.05:0.95]`.
+    pub map: f32,
+}
+
+/// OKS of a predicted and a ground-truth skeleton (see [oks]).
+fn keypoint_similarity(pred: &PosePrediction, gt: &PoseGroundTruth, sigmas: &[f32]) -> f32 {
+    let (sum, num_visible) = pred
+        .keypoints
+        .iter()
+        .zip(&gt.keypoints)
+        .zip(&gt.visible)
+        .zip(sigmas)
+        .filter(|(((_, _), &visible), _)| visible)
+        .fold((0., 0), |(sum, count), (((p, g), _), sigma)| {
+            let d2 = (p[0] - g[0]).powi(2) + (p[1] - g[1]).powi(2);
+            let kappa2 = (2. * sigma).powi(2);
+            (sum + (-d2 / (2. * gt.area * kappa2)).exp(), count + 1)
+        });
+
+    if num_visible == 0 {
+        0.
+    } else {
+        sum / num_visible as f32
+    }
+}
+
+/// Average precision of the predicted skeletons at the given OKS threshold.
+///
+/// The ground truths without visible keypoints are ignored.
+fn pose_average_precision(
+    predictions: &[&PosePrediction],
+    ground_truths: &[&PoseGroundTruth],
+    sigmas: &[f32],
+    oks_threshold: IoUThreshold,
+) -> f32 {
+    let num_positives = ground_truths.len();
+    if num_positives == 0 {
+        return 0.;
+    }
+
+    let mut matched = vec![false; num_positives];
+    let tp: Vec<bool> = predictions
+        .iter()
+        .map(|pred| {
+            // Best unmatched ground-truth in the same image
+            let best = ground_truths
+                .iter()
+                .enumerate()
+                .filter(|(i, gt)| gt.image_id == pred.image_id && !matched[*i])
+                .map(|(i, gt)| (i, keypoint_similarity(pred, gt, sigmas)))
+                .filter(|&(_, similarity)| similarity >= oks_threshold.value())
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+            match best {
+                Some((i, _)) => {
+                    matched[i] = true;
+                    true
+                }
+                None => false,
+            }
+        })
+        .collect();
+
+    interpolated_average_precision(&tp, num_positives)
+}
+
+/// OKS-based average precision of the predicted skeletons, as in the COCO keypoint evaluation,
+/// with the [COCO keypoint sigmas](COCO_KEYPOINT_SIGMAS).
+///
+/// # Arguments
+///
+/// * `predictions`: Predicted skeletons for all images of the dataset.
+/// * `ground_truths` - Ground-truth skeletons for all images of the dataset, with 17 keypoints.
+/// * `oks_thresholds` - OKS thresholds of the reported average precisions (e.g., `[0.5, 0.75]`),
+///   which play the role of the IoU thresholds of the box evaluation.
+pub fn pose_map(
+    predictions: Vec<PosePrediction>,
+    ground_truths: Vec<PoseGroundTruth>,
+    oks_thresholds: &[IoUThreshold],
+) -> PoseMetrics {
+    let sigmas = &COCO_KEYPOINT_SIGMAS;
+    assert!(
+        ground_truths
+            .iter()
+            .all(|gt| gt.keypoints.len() == sigmas.len() && gt.visible.len() == sigmas.len()),
+        "expected {} keypoints per ground-truth skeleton",
+        sigmas.len()
+    );
+
+    let mut predictions: Vec<_> = predictions.iter().collect();
+    predictions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    let ground_truths: Vec<_> = ground_truths
+        .iter()
+        .filter(|gt| gt.visible.iter().any(|&v| v))
+        .collect();
+
+    let ap = |threshold| pose_average_precision(&predictions, &ground_truths, sigmas, threshold);
+
+    PoseMetrics {
+        ap: oks_thresholds.iter().map(|&t| ap(t)).collect(),
+        map: COCO_IOU_THRESHOLDS
+            .iter()
+            .map(|&t| ap(t.into()))
+            .sum::<f32>()
+            / COCO_IOU_THRESHOLDS.len() as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Skeleton of 17 keypoints on a diagonal, shifted by `offset`.
+    fn skeleton(offset: f32) -> Vec<[f32; 2]> {
+        (0..17)
+            .map(|i| [offset + 4. * i as f32, offset + 2. * i as f32])
+            .collect()
+    }
+
+    fn keypoints(skeletons: &[Vec<[f32; 2]>]) -> Tensor<TestBackend, 3> {
+        let values: Vec<f32> = skeletons.iter().flatten().flatten().copied().collect();
+        Tensor::<TestBackend, 1>::from_floats(values.as_slice(), &Default::default()).reshape([
+            skeletons.len(),
+            17,
+            2,
+        ])
+    }
+
+    fn ground_truth(image_id: usize, offset: f32) -> PoseGroundTruth {
+        PoseGroundTruth {
+            image_id,
+            keypoints: skeleton(offset),
+            visible: vec![true; 17],
+            area: 64. * 32.,
+        }
+    }
+
+    fn prediction(image_id: usize, offset: f32, score: f32) -> PosePrediction {
+        PosePrediction {
+            image_id,
+            keypoints: skeleton(offset),
+            score,
+        }
+    }
+
+    #[test]
+    fn oks_perfect_and_distant_skeletons() {
+        let device = Default::default();
+        let preds = keypoints(&[skeleton(0.), skeleton(500.)]);
+        let gts = keypoints(&[skeleton(0.)]);
+
+        let similarity = oks(
+            preds,
+            gts,
+            Tensor::ones([1, 17], &device),
+            Tensor::from_floats([64. * 32.], &device),
+            &COCO_KEYPOINT_SIGMAS,
+        );
+
+        similarity
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32], [0.]]), 5);
+    }
+
+    #[test]
+    fn oks_visible_keypoints_only() {
+        let device = Default::default();
+        let mut pred = skeleton(0.);
+        pred[0] = [1000., 1000.];
+        let mut visibility = [1f32; 17];
+        visibility[0] = 0.;
+
+        let similarity = oks(
+            keypoints(&[pred.clone()]),
+            keypoints(&[skeleton(0.)]),
+            Tensor::from_floats([visibility], &device),
+            Tensor::from_floats([64. * 32.], &device),
+            &COCO_KEYPOINT_SIGMAS,
+        );
+        similarity
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32]]), 5);
+
+        // Same similarity on the host
+        let mut gt = ground_truth(0, 0.);
+        gt.visible[0] = false;
+        let pred = PosePrediction {
+            image_id: 0,
+            keypoints: pred,
+            score: 1.,
+        };
+        assert!((keypoint_similarity(&pred, &gt, &COCO_KEYPOINT_SIGMAS) - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn oks_known_value() {
+        let device = Default::default();
+        // A single keypoint at distance 1 with s^2 = 1 and k = 0.5: exp(-1 / 0.5)
+        let similarity = oks(
+            Tensor::<TestBackend, 3>::from_floats([[[1., 0.]]], &device),
+            Tensor::from_floats([[[0., 0.]]], &device),
+            Tensor::ones([1, 1], &device),
+            Tensor::ones([1], &device),
+            &[0.25],
+        );
+
+        similarity
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[(-2f32).exp()]]), 5);
+    }
+
+    #[test]
+    fn pose_map_perfect_predictions() {
+        let metrics = pose_map(
+            vec![prediction(0, 0., 0.9), prediction(1, 10., 0.8)],
+            vec![ground_truth(0, 0.), ground_truth(1, 10.)],
+            &[0.5, 0.75].map(IoUThreshold::from),
+        );
+
+        assert_eq!(metrics.ap, [1., 1.]);
+        assert!((metrics.map - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pose_map_distant_predictions() {
+        let metrics = pose_map(
+            vec![prediction(0, 500., 0.9), prediction(1, 10., 0.8)],
+            vec![ground_truth(0, 0.), ground_truth(1, 10.)],
+            &[IoUThreshold::default()],
+        );
+
+        // Only the second skeleton is detected, after a false positive: precision of 0.5 up to
+        // a recall of 0.5, over the 101 recall points
+        assert!((metrics.ap[0] - 51. * 0.5 / 101.).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic = "expected 17 keypoints per ground-truth skeleton"]
+    fn pose_map_wrong_keypoint_count() {
+        let mut gt = ground_truth(0, 0.);
+        gt.keypoints.pop();
+
+        pose_map(vec![], vec![gt], &[IoUThreshold::default()]);
+    }
+}
@@ -0,0 +1,1041 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        Dropout, DropoutConfig, Linear, LinearConfig, PaddingConfig2d, Relu,
+    },
+    tensor::{
+        activation::softmax, backend::Backend, Device, Distribution, Int, Tensor, TensorData,
+    },
+};
+
+use super::{
+    blocks::{ActivationType, BaseConv, BaseConvConfig},
+    heads::l2_normalize,
+};
+
+/// Value added to the attention logits of masked positions.
+const MASK_VALUE: f32 = -100.;
+
+/// Partition a feature map into non-overlapping windows for the
+/// [Swin Transformer](https://arxiv.org/abs/2103.14030) local attention.
+///
+/// # Shapes
+///   - x: `[batch_size, height, width, channels]`
+///   - output: `[batch_size, num_windows, window_size, window_size, channels]`
+///
+/// # Panics
+///
+/// If the height or width is not divisible by the window size.
+pub fn window_partition<B: Backend>(x: Tensor<B, 4>, window_size: usize) -> Tensor<B, 5> {
+    let [b, h, w, c] = x.dims();
+    assert!(
+        h % window_size == 0 && w % window_size == 0,
+        "feature map size ({h}, {w}) should be divisible by the window size {window_size}"
+    );
+    let (nh, nw) = (h / window_size, w / window_size);
+
+    x.reshape([b, nh, window_size, nw, window_size, c])
+        .permute([0, 1, 3, 2, 4, 5])
+        .reshape([b, nh * nw, window_size, window_size, c])
+}
+
+/// Merge the windows back into the feature map. Inverse of [window_partition].
+///
+/// # Shapes
+///   - windows: `[batch_size, num_windows, window_size, window_size, channels]`
+///   - output: `[batch_size, height, width, channels]`
+pub fn window_reverse<B: Backend>(
+    windows: Tensor<B, 5>,
+    window_size: usize,
+    height: usize,
+    width: usize,
+) -> Tensor<B, 4> {
+    let [b, _, _, _, c] = windows.dims();
+    let (nh, nw) = (height / window_size, width / window_size);
+
+    windows
+        .reshape([b, nh, nw, window_size, window_size, c])
+        .permute([0, 1, 3, 2, 4, 5])
+        .reshape([b, height, width, c])
+}
+
+/// Compute the attention mask of the shifted window self-attention.
+///
+/// After the cyclic shift, a window can contain tokens from non-adjacent regions of the feature
+/// map. Attention between tokens of different regions is masked.
+///
+/// # Returns
+///
+/// The mask to add to the attention logits, with `0` for allowed positions and a large negative
+/// value for masked positions. Shape: `[num_windows, window_size^2, window_size^2]`.
+pub fn compute_attention_mask<B: Backend>(
+    height: usize,
+    width: usize,
+    window_size: usize,
+    shift_size: usize,
+    device: &Device<B>,
+) -> Tensor<B, 3> {
+    // Region label of each position
+    let region = |pos: usize, size: usize| {
+        if pos < size - window_size {
+            0
+        } else if pos < size - shift_size {
+            1
+        } else {
+            2
+        }
+    };
+
+    let (nh, nw) = (height / window_size, width / window_size);
+    let area = window_size * window_size;
+    let mut mask = Vec::with_capacity(nh * nw * area * area);
+    for wy in 0..nh {
+        for wx in 0..nw {
+            let labels: Vec<usize> = (0..area)
+                .map(|i| {
+                    let y = wy * window_size + i / window_size;
+                    let x = wx * window_size + i % window_size;
+                    region(y, height) * 3 + region(x, width)
+                })
+                .collect();
+
+            for i in &labels {
+                mask.extend(labels.iter().map(|j| if i == j { 0. } else { MASK_VALUE }));
+            }
+        }
+    }
+
+    Tensor::from_data(
+        TensorData::new(mask, [nh * nw, area, area]).convert::<B::FloatElem>(),
+        device,
+    )
+}
+
+/// Learnable relative position bias of the window self-attention.
+///
+/// Each pair of positions within a window is mapped to a bias per attention head, shared by all
+/// the pairs with the same relative offset.
+#[derive(Module, Debug)]
+pub struct RelativePositionBias<B: Backend> {
+    /// Bias table. Shape: `[(2 * window_size - 1)^2, num_heads]`.
+    table: Param<Tensor<B, 2>>,
+    /// Fixed table index of each pair of positions. Shape: `[window_size^4]`.
+    index: Tensor<B, 1, Int>,
+    window_size: usize,
+}
+
+impl<B: Backend> RelativePositionBias<B> {
+    /// Compute the bias to add to the attention logits.
+    ///
+    /// # Shapes
+    ///   - output: `[num_heads, window_size^2, window_size^2]`
+    pub fn forward(&self) -> Tensor<B, 3> {
+        let [_, num_heads] = self.table.dims();
+        let area = self.window_size * self.window_size;
+
+        self.table
+            .val()
+            .select(0, self.index.clone())
+            .reshape([area, area, num_heads])
+            .permute([2, 0, 1])
+    }
+}
+
+/// [Relative position bias](RelativePositionBias) configuration.
+pub struct RelativePositionBiasConfig {
+    window_size: usize,
+    num_heads: usize,
+}
+
+impl RelativePositionBiasConfig {
+    /// Create a new instance of the relative position bias [config](RelativePositionBiasConfig).
+    pub fn new(window_size: usize, num_heads: usize) -> Self {
+        Self {
+            window_size,
+            num_heads,
+        }
+    }
+
+    /// Initialize a new [relative position bias](RelativePositionBias) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> RelativePositionBias<B> {
+        let ws = self.window_size;
+        let num_offsets = 2 * ws - 1;
+        let area = ws * ws;
+
+        // Offsets are shifted to start at 0
+        let mut index = Vec::with_capacity(area * area);
+        for i in 0..area {
+            for j in 0..area {
+                let dy = (i / ws) + ws - 1 - (j / ws);
+                let dx = (i % ws) + ws - 1 - (j % ws);
+                index.push((dy * num_offsets + dx) as i64);
+            }
+        }
+        let index = Tensor::from_data(
+            TensorData::new(index, [area * area]).convert::<B::IntElem>(),
+            device,
+        );
+
+        let table = Tensor::random(
+            [num_offsets * num_offsets, self.num_heads],
+            Distribution::Normal(0., 0.02),
+            device,
+        );
+
+        RelativePositionBias {
+            table: Param::from_tensor(table),
+            index,
+            window_size: ws,
+        }
+    }
+}
+
+/// Multi-head self-attention along a single axis with a learnable 1D relative position bias, used
+/// by the [axial attention](AxialAttention).
+#[derive(Module, Debug)]
+pub struct AxialAttention1d<B: Backend> {
+    qkv: Linear<B>,
+    output: Linear<B>,
+    /// Bias of each relative offset in `-(kernel_size - 1)..kernel_size`, for each head.
+    /// Shape: `[2 * kernel_size - 1, num_heads]`.
+    bias_table: Param<Tensor<B, 2>>,
+    num_heads: usize,
+    kernel_size: usize,
+}
+
+impl<B: Backend> AxialAttention1d<B> {
+    /// Apply the self-attention to each sequence.
+    ///
+    /// # Shapes
+    ///   - x: `[num_sequences, length, channels]`
+    ///   - output: `[num_sequences, length, channels]`
+    ///
+    /// # Panics
+    ///
+    /// If the sequence length is larger than the kernel size.
+    pub fn forward(&self, x: Tensor<B, 3>) -> Tensor<B, 3> {
+        let [m, l, c] = x.dims();
+        let h = self.num_heads;
+        let split_heads = |x: Tensor<B, 3>| x.reshape([m, l, h, c / h]).swap_dims(1, 2);
+
+        let qkv = self.qkv.forward(x);
+        let query = split_heads(qkv.clone().slice([0..m, 0..l, 0..c]));
+        let key = split_heads(qkv.clone().slice([0..m, 0..l, c..2 * c]));
+        let value = split_heads(qkv.slice([0..m, 0..l, 2 * c..3 * c]));
+
+        let logits = query
+            .matmul(key.swap_dims(2, 3))
+            .div_scalar(((c / h) as f32).sqrt());
+        let weights = softmax(logits + self.relative_bias(l).unsqueeze::<4>(), 3);
+
+        let x = weights.matmul(value).swap_dims(1, 2).reshape([m, l, c]);
+        self.output.forward(x)
+    }
+
+    /// Relative position bias of each pair of positions of a sequence.
+    ///
+    /// # Shapes
+    ///   - output: `[num_heads, length, length]`
+    fn relative_bias(&self, length: usize) -> Tensor<B, 3> {
+        assert!(
+            length <= self.kernel_size,
+            "sequence length {length} exceeds the attention span {}",
+            self.kernel_size
+        );
+        let device = self.bias_table.device();
+        let positions = Tensor::<B, 1, Int>::arange(0..length as i64, &device);
+
+        // Offsets are shifted to start at 0
+        let index = positions.clone().reshape([length, 1]).repeat_dim(1, length)
+            - positions.reshape([1, length]).repeat_dim(0, length);
+        let index = index.add_scalar(self.kernel_size as i64 - 1);
+
+        self.bias_table
+            .val()
+            .select(0, index.reshape([length * length]))
+            .reshape([length, length, self.num_heads])
+            .permute([2, 0, 1])
+    }
+}
+
+/// [Axial attention](AxialAttention1d) configuration.
+pub struct AxialAttention1dConfig {
+    in_channels: usize,
+    num_heads: usize,
+    kernel_size: usize,
+}
+
+impl AxialAttention1dConfig {
+    /// Create a new instance of the axial attention [config](AxialAttention1dConfig).
+    pub fn new(in_channels: usize, num_heads: usize, kernel_size: usize) -> Self {
+        assert!(
+            in_channels % num_heads == 0,
+            "the number of channels should be divisible by the number of heads"
+        );
+
+        Self {
+            in_channels,
+            num_heads,
+            kernel_size,
+        }
+    }
+
+    /// Initialize a new [axial attention](AxialAttention1d) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> AxialAttention1d<B> {
+        let c = self.in_channels;
+        let bias_table = Tensor::random(
+            [2 * self.kernel_size - 1, self.num_heads],
+            Distribution::Normal(0., 0.02),
+            device,
+        );
+
+        AxialAttention1d {
+            qkv: LinearConfig::new(c, 3 * c).init(device),
+            output: LinearConfig::new(c, c).init(device),
+            bias_table: Param::from_tensor(bias_table),
+            num_heads: self.num_heads,
+            kernel_size: self.kernel_size,
+        }
+    }
+}
+
+/// Self-attention along the height axis of a feature map: each column is attended independently.
+#[derive(Module, Debug)]
+pub struct HeightAttention<B: Backend> {
+    attention: AxialAttention1d<B>,
+}
+
+impl<B: Backend> HeightAttention<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, c, h, w] = x.dims();
+
+        // [N, C, H, W] -> [N * W, H, C]
+        let x = x.permute([0, 3, 2, 1]).reshape([n * w, h, c]);
+        let x = self.attention.forward(x);
+
+        x.reshape([n, w, h, c]).permute([0, 3, 2, 1])
+    }
+}
+
+/// Self-attention along the width axis of a feature map: each row is attended independently.
+#[derive(Module, Debug)]
+pub struct WidthAttention<B: Backend> {
+    attention: AxialAttention1d<B>,
+}
+
+impl<B: Backend> WidthAttention<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, c, h, w] = x.dims();
+
+        // [N, C, H, W] -> [N * H, W, C]
+        let x = x.permute([0, 2, 3, 1]).reshape([n * h, w, c]);
+        let x = self.attention.forward(x);
+
+        x.reshape([n, h, w, c]).permute([0, 3, 1, 2])
+    }
+}
+
+/// [Axial attention](https://arxiv.org/abs/1912.12180), factorizing the 2D self-attention of a
+/// feature map into a [height attention](HeightAttention) followed by a
+/// [width attention](WidthAttention).
+///
+/// Each position attends to its column and then to its row, which reduces the cost from
+/// `O((HW)^2)` to `O(HW(H + W))`.
+#[derive(Module, Debug)]
+pub struct AxialAttention<B: Backend> {
+    height: HeightAttention<B>,
+    width: WidthAttention<B>,
+}
+
+impl<B: Backend> AxialAttention<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.height.forward(x);
+        self.width.forward(x)
+    }
+}
+
+/// [Axial attention](AxialAttention) configuration.
+pub struct AxialAttentionConfig {
+    attention: AxialAttention1dConfig,
+}
+
+impl AxialAttentionConfig {
+    /// Create a new instance of the axial attention [config](AxialAttentionConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input (and output) channels.
+    /// * `num_heads` - Number of attention heads.
+    /// * `kernel_size` - Maximum height and width of the feature maps, which bounds the relative
+    ///   positions with a learned bias.
+    pub fn new(in_channels: usize, num_heads: usize, kernel_size: usize) -> Self {
+        Self {
+            attention: AxialAttention1dConfig::new(in_channels, num_heads, kernel_size),
+        }
+    }
+
+    /// Initialize a new [axial attention](AxialAttention) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> AxialAttention<B> {
+        AxialAttention {
+            height: HeightAttention {
+                attention: self.attention.init(device),
+            },
+            width: WidthAttention {
+                attention: self.attention.init(device),
+            },
+        }
+    }
+}
+
+/// `1x1` convolution with bias.
+fn pointwise_conv<B: Backend>(
+    in_channels: usize,
+    out_channels: usize,
+    device: &Device<B>,
+) -> Conv2d<B> {
+    Conv2dConfig::new([in_channels, out_channels], [1, 1])
+        .with_padding(PaddingConfig2d::Explicit(0, 0))
+        .init(device)
+}
+
+/// Position attention module of the [dual attention network](DaNet): a self-attention over the
+/// spatial positions, added to the input with a learnable scale initialized to 0.
+#[derive(Module, Debug)]
+pub struct PositionAttentionModule<B: Backend> {
+    query: Conv2d<B>,
+    key: Conv2d<B>,
+    value: Conv2d<B>,
+    gamma: Param<Tensor<B, 1>>,
+}
+
+impl<B: Backend> PositionAttentionModule<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, c, h, w] = x.dims();
+        let attention = self.attention_map(x.clone());
+
+        // Each output position is the weighted sum of the values of all the positions
+        let value = self.value.forward(x.clone()).reshape([n, c, h * w]);
+        let out = value
+            .matmul(attention.swap_dims(1, 2))
+            .reshape([n, c, h, w]);
+
+        out * self.gamma.val().reshape([1, 1, 1, 1]) + x
+    }
+
+    /// Attention weights of each position (rows) over all the positions (columns).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, height * width, height * width]`
+    pub fn attention_map(&self, x: Tensor<B, 4>) -> Tensor<B, 3> {
+        let [n, _, h, w] = x.dims();
+
+        let query = self.query.forward(x.clone());
+        let [_, c, _, _] = query.dims();
+        let query = query.reshape([n, c, h * w]).swap_dims(1, 2);
+        let key = self.key.forward(x).reshape([n, c, h * w]);
+
+        softmax(query.matmul(key), 2)
+    }
+}
+
+/// [Position attention module](PositionAttentionModule) configuration.
+pub struct PositionAttentionModuleConfig {
+    channels: usize,
+}
+
+impl PositionAttentionModuleConfig {
+    /// Create a new instance of the position attention module
+    /// [config](PositionAttentionModuleConfig).
+    pub fn new(channels: usize) -> Self {
+        Self { channels }
+    }
+
+    /// Initialize a new [position attention module](PositionAttentionModule).
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> PositionAttentionModule<B> {
+        let c = self.channels;
+        let key_channels = (c / 8).max(1);
+
+        PositionAttentionModule {
+            query: pointwise_conv(c, key_channels, device),
+            key: pointwise_conv(c, key_channels, device),
+            value: pointwise_conv(c, c, device),
+            gamma: Param::from_tensor(Tensor::zeros([1], device)),
+        }
+    }
+}
+
+/// Channel attention module of the [dual attention network](DaNet): a self-attention over the
+/// channels, added to the input with a learnable scale initialized to 0.
+#[derive(Module, Debug)]
+pub struct ChannelAttentionModule<B: Backend> {
+    gamma: Param<Tensor<B, 1>>,
+}
+
+impl<B: Backend> ChannelAttentionModule<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, c, h, w] = x.dims();
+        let attention = self.attention_map(x.clone());
+
+        let out = attention
+            .matmul(x.clone().reshape([n, c, h * w]))
+            .reshape([n, c, h, w]);
+
+        out * self.gamma.val().reshape([1, 1, 1, 1]) + x
+    }
+
+    /// Attention weights of each channel (rows) over all the channels (columns).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, channels]`
+    pub fn attention_map(&self, x: Tensor<B, 4>) -> Tensor<B, 3> {
+        let [n, c, h, w] = x.dims();
+        let x = x.reshape([n, c, h * w]);
+
+        let energy = x.clone().matmul(x.swap_dims(1, 2));
+        // Subtracting the energy from its maximum favors the less similar channels, as in the
+        // reference implementation
+        let energy = energy.clone().max_dim(2) - energy;
+
+        softmax(energy, 2)
+    }
+}
+
+/// [Channel attention module](ChannelAttentionModule) configuration.
+pub struct ChannelAttentionModuleConfig;
+
+impl ChannelAttentionModuleConfig {
+    /// Create a new instance of the channel attention module
+    /// [config](ChannelAttentionModuleConfig).
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Initialize a new [channel attention module](ChannelAttentionModule).
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ChannelAttentionModule<B> {
+        ChannelAttentionModule {
+            gamma: Param::from_tensor(Tensor::zeros([1], device)),
+        }
+    }
+}
+
+impl Default for ChannelAttentionModuleConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// [Dual Attention Network](https://arxiv.org/abs/1809.02983) (DANet) segmentation head.
+///
+/// The input features are reduced and refined in parallel by a
+/// [position attention](PositionAttentionModule) and a
+/// [channel attention](ChannelAttentionModule) branch, whose outputs are summed and projected to
+/// the output channels (e.g., the class logits).
+#[derive(Module, Debug)]
+pub struct DaNet<B: Backend> {
+    position_in: BaseConv<B>,
+    position: PositionAttentionModule<B>,
+    position_out: BaseConv<B>,
+    channel_in: BaseConv<B>,
+    channel: ChannelAttentionModule<B>,
+    channel_out: BaseConv<B>,
+    dropout: Dropout,
+    output: Conv2d<B>,
+}
+
+impl<B: Backend> DaNet<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, out_channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let position = self.position_in.forward(x.clone());
+        let position = self.position_out.forward(self.position.forward(position));
+
+        let channel = self.channel_in.forward(x);
+        let channel = self.channel_out.forward(self.channel.forward(channel));
+
+        let x = self.dropout.forward(position + channel);
+        self.output.forward(x)
+    }
+}
+
+/// [Dual attention network](DaNet) configuration.
+pub struct DaNetConfig {
+    in_channels: usize,
+    out_channels: usize,
+    dropout: f64,
+}
+
+impl DaNetConfig {
+    /// Create a new instance of the dual attention network [config](DaNetConfig).
+    pub fn new(in_channels: usize, out_channels: usize) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+            dropout: 0.1,
+        }
+    }
+
+    /// Set the dropout probability before the output projection (defaults to 0.1).
+    pub fn with_dropout(mut self, dropout: f64) -> Self {
+        self.dropout = dropout;
+        self
+    }
+
+    /// Initialize a new [dual attention network](DaNet) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DaNet<B> {
+        let inter_channels = (self.in_channels / 4).max(1);
+        let conv = |in_channels| {
+            BaseConvConfig::new(in_channels, inter_channels, 3, 1, 1)
+                .with_activation(ActivationType::Relu)
+                .init(device)
+        };
+
+        DaNet {
+            position_in: conv(self.in_channels),
+            position: PositionAttentionModuleConfig::new(inter_channels).init(device),
+            position_out: conv(inter_channels),
+            channel_in: conv(self.in_channels),
+            channel: ChannelAttentionModuleConfig::new().init(device),
+            channel_out: conv(inter_channels),
+            dropout: DropoutConfig::new(self.dropout).init(),
+            output: pointwise_conv(inter_channels, self.out_channels, device),
+        }
+    }
+}
+
+/// Object-level positional encoding of the queries of detection transformers, as used by
+/// [Conditional DETR](https://arxiv.org/abs/2108.06152) and
+/// [DAB-DETR](https://arxiv.org/abs/2201.12329).
+///
+/// Each coordinate of the reference box `(cx, cy, w, h)` is embedded with `num_pos_feats` sine
+/// and cosine features, and the concatenated embeddings are projected to `d_model` dimensions by
+/// a two-layer MLP. The encodings are L2-normalized.
+#[derive(Module, Debug)]
+pub struct ObjectLevelPositionalEncoding<B: Backend> {
+    fc1: Linear<B>,
+    fc2: Linear<B>,
+    activation: Relu,
+    num_pos_feats: usize,
+    temperature: f32,
+}
+
+impl<B: Backend> ObjectLevelPositionalEncoding<B> {
+    /// Encode the reference boxes of the queries.
+    ///
+    /// # Shapes
+    ///   - reference_points: `[batch_size, num_queries, 4]` with normalized `(cx, cy, w, h)`
+    ///     coordinates in the range `[0, 1]`
+    ///   - output: `[batch_size, num_queries, d_model]`
+    pub fn forward(&self, reference_points: Tensor<B, 3>) -> Tensor<B, 3> {
+        let [n, q, _] = reference_points.dims();
+
+        let x = self.sine_embedding(reference_points);
+        let x = self.activation.forward(self.fc1.forward(x));
+        let x = self.fc2.forward(x);
+        let [_, _, d_model] = x.dims();
+
+        l2_normalize(x.reshape([n * q, d_model])).reshape([n, q, d_model])
+    }
+
+    /// Sine embedding of each coordinate of the reference boxes.
+    ///
+    /// # Shapes
+    ///   - reference_points: `[batch_size, num_queries, 4]`
+    ///   - output: `[batch_size, num_queries, 4 * num_pos_feats]`
+    pub fn sine_embedding(&self, reference_points: Tensor<B, 3>) -> Tensor<B, 3> {
+        let [n, q, _] = reference_points.dims();
+        let half = self.num_pos_feats / 2;
+
+        let device = reference_points.device();
+
+        // Frequencies 2π / temperature^(2i / num_pos_feats) [1, 1, half]
+        let ln_temperature = Tensor::<B, 1>::from_floats([self.temperature], &device).log();
+        let freqs = (Tensor::<B, 1, Int>::arange(0..half as i64, &device)
+            .float()
+            .mul_scalar(-2. / self.num_pos_feats as f32)
+            * ln_temperature)
+            .exp()
+            .mul_scalar(2. * core::f32::consts::PI)
+            .reshape([1, 1, half]);
+
+        let embeddings = (0..4)
+            .map(|i| {
+                let x = reference_points.clone().slice([0..n, 0..q, i..i + 1]) * freqs.clone();
+                Tensor::cat(vec![x.clone().sin(), x.cos()], 2)
+            })
+            .collect();
+
+        Tensor::cat(embeddings, 2)
+    }
+}
+
+/// [Object-level positional encoding](ObjectLevelPositionalEncoding) configuration.
+pub struct ObjectLevelPositionalEncodingConfig {
+    d_model: usize,
+    num_pos_feats: usize,
+    temperature: f32,
+}
+
+impl ObjectLevelPositionalEncodingConfig {
+    /// Create a new instance of the object-level positional encoding
+    /// [config](ObjectLevelPositionalEncodingConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `d_model`: Dimension of the encodings.
+    /// * `num_pos_feats` - Number of sine and cosine features of each box coordinate (even).
+    /// * `temperature` - Temperature of the sine embeddings (e.g., 10000).
+    pub fn new(d_model: usize, num_pos_feats: usize, temperature: f32) -> Self {
+        assert!(
+            num_pos_feats % 2 == 0,
+            "the number of positional features should be even"
+        );
+
+        Self {
+            d_model,
+            num_pos_feats,
+            temperature,
+        }
+    }
+
+    /// Initialize a new [object-level positional encoding](ObjectLevelPositionalEncoding)
+    /// module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ObjectLevelPositionalEncoding<B> {
+        ObjectLevelPositionalEncoding {
+            fc1: LinearConfig::new(4 * self.num_pos_feats, self.d_model).init(device),
+            fc2: LinearConfig::new(self.d_model, self.d_model).init(device),
+            activation: Relu::new(),
+            num_pos_feats: self.num_pos_feats,
+            temperature: self.temperature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn window_partition_round_trip() {
+        let device = Default::default();
+        for (h, w, ws) in [(8, 8, 4), (6, 12, 3), (7, 14, 7), (4, 4, 1)] {
+            let x = Tensor::<TestBackend, 4>::random([2, h, w, 5], Distribution::Default, &device);
+
+            let windows = window_partition(x.clone(), ws);
+            assert_eq!(windows.dims(), [2, (h / ws) * (w / ws), ws, ws, 5]);
+
+            window_reverse(windows, ws, h, w)
+                .into_data()
+                .assert_eq(&x.into_data(), true);
+        }
+    }
+
+    #[test]
+    fn window_partition_contents() {
+        let device = Default::default();
+        // Position index as the single channel of a 4x4 feature map
+        let x = Tensor::<TestBackend, 1, Int>::arange(0..16, &device)
+            .float()
+            .reshape([1, 4, 4, 1]);
+
+        let windows = window_partition(x, 2);
+
+        // Second window: top-right 2x2 block
+        let second: Vec<f32> = windows
+            .slice([0..1, 1..2, 0..2, 0..2, 0..1])
+            .into_data()
+            .to_vec()
+            .unwrap();
+        assert_eq!(second, [2., 3., 6., 7.]);
+    }
+
+    #[test]
+    #[should_panic = "should be divisible by the window size"]
+    fn window_partition_not_divisible() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::zeros([1, 6, 8, 3], &device);
+
+        window_partition(x, 4);
+    }
+
+    #[test]
+    fn attention_mask_without_shift() {
+        let device = Default::default();
+        let mask = compute_attention_mask::<TestBackend>(8, 8, 4, 0, &device);
+
+        assert_eq!(mask.dims(), [4, 16, 16]);
+        let values: Vec<f32> = mask.into_data().to_vec().unwrap();
+        assert!(values.iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn attention_mask_with_shift() {
+        let device = Default::default();
+        let mask = compute_attention_mask::<TestBackend>(4, 4, 2, 1, &device);
+
+        assert_eq!(mask.dims(), [4, 4, 4]);
+        let values: Vec<f32> = mask.into_data().to_vec().unwrap();
+        // The first window is not shifted, the last one mixes 4 regions
+        assert!(values[..16].iter().all(|&v| v == 0.));
+        let last = &values[48..];
+        let masked = last.iter().filter(|&&v| v == MASK_VALUE).count();
+        assert_eq!(masked, 12);
+        // A position always attends to itself
+        for i in 0..4 {
+            assert_eq!(last[i * 4 + i], 0.);
+        }
+    }
+
+    #[test]
+    fn relative_position_bias_shapes() {
+        let device = Default::default();
+        let bias = RelativePositionBiasConfig::new(3, 4).init::<TestBackend>(&device);
+
+        assert_eq!(bias.table.dims(), [25, 4]);
+        // The index is a buffer, only the table is a parameter
+        assert_eq!(bias.num_params(), 25 * 4);
+        assert_eq!(bias.forward().dims(), [4, 9, 9]);
+    }
+
+    #[test]
+    fn relative_position_bias_same_offset() {
+        let device = Default::default();
+        let ws = 3;
+        let bias = RelativePositionBiasConfig::new(ws, 2).init::<TestBackend>(&device);
+
+        let values: Vec<f32> = bias.forward().into_data().to_vec().unwrap();
+        let area = ws * ws;
+        let at = |head: usize, i: usize, j: usize| values[(head * area + i) * area + j];
+        let offset = |i: usize, j: usize| {
+            (
+                (i / ws) as i64 - (j / ws) as i64,
+                (i % ws) as i64 - (j % ws) as i64,
+            )
+        };
+
+        for head in 0..2 {
+            for i in 0..area {
+                // Same location
+                assert_eq!(at(head, i, i), at(head, 0, 0));
+                for j in 0..area {
+                    for (k, l) in (0..area).flat_map(|k| (0..area).map(move |l| (k, l))) {
+                        if offset(i, j) == offset(k, l) {
+                            assert_eq!(at(head, i, j), at(head, k, l));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn axial_attention_symmetric_params() {
+        let device = Default::default();
+        let attention = AxialAttentionConfig::new(8, 2, 4).init::<TestBackend>(&device);
+
+        assert_eq!(attention.height.num_params(), attention.width.num_params());
+    }
+
+    #[test]
+    fn axial_attention_shape() {
+        let device = Default::default();
+        let attention = AxialAttentionConfig::new(8, 2, 4).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 8, 4, 4], Distribution::Default, &device);
+
+        assert_eq!(attention.forward(x).dims(), [2, 8, 4, 4]);
+    }
+
+    #[test]
+    fn height_attention_transposed_width_attention() {
+        let device = Default::default();
+        let attention = AxialAttention1dConfig::new(8, 2, 5).init::<TestBackend>(&device);
+        let height = HeightAttention {
+            attention: attention.clone(),
+        };
+        let width = WidthAttention { attention };
+        let x = Tensor::random([2, 8, 3, 5], Distribution::Default, &device);
+
+        let expected = width.forward(x.clone());
+        let output = height.forward(x.swap_dims(2, 3)).swap_dims(2, 3);
+
+        output
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 4);
+    }
+
+    #[test]
+    fn axial_attention_relative_bias() {
+        let device = Default::default();
+        let mut attention = AxialAttention1dConfig::new(4, 1, 3).init::<TestBackend>(&device);
+        attention.bias_table = Param::from_tensor(
+            Tensor::<TestBackend, 1, Int>::arange(0..5, &device)
+                .float()
+                .reshape([5, 1]),
+        );
+
+        // Bias of offset i - j, shifted by kernel_size - 1
+        attention.relative_bias(3).into_data().assert_eq(
+            &TensorData::from([[[2.0f32, 1., 0.], [3., 2., 1.], [4., 3., 2.]]]),
+            false,
+        );
+    }
+
+    #[test]
+    #[should_panic = "sequence length 5 exceeds the attention span 4"]
+    fn axial_attention_span() {
+        let device = Default::default();
+        let attention = AxialAttentionConfig::new(8, 2, 4).init::<TestBackend>(&device);
+
+        attention.forward(Tensor::zeros([1, 8, 5, 4], &device));
+    }
+
+    #[test]
+    fn position_attention_shape() {
+        let device = Default::default();
+        let mut attention = PositionAttentionModuleConfig::new(16).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 16, 4, 5], Distribution::Default, &device);
+
+        // The scale is initialized to zero, so the module is the identity
+        attention
+            .forward(x.clone())
+            .into_data()
+            .assert_approx_eq(&x.clone().into_data(), 5);
+
+        attention.gamma = Param::from_tensor(Tensor::ones([1], &device));
+        assert_eq!(attention.forward(x).dims(), [2, 16, 4, 5]);
+    }
+
+    #[test]
+    fn position_attention_map_normalized() {
+        let device = Default::default();
+        let attention = PositionAttentionModuleConfig::new(16).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 16, 4, 5], Distribution::Default, &device);
+
+        let map = attention.attention_map(x);
+
+        assert_eq!(map.dims(), [2, 20, 20]);
+        map.sum_dim(2).into_data().assert_approx_eq(
+            &Tensor::<TestBackend, 3>::ones([2, 20, 1], &device).into_data(),
+            5,
+        );
+    }
+
+    #[test]
+    fn channel_attention_map_normalized() {
+        let device = Default::default();
+        let attention = ChannelAttentionModuleConfig::new().init::<TestBackend>(&device);
+        let x = Tensor::random([2, 6, 4, 5], Distribution::Default, &device);
+
+        let map = attention.attention_map(x);
+
+        assert_eq!(map.dims(), [2, 6, 6]);
+        map.sum_dim(2).into_data().assert_approx_eq(
+            &Tensor::<TestBackend, 3>::ones([2, 6, 1], &device).into_data(),
+            5,
+        );
+    }
+
+    #[test]
+    fn danet_spatial_shape() {
+        let device = Default::default();
+        let danet = DaNetConfig::new(32, 5).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 32, 8, 6], Distribution::Default, &device);
+
+        assert_eq!(danet.forward(x).dims(), [2, 5, 8, 6]);
+    }
+
+    #[test]
+    fn object_positional_encoding_identical_points() {
+        let device = Default::default();
+        let encoding =
+            ObjectLevelPositionalEncodingConfig::new(32, 16, 10000.).init::<TestBackend>(&device);
+        let reference_points = Tensor::<TestBackend, 3>::from_floats(
+            [[
+                [0.5, 0.5, 0.2, 0.3],
+                [0.1, 0.8, 0.05, 0.1],
+                [0.5, 0.5, 0.2, 0.3],
+            ]],
+            &device,
+        );
+
+        let encodings = encoding.forward(reference_points);
+
+        assert_eq!(encodings.dims(), [1, 3, 32]);
+        let first = encodings.clone().slice([0..1, 0..1, 0..32]).into_data();
+        encodings
+            .clone()
+            .slice([0..1, 2..3, 0..32])
+            .into_data()
+            .assert_eq(&first, true);
+        assert_ne!(
+            encodings
+                .slice([0..1, 1..2, 0..32])
+                .into_data()
+                .to_vec::<f32>()
+                .unwrap(),
+            first.to_vec::<f32>().unwrap()
+        );
+    }
+
+    #[test]
+    fn object_positional_encoding_unit_norm() {
+        let device = Default::default();
+        let encoding =
+            ObjectLevelPositionalEncodingConfig::new(32, 16, 10000.).init::<TestBackend>(&device);
+        let reference_points =
+            Tensor::<TestBackend, 3>::random([2, 5, 4], Distribution::Default, &device);
+
+        let norms = encoding
+            .forward(reference_points)
+            .powf_scalar(2.)
+            .sum_dim(2)
+            .sqrt();
+
+        norms
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[1f32]; 5]; 2]), 4);
+    }
+
+    #[test]
+    fn object_sine_embedding_at_origin() {
+        let device = Default::default();
+        let encoding =
+            ObjectLevelPositionalEncodingConfig::new(8, 4, 100.).init::<TestBackend>(&device);
+
+        let embedding = encoding.sine_embedding(Tensor::zeros([1, 1, 4], &device));
+
+        // (sin, sin, cos, cos) of each coordinate
+        embedding
+            .reshape([4, 4])
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[0f32, 0., 1., 1.]; 4]), 5);
+    }
+
+    #[test]
+    #[should_panic = "the number of positional features should be even"]
+    fn object_positional_encoding_odd_features() {
+        ObjectLevelPositionalEncodingConfig::new(32, 15, 10000.);
+    }
+}
@@ -0,0 +1,304 @@
+use alloc::vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d,
+    },
+    tensor::{activation::softmax, backend::Backend, Device, Int, Tensor},
+};
+
+use super::{BevGrid, VoxelPool};
+use crate::model::darknet::{CspDarknet, CspDarknetConfig, DarknetFeatures};
+
+/// Stride of the backbone feature map used for the view transform (`dark4`).
+const FEATURE_STRIDE: usize = 16;
+/// Index of the `dark4` stage in the [backbone stages](CspDarknetConfig::stage_channels).
+const FEATURE_STAGE: usize = 2;
+
+/// Predict a categorical depth distribution and context features for each pixel of a feature
+/// map (i.e., the "lift" step of lift-splat-shoot).
+#[derive(Module, Debug)]
+pub struct DepthDistribution<B: Backend> {
+    conv: Conv2d<B>,
+    num_bins: usize,
+}
+
+impl<B: Backend> DepthDistribution<B> {
+    /// Compute the depth distribution and the context features.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, in_channels, H, W]`
+    ///   - depth: `[batch_size, num_bins, H, W]`, summing to one along the depth axis
+    ///   - context: `[batch_size, context_channels, H, W]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> (Tensor<B, 4>, Tensor<B, 4>) {
+        let x = self.conv.forward(x);
+        let [b, c, h, w] = x.dims();
+
+        let depth = softmax(x.clone().slice([0..b, 0..self.num_bins, 0..h, 0..w]), 1);
+        let context = x.slice([0..b, self.num_bins..c, 0..h, 0..w]);
+
+        (depth, context)
+    }
+}
+
+/// [Depth distribution](DepthDistribution) configuration.
+pub struct DepthDistributionConfig {
+    conv: Conv2dConfig,
+    num_bins: usize,
+}
+
+impl DepthDistributionConfig {
+    /// Create a new instance of the depth distribution [config](DepthDistributionConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the image features.
+    /// * `num_bins` - Number of depth bins.
+    /// * `context_channels` - Number of channels of the context features.
+    pub fn new(in_channels: usize, num_bins: usize, context_channels: usize) -> Self {
+        let conv = Conv2dConfig::new([in_channels, num_bins + context_channels], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+
+        Self { conv, num_bins }
+    }
+
+    /// Initialize a new [depth distribution](DepthDistribution) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DepthDistribution<B> {
+        DepthDistribution {
+            conv: self.conv.init(device),
+            num_bins: self.num_bins,
+        }
+    }
+}
+
+/// Camera stream of [BEVFusion](super::BevFusion): image backbone followed by the
+/// [lift-splat-shoot](https://arxiv.org/abs/2008.05711) projection of the image features to the
+/// BEV grid.
+#[derive(Module, Debug)]
+pub struct CameraStreamEncoder<B: Backend> {
+    backbone: CspDarknet<B>,
+    depth: DepthDistribution<B>,
+    pool: VoxelPool,
+    depth_min: f32,
+    depth_max: f32,
+}
+
+impl<B: Backend> CameraStreamEncoder<B> {
+    /// Encode the camera images into BEV features.
+    ///
+    /// # Arguments
+    ///
+    /// * `images`: Camera images. Shape: `[batch_size, 3, height, width]`.
+    /// * `intrinsics` - Camera intrinsics `(fx, fy, cx, cy)` in pixels. Shape: `[batch_size, 4]`.
+    /// * `cam_to_ego` - Camera to ego vehicle transform. Shape: `[batch_size, 4, 4]`.
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, out_channels, bev_h, bev_w]`
+    pub fn forward(
+        &self,
+        images: Tensor<B, 4>,
+        intrinsics: Tensor<B, 2>,
+        cam_to_ego: Tensor<B, 3>,
+    ) -> Tensor<B, 4> {
+        let DarknetFeatures(_, x, _) = self.backbone.forward(images);
+
+        // Lift: outer product of the depth distribution and the context [B, C, D, H, W]
+        let (depth, context) = self.depth.forward(x);
+        let [b, d, h, w] = depth.dims();
+        let [_, c, _, _] = context.dims();
+        let frustum = depth.unsqueeze_dim::<5>(1) * context.unsqueeze_dim::<5>(2);
+
+        // Splat: pool the frustum features into the BEV grid
+        let points = self.frustum_points([d, h, w], intrinsics, cam_to_ego);
+        self.pool
+            .forward(frustum.reshape([b, c, d * h * w]), points)
+    }
+
+    /// Compute the `(x, y)` coordinates in the ego frame of the frustum points, for each depth
+    /// bin and pixel of the feature map.
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, num_bins * H * W, 2]`
+    fn frustum_points(
+        &self,
+        [d, h, w]: [usize; 3],
+        intrinsics: Tensor<B, 2>,
+        cam_to_ego: Tensor<B, 3>,
+    ) -> Tensor<B, 3> {
+        let device = intrinsics.device();
+        let [b, _] = intrinsics.dims();
+        let n = d * h * w;
+        let stride = FEATURE_STRIDE as f32;
+        let step = (self.depth_max - self.depth_min) / d as f32;
+
+        // Depth bin and pixel centers [1, D * H * W]
+        let depths = Tensor::<B, 1, Int>::arange(0..d as i64, &device)
+            .float()
+            .add_scalar(0.5)
+            .mul_scalar(step)
+            .add_scalar(self.depth_min)
+            .reshape([d, 1, 1])
+            .repeat_dim(1, h)
+            .repeat_dim(2, w)
+            .reshape([1, n]);
+        let v = Tensor::<B, 1, Int>::arange(0..h as i64, &device)
+            .float()
+            .add_scalar(0.5)
+            .mul_scalar(stride)
+            .reshape([1, h, 1])
+            .repeat_dim(0, d)
+            .repeat_dim(2, w)
+            .reshape([1, n]);
+        let u = Tensor::<B, 1, Int>::arange(0..w as i64, &device)
+            .float()
+            .add_scalar(0.5)
+            .mul_scalar(stride)
+            .reshape([1, 1, w])
+            .repeat_dim(0, d)
+            .repeat_dim(1, h)
+            .reshape([1, n]);
+
+        // Unproject to the camera frame [B, 3, N]
+        let fx = intrinsics.clone().slice([0..b, 0..1]);
+        let fy = intrinsics.clone().slice([0..b, 1..2]);
+        let cx = intrinsics.clone().slice([0..b, 2..3]);
+        let cy = intrinsics.slice([0..b, 3..4]);
+        let x = (u - cx) * depths.clone() / fx;
+        let y = (v - cy) * depths.clone() / fy;
+        let z = depths.repeat_dim(0, b);
+        let points = Tensor::stack::<3>(vec![x, y, z], 1);
+
+        // Transform to the ego frame
+        let rotation = cam_to_ego.clone().slice([0..b, 0..3, 0..3]);
+        let translation = cam_to_ego.slice([0..b, 0..3, 3..4]);
+        let points = rotation.matmul(points) + translation;
+
+        points.slice([0..b, 0..2, 0..n]).swap_dims(1, 2)
+    }
+}
+
+/// [Camera stream encoder](CameraStreamEncoder) configuration.
+pub struct CameraStreamEncoderConfig {
+    backbone: CspDarknetConfig,
+    out_channels: usize,
+    grid: BevGrid,
+    depth_range: (f32, f32),
+    num_bins: usize,
+}
+
+impl CameraStreamEncoderConfig {
+    /// Create a new instance of the camera stream encoder [config](CameraStreamEncoderConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `backbone`: Image backbone configuration.
+    /// * `out_channels` - Number of channels of the BEV features.
+    /// * `grid` - BEV grid.
+    pub fn new(backbone: CspDarknetConfig, out_channels: usize, grid: BevGrid) -> Self {
+        Self {
+            backbone,
+            out_channels,
+            grid,
+            depth_range: (1.0, 60.0),
+            num_bins: 59,
+        }
+    }
+
+    /// Set the BEV grid.
+    pub fn with_grid(mut self, grid: BevGrid) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Set the depth range in meters and the number of depth bins (defaults to 59 bins of one
+    /// meter from 1 to 60 meters).
+    pub fn with_depth_bins(mut self, depth_range: (f32, f32), num_bins: usize) -> Self {
+        assert!(
+            depth_range.0 > 0. && depth_range.0 < depth_range.1,
+            "invalid depth range"
+        );
+        self.depth_range = depth_range;
+        self.num_bins = num_bins;
+        self
+    }
+
+    /// Initialize a new [camera stream encoder](CameraStreamEncoder) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> CameraStreamEncoder<B> {
+        let in_channels = self.backbone.stage_channels()[FEATURE_STAGE];
+
+        CameraStreamEncoder {
+            backbone: self.backbone.init(device),
+            depth: DepthDistributionConfig::new(in_channels, self.num_bins, self.out_channels)
+                .init(device),
+            pool: VoxelPool::new(&self.grid),
+            depth_min: self.depth_range.0,
+            depth_max: self.depth_range.1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn depth_distribution_sums_to_one() {
+        let device = Default::default();
+        let depth = DepthDistributionConfig::new(16, 8, 4).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 16, 3, 5], Distribution::Default, &device);
+
+        let (depth, context) = depth.forward(x);
+
+        assert_eq!(depth.dims(), [2, 8, 3, 5]);
+        assert_eq!(context.dims(), [2, 4, 3, 5]);
+        depth
+            .sum_dim(1)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[[1f32; 5]; 3]]; 2]), 4);
+    }
+
+    #[test]
+    fn camera_bev_features_shape() {
+        let device = Default::default();
+        let grid = BevGrid::new((0., 16.), (-4., 4.), 0.5);
+        let encoder =
+            CameraStreamEncoderConfig::new(CspDarknetConfig::new(0.33, 0.25, false), 8, grid)
+                .with_depth_bins((1., 9.), 4)
+                .init::<TestBackend>(&device);
+
+        let images = Tensor::random([2, 3, 64, 64], Distribution::Default, &device);
+        let intrinsics = Tensor::from_floats([[32., 32., 32., 32.]; 2], &device);
+        // Camera looking forward: z (camera) -> x (ego), x (camera) -> -y (ego)
+        let cam_to_ego = Tensor::from_floats(
+            [[
+                [0., 0., 1., 0.],
+                [-1., 0., 0., 0.],
+                [0., -1., 0., 1.5],
+                [0., 0., 0., 1.],
+            ]; 2],
+            &device,
+        );
+
+        let bev = encoder.forward(images, intrinsics, cam_to_ego);
+
+        assert_eq!(bev.dims(), [2, 8, 16, 32]);
+    }
+
+    #[test]
+    #[should_panic = "invalid depth range"]
+    fn invalid_depth_range() {
+        let _ = CameraStreamEncoderConfig::new(
+            CspDarknetConfig::new(0.33, 0.25, false),
+            8,
+            BevGrid::default(),
+        )
+        .with_depth_bins((10., 1.), 4);
+    }
+}
@@ -0,0 +1,113 @@
+use alloc::vec;
+use burn::{
+    module::Module,
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use super::{
+    BevDetectionHead, BevDetectionHeadConfig, BevGrid, BevHeadOutput, CameraStreamEncoder,
+    CameraStreamEncoderConfig, ConvBevNeck, ConvBevNeckConfig, LidarStreamEncoder,
+    LidarStreamEncoderConfig,
+};
+use crate::model::{
+    blocks::{BaseConv, BaseConvConfig},
+    darknet::CspDarknetConfig,
+};
+
+/// [BEVFusion](https://arxiv.org/abs/2205.13542) multi-modal 3D object detection model, which
+/// fuses the camera and LiDAR features in a shared bird's-eye view (BEV) representation.
+#[derive(Module, Debug)]
+pub struct BevFusion<B: Backend> {
+    camera: CameraStreamEncoder<B>,
+    lidar: LidarStreamEncoder<B>,
+    fuser: BaseConv<B>,
+    neck: ConvBevNeck<B>,
+    head: BevDetectionHead<B>,
+}
+
+impl<B: Backend> BevFusion<B> {
+    /// Detect objects from the camera images and the LiDAR points.
+    ///
+    /// # Arguments
+    ///
+    /// * `images`: Camera images. Shape: `[batch_size, 3, height, width]`.
+    /// * `intrinsics` - Camera intrinsics `(fx, fy, cx, cy)` in pixels. Shape: `[batch_size, 4]`.
+    /// * `cam_to_ego` - Camera to ego vehicle transform. Shape: `[batch_size, 4, 4]`.
+    /// * `points` - LiDAR points in the ego frame. Shape: `[batch_size, num_points, point_features]`.
+    pub fn forward(
+        &self,
+        images: Tensor<B, 4>,
+        intrinsics: Tensor<B, 2>,
+        cam_to_ego: Tensor<B, 3>,
+        points: Tensor<B, 3>,
+    ) -> BevHeadOutput<B> {
+        let camera = self.camera.forward(images, intrinsics, cam_to_ego);
+        let lidar = self.lidar.forward(points);
+
+        let x = self.fuser.forward(Tensor::cat(vec![camera, lidar], 1));
+        let x = self.neck.forward(x);
+
+        self.head.forward(x)
+    }
+}
+
+/// [BEVFusion](BevFusion) configuration.
+pub struct BevFusionConfig {
+    camera: CameraStreamEncoderConfig,
+    lidar: LidarStreamEncoderConfig,
+    fuser: BaseConvConfig,
+    neck: ConvBevNeckConfig,
+    head: BevDetectionHeadConfig,
+    grid: BevGrid,
+}
+
+impl BevFusionConfig {
+    /// Create a new instance of the BEVFusion [config](BevFusionConfig).
+    ///
+    /// The camera backbone is the YOLOX-S [CSPDarknet](crate::model::darknet::CspDarknet) and the
+    /// LiDAR points have `(x, y, z, intensity)` features.
+    pub fn new(num_classes: usize) -> Self {
+        let (camera_channels, lidar_channels, bev_channels) = (80, 64, 128);
+        let grid = BevGrid::default();
+
+        let backbone = CspDarknetConfig::new(0.33, 0.5, false);
+        let camera = CameraStreamEncoderConfig::new(backbone, camera_channels, grid);
+        let lidar = LidarStreamEncoderConfig::new(4, lidar_channels, grid);
+        let fuser = BaseConvConfig::new(camera_channels + lidar_channels, bev_channels, 3, 1, 1);
+        let neck = ConvBevNeckConfig::new(bev_channels, bev_channels, 2);
+        let head = BevDetectionHeadConfig::new(bev_channels, bev_channels, num_classes);
+
+        Self {
+            camera,
+            lidar,
+            fuser,
+            neck,
+            head,
+            grid,
+        }
+    }
+
+    /// Set the BEV grid (defaults to the nuScenes detection range).
+    pub fn with_grid(mut self, grid: BevGrid) -> Self {
+        self.camera = self.camera.with_grid(grid);
+        self.lidar = self.lidar.with_grid(grid);
+        self.grid = grid;
+        self
+    }
+
+    /// BEV grid of the predictions.
+    pub fn grid(&self) -> &BevGrid {
+        &self.grid
+    }
+
+    /// Initialize a new [BEVFusion](BevFusion) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> BevFusion<B> {
+        BevFusion {
+            camera: self.camera.init(device),
+            lidar: self.lidar.init(device),
+            fuser: self.fuser.init(device),
+            neck: self.neck.init(device),
+            head: self.head.init(device),
+        }
+    }
+}
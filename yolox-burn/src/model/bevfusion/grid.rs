@@ -0,0 +1,152 @@
+use burn::{
+    module::Module,
+    tensor::{backend::Backend, Tensor},
+};
+
+/// Bird's-eye view (BEV) grid in the ego vehicle frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BevGrid {
+    /// Range of the `x` (forward) coordinates in meters.
+    pub x_range: (f32, f32),
+    /// Range of the `y` (left) coordinates in meters.
+    pub y_range: (f32, f32),
+    /// Size of a grid cell in meters.
+    pub resolution: f32,
+}
+
+impl BevGrid {
+    /// Create a new BEV grid.
+    pub fn new(x_range: (f32, f32), y_range: (f32, f32), resolution: f32) -> Self {
+        assert!(
+            x_range.0 < x_range.1 && y_range.0 < y_range.1,
+            "invalid BEV grid ranges"
+        );
+        assert!(
+            resolution > 0.,
+            "the BEV grid resolution should be positive"
+        );
+
+        Self {
+            x_range,
+            y_range,
+            resolution,
+        }
+    }
+
+    /// Number of cells `[bev_h, bev_w]` along the `y` and `x` axes.
+    pub fn size(&self) -> [usize; 2] {
+        let h = ((self.y_range.1 - self.y_range.0) / self.resolution).round() as usize;
+        let w = ((self.x_range.1 - self.x_range.0) / self.resolution).round() as usize;
+        [h, w]
+    }
+}
+
+impl Default for BevGrid {
+    /// nuScenes detection range of 51.2 meters around the ego vehicle, with 0.8 meter cells.
+    fn default() -> Self {
+        Self::new((-51.2, 51.2), (-51.2, 51.2), 0.8)
+    }
+}
+
+/// Pool point features into the cells of a [BEV grid](BevGrid) by summing the features of all the
+/// points that fall into the same cell (i.e., the "splat" step of lift-splat-shoot). Points outside
+/// of the grid are ignored.
+#[derive(Module, Debug, Clone)]
+pub struct VoxelPool {
+    x_min: f32,
+    y_min: f32,
+    resolution: f32,
+    bev_h: usize,
+    bev_w: usize,
+}
+
+impl VoxelPool {
+    /// Create a new voxel pooling module for the grid.
+    pub fn new(grid: &BevGrid) -> Self {
+        let [bev_h, bev_w] = grid.size();
+
+        Self {
+            x_min: grid.x_range.0,
+            y_min: grid.y_range.0,
+            resolution: grid.resolution,
+            bev_h,
+            bev_w,
+        }
+    }
+
+    /// Sum the point features in each grid cell.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, channels, num_points]`
+    ///   - points: `[batch_size, num_points, 2]` with the `(x, y)` coordinates in meters
+    ///   - output: `[batch_size, channels, bev_h, bev_w]`
+    pub fn forward<B: Backend>(
+        &self,
+        features: Tensor<B, 3>,
+        points: Tensor<B, 3>,
+    ) -> Tensor<B, 4> {
+        let [b, c, p] = features.dims();
+        let num_cells = self.bev_h * self.bev_w;
+
+        // Continuous cell coordinates [B, P]
+        let x = points
+            .clone()
+            .slice([0..b, 0..p, 0..1])
+            .reshape([b, p])
+            .sub_scalar(self.x_min)
+            .div_scalar(self.resolution);
+        let y = points
+            .slice([0..b, 0..p, 1..2])
+            .reshape([b, p])
+            .sub_scalar(self.y_min)
+            .div_scalar(self.resolution);
+
+        let outside = x.clone().lower_elem(0.).float()
+            + x.clone().greater_equal_elem(self.bev_w as f32).float()
+            + y.clone().lower_elem(0.).float()
+            + y.clone().greater_equal_elem(self.bev_h as f32).float();
+
+        // Flat cell index, with an extra cell collecting the points outside of the grid
+        let index = y.int().mul_scalar(self.bev_w as i64) + x.int();
+        let index = index
+            .mask_fill(outside.greater_elem(0.), num_cells as i64)
+            .unsqueeze_dim::<3>(1)
+            .repeat_dim(1, c);
+
+        Tensor::zeros([b, c, num_cells + 1], &features.device())
+            .scatter(2, index, features)
+            .slice([0..b, 0..c, 0..num_cells])
+            .reshape([b, c, self.bev_h, self.bev_w])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn grid_size() {
+        assert_eq!(BevGrid::default().size(), [128, 128]);
+        assert_eq!(BevGrid::new((0., 16.), (-4., 4.), 0.5).size(), [16, 32]);
+    }
+
+    #[test]
+    fn voxel_pool_sums_cells() {
+        let device = Default::default();
+        let pool = VoxelPool::new(&BevGrid::new((0., 2.), (0., 3.), 1.));
+        let features = Tensor::<TestBackend, 3>::from_floats([[[1., 2., 4., 8.]]], &device);
+        // Two points in cell (y=2, x=1), one in cell (y=0, x=0) and one outside of the grid
+        let points =
+            Tensor::from_floats([[[1.5, 2.5], [0.2, 0.7], [1.1, 2.9], [2.5, 0.5]]], &device);
+
+        let output = pool.forward(features, points);
+
+        output.into_data().assert_eq(
+            &TensorData::from([[[[2f32, 0.], [0., 0.], [0., 5.]]]]),
+            false,
+        );
+    }
+}
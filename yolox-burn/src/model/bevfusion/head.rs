@@ -0,0 +1,207 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        Initializer, PaddingConfig2d,
+    },
+    tensor::{activation::sigmoid, backend::Backend, Device, Tensor},
+};
+
+#[cfg(feature = "std")]
+use {
+    super::BevGrid,
+    crate::types::ConfThreshold,
+    burn::tensor::{module::max_pool2d, ElementConversion},
+    itertools::Itertools,
+};
+
+use crate::model::blocks::{BaseConv, BaseConvConfig};
+
+const PRIOR_PROB: f64 = 0.1;
+/// Box regression outputs `(dx, dy, z, log(w), log(l), log(h), sin(yaw), cos(yaw))`.
+const NUM_REG_OUTPUTS: usize = 8;
+
+/// Convolutional BEV neck applied to the fused BEV features.
+#[derive(Module, Debug)]
+pub struct ConvBevNeck<B: Backend> {
+    convs: Vec<BaseConv<B>>,
+}
+
+impl<B: Backend> ConvBevNeck<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.convs.iter().fold(x, |x, conv| conv.forward(x))
+    }
+}
+
+/// [Convolutional BEV neck](ConvBevNeck) configuration.
+pub struct ConvBevNeckConfig {
+    convs: Vec<BaseConvConfig>,
+}
+
+impl ConvBevNeckConfig {
+    /// Create a new instance of the convolutional BEV neck [config](ConvBevNeckConfig).
+    pub fn new(in_channels: usize, out_channels: usize, num_blocks: usize) -> Self {
+        let convs = (0..num_blocks.max(1))
+            .map(|i| {
+                let in_channels = if i == 0 { in_channels } else { out_channels };
+                BaseConvConfig::new(in_channels, out_channels, 3, 1, 1)
+            })
+            .collect();
+
+        Self { convs }
+    }
+
+    /// Initialize a new [convolutional BEV neck](ConvBevNeck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ConvBevNeck<B> {
+        ConvBevNeck {
+            convs: self.convs.iter().map(|m| m.init(device)).collect(),
+        }
+    }
+}
+
+/// 3D bounding box in the ego vehicle frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Box3d {
+    /// Box center `(x, y, z)` in meters.
+    pub center: [f32; 3],
+    /// Box size `(width, length, height)` in meters.
+    pub size: [f32; 3],
+    /// Heading angle around the `z` axis in radians.
+    pub yaw: f32,
+    pub score: f32,
+    pub class_id: usize,
+}
+
+/// Raw [BEV detection head](BevDetectionHead) outputs.
+pub struct BevHeadOutput<B: Backend> {
+    /// Class center heatmaps. Shape: `[batch_size, num_classes, bev_h, bev_w]`.
+    pub heatmap: Tensor<B, 4>,
+    /// Box regression `(dx, dy, z, log(w), log(l), log(h), sin(yaw), cos(yaw))` for each cell.
+    /// Shape: `[batch_size, 8, bev_h, bev_w]`.
+    pub regression: Tensor<B, 4>,
+}
+
+impl<B: Backend> BevHeadOutput<B> {
+    /// Decode the 3D box detections of each sample from the local maxima of the heatmaps.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid`: BEV grid of the predictions.
+    /// * `score_threshold` - Minimum score of the detections.
+    /// * `max_detections` - Maximum number of detections per sample.
+    #[cfg(feature = "std")]
+    pub fn decode(
+        self,
+        grid: &BevGrid,
+        score_threshold: ConfThreshold,
+        max_detections: usize,
+    ) -> Vec<Vec<Box3d>> {
+        let [b, num_classes, h, w] = self.heatmap.dims();
+
+        // Keep the local maxima only
+        let peaks = max_pool2d(self.heatmap.clone(), [3, 3], [1, 1], [1, 1], [1, 1]);
+        let keep = self.heatmap.clone().equal(peaks).float();
+        let scores: Vec<f32> = (self.heatmap * keep)
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem::<f32>())
+            .collect();
+        let regression: Vec<f32> = self
+            .regression
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem::<f32>())
+            .collect();
+
+        let num_cells = h * w;
+        (0..b)
+            .map(|batch| {
+                let scores = &scores[batch * num_classes * num_cells..][..num_classes * num_cells];
+                let reg = &regression[batch * NUM_REG_OUTPUTS * num_cells..];
+                let reg = |k: usize, cell: usize| reg[k * num_cells + cell];
+
+                scores
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &score)| score >= score_threshold.value())
+                    .sorted_unstable_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap())
+                    .take(max_detections)
+                    .map(|(idx, &score)| {
+                        let (class_id, cell) = (idx / num_cells, idx % num_cells);
+                        let (row, col) = ((cell / w) as f32, (cell % w) as f32);
+
+                        Box3d {
+                            center: [
+                                grid.x_range.0 + (col + reg(0, cell)) * grid.resolution,
+                                grid.y_range.0 + (row + reg(1, cell)) * grid.resolution,
+                                reg(2, cell),
+                            ],
+                            size: [reg(3, cell).exp(), reg(4, cell).exp(), reg(5, cell).exp()],
+                            yaw: reg(6, cell).atan2(reg(7, cell)),
+                            score,
+                            class_id,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Center-based detection head predicting a heatmap of the object centers for each class and the
+/// 3D box parameters at each BEV cell, as in [CenterPoint](https://arxiv.org/abs/2006.11275).
+#[derive(Module, Debug)]
+pub struct BevDetectionHead<B: Backend> {
+    shared: BaseConv<B>,
+    heatmap: Conv2d<B>,
+    regression: Conv2d<B>,
+}
+
+impl<B: Backend> BevDetectionHead<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> BevHeadOutput<B> {
+        let x = self.shared.forward(x);
+
+        BevHeadOutput {
+            heatmap: sigmoid(self.heatmap.forward(x.clone())),
+            regression: self.regression.forward(x),
+        }
+    }
+}
+
+/// [BEV detection head](BevDetectionHead) configuration.
+pub struct BevDetectionHeadConfig {
+    shared: BaseConvConfig,
+    heatmap: Conv2dConfig,
+    regression: Conv2dConfig,
+}
+
+impl BevDetectionHeadConfig {
+    /// Create a new instance of the BEV detection head [config](BevDetectionHeadConfig).
+    pub fn new(in_channels: usize, hidden_channels: usize, num_classes: usize) -> Self {
+        // Initialize the heatmap biases to the prior probability
+        let bias = -f64::ln((1.0 - PRIOR_PROB) / PRIOR_PROB);
+
+        let shared = BaseConvConfig::new(in_channels, hidden_channels, 3, 1, 1);
+        let heatmap = Conv2dConfig::new([hidden_channels, num_classes], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0))
+            .with_initializer(Initializer::Constant { value: bias });
+        let regression = Conv2dConfig::new([hidden_channels, NUM_REG_OUTPUTS], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+
+        Self {
+            shared,
+            heatmap,
+            regression,
+        }
+    }
+
+    /// Initialize a new [BEV detection head](BevDetectionHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> BevDetectionHead<B> {
+        BevDetectionHead {
+            shared: self.shared.init(device),
+            heatmap: self.heatmap.init(device),
+            regression: self.regression.init(device),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use burn::{
+    module::Module,
+    nn::{Linear, LinearConfig},
+    tensor::{activation::relu, backend::Backend, Device, Tensor},
+};
+
+use super::{BevGrid, VoxelPool};
+use crate::model::blocks::{BaseConv, BaseConvConfig};
+
+/// LiDAR stream of [BEVFusion](super::BevFusion): pillar feature extraction, where the point
+/// features are averaged in each BEV cell and refined with a convolution.
+#[derive(Module, Debug)]
+pub struct LidarStreamEncoder<B: Backend> {
+    point_fc: Linear<B>,
+    pool: VoxelPool,
+    conv: BaseConv<B>,
+}
+
+impl<B: Backend> LidarStreamEncoder<B> {
+    /// Encode the LiDAR points into BEV features.
+    ///
+    /// # Shapes
+    ///   - points: `[batch_size, num_points, point_features]`, starting with the `(x, y)`
+    ///     coordinates in the ego frame
+    ///   - output: `[batch_size, out_channels, bev_h, bev_w]`
+    pub fn forward(&self, points: Tensor<B, 3>) -> Tensor<B, 4> {
+        let [b, p, _] = points.dims();
+        let xy = points.clone().slice([0..b, 0..p, 0..2]);
+
+        // Per-point features [B, C, P]
+        let features = relu(self.point_fc.forward(points)).swap_dims(1, 2);
+
+        // Average the point features of each cell
+        let sum = self.pool.forward(features, xy.clone());
+        let count = self
+            .pool
+            .forward(Tensor::ones([b, 1, p], &xy.device()), xy)
+            .clamp_min(1.);
+
+        self.conv.forward(sum / count)
+    }
+}
+
+/// [LiDAR stream encoder](LidarStreamEncoder) configuration.
+pub struct LidarStreamEncoderConfig {
+    point_fc: LinearConfig,
+    conv: BaseConvConfig,
+    grid: BevGrid,
+}
+
+impl LidarStreamEncoderConfig {
+    /// Create a new instance of the LiDAR stream encoder [config](LidarStreamEncoderConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `point_features`: Number of features of each point (e.g., 4 for `(x, y, z, intensity)`).
+    /// * `out_channels` - Number of channels of the BEV features.
+    /// * `grid` - BEV grid.
+    pub fn new(point_features: usize, out_channels: usize, grid: BevGrid) -> Self {
+        assert!(
+            point_features >= 2,
+            "the points should at least have (x, y) coordinates"
+        );
+
+        Self {
+            point_fc: LinearConfig::new(point_features, out_channels),
+            conv: BaseConvConfig::new(out_channels, out_channels, 3, 1, 1),
+            grid,
+        }
+    }
+
+    /// Set the BEV grid.
+    pub fn with_grid(mut self, grid: BevGrid) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Initialize a new [LiDAR stream encoder](LidarStreamEncoder) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> LidarStreamEncoder<B> {
+        LidarStreamEncoder {
+            point_fc: self.point_fc.init(device),
+            pool: VoxelPool::new(&self.grid),
+            conv: self.conv.init(device),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod camera;
+mod fusion;
+mod grid;
+mod head;
+mod lidar;
+
+pub use camera::*;
+pub use fusion::*;
+pub use grid::*;
+pub use head::*;
+pub use lidar::*;
@@ -1,14 +1,150 @@
-use alloc::vec;
+use alloc::{vec, vec::Vec};
 use burn::{
     config::Config,
-    module::Module,
+    module::{Module, RunningState},
     nn::{
         conv::{Conv2d, Conv2dConfig},
-        BatchNorm, BatchNormConfig, PaddingConfig2d,
+        BatchNormConfig, Dropout, DropoutConfig, Gelu, Initializer, Linear, LinearConfig,
+        PaddingConfig2d, Relu,
     },
-    tensor::{activation::silu, backend::Backend, Device, Tensor},
+    tensor::{
+        activation::{relu, sigmoid, silu},
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Distribution, Int, Tensor,
+    },
+};
+
+use super::functional::{hard_sigmoid, hard_swish, mish};
+use super::heads::l2_normalize;
+use super::normalizations::{
+    FreezeBatchNorms, Normalization, SetBatchNormMomentum, SyncBatchNorms,
 };
 
+/// Sigmoid linear unit (SiLU) activation, also known as swish.
+#[derive(Module, Debug, Clone, Default)]
+pub struct Silu {}
+
+impl Silu {
+    pub fn forward<B: Backend, const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        silu(x)
+    }
+}
+
+/// [Mish](https://arxiv.org/abs/1908.08681) activation, as used by the YOLOv4 backbone.
+#[derive(Module, Debug, Clone, Default)]
+pub struct Mish {}
+
+impl Mish {
+    pub fn forward<B: Backend, const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        mish(x)
+    }
+}
+
+/// Sigmoid activation, as used by gating blocks.
+#[derive(Module, Debug, Clone, Default)]
+pub struct Sigmoid {}
+
+impl Sigmoid {
+    pub fn forward<B: Backend, const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        sigmoid(x)
+    }
+}
+
+/// Hard swish activation, the hardware friendly approximation of [SiLU](Silu) used by
+/// [MobileNetV3](https://arxiv.org/abs/1905.02244).
+#[derive(Module, Debug, Clone, Default)]
+pub struct HardSwish {}
+
+impl HardSwish {
+    /// Create a new hard swish activation.
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    pub fn forward<B: Backend, const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        hard_swish(x)
+    }
+}
+
+/// Hard sigmoid activation, the hardware friendly approximation of the sigmoid used by
+/// [MobileNetV3](https://arxiv.org/abs/1905.02244).
+#[derive(Module, Debug, Clone, Default)]
+pub struct HardSigmoid {}
+
+impl HardSigmoid {
+    /// Create a new hard sigmoid activation.
+    pub fn init() -> Self {
+        Self {}
+    }
+
+    pub fn forward<B: Backend, const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        hard_sigmoid(x)
+    }
+}
+
+/// Activation function type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ActivationType {
+    /// Sigmoid linear unit, used by all YOLOX blocks.
+    #[default]
+    Silu,
+    /// Rectified linear unit.
+    Relu,
+    /// Gaussian error linear unit, usually used by transformer blocks.
+    Gelu,
+    /// Mish, used by the YOLOv4 backbone.
+    Mish,
+    /// Hard swish, used by MobileNetV3.
+    HardSwish,
+    /// Hard sigmoid, used by MobileNetV3.
+    HardSigmoid,
+    /// Sigmoid, used by gating blocks.
+    Sigmoid,
+}
+
+impl ActivationType {
+    /// Initialize a new [activation](Activation) module.
+    pub fn init(&self) -> Activation {
+        match self {
+            Self::Silu => Activation::Silu(Silu {}),
+            Self::Relu => Activation::Relu(Relu::new()),
+            Self::Gelu => Activation::Gelu(Gelu::new()),
+            Self::Mish => Activation::Mish(Mish {}),
+            Self::HardSwish => Activation::HardSwish(HardSwish::init()),
+            Self::HardSigmoid => Activation::HardSigmoid(HardSigmoid::init()),
+            Self::Sigmoid => Activation::Sigmoid(Sigmoid {}),
+        }
+    }
+}
+
+/// Activation function module.
+#[derive(Module, Debug, Clone)]
+pub enum Activation {
+    Silu(Silu),
+    Relu(Relu),
+    Gelu(Gelu),
+    Mish(Mish),
+    HardSwish(HardSwish),
+    HardSigmoid(HardSigmoid),
+    Sigmoid(Sigmoid),
+}
+
+impl Activation {
+    pub fn forward<B: Backend, const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        match self {
+            Self::Silu(act) => act.forward(x),
+            Self::Relu(act) => act.forward(x),
+            Self::Gelu(act) => act.forward(x),
+            Self::Mish(act) => act.forward(x),
+            Self::HardSwish(act) => act.forward(x),
+            Self::HardSigmoid(act) => act.forward(x),
+            Self::Sigmoid(act) => act.forward(x),
+        }
+    }
+}
+
 /// Compute the number of channels based on the provided factor.
 pub fn expand(num_channels: usize, factor: f64) -> usize {
     (num_channels as f64 * factor).floor() as usize
@@ -34,6 +170,33 @@ impl<B: Backend> Conv<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for Conv<B> {
+    fn freeze_batch_norms(self) -> Self {
+        match self {
+            Self::BaseConv(conv) => Self::BaseConv(conv.freeze_batch_norms()),
+            Self::DwsConv(conv) => Self::DwsConv(conv.freeze_batch_norms()),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for Conv<B> {
+    fn sync_batch_norms(self) -> Self {
+        match self {
+            Self::BaseConv(conv) => Self::BaseConv(conv.sync_batch_norms()),
+            Self::DwsConv(conv) => Self::DwsConv(conv.sync_batch_norms()),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for Conv<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        match self {
+            Self::BaseConv(m) => m.set_bn_momentum(momentum),
+            Self::DwsConv(m) => m.set_bn_momentum(momentum),
+        }
+    }
+}
+
 #[derive(Config)]
 pub struct ConvConfig {
     in_channels: usize,
@@ -75,7 +238,8 @@ impl ConvConfig {
 #[derive(Module, Debug)]
 pub struct BaseConv<B: Backend> {
     conv: Conv2d<B>,
-    bn: BatchNorm<B, 2>,
+    bn: Normalization<B>,
+    activation: Activation,
 }
 
 impl<B: Backend> BaseConv<B> {
@@ -83,7 +247,31 @@ impl<B: Backend> BaseConv<B> {
         let x = self.conv.forward(x);
         let x = self.bn.forward(x);
 
-        silu(x)
+        self.activation.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for BaseConv<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            bn: self.bn.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for BaseConv<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            bn: self.bn.sync_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for BaseConv<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.bn.set_bn_momentum(momentum);
     }
 }
 
@@ -91,6 +279,7 @@ impl<B: Backend> BaseConv<B> {
 pub struct BaseConvConfig {
     conv: Conv2dConfig,
     bn: BatchNormConfig,
+    activation: ActivationType,
 }
 
 impl BaseConvConfig {
@@ -114,14 +303,25 @@ impl BaseConvConfig {
             .with_epsilon(1e-3)
             .with_momentum(0.03);
 
-        Self { conv, bn }
+        Self {
+            conv,
+            bn,
+            activation: ActivationType::Silu,
+        }
+    }
+
+    /// Set the activation function (defaults to [SiLU](ActivationType::Silu)).
+    pub fn with_activation(mut self, activation: ActivationType) -> Self {
+        self.activation = activation;
+        self
     }
 
     /// Initialize a new [base convolution block](BaseConv) module.
     pub fn init<B: Backend>(&self, device: &Device<B>) -> BaseConv<B> {
         BaseConv {
             conv: self.conv.init(device),
-            bn: self.bn.init(device),
+            bn: Normalization::Batch(self.bn.init(device)),
+            activation: self.activation.init(),
         }
     }
 }
@@ -141,6 +341,31 @@ impl<B: Backend> DwsConv<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for DwsConv<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            dconv: self.dconv.freeze_batch_norms(),
+            pconv: self.pconv.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for DwsConv<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            dconv: self.dconv.sync_batch_norms(),
+            pconv: self.pconv.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for DwsConv<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.dconv.set_bn_momentum(momentum);
+        self.pconv.set_bn_momentum(momentum);
+    }
+}
+
 /// [Depthwise separable convolution block](DwsConv) configuration.
 pub struct DwsConvConfig {
     dconv: BaseConvConfig,
@@ -211,6 +436,28 @@ impl<B: Backend> Focus<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for Focus<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv: self.conv.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for Focus<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv: self.conv.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for Focus<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv.set_bn_momentum(momentum);
+    }
+}
+
 /// [Focus block](Focus) configuration.
 pub struct FocusConfig {
     conv: BaseConvConfig,
@@ -232,6 +479,173 @@ impl FocusConfig {
     }
 }
 
+/// Strided convolution alternative to the [focus block](Focus).
+///
+/// Slicing the input into 4 patches followed by a `k x k` convolution is equivalent to a single
+/// `2k x 2k` convolution with stride 2 on the original input, which avoids the costly indexing
+/// operations on some hardware.
+#[derive(Module, Debug)]
+pub struct FocusFree<B: Backend> {
+    conv: BaseConv<B>,
+}
+
+impl<B: Backend> FocusFree<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.conv.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for FocusFree<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv: self.conv.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for FocusFree<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv: self.conv.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for FocusFree<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv.set_bn_momentum(momentum);
+    }
+}
+
+/// [Focus-free block](FocusFree) configuration.
+pub struct FocusFreeConfig {
+    conv: BaseConvConfig,
+}
+
+impl FocusFreeConfig {
+    /// Create a new instance of the focus-free block [config](FocusFreeConfig).
+    ///
+    /// # Panics
+    ///
+    /// If the kernel size is smaller than 2, which has no focus block equivalent.
+    pub fn new(in_channels: usize, out_channels: usize, kernel_size: usize) -> Self {
+        assert!(
+            kernel_size >= 2,
+            "the kernel size should be at least 2, got {kernel_size}"
+        );
+        let mut conv = BaseConvConfig::new(in_channels, out_channels, kernel_size, 2, 1);
+        // Padding of the equivalent focus block convolution, scaled to the input resolution
+        let pad = kernel_size / 2 - 1;
+        conv.conv.padding = PaddingConfig2d::Explicit(pad, pad);
+
+        Self { conv }
+    }
+
+    /// Create a new instance of the focus-free block [config](FocusFreeConfig) with the same
+    /// receptive field and output shape as the [focus block](Focus).
+    ///
+    /// For example, the YOLOX stem `Focus(3, 3)` is equivalent to a `6 x 6` convolution with
+    /// stride 2 and padding 2.
+    pub fn equivalent_to(focus: &FocusConfig) -> Self {
+        let [in_channels, out_channels] = focus.conv.conv.channels;
+        let [kernel_size, _] = focus.conv.conv.kernel_size;
+        assert_eq!(
+            focus.conv.conv.stride,
+            [1, 1],
+            "only focus blocks with stride 1 have a focus-free equivalent"
+        );
+
+        Self::new(in_channels / 4, out_channels, kernel_size * 2)
+    }
+
+    /// Initialize a new [focus-free block](FocusFree) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> FocusFree<B> {
+        FocusFree {
+            conv: self.conv.init(device),
+        }
+    }
+}
+
+/// Deep stem of [ResNet-D](https://arxiv.org/abs/1812.01187).
+///
+/// The `7x7` stem convolution is replaced by three `3x3` convolutions (the first one with stride
+/// 2), which has a similar receptive field at a lower cost.
+#[derive(Module, Debug)]
+pub struct ResNetDStem<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: BaseConv<B>,
+    conv3: BaseConv<B>,
+}
+
+impl<B: Backend> ResNetDStem<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.conv1.forward(x);
+        let x = self.conv2.forward(x);
+
+        self.conv3.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for ResNetDStem<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            conv3: self.conv3.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for ResNetDStem<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            conv3: self.conv3.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for ResNetDStem<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+        self.conv3.set_bn_momentum(momentum);
+    }
+}
+
+/// [ResNet-D stem](ResNetDStem) configuration.
+pub struct ResNetDStemConfig {
+    conv1: BaseConvConfig,
+    conv2: BaseConvConfig,
+    conv3: BaseConvConfig,
+}
+
+impl ResNetDStemConfig {
+    /// Create a new instance of the ResNet-D stem [config](ResNetDStemConfig).
+    ///
+    /// The first two convolutions have `out_channels / 2` channels (e.g., `[32, 32, 64]` for the
+    /// 64 output channels of ResNet).
+    pub fn new(in_channels: usize, out_channels: usize) -> Self {
+        let hidden_channels = out_channels / 2;
+
+        Self {
+            conv1: BaseConvConfig::new(in_channels, hidden_channels, 3, 2, 1),
+            conv2: BaseConvConfig::new(hidden_channels, hidden_channels, 3, 1, 1),
+            conv3: BaseConvConfig::new(hidden_channels, out_channels, 3, 1, 1),
+        }
+    }
+
+    /// Initialize a new [ResNet-D stem](ResNetDStem) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ResNetDStem<B> {
+        ResNetDStem {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+            conv3: self.conv3.init(device),
+        }
+    }
+}
+
 /// Dual convolution block used for feature extraction in the prediction head.
 #[derive(Module, Debug)]
 pub struct ConvBlock<B: Backend> {
@@ -246,6 +660,31 @@ impl<B: Backend> ConvBlock<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for ConvBlock<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv0: self.conv0.freeze_batch_norms(),
+            conv1: self.conv1.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for ConvBlock<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv0: self.conv0.sync_batch_norms(),
+            conv1: self.conv1.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for ConvBlock<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv0.set_bn_momentum(momentum);
+        self.conv1.set_bn_momentum(momentum);
+    }
+}
+
 /// [Dual convolution block](ConvBlock) configuration.
 pub struct ConvBlockConfig {
     conv0: ConvConfig,
@@ -269,3 +708,1380 @@ impl ConvBlockConfig {
         }
     }
 }
+
+/// Residual connection adding a shortcut to the output of a block.
+///
+/// The shortcut is projected with a `1x1` convolution and batch normalization when the number of
+/// channels or the resolution of the block output differs from its input, and is the identity
+/// otherwise.
+#[derive(Module, Debug)]
+pub struct ResidualConnection<B: Backend> {
+    projection: Option<ResidualProjection<B>>,
+}
+
+impl<B: Backend> ResidualConnection<B> {
+    /// Add the (projected) shortcut to the output of the main branch.
+    ///
+    /// # Shapes
+    ///   - shortcut: `[batch_size, in_channels, height, width]`
+    ///   - main: `[batch_size, out_channels, height / stride, width / stride]`
+    ///   - output: `[batch_size, out_channels, height / stride, width / stride]`
+    pub fn apply(&self, shortcut: Tensor<B, 4>, main: Tensor<B, 4>) -> Tensor<B, 4> {
+        match &self.projection {
+            Some(projection) => projection.forward(shortcut) + main,
+            None => shortcut + main,
+        }
+    }
+
+    /// Whether the shortcut is the identity.
+    pub fn is_identity(&self) -> bool {
+        self.projection.is_none()
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for ResidualConnection<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            projection: self.projection.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for ResidualConnection<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            projection: self.projection.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for ResidualConnection<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.projection.set_bn_momentum(momentum);
+    }
+}
+
+/// A 1x1 Conv2d -> BatchNorm projection of a [residual connection](ResidualConnection).
+#[derive(Module, Debug)]
+pub struct ResidualProjection<B: Backend> {
+    conv: Conv2d<B>,
+    bn: Normalization<B>,
+}
+
+impl<B: Backend> ResidualProjection<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.bn.forward(self.conv.forward(x))
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for ResidualProjection<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            bn: self.bn.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for ResidualProjection<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            bn: self.bn.sync_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for ResidualProjection<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.bn.set_bn_momentum(momentum);
+    }
+}
+
+/// [Residual connection](ResidualConnection) configuration.
+pub struct ResidualConnectionConfig {
+    in_channels: usize,
+    out_channels: usize,
+    stride: usize,
+}
+
+impl ResidualConnectionConfig {
+    /// Create a new instance of the residual connection [config](ResidualConnectionConfig).
+    pub fn new(in_channels: usize, out_channels: usize, stride: usize) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+            stride,
+        }
+    }
+
+    /// Initialize a new [residual connection](ResidualConnection) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ResidualConnection<B> {
+        let projection = if self.in_channels == self.out_channels && self.stride == 1 {
+            None
+        } else {
+            let conv = Conv2dConfig::new([self.in_channels, self.out_channels], [1, 1])
+                .with_stride([self.stride, self.stride])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+                .with_bias(false);
+            let bn = BatchNormConfig::new(self.out_channels);
+
+            Some(ResidualProjection {
+                conv: conv.init(device),
+                bn: Normalization::Batch(bn.init(device)),
+            })
+        };
+
+        ResidualConnection { projection }
+    }
+}
+
+/// [Squeeze-and-excitation](https://arxiv.org/abs/1709.01507) block, which recalibrates the
+/// channels with a gating computed from the globally pooled features.
+#[derive(Module, Debug)]
+pub struct SeBlock<B: Backend> {
+    reduce: Conv2d<B>,
+    expand: Conv2d<B>,
+    activation: Relu,
+}
+
+impl<B: Backend> SeBlock<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [b, c, _, _] = x.dims();
+
+        // Squeeze [B, C, 1, 1]
+        let scale = x
+            .clone()
+            .flatten::<3>(2, 3)
+            .mean_dim(2)
+            .reshape([b, c, 1, 1]);
+
+        // Excitation
+        let scale = self.activation.forward(self.reduce.forward(scale));
+        let scale = sigmoid(self.expand.forward(scale));
+
+        x * scale
+    }
+}
+
+/// [Squeeze-and-excitation block](SeBlock) configuration.
+pub struct SeBlockConfig {
+    reduce: Conv2dConfig,
+    expand: Conv2dConfig,
+}
+
+impl SeBlockConfig {
+    /// Create a new instance of the squeeze-and-excitation block [config](SeBlockConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `channels`: Number of input and output channels.
+    /// * `reduction` - Reduction ratio of the number of channels for the gating.
+    pub fn new(channels: usize, reduction: usize) -> Self {
+        let hidden_channels = (channels / reduction).max(1);
+
+        let reduce = Conv2dConfig::new([channels, hidden_channels], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+        let expand = Conv2dConfig::new([hidden_channels, channels], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+
+        Self { reduce, expand }
+    }
+
+    /// Initialize a new [squeeze-and-excitation block](SeBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> SeBlock<B> {
+        SeBlock {
+            reduce: self.reduce.init(device),
+            expand: self.expand.init(device),
+            activation: Relu::new(),
+        }
+    }
+}
+
+/// Additive [attention gate](https://arxiv.org/abs/1804.03999) of Attention U-Net.
+///
+/// The gating signal `g` of the decoder selects the relevant regions of the encoder features `x`
+/// of the skip connection: the attention mask is `sigmoid(psi(relu(W_g(g) + W_x(x))))`, where
+/// `W_g`, `W_x` and `psi` are `1x1` convolutions, and the gated features are `x * mask`. The
+/// gating signal is resized to the resolution of the encoder features.
+#[derive(Module, Debug)]
+pub struct AttentionGate<B: Backend> {
+    w_g: Conv2d<B>,
+    w_x: Conv2d<B>,
+    psi: Conv2d<B>,
+}
+
+impl<B: Backend> AttentionGate<B> {
+    /// Gate the encoder features.
+    ///
+    /// # Shapes
+    ///   - g: `[batch_size, gate_channels, height_g, width_g]`
+    ///   - x: `[batch_size, query_channels, height, width]`
+    ///   - output: `[batch_size, query_channels, height, width]`
+    pub fn forward(&self, g: Tensor<B, 4>, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let mask = self.attention_mask(g, x.clone());
+        x * mask
+    }
+
+    /// Attention mask in the range `[0, 1]` of each location of the encoder features.
+    ///
+    /// # Shapes
+    ///   - g: `[batch_size, gate_channels, height_g, width_g]`
+    ///   - x: `[batch_size, query_channels, height, width]`
+    ///   - output: `[batch_size, 1, height, width]`
+    pub fn attention_mask(&self, g: Tensor<B, 4>, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [_, _, h, w] = x.dims();
+
+        let g = self.w_g.forward(g);
+        let [_, _, h_g, w_g] = g.dims();
+        let g = if [h_g, w_g] != [h, w] {
+            interpolate(
+                g,
+                [h, w],
+                InterpolateOptions::new(InterpolateMode::Bilinear),
+            )
+        } else {
+            g
+        };
+
+        sigmoid(self.psi.forward(relu(g + self.w_x.forward(x))))
+    }
+}
+
+/// [Attention gate](AttentionGate) configuration.
+pub struct AttentionGateConfig {
+    w_g: Conv2dConfig,
+    w_x: Conv2dConfig,
+    psi: Conv2dConfig,
+}
+
+impl AttentionGateConfig {
+    /// Create a new instance of the attention gate [config](AttentionGateConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `gate_channels`: Number of channels of the gating signal (decoder features).
+    /// * `query_channels` - Number of channels of the gated encoder features.
+    /// * `inter_channels` - Number of channels of the intermediate representation.
+    pub fn new(gate_channels: usize, query_channels: usize, inter_channels: usize) -> Self {
+        let pointwise = |in_channels, out_channels| {
+            Conv2dConfig::new([in_channels, out_channels], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+        };
+
+        Self {
+            w_g: pointwise(gate_channels, inter_channels),
+            w_x: pointwise(query_channels, inter_channels).with_bias(false),
+            psi: pointwise(inter_channels, 1),
+        }
+    }
+
+    /// Initialize a new [attention gate](AttentionGate) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> AttentionGate<B> {
+        AttentionGate {
+            w_g: self.w_g.init(device),
+            w_x: self.w_x.init(device),
+            psi: self.psi.init(device),
+        }
+    }
+}
+
+/// Patch embedding that splits an image into patches and projects them to the embedding
+/// dimension, as used by vision transformers.
+#[derive(Module, Debug)]
+pub struct PatchEmbedding<B: Backend> {
+    proj: Conv2d<B>,
+}
+
+impl<B: Backend> PatchEmbedding<B> {
+    /// Embed the image patches.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, num_patches, embed_dim]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 3> {
+        let x = self.proj.forward(x);
+
+        // [B, C, H, W] -> [B, H * W, C]
+        x.flatten::<3>(2, 3).swap_dims(1, 2)
+    }
+}
+
+/// [Patch embedding](PatchEmbedding) configuration.
+pub struct PatchEmbeddingConfig {
+    image_size: (usize, usize),
+    patch_size: (usize, usize),
+    overlap: bool,
+    proj: Conv2dConfig,
+}
+
+impl PatchEmbeddingConfig {
+    /// Create a new instance of the patch embedding [config](PatchEmbeddingConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `image_size`: Input image `(height, width)`.
+    /// * `patch_size` - Patch `(height, width)`, which is also the stride of the projection.
+    /// * `in_channels` - Number of input image channels.
+    /// * `embed_dim` - Embedding dimension.
+    /// * `overlap` - Use overlapping patches with a `2p - 1` kernel (as in
+    ///   [SegFormer](https://arxiv.org/abs/2105.15203)) instead of non-overlapping `p` kernels.
+    pub fn new(
+        image_size: (usize, usize),
+        patch_size: (usize, usize),
+        in_channels: usize,
+        embed_dim: usize,
+        overlap: bool,
+    ) -> Self {
+        let (ph, pw) = patch_size;
+        let proj = if overlap {
+            let kernel_size = [2 * ph - 1, 2 * pw - 1];
+            Conv2dConfig::new([in_channels, embed_dim], kernel_size)
+                .with_stride([ph, pw])
+                .with_padding(PaddingConfig2d::Explicit(ph - 1, pw - 1))
+        } else {
+            Conv2dConfig::new([in_channels, embed_dim], [ph, pw])
+                .with_stride([ph, pw])
+                .with_padding(PaddingConfig2d::Valid)
+        };
+
+        Self {
+            image_size,
+            patch_size,
+            overlap,
+            proj,
+        }
+    }
+
+    /// Number of patches (i.e., output tokens) for the configured image size.
+    pub fn num_patches(&self) -> usize {
+        let (h, w) = self.image_size;
+        let (ph, pw) = self.patch_size;
+
+        if self.overlap {
+            h.div_ceil(ph) * w.div_ceil(pw)
+        } else {
+            (h / ph) * (w / pw)
+        }
+    }
+
+    /// Initialize a new [patch embedding](PatchEmbedding) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> PatchEmbedding<B> {
+        PatchEmbedding {
+            proj: self.proj.init(device),
+        }
+    }
+}
+
+/// Multi-layer perceptron block: Linear -> activation -> dropout -> Linear -> dropout.
+///
+/// Used as the feed-forward network of transformer blocks.
+#[derive(Module, Debug)]
+pub struct MlpBlock<B: Backend> {
+    fc1: Linear<B>,
+    fc2: Linear<B>,
+    activation: Activation,
+    dropout: Dropout,
+}
+
+impl<B: Backend> MlpBlock<B> {
+    /// Apply the MLP to the last dimension of the input (e.g., `[batch_size, seq_length, in_dim]`).
+    ///
+    /// Dropout is only applied during training (i.e., with an autodiff backend).
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let x = self.fc1.forward(x);
+        let x = self.activation.forward(x);
+        let x = self.dropout.forward(x);
+        let x = self.fc2.forward(x);
+
+        self.dropout.forward(x)
+    }
+}
+
+/// [MLP block](MlpBlock) configuration.
+pub struct MlpBlockConfig {
+    fc1: LinearConfig,
+    fc2: LinearConfig,
+    activation: ActivationType,
+    dropout: DropoutConfig,
+}
+
+impl MlpBlockConfig {
+    /// Create a new instance of the MLP block [config](MlpBlockConfig).
+    pub fn new(
+        in_dim: usize,
+        hidden_dim: usize,
+        out_dim: usize,
+        dropout: f64,
+        activation: ActivationType,
+    ) -> Self {
+        Self {
+            fc1: LinearConfig::new(in_dim, hidden_dim),
+            fc2: LinearConfig::new(hidden_dim, out_dim),
+            activation,
+            dropout: DropoutConfig::new(dropout),
+        }
+    }
+
+    /// Create a new instance of the MLP block [config](MlpBlockConfig) with the same input and
+    /// output dimensions, as used for the transformer feed-forward networks.
+    pub fn ffn(in_dim: usize, hidden_dim: usize, dropout: f64, activation: ActivationType) -> Self {
+        Self::new(in_dim, hidden_dim, in_dim, dropout, activation)
+    }
+
+    /// Initialize a new [MLP block](MlpBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> MlpBlock<B> {
+        MlpBlock {
+            fc1: self.fc1.init(device),
+            fc2: self.fc2.init(device),
+            activation: self.activation.init(),
+            dropout: self.dropout.init(),
+        }
+    }
+}
+
+/// Classification head: an [MLP block](MlpBlock) followed by the final linear classifier.
+#[derive(Module, Debug)]
+pub struct ClassifierHead<B: Backend> {
+    mlp: MlpBlock<B>,
+    fc: Linear<B>,
+}
+
+impl<B: Backend> ClassifierHead<B> {
+    /// Compute the class logits from the pooled features (e.g., the class token of a vision
+    /// transformer).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_dim]`
+    ///   - output: `[batch_size, num_classes]`
+    pub fn forward(&self, x: Tensor<B, 2>) -> Tensor<B, 2> {
+        let x = self.mlp.forward(x);
+        self.fc.forward(x)
+    }
+}
+
+/// [Classification head](ClassifierHead) configuration.
+pub struct ClassifierHeadConfig {
+    mlp: MlpBlockConfig,
+    fc: LinearConfig,
+}
+
+impl ClassifierHeadConfig {
+    /// Create a new instance of the classification head [config](ClassifierHeadConfig).
+    pub fn new(in_dim: usize, hidden_dim: usize, num_classes: usize, dropout: f64) -> Self {
+        Self {
+            mlp: MlpBlockConfig::ffn(in_dim, hidden_dim, dropout, ActivationType::Gelu),
+            fc: LinearConfig::new(in_dim, num_classes),
+        }
+    }
+
+    /// Initialize a new [classification head](ClassifierHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ClassifierHead<B> {
+        ClassifierHead {
+            mlp: self.mlp.init(device),
+            fc: self.fc.init(device),
+        }
+    }
+}
+
+/// Modulated [deformable convolution](https://arxiv.org/abs/1811.11168) (DCNv2).
+///
+/// A regular convolution predicts an offset and a modulation scalar for each sampling point of
+/// the kernel. The input is bilinearly sampled at the shifted locations, weighted by the
+/// modulation scalars and projected to the output channels.
+///
+/// With multiple deformable groups, the input channels are split into groups which are sampled
+/// with their own offsets and modulation scalars.
+#[derive(Module, Debug)]
+pub struct DeformConv2d<B: Backend> {
+    /// Offsets and modulation scalars prediction (`3 * kernel_size^2 * deformable_groups`
+    /// channels).
+    offset: Conv2d<B>,
+    /// Projection of the sampled values (equivalent to the deformable convolution kernel).
+    proj: Conv2d<B>,
+    kernel_size: usize,
+    deformable_groups: usize,
+}
+
+impl<B: Backend> DeformConv2d<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, out_channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.forward_guided(x.clone(), x)
+    }
+
+    /// Deformable convolution of `x`, with the offsets and modulation scalars predicted from the
+    /// `guide` feature map instead of the input (e.g., to align features from another scale).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - guide: `[batch_size, guide_channels, height, width]`
+    ///   - output: `[batch_size, out_channels, height, width]`
+    pub fn forward_guided(&self, x: Tensor<B, 4>, guide: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, _, h, w] = x.dims();
+        let k = self.kernel_size * self.kernel_size;
+        let g = self.deformable_groups;
+
+        let out = self.offset.forward(guide);
+        let offsets = out.clone().slice([0..n, 0..2 * k * g, 0..h, 0..w]);
+        let masks = sigmoid(out.slice([0..n, 2 * k * g..3 * k * g, 0..h, 0..w]));
+
+        let samples = deform_sample(x, offsets, Some(masks), self.kernel_size, g);
+        self.proj.forward(samples)
+    }
+
+    /// Predicted sampling offsets `(dy, dx)` of each kernel point and deformable group.
+    ///
+    /// # Shapes
+    ///   - guide: `[batch_size, guide_channels, height, width]`
+    ///   - output: `[batch_size, 2 * kernel_size^2 * deformable_groups, height, width]`
+    pub fn offsets(&self, guide: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, _, h, w] = guide.dims();
+        let k = self.kernel_size * self.kernel_size;
+
+        self.offset
+            .forward(guide)
+            .slice([0..n, 0..2 * k * self.deformable_groups, 0..h, 0..w])
+    }
+}
+
+/// Sample the input at the shifted locations of each kernel point of a deformable convolution.
+///
+/// The samples are ordered by kernel point then channel, so that a `1x1` convolution of the
+/// samples is equivalent to the deformable convolution kernel. Without modulation masks, zero
+/// offsets sample the regular convolution grid.
+///
+/// # Shapes
+///   - x: `[batch_size, channels, height, width]`
+///   - offsets: `[batch_size, 2 * kernel_size^2 * groups, height, width]`, the `(dy, dx)` offsets
+///     of each kernel point, for each group
+///   - masks: `[batch_size, kernel_size^2 * groups, height, width]`
+///   - output: `[batch_size, kernel_size^2 * channels, height, width]`
+pub(crate) fn deform_sample<B: Backend>(
+    x: Tensor<B, 4>,
+    offsets: Tensor<B, 4>,
+    masks: Option<Tensor<B, 4>>,
+    kernel_size: usize,
+    groups: usize,
+) -> Tensor<B, 4> {
+    let device = x.device();
+    let [n, c, h, w] = x.dims();
+    let ks = kernel_size;
+    let k = ks * ks;
+    let g = groups;
+    let pad = (ks / 2) as f32;
+
+    let offsets = offsets.reshape([n * g, 2 * k, h, w]);
+    let masks = masks.map(|masks| masks.reshape([n * g, k, h, w]));
+
+    // Sampling grid
+    let ys = Tensor::<B, 1, Int>::arange(0..h as i64, &device)
+        .float()
+        .reshape([1, 1, h, 1])
+        .repeat_dim(3, w);
+    let xs = Tensor::<B, 1, Int>::arange(0..w as i64, &device)
+        .float()
+        .reshape([1, 1, 1, w])
+        .repeat_dim(2, h);
+
+    let m = n * g;
+    let flat = x.reshape([m, c / g, h * w]);
+    let samples: Vec<_> = (0..k)
+        .map(|i| {
+            let ky = (i / ks) as f32 - pad;
+            let kx = (i % ks) as f32 - pad;
+            let dy = offsets.clone().slice([0..m, 2 * i..2 * i + 1, 0..h, 0..w]);
+            let dx = offsets
+                .clone()
+                .slice([0..m, 2 * i + 1..2 * i + 2, 0..h, 0..w]);
+
+            let py = ys.clone().add_scalar(ky) + dy;
+            let px = xs.clone().add_scalar(kx) + dx;
+
+            let sample = bilinear_sample(flat.clone(), py, px, [h, w]);
+            match &masks {
+                Some(masks) => sample * masks.clone().slice([0..m, i..i + 1, 0..h, 0..w]),
+                None => sample,
+            }
+        })
+        .collect();
+
+    // [N * G, K * C / G, H, W] -> [N, K * C, H, W], ordered by kernel point then channel
+    Tensor::cat(samples, 1)
+        .reshape([n, g, k, (c / g) * h * w])
+        .swap_dims(1, 2)
+        .reshape([n, k * c, h, w])
+}
+
+/// Bilinearly sample the flattened feature map `[N, C, H * W]` at the (fractional) locations
+/// `[N, 1, H, W]`. Locations outside of the feature map are sampled as zeros.
+fn bilinear_sample<B: Backend>(
+    flat: Tensor<B, 3>,
+    py: Tensor<B, 4>,
+    px: Tensor<B, 4>,
+    size: [usize; 2],
+) -> Tensor<B, 4> {
+    let [n, c, _] = flat.dims();
+    let [h, w] = size;
+
+    // Floor through the truncation of positive values
+    let shift = (2 * h.max(w)) as f32;
+    let floor = |t: Tensor<B, 4>| t.add_scalar(shift).int().float().sub_scalar(shift);
+    let y0 = floor(py.clone());
+    let x0 = floor(px.clone());
+    let wy1 = py - y0.clone();
+    let wx1 = px - x0.clone();
+
+    let mut out: Option<Tensor<B, 4>> = None;
+    for (dy, dx) in [(0., 0.), (0., 1.), (1., 0.), (1., 1.)] {
+        let yy = y0.clone().add_scalar(dy);
+        let xx = x0.clone().add_scalar(dx);
+        let valid = yy.clone().greater_equal_elem(0.).float()
+            * yy.clone().lower_equal_elem((h - 1) as f32).float()
+            * xx.clone().greater_equal_elem(0.).float()
+            * xx.clone().lower_equal_elem((w - 1) as f32).float();
+        let wy = if dy == 0. {
+            wy1.clone().neg().add_scalar(1.)
+        } else {
+            wy1.clone()
+        };
+        let wx = if dx == 0. {
+            wx1.clone().neg().add_scalar(1.)
+        } else {
+            wx1.clone()
+        };
+
+        let idx = (yy.clamp(0., (h - 1) as f32).mul_scalar(w as f32)
+            + xx.clamp(0., (w - 1) as f32))
+        .int()
+        .reshape([n, 1, h * w])
+        .repeat_dim(1, c);
+        let values = flat.clone().gather(2, idx).reshape([n, c, h, w]);
+
+        let corner = values * (wy * wx * valid);
+        out = Some(match out {
+            Some(out) => out + corner,
+            None => corner,
+        });
+    }
+
+    out.unwrap()
+}
+
+/// Bilinearly sample the flattened feature map `[M, C, H * W]` at the (fractional) pixel
+/// locations `[M, P]`. Locations outside of the feature map are sampled as zeros.
+///
+/// Returns the sampled features with shape `[M, C, P]`.
+pub(crate) fn bilinear_sample_points<B: Backend>(
+    flat: Tensor<B, 3>,
+    py: Tensor<B, 2>,
+    px: Tensor<B, 2>,
+    [h, w]: [usize; 2],
+) -> Tensor<B, 3> {
+    let [m, c, _] = flat.dims();
+    let [_, p] = py.dims();
+
+    // Locations further outside of the feature map are sampled as zeros all the same
+    let py = py.clamp(-2., h as f32 + 1.);
+    let px = px.clamp(-2., w as f32 + 1.);
+
+    // Floor through the truncation of positive values
+    let floor = |t: Tensor<B, 2>| t.add_scalar(4.).int().float().sub_scalar(4.);
+    let y0 = floor(py.clone());
+    let x0 = floor(px.clone());
+    let wy1 = py - y0.clone();
+    let wx1 = px - x0.clone();
+
+    let mut out: Option<Tensor<B, 3>> = None;
+    for (dy, dx) in [(0., 0.), (0., 1.), (1., 0.), (1., 1.)] {
+        let yy = y0.clone().add_scalar(dy);
+        let xx = x0.clone().add_scalar(dx);
+        let valid = yy.clone().greater_equal_elem(0.).float()
+            * yy.clone().lower_equal_elem((h - 1) as f32).float()
+            * xx.clone().greater_equal_elem(0.).float()
+            * xx.clone().lower_equal_elem((w - 1) as f32).float();
+        let wy = if dy == 0. {
+            wy1.clone().neg().add_scalar(1.)
+        } else {
+            wy1.clone()
+        };
+        let wx = if dx == 0. {
+            wx1.clone().neg().add_scalar(1.)
+        } else {
+            wx1.clone()
+        };
+
+        let idx = (yy.clamp(0., (h - 1) as f32).mul_scalar(w as f32)
+            + xx.clamp(0., (w - 1) as f32))
+        .int()
+        .reshape([m, 1, p])
+        .repeat_dim(1, c);
+        let values = flat.clone().gather(2, idx);
+
+        let corner = values * (wy * wx * valid).reshape([m, 1, p]);
+        out = Some(match out {
+            Some(out) => out + corner,
+            None => corner,
+        });
+    }
+
+    out.unwrap()
+}
+
+/// [Deformable convolution](DeformConv2d) configuration.
+pub struct DeformConv2dConfig {
+    in_channels: usize,
+    guide_channels: usize,
+    deformable_groups: usize,
+    offset_initializer: Initializer,
+    proj: Conv2dConfig,
+    kernel_size: usize,
+}
+
+impl DeformConv2dConfig {
+    /// Create a new instance of the deformable convolution [config](DeformConv2dConfig).
+    pub fn new(in_channels: usize, out_channels: usize, kernel_size: usize) -> Self {
+        let k = kernel_size * kernel_size;
+        let proj = Conv2dConfig::new([in_channels * k, out_channels], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+
+        // Zero offsets and 0.5 modulation at initialization
+        Self {
+            in_channels,
+            guide_channels: in_channels,
+            deformable_groups: 1,
+            offset_initializer: Initializer::Zeros,
+            proj,
+            kernel_size,
+        }
+    }
+
+    /// Set the number of channels of the feature map the offsets are predicted from (see
+    /// [forward_guided](DeformConv2d::forward_guided)), which defaults to the input channels.
+    pub fn with_guide_channels(mut self, guide_channels: usize) -> Self {
+        self.guide_channels = guide_channels;
+        self
+    }
+
+    /// Set the number of deformable groups (defaults to 1), which should divide the number of
+    /// input channels.
+    pub fn with_deformable_groups(mut self, deformable_groups: usize) -> Self {
+        assert!(
+            deformable_groups > 0 && self.in_channels % deformable_groups == 0,
+            "the number of deformable groups should divide the number of input channels"
+        );
+        self.deformable_groups = deformable_groups;
+        self
+    }
+
+    /// Set the initializer of the offset prediction (defaults to zeros, i.e., a regular
+    /// convolution at initialization).
+    pub fn with_offset_initializer(mut self, initializer: Initializer) -> Self {
+        self.offset_initializer = initializer;
+        self
+    }
+
+    /// Initialize a new [deformable convolution](DeformConv2d) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DeformConv2d<B> {
+        let ks = self.kernel_size;
+        let pad = ks / 2;
+        let offset_channels = 3 * ks * ks * self.deformable_groups;
+
+        DeformConv2d {
+            offset: Conv2dConfig::new([self.guide_channels, offset_channels], [ks, ks])
+                .with_padding(PaddingConfig2d::Explicit(pad, pad))
+                .with_initializer(self.offset_initializer.clone())
+                .init(device),
+            proj: self.proj.init(device),
+            kernel_size: ks,
+            deformable_groups: self.deformable_groups,
+        }
+    }
+}
+
+/// Number of power iterations used to estimate the spectral norm when wrapping a layer, so that
+/// the normalized weight has a spectral norm close to 1 from the first step.
+const INIT_POWER_ITERATIONS: usize = 20;
+
+/// Estimate the largest singular value of the weight matrix `[rows, cols]` by power iteration,
+/// starting from the left singular vector estimate `u` with shape `[1, rows]`.
+///
+/// Returns the updated left and right singular vector estimates, with shapes `[1, rows]` and
+/// `[1, cols]`.
+fn power_iteration<B: Backend>(
+    weight: Tensor<B, 2>,
+    u: Tensor<B, 2>,
+    num_iterations: usize,
+) -> (Tensor<B, 2>, Tensor<B, 2>) {
+    let mut u = u;
+    let mut v = l2_normalize(u.clone().matmul(weight.clone()));
+    for _ in 0..num_iterations {
+        u = l2_normalize(v.matmul(weight.clone().transpose()));
+        v = l2_normalize(u.clone().matmul(weight.clone()));
+    }
+
+    (u, v)
+}
+
+/// Spectral norm `u^T W v` of the weight matrix `[rows, cols]`. Shape: `[1]`.
+fn spectral_norm_estimate<B: Backend>(
+    weight: Tensor<B, 2>,
+    u: Tensor<B, 2>,
+    v: Tensor<B, 2>,
+) -> Tensor<B, 1> {
+    (u.matmul(weight) * v).sum()
+}
+
+/// Random unit vector with shape `[1, size]`, used to initialize the power iteration.
+fn random_unit_vector<B: Backend>(size: usize, device: &Device<B>) -> Tensor<B, 2> {
+    l2_normalize(Tensor::random(
+        [1, size],
+        Distribution::Normal(0., 1.),
+        device,
+    ))
+}
+
+/// Linear layer with [spectral normalization](https://arxiv.org/abs/1802.05957).
+///
+/// The weight is divided by its largest singular value, estimated with one step of power
+/// iteration per training forward pass. The singular vector estimate is stored as a buffer (not a
+/// trainable parameter) and is only updated when autodiff is enabled.
+#[derive(Module, Debug)]
+pub struct SpectralNormLinear<B: Backend> {
+    linear: Linear<B>,
+    u: RunningState<Tensor<B, 2>>,
+}
+
+impl<B: Backend> SpectralNormLinear<B> {
+    /// Weight as a `[d_output, d_input]` matrix.
+    fn weight_matrix(&self) -> Tensor<B, 2> {
+        self.linear.weight.val().transpose()
+    }
+
+    /// Current spectral norm of the weight. Shape: `[1]`.
+    fn sigma(&self) -> Tensor<B, 1> {
+        let weight = self.weight_matrix();
+        let (u, v) = power_iteration(weight.clone().detach(), self.u.value(), 1);
+        if B::ad_enabled() {
+            self.u.update(u.clone());
+        }
+
+        spectral_norm_estimate(weight, u, v)
+    }
+
+    /// # Shapes
+    ///   - input: `[..., d_input]`
+    ///   - output: `[..., d_output]`
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let sigma = self.sigma().reshape([1; D]);
+        let x = self.linear.forward(x);
+
+        // (x W + b) / sigma would also scale the bias, which is not normalized
+        match &self.linear.bias {
+            Some(bias) => {
+                let bias = bias.val().unsqueeze::<D>();
+                (x - bias.clone()) / sigma + bias
+            }
+            None => x / sigma,
+        }
+    }
+}
+
+/// Wrap a [linear layer](Linear) with [spectral normalization](SpectralNormLinear).
+pub fn spectral_norm<B: Backend>(linear: Linear<B>) -> SpectralNormLinear<B> {
+    let weight = linear.weight.val().transpose().detach();
+    let [d_output, _] = weight.dims();
+    let u = random_unit_vector(d_output, &weight.device());
+    let (u, _) = power_iteration(weight, u, INIT_POWER_ITERATIONS);
+
+    SpectralNormLinear {
+        linear,
+        u: RunningState::new(u),
+    }
+}
+
+/// 2D convolution with [spectral normalization](https://arxiv.org/abs/1802.05957).
+///
+/// The kernel is reshaped to a `[out_channels, in_channels * kernel_height * kernel_width]`
+/// matrix to estimate its spectral norm, as for [SpectralNormLinear].
+#[derive(Module, Debug)]
+pub struct SpectralNormConv2d<B: Backend> {
+    conv: Conv2d<B>,
+    u: RunningState<Tensor<B, 2>>,
+}
+
+impl<B: Backend> SpectralNormConv2d<B> {
+    /// Kernel as a `[out_channels, in_channels * kernel_height * kernel_width]` matrix.
+    fn weight_matrix(&self) -> Tensor<B, 2> {
+        let [out_channels, in_channels, kh, kw] = self.conv.weight.dims();
+        self.conv
+            .weight
+            .val()
+            .reshape([out_channels, in_channels * kh * kw])
+    }
+
+    /// Current spectral norm of the kernel. Shape: `[1]`.
+    fn sigma(&self) -> Tensor<B, 1> {
+        let weight = self.weight_matrix();
+        let (u, v) = power_iteration(weight.clone().detach(), self.u.value(), 1);
+        if B::ad_enabled() {
+            self.u.update(u.clone());
+        }
+
+        spectral_norm_estimate(weight, u, v)
+    }
+
+    /// # Shapes
+    ///   - input: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, out_channels, height_out, width_out]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let sigma = self.sigma().reshape([1, 1, 1, 1]);
+        let x = self.conv.forward(x);
+
+        // The convolution is linear in the kernel, so only the bias needs to be excluded
+        match &self.conv.bias {
+            Some(bias) => {
+                let [channels] = bias.dims();
+                let bias = bias.val().reshape([1, channels, 1, 1]);
+                (x - bias.clone()) / sigma + bias
+            }
+            None => x / sigma,
+        }
+    }
+}
+
+/// Wrap a [2D convolution](Conv2d) with [spectral normalization](SpectralNormConv2d).
+pub fn spectral_norm_conv<B: Backend>(conv: Conv2d<B>) -> SpectralNormConv2d<B> {
+    let [out_channels, in_channels, kh, kw] = conv.weight.dims();
+    let weight = conv
+        .weight
+        .val()
+        .reshape([out_channels, in_channels * kh * kw])
+        .detach();
+    let u = random_unit_vector(out_channels, &weight.device());
+    let (u, _) = power_iteration(weight, u, INIT_POWER_ITERATIONS);
+
+    SpectralNormConv2d {
+        conv,
+        u: RunningState::new(u),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn focus_free_output_shape() {
+        let device = Default::default();
+        let focus_config = FocusConfig::new(3, 16, 3, 1);
+        let focus = focus_config.init::<TestBackend>(&device);
+        let focus_free = FocusFreeConfig::equivalent_to(&focus_config).init(&device);
+
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 32, 48], Distribution::Default, &device);
+
+        assert_eq!(focus.forward(x.clone()).dims(), [2, 16, 16, 24]);
+        assert_eq!(focus_free.forward(x).dims(), [2, 16, 16, 24]);
+    }
+
+    #[test]
+    fn focus_free_param_count() {
+        let device = Default::default();
+        let focus_config = FocusConfig::new(3, 16, 3, 1);
+        let focus = focus_config.init::<TestBackend>(&device);
+        let equivalent = FocusFreeConfig::equivalent_to(&focus_config).init(&device);
+        let smaller = FocusFreeConfig::new(3, 16, 3).init::<TestBackend>(&device);
+
+        // 4c x k x k weights of the focus convolution, c x 2k x 2k of the equivalent one
+        assert_eq!(focus.num_params(), 12 * 16 * 3 * 3 + 2 * 16);
+        assert_eq!(equivalent.num_params(), focus.num_params());
+        assert_eq!(smaller.num_params(), 3 * 16 * 3 * 3 + 2 * 16);
+        assert_ne!(smaller.num_params(), focus.num_params());
+    }
+
+    #[test]
+    fn focus_free_kernel_size_2() {
+        let device = Default::default();
+        let focus_free = FocusFreeConfig::new(3, 8, 2).init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 4>::zeros([1, 3, 16, 16], &device);
+
+        assert_eq!(focus_free.forward(x).dims(), [1, 8, 8, 8]);
+    }
+
+    #[test]
+    #[should_panic = "the kernel size should be at least 2"]
+    fn focus_free_kernel_size_1() {
+        FocusFreeConfig::new(3, 8, 1);
+    }
+
+    #[test]
+    fn patch_embedding_num_patches() {
+        let device = Default::default();
+        let config = PatchEmbeddingConfig::new((224, 224), (16, 16), 3, 32, false);
+        let embedding = config.init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 4>::zeros([2, 3, 224, 224], &device);
+
+        assert_eq!(config.num_patches(), 196);
+        assert_eq!(embedding.forward(x).dims(), [2, 196, 32]);
+    }
+
+    #[test]
+    fn overlapping_patch_embedding_num_patches() {
+        let device = Default::default();
+        let config = PatchEmbeddingConfig::new((30, 30), (4, 4), 3, 8, true);
+        let embedding = config.init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 4>::zeros([1, 3, 30, 30], &device);
+
+        assert_eq!(config.num_patches(), 64);
+        assert_eq!(embedding.forward(x).dims(), [1, 64, 8]);
+    }
+
+    #[test]
+    fn patch_embedding_is_linear_projection_of_patches() {
+        let device = Default::default();
+        let (c, p, e) = (3, 4, 8);
+        let embedding =
+            PatchEmbeddingConfig::new((8, 12), (p, p), c, e, false).init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 4>::random([2, c, 8, 12], Distribution::Default, &device);
+
+        // [B, C, H, W] -> [B, num_patches, C * p * p] with patches in row-major order
+        let patches = x
+            .clone()
+            .reshape([2, c, 2, p, 3, p])
+            .permute([0, 2, 4, 1, 3, 5])
+            .reshape([2, 6, c * p * p]);
+        let weight = embedding
+            .proj
+            .weight
+            .val()
+            .reshape([e, c * p * p])
+            .transpose();
+        let bias = embedding.proj.bias.as_ref().unwrap().val();
+        let expected = patches.matmul(weight.unsqueeze()) + bias.unsqueeze::<3>();
+
+        embedding
+            .forward(x)
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 4);
+    }
+
+    #[test]
+    fn mlp_block_output_dim() {
+        let device = Default::default();
+        let mlp =
+            MlpBlockConfig::ffn(16, 64, 0.1, ActivationType::Gelu).init::<TestBackend>(&device);
+        let projection =
+            MlpBlockConfig::new(16, 64, 8, 0.1, ActivationType::Gelu).init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 3>::random([2, 5, 16], Distribution::Default, &device);
+
+        assert_eq!(mlp.forward(x.clone()).dims(), [2, 5, 16]);
+        assert_eq!(projection.forward(x).dims(), [2, 5, 8]);
+    }
+
+    #[test]
+    fn mlp_block_dropout_only_in_training() {
+        type TrainingBackend = burn::backend::Autodiff<TestBackend>;
+        let device = Default::default();
+        let config = MlpBlockConfig::ffn(16, 64, 0.5, ActivationType::Gelu);
+
+        // Without autodiff, dropout is disabled and the output is deterministic
+        let mlp = config.init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 3>::random([2, 5, 16], Distribution::Default, &device);
+        mlp.forward(x.clone())
+            .into_data()
+            .assert_eq(&mlp.forward(x).into_data(), true);
+
+        // During training, different units are dropped at each forward pass
+        let mlp = config.init::<TrainingBackend>(&device);
+        let x = Tensor::<TrainingBackend, 3>::random([2, 5, 16], Distribution::Default, &device);
+        let out1: Vec<f32> = mlp.forward(x.clone()).into_data().to_vec().unwrap();
+        let out2: Vec<f32> = mlp.forward(x).into_data().to_vec().unwrap();
+        assert_ne!(out1, out2);
+    }
+
+    #[test]
+    fn classifier_head_output_shape() {
+        let device = Default::default();
+        let head = ClassifierHeadConfig::new(16, 32, 10, 0.).init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 2>::random([4, 16], Distribution::Default, &device);
+
+        assert_eq!(head.forward(x).dims(), [4, 10]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn base_conv_import_batch_norm() {
+        use burn::record::{FullPrecisionSettings, Recorder};
+        use burn_import::pytorch::{LoadArgs, PyTorchFileRecorder};
+        use std::path::Path;
+
+        let device = Default::default();
+        // YOLOX BaseConv(1, 2, ksize=1) state dict, including `bn.num_batches_tracked`
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/base_conv.pth");
+        let record: BaseConvRecord<TestBackend> =
+            PyTorchFileRecorder::<FullPrecisionSettings>::new()
+                .load(LoadArgs::new(path), &device)
+                .expect("should load the PyTorch checkpoint");
+        let conv = BaseConvConfig::new(1, 2, 1, 1, 1)
+            .init::<TestBackend>(&device)
+            .load_record(record);
+
+        let Normalization::Batch(bn) = &conv.bn else {
+            panic!("expected a batch normalization layer");
+        };
+        bn.gamma
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::from([1.5f32, 0.5]), false);
+        bn.beta
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::from([0.25f32, -0.25]), false);
+        bn.running_mean
+            .value()
+            .into_data()
+            .assert_eq(&TensorData::from([1f32, 2.]), false);
+        bn.running_var
+            .value()
+            .into_data()
+            .assert_eq(&TensorData::from([4f32, 0.25]), false);
+
+        // silu((w * x - mean) / sqrt(var + eps) * gamma + beta) with x = 1 and eps = 1e-3
+        let out = conv.forward(Tensor::ones([1, 1, 1, 1], &device));
+        let silu = |x: f32| x / (1. + (-x).exp());
+        let expected = [
+            silu((2. - 1.) / 4.001f32.sqrt() * 1.5 + 0.25),
+            silu((-1. - 2.) / 0.251f32.sqrt() * 0.5 - 0.25),
+        ];
+        out.into_data()
+            .assert_approx_eq(&TensorData::new(expected.to_vec(), [1, 2, 1, 1]), 5);
+    }
+
+    #[test]
+    fn frozen_base_conv_ignores_batch_statistics() {
+        type TrainingBackend = burn::backend::Autodiff<TestBackend>;
+        let device = Default::default();
+        let conv = BaseConvConfig::new(3, 4, 3, 1, 1).init::<TrainingBackend>(&device);
+        let x = Tensor::<TrainingBackend, 4>::random([1, 3, 8, 8], Distribution::Default, &device);
+        let other = |scale: f32| {
+            Tensor::random([3, 3, 8, 8], Distribution::Default, &device).mul_scalar(scale)
+        };
+        // Output of the first image, batched with images of different statistics
+        let first = |conv: &BaseConv<TrainingBackend>, scale: f32| {
+            conv.forward(Tensor::cat(vec![x.clone(), other(scale)], 0))
+                .slice([0..1, 0..4, 0..8, 0..8])
+                .into_data()
+        };
+
+        // The batch statistics are used by regular batch normalization during training
+        let out = first(&conv, 1.).to_vec::<f32>().unwrap();
+        let out_scaled = first(&conv, 10.).to_vec::<f32>().unwrap();
+        assert!(out
+            .iter()
+            .zip(out_scaled)
+            .any(|(a, b)| (a - b).abs() > 1e-3));
+
+        let conv = conv.freeze_batch_norms();
+        assert!(matches!(conv.bn, Normalization::Frozen(_)));
+        first(&conv, 1.).assert_approx_eq(&first(&conv, 10.), 5);
+    }
+
+    #[test]
+    fn residual_identity() {
+        let device = Default::default();
+        let residual = ResidualConnectionConfig::new(8, 8, 1).init::<TestBackend>(&device);
+        let shortcut =
+            Tensor::<TestBackend, 4>::random([2, 8, 4, 4], Distribution::Default, &device);
+        let main = Tensor::<TestBackend, 4>::random([2, 8, 4, 4], Distribution::Default, &device);
+
+        let out = residual.apply(shortcut.clone(), main.clone());
+
+        assert!(residual.is_identity());
+        assert_eq!(residual.num_params(), 0);
+        out.into_data()
+            .assert_eq(&(shortcut + main).into_data(), true);
+    }
+
+    #[test]
+    fn residual_projection() {
+        let device = Default::default();
+        let residual = ResidualConnectionConfig::new(8, 16, 1).init::<TestBackend>(&device);
+        let shortcut =
+            Tensor::<TestBackend, 4>::random([2, 8, 4, 4], Distribution::Default, &device);
+        let main = Tensor::<TestBackend, 4>::random([2, 16, 4, 4], Distribution::Default, &device);
+
+        let out = residual.apply(shortcut.clone(), main.clone());
+
+        assert!(!residual.is_identity());
+        let projection = residual.projection.as_ref().unwrap();
+        // 1x1 convolution weights without bias, and the batch norm scale and shift
+        assert_eq!(projection.conv.weight.dims(), [16, 8, 1, 1]);
+        assert_eq!(residual.num_params(), 8 * 16 + 2 * 16);
+        // Batch normalization with the initial running statistics during inference
+        let expected = projection
+            .conv
+            .forward(shortcut)
+            .div_scalar((1. + 1e-5f32).sqrt())
+            + main;
+        out.into_data().assert_approx_eq(&expected.into_data(), 4);
+    }
+
+    #[test]
+    fn residual_strided_projection() {
+        let device = Default::default();
+        let residual = ResidualConnectionConfig::new(8, 8, 2).init::<TestBackend>(&device);
+        let shortcut =
+            Tensor::<TestBackend, 4>::random([2, 8, 8, 8], Distribution::Default, &device);
+        let main = Tensor::<TestBackend, 4>::random([2, 8, 4, 4], Distribution::Default, &device);
+
+        assert!(!residual.is_identity());
+        assert_eq!(residual.apply(shortcut, main).dims(), [2, 8, 4, 4]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn residual_record_round_trip() {
+        use burn::record::{BinBytesRecorder, FullPrecisionSettings, Recorder};
+
+        let device = Default::default();
+        let config = ResidualConnectionConfig::new(8, 16, 2);
+        let residual = config.init::<TestBackend>(&device);
+        let recorder = BinBytesRecorder::<FullPrecisionSettings>::new();
+
+        let bytes = recorder
+            .record(residual.clone().into_record(), ())
+            .expect("should serialize the record");
+        let record = recorder
+            .load(bytes, &device)
+            .expect("should deserialize the record");
+        let loaded = config.init::<TestBackend>(&device).load_record(record);
+
+        let shortcut =
+            Tensor::<TestBackend, 4>::random([1, 8, 8, 8], Distribution::Default, &device);
+        let main = Tensor::<TestBackend, 4>::zeros([1, 16, 4, 4], &device);
+        loaded
+            .apply(shortcut.clone(), main.clone())
+            .into_data()
+            .assert_eq(&residual.apply(shortcut, main).into_data(), true);
+    }
+
+    /// Spectral norm of a matrix, estimated with many power iterations.
+    fn spectral_norm_of(weight: Tensor<TestBackend, 2>) -> f32 {
+        let [rows, _] = weight.dims();
+        let u = random_unit_vector(rows, &weight.device());
+        let (u, v) = power_iteration(weight.clone(), u, 200);
+
+        spectral_norm_estimate(weight, u, v).into_scalar()
+    }
+
+    #[test]
+    fn spectral_norm_linear_unit_norm() {
+        let device = Default::default();
+        let linear = LinearConfig::new(16, 8).init::<TestBackend>(&device);
+        let layer = spectral_norm(linear);
+
+        let weight = layer.weight_matrix() / layer.sigma().reshape([1, 1]);
+
+        let norm = spectral_norm_of(weight);
+        assert!(norm <= 1. + 1e-2, "spectral norm {norm} larger than 1");
+        assert!(norm >= 1. - 1e-2, "spectral norm {norm} smaller than 1");
+    }
+
+    #[test]
+    fn spectral_norm_conv_unit_norm() {
+        let device = Default::default();
+        let conv = Conv2dConfig::new([4, 6], [3, 3]).init::<TestBackend>(&device);
+        let layer = spectral_norm_conv(conv);
+
+        let weight = layer.weight_matrix() / layer.sigma().reshape([1, 1]);
+
+        let norm = spectral_norm_of(weight);
+        assert!(norm <= 1. + 1e-2, "spectral norm {norm} larger than 1");
+    }
+
+    #[test]
+    fn spectral_norm_linear_lipschitz() {
+        let device = Default::default();
+        let linear = LinearConfig::new(16, 8)
+            .with_bias(false)
+            .init::<TestBackend>(&device);
+        let layer = spectral_norm(linear);
+        let x = Tensor::<TestBackend, 2>::random([32, 16], Distribution::Default, &device);
+
+        // |f(x)| <= |x| for each input, since the layer is 1-Lipschitz
+        let norms = |x: Tensor<TestBackend, 2>| -> Vec<f32> {
+            x.powf_scalar(2.)
+                .sum_dim(1)
+                .sqrt()
+                .into_data()
+                .to_vec()
+                .unwrap()
+        };
+        let inputs = norms(x.clone());
+        let outputs = norms(layer.forward(x));
+
+        for (input, output) in inputs.iter().zip(&outputs) {
+            assert!(*output <= input * (1. + 1e-2));
+        }
+    }
+
+    #[test]
+    fn convert_sync_batch_norms() {
+        let device = Default::default();
+        let focus = FocusConfig::new(3, 16, 3, 1).init::<TestBackend>(&device);
+        assert!(matches!(focus.conv.bn, Normalization::Batch(_)));
+
+        let focus = crate::model::normalizations::convert_sync_batchnorm(focus);
+
+        let Normalization::Sync(bn) = &focus.conv.bn else {
+            panic!("expected a synchronized batch normalization layer");
+        };
+        assert_eq!(bn.bn.momentum, 0.03);
+        assert_eq!(bn.bn.epsilon, 1e-3);
+    }
+
+    #[test]
+    fn attention_gate_mask_range() {
+        let device = Default::default();
+        let gate = AttentionGateConfig::new(16, 8, 4).init::<TestBackend>(&device);
+        let g = Tensor::<TestBackend, 4>::random([2, 16, 4, 4], Distribution::Default, &device);
+        let x = Tensor::<TestBackend, 4>::random([2, 8, 8, 8], Distribution::Default, &device);
+
+        let mask = gate.attention_mask(g.clone(), x.clone());
+        assert_eq!(mask.dims(), [2, 1, 8, 8]);
+        for value in mask.into_data().to_vec::<f32>().unwrap() {
+            assert!((0. ..=1.).contains(&value));
+        }
+
+        assert_eq!(gate.forward(g, x).dims(), [2, 8, 8, 8]);
+    }
+
+    #[test]
+    fn closed_attention_gate() {
+        use burn::module::Param;
+
+        let device = Default::default();
+        let mut gate = AttentionGateConfig::new(16, 8, 4).init::<TestBackend>(&device);
+        // The mask saturates to 0 whatever the inputs
+        gate.psi.weight = Param::from_tensor(Tensor::zeros([1, 4, 1, 1], &device));
+        gate.psi.bias = Some(Param::from_tensor(Tensor::full([1], -100., &device)));
+        let g = Tensor::<TestBackend, 4>::random([1, 16, 8, 8], Distribution::Default, &device);
+        let x = Tensor::<TestBackend, 4>::random([1, 8, 8, 8], Distribution::Default, &device);
+
+        let gated = gate.forward(g, x);
+
+        gated
+            .into_data()
+            .assert_approx_eq(&TensorData::zeros::<f32, _>([1, 8, 8, 8]), 5);
+    }
+}
@@ -1,20 +1,34 @@
 use alloc::{vec, vec::Vec};
 use burn::{
-    module::Module,
-    nn::pool::{MaxPool2d, MaxPool2dConfig},
-    tensor::{backend::Backend, Device, Tensor},
+    module::{Module, Param},
+    nn::{
+        pool::{MaxPool2d, MaxPool2dConfig},
+        Linear, LinearConfig,
+    },
+    tensor::{activation::sigmoid, backend::Backend, Device, Tensor},
 };
 
-use super::blocks::{expand, BaseConv, BaseConvConfig, Conv, ConvConfig};
+use super::{
+    blocks::{
+        expand, Activation, ActivationType, BaseConv, BaseConvConfig, Conv, ConvConfig,
+        ResidualConnection, ResidualConnectionConfig, SeBlock, SeBlockConfig,
+    },
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
+};
 
 pub(crate) const SPP_POOLING: [usize; 3] = [5, 9, 13];
+const SE_REDUCTION: usize = 16;
 
 /// Standard bottleneck block.
+///
+/// When the input and output channels differ, the shortcut connection goes through a `1x1`
+/// [projection](ResidualConnection). Blocks without shortcut have no residual connection.
 #[derive(Module, Debug)]
 pub struct Bottleneck<B: Backend> {
     conv1: BaseConv<B>,
     conv2: Conv<B>,
-    shortcut: bool,
+    se: Option<SeBlock<B>>,
+    residual: Option<ResidualConnection<B>>,
 }
 
 impl<B: Backend> Bottleneck<B> {
@@ -24,11 +38,44 @@ impl<B: Backend> Bottleneck<B> {
         let x = self.conv1.forward(x);
         let mut x = self.conv2.forward(x);
 
-        if self.shortcut {
-            x = x + identity;
+        if let Some(se) = &self.se {
+            x = se.forward(x);
+        }
+
+        match &self.residual {
+            Some(residual) => residual.apply(identity, x),
+            None => x,
+        }
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for Bottleneck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            residual: self.residual.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for Bottleneck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            residual: self.residual.sync_batch_norms(),
+            ..self
         }
+    }
+}
 
-        x
+impl<B: Backend> SetBatchNormMomentum<B> for Bottleneck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+        self.residual.set_bn_momentum(momentum);
     }
 }
 
@@ -36,7 +83,7 @@ impl<B: Backend> Bottleneck<B> {
 struct BottleneckConfig {
     conv1: BaseConvConfig,
     conv2: ConvConfig,
-    shortcut: bool,
+    residual: Option<ResidualConnectionConfig>,
 }
 
 impl BottleneckConfig {
@@ -47,19 +94,121 @@ impl BottleneckConfig {
 
         let conv1 = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
         let conv2 = ConvConfig::new(hidden_channels, out_channels, 3, 1, depthwise);
+        let residual =
+            shortcut.then(|| ResidualConnectionConfig::new(in_channels, out_channels, 1));
 
         Self {
             conv1,
             conv2,
-            shortcut,
+            residual,
         }
     }
 
-    /// Initialize a new [bottleneck block](Bottleneck) module.
-    pub fn init<B: Backend>(&self, device: &Device<B>) -> Bottleneck<B> {
+    /// Initialize a new [bottleneck block](Bottleneck) module, with an optional
+    /// [squeeze-and-excitation block](SeBlock) after the bottleneck body.
+    pub fn init<B: Backend>(
+        &self,
+        se: Option<&SeBlockConfig>,
+        device: &Device<B>,
+    ) -> Bottleneck<B> {
         Bottleneck {
             conv1: self.conv1.init(device),
             conv2: self.conv2.init(device),
+            se: se.map(|se| se.init(device)),
+            residual: self.residual.as_ref().map(|residual| residual.init(device)),
+        }
+    }
+}
+
+/// Bottleneck block with a gated residual connection.
+///
+/// The residual branch is scaled by a learnable scalar gate: `x + sigmoid(alpha) * f(x)`. The
+/// shortcut connection is disabled when the input and output channels differ.
+#[derive(Module, Debug)]
+pub struct GatedBottleneck<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: Conv<B>,
+    alpha: Param<Tensor<B, 1>>,
+    shortcut: bool,
+}
+
+impl<B: Backend> GatedBottleneck<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let identity = x.clone();
+
+        let x = self.conv1.forward(x);
+        let x = self.conv2.forward(x);
+
+        if self.shortcut {
+            identity + x * self.gate().reshape([1, 1, 1, 1])
+        } else {
+            x
+        }
+    }
+
+    /// Value of the residual gate `sigmoid(alpha)`.
+    pub fn gate(&self) -> Tensor<B, 1> {
+        sigmoid(self.alpha.val())
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for GatedBottleneck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for GatedBottleneck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for GatedBottleneck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+    }
+}
+
+/// [Gated bottleneck block](GatedBottleneck) configuration.
+struct GatedBottleneckConfig {
+    conv1: BaseConvConfig,
+    conv2: ConvConfig,
+    shortcut: bool,
+}
+
+impl GatedBottleneckConfig {
+    /// Create a new instance of the gated bottleneck block [config](GatedBottleneckConfig).
+    pub fn new(in_channels: usize, out_channels: usize, shortcut: bool) -> Self {
+        let hidden_channels = out_channels;
+
+        let conv1 = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
+        let conv2 = ConvConfig::new(hidden_channels, out_channels, 3, 1, false);
+
+        Self {
+            conv1,
+            conv2,
+            // The identity cannot be added to the output with a different number of channels
+            shortcut: shortcut && in_channels == out_channels,
+        }
+    }
+
+    /// Initialize a new [gated bottleneck block](GatedBottleneck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> GatedBottleneck<B> {
+        GatedBottleneck {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+            // Gate is initialized to sigmoid(0) = 0.5
+            alpha: Param::from_tensor(Tensor::zeros([1], device)),
             shortcut: self.shortcut,
         }
     }
@@ -91,6 +240,33 @@ impl<B: Backend> SppBottleneck<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for SppBottleneck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for SppBottleneck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for SppBottleneck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+    }
+}
+
 /// [SppBottleneck block](SppBottleneck) configuration.
 pub struct SppBottleneckConfig {
     conv1: BaseConvConfig,
@@ -154,16 +330,54 @@ impl<B: Backend> CspBottleneck<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for CspBottleneck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            conv3: self.conv3.freeze_batch_norms(),
+            m: self.m.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for CspBottleneck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            conv3: self.conv3.sync_batch_norms(),
+            m: self.m.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for CspBottleneck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+        self.conv3.set_bn_momentum(momentum);
+        self.m.set_bn_momentum(momentum);
+    }
+}
+
 /// [CspBottleneck block](CspBottleneck) configuration.
 pub struct CspBottleneckConfig {
     conv1: BaseConvConfig,
     conv2: BaseConvConfig,
     conv3: BaseConvConfig,
     m: Vec<BottleneckConfig>,
+    hidden_channels: usize,
+    use_se: bool,
+    se_reduction: usize,
 }
 
 impl CspBottleneckConfig {
     /// Create a new instance of the bottleneck block [config](CspBottleneckConfig).
+    ///
+    /// The `shortcut` connections are applied by the inner [bottleneck blocks](Bottleneck), which
+    /// map the hidden channels to themselves, so they are valid for any `in_channels` and
+    /// `out_channels`.
     pub fn new(
         in_channels: usize,
         out_channels: usize,
@@ -191,12 +405,137 @@ impl CspBottleneckConfig {
             conv2,
             conv3,
             m,
+            hidden_channels,
+            use_se: false,
+            se_reduction: SE_REDUCTION,
         }
     }
 
+    /// Insert a [squeeze-and-excitation block](SeBlock) in each bottleneck block.
+    pub fn with_use_se(mut self, use_se: bool) -> Self {
+        self.use_se = use_se;
+        self
+    }
+
+    /// Set the reduction ratio of the squeeze-and-excitation blocks (defaults to 16).
+    pub fn with_se_reduction(mut self, se_reduction: usize) -> Self {
+        self.se_reduction = se_reduction;
+        self
+    }
+
     /// Initialize a new [bottleneck block](CspBottleneck) module.
     pub fn init<B: Backend>(&self, device: &Device<B>) -> CspBottleneck<B> {
+        let se = self
+            .use_se
+            .then(|| SeBlockConfig::new(self.hidden_channels, self.se_reduction));
+
         CspBottleneck {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+            conv3: self.conv3.init(device),
+            m: self.m.iter().map(|b| b.init(se.as_ref(), device)).collect(),
+        }
+    }
+}
+
+/// [CspBottleneck](CspBottleneck) variant with [gated residual connections](GatedBottleneck).
+#[derive(Module, Debug)]
+pub struct GatedCspBottleneck<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: BaseConv<B>,
+    conv3: BaseConv<B>,
+    m: Vec<GatedBottleneck<B>>,
+}
+
+impl<B: Backend> GatedCspBottleneck<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x1 = self.conv1.forward(x.clone());
+        let x2 = self.conv2.forward(x);
+
+        let x1 = self
+            .m
+            .iter()
+            .fold(x1, |x_i, bottleneck| bottleneck.forward(x_i));
+
+        let x = Tensor::cat(vec![x1, x2], 1);
+
+        self.conv3.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for GatedCspBottleneck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            conv3: self.conv3.freeze_batch_norms(),
+            m: self.m.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for GatedCspBottleneck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            conv3: self.conv3.sync_batch_norms(),
+            m: self.m.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for GatedCspBottleneck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+        self.conv3.set_bn_momentum(momentum);
+        self.m.set_bn_momentum(momentum);
+    }
+}
+
+/// [GatedCspBottleneck block](GatedCspBottleneck) configuration.
+pub struct GatedCspBottleneckConfig {
+    conv1: BaseConvConfig,
+    conv2: BaseConvConfig,
+    conv3: BaseConvConfig,
+    m: Vec<GatedBottleneckConfig>,
+}
+
+impl GatedCspBottleneckConfig {
+    /// Create a new instance of the gated bottleneck block [config](GatedCspBottleneckConfig).
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        num_blocks: usize,
+        expansion: f64,
+        use_shortcut: bool,
+    ) -> Self {
+        assert!(
+            expansion > 0.0 && expansion <= 1.0,
+            "expansion should be in range (0, 1]"
+        );
+
+        let hidden_channels = expand(out_channels, expansion);
+
+        let conv1 = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
+        let conv2 = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
+        let conv3 = BaseConvConfig::new(2 * hidden_channels, out_channels, 1, 1, 1);
+        let m = (0..num_blocks)
+            .map(|_| GatedBottleneckConfig::new(hidden_channels, hidden_channels, use_shortcut))
+            .collect();
+
+        Self {
+            conv1,
+            conv2,
+            conv3,
+            m,
+        }
+    }
+
+    /// Initialize a new [gated bottleneck block](GatedCspBottleneck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> GatedCspBottleneck<B> {
+        GatedCspBottleneck {
             conv1: self.conv1.init(device),
             conv2: self.conv2.init(device),
             conv3: self.conv3.init(device),
@@ -204,3 +543,541 @@ impl CspBottleneckConfig {
         }
     }
 }
+
+/// [CspBottleneck](CspBottleneck) variant whose cross-stage skip connection is gated by a
+/// per-channel attention weight.
+///
+/// The gate is computed from the output of the bottleneck blocks (global average pooling, then a
+/// linear layer and the gate activation), and scales the skip branch before it is merged with the
+/// bottleneck output. Unlike a [squeeze-and-excitation block](SeBlock), which recalibrates the
+/// whole feature map, only the skip connection is modulated. With a gate of one, the block is
+/// equivalent to the [CspBottleneck](CspBottleneck).
+#[derive(Module, Debug)]
+pub struct CspAttBottleneck<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: BaseConv<B>,
+    conv3: BaseConv<B>,
+    m: Vec<Bottleneck<B>>,
+    gate: Linear<B>,
+    gate_activation: Activation,
+}
+
+impl<B: Backend> CspAttBottleneck<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x1 = self.conv1.forward(x.clone());
+        let x2 = self.conv2.forward(x);
+
+        let x1 = self
+            .m
+            .iter()
+            .fold(x1, |x_i, bottleneck| bottleneck.forward(x_i));
+
+        // Per-channel gate of the skip connection [B, C, 1, 1]
+        let [b, c, _, _] = x1.dims();
+        let pooled = x1.clone().mean_dim(3).mean_dim(2).reshape([b, c]);
+        let gate = self
+            .gate_activation
+            .forward(self.gate.forward(pooled))
+            .reshape([b, c, 1, 1]);
+
+        let x = Tensor::cat(vec![x1, x2 * gate], 1);
+
+        self.conv3.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for CspAttBottleneck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            conv3: self.conv3.freeze_batch_norms(),
+            m: self.m.freeze_batch_norms(),
+            gate: self.gate,
+            gate_activation: self.gate_activation,
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for CspAttBottleneck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            conv3: self.conv3.sync_batch_norms(),
+            m: self.m.sync_batch_norms(),
+            gate: self.gate,
+            gate_activation: self.gate_activation,
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for CspAttBottleneck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+        self.conv3.set_bn_momentum(momentum);
+        self.m.set_bn_momentum(momentum);
+    }
+}
+
+/// [Attention-gated bottleneck block](CspAttBottleneck) configuration.
+pub struct CspAttBottleneckConfig {
+    conv1: BaseConvConfig,
+    conv2: BaseConvConfig,
+    conv3: BaseConvConfig,
+    m: Vec<BottleneckConfig>,
+    hidden_channels: usize,
+    gate_activation: ActivationType,
+}
+
+impl CspAttBottleneckConfig {
+    /// Create a new instance of the attention-gated bottleneck block
+    /// [config](CspAttBottleneckConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input channels.
+    /// * `out_channels` - Number of output channels.
+    /// * `num_blocks` - Number of bottleneck blocks (with shortcut connections).
+    /// * `expansion` - Ratio of the hidden channels to the output channels, in `(0, 1]`.
+    /// * `gate_activation` - Activation of the gate, usually [sigmoid](ActivationType::Sigmoid).
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        num_blocks: usize,
+        expansion: f64,
+        gate_activation: ActivationType,
+    ) -> Self {
+        assert!(
+            expansion > 0.0 && expansion <= 1.0,
+            "expansion should be in range (0, 1]"
+        );
+
+        let hidden_channels = expand(out_channels, expansion);
+
+        let conv1 = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
+        let conv2 = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
+        let conv3 = BaseConvConfig::new(2 * hidden_channels, out_channels, 1, 1, 1);
+        let m = (0..num_blocks)
+            .map(|_| BottleneckConfig::new(hidden_channels, hidden_channels, true, false))
+            .collect();
+
+        Self {
+            conv1,
+            conv2,
+            conv3,
+            m,
+            hidden_channels,
+            gate_activation,
+        }
+    }
+
+    /// Initialize a new [attention-gated bottleneck block](CspAttBottleneck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> CspAttBottleneck<B> {
+        CspAttBottleneck {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+            conv3: self.conv3.init(device),
+            m: self.m.iter().map(|b| b.init(None, device)).collect(),
+            gate: LinearConfig::new(self.hidden_channels, self.hidden_channels).init(device),
+            gate_activation: self.gate_activation.init(),
+        }
+    }
+}
+
+/// Cross Stage Partial block with two convolutions and a concatenation of all the intermediate
+/// bottleneck outputs. Equivalent to C2f in YOLOv8.
+///
+/// Unlike the [CspBottleneck](CspBottleneck), the output of the first convolution is split into
+/// two halves, the bottleneck blocks are applied sequentially to the second half, and the two
+/// halves are concatenated with the output of every bottleneck block before the projection.
+#[derive(Module, Debug)]
+pub struct C2f<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: BaseConv<B>,
+    m: Vec<Bottleneck<B>>,
+    hidden_channels: usize,
+}
+
+impl<B: Backend> C2f<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.conv1.forward(x);
+        let [b, _, h, w] = x.dims();
+        let c = self.hidden_channels;
+
+        let mut outputs = vec![
+            x.clone().slice([0..b, 0..c, 0..h, 0..w]),
+            x.slice([0..b, c..2 * c, 0..h, 0..w]),
+        ];
+        for bottleneck in self.m.iter() {
+            let x_i = bottleneck.forward(outputs[outputs.len() - 1].clone());
+            outputs.push(x_i);
+        }
+
+        // [B, (num_blocks + 2) * C, H, W]
+        let x = Tensor::cat(outputs, 1);
+
+        self.conv2.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for C2f<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.freeze_batch_norms(),
+            conv2: self.conv2.freeze_batch_norms(),
+            m: self.m.freeze_batch_norms(),
+            hidden_channels: self.hidden_channels,
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for C2f<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv1: self.conv1.sync_batch_norms(),
+            conv2: self.conv2.sync_batch_norms(),
+            m: self.m.sync_batch_norms(),
+            hidden_channels: self.hidden_channels,
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for C2f<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv1.set_bn_momentum(momentum);
+        self.conv2.set_bn_momentum(momentum);
+        self.m.set_bn_momentum(momentum);
+    }
+}
+
+/// [C2f block](C2f) configuration.
+pub struct C2fConfig {
+    conv1: BaseConvConfig,
+    conv2: BaseConvConfig,
+    m: Vec<BottleneckConfig>,
+    hidden_channels: usize,
+}
+
+impl C2fConfig {
+    /// Create a new instance of the C2f block [config](C2fConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input channels.
+    /// * `out_channels` - Number of output channels.
+    /// * `num_blocks` - Number of bottleneck blocks.
+    /// * `expansion` - Ratio of the hidden channels of each half to the output channels, in
+    ///   `(0, 1]`.
+    /// * `shortcut` - Whether the bottleneck blocks use shortcut connections.
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        num_blocks: usize,
+        expansion: f64,
+        shortcut: bool,
+    ) -> Self {
+        assert!(
+            expansion > 0.0 && expansion <= 1.0,
+            "expansion should be in range (0, 1]"
+        );
+
+        let hidden_channels = expand(out_channels, expansion);
+
+        let conv1 = BaseConvConfig::new(in_channels, 2 * hidden_channels, 1, 1, 1);
+        let conv2 = BaseConvConfig::new((num_blocks + 2) * hidden_channels, out_channels, 1, 1, 1);
+        let m = (0..num_blocks)
+            .map(|_| BottleneckConfig::new(hidden_channels, hidden_channels, shortcut, false))
+            .collect();
+
+        Self {
+            conv1,
+            conv2,
+            m,
+            hidden_channels,
+        }
+    }
+
+    /// Number of channels of the concatenated outputs, before the projection.
+    pub fn concat_channels(&self) -> usize {
+        (self.m.len() + 2) * self.hidden_channels
+    }
+
+    /// Initialize a new [C2f block](C2f) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> C2f<B> {
+        C2f {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+            m: self.m.iter().map(|b| b.init(None, device)).collect(),
+            hidden_channels: self.hidden_channels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn gated_bottleneck_initial_gate() {
+        let device = Default::default();
+        let block =
+            GatedCspBottleneckConfig::new(16, 32, 2, 0.5, true).init::<TestBackend>(&device);
+
+        assert_eq!(block.m.len(), 2);
+        for bottleneck in block.m.iter() {
+            let gate: Vec<f32> = bottleneck.gate().into_data().to_vec().unwrap();
+            assert_eq!(gate, [0.5]);
+        }
+    }
+
+    #[test]
+    fn gated_csp_bottleneck_output_shape() {
+        let device = Default::default();
+        let block =
+            GatedCspBottleneckConfig::new(16, 32, 2, 0.5, true).init::<TestBackend>(&device);
+
+        let x = Tensor::<TestBackend, 4>::random([2, 16, 8, 8], Distribution::Default, &device);
+
+        assert_eq!(block.forward(x).dims(), [2, 32, 8, 8]);
+    }
+
+    #[test]
+    fn gated_csp_bottleneck_param_count() {
+        let device = Default::default();
+        let gated =
+            GatedCspBottleneckConfig::new(16, 32, 3, 0.5, true).init::<TestBackend>(&device);
+        let plain =
+            CspBottleneckConfig::new(16, 32, 3, 0.5, true, false).init::<TestBackend>(&device);
+
+        // One gate parameter per bottleneck block
+        assert_eq!(gated.num_params(), plain.num_params() + 3);
+    }
+
+    #[test]
+    fn bottleneck_residual_connection() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::random([2, 16, 8, 8], Distribution::Default, &device);
+
+        let same = BottleneckConfig::new(16, 16, true, false).init::<TestBackend>(None, &device);
+        let projected =
+            BottleneckConfig::new(16, 32, true, false).init::<TestBackend>(None, &device);
+
+        assert!(same.residual.unwrap().is_identity());
+        assert!(!projected.residual.unwrap().is_identity());
+        assert_eq!(same.forward(x.clone()).dims(), [2, 16, 8, 8]);
+        assert_eq!(projected.forward(x).dims(), [2, 32, 8, 8]);
+    }
+
+    #[test]
+    fn bottleneck_without_shortcut() {
+        let device = Default::default();
+        let block = BottleneckConfig::new(16, 16, false, false).init::<TestBackend>(None, &device);
+        let x = Tensor::<TestBackend, 4>::random([1, 16, 4, 4], Distribution::Default, &device);
+
+        // Main branch only
+        let expected = block.conv2.forward(block.conv1.forward(x.clone()));
+
+        block
+            .forward(x)
+            .into_data()
+            .assert_eq(&expected.into_data(), true);
+    }
+
+    #[test]
+    fn bottleneck_without_shortcut_has_no_projection() {
+        let device = Default::default();
+        let block = BottleneckConfig::new(16, 32, false, false).init::<TestBackend>(None, &device);
+        let projected =
+            BottleneckConfig::new(16, 32, true, false).init::<TestBackend>(None, &device);
+
+        assert!(block.residual.is_none());
+        // 1x1 convolution without bias followed by a batch norm with weight and bias
+        assert_eq!(
+            block.num_params() + 16 * 32 + 2 * 32,
+            projected.num_params()
+        );
+    }
+
+    #[test]
+    fn csp_bottleneck_se_changes_output() {
+        let device = Default::default();
+        let with_se = CspBottleneckConfig::new(32, 64, 2, 0.5, true, false)
+            .with_use_se(true)
+            .with_se_reduction(4)
+            .init::<TestBackend>(&device);
+        // Same weights without the squeeze-and-excitation blocks
+        let mut without_se = with_se.clone();
+        without_se.m.iter_mut().for_each(|block| block.se = None);
+        let x = Tensor::<TestBackend, 4>::random([2, 32, 8, 8], Distribution::Default, &device);
+
+        let out = with_se
+            .forward(x.clone())
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap();
+        let out_without_se = without_se.forward(x).into_data().to_vec::<f32>().unwrap();
+
+        assert_eq!(out.len(), 2 * 64 * 8 * 8);
+        assert!(out
+            .iter()
+            .zip(out_without_se)
+            .any(|(a, b)| (a - b).abs() > 1e-4));
+    }
+
+    #[test]
+    fn csp_bottleneck_se_param_count() {
+        let device = Default::default();
+        let config = || CspBottleneckConfig::new(32, 64, 2, 0.5, true, false);
+        let plain = config().init::<TestBackend>(&device);
+        let with_se = config()
+            .with_use_se(true)
+            .with_se_reduction(4)
+            .init::<TestBackend>(&device);
+
+        // Two 1x1 convolutions 32 -> 8 -> 32 with biases in each of the 2 blocks
+        let se_params = 32 * 8 + 8 + 8 * 32 + 32;
+        assert_eq!(with_se.num_params(), plain.num_params() + 2 * se_params);
+    }
+
+    #[test]
+    fn darknet_se_in_last_stage() {
+        use crate::model::darknet::CspDarknetConfig;
+
+        let device = Default::default();
+        let plain = CspDarknetConfig::new(0.33, 0.25, false).init::<TestBackend>(&device);
+        let with_se = CspDarknetConfig::new(0.33, 0.25, false)
+            .with_use_se(true)
+            .init::<TestBackend>(&device);
+
+        // A single bottleneck block of dark5 with 128 hidden channels and a reduction of 16
+        let se_params = 128 * 8 + 8 + 8 * 128 + 128;
+        assert_eq!(with_se.num_params(), plain.num_params() + se_params);
+    }
+
+    #[test]
+    fn csp_bottleneck_shortcut_mismatched_channels() {
+        let device = Default::default();
+        let block =
+            CspBottleneckConfig::new(16, 48, 2, 0.5, true, false).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 16, 8, 8], Distribution::Default, &device);
+
+        // The inner shortcuts map the hidden channels to themselves
+        for bottleneck in block.m.iter() {
+            assert!(bottleneck.residual.as_ref().unwrap().is_identity());
+        }
+        assert_eq!(block.forward(x).dims(), [2, 48, 8, 8]);
+    }
+
+    #[test]
+    fn gated_bottleneck_shortcut_disabled_on_mismatch() {
+        let device = Default::default();
+        let same = GatedBottleneckConfig::new(16, 16, true).init::<TestBackend>(&device);
+        let mismatched = GatedBottleneckConfig::new(16, 32, true).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([1, 16, 4, 4], Distribution::Default, &device);
+
+        assert!(same.shortcut);
+        assert!(!mismatched.shortcut);
+        assert_eq!(mismatched.forward(x).dims(), [1, 32, 4, 4]);
+    }
+
+    #[test]
+    fn att_bottleneck_open_gate_matches_csp_bottleneck() {
+        let device = Default::default();
+        let mut block = CspAttBottleneckConfig::new(16, 32, 2, 0.5, ActivationType::Sigmoid)
+            .init::<TestBackend>(&device);
+        // Gate of one for any input
+        block.gate.weight = Param::from_tensor(block.gate.weight.val().zeros_like());
+        block.gate.bias = Some(Param::from_tensor(Tensor::full([16], 100., &device)));
+        let plain = CspBottleneck {
+            conv1: block.conv1.clone(),
+            conv2: block.conv2.clone(),
+            conv3: block.conv3.clone(),
+            m: block.m.clone(),
+        };
+        let x = Tensor::<TestBackend, 4>::random([2, 16, 8, 8], Distribution::Default, &device);
+
+        block
+            .forward(x.clone())
+            .into_data()
+            .assert_approx_eq(&plain.forward(x).into_data(), 5);
+    }
+
+    #[test]
+    fn att_bottleneck_gate_parameters() {
+        let device = Default::default();
+        let block = CspAttBottleneckConfig::new(16, 32, 2, 1., ActivationType::Sigmoid)
+            .init::<TestBackend>(&device);
+        let plain =
+            CspBottleneckConfig::new(16, 32, 2, 1., true, false).init::<TestBackend>(&device);
+
+        // One gate per output channel
+        assert_eq!(block.gate.weight.dims(), [32, 32]);
+        assert_eq!(block.gate.bias.as_ref().unwrap().dims(), [32]);
+        assert_eq!(block.num_params(), plain.num_params() + 32 * 32 + 32);
+    }
+
+    #[test]
+    fn att_bottleneck_closed_gate() {
+        let device = Default::default();
+        let mut block = CspAttBottleneckConfig::new(16, 32, 1, 0.5, ActivationType::Sigmoid)
+            .init::<TestBackend>(&device);
+        block.gate.weight = Param::from_tensor(block.gate.weight.val().zeros_like());
+        block.gate.bias = Some(Param::from_tensor(Tensor::full([16], -100., &device)));
+        let x = Tensor::<TestBackend, 4>::random([1, 16, 4, 4], Distribution::Default, &device);
+
+        // The skip branch is removed
+        let x1 = block.m[0].forward(block.conv1.forward(x.clone()));
+        let expected = block
+            .conv3
+            .forward(Tensor::cat(vec![x1.clone(), x1.zeros_like()], 1));
+
+        block
+            .forward(x)
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 5);
+    }
+
+    #[test]
+    fn c2f_concatenates_all_outputs() {
+        let device = Default::default();
+        let block = C2fConfig::new(16, 32, 3, 0.5, true).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 16, 8, 8], Distribution::Default, &device);
+
+        // Both halves of the first convolution, then the output of each bottleneck block
+        let y = block.conv1.forward(x.clone());
+        let mut outputs = vec![
+            y.clone().slice([0..2, 0..16, 0..8, 0..8]),
+            y.slice([0..2, 16..32, 0..8, 0..8]),
+        ];
+        for bottleneck in block.m.iter() {
+            outputs.push(bottleneck.forward(outputs[outputs.len() - 1].clone()));
+        }
+        let concat = Tensor::cat(outputs, 1);
+        assert_eq!(block.hidden_channels, 16);
+        assert_eq!(concat.dims(), [2, (3 + 2) * 16, 8, 8]);
+
+        block
+            .forward(x)
+            .into_data()
+            .assert_approx_eq(&block.conv2.forward(concat).into_data(), 5);
+    }
+
+    #[test]
+    fn c2f_matches_csp_bottleneck_shape() {
+        let device = Default::default();
+        let c2f = C2fConfig::new(64, 128, 3, 0.5, true).init::<TestBackend>(&device);
+        let csp =
+            CspBottleneckConfig::new(64, 128, 3, 0.5, true, false).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([1, 64, 8, 8], Distribution::Default, &device);
+
+        assert_eq!(c2f.forward(x.clone()).dims(), csp.forward(x).dims());
+        assert_eq!(c2f.m.len(), csp.m.len());
+        // The projection of the 5 concatenated outputs is larger than the CSP one
+        assert!(c2f.num_params() > csp.num_params());
+    }
+}
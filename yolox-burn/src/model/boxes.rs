@@ -1,13 +1,8 @@
+use alloc::vec::Vec;
 use burn::tensor::{backend::Backend, ElementConversion, Tensor};
 use itertools::Itertools;
 
-pub struct BoundingBox {
-    pub xmin: f32,
-    pub ymin: f32,
-    pub xmax: f32,
-    pub ymax: f32,
-    pub confidence: f32,
-}
+use crate::types::{ConfThreshold, Detection, IoUThreshold};
 
 /// Non-maximum suppression (NMS) filters overlapping bounding boxes that have an intersection-over-
 /// union (IoU) greater or equal than the specified `iou_threshold` with previously selected boxes.
@@ -24,23 +19,44 @@ pub struct BoundingBox {
 ///
 /// # Returns
 ///
-/// Vector of bounding boxes grouped by class for each batch. The boxes are sorted in decreasing
-/// order of scores for each class.
+/// Vector of detections grouped by class for each batch, with the batch index as image id. The
+/// detections are sorted in decreasing order of scores for each class.
 pub fn nms<B: Backend>(
     boxes: Tensor<B, 3>,
     scores: Tensor<B, 3>,
-    iou_threshold: f32,
-    score_threshold: f32,
-) -> Vec<Vec<Vec<BoundingBox>>> {
-    let [batch_size, num_boxes, num_classes] = scores.dims();
+    iou_threshold: IoUThreshold,
+    score_threshold: ConfThreshold,
+) -> Vec<Vec<Vec<Detection>>> {
+    let [_, _, num_classes] = scores.dims();
+
+    // Detections grouped by batch and by (maximum) class index
+    candidates(boxes, scores)
+        .into_iter()
+        .enumerate()
+        .map(|(image_id, (candidate_boxes, cls_score, cls_idx))| {
+            nms_candidates(
+                image_id,
+                &candidate_boxes,
+                &cls_score,
+                &cls_idx,
+                num_classes,
+                iou_threshold,
+                score_threshold,
+            )
+        })
+        .collect()
+}
+
+/// Per-batch candidate boxes `[num_boxes * 4]`, maximum class scores `[num_boxes]` and maximum
+/// class indices `[num_boxes]`.
+pub(crate) type Candidates = (Vec<f32>, Vec<f32>, Vec<usize>);
 
-    // Bounding boxes grouped by batch and by (maximum) class index
-    let mut bboxes = boxes
+/// Extract the candidate boxes and their maximum class score for each batch.
+pub(crate) fn candidates<B: Backend>(boxes: Tensor<B, 3>, scores: Tensor<B, 3>) -> Vec<Candidates> {
+    boxes
         .iter_dim(0)
         .zip(scores.iter_dim(0))
-        .enumerate()
-        // Per-batch
-        .map(|(_, (candidate_boxes, candidate_scores))| {
+        .map(|(candidate_boxes, candidate_scores)| {
             // Keep max scoring boxes only ([num_boxes, 1], [num_boxes, 1])
             let (cls_score, cls_idx) = candidate_scores.squeeze::<2>(0).max_dim_with_indices(1);
             let cls_score: Vec<_> = cls_score
@@ -61,66 +77,86 @@ pub fn nms<B: Backend>(
                 .map(|v| v.elem::<f32>())
                 .collect();
 
-            // Per-class filtering based on score
-            (0..num_classes)
-                .map(|cls_id| {
-                    // [num_boxes, 1]
-                    (0..num_boxes)
-                        .filter_map(|box_idx| {
-                            let box_cls_idx = cls_idx[box_idx];
-                            if box_cls_idx != cls_id {
-                                return None;
-                            }
-                            let box_cls_score = cls_score[box_idx];
-                            if box_cls_score >= score_threshold {
-                                let bbox = &candidate_boxes[box_idx * 4..box_idx * 4 + 4];
-                                Some(BoundingBox {
-                                    xmin: bbox[0] - bbox[2] / 2.,
-                                    ymin: bbox[1] - bbox[3] / 2.,
-                                    xmax: bbox[0] + bbox[2] / 2.,
-                                    ymax: bbox[1] + bbox[3] / 2.,
-                                    confidence: box_cls_score,
-                                })
-                            } else {
-                                None
-                            }
-                        })
-                        .sorted_unstable_by(|a, b| a.confidence.partial_cmp(&b.confidence).unwrap())
-                        .collect::<Vec<_>>()
+            (candidate_boxes, cls_score, cls_idx)
+        })
+        .collect()
+}
+
+/// Filter the candidate boxes of a single image based on score and perform non-maximum
+/// suppression for each class.
+pub(crate) fn nms_candidates(
+    image_id: usize,
+    candidate_boxes: &[f32],
+    cls_score: &[f32],
+    cls_idx: &[usize],
+    num_classes: usize,
+    iou_threshold: IoUThreshold,
+    score_threshold: ConfThreshold,
+) -> Vec<Vec<Detection>> {
+    let num_boxes = cls_score.len();
+
+    // Per-class filtering based on score
+    let mut bboxes = (0..num_classes)
+        .map(|cls_id| {
+            // [num_boxes, 1]
+            (0..num_boxes)
+                .filter_map(|box_idx| {
+                    let box_cls_idx = cls_idx[box_idx];
+                    if box_cls_idx != cls_id {
+                        return None;
+                    }
+                    let box_cls_score = cls_score[box_idx];
+                    if box_cls_score >= score_threshold.value() {
+                        let bbox = &candidate_boxes[box_idx * 4..box_idx * 4 + 4];
+                        Some(Detection::new(
+                            image_id,
+                            [
+                                bbox[0] - bbox[2] / 2.,
+                                bbox[1] - bbox[3] / 2.,
+                                bbox[0] + bbox[2] / 2.,
+                                bbox[1] + bbox[3] / 2.,
+                            ],
+                            box_cls_score,
+                            cls_id,
+                        ))
+                    } else {
+                        None
+                    }
                 })
+                .sorted_unstable_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
                 .collect::<Vec<_>>()
         })
         .collect::<Vec<_>>();
 
-    for batch_bboxes in bboxes.iter_mut().take(batch_size) {
-        non_maximum_suppression(batch_bboxes, iou_threshold);
-    }
+    non_maximum_suppression(&mut bboxes, iou_threshold);
 
     bboxes
 }
 
-/// Intersection over union of two bounding boxes.
-pub fn iou(b1: &BoundingBox, b2: &BoundingBox) -> f32 {
-    let b1_area = (b1.xmax - b1.xmin + 1.) * (b1.ymax - b1.ymin + 1.);
-    let b2_area = (b2.xmax - b2.xmin + 1.) * (b2.ymax - b2.ymin + 1.);
-    let i_xmin = b1.xmin.max(b2.xmin);
-    let i_xmax = b1.xmax.min(b2.xmax);
-    let i_ymin = b1.ymin.max(b2.ymin);
-    let i_ymax = b1.ymax.min(b2.ymax);
+/// Intersection over union of the bounding boxes of two detections.
+pub fn iou(d1: &Detection, d2: &Detection) -> f32 {
+    let [b1_xmin, b1_ymin, b1_xmax, b1_ymax] = d1.box_xyxy;
+    let [b2_xmin, b2_ymin, b2_xmax, b2_ymax] = d2.box_xyxy;
+    let b1_area = (b1_xmax - b1_xmin + 1.) * (b1_ymax - b1_ymin + 1.);
+    let b2_area = (b2_xmax - b2_xmin + 1.) * (b2_ymax - b2_ymin + 1.);
+    let i_xmin = b1_xmin.max(b2_xmin);
+    let i_xmax = b1_xmax.min(b2_xmax);
+    let i_ymin = b1_ymin.max(b2_ymin);
+    let i_ymax = b1_ymax.min(b2_ymax);
     let i_area = (i_xmax - i_xmin + 1.).max(0.) * (i_ymax - i_ymin + 1.).max(0.);
     i_area / (b1_area + b2_area - i_area)
 }
 
 /// Perform non-maximum suppression over boxes of the same class.
-pub fn non_maximum_suppression(bboxes: &mut [Vec<BoundingBox>], threshold: f32) {
+pub fn non_maximum_suppression(bboxes: &mut [Vec<Detection>], threshold: IoUThreshold) {
     for bboxes_for_class in bboxes.iter_mut() {
-        bboxes_for_class.sort_by(|b1, b2| b2.confidence.partial_cmp(&b1.confidence).unwrap());
+        bboxes_for_class.sort_by(|b1, b2| b2.score.partial_cmp(&b1.score).unwrap());
         let mut current_index = 0;
         for index in 0..bboxes_for_class.len() {
             let mut drop = false;
             for prev_index in 0..current_index {
                 let iou = iou(&bboxes_for_class[prev_index], &bboxes_for_class[index]);
-                if iou > threshold {
+                if iou > threshold.value() {
                     drop = true;
                     break;
                 }
@@ -133,3 +169,69 @@ pub fn non_maximum_suppression(bboxes: &mut [Vec<BoundingBox>], threshold: f32)
         bboxes_for_class.truncate(current_index);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn nms_returns_detections() {
+        let device = Default::default();
+        // Two overlapping boxes of class 1 and a box of class 0 in the second image
+        let boxes = Tensor::<TestBackend, 3>::from_floats(
+            [
+                [
+                    [50., 50., 20., 20.],
+                    [51., 51., 20., 20.],
+                    [10., 10., 4., 4.],
+                ],
+                [
+                    [30., 30., 10., 10.],
+                    [80., 80., 10., 10.],
+                    [10., 10., 4., 4.],
+                ],
+            ],
+            &device,
+        );
+        let scores = Tensor::<TestBackend, 3>::from_floats(
+            [
+                [[0.1, 0.9], [0.2, 0.8], [0.05, 0.1]],
+                [[0.7, 0.1], [0.1, 0.2], [0.05, 0.1]],
+            ],
+            &device,
+        );
+
+        let detections = nms(
+            boxes,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.5).unwrap(),
+        );
+
+        assert_eq!(detections.len(), 2);
+        assert!(detections[0][0].is_empty());
+        assert_eq!(
+            detections[0][1],
+            vec![Detection::new(0, [40., 40., 60., 60.], 0.9, 1)]
+        );
+        assert_eq!(
+            detections[1][0],
+            vec![Detection::new(1, [25., 25., 35., 35.], 0.7, 0)]
+        );
+        assert!(detections[1][1].is_empty());
+    }
+
+    #[test]
+    fn iou_of_detections() {
+        let d1 = Detection::new(0, [0., 0., 9., 9.], 0.9, 0);
+        let d2 = Detection::new(0, [5., 0., 14., 9.], 0.8, 0);
+
+        // Inclusive pixel coordinates: 10x10 boxes overlapping on 5x10 pixels
+        assert!((iou(&d1, &d2) - 50. / 150.).abs() < 1e-6);
+        assert_eq!(iou(&d1, &d1), 1.);
+    }
+}
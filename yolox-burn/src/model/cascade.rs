@@ -0,0 +1,579 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{Linear, LinearConfig, Relu},
+    tensor::{activation::softmax, backend::Backend, Device, Int, Tensor},
+};
+
+use super::blocks::bilinear_sample_points;
+use crate::types::IoUThreshold;
+
+/// Default number of channels of the feature maps.
+const IN_CHANNELS: usize = 256;
+/// Default number of hidden units of the box head MLP.
+const HIDDEN_DIM: usize = 1024;
+/// Default strides of the feature maps (FPN levels P3 to P5).
+const STRIDES: [usize; 3] = [8, 16, 32];
+/// Number of sampling points along each axis of a RoI Align bin.
+const SAMPLING_RATIO: usize = 2;
+/// Canonical box size and level of the FPN level assignment heuristic.
+const CANONICAL_SIZE: f32 = 224.;
+const CANONICAL_LEVEL: f32 = 4.;
+/// Maximum log-scale box size update, to avoid overflows of the exponential.
+const MAX_LOG_SCALE: f32 = 4.135; // ln(1000 / 16)
+
+/// [RoI Align](https://arxiv.org/abs/1703.06870): bilinearly pool a fixed size feature map for
+/// each region of interest.
+///
+/// # Arguments
+///
+/// * `features`: Feature map. Shape: `[batch_size, channels, height, width]`.
+/// * `rois` - Regions of interest `(batch_index, x1, y1, x2, y2)` in input image pixels. Shape:
+///   `[num_rois, 5]`.
+/// * `output_size` - Size of the pooled feature maps.
+/// * `stride` - Stride of the feature map relative to the input image.
+///
+/// # Returns
+///
+/// The pooled features with shape `[num_rois, channels, output_size, output_size]`.
+pub fn roi_align<B: Backend>(
+    features: Tensor<B, 4>,
+    rois: Tensor<B, 2>,
+    output_size: usize,
+    stride: usize,
+) -> Tensor<B, 4> {
+    let [n, c, h, w] = features.dims();
+    let [m, _] = rois.dims();
+    let device = features.device();
+    let scale = 1. / stride as f32;
+
+    // Feature map of the image of each RoI [M, C, H * W]
+    let batch_indices = rois.clone().slice([0..m, 0..1]).reshape([m]).int();
+    let flat = features.reshape([n, c, h * w]).select(0, batch_indices);
+
+    // Continuous coordinates, with pixel centers at half-integers (i.e., aligned)
+    let coord = |i: usize| {
+        rois.clone()
+            .slice([0..m, i..i + 1])
+            .mul_scalar(scale)
+            .sub_scalar(0.5)
+    };
+    let (x1, y1, x2, y2) = (coord(1), coord(2), coord(3), coord(4));
+    let bin_w = (x2 - x1.clone()).div_scalar(output_size as f32);
+    let bin_h = (y2 - y1.clone()).div_scalar(output_size as f32);
+
+    // Regularly spaced sampling points in each bin, in units of bins [1, S * R]
+    let s = output_size * SAMPLING_RATIO;
+    let grid = Tensor::<B, 1, Int>::arange(0..s as i64, &device)
+        .float()
+        .add_scalar(0.5)
+        .div_scalar(SAMPLING_RATIO as f32)
+        .reshape([1, s]);
+    let ys = y1 + grid.clone() * bin_h;
+    let xs = x1 + grid * bin_w;
+
+    let py = ys.reshape([m, s, 1]).repeat_dim(2, s).reshape([m, s * s]);
+    let px = xs.reshape([m, 1, s]).repeat_dim(1, s).reshape([m, s * s]);
+
+    // Average of the sampling points of each bin
+    bilinear_sample_points(flat, py, px, [h, w])
+        .reshape([
+            m,
+            c,
+            output_size,
+            SAMPLING_RATIO,
+            output_size,
+            SAMPLING_RATIO,
+        ])
+        .mean_dim(5)
+        .mean_dim(3)
+        .reshape([m, c, output_size, output_size])
+}
+
+/// Pool the features of each RoI from the feature pyramid level matching its size, following
+/// the [FPN](https://arxiv.org/abs/1612.03144) level assignment heuristic.
+///
+/// # Shapes
+///   - features: `[batch_size, channels, H / strides[i], W / strides[i]]` for each level `i`
+///   - rois: `[num_rois, 5]`
+///   - output: `[num_rois, channels, output_size, output_size]`
+pub fn multi_level_roi_align<B: Backend>(
+    features: Vec<Tensor<B, 4>>,
+    rois: Tensor<B, 2>,
+    output_size: usize,
+    strides: &[usize],
+) -> Tensor<B, 4> {
+    assert_eq!(
+        features.len(),
+        strides.len(),
+        "expected one stride per level"
+    );
+    let [m, _] = rois.dims();
+
+    if features.len() == 1 {
+        let features = features.into_iter().next().unwrap();
+        return roi_align(features, rois, output_size, strides[0]);
+    }
+
+    // Target level of each RoI [M, 1, 1, 1]
+    let min_level = strides[0].ilog2() as f32;
+    let max_level = min_level + (strides.len() - 1) as f32;
+    let wh = rois.clone().slice([0..m, 3..5]) - rois.clone().slice([0..m, 1..3]);
+    let wh = wh.clamp_min(1.);
+    let size = (wh.clone().slice([0..m, 0..1]) * wh.slice([0..m, 1..2])).sqrt();
+    let level = (size.div_scalar(CANONICAL_SIZE).log() / core::f32::consts::LN_2)
+        .add_scalar(CANONICAL_LEVEL)
+        .clamp(min_level, max_level)
+        // Floor through the truncation of positive values
+        .int()
+        .float()
+        .reshape([m, 1, 1, 1]);
+
+    features
+        .into_iter()
+        .zip(strides.iter())
+        .enumerate()
+        .map(|(i, (features, &stride))| {
+            let mask = level.clone().equal_elem(min_level + i as f32).float();
+            roi_align(features, rois.clone(), output_size, stride) * mask
+        })
+        .reduce(|acc, pooled| acc + pooled)
+        .unwrap()
+}
+
+/// Apply the normalized box deltas `(dx, dy, dw, dh)` to the boxes `(x1, y1, x2, y2)`.
+///
+/// # Shapes
+///   - boxes: `[num_boxes, 4]`
+///   - deltas: `[num_boxes, 4]`
+///   - output: `[num_boxes, 4]`
+fn apply_deltas<B: Backend>(
+    boxes: Tensor<B, 2>,
+    deltas: Tensor<B, 2>,
+    stds: [f32; 2],
+) -> Tensor<B, 2> {
+    let [m, _] = boxes.dims();
+    let col = |t: &Tensor<B, 2>, i: usize| t.clone().slice([0..m, i..i + 1]);
+
+    let w = col(&boxes, 2) - col(&boxes, 0);
+    let h = col(&boxes, 3) - col(&boxes, 1);
+    let cx = col(&boxes, 0) + w.clone().mul_scalar(0.5);
+    let cy = col(&boxes, 1) + h.clone().mul_scalar(0.5);
+
+    let cx = cx + col(&deltas, 0).mul_scalar(stds[0]) * w.clone();
+    let cy = cy + col(&deltas, 1).mul_scalar(stds[0]) * h.clone();
+    let half_w = col(&deltas, 2)
+        .mul_scalar(stds[1])
+        .clamp_max(MAX_LOG_SCALE)
+        .exp()
+        * w.mul_scalar(0.5);
+    let half_h = col(&deltas, 3)
+        .mul_scalar(stds[1])
+        .clamp_max(MAX_LOG_SCALE)
+        .exp()
+        * h.mul_scalar(0.5);
+
+    Tensor::cat(
+        vec![
+            cx.clone() - half_w.clone(),
+            cy.clone() - half_h.clone(),
+            cx + half_w,
+            cy + half_h,
+        ],
+        1,
+    )
+}
+
+/// RoI box head: [multi-level RoI Align](multi_level_roi_align) followed by a two-layer MLP, a
+/// classifier and a class-agnostic box regressor.
+#[derive(Module, Debug)]
+pub struct RoiHead<B: Backend> {
+    fc1: Linear<B>,
+    fc2: Linear<B>,
+    cls_score: Linear<B>,
+    bbox_pred: Linear<B>,
+    activation: Relu,
+    pool_size: usize,
+}
+
+impl<B: Backend> RoiHead<B> {
+    /// Compute the class logits (including the background class at index 0) and the box deltas
+    /// of each RoI.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, channels, H / strides[i], W / strides[i]]` for each level `i`
+    ///   - rois: `[num_rois, 5]`
+    ///   - output: `[num_rois, num_classes + 1]` and `[num_rois, 4]`
+    pub fn forward(
+        &self,
+        features: Vec<Tensor<B, 4>>,
+        rois: Tensor<B, 2>,
+        strides: &[usize],
+    ) -> (Tensor<B, 2>, Tensor<B, 2>) {
+        let pooled = multi_level_roi_align(features, rois, self.pool_size, strides);
+        let x = pooled.flatten::<2>(1, 3);
+
+        let x = self.activation.forward(self.fc1.forward(x));
+        let x = self.activation.forward(self.fc2.forward(x));
+
+        (self.cls_score.forward(x.clone()), self.bbox_pred.forward(x))
+    }
+}
+
+/// [RoI head](RoiHead) configuration.
+pub struct RoiHeadConfig {
+    in_channels: usize,
+    hidden_dim: usize,
+    pool_size: usize,
+    num_classes: usize,
+}
+
+impl RoiHeadConfig {
+    /// Create a new instance of the RoI head [config](RoiHeadConfig).
+    pub fn new(
+        in_channels: usize,
+        hidden_dim: usize,
+        pool_size: usize,
+        num_classes: usize,
+    ) -> Self {
+        Self {
+            in_channels,
+            hidden_dim,
+            pool_size,
+            num_classes,
+        }
+    }
+
+    /// Initialize a new [RoI head](RoiHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> RoiHead<B> {
+        let in_dim = self.in_channels * self.pool_size * self.pool_size;
+
+        RoiHead {
+            fc1: LinearConfig::new(in_dim, self.hidden_dim).init(device),
+            fc2: LinearConfig::new(self.hidden_dim, self.hidden_dim).init(device),
+            cls_score: LinearConfig::new(self.hidden_dim, self.num_classes + 1).init(device),
+            bbox_pred: LinearConfig::new(self.hidden_dim, 4).init(device),
+            activation: Relu::new(),
+            pool_size: self.pool_size,
+        }
+    }
+}
+
+/// Predictions of a [cascade](CascadeDetection) stage.
+pub struct StageOutput<B: Backend> {
+    /// Proposals `(batch_index, x1, y1, x2, y2)` refined by the stage. Shape: `[num_rois, 5]`.
+    pub proposals: Tensor<B, 2>,
+    /// Class logits, with the background class at index 0. Shape: `[num_rois, num_classes + 1]`.
+    pub cls_logits: Tensor<B, 2>,
+    /// Normalized box deltas. Shape: `[num_rois, 4]`.
+    pub box_deltas: Tensor<B, 2>,
+    /// Refined boxes `(x1, y1, x2, y2)`, used as proposals by the next stage. Shape:
+    /// `[num_rois, 4]`.
+    pub boxes: Tensor<B, 2>,
+    /// IoU threshold of the positive proposals when training the stage.
+    pub iou_threshold: IoUThreshold,
+}
+
+/// [Cascade R-CNN](https://arxiv.org/abs/1712.00726) multi-stage box refinement.
+///
+/// Each stage has its own [RoI head](RoiHead), trained with a higher IoU threshold than the
+/// previous one, and refines the boxes predicted by the previous stage. The gradients do not flow
+/// between stages through the proposals.
+#[derive(Module, Debug)]
+pub struct CascadeDetection<B: Backend> {
+    stages: Vec<RoiHead<B>>,
+    iou_thresholds: Vec<f32>,
+    strides: Vec<usize>,
+}
+
+impl<B: Backend> CascadeDetection<B> {
+    /// Compute the predictions of each stage.
+    ///
+    /// # Arguments
+    ///
+    /// * `features`: Feature pyramid maps, from the highest to the lowest resolution.
+    /// * `proposals` - Region proposals `(batch_index, x1, y1, x2, y2)` in input image pixels.
+    ///   Shape: `[num_rois, 5]`.
+    pub fn forward(
+        &self,
+        features: Vec<Tensor<B, 4>>,
+        proposals: Tensor<B, 2>,
+    ) -> Vec<StageOutput<B>> {
+        let [m, _] = proposals.dims();
+        let batch_indices = proposals.clone().slice([0..m, 0..1]);
+
+        let mut proposals = proposals;
+        let mut outputs = Vec::with_capacity(self.stages.len());
+        for (i, (head, &iou_threshold)) in self
+            .stages
+            .iter()
+            .zip(self.iou_thresholds.iter())
+            .enumerate()
+        {
+            let (cls_logits, box_deltas) =
+                head.forward(features.clone(), proposals.clone(), &self.strides);
+
+            // The deltas are normalized with smaller standard deviations as the boxes get more
+            // accurate
+            let stds = [0.1 / (i + 1) as f32, 0.2 / (i + 1) as f32];
+            let boxes = apply_deltas(
+                proposals.clone().slice([0..m, 1..5]),
+                box_deltas.clone(),
+                stds,
+            );
+
+            outputs.push(StageOutput {
+                proposals: proposals.clone(),
+                cls_logits,
+                box_deltas,
+                boxes: boxes.clone(),
+                iou_threshold: IoUThreshold::from(iou_threshold),
+            });
+            proposals = Tensor::cat(vec![batch_indices.clone(), boxes.detach()], 1);
+        }
+
+        outputs
+    }
+
+    /// Compute the final predictions: the class probabilities averaged over the stages and the
+    /// boxes refined by the last stage.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, channels, H / strides[i], W / strides[i]]` for each level `i`
+    ///   - proposals: `[num_rois, 5]`
+    ///   - output: `[num_rois, num_classes + 1]` and `[num_rois, 4]`
+    pub fn predict(
+        &self,
+        features: Vec<Tensor<B, 4>>,
+        proposals: Tensor<B, 2>,
+    ) -> (Tensor<B, 2>, Tensor<B, 2>) {
+        let outputs = self.forward(features, proposals);
+        Self::average_stages(&outputs)
+    }
+
+    /// Average the class probabilities of all the stages, and return them with the boxes of the
+    /// last stage.
+    pub fn average_stages(outputs: &[StageOutput<B>]) -> (Tensor<B, 2>, Tensor<B, 2>) {
+        let num_stages = outputs.len();
+        assert!(num_stages > 0, "at least one stage output is required");
+
+        let scores = outputs
+            .iter()
+            .map(|output| softmax(output.cls_logits.clone(), 1))
+            .reduce(|acc, scores| acc + scores)
+            .unwrap()
+            .div_scalar(num_stages as f32);
+
+        (scores, outputs[num_stages - 1].boxes.clone())
+    }
+
+    /// Number of refinement stages.
+    pub fn num_stages(&self) -> usize {
+        self.stages.len()
+    }
+}
+
+/// [Cascade detection](CascadeDetection) configuration.
+pub struct CascadeDetectionConfig {
+    iou_thresholds: Vec<IoUThreshold>,
+    roi_pool_size: usize,
+    num_classes: usize,
+    in_channels: usize,
+    hidden_dim: usize,
+    strides: Vec<usize>,
+}
+
+impl CascadeDetectionConfig {
+    /// Create a new instance of the cascade detection [config](CascadeDetectionConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `num_stages`: Number of refinement stages (3 for Cascade R-CNN).
+    /// * `iou_thresholds` - Increasing IoU threshold of each stage (e.g., `[0.5, 0.6, 0.7]`).
+    /// * `roi_pool_size` - Size of the pooled RoI features (e.g., 7).
+    /// * `num_classes` - Number of object classes, without the background class.
+    pub fn new(
+        num_stages: usize,
+        iou_thresholds: Vec<IoUThreshold>,
+        roi_pool_size: usize,
+        num_classes: usize,
+    ) -> Self {
+        assert!(num_stages > 0, "at least one stage is required");
+        assert_eq!(
+            iou_thresholds.len(),
+            num_stages,
+            "expected one IoU threshold per stage"
+        );
+
+        Self {
+            iou_thresholds,
+            roi_pool_size,
+            num_classes,
+            in_channels: IN_CHANNELS,
+            hidden_dim: HIDDEN_DIM,
+            strides: STRIDES.to_vec(),
+        }
+    }
+
+    /// Set the number of channels of the feature maps (defaults to 256).
+    pub fn with_in_channels(mut self, in_channels: usize) -> Self {
+        self.in_channels = in_channels;
+        self
+    }
+
+    /// Set the number of hidden units of the box head MLPs (defaults to 1024).
+    pub fn with_hidden_dim(mut self, hidden_dim: usize) -> Self {
+        self.hidden_dim = hidden_dim;
+        self
+    }
+
+    /// Set the strides of the feature maps, which should be powers of 2 (defaults to
+    /// `[8, 16, 32]`).
+    pub fn with_strides(mut self, strides: Vec<usize>) -> Self {
+        assert!(
+            strides.iter().all(|s| s.is_power_of_two()),
+            "the strides should be powers of 2"
+        );
+        self.strides = strides;
+        self
+    }
+
+    /// Initialize a new [cascade detection](CascadeDetection) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> CascadeDetection<B> {
+        let head = RoiHeadConfig::new(
+            self.in_channels,
+            self.hidden_dim,
+            self.roi_pool_size,
+            self.num_classes,
+        );
+
+        CascadeDetection {
+            stages: self
+                .iou_thresholds
+                .iter()
+                .map(|_| head.init(device))
+                .collect(),
+            iou_thresholds: self.iou_thresholds.iter().map(|t| t.value()).collect(),
+            strides: self.strides.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn cascade(device: &Device<TestBackend>) -> CascadeDetection<TestBackend> {
+        let iou_thresholds = [0.5, 0.6, 0.7].map(IoUThreshold::from).to_vec();
+
+        CascadeDetectionConfig::new(3, iou_thresholds, 2, 3)
+            .with_in_channels(4)
+            .with_hidden_dim(16)
+            .init(device)
+    }
+
+    fn inputs(
+        device: &Device<TestBackend>,
+    ) -> (Vec<Tensor<TestBackend, 4>>, Tensor<TestBackend, 2>) {
+        let features = [8, 4, 2]
+            .into_iter()
+            .map(|size| Tensor::random([2, 4, size, size], Distribution::Default, device))
+            .collect();
+        let proposals = Tensor::from_floats(
+            [
+                [0., 4., 4., 20., 20.],
+                [0., 10., 8., 60., 40.],
+                [1., 0., 0., 64., 64.],
+                [1., 30., 12., 42., 50.],
+            ],
+            device,
+        );
+
+        (features, proposals)
+    }
+
+    #[test]
+    fn one_head_per_stage() {
+        let cascade = cascade(&Default::default());
+
+        assert_eq!(cascade.num_stages(), 3);
+        assert_eq!(cascade.stages.len(), 3);
+        assert_eq!(cascade.iou_thresholds, vec![0.5, 0.6, 0.7]);
+    }
+
+    #[test]
+    fn stage_output_shapes() {
+        let device = Default::default();
+        let cascade = cascade(&device);
+        let (features, proposals) = inputs(&device);
+
+        let outputs = cascade.forward(features, proposals.clone());
+
+        assert_eq!(outputs.len(), 3);
+        for output in outputs.iter() {
+            assert_eq!(output.proposals.dims(), [4, 5]);
+            assert_eq!(output.cls_logits.dims(), [4, 4]);
+            assert_eq!(output.box_deltas.dims(), [4, 4]);
+            assert_eq!(output.boxes.dims(), [4, 4]);
+        }
+        assert_eq!(outputs[2].iou_threshold, IoUThreshold::from(0.7));
+
+        // The first stage refines the input proposals, the next ones the boxes of the previous
+        // stage, keeping the batch indices
+        outputs[0]
+            .proposals
+            .clone()
+            .into_data()
+            .assert_eq(&proposals.clone().into_data(), true);
+        for (prev, next) in outputs.iter().zip(outputs.iter().skip(1)) {
+            let expected = Tensor::cat(
+                vec![proposals.clone().slice([0..4, 0..1]), prev.boxes.clone()],
+                1,
+            );
+            next.proposals
+                .clone()
+                .into_data()
+                .assert_eq(&expected.into_data(), true);
+        }
+    }
+
+    #[test]
+    fn averaged_scores_sum_to_one() {
+        let device = Default::default();
+        let cascade = cascade(&device);
+        let (features, proposals) = inputs(&device);
+
+        let outputs = cascade.forward(features.clone(), proposals.clone());
+        let (scores, boxes) = CascadeDetection::average_stages(&outputs);
+
+        assert_eq!(scores.dims(), [4, 4]);
+        scores
+            .sum_dim(1)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32], [1.], [1.], [1.]]), 5);
+        boxes
+            .into_data()
+            .assert_eq(&outputs[2].boxes.clone().into_data(), true);
+
+        let (predicted, _) = cascade.predict(features, proposals);
+        predicted
+            .into_data()
+            .assert_approx_eq(&scores.into_data(), 5);
+    }
+
+    #[test]
+    fn zero_deltas_keep_boxes() {
+        let device = Default::default();
+        let boxes = Tensor::<TestBackend, 2>::from_floats([[4., 4., 20., 12.]], &device);
+
+        let refined = apply_deltas(boxes.clone(), Tensor::zeros([1, 4], &device), [0.1, 0.2]);
+
+        refined.into_data().assert_approx_eq(&boxes.into_data(), 5);
+    }
+}
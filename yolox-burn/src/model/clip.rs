@@ -0,0 +1,347 @@
+use alloc::vec;
+use burn::{
+    module::{Module, Param},
+    nn::{
+        attention::generate_autoregressive_mask,
+        transformer::{TransformerEncoder, TransformerEncoderConfig, TransformerEncoderInput},
+        Embedding, EmbeddingConfig, LayerNorm, LayerNormConfig, Linear, LinearConfig,
+    },
+    tensor::{backend::Backend, Device, Distribution, Int, Tensor},
+};
+
+use super::{
+    heads::l2_normalize,
+    vit::{Vit, VitConfig, VitVariant},
+};
+
+/// Default input image size.
+const IMAGE_SIZE: usize = 224;
+/// Dimension of each attention head of the text encoder.
+const TEXT_HEAD_DIM: usize = 64;
+/// Initial temperature of the similarity logits.
+const INIT_TEMPERATURE: f64 = 0.07;
+/// Maximum scale of the similarity logits.
+const MAX_LOGIT_SCALE: f64 = 100.;
+
+/// Image encoder of [CLIP](Clip): a [Vision Transformer](Vit) whose class token is projected to
+/// the joint embedding space.
+#[derive(Module, Debug)]
+pub struct ClipImageEncoder<B: Backend> {
+    vit: Vit<B>,
+    projection: Linear<B>,
+}
+
+impl<B: Backend> ClipImageEncoder<B> {
+    /// # Shapes
+    ///   - images: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, embed_dim]`
+    pub fn forward(&self, images: Tensor<B, 4>) -> Tensor<B, 2> {
+        let tokens = self.vit.forward(images);
+        let [batch_size, _, width] = tokens.dims();
+        let cls = tokens
+            .slice([0..batch_size, 0..1, 0..width])
+            .reshape([batch_size, width]);
+
+        self.projection.forward(cls)
+    }
+}
+
+/// Text encoder of [CLIP](Clip): a causal transformer over the text tokens, whose output at the
+/// end of text (EOS) token is projected to the joint embedding space.
+///
+/// As in the CLIP tokenizer, the EOS token is expected to have the largest token ID of each
+/// sequence.
+#[derive(Module, Debug)]
+pub struct ClipTextEncoder<B: Backend> {
+    token_embedding: Embedding<B>,
+    pos_embed: Param<Tensor<B, 3>>,
+    transformer: TransformerEncoder<B>,
+    norm: LayerNorm<B>,
+    projection: Linear<B>,
+}
+
+impl<B: Backend> ClipTextEncoder<B> {
+    /// # Shapes
+    ///   - tokens: `[batch_size, seq_length]` with `seq_length <= text_max_length`
+    ///   - output: `[batch_size, embed_dim]`
+    pub fn forward(&self, tokens: Tensor<B, 2, Int>) -> Tensor<B, 2> {
+        let [batch_size, seq_length] = tokens.dims();
+        let device = tokens.device();
+
+        let x = self.token_embedding.forward(tokens.clone());
+        let [_, _, width] = x.dims();
+        let x = x + self.pos_embed.val().slice([0..1, 0..seq_length, 0..width]);
+
+        let mask = generate_autoregressive_mask::<B>(batch_size, seq_length, &device);
+        let x = self
+            .transformer
+            .forward(TransformerEncoderInput::new(x).mask_attn(mask));
+        let x = self.norm.forward(x);
+
+        // Features of the EOS token
+        let eos = tokens.argmax(1).unsqueeze_dim::<3>(2).repeat_dim(2, width);
+        let x = x.gather(1, eos).reshape([batch_size, width]);
+
+        self.projection.forward(x)
+    }
+}
+
+/// [CLIP](https://arxiv.org/abs/2103.00020) image and text encoders, trained contrastively so that
+/// the embeddings of matching images and texts are close.
+///
+/// The text embeddings can be used as class embeddings for zero-shot classification (e.g., with
+/// [CosineSimilarityHead](super::heads::CosineSimilarityHead)) or as text queries for
+/// open-vocabulary detection (e.g., with [OWL-ViT](super::owl_vit::OwlVit)).
+#[derive(Module, Debug)]
+pub struct Clip<B: Backend> {
+    image_encoder: ClipImageEncoder<B>,
+    text_encoder: ClipTextEncoder<B>,
+    /// Log of the scale of the similarity logits.
+    logit_scale: Param<Tensor<B, 1>>,
+}
+
+impl<B: Backend> Clip<B> {
+    /// Compute the L2 normalized image embeddings.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, embed_dim]`
+    pub fn encode_image(&self, x: Tensor<B, 4>) -> Tensor<B, 2> {
+        l2_normalize(self.image_encoder.forward(x))
+    }
+
+    /// Compute the L2 normalized text embeddings.
+    ///
+    /// # Shapes
+    ///   - tokens: `[batch_size, seq_length]`
+    ///   - output: `[batch_size, embed_dim]`
+    pub fn encode_text(&self, tokens: Tensor<B, 2, Int>) -> Tensor<B, 2> {
+        l2_normalize(self.text_encoder.forward(tokens))
+    }
+
+    /// Compute the scaled cosine similarity logits between the images and the texts.
+    ///
+    /// # Shapes
+    ///   - images: `[num_images, 3, height, width]`
+    ///   - tokens: `[num_texts, seq_length]`
+    ///   - output: `[num_images, num_texts]` (the logits per text are the transpose)
+    pub fn forward(&self, images: Tensor<B, 4>, tokens: Tensor<B, 2, Int>) -> Tensor<B, 2> {
+        let image_embeds = self.encode_image(images);
+        let text_embeds = self.encode_text(tokens);
+
+        image_embeds
+            .matmul(text_embeds.transpose())
+            .mul(self.logit_scale().unsqueeze())
+    }
+
+    /// Scale of the similarity logits, i.e., the exponential of the learned log scale, clamped
+    /// to 100. Shape: `[1]`.
+    pub fn logit_scale(&self) -> Tensor<B, 1> {
+        self.logit_scale.val().exp().clamp_max(MAX_LOGIT_SCALE)
+    }
+}
+
+/// [CLIP](Clip) configuration.
+pub struct ClipConfig {
+    image_variant: VitVariant,
+    image_size: usize,
+    text_max_length: usize,
+    vocab_size: usize,
+    transformer_layers: usize,
+    transformer_width: usize,
+    embed_dim: usize,
+}
+
+impl ClipConfig {
+    /// Create a new instance of the CLIP [config](ClipConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `image_variant`: ViT variant of the image encoder.
+    /// * `text_max_length` - Maximum number of text tokens (77 for CLIP).
+    /// * `vocab_size` - Size of the text vocabulary (49408 for CLIP).
+    /// * `transformer_layers` - Number of transformer blocks of the text encoder.
+    /// * `transformer_width` - Embedding dimension of the text encoder (a multiple of 64).
+    /// * `embed_dim` - Dimension of the joint image and text embeddings.
+    pub fn new(
+        image_variant: VitVariant,
+        text_max_length: usize,
+        vocab_size: usize,
+        transformer_layers: usize,
+        transformer_width: usize,
+        embed_dim: usize,
+    ) -> Self {
+        assert!(
+            transformer_width % TEXT_HEAD_DIM == 0,
+            "text transformer width should be a multiple of {TEXT_HEAD_DIM}"
+        );
+
+        Self {
+            image_variant,
+            image_size: IMAGE_SIZE,
+            text_max_length,
+            vocab_size,
+            transformer_layers,
+            transformer_width,
+            embed_dim,
+        }
+    }
+
+    /// CLIP ViT-B/32.
+    pub fn vit_b32() -> Self {
+        Self::new(VitVariant::B32, 77, 49408, 12, 512, 512)
+    }
+
+    /// CLIP ViT-B/16.
+    pub fn vit_b16() -> Self {
+        Self::new(VitVariant::B16, 77, 49408, 12, 512, 512)
+    }
+
+    /// CLIP ViT-L/14.
+    pub fn vit_l14() -> Self {
+        Self::new(VitVariant::L14, 77, 49408, 12, 768, 768)
+    }
+
+    /// Set the input image size (defaults to 224).
+    pub fn with_image_size(mut self, image_size: usize) -> Self {
+        self.image_size = image_size;
+        self
+    }
+
+    /// Initialize a new [CLIP](Clip) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Clip<B> {
+        let vit = VitConfig::from_variant(self.image_variant, self.image_size);
+
+        self.init_with_vit(&vit, device)
+    }
+
+    /// Initialize a new [CLIP](Clip) module with the given image encoder backbone.
+    fn init_with_vit<B: Backend>(&self, vit: &VitConfig, device: &Device<B>) -> Clip<B> {
+        let width = self.transformer_width;
+
+        let image_encoder = ClipImageEncoder {
+            projection: LinearConfig::new(vit.embed_dim(), self.embed_dim)
+                .with_bias(false)
+                .init(device),
+            vit: vit.init(device),
+        };
+
+        let text_encoder = ClipTextEncoder {
+            token_embedding: EmbeddingConfig::new(self.vocab_size, width).init(device),
+            pos_embed: Param::from_tensor(Tensor::random(
+                [1, self.text_max_length, width],
+                Distribution::Normal(0., 0.01),
+                device,
+            )),
+            transformer: TransformerEncoderConfig::new(
+                width,
+                4 * width,
+                width / TEXT_HEAD_DIM,
+                self.transformer_layers,
+            )
+            .with_dropout(0.)
+            .with_norm_first(true)
+            .init(device),
+            norm: LayerNormConfig::new(width).init(device),
+            projection: LinearConfig::new(width, self.embed_dim)
+                .with_bias(false)
+                .init(device),
+        };
+
+        Clip {
+            image_encoder,
+            text_encoder,
+            logit_scale: Param::from_tensor(Tensor::from_floats(
+                [(1. / INIT_TEMPERATURE).ln() as f32],
+                device,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    /// CLIP with a tiny ViT on 32x32 images and a single layer text transformer.
+    fn clip(device: &Device<TestBackend>) -> Clip<TestBackend> {
+        let vit = VitConfig::new(32, 8, 3, 16, 1, 2, 2.);
+        ClipConfig::new(VitVariant::B32, 8, 100, 1, 64, 12).init_with_vit(&vit, device)
+    }
+
+    #[test]
+    fn image_embeddings_unit_norm() {
+        let device = Default::default();
+        let clip = clip(&device);
+        let images =
+            Tensor::<TestBackend, 4>::random([3, 3, 32, 32], Distribution::Default, &device);
+
+        let embeddings = clip.encode_image(images);
+
+        assert_eq!(embeddings.dims(), [3, 12]);
+        embeddings
+            .powf_scalar(2.)
+            .sum_dim(1)
+            .sqrt()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32], [1.], [1.]]), 4);
+    }
+
+    #[test]
+    fn text_embeddings_unit_norm() {
+        let device = Default::default();
+        let clip = clip(&device);
+        // The EOS token has the largest ID of each sequence
+        let tokens =
+            Tensor::<TestBackend, 2, Int>::from_ints([[1, 5, 99, 0], [3, 99, 0, 0]], &device);
+
+        let embeddings = clip.encode_text(tokens);
+
+        assert_eq!(embeddings.dims(), [2, 12]);
+        embeddings
+            .powf_scalar(2.)
+            .sum_dim(1)
+            .sqrt()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32], [1.]]), 4);
+    }
+
+    #[test]
+    fn logit_scale_initialization() {
+        let clip = clip(&Default::default());
+
+        clip.logit_scale
+            .val()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([2.65926f32]), 5);
+        clip.logit_scale()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([1. / 0.07f32]), 3);
+    }
+
+    #[test]
+    fn similarity_logits_shape() {
+        let device = Default::default();
+        let clip = clip(&device);
+        let images =
+            Tensor::<TestBackend, 4>::random([2, 3, 32, 32], Distribution::Default, &device);
+        let tokens = Tensor::<TestBackend, 2, Int>::from_ints([[1, 99], [2, 99], [3, 99]], &device);
+
+        let logits = clip.forward(images, tokens);
+
+        assert_eq!(logits.dims(), [2, 3]);
+        // Cosine similarities scaled by 1 / 0.07
+        for logit in logits.into_data().to_vec::<f32>().unwrap() {
+            assert!(logit.abs() <= 1. / 0.07 + 1e-3);
+        }
+    }
+
+    #[test]
+    #[should_panic = "text transformer width should be a multiple of 64"]
+    fn invalid_text_width() {
+        ClipConfig::new(VitVariant::B32, 8, 100, 1, 48, 12);
+    }
+}
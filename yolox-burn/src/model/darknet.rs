@@ -3,21 +3,160 @@ use core::cmp::max;
 use crate::model::blocks::expand;
 
 use super::{
-    blocks::{Conv, ConvConfig, Focus, FocusConfig},
+    blocks::{
+        BaseConv, BaseConvConfig, Conv, ConvConfig, Focus, FocusConfig, FocusFree, FocusFreeConfig,
+        ResNetDStem, ResNetDStemConfig,
+    },
     bottleneck::{CspBottleneck, CspBottleneckConfig, SppBottleneck, SppBottleneckConfig},
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
 };
 use burn::{
     module::Module,
     tensor::{backend::Backend, Device, Tensor},
 };
 
+#[cfg(feature = "pretrained")]
+use {
+    super::{
+        bottleneck::SPP_POOLING,
+        registry::{self, DownloadError},
+        weights::pytorch::yolox_load_args,
+    },
+    burn::{
+        module::ConstantRecord,
+        record::{FullPrecisionSettings, Recorder},
+    },
+    burn_import::pytorch::PyTorchFileRecorder,
+    std::path::Path,
+};
+
 /// Darknet backbone feature maps.
 pub struct DarknetFeatures<B: Backend>(pub Tensor<B, 4>, pub Tensor<B, 4>, pub Tensor<B, 4>);
 
+/// Type of stem block of the [backbone](CspDarknet).
+///
+/// All the stem blocks downsample the input by 2. The max pooling which follows the stem of
+/// ResNet is not part of the stem.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StemType {
+    /// [Focus block](Focus), as in the original YOLOX.
+    #[default]
+    Focus,
+    /// Equivalent [strided convolution](FocusFree).
+    FocusFree,
+    /// Single `7x7` convolution with stride 2, as in the original ResNet.
+    Standard7x7,
+    /// Three `3x3` convolutions of [ResNet-D](ResNetDStem).
+    ResNetD,
+}
+
+/// Backbone stem block.
+#[derive(Module, Debug)]
+pub enum Stem<B: Backend> {
+    Focus(Focus<B>),
+    FocusFree(FocusFree<B>),
+    Standard7x7(BaseConv<B>),
+    ResNetD(ResNetDStem<B>),
+}
+
+impl<B: Backend> Stem<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        match self {
+            Self::Focus(stem) => stem.forward(x),
+            Self::FocusFree(stem) => stem.forward(x),
+            Self::Standard7x7(stem) => stem.forward(x),
+            Self::ResNetD(stem) => stem.forward(x),
+        }
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for Stem<B> {
+    fn freeze_batch_norms(self) -> Self {
+        match self {
+            Self::Focus(stem) => Self::Focus(stem.freeze_batch_norms()),
+            Self::FocusFree(stem) => Self::FocusFree(stem.freeze_batch_norms()),
+            Self::Standard7x7(stem) => Self::Standard7x7(stem.freeze_batch_norms()),
+            Self::ResNetD(stem) => Self::ResNetD(stem.freeze_batch_norms()),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for Stem<B> {
+    fn sync_batch_norms(self) -> Self {
+        match self {
+            Self::Focus(stem) => Self::Focus(stem.sync_batch_norms()),
+            Self::FocusFree(stem) => Self::FocusFree(stem.sync_batch_norms()),
+            Self::Standard7x7(stem) => Self::Standard7x7(stem.sync_batch_norms()),
+            Self::ResNetD(stem) => Self::ResNetD(stem.sync_batch_norms()),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for Stem<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        match self {
+            Self::Focus(m) => m.set_bn_momentum(momentum),
+            Self::FocusFree(m) => m.set_bn_momentum(momentum),
+            Self::Standard7x7(m) => m.set_bn_momentum(momentum),
+            Self::ResNetD(m) => m.set_bn_momentum(momentum),
+        }
+    }
+}
+
+/// [Stem block](Stem) configuration.
+pub struct StemConfig {
+    stem_type: StemType,
+    in_channels: usize,
+    out_channels: usize,
+}
+
+impl StemConfig {
+    /// Create a new instance of the stem block [config](StemConfig), with 3 input channels and 64
+    /// output channels.
+    pub fn new(stem_type: StemType) -> Self {
+        Self {
+            stem_type,
+            in_channels: 3,
+            out_channels: 64,
+        }
+    }
+
+    /// Set the number of input and output channels.
+    pub fn with_channels(mut self, in_channels: usize, out_channels: usize) -> Self {
+        self.in_channels = in_channels;
+        self.out_channels = out_channels;
+        self
+    }
+
+    /// Set the type of stem block (see [StemType]).
+    pub fn with_stem_type(mut self, stem_type: StemType) -> Self {
+        self.stem_type = stem_type;
+        self
+    }
+
+    /// Initialize a new [stem block](Stem) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Stem<B> {
+        let (c_in, c_out) = (self.in_channels, self.out_channels);
+        // YOLOX focus block
+        let focus = FocusConfig::new(c_in, c_out, 3, 1);
+
+        match self.stem_type {
+            StemType::Focus => Stem::Focus(focus.init(device)),
+            StemType::FocusFree => {
+                Stem::FocusFree(FocusFreeConfig::equivalent_to(&focus).init(device))
+            }
+            StemType::Standard7x7 => {
+                Stem::Standard7x7(BaseConvConfig::new(c_in, c_out, 7, 2, 1).init(device))
+            }
+            StemType::ResNetD => Stem::ResNetD(ResNetDStemConfig::new(c_in, c_out).init(device)),
+        }
+    }
+}
+
 /// [CSPDarknet-53](https://paperswithcode.com/method/cspdarknet53) backbone.
 #[derive(Module, Debug)]
 pub struct CspDarknet<B: Backend> {
-    stem: Focus<B>,
+    stem: Stem<B>,
     dark2: CspBlock<B>,
     dark3: CspBlock<B>,
     dark4: CspBlock<B>,
@@ -34,11 +173,63 @@ impl<B: Backend> CspDarknet<B> {
 
         DarknetFeatures(f1, f2, f3)
     }
+
+    /// Number of stages for [stage-wise](Self::forward_stage) inference.
+    pub fn num_stages(&self) -> usize {
+        4
+    }
+
+    /// Apply a single stage of the backbone: stem and dark2 (stride 4), dark3 (stride 8), dark4
+    /// (stride 16) or dark5 (stride 32).
+    pub fn forward_stage(&self, stage: usize, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        match stage {
+            0 => self.dark2.forward(self.stem.forward(x)),
+            1 => self.dark3.forward(x),
+            2 => self.dark4.forward(x),
+            3 => self.dark5.forward(x),
+            _ => panic!("invalid stage index {stage}"),
+        }
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for CspDarknet<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            stem: self.stem.freeze_batch_norms(),
+            dark2: self.dark2.freeze_batch_norms(),
+            dark3: self.dark3.freeze_batch_norms(),
+            dark4: self.dark4.freeze_batch_norms(),
+            dark5: self.dark5.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for CspDarknet<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            stem: self.stem.sync_batch_norms(),
+            dark2: self.dark2.sync_batch_norms(),
+            dark3: self.dark3.sync_batch_norms(),
+            dark4: self.dark4.sync_batch_norms(),
+            dark5: self.dark5.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for CspDarknet<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.stem.set_bn_momentum(momentum);
+        self.dark2.set_bn_momentum(momentum);
+        self.dark3.set_bn_momentum(momentum);
+        self.dark4.set_bn_momentum(momentum);
+        self.dark5.set_bn_momentum(momentum);
+    }
 }
 
 /// [CSPDarknet-53](CspDarknet) configuration.
 pub struct CspDarknetConfig {
-    stem: FocusConfig,
+    base_channels: usize,
+    stem: StemConfig,
     dark2: CspBlockConfig,
     dark3: CspBlockConfig,
     dark4: CspBlockConfig,
@@ -61,7 +252,7 @@ impl CspDarknetConfig {
         let base_channels = expand(64, width);
         let base_depth = max((depth * 3_f64).round() as usize, 1);
 
-        let stem = FocusConfig::new(3, base_channels, 3, 1);
+        let stem = StemConfig::new(StemType::Focus).with_channels(3, base_channels);
         let dark2 = CspBlockConfig::new(
             base_channels,
             base_channels * 2,
@@ -92,6 +283,7 @@ impl CspDarknetConfig {
         );
 
         Self {
+            base_channels,
             stem,
             dark2,
             dark3,
@@ -100,6 +292,25 @@ impl CspDarknetConfig {
         }
     }
 
+    /// Number of output channels of each [stage](CspDarknet::forward_stage).
+    pub fn stage_channels(&self) -> [usize; 4] {
+        let c = self.base_channels;
+        [c * 2, c * 4, c * 8, c * 16]
+    }
+
+    /// Set the type of stem block.
+    pub fn with_stem_type(mut self, stem_type: StemType) -> Self {
+        self.stem = self.stem.with_stem_type(stem_type);
+        self
+    }
+
+    /// Insert [squeeze-and-excitation blocks](super::blocks::SeBlock) in the bottleneck blocks of
+    /// the last stage (`dark5`).
+    pub fn with_use_se(mut self, use_se: bool) -> Self {
+        self.dark5.c3 = self.dark5.c3.with_use_se(use_se);
+        self
+    }
+
     /// Initialize a new [CspDarknet](CspDarknet) module.
     pub fn init<B: Backend>(&self, device: &Device<B>) -> CspDarknet<B> {
         CspDarknet {
@@ -110,6 +321,62 @@ impl CspDarknetConfig {
             dark5: self.dark5.init(device),
         }
     }
+
+    /// Initialize a new [CspDarknet](CspDarknet) module with the pre-trained backbone weights of
+    /// a [registered model](registry::MODELS_REGISTRY).
+    ///
+    /// The checkpoint is downloaded and cached locally in `~/.cache/burn-models/`.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_id`: Registered model ID (e.g., `"yolox-s/coco"`).
+    /// * `device` - Device to create the module on.
+    #[cfg(feature = "pretrained")]
+    pub fn init_with_pretrained<B: Backend>(
+        model_id: &str,
+        device: &Device<B>,
+    ) -> Result<CspDarknet<B>, DownloadError> {
+        let entry = registry::find(model_id)?;
+        let checkpoint = entry.download()?;
+
+        entry
+            .config()
+            .darknet()
+            .init_with_checkpoint(&checkpoint, device)
+    }
+
+    /// Initialize a new [CspDarknet](CspDarknet) module with the backbone weights of a local
+    /// YOLOX PyTorch checkpoint file.
+    ///
+    /// The architecture of the config should match the checkpoint, with the default focus stem.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint`: Path to the PyTorch checkpoint file.
+    /// * `device` - Device to create the module on.
+    #[cfg(feature = "pretrained")]
+    pub fn init_with_checkpoint<B: Backend>(
+        &self,
+        checkpoint: &Path,
+        device: &Device<B>,
+    ) -> Result<CspDarknet<B>, DownloadError> {
+        // Load backbone weights from the full YOLOX torch state_dict, with the YOLOX remapping
+        let load_args = yolox_load_args(checkpoint.to_path_buf())
+            // Map backbone.backbone.* -> *
+            .with_key_remap("^backbone\\.backbone\\.(.+)", "$1");
+
+        let mut record: CspDarknetRecord<B> =
+            PyTorchFileRecorder::<FullPrecisionSettings>::new().load(load_args, device)?;
+
+        if let Some(ref mut spp) = record.dark5.spp {
+            // Vec<MaxPool2d> has no parameters and would be initialized as empty
+            if spp.m.is_empty() {
+                spp.m = alloc::vec![ConstantRecord; SPP_POOLING.len()];
+            }
+        }
+
+        Ok(self.init(device).load_record(record))
+    }
 }
 
 /// A BaseConv -> CspBottleneck block.
@@ -133,6 +400,34 @@ impl<B: Backend> CspBlock<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for CspBlock<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            conv: self.conv.freeze_batch_norms(),
+            c3: self.c3.freeze_batch_norms(),
+            spp: self.spp.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for CspBlock<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            conv: self.conv.sync_batch_norms(),
+            c3: self.c3.sync_batch_norms(),
+            spp: self.spp.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for CspBlock<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.conv.set_bn_momentum(momentum);
+        self.c3.set_bn_momentum(momentum);
+        self.spp.set_bn_momentum(momentum);
+    }
+}
+
 /// [CSP block](CspBlock) configuration.
 pub struct CspBlockConfig {
     conv: ConvConfig,
@@ -170,3 +465,170 @@ impl CspBlockConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use burn::{
+        backend::NdArray,
+        module::{ModuleVisitor, ParamId},
+    };
+
+    use super::*;
+
+    type TestBackend = NdArray<f32>;
+
+    const STEM_TYPES: [StemType; 4] = [
+        StemType::Focus,
+        StemType::FocusFree,
+        StemType::Standard7x7,
+        StemType::ResNetD,
+    ];
+
+    /// Counts the convolution weights (4D parameters).
+    struct ConvWeights(usize);
+
+    impl<B: Backend> ModuleVisitor<B> for ConvWeights {
+        fn visit_float<const D: usize>(&mut self, _id: &ParamId, _tensor: &Tensor<B, D>) {
+            if D == 4 {
+                self.0 += 1;
+            }
+        }
+    }
+
+    fn conv_weights(stem: &Stem<TestBackend>) -> usize {
+        let mut visitor = ConvWeights(0);
+        stem.visit(&mut visitor);
+        visitor.0
+    }
+
+    #[test]
+    fn stems_have_same_stride() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::zeros([1, 3, 64, 48], &device);
+
+        for stem_type in STEM_TYPES {
+            let stem = StemConfig::new(stem_type).init::<TestBackend>(&device);
+            assert_eq!(
+                stem.forward(x.clone()).dims(),
+                [1, 64, 32, 24],
+                "{stem_type:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn darknet_stem_types_have_same_outputs() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::zeros([1, 3, 64, 64], &device);
+
+        for stem_type in STEM_TYPES {
+            let darknet = CspDarknetConfig::new(0.33, 0.25, false)
+                .with_stem_type(stem_type)
+                .init::<TestBackend>(&device);
+
+            // Stride of 4 after the first stage
+            assert_eq!(darknet.forward_stage(0, x.clone()).dims(), [1, 32, 16, 16]);
+            let features = darknet.forward(x.clone());
+            assert_eq!(features.0.dims(), [1, 64, 8, 8], "{stem_type:?}");
+        }
+    }
+
+    #[test]
+    fn resnet_d_stem_convolutions() {
+        let device = Default::default();
+        let standard = StemConfig::new(StemType::Standard7x7).init::<TestBackend>(&device);
+        let resnet_d = StemConfig::new(StemType::ResNetD).init::<TestBackend>(&device);
+
+        assert_eq!(conv_weights(&standard), 1);
+        assert_eq!(conv_weights(&resnet_d), 3 * conv_weights(&standard));
+    }
+
+    #[test]
+    fn stem_config_with_stem_type() {
+        let config = StemConfig::new(StemType::Focus).with_stem_type(StemType::ResNetD);
+        assert_eq!(config.stem_type, StemType::ResNetD);
+
+        let stem = config.init::<TestBackend>(&Default::default());
+        assert!(matches!(stem, Stem::ResNetD(_)));
+    }
+}
+
+#[cfg(all(test, feature = "pretrained"))]
+mod pretrained_tests {
+    use std::{fs, path::PathBuf};
+
+    use burn::{
+        backend::NdArray,
+        module::{ModuleVisitor, ParamId},
+        tensor::ElementConversion,
+    };
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::model::{weights::Weights, yolox::YoloxConfig};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Value of all the tensors of the test checkpoint (see `tests/fixtures/make_pth.py`).
+    const VALUE: f32 = 0.01;
+
+    /// Checks that all the parameters have the checkpoint value.
+    struct AllLoaded(bool);
+
+    impl<B: Backend> ModuleVisitor<B> for AllLoaded {
+        fn visit_float<const D: usize>(&mut self, _id: &ParamId, tensor: &Tensor<B, D>) {
+            let loaded = tensor
+                .to_data()
+                .iter::<B::FloatElem>()
+                .all(|v| (v.elem::<f32>() - VALUE).abs() < 1e-6);
+            self.0 &= loaded;
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yolox-burn-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn pretrained_backbone_from_local_download() {
+        let device = Default::default();
+        let dir = temp_dir("darknet");
+        // Backbone parameters of a YOLOX-Nano checkpoint
+        let source =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/yolox_nano_backbone.pth");
+        let digest: String = Sha256::digest(fs::read(&source).unwrap())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        // Download from a local file instead of the release URL
+        let weights = Weights {
+            url: Box::leak(format!("file://{}", source.display()).into_boxed_str()),
+            num_classes: 80,
+            sha256: Some(Box::leak(digest.into_boxed_str())),
+        };
+        let checkpoint = weights.download_to(&dir).unwrap();
+        assert!(checkpoint.exists());
+
+        let model = YoloxConfig::yolox_nano()
+            .darknet()
+            .init_with_checkpoint::<TestBackend>(&checkpoint, &device)
+            .unwrap();
+        let mut visitor = AllLoaded(true);
+        model.visit(&mut visitor);
+        assert!(
+            visitor.0,
+            "all the parameters should be loaded from the checkpoint"
+        );
+
+        let DarknetFeatures(f1, f2, f3) = model.forward(Tensor::ones([1, 3, 64, 64], &device));
+        assert_eq!(f1.dims(), [1, 64, 8, 8]);
+        assert_eq!(f2.dims(), [1, 128, 4, 4]);
+        assert_eq!(f3.dims(), [1, 256, 2, 2]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+//! Grids of anchor points for decoding the predictions of anchor-free models (e.g., YOLOX, FCOS
+//! or CenterNet) at each output scale.
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    tensor::{activation::sigmoid, backend::Backend, Device, Int, Shape, Tensor},
+};
+
+/// Maximum absolute log-scale box size prediction, to avoid overflows of the exponential.
+const MAX_LOG_SIZE: f32 = 40.;
+
+/// Create a 2D coordinate grid for the specified dimensions.
+/// Similar to [`numpy.indices`](https://numpy.org/doc/stable/reference/generated/numpy.indices.html)
+/// but specific to two dimensions.
+pub(crate) fn create_2d_grid<B: Backend>(
+    x: usize,
+    y: usize,
+    device: &Device<B>,
+) -> Tensor<B, 3, Int> {
+    let y_idx = Tensor::arange(0..y as i64, device)
+        .reshape(Shape::new([y, 1]))
+        .repeat_dim(1, x)
+        .reshape(Shape::new([y, x]));
+    let x_idx = Tensor::arange(0..x as i64, device)
+        .reshape(Shape::new([1, x])) // can only repeat with dim=1
+        .repeat_dim(0, y)
+        .reshape(Shape::new([y, x]));
+
+    Tensor::stack(vec![x_idx, y_idx], 2)
+}
+
+/// Compute the anchor points of all the output scales.
+///
+/// The anchor point of the grid cell `(grid_x, grid_y)` is the cell center
+/// `((grid_x + 0.5) * stride, (grid_y + 0.5) * stride)` in image coordinates. The cells of each
+/// level are in row-major order and the levels are concatenated.
+///
+/// # Arguments
+///
+/// * `feature_shapes`: Feature map size `(height, width)` of each level.
+/// * `strides` - Stride of each level.
+/// * `device` - Device on which to create the tensors.
+///
+/// # Returns
+///
+/// The `(x, y)` anchor points with shape `[total_anchors, 2]` and the stride of each anchor with
+/// shape `[total_anchors]`, where `total_anchors` is the sum of `height * width` over the levels.
+pub fn make_anchor_grid<B: Backend>(
+    feature_shapes: &[(usize, usize)],
+    strides: &[usize],
+    device: &Device<B>,
+) -> (Tensor<B, 2>, Tensor<B, 1>) {
+    assert_eq!(
+        feature_shapes.len(),
+        strides.len(),
+        "expected one stride per feature map"
+    );
+
+    let (points, strides): (Vec<_>, Vec<_>) = feature_shapes
+        .iter()
+        .zip(strides)
+        .map(|(&(h, w), &stride)| {
+            let points = create_2d_grid::<B>(w, h, device)
+                .float()
+                .reshape([h * w, 2])
+                .add_scalar(0.5)
+                .mul_scalar(stride as f32);
+            let strides = Tensor::full([h * w], stride as f32, device);
+
+            (points, strides)
+        })
+        .unzip();
+
+    (Tensor::cat(points, 0), Tensor::cat(strides, 0))
+}
+
+/// Convert the box predictions of a feature map from grid units to image coordinates.
+///
+/// The box center is predicted as a (sigmoid) offset from the grid cell and the box size is
+/// `log` encoded, both in units of the stride: `xy = (sigmoid(pred_xy) + grid_xy) * stride` and
+/// `wh = exp(pred_wh) * stride`. The log sizes are clamped to `[-40, 40]`, so that extreme
+/// predictions do not overflow.
+///
+/// # Shapes
+///   - pred_xy: `[num_boxes, 2]`
+///   - pred_wh: `[num_boxes, 2]`
+///   - grid_xy: `[num_boxes, 2]`, the `(grid_x, grid_y)` cell of each box
+///   - output: `[num_boxes, 4]`, the `(cx, cy, w, h)` boxes
+pub fn stride_output_to_image<B: Backend>(
+    pred_xy: Tensor<B, 2>,
+    pred_wh: Tensor<B, 2>,
+    grid_xy: Tensor<B, 2>,
+    stride: usize,
+) -> Tensor<B, 2> {
+    assert_eq!(
+        pred_xy.dims(),
+        grid_xy.dims(),
+        "the predictions and grid should have the same shape"
+    );
+    assert_eq!(
+        pred_wh.dims(),
+        grid_xy.dims(),
+        "the predictions and grid should have the same shape"
+    );
+    let stride = stride as f32;
+
+    let xy = (sigmoid(pred_xy) + grid_xy).mul_scalar(stride);
+    let wh = pred_wh
+        .clamp(-MAX_LOG_SIZE, MAX_LOG_SIZE)
+        .exp()
+        .mul_scalar(stride);
+
+    Tensor::cat(vec![xy, wh], 1)
+}
+
+/// Same as [stride_output_to_image], with `(xmin, ymin, xmax, ymax)` output boxes.
+///
+/// # Shapes
+///   - pred_xy: `[num_boxes, 2]`
+///   - pred_wh: `[num_boxes, 2]`
+///   - grid_xy: `[num_boxes, 2]`
+///   - output: `[num_boxes, 4]`
+pub fn stride_output_to_xyxy<B: Backend>(
+    pred_xy: Tensor<B, 2>,
+    pred_wh: Tensor<B, 2>,
+    grid_xy: Tensor<B, 2>,
+    stride: usize,
+) -> Tensor<B, 2> {
+    let boxes = stride_output_to_image(pred_xy, pred_wh, grid_xy, stride);
+    let [n, _] = boxes.dims();
+
+    let xy = boxes.clone().slice([0..n, 0..2]);
+    let half_wh = boxes.slice([0..n, 2..4]).mul_scalar(0.5);
+
+    Tensor::cat(vec![xy.clone() - half_wh.clone(), xy + half_wh], 1)
+}
+
+/// [Anchor grid](make_anchor_grid) precomputed for fixed feature map sizes (i.e., a fixed input
+/// resolution), so that it is not recomputed at each forward pass.
+#[derive(Module, Debug)]
+pub struct CachedAnchorGrid<B: Backend> {
+    heights: Vec<usize>,
+    widths: Vec<usize>,
+    strides: Vec<usize>,
+    points: Tensor<B, 2>,
+    anchor_strides: Tensor<B, 1>,
+}
+
+impl<B: Backend> CachedAnchorGrid<B> {
+    /// Compute the anchor grid of the feature map sizes `(height, width)` of each level.
+    pub fn new(feature_shapes: &[(usize, usize)], strides: &[usize], device: &Device<B>) -> Self {
+        let (points, anchor_strides) = make_anchor_grid(feature_shapes, strides, device);
+        let (heights, widths) = feature_shapes.iter().copied().unzip();
+
+        Self {
+            heights,
+            widths,
+            strides: strides.to_vec(),
+            points,
+            anchor_strides,
+        }
+    }
+
+    /// Compute the anchor grid of `[height, width]` input images, for feature maps with the
+    /// given strides.
+    pub fn for_input_size(
+        [height, width]: [usize; 2],
+        strides: &[usize],
+        device: &Device<B>,
+    ) -> Self {
+        let feature_shapes: Vec<_> = strides
+            .iter()
+            .map(|&stride| (height / stride, width / stride))
+            .collect();
+
+        Self::new(&feature_shapes, strides, device)
+    }
+
+    /// Whether the grid was computed for the feature map sizes.
+    pub fn matches(&self, feature_shapes: &[(usize, usize)]) -> bool {
+        feature_shapes.len() == self.heights.len()
+            && feature_shapes
+                .iter()
+                .zip(self.heights.iter().zip(&self.widths))
+                .all(|(&(h, w), (&height, &width))| h == height && w == width)
+    }
+
+    /// Get the anchor points and strides for the feature map sizes (see [make_anchor_grid]).
+    ///
+    /// The cached grid is returned when the sizes match, otherwise the grid is recomputed.
+    pub fn get(
+        &self,
+        feature_shapes: &[(usize, usize)],
+        device: &Device<B>,
+    ) -> (Tensor<B, 2>, Tensor<B, 1>) {
+        if self.matches(feature_shapes) {
+            (
+                self.points.clone().to_device(device),
+                self.anchor_strides.clone().to_device(device),
+            )
+        } else {
+            make_anchor_grid(feature_shapes, &self.strides, device)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    const SHAPES: [(usize, usize); 3] = [(8, 10), (4, 5), (2, 3)];
+    const STRIDES: [usize; 3] = [8, 16, 32];
+
+    #[test]
+    fn anchor_grid_count() {
+        let device = Default::default();
+        let (points, strides) = make_anchor_grid::<TestBackend>(&SHAPES, &STRIDES, &device);
+
+        assert_eq!(points.dims(), [80 + 20 + 6, 2]);
+        assert_eq!(strides.dims(), [106]);
+        let strides = strides.into_data().to_vec::<f32>().unwrap();
+        assert!(strides[..80].iter().all(|&s| s == 8.));
+        assert!(strides[80..100].iter().all(|&s| s == 16.));
+        assert!(strides[100..].iter().all(|&s| s == 32.));
+    }
+
+    #[test]
+    fn anchor_grid_corners() {
+        let device = Default::default();
+        let (points, _) = make_anchor_grid::<TestBackend>(&SHAPES, &STRIDES, &device);
+
+        let mut offset = 0;
+        for (&(h, w), &stride) in SHAPES.iter().zip(&STRIDES) {
+            let s = stride as f32;
+            let level = points.clone().slice([offset..offset + h * w, 0..2]);
+            // First and last cells of the level, in row-major order
+            let first = level.clone().slice([0..1, 0..2]);
+            let last = level.slice([h * w - 1..h * w, 0..2]);
+
+            first
+                .into_data()
+                .assert_eq(&TensorData::from([[0.5 * s, 0.5 * s]]), false);
+            last.into_data().assert_eq(
+                &TensorData::from([[(w as f32 - 0.5) * s, (h as f32 - 0.5) * s]]),
+                false,
+            );
+            offset += h * w;
+        }
+    }
+
+    #[test]
+    fn cached_grid_matches() {
+        let device = Default::default();
+        let grid = CachedAnchorGrid::<TestBackend>::for_input_size([64, 96], &STRIDES, &device);
+        let shapes = [(8, 12), (4, 6), (2, 3)];
+
+        assert!(grid.matches(&shapes));
+        assert!(!grid.matches(&SHAPES));
+        assert!(!grid.matches(&shapes[..2]));
+
+        let (points, strides) = make_anchor_grid::<TestBackend>(&shapes, &STRIDES, &device);
+        let (cached_points, cached_strides) = grid.get(&shapes, &device);
+        cached_points
+            .into_data()
+            .assert_eq(&points.into_data(), true);
+        cached_strides
+            .into_data()
+            .assert_eq(&strides.into_data(), true);
+    }
+
+    #[test]
+    fn cached_grid_recomputed_for_other_sizes() {
+        let device = Default::default();
+        let grid = CachedAnchorGrid::<TestBackend>::new(&SHAPES, &STRIDES, &device);
+        let shapes = [(4, 4), (2, 2), (1, 1)];
+
+        let (points, strides) = grid.get(&shapes, &device);
+
+        assert_eq!(points.dims(), [21, 2]);
+        assert_eq!(strides.dims(), [21]);
+    }
+
+    #[test]
+    fn stride_output_center() {
+        let device = Default::default();
+        let pred_xy = Tensor::<TestBackend, 2>::from_floats([[0., 0.]], &device);
+        let pred_wh = Tensor::from_floats([[0., 0.]], &device);
+        let grid_xy = Tensor::from_floats([[2., 2.]], &device);
+
+        // Center of the cell (2, 2) with a stride of 8, box of one stride
+        stride_output_to_image(pred_xy.clone(), pred_wh.clone(), grid_xy.clone(), 8)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[20f32, 20., 8., 8.]]), 5);
+        stride_output_to_xyxy(pred_xy, pred_wh, grid_xy, 8)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[16f32, 16., 24., 24.]]), 5);
+    }
+
+    #[test]
+    fn stride_output_log_size() {
+        let device = Default::default();
+        let pred_xy = Tensor::<TestBackend, 2>::from_floats([[100., -100.]], &device);
+        let pred_wh = Tensor::from_floats([[2f32.ln(), 0.5f32.ln()]], &device);
+        let grid_xy = Tensor::from_floats([[0., 3.]], &device);
+
+        stride_output_to_image(pred_xy, pred_wh, grid_xy, 16)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[16f32, 48., 32., 8.]]), 4);
+    }
+
+    #[test]
+    fn stride_output_clamps_extreme_sizes() {
+        let device = Default::default();
+        let pred_xy = Tensor::<TestBackend, 2>::zeros([2, 2], &device);
+        let pred_wh = Tensor::from_floats([[1000., -1000.], [f32::MAX, 40.]], &device);
+        let grid_xy = Tensor::zeros([2, 2], &device);
+
+        let boxes: Vec<f32> = stride_output_to_image(pred_xy, pred_wh, grid_xy, 8)
+            .into_data()
+            .to_vec()
+            .unwrap();
+
+        assert!(boxes.iter().all(|x| x.is_finite()));
+        // Sizes clamped to exp(±40) strides
+        let close = |x: f32, expected: f32| ((x - expected) / expected).abs() < 1e-5;
+        let (max, min) = (40f32.exp() * 8., (-40f32).exp() * 8.);
+        assert!(close(boxes[2], max) && close(boxes[3], min));
+        assert!(close(boxes[6], max) && close(boxes[7], max));
+    }
+
+    #[test]
+    #[should_panic = "the predictions and grid should have the same shape"]
+    fn stride_output_mismatched_grid() {
+        let device = Default::default();
+        let pred = Tensor::<TestBackend, 2>::zeros([3, 2], &device);
+
+        stride_output_to_image(pred.clone(), pred, Tensor::zeros([2, 2], &device), 8);
+    }
+}
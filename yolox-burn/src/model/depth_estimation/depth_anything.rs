@@ -0,0 +1,194 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use super::{DptHead, DptHeadConfig};
+use crate::model::vit::{Vit, VitConfig, VitVariant};
+
+/// Default input image size, a multiple of the patch size.
+const IMAGE_SIZE: usize = 518;
+
+/// Size of the [Depth Anything V2](DepthAnythingV2) encoder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DepthAnythingVariant {
+    /// ViT-S/14 encoder.
+    #[default]
+    VitS,
+    /// ViT-B/14 encoder.
+    VitB,
+    /// ViT-L/14 encoder.
+    VitL,
+}
+
+impl DepthAnythingVariant {
+    /// Indices of the transformer blocks whose tokens are used by the [DPT head](DptHead).
+    pub fn hooks(&self) -> [usize; 4] {
+        match self {
+            Self::VitS | Self::VitB => [2, 5, 8, 11],
+            Self::VitL => [4, 11, 17, 23],
+        }
+    }
+
+    /// ViT variant of the encoder.
+    fn vit(&self) -> VitVariant {
+        match self {
+            Self::VitS => VitVariant::S14,
+            Self::VitB => VitVariant::B14,
+            Self::VitL => VitVariant::L14,
+        }
+    }
+
+    /// Number of channels of the reassembled maps and of the fusion blocks.
+    fn head_channels(&self) -> ([usize; 4], usize) {
+        match self {
+            Self::VitS => ([48, 96, 192, 384], 64),
+            Self::VitB => ([96, 192, 384, 768], 128),
+            Self::VitL => ([256, 512, 1024, 1024], 256),
+        }
+    }
+}
+
+/// [Depth Anything V2](https://arxiv.org/abs/2406.09414) monocular relative depth estimation.
+///
+/// A [Vision Transformer](Vit) encoder followed by a [DPT head](DptHead) using the patch tokens
+/// of four intermediate transformer blocks.
+#[derive(Module, Debug)]
+pub struct DepthAnythingV2<B: Backend> {
+    encoder: Vit<B>,
+    head: DptHead<B>,
+    hooks: Vec<usize>,
+    patch_size: usize,
+}
+
+impl<B: Backend> DepthAnythingV2<B> {
+    /// Predict the relative depth of each pixel, in the range `[0, 1]`.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, 3, height, width]`, with the image size of the config
+    ///   - output: `[batch_size, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 3> {
+        let [b, _, h, w] = x.dims();
+        let patch_grid = [h / self.patch_size, w / self.patch_size];
+
+        let tokens = self.encoder.forward_hooks(x, &self.hooks);
+        let depth = self.head.forward(tokens, patch_grid);
+        let [_, _, out_h, out_w] = depth.dims();
+
+        depth.reshape([b, out_h, out_w])
+    }
+
+    /// Indices of the encoder blocks whose tokens are used by the head.
+    pub fn hooks(&self) -> &[usize] {
+        &self.hooks
+    }
+}
+
+/// [Depth Anything V2](DepthAnythingV2) configuration.
+pub struct DepthAnythingV2Config {
+    variant: DepthAnythingVariant,
+    image_size: usize,
+}
+
+impl DepthAnythingV2Config {
+    /// Create a new instance of the Depth Anything V2 [config](DepthAnythingV2Config), for
+    /// `518x518` images.
+    pub fn new(variant: DepthAnythingVariant) -> Self {
+        Self {
+            variant,
+            image_size: IMAGE_SIZE,
+        }
+    }
+
+    /// Set the input image size (square), which should be a multiple of 14 (defaults to 518).
+    pub fn with_image_size(mut self, image_size: usize) -> Self {
+        assert!(
+            image_size % 14 == 0,
+            "the image size should be a multiple of the patch size"
+        );
+        self.image_size = image_size;
+        self
+    }
+
+    /// Initialize a new [Depth Anything V2](DepthAnythingV2) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DepthAnythingV2<B> {
+        let vit = VitConfig::from_variant(self.variant.vit(), self.image_size);
+        let (out_channels, features) = self.variant.head_channels();
+        let head = DptHeadConfig::new(vit.embed_dim(), out_channels, features, vit.patch_size());
+
+        DepthAnythingV2 {
+            encoder: vit.init(device),
+            head: head.init(device),
+            hooks: self.variant.hooks().to_vec(),
+            patch_size: vit.patch_size(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn vits_hooks() {
+        let device = Default::default();
+        let model = DepthAnythingV2Config::new(DepthAnythingVariant::VitS)
+            .with_image_size(28)
+            .init::<TestBackend>(&device);
+
+        assert_eq!(model.hooks(), [2, 5, 8, 11]);
+        assert_eq!(model.encoder.depth(), 12);
+        assert_eq!(DepthAnythingVariant::VitL.hooks(), [4, 11, 17, 23]);
+    }
+
+    #[test]
+    fn vits_hooked_tokens() {
+        let device = Default::default();
+        let model = DepthAnythingV2Config::new(DepthAnythingVariant::VitS)
+            .with_image_size(28)
+            .init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([1, 3, 28, 28], Distribution::Default, &device);
+
+        let tokens = model.encoder.forward_hooks(x.clone(), model.hooks());
+        assert_eq!(tokens.len(), 4);
+        for t in tokens.iter() {
+            assert_eq!(t.dims(), [1, 4, 384]);
+        }
+
+        // The last hook is the output of the last block, without the class token
+        let expected = model.encoder.forward(x).slice([0..1, 1..5, 0..384]);
+        tokens[3]
+            .clone()
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 4);
+        assert_ne!(
+            tokens[0].clone().into_data().to_vec::<f32>().unwrap(),
+            tokens[3].clone().into_data().to_vec::<f32>().unwrap()
+        );
+    }
+
+    #[test]
+    fn depth_same_resolution_as_input() {
+        let device = Default::default();
+        let model = DepthAnythingV2Config::new(DepthAnythingVariant::VitS)
+            .with_image_size(42)
+            .init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 42, 42], Distribution::Default, &device);
+
+        let depth = model.forward(x);
+
+        assert_eq!(depth.dims(), [2, 42, 42]);
+        let (min, max) = (depth.clone().min().into_scalar(), depth.max().into_scalar());
+        assert!((0. ..=1.).contains(&min) && (0. ..=1.).contains(&max));
+    }
+
+    #[test]
+    #[should_panic = "the image size should be a multiple of the patch size"]
+    fn image_size_not_multiple_of_patch_size() {
+        let _ = DepthAnythingV2Config::new(DepthAnythingVariant::VitS).with_image_size(500);
+    }
+}
@@ -0,0 +1,353 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig, ConvTranspose2d, ConvTranspose2dConfig},
+        PaddingConfig2d, Relu,
+    },
+    tensor::{
+        activation::{relu, sigmoid},
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Tensor,
+    },
+};
+
+/// Number of channels of the hidden layer of the depth output head.
+const HEAD_HIDDEN_CHANNELS: usize = 32;
+/// Resampling factor of the reassembled maps of each hooked layer, from the highest to the
+/// lowest resolution (e.g., 4 for stride 4 from patches of size 16).
+const RESAMPLE_FACTORS: [f32; 4] = [4., 2., 1., 0.5];
+
+/// `3x3` convolution with same padding.
+fn conv3x3<B: Backend>(
+    in_channels: usize,
+    out_channels: usize,
+    bias: bool,
+    device: &Device<B>,
+) -> Conv2d<B> {
+    Conv2dConfig::new([in_channels, out_channels], [3, 3])
+        .with_padding(PaddingConfig2d::Explicit(1, 1))
+        .with_bias(bias)
+        .init(device)
+}
+
+/// `1x1` convolution.
+fn conv1x1<B: Backend>(in_channels: usize, out_channels: usize, device: &Device<B>) -> Conv2d<B> {
+    Conv2dConfig::new([in_channels, out_channels], [1, 1]).init(device)
+}
+
+/// Resampling of the reassembled feature maps.
+#[derive(Module, Debug)]
+pub enum Resample<B: Backend> {
+    /// Upsampling with a transposed convolution (kernel size and stride of the factor).
+    Up(ConvTranspose2d<B>),
+    /// Downsampling by 2 with a strided `3x3` convolution.
+    Down(Conv2d<B>),
+}
+
+impl<B: Backend> Resample<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        match self {
+            Self::Up(conv) => conv.forward(x),
+            Self::Down(conv) => conv.forward(x),
+        }
+    }
+}
+
+/// Reassemble the patch tokens of a transformer layer into a feature map.
+///
+/// The tokens are reshaped to the patch grid, projected with a `1x1` convolution, resampled to
+/// the resolution of the layer in the feature pyramid, and projected to the number of channels of
+/// the [fusion blocks](FusionBlock).
+#[derive(Module, Debug)]
+pub struct ReassembleBlock<B: Backend> {
+    project: Conv2d<B>,
+    resample: Option<Resample<B>>,
+    layer_rn: Conv2d<B>,
+}
+
+impl<B: Backend> ReassembleBlock<B> {
+    /// # Shapes
+    ///   - tokens: `[batch_size, patch_h * patch_w, embed_dim]`
+    ///   - output: `[batch_size, features, patch_h * factor, patch_w * factor]`
+    pub fn forward(&self, tokens: Tensor<B, 3>, [patch_h, patch_w]: [usize; 2]) -> Tensor<B, 4> {
+        let [b, _, c] = tokens.dims();
+        let x = tokens.swap_dims(1, 2).reshape([b, c, patch_h, patch_w]);
+
+        let x = self.project.forward(x);
+        let x = match &self.resample {
+            Some(resample) => resample.forward(x),
+            None => x,
+        };
+
+        self.layer_rn.forward(x)
+    }
+}
+
+/// [Reassemble block](ReassembleBlock) configuration.
+pub struct ReassembleBlockConfig {
+    embed_dim: usize,
+    out_channels: usize,
+    features: usize,
+    factor: f32,
+}
+
+impl ReassembleBlockConfig {
+    /// Create a new instance of the reassemble block [config](ReassembleBlockConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `embed_dim`: Embedding dimension of the tokens.
+    /// * `out_channels` - Number of channels of the projected tokens.
+    /// * `features` - Number of output channels.
+    /// * `factor` - Resampling factor: 4 or 2 (upsampling), 1 (none) or 0.5 (downsampling).
+    pub fn new(embed_dim: usize, out_channels: usize, features: usize, factor: f32) -> Self {
+        assert!(
+            [4., 2., 1., 0.5].contains(&factor),
+            "invalid resampling factor {factor}"
+        );
+
+        Self {
+            embed_dim,
+            out_channels,
+            features,
+            factor,
+        }
+    }
+
+    /// Initialize a new [reassemble block](ReassembleBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ReassembleBlock<B> {
+        let c = self.out_channels;
+        let resample = if self.factor > 1. {
+            let k = self.factor as usize;
+            Some(Resample::Up(
+                ConvTranspose2dConfig::new([c, c], [k, k])
+                    .with_stride([k, k])
+                    .init(device),
+            ))
+        } else if self.factor < 1. {
+            Some(Resample::Down(
+                Conv2dConfig::new([c, c], [3, 3])
+                    .with_stride([2, 2])
+                    .with_padding(PaddingConfig2d::Explicit(1, 1))
+                    .init(device),
+            ))
+        } else {
+            None
+        };
+
+        ReassembleBlock {
+            project: conv1x1(self.embed_dim, c, device),
+            resample,
+            layer_rn: conv3x3(c, self.features, false, device),
+        }
+    }
+}
+
+/// Residual convolution unit: `x + conv(relu(conv(relu(x))))`.
+#[derive(Module, Debug)]
+pub struct ResidualConvUnit<B: Backend> {
+    conv1: Conv2d<B>,
+    conv2: Conv2d<B>,
+    activation: Relu,
+}
+
+impl<B: Backend> ResidualConvUnit<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let out = self.conv1.forward(self.activation.forward(x.clone()));
+        let out = self.conv2.forward(self.activation.forward(out));
+
+        out + x
+    }
+}
+
+/// Fusion block of the [DPT head](DptHead).
+///
+/// The skip feature map (from the [reassemble block](ReassembleBlock) of the same resolution) is
+/// refined by a [residual unit](ResidualConvUnit) and added to the coarser path, which is refined
+/// again, upsampled and projected with a `1x1` convolution.
+#[derive(Module, Debug)]
+pub struct FusionBlock<B: Backend> {
+    res_conf_unit1: ResidualConvUnit<B>,
+    res_conf_unit2: ResidualConvUnit<B>,
+    out_conv: Conv2d<B>,
+}
+
+impl<B: Backend> FusionBlock<B> {
+    /// Fuse the coarser path with the skip feature map.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Coarser path (or the lowest resolution reassembled map).
+    /// * `skip` - Reassembled map with the same resolution as `x`, if any.
+    /// * `size` - Output size, or twice the input size if `None`.
+    pub fn forward(
+        &self,
+        x: Tensor<B, 4>,
+        skip: Option<Tensor<B, 4>>,
+        size: Option<[usize; 2]>,
+    ) -> Tensor<B, 4> {
+        let x = match skip {
+            Some(skip) => x + self.res_conf_unit1.forward(skip),
+            None => x,
+        };
+        let x = self.res_conf_unit2.forward(x);
+
+        let [_, _, h, w] = x.dims();
+        let x = interpolate(
+            x,
+            size.unwrap_or([h * 2, w * 2]),
+            InterpolateOptions::new(InterpolateMode::Bilinear),
+        );
+
+        self.out_conv.forward(x)
+    }
+}
+
+/// [Fusion block](FusionBlock) configuration.
+pub struct FusionBlockConfig {
+    features: usize,
+}
+
+impl FusionBlockConfig {
+    /// Create a new instance of the fusion block [config](FusionBlockConfig).
+    pub fn new(features: usize) -> Self {
+        Self { features }
+    }
+
+    /// Initialize a new [fusion block](FusionBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> FusionBlock<B> {
+        let f = self.features;
+        let unit = || ResidualConvUnit {
+            conv1: conv3x3(f, f, true, device),
+            conv2: conv3x3(f, f, true, device),
+            activation: Relu::new(),
+        };
+
+        FusionBlock {
+            res_conf_unit1: unit(),
+            res_conf_unit2: unit(),
+            out_conv: conv1x1(f, f, device),
+        }
+    }
+}
+
+/// [Dense Prediction Transformer](https://arxiv.org/abs/2103.13413) (DPT) depth head.
+///
+/// The patch tokens of four transformer layers are [reassembled](ReassembleBlock) into a feature
+/// pyramid (strides 4 to 32 relative to the patch grid), which is [fused](FusionBlock) from the
+/// lowest to the highest resolution and upsampled to the input resolution.
+#[derive(Module, Debug)]
+pub struct DptHead<B: Backend> {
+    reassembles: Vec<ReassembleBlock<B>>,
+    fusions: Vec<FusionBlock<B>>,
+    output_conv1: Conv2d<B>,
+    output_conv2: Conv2d<B>,
+    output_conv3: Conv2d<B>,
+    patch_size: usize,
+}
+
+impl<B: Backend> DptHead<B> {
+    /// Predict the relative depth in `[0, 1]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens`: Patch tokens of the four hooked layers, from the shallowest to the deepest.
+    /// * `patch_grid` - Number of patches `(patch_h, patch_w)` along each axis.
+    ///
+    /// # Shapes
+    ///   - tokens: `[batch_size, patch_h * patch_w, embed_dim]` for each layer
+    ///   - output: `[batch_size, 1, patch_h * patch_size, patch_w * patch_size]`
+    pub fn forward(&self, tokens: Vec<Tensor<B, 3>>, patch_grid: [usize; 2]) -> Tensor<B, 4> {
+        assert_eq!(
+            tokens.len(),
+            self.reassembles.len(),
+            "expected 4 hooked layers"
+        );
+        let [patch_h, patch_w] = patch_grid;
+
+        let layers: Vec<_> = tokens
+            .into_iter()
+            .zip(self.reassembles.iter())
+            .map(|(t, reassemble)| reassemble.forward(t, patch_grid))
+            .collect();
+        let size = |x: &Tensor<B, 4>| {
+            let [_, _, h, w] = x.dims();
+            [h, w]
+        };
+
+        // Fusion from the lowest resolution
+        let [l1, l2, l3, l4]: [Tensor<B, 4>; 4] = layers.try_into().unwrap();
+        let path = self.fusions[3].forward(l4, None, Some(size(&l3)));
+        let path = self.fusions[2].forward(path, Some(l3), Some(size(&l2)));
+        let path = self.fusions[1].forward(path, Some(l2), Some(size(&l1)));
+        let path = self.fusions[0].forward(path, Some(l1), None);
+
+        let x = self.output_conv1.forward(path);
+        let x = interpolate(
+            x,
+            [patch_h * self.patch_size, patch_w * self.patch_size],
+            InterpolateOptions::new(InterpolateMode::Bilinear),
+        );
+        let x = relu(self.output_conv2.forward(x));
+
+        sigmoid(self.output_conv3.forward(x))
+    }
+}
+
+/// [DPT head](DptHead) configuration.
+pub struct DptHeadConfig {
+    embed_dim: usize,
+    out_channels: [usize; 4],
+    features: usize,
+    patch_size: usize,
+}
+
+impl DptHeadConfig {
+    /// Create a new instance of the DPT head [config](DptHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `embed_dim`: Embedding dimension of the tokens.
+    /// * `out_channels` - Number of channels of the projected tokens of each hooked layer.
+    /// * `features` - Number of channels of the fusion blocks.
+    /// * `patch_size` - Patch size of the transformer.
+    pub fn new(
+        embed_dim: usize,
+        out_channels: [usize; 4],
+        features: usize,
+        patch_size: usize,
+    ) -> Self {
+        Self {
+            embed_dim,
+            out_channels,
+            features,
+            patch_size,
+        }
+    }
+
+    /// Initialize a new [DPT head](DptHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DptHead<B> {
+        let f = self.features;
+
+        DptHead {
+            reassembles: self
+                .out_channels
+                .iter()
+                .zip(RESAMPLE_FACTORS)
+                .map(|(&c, factor)| {
+                    ReassembleBlockConfig::new(self.embed_dim, c, f, factor).init(device)
+                })
+                .collect(),
+            fusions: (0..4)
+                .map(|_| FusionBlockConfig::new(f).init(device))
+                .collect(),
+            output_conv1: conv3x3(f, f / 2, true, device),
+            output_conv2: conv3x3(f / 2, HEAD_HIDDEN_CHANNELS, true, device),
+            output_conv3: conv1x1(HEAD_HIDDEN_CHANNELS, 1, device),
+            patch_size: self.patch_size,
+        }
+    }
+}
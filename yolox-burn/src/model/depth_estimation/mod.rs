@@ -0,0 +1,5 @@
+mod depth_anything;
+mod dpt;
+
+pub use depth_anything::*;
+pub use dpt::*;
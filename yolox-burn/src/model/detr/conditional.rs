@@ -0,0 +1,524 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{
+        attention::{MhaInput, MultiHeadAttention, MultiHeadAttentionConfig},
+        conv::{Conv2d, Conv2dConfig},
+        transformer::{TransformerEncoder, TransformerEncoderConfig, TransformerEncoderInput},
+        LayerNorm, LayerNormConfig, Linear, LinearConfig, PaddingConfig2d,
+    },
+    tensor::{
+        activation::{sigmoid, softmax},
+        backend::Backend,
+        Device, Distribution, Tensor,
+    },
+};
+
+use super::{sine_embedding, sine_position_encoding, Mlp, MlpConfig};
+use crate::model::{
+    blocks::{ActivationType, MlpBlock, MlpBlockConfig},
+    darknet::{CspDarknet, CspDarknetConfig, DarknetFeatures},
+};
+
+/// Index of the `dark5` stage in the [backbone stages](CspDarknetConfig::stage_channels).
+const FEATURE_STAGE: usize = 3;
+/// Ratio of the feed-forward dimension to the hidden dimension of the transformer layers.
+const FFN_RATIO: usize = 8;
+
+/// Inverse of the sigmoid function.
+pub(super) fn inverse_sigmoid<B: Backend, const D: usize>(x: Tensor<B, D>) -> Tensor<B, D> {
+    let x = x.clamp(1e-5, 1. - 1e-5);
+    (x.clone() / x.neg().add_scalar(1.)).log()
+}
+
+/// Split the last dimension into the attention heads:
+/// `[B, N, C] -> [B, num_heads, N, C / num_heads]`.
+fn split_heads<B: Backend>(x: Tensor<B, 3>, num_heads: usize) -> Tensor<B, 4> {
+    let [b, n, c] = x.dims();
+    x.reshape([b, n, num_heads, c / num_heads]).swap_dims(1, 2)
+}
+
+/// Conditional cross-attention of [Conditional DETR](ConditionalDetr).
+///
+/// The queries (resp. keys) are the concatenation, for each head, of a content part and a
+/// spatial part, so that the attention weights are the sum of a content and a spatial attention.
+#[derive(Module, Debug)]
+pub struct ConditionalCrossAttention<B: Backend> {
+    query_content: Linear<B>,
+    query_spatial: Linear<B>,
+    key_content: Linear<B>,
+    key_spatial: Linear<B>,
+    value: Linear<B>,
+    output: Linear<B>,
+    num_heads: usize,
+}
+
+impl<B: Backend> ConditionalCrossAttention<B> {
+    /// Attend to the encoder memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `tgt`: Decoder embeddings. Shape: `[batch_size, num_queries, hidden_dim]`.
+    /// * `spatial_query` - Conditional spatial queries.
+    ///   Shape: `[batch_size, num_queries, hidden_dim]`.
+    /// * `memory` - Encoder memory. Shape: `[batch_size, seq_length, hidden_dim]`.
+    /// * `pos` - Positional encoding of the memory. Shape: `[1, seq_length, hidden_dim]`.
+    ///
+    /// # Returns
+    ///
+    /// The attention output with shape `[batch_size, num_queries, hidden_dim]` and the attention
+    /// weights with shape `[batch_size, num_heads, num_queries, seq_length]`.
+    pub fn forward(
+        &self,
+        tgt: Tensor<B, 3>,
+        spatial_query: Tensor<B, 3>,
+        memory: Tensor<B, 3>,
+        pos: Tensor<B, 3>,
+    ) -> (Tensor<B, 3>, Tensor<B, 4>) {
+        let [b, q, c] = tgt.dims();
+        let h = self.num_heads;
+
+        // Content and spatial parts concatenated per head [B, h, N, 2 * C / h]
+        let query = Tensor::cat(
+            vec![
+                split_heads(self.query_content.forward(tgt), h),
+                split_heads(self.query_spatial.forward(spatial_query), h),
+            ],
+            3,
+        );
+        let key = Tensor::cat(
+            vec![
+                split_heads(self.key_content.forward(memory.clone()), h),
+                split_heads(self.key_spatial.forward(pos), h).repeat_dim(0, b),
+            ],
+            3,
+        );
+        let value = split_heads(self.value.forward(memory), h);
+
+        let [_, _, _, dim] = query.dims();
+        let scale = Tensor::<B, 1>::from_floats([dim as f32], &query.device())
+            .sqrt()
+            .reshape([1, 1, 1, 1]);
+        let weights = softmax(query.matmul(key.swap_dims(2, 3)) / scale, 3);
+
+        let x = weights
+            .clone()
+            .matmul(value)
+            .swap_dims(1, 2)
+            .reshape([b, q, c]);
+
+        (self.output.forward(x), weights)
+    }
+}
+
+/// [Conditional cross-attention](ConditionalCrossAttention) configuration.
+pub struct ConditionalCrossAttentionConfig {
+    hidden_dim: usize,
+    num_heads: usize,
+}
+
+impl ConditionalCrossAttentionConfig {
+    /// Create a new instance of the conditional cross-attention
+    /// [config](ConditionalCrossAttentionConfig).
+    pub fn new(hidden_dim: usize, num_heads: usize) -> Self {
+        assert!(
+            hidden_dim % num_heads == 0,
+            "the hidden dimension should be divisible by the number of heads"
+        );
+
+        Self {
+            hidden_dim,
+            num_heads,
+        }
+    }
+
+    /// Initialize a new [conditional cross-attention](ConditionalCrossAttention) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ConditionalCrossAttention<B> {
+        let linear = || LinearConfig::new(self.hidden_dim, self.hidden_dim).init(device);
+
+        ConditionalCrossAttention {
+            query_content: linear(),
+            query_spatial: linear(),
+            key_content: linear(),
+            key_spatial: linear(),
+            value: linear(),
+            output: linear(),
+            num_heads: self.num_heads,
+        }
+    }
+}
+
+/// Decoder layer of [Conditional DETR](ConditionalDetr): self-attention, conditional
+/// cross-attention and feed-forward network, each followed by a residual connection and a layer
+/// normalization.
+#[derive(Module, Debug)]
+pub struct ConditionalDecoderLayer<B: Backend> {
+    self_attn: MultiHeadAttention<B>,
+    norm1: LayerNorm<B>,
+    cross_attn: ConditionalCrossAttention<B>,
+    norm2: LayerNorm<B>,
+    ffn: MlpBlock<B>,
+    norm3: LayerNorm<B>,
+    /// Predicts the 2D reference point (box center) of each query from its positional embedding.
+    reference_point: Mlp<B>,
+    /// Predicts the transformation of the reference point embedding from the decoder embedding.
+    query_scale: Mlp<B>,
+}
+
+impl<B: Backend> ConditionalDecoderLayer<B> {
+    /// Apply the decoder layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `tgt`: Decoder embeddings. Shape: `[batch_size, num_queries, hidden_dim]`.
+    /// * `query_pos` - Query positional embeddings.
+    ///   Shape: `[batch_size, num_queries, hidden_dim]`.
+    /// * `memory` - Encoder memory. Shape: `[batch_size, seq_length, hidden_dim]`.
+    /// * `pos` - Positional encoding of the memory. Shape: `[1, seq_length, hidden_dim]`.
+    ///
+    /// # Returns
+    ///
+    /// The decoder embeddings with shape `[batch_size, num_queries, hidden_dim]` and the
+    /// normalized reference points `(x, y)` with shape `[batch_size, num_queries, 2]`.
+    pub fn forward(
+        &self,
+        tgt: Tensor<B, 3>,
+        query_pos: Tensor<B, 3>,
+        memory: Tensor<B, 3>,
+        pos: Tensor<B, 3>,
+    ) -> (Tensor<B, 3>, Tensor<B, 3>) {
+        let [_, _, hidden_dim] = tgt.dims();
+
+        // Self-attention
+        let q = tgt.clone() + query_pos.clone();
+        let x = self
+            .self_attn
+            .forward(MhaInput::new(q.clone(), q, tgt.clone()))
+            .context;
+        let tgt = self.norm1.forward(tgt + x);
+
+        // Conditional spatial query
+        let reference_points = sigmoid(self.reference_point.forward(query_pos));
+        let spatial_query = sine_embedding(reference_points.clone(), hidden_dim / 2)
+            * self.query_scale.forward(tgt.clone());
+
+        // Cross-attention
+        let (x, _) = self
+            .cross_attn
+            .forward(tgt.clone(), spatial_query, memory, pos);
+        let tgt = self.norm2.forward(tgt + x);
+
+        // Feed-forward network
+        let x = self.ffn.forward(tgt.clone());
+        let tgt = self.norm3.forward(tgt + x);
+
+        (tgt, reference_points)
+    }
+}
+
+/// [Conditional decoder layer](ConditionalDecoderLayer) configuration.
+pub struct ConditionalDecoderLayerConfig {
+    self_attn: MultiHeadAttentionConfig,
+    cross_attn: ConditionalCrossAttentionConfig,
+    ffn: MlpBlockConfig,
+    norm: LayerNormConfig,
+    reference_point: MlpConfig,
+    query_scale: MlpConfig,
+}
+
+impl ConditionalDecoderLayerConfig {
+    /// Create a new instance of the conditional decoder layer
+    /// [config](ConditionalDecoderLayerConfig).
+    pub fn new(hidden_dim: usize, num_heads: usize) -> Self {
+        Self {
+            self_attn: MultiHeadAttentionConfig::new(hidden_dim, num_heads).with_dropout(0.),
+            cross_attn: ConditionalCrossAttentionConfig::new(hidden_dim, num_heads),
+            ffn: MlpBlockConfig::ffn(hidden_dim, hidden_dim * FFN_RATIO, 0., ActivationType::Relu),
+            norm: LayerNormConfig::new(hidden_dim),
+            reference_point: MlpConfig::new(hidden_dim, hidden_dim, 2, 2),
+            query_scale: MlpConfig::new(hidden_dim, hidden_dim, hidden_dim, 2),
+        }
+    }
+
+    /// Initialize a new [conditional decoder layer](ConditionalDecoderLayer) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ConditionalDecoderLayer<B> {
+        ConditionalDecoderLayer {
+            self_attn: self.self_attn.init(device),
+            norm1: self.norm.init(device),
+            cross_attn: self.cross_attn.init(device),
+            norm2: self.norm.init(device),
+            ffn: self.ffn.init(device),
+            norm3: self.norm.init(device),
+            reference_point: self.reference_point.init(device),
+            query_scale: self.query_scale.init(device),
+        }
+    }
+}
+
+/// Class logits and boxes predicted by a DETR decoder layer.
+pub struct DetrPrediction<B: Backend> {
+    /// Class logits (sigmoid classification). Shape: `[batch_size, num_queries, num_classes]`.
+    pub logits: Tensor<B, 3>,
+    /// Normalized `(cx, cy, w, h)` boxes. Shape: `[batch_size, num_queries, 4]`.
+    pub boxes: Tensor<B, 3>,
+}
+
+/// [Conditional DETR](ConditionalDetr) outputs.
+pub struct ConditionalDetrOutput<B: Backend> {
+    /// Predictions of the last decoder layer.
+    pub prediction: DetrPrediction<B>,
+    /// Predictions of the intermediate decoder layers, for the auxiliary losses.
+    pub aux_predictions: Vec<DetrPrediction<B>>,
+    /// Reference points `(x, y)` of each decoder layer. Shape: `[batch_size, num_queries, 2]`.
+    pub reference_points: Vec<Tensor<B, 3>>,
+}
+
+/// [Conditional DETR](https://arxiv.org/abs/2108.06152) object detection model.
+///
+/// The decoder cross-attention is conditioned on a spatial query computed from a reference point
+/// of each object query, which narrows down the regions attended by each query and speeds up
+/// the training convergence compared to DETR.
+#[derive(Module, Debug)]
+pub struct ConditionalDetr<B: Backend> {
+    backbone: CspDarknet<B>,
+    input_proj: Conv2d<B>,
+    encoder: TransformerEncoder<B>,
+    /// Object query positional embeddings. Shape: `[num_queries, hidden_dim]`.
+    query_pos: Param<Tensor<B, 2>>,
+    decoder: Vec<ConditionalDecoderLayer<B>>,
+    decoder_norm: LayerNorm<B>,
+    class_head: Mlp<B>,
+    box_head: Mlp<B>,
+}
+
+impl<B: Backend> ConditionalDetr<B> {
+    /// Predict the objects of a batch of images.
+    ///
+    /// # Shapes
+    ///   - images: `[batch_size, 3, height, width]`
+    pub fn forward(&self, images: Tensor<B, 4>) -> ConditionalDetrOutput<B> {
+        let DarknetFeatures(_, _, x) = self.backbone.forward(images);
+        let x = self.input_proj.forward(x);
+        let [b, c, h, w] = x.dims();
+
+        // Encoder, with the positional encoding added to the input tokens
+        let pos = sine_position_encoding([h, w], c / 2, &x.device());
+        let src = x.reshape([b, c, h * w]).swap_dims(1, 2);
+        let memory = self
+            .encoder
+            .forward(TransformerEncoderInput::new(src + pos.clone()));
+
+        // Decoder
+        let [num_queries, _] = self.query_pos.dims();
+        let query_pos = self.query_pos.val().unsqueeze::<3>().repeat_dim(0, b);
+        let mut tgt = Tensor::zeros([b, num_queries, c], &memory.device());
+        let mut predictions = Vec::with_capacity(self.decoder.len());
+        let mut reference_points = Vec::with_capacity(self.decoder.len());
+        for layer in self.decoder.iter() {
+            let (x, points) = layer.forward(tgt, query_pos.clone(), memory.clone(), pos.clone());
+            predictions.push(self.predict(self.decoder_norm.forward(x.clone()), points.clone()));
+            reference_points.push(points);
+            tgt = x;
+        }
+
+        let prediction = predictions.pop().expect("the decoder should have layers");
+
+        ConditionalDetrOutput {
+            prediction,
+            aux_predictions: predictions,
+            reference_points,
+        }
+    }
+
+    /// Predict the class logits and the boxes, whose centers are relative to the reference points.
+    fn predict(&self, x: Tensor<B, 3>, reference_points: Tensor<B, 3>) -> DetrPrediction<B> {
+        let [b, q, _] = x.dims();
+        let boxes = self.box_head.forward(x.clone());
+
+        let xy = boxes.clone().slice([0..b, 0..q, 0..2]) + inverse_sigmoid(reference_points);
+        let wh = boxes.slice([0..b, 0..q, 2..4]);
+
+        DetrPrediction {
+            logits: self.class_head.forward(x),
+            boxes: sigmoid(Tensor::cat(vec![xy, wh], 2)),
+        }
+    }
+}
+
+/// [Conditional DETR](ConditionalDetr) configuration.
+pub struct ConditionalDetrConfig {
+    backbone: CspDarknetConfig,
+    input_proj: Conv2dConfig,
+    encoder: TransformerEncoderConfig,
+    decoder: ConditionalDecoderLayerConfig,
+    decoder_norm: LayerNormConfig,
+    class_head: MlpConfig,
+    box_head: MlpConfig,
+    num_queries: usize,
+    hidden_dim: usize,
+    dec_layers: usize,
+}
+
+impl ConditionalDetrConfig {
+    /// Create a new instance of the Conditional DETR [config](ConditionalDetrConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `backbone_depth`: Depth multiple of the [CSPDarknet](CspDarknet) backbone, which selects
+    ///   the YOLOX variant (0.33 for S, 0.67 for M, 1.0 for L and 1.33 for X).
+    /// * `num_classes` - Number of classes.
+    /// * `num_queries` - Number of object queries.
+    /// * `hidden_dim` - Hidden dimension of the transformer.
+    /// * `nheads` - Number of attention heads.
+    /// * `enc_layers` - Number of encoder layers.
+    /// * `dec_layers` - Number of decoder layers.
+    pub fn new(
+        backbone_depth: f64,
+        num_classes: usize,
+        num_queries: usize,
+        hidden_dim: usize,
+        nheads: usize,
+        enc_layers: usize,
+        dec_layers: usize,
+    ) -> Self {
+        assert!(dec_layers > 0, "the decoder should have at least one layer");
+
+        let backbone_width = match backbone_depth {
+            d if d < 0.5 => 0.5,
+            d if d < 0.8 => 0.75,
+            d if d < 1.2 => 1.0,
+            _ => 1.25,
+        };
+        let backbone = CspDarknetConfig::new(backbone_depth, backbone_width, false);
+        let in_channels = backbone.stage_channels()[FEATURE_STAGE];
+        let input_proj = Conv2dConfig::new([in_channels, hidden_dim], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+        let encoder =
+            TransformerEncoderConfig::new(hidden_dim, hidden_dim * FFN_RATIO, nheads, enc_layers)
+                .with_dropout(0.);
+
+        Self {
+            backbone,
+            input_proj,
+            encoder,
+            decoder: ConditionalDecoderLayerConfig::new(hidden_dim, nheads),
+            decoder_norm: LayerNormConfig::new(hidden_dim),
+            class_head: MlpConfig::new(hidden_dim, hidden_dim, num_classes, 3),
+            box_head: MlpConfig::new(hidden_dim, hidden_dim, 4, 3),
+            num_queries,
+            hidden_dim,
+            dec_layers,
+        }
+    }
+
+    /// Initialize a new [Conditional DETR](ConditionalDetr) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ConditionalDetr<B> {
+        ConditionalDetr {
+            backbone: self.backbone.init(device),
+            input_proj: self.input_proj.init(device),
+            encoder: self.encoder.init(device),
+            query_pos: Param::from_tensor(Tensor::random(
+                [self.num_queries, self.hidden_dim],
+                Distribution::Normal(0., 1.),
+                device,
+            )),
+            decoder: (0..self.dec_layers)
+                .map(|_| self.decoder.init(device))
+                .collect(),
+            decoder_norm: self.decoder_norm.init(device),
+            class_head: self.class_head.init(device),
+            box_head: self.box_head.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Identity linear layer without bias.
+    fn identity(dim: usize, device: &Device<TestBackend>) -> Linear<TestBackend> {
+        let weight = (0..dim * dim)
+            .map(|i| if i / dim == i % dim { 1. } else { 0. })
+            .collect::<Vec<f32>>();
+
+        Linear {
+            weight: Param::from_tensor(Tensor::from_data(
+                TensorData::new(weight, [dim, dim]),
+                device,
+            )),
+            bias: None,
+        }
+    }
+
+    /// Mean entropy of the attention weights `[B, h, N, L]` along the last dimension.
+    fn entropy(weights: Tensor<TestBackend, 4>) -> f32 {
+        (weights.clone() * weights.clamp_min(1e-12).log())
+            .sum_dim(3)
+            .neg()
+            .mean()
+            .into_scalar()
+    }
+
+    #[test]
+    fn conditional_detr_outputs() {
+        let device = Default::default();
+        let model =
+            ConditionalDetrConfig::new(0.33, 3, 5, 16, 2, 1, 3).init::<TestBackend>(&device);
+        let images = Tensor::random([2, 3, 64, 64], Distribution::Default, &device);
+
+        let output = model.forward(images);
+
+        assert_eq!(output.prediction.logits.dims(), [2, 5, 3]);
+        assert_eq!(output.prediction.boxes.dims(), [2, 5, 4]);
+        assert_eq!(output.aux_predictions.len(), 2);
+        for prediction in output.aux_predictions.iter() {
+            assert_eq!(prediction.logits.dims(), [2, 5, 3]);
+            assert_eq!(prediction.boxes.dims(), [2, 5, 4]);
+        }
+        assert_eq!(output.reference_points.len(), 3);
+        for points in output.reference_points {
+            assert_eq!(points.dims(), [2, 5, 2]);
+        }
+    }
+
+    #[test]
+    fn spatial_query_sharpens_attention() {
+        let device = Default::default();
+        let hidden_dim = 16;
+        let mut attn = ConditionalCrossAttentionConfig::new(hidden_dim, 1).init(&device);
+        attn.query_spatial = identity(hidden_dim, &device);
+        attn.key_spatial = identity(hidden_dim, &device);
+
+        let pos = sine_position_encoding::<TestBackend>([4, 4], hidden_dim / 2, &device);
+        let memory = Tensor::random([1, 16, hidden_dim], Distribution::Default, &device);
+        let tgt = Tensor::random([1, 3, hidden_dim], Distribution::Default, &device);
+
+        // Content attention only, then conditioned on the positions of cells 0, 5 and 10
+        let (_, unconditional) = attn.forward(
+            tgt.clone(),
+            Tensor::zeros([1, 3, hidden_dim], &device),
+            memory.clone(),
+            pos.clone(),
+        );
+        let indices = Tensor::from_ints([0, 5, 10], &device);
+        let spatial_query = pos.clone().select(1, indices).mul_scalar(100.);
+        let (_, conditional) = attn.forward(tgt, spatial_query, memory, pos);
+
+        assert_eq!(conditional.dims(), [1, 1, 3, 16]);
+        assert!(entropy(conditional.clone()) < entropy(unconditional));
+        let (_, attended) = conditional.reshape([3, 16]).max_dim_with_indices(1);
+        attended
+            .into_data()
+            .assert_eq(&TensorData::from([[0i64], [5], [10]]), false);
+    }
+
+    #[test]
+    #[should_panic = "at least one layer"]
+    fn conditional_detr_without_decoder() {
+        let _ = ConditionalDetrConfig::new(0.33, 3, 5, 16, 2, 1, 0);
+    }
+}
@@ -0,0 +1,818 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{
+        attention::{MhaInput, MultiHeadAttention, MultiHeadAttentionConfig},
+        conv::{Conv2d, Conv2dConfig},
+        Initializer, LayerNorm, LayerNormConfig, Linear, LinearConfig, PaddingConfig2d,
+    },
+    tensor::{
+        activation::{sigmoid, softmax},
+        backend::Backend,
+        Device, Distribution, Int, Tensor,
+    },
+};
+
+use super::{conditional::inverse_sigmoid, sine_position_encoding, DetrPrediction, Mlp, MlpConfig};
+use crate::model::{
+    blocks::{bilinear_sample_points, ActivationType, MlpBlock, MlpBlockConfig},
+    darknet::{CspDarknet, CspDarknetConfig, DarknetFeatures},
+};
+
+/// Index of the `dark3` stage in the [backbone stages](CspDarknetConfig::stage_channels).
+const FIRST_FEATURE_STAGE: usize = 1;
+/// Number of feature levels extracted from the backbone (`dark3`, `dark4` and `dark5`).
+const NUM_BACKBONE_LEVELS: usize = 3;
+/// Ratio of the feed-forward dimension to the hidden dimension of the transformer layers.
+const FFN_RATIO: usize = 4;
+
+/// Normalized `(x, y)` centers of the cells of each feature map, used as the reference points of
+/// the encoder queries.
+///
+/// # Shapes
+///   - output: `[1, sum(height * width), 2]`
+pub fn grid_reference_points<B: Backend>(
+    spatial_shapes: &[[usize; 2]],
+    device: &Device<B>,
+) -> Tensor<B, 3> {
+    let points = spatial_shapes
+        .iter()
+        .map(|&[height, width]| {
+            let n = height * width;
+            let xs = Tensor::<B, 1, Int>::arange(0..width as i64, device)
+                .float()
+                .add_scalar(0.5)
+                .div_scalar(width as f32)
+                .reshape([1, width])
+                .repeat_dim(0, height)
+                .reshape([1, n, 1]);
+            let ys = Tensor::<B, 1, Int>::arange(0..height as i64, device)
+                .float()
+                .add_scalar(0.5)
+                .div_scalar(height as f32)
+                .reshape([height, 1])
+                .repeat_dim(1, width)
+                .reshape([1, n, 1]);
+
+            Tensor::cat(vec![xs, ys], 2)
+        })
+        .collect();
+
+    Tensor::cat(points, 1)
+}
+
+/// Multi-scale deformable attention of [Deformable DETR](DeformableDetr).
+///
+/// Instead of attending to all the positions of the feature maps, each query attends to a small
+/// set of sampling points per head and per feature level, predicted as offsets relative to the
+/// reference point of the query. The features are bilinearly interpolated at the sampling points
+/// and combined with the predicted attention weights (normalized over the levels and points).
+#[derive(Module, Debug)]
+pub struct MultiScaleDeformableAttention<B: Backend> {
+    sampling_offsets: Linear<B>,
+    attention_weights: Linear<B>,
+    value_proj: Linear<B>,
+    output_proj: Linear<B>,
+    num_heads: usize,
+    num_levels: usize,
+    num_points: usize,
+}
+
+impl<B: Backend> MultiScaleDeformableAttention<B> {
+    /// Predict the sampling offsets of the queries, in pixels of each feature level.
+    ///
+    /// The offsets of the sampling points of level `l` are at indices `l * num_points` to
+    /// `(l + 1) * num_points` of the fourth dimension.
+    ///
+    /// # Shapes
+    ///   - query: `[batch_size, num_queries, d_model]`
+    ///   - output: `[batch_size, num_queries, num_heads, num_levels * num_points, 2]`
+    pub fn sampling_offsets(&self, query: Tensor<B, 3>) -> Tensor<B, 5> {
+        let [b, q, _] = query.dims();
+
+        self.sampling_offsets.forward(query).reshape([
+            b,
+            q,
+            self.num_heads,
+            self.num_levels * self.num_points,
+            2,
+        ])
+    }
+
+    /// Attend to the multi-scale feature maps.
+    ///
+    /// # Arguments
+    ///
+    /// * `query`: Queries. Shape: `[batch_size, num_queries, d_model]`.
+    /// * `reference_points` - Normalized `(x, y)` reference points of the queries, shared by
+    ///   all the feature levels. Shape: `[batch_size, num_queries, 2]`.
+    /// * `input` - Flattened and concatenated feature maps.
+    ///   Shape: `[batch_size, sum(height * width), d_model]`.
+    /// * `spatial_shapes` - Size `[height, width]` of each feature map.
+    ///
+    /// # Returns
+    ///
+    /// The attention output with shape `[batch_size, num_queries, d_model]`.
+    pub fn forward(
+        &self,
+        query: Tensor<B, 3>,
+        reference_points: Tensor<B, 3>,
+        input: Tensor<B, 3>,
+        spatial_shapes: &[[usize; 2]],
+    ) -> Tensor<B, 3> {
+        let [b, q, c] = query.dims();
+        let [_, s, _] = input.dims();
+        let (h, l, p) = (self.num_heads, self.num_levels, self.num_points);
+        let d = c / h;
+        assert_eq!(
+            spatial_shapes.len(),
+            l,
+            "expected one spatial shape per feature level"
+        );
+        let device = query.device();
+
+        let offsets = self.sampling_offsets(query.clone());
+        // Attention weights, normalized over the levels and points [B * h, 1, q, l * p]
+        let weights = softmax(
+            self.attention_weights
+                .forward(query)
+                .reshape([b, q, h, l * p]),
+            3,
+        )
+        .swap_dims(1, 2)
+        .reshape([b * h, 1, q, l * p]);
+
+        // Values of each head [B * h, d, S]
+        let value = self
+            .value_proj
+            .forward(input)
+            .reshape([b, s, h, d])
+            .swap_dims(1, 2)
+            .swap_dims(2, 3)
+            .reshape([b * h, d, s]);
+
+        let reference_points = reference_points.reshape([b, q, 1, 1, 2]);
+        let mut start = 0;
+        let mut sampled = Vec::with_capacity(l);
+        for (level, &[height, width]) in spatial_shapes.iter().enumerate() {
+            let value = value
+                .clone()
+                .slice([0..b * h, 0..d, start..start + height * width]);
+            start += height * width;
+
+            // Sampling locations in pixels [B, q, h, p, 2]
+            let size = Tensor::<B, 1>::from_floats([width as f32, height as f32], &device)
+                .reshape([1, 1, 1, 1, 2]);
+            let offsets =
+                offsets
+                    .clone()
+                    .slice([0..b, 0..q, 0..h, level * p..(level + 1) * p, 0..2]);
+            let locations = (reference_points.clone() * size + offsets)
+                .sub_scalar(0.5)
+                .swap_dims(1, 2)
+                .reshape([b * h, q * p, 2]);
+            let px = locations.clone().slice([0..b * h, 0..q * p, 0..1]);
+            let py = locations.slice([0..b * h, 0..q * p, 1..2]);
+
+            let features = bilinear_sample_points(
+                value,
+                py.reshape([b * h, q * p]),
+                px.reshape([b * h, q * p]),
+                [height, width],
+            );
+            sampled.push(features.reshape([b * h, d, q, p]));
+        }
+
+        // Weighted sum of the sampled features [B, q, C]
+        let x = (Tensor::cat(sampled, 3) * weights)
+            .sum_dim(3)
+            .reshape([b, h * d, q])
+            .swap_dims(1, 2);
+
+        self.output_proj.forward(x)
+    }
+}
+
+/// [Multi-scale deformable attention](MultiScaleDeformableAttention) configuration.
+pub struct MultiScaleDeformableAttentionConfig {
+    d_model: usize,
+    num_heads: usize,
+    num_levels: usize,
+    num_points: usize,
+}
+
+impl MultiScaleDeformableAttentionConfig {
+    /// Create a new instance of the multi-scale deformable attention
+    /// [config](MultiScaleDeformableAttentionConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `d_model`: Dimension of the queries and features.
+    /// * `num_heads` - Number of attention heads.
+    /// * `num_levels` - Number of feature levels.
+    /// * `num_points` - Number of sampling points per head and per level.
+    pub fn new(d_model: usize, num_heads: usize, num_levels: usize, num_points: usize) -> Self {
+        assert!(
+            d_model % num_heads == 0,
+            "the model dimension should be divisible by the number of heads"
+        );
+
+        Self {
+            d_model,
+            num_heads,
+            num_levels,
+            num_points,
+        }
+    }
+
+    /// Initial sampling offsets, pointing in a different direction for each head at increasing
+    /// distances for each point. Shape: `[num_heads * num_levels * num_points * 2]`.
+    fn initial_offsets<B: Backend>(&self, device: &Device<B>) -> Tensor<B, 1> {
+        let (h, l, p) = (self.num_heads, self.num_levels, self.num_points);
+
+        let angles = Tensor::<B, 1, Int>::arange(0..h as i64, device)
+            .float()
+            .mul_scalar(2. * core::f32::consts::PI / h as f32)
+            .reshape([h, 1]);
+        let directions = Tensor::cat(vec![angles.clone().cos(), angles.sin()], 1);
+        let directions = directions.clone() / directions.abs().max_dim(1);
+        let distances = Tensor::<B, 1, Int>::arange(1..p as i64 + 1, device)
+            .float()
+            .reshape([1, 1, p, 1]);
+
+        (directions.reshape([h, 1, 1, 2]).repeat_dim(1, l) * distances).reshape([h * l * p * 2])
+    }
+
+    /// Initialize a new [multi-scale deformable attention](MultiScaleDeformableAttention) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> MultiScaleDeformableAttention<B> {
+        let num_samples = self.num_heads * self.num_levels * self.num_points;
+
+        let mut sampling_offsets = LinearConfig::new(self.d_model, num_samples * 2)
+            .with_initializer(Initializer::Zeros)
+            .init(device);
+        sampling_offsets.bias = Some(Param::from_tensor(self.initial_offsets(device)));
+        // Uniform attention weights at initialization
+        let attention_weights = LinearConfig::new(self.d_model, num_samples)
+            .with_initializer(Initializer::Zeros)
+            .init(device);
+
+        MultiScaleDeformableAttention {
+            sampling_offsets,
+            attention_weights,
+            value_proj: LinearConfig::new(self.d_model, self.d_model).init(device),
+            output_proj: LinearConfig::new(self.d_model, self.d_model).init(device),
+            num_heads: self.num_heads,
+            num_levels: self.num_levels,
+            num_points: self.num_points,
+        }
+    }
+}
+
+/// Encoder layer of [Deformable DETR](DeformableDetr): multi-scale deformable self-attention and
+/// feed-forward network, each followed by a residual connection and a layer normalization.
+#[derive(Module, Debug)]
+pub struct DeformableEncoderLayer<B: Backend> {
+    self_attn: MultiScaleDeformableAttention<B>,
+    norm1: LayerNorm<B>,
+    ffn: MlpBlock<B>,
+    norm2: LayerNorm<B>,
+}
+
+impl<B: Backend> DeformableEncoderLayer<B> {
+    /// Apply the encoder layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `src`: Flattened feature maps. Shape: `[batch_size, seq_length, d_model]`.
+    /// * `pos` - Positional (and level) encoding. Shape: `[1, seq_length, d_model]`.
+    /// * `reference_points` - Normalized reference points. Shape: `[batch_size, seq_length, 2]`.
+    /// * `spatial_shapes` - Size `[height, width]` of each feature map.
+    pub fn forward(
+        &self,
+        src: Tensor<B, 3>,
+        pos: Tensor<B, 3>,
+        reference_points: Tensor<B, 3>,
+        spatial_shapes: &[[usize; 2]],
+    ) -> Tensor<B, 3> {
+        let x = self.self_attn.forward(
+            src.clone() + pos,
+            reference_points,
+            src.clone(),
+            spatial_shapes,
+        );
+        let src = self.norm1.forward(src + x);
+
+        let x = self.ffn.forward(src.clone());
+        self.norm2.forward(src + x)
+    }
+}
+
+/// [Deformable encoder layer](DeformableEncoderLayer) configuration.
+pub struct DeformableEncoderLayerConfig {
+    self_attn: MultiScaleDeformableAttentionConfig,
+    ffn: MlpBlockConfig,
+    norm: LayerNormConfig,
+}
+
+impl DeformableEncoderLayerConfig {
+    /// Create a new instance of the deformable encoder layer
+    /// [config](DeformableEncoderLayerConfig).
+    pub fn new(d_model: usize, num_heads: usize, num_levels: usize, num_points: usize) -> Self {
+        Self {
+            self_attn: MultiScaleDeformableAttentionConfig::new(
+                d_model, num_heads, num_levels, num_points,
+            ),
+            ffn: MlpBlockConfig::ffn(d_model, d_model * FFN_RATIO, 0., ActivationType::Relu),
+            norm: LayerNormConfig::new(d_model),
+        }
+    }
+
+    /// Initialize a new [deformable encoder layer](DeformableEncoderLayer) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DeformableEncoderLayer<B> {
+        DeformableEncoderLayer {
+            self_attn: self.self_attn.init(device),
+            norm1: self.norm.init(device),
+            ffn: self.ffn.init(device),
+            norm2: self.norm.init(device),
+        }
+    }
+}
+
+/// Decoder layer of [Deformable DETR](DeformableDetr): self-attention, multi-scale deformable
+/// cross-attention and feed-forward network, each followed by a residual connection and a layer
+/// normalization.
+#[derive(Module, Debug)]
+pub struct DeformableDecoderLayer<B: Backend> {
+    self_attn: MultiHeadAttention<B>,
+    norm1: LayerNorm<B>,
+    cross_attn: MultiScaleDeformableAttention<B>,
+    norm2: LayerNorm<B>,
+    ffn: MlpBlock<B>,
+    norm3: LayerNorm<B>,
+}
+
+impl<B: Backend> DeformableDecoderLayer<B> {
+    /// Apply the decoder layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `tgt`: Decoder embeddings. Shape: `[batch_size, num_queries, d_model]`.
+    /// * `query_pos` - Query positional embeddings. Shape: `[batch_size, num_queries, d_model]`.
+    /// * `reference_points` - Normalized reference points. Shape: `[batch_size, num_queries, 2]`.
+    /// * `memory` - Encoder memory. Shape: `[batch_size, seq_length, d_model]`.
+    /// * `spatial_shapes` - Size `[height, width]` of each feature map.
+    pub fn forward(
+        &self,
+        tgt: Tensor<B, 3>,
+        query_pos: Tensor<B, 3>,
+        reference_points: Tensor<B, 3>,
+        memory: Tensor<B, 3>,
+        spatial_shapes: &[[usize; 2]],
+    ) -> Tensor<B, 3> {
+        // Self-attention
+        let q = tgt.clone() + query_pos.clone();
+        let x = self
+            .self_attn
+            .forward(MhaInput::new(q.clone(), q, tgt.clone()))
+            .context;
+        let tgt = self.norm1.forward(tgt + x);
+
+        // Deformable cross-attention
+        let x = self.cross_attn.forward(
+            tgt.clone() + query_pos,
+            reference_points,
+            memory,
+            spatial_shapes,
+        );
+        let tgt = self.norm2.forward(tgt + x);
+
+        // Feed-forward network
+        let x = self.ffn.forward(tgt.clone());
+        self.norm3.forward(tgt + x)
+    }
+}
+
+/// [Deformable decoder layer](DeformableDecoderLayer) configuration.
+pub struct DeformableDecoderLayerConfig {
+    self_attn: MultiHeadAttentionConfig,
+    cross_attn: MultiScaleDeformableAttentionConfig,
+    ffn: MlpBlockConfig,
+    norm: LayerNormConfig,
+}
+
+impl DeformableDecoderLayerConfig {
+    /// Create a new instance of the deformable decoder layer
+    /// [config](DeformableDecoderLayerConfig).
+    pub fn new(d_model: usize, num_heads: usize, num_levels: usize, num_points: usize) -> Self {
+        Self {
+            self_attn: MultiHeadAttentionConfig::new(d_model, num_heads).with_dropout(0.),
+            cross_attn: MultiScaleDeformableAttentionConfig::new(
+                d_model, num_heads, num_levels, num_points,
+            ),
+            ffn: MlpBlockConfig::ffn(d_model, d_model * FFN_RATIO, 0., ActivationType::Relu),
+            norm: LayerNormConfig::new(d_model),
+        }
+    }
+
+    /// Initialize a new [deformable decoder layer](DeformableDecoderLayer) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DeformableDecoderLayer<B> {
+        DeformableDecoderLayer {
+            self_attn: self.self_attn.init(device),
+            norm1: self.norm.init(device),
+            cross_attn: self.cross_attn.init(device),
+            norm2: self.norm.init(device),
+            ffn: self.ffn.init(device),
+            norm3: self.norm.init(device),
+        }
+    }
+}
+
+/// [Deformable DETR](DeformableDetr) outputs.
+pub struct DeformableDetrOutput<B: Backend> {
+    /// Predictions of the last decoder layer.
+    pub prediction: DetrPrediction<B>,
+    /// Predictions of the intermediate decoder layers, for the auxiliary losses.
+    pub aux_predictions: Vec<DetrPrediction<B>>,
+    /// Reference points `(x, y)` of each decoder layer. Shape: `[batch_size, num_queries, 2]`.
+    pub reference_points: Vec<Tensor<B, 3>>,
+}
+
+/// [Deformable DETR](https://arxiv.org/abs/2010.04159) object detection model.
+///
+/// The transformer attends to multi-scale feature maps with
+/// [deformable attention](MultiScaleDeformableAttention), whose complexity is linear in the
+/// number of positions of the feature maps. The encoder reference points are the centers of the
+/// feature map cells, and the decoder reference points are iteratively refined: each decoder
+/// layer uses the box centers predicted by the previous layer.
+#[derive(Module, Debug)]
+pub struct DeformableDetr<B: Backend> {
+    backbone: CspDarknet<B>,
+    /// Projection of the backbone features, followed by the strided convolutions of the extra
+    /// feature levels.
+    input_proj: Vec<Conv2d<B>>,
+    /// Embedding of each feature level. Shape: `[num_feature_levels, d_model]`.
+    level_embed: Param<Tensor<B, 2>>,
+    encoder: Vec<DeformableEncoderLayer<B>>,
+    /// Object query positional embeddings. Shape: `[num_queries, d_model]`.
+    query_pos: Param<Tensor<B, 2>>,
+    /// Initial object query embeddings. Shape: `[num_queries, d_model]`.
+    query_embed: Param<Tensor<B, 2>>,
+    /// Predicts the initial reference point of each query from its positional embedding.
+    reference_point: Linear<B>,
+    decoder: Vec<DeformableDecoderLayer<B>>,
+    class_head: Linear<B>,
+    box_head: Mlp<B>,
+}
+
+impl<B: Backend> DeformableDetr<B> {
+    /// Multi-scale feature maps, from the highest to the lowest resolution.
+    fn features(&self, images: Tensor<B, 4>) -> Vec<Tensor<B, 4>> {
+        let DarknetFeatures(dark3, dark4, dark5) = self.backbone.forward(images);
+        let num_levels = self.input_proj.len();
+        let backbone_levels = num_levels.min(NUM_BACKBONE_LEVELS);
+
+        let mut inputs = vec![dark3, dark4, dark5];
+        inputs.drain(..NUM_BACKBONE_LEVELS - backbone_levels);
+
+        let mut features: Vec<_> = inputs
+            .iter()
+            .zip(self.input_proj.iter())
+            .map(|(x, proj)| proj.forward(x.clone()))
+            .collect();
+        // The first extra level is computed from the last backbone features
+        let mut last = inputs.pop().unwrap();
+        for proj in self.input_proj.iter().skip(backbone_levels) {
+            last = proj.forward(last);
+            features.push(last.clone());
+        }
+
+        features
+    }
+
+    /// Predict the objects of a batch of images.
+    ///
+    /// # Shapes
+    ///   - images: `[batch_size, 3, height, width]`
+    pub fn forward(&self, images: Tensor<B, 4>) -> DeformableDetrOutput<B> {
+        let features = self.features(images);
+        let device = features[0].device();
+        let [b, c, _, _] = features[0].dims();
+
+        // Flatten the feature maps, with the positional and level encodings
+        let spatial_shapes: Vec<[usize; 2]> = features
+            .iter()
+            .map(|x| {
+                let [_, _, h, w] = x.dims();
+                [h, w]
+            })
+            .collect();
+        let [num_levels, _] = self.level_embed.dims();
+        let mut src = Vec::with_capacity(num_levels);
+        let mut pos = Vec::with_capacity(num_levels);
+        for (level, (x, &[h, w])) in features.into_iter().zip(&spatial_shapes).enumerate() {
+            let level_embed = self
+                .level_embed
+                .val()
+                .slice([level..level + 1, 0..c])
+                .reshape([1, 1, c]);
+            src.push(x.reshape([b, c, h * w]).swap_dims(1, 2));
+            pos.push(sine_position_encoding([h, w], c / 2, &device) + level_embed);
+        }
+        let src = Tensor::cat(src, 1);
+        let pos = Tensor::cat(pos, 1);
+
+        // Encoder, with the feature map cells as reference points
+        let encoder_points = grid_reference_points(&spatial_shapes, &device).repeat_dim(0, b);
+        let memory = self.encoder.iter().fold(src, |x, layer| {
+            layer.forward(x, pos.clone(), encoder_points.clone(), &spatial_shapes)
+        });
+
+        // Decoder, with iterative refinement of the reference points
+        let [num_queries, _] = self.query_pos.dims();
+        let query_pos = self.query_pos.val().unsqueeze::<3>().repeat_dim(0, b);
+        let mut tgt = self.query_embed.val().unsqueeze::<3>().repeat_dim(0, b);
+        let mut points = sigmoid(self.reference_point.forward(query_pos.clone()));
+        let mut predictions = Vec::with_capacity(self.decoder.len());
+        let mut reference_points = Vec::with_capacity(self.decoder.len());
+        for layer in self.decoder.iter() {
+            tgt = layer.forward(
+                tgt,
+                query_pos.clone(),
+                points.clone(),
+                memory.clone(),
+                &spatial_shapes,
+            );
+            let prediction = self.predict(tgt.clone(), points.clone());
+            reference_points.push(points);
+
+            // The predicted box centers are the reference points of the next layer
+            points = prediction
+                .boxes
+                .clone()
+                .slice([0..b, 0..num_queries, 0..2])
+                .detach();
+            predictions.push(prediction);
+        }
+
+        let prediction = predictions.pop().expect("the decoder should have layers");
+
+        DeformableDetrOutput {
+            prediction,
+            aux_predictions: predictions,
+            reference_points,
+        }
+    }
+
+    /// Predict the class logits and the boxes, whose centers are relative to the reference points.
+    fn predict(&self, x: Tensor<B, 3>, reference_points: Tensor<B, 3>) -> DetrPrediction<B> {
+        let [b, q, _] = x.dims();
+        let boxes = self.box_head.forward(x.clone());
+
+        let xy = boxes.clone().slice([0..b, 0..q, 0..2]) + inverse_sigmoid(reference_points);
+        let wh = boxes.slice([0..b, 0..q, 2..4]);
+
+        DetrPrediction {
+            logits: self.class_head.forward(x),
+            boxes: sigmoid(Tensor::cat(vec![xy, wh], 2)),
+        }
+    }
+}
+
+/// [Deformable DETR](DeformableDetr) configuration.
+pub struct DeformableDetrConfig {
+    backbone: CspDarknetConfig,
+    input_proj: Vec<Conv2dConfig>,
+    encoder: DeformableEncoderLayerConfig,
+    decoder: DeformableDecoderLayerConfig,
+    reference_point: LinearConfig,
+    class_head: LinearConfig,
+    box_head: MlpConfig,
+    num_queries: usize,
+    d_model: usize,
+    enc_layers: usize,
+    dec_layers: usize,
+}
+
+impl DeformableDetrConfig {
+    /// Create a new instance of the Deformable DETR [config](DeformableDetrConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `backbone_depth`: Depth multiple of the [CSPDarknet](CspDarknet) backbone, which selects
+    ///   the YOLOX variant (0.33 for S, 0.67 for M, 1.0 for L and 1.33 for X).
+    /// * `num_classes` - Number of classes.
+    /// * `num_queries` - Number of object queries.
+    /// * `d_model` - Hidden dimension of the transformer.
+    /// * `nheads` - Number of attention heads.
+    /// * `enc_layers` - Number of encoder layers.
+    /// * `dec_layers` - Number of decoder layers.
+    /// * `num_feature_levels` - Number of feature levels: the last backbone stages (up to 3),
+    ///   followed by extra levels computed with strided convolutions.
+    /// * `num_points_per_head` - Number of sampling points per attention head and per level.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        backbone_depth: f64,
+        num_classes: usize,
+        num_queries: usize,
+        d_model: usize,
+        nheads: usize,
+        enc_layers: usize,
+        dec_layers: usize,
+        num_feature_levels: usize,
+        num_points_per_head: usize,
+    ) -> Self {
+        assert!(dec_layers > 0, "the decoder should have at least one layer");
+        assert!(
+            num_feature_levels > 0,
+            "at least one feature level is required"
+        );
+
+        let backbone_width = match backbone_depth {
+            d if d < 0.5 => 0.5,
+            d if d < 0.8 => 0.75,
+            d if d < 1.2 => 1.0,
+            _ => 1.25,
+        };
+        let backbone = CspDarknetConfig::new(backbone_depth, backbone_width, false);
+
+        // The last stages of the backbone, followed by the extra levels
+        let backbone_levels = num_feature_levels.min(NUM_BACKBONE_LEVELS);
+        let stage_channels = backbone.stage_channels();
+        let first_stage = FIRST_FEATURE_STAGE + NUM_BACKBONE_LEVELS - backbone_levels;
+        let mut input_proj: Vec<_> = stage_channels[first_stage..]
+            .iter()
+            .map(|&channels| {
+                Conv2dConfig::new([channels, d_model], [1, 1])
+                    .with_padding(PaddingConfig2d::Explicit(0, 0))
+            })
+            .collect();
+        let mut in_channels = stage_channels[stage_channels.len() - 1];
+        for _ in backbone_levels..num_feature_levels {
+            input_proj.push(
+                Conv2dConfig::new([in_channels, d_model], [3, 3])
+                    .with_stride([2, 2])
+                    .with_padding(PaddingConfig2d::Explicit(1, 1)),
+            );
+            in_channels = d_model;
+        }
+
+        let encoder = DeformableEncoderLayerConfig::new(
+            d_model,
+            nheads,
+            num_feature_levels,
+            num_points_per_head,
+        );
+        let decoder = DeformableDecoderLayerConfig::new(
+            d_model,
+            nheads,
+            num_feature_levels,
+            num_points_per_head,
+        );
+
+        Self {
+            backbone,
+            input_proj,
+            encoder,
+            decoder,
+            reference_point: LinearConfig::new(d_model, 2),
+            class_head: LinearConfig::new(d_model, num_classes),
+            box_head: MlpConfig::new(d_model, d_model, 4, 3),
+            num_queries,
+            d_model,
+            enc_layers,
+            dec_layers,
+        }
+    }
+
+    /// Initialize a new [Deformable DETR](DeformableDetr) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DeformableDetr<B> {
+        let embedding = |n: usize| {
+            Param::from_tensor(Tensor::random(
+                [n, self.d_model],
+                Distribution::Normal(0., 1.),
+                device,
+            ))
+        };
+
+        DeformableDetr {
+            backbone: self.backbone.init(device),
+            input_proj: self.input_proj.iter().map(|c| c.init(device)).collect(),
+            level_embed: embedding(self.input_proj.len()),
+            encoder: (0..self.enc_layers)
+                .map(|_| self.encoder.init(device))
+                .collect(),
+            query_pos: embedding(self.num_queries),
+            query_embed: embedding(self.num_queries),
+            reference_point: self.reference_point.init(device),
+            decoder: (0..self.dec_layers)
+                .map(|_| self.decoder.init(device))
+                .collect(),
+            class_head: self.class_head.init(device),
+            box_head: self.box_head.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn grid_reference_points_centers() {
+        let device = Default::default();
+
+        let points = grid_reference_points::<TestBackend>(&[[2, 2], [1, 1]], &device);
+
+        points.into_data().assert_approx_eq(
+            &TensorData::from([[
+                [0.25, 0.25],
+                [0.75, 0.25],
+                [0.25, 0.75],
+                [0.75, 0.75],
+                [0.5, 0.5],
+            ]]),
+            5,
+        );
+    }
+
+    #[test]
+    fn deformable_attention_sampling_offsets_shape() {
+        let device = Default::default();
+        let attn =
+            MultiScaleDeformableAttentionConfig::new(32, 4, 3, 2).init::<TestBackend>(&device);
+        let query = Tensor::random([2, 21, 32], Distribution::Default, &device);
+
+        let offsets = attn.sampling_offsets(query);
+
+        assert_eq!(offsets.dims(), [2, 21, 4, 3 * 2, 2]);
+    }
+
+    #[test]
+    fn deformable_attention_initial_offsets() {
+        let device = Default::default();
+        let attn =
+            MultiScaleDeformableAttentionConfig::new(8, 4, 1, 2).init::<TestBackend>(&device);
+        let query = Tensor::random([1, 1, 8], Distribution::Default, &device);
+
+        // The offsets do not depend on the query at initialization: one direction per head, at
+        // increasing distances for each point
+        let offsets = attn.sampling_offsets(query).reshape([4, 2, 2]);
+
+        offsets.into_data().assert_approx_eq(
+            &TensorData::from([
+                [[1., 0.], [2., 0.]],
+                [[0., 1.], [0., 2.]],
+                [[-1., 0.], [-2., 0.]],
+                [[0., -1.], [0., -2.]],
+            ]),
+            4,
+        );
+    }
+
+    #[test]
+    fn deformable_attention_output_shape() {
+        let device = Default::default();
+        let attn =
+            MultiScaleDeformableAttentionConfig::new(32, 4, 3, 2).init::<TestBackend>(&device);
+        let spatial_shapes = [[4, 4], [2, 2], [1, 1]];
+        let input = Tensor::random([2, 21, 32], Distribution::Default, &device);
+        let reference_points = grid_reference_points(&spatial_shapes, &device).repeat_dim(0, 2);
+
+        let output = attn.forward(input.clone(), reference_points, input, &spatial_shapes);
+
+        assert_eq!(output.dims(), [2, 21, 32]);
+    }
+
+    #[test]
+    #[should_panic = "expected one spatial shape per feature level"]
+    fn deformable_attention_wrong_levels() {
+        let device = Default::default();
+        let attn =
+            MultiScaleDeformableAttentionConfig::new(32, 4, 3, 2).init::<TestBackend>(&device);
+        let input = Tensor::random([1, 20, 32], Distribution::Default, &device);
+        let reference_points = grid_reference_points(&[[4, 4], [2, 2]], &device);
+
+        attn.forward(input.clone(), reference_points, input, &[[4, 4], [2, 2]]);
+    }
+
+    #[test]
+    fn deformable_detr_outputs() {
+        let device = Default::default();
+        let model =
+            DeformableDetrConfig::new(0.33, 3, 5, 32, 4, 1, 2, 4, 2).init::<TestBackend>(&device);
+        let images = Tensor::random([2, 3, 64, 64], Distribution::Default, &device);
+
+        let output = model.forward(images);
+
+        assert_eq!(output.prediction.logits.dims(), [2, 5, 3]);
+        assert_eq!(output.prediction.boxes.dims(), [2, 5, 4]);
+        assert_eq!(output.aux_predictions.len(), 1);
+        assert_eq!(output.reference_points.len(), 2);
+        for points in output.reference_points {
+            assert_eq!(points.dims(), [2, 5, 2]);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{Linear, LinearConfig},
+    tensor::{activation::relu, backend::Backend, Device, Tensor},
+};
+
+/// Multi-layer perceptron with ReLU activations between the linear layers, as used for the
+/// prediction heads of DETR models.
+#[derive(Module, Debug)]
+pub struct Mlp<B: Backend> {
+    layers: Vec<Linear<B>>,
+}
+
+impl<B: Backend> Mlp<B> {
+    /// Apply the MLP to the last dimension of the input.
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let num_layers = self.layers.len();
+
+        self.layers.iter().enumerate().fold(x, |x, (i, layer)| {
+            let x = layer.forward(x);
+            if i + 1 < num_layers {
+                relu(x)
+            } else {
+                x
+            }
+        })
+    }
+}
+
+/// [MLP](Mlp) configuration.
+pub struct MlpConfig {
+    layers: Vec<LinearConfig>,
+}
+
+impl MlpConfig {
+    /// Create a new instance of the MLP [config](MlpConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_dim`: Input dimension.
+    /// * `hidden_dim` - Dimension of the hidden layers.
+    /// * `out_dim` - Output dimension.
+    /// * `num_layers` - Number of linear layers.
+    pub fn new(in_dim: usize, hidden_dim: usize, out_dim: usize, num_layers: usize) -> Self {
+        assert!(num_layers > 0, "the MLP should have at least one layer");
+
+        let layers = (0..num_layers)
+            .map(|i| {
+                let d_in = if i == 0 { in_dim } else { hidden_dim };
+                let d_out = if i + 1 == num_layers {
+                    out_dim
+                } else {
+                    hidden_dim
+                };
+                LinearConfig::new(d_in, d_out)
+            })
+            .collect();
+
+        Self { layers }
+    }
+
+    /// Initialize a new [MLP](Mlp) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Mlp<B> {
+        Mlp {
+            layers: self.layers.iter().map(|l| l.init(device)).collect(),
+        }
+    }
+}
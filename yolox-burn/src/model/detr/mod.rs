@@ -0,0 +1,9 @@
+mod conditional;
+mod deformable;
+mod mlp;
+mod position;
+
+pub use conditional::*;
+pub use deformable::*;
+pub use mlp::*;
+pub use position::*;
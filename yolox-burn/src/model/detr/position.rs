@@ -0,0 +1,61 @@
+use alloc::vec;
+use burn::tensor::{backend::Backend, Device, Int, Tensor};
+
+/// Natural logarithm of the temperature (10000) of the sine embeddings.
+const LN_TEMPERATURE: f32 = 9.210_34;
+
+/// Sine embedding of normalized 2D coordinates, as used for the positional encodings of DETR.
+///
+/// Each coordinate is embedded with `num_feats` sine and cosine features of geometrically
+/// increasing wavelengths, and the `y` embedding is followed by the `x` embedding.
+///
+/// # Shapes
+///   - points: `[batch_size, num_points, 2]` with `(x, y)` coordinates in the range `[0, 1]`
+///   - output: `[batch_size, num_points, 2 * num_feats]`
+pub fn sine_embedding<B: Backend>(points: Tensor<B, 3>, num_feats: usize) -> Tensor<B, 3> {
+    let [b, n, _] = points.dims();
+    let half = num_feats / 2;
+
+    // Frequencies 2π / temperature^(2i / num_feats) [1, 1, half]
+    let freqs = Tensor::<B, 1, Int>::arange(0..half as i64, &points.device())
+        .float()
+        .mul_scalar(-2. * LN_TEMPERATURE / num_feats as f32)
+        .exp()
+        .mul_scalar(2. * core::f32::consts::PI)
+        .reshape([1, 1, half]);
+
+    let embed = |i: usize| {
+        let x = points.clone().slice([0..b, 0..n, i..i + 1]) * freqs.clone();
+        Tensor::cat(vec![x.clone().sin(), x.cos()], 2)
+    };
+
+    Tensor::cat(vec![embed(1), embed(0)], 2)
+}
+
+/// Sine positional encoding of the cells of a `height x width` feature map.
+///
+/// # Shapes
+///   - output: `[1, height * width, 2 * num_feats]`
+pub fn sine_position_encoding<B: Backend>(
+    [height, width]: [usize; 2],
+    num_feats: usize,
+    device: &Device<B>,
+) -> Tensor<B, 3> {
+    let n = height * width;
+    let xs = Tensor::<B, 1, Int>::arange(0..width as i64, device)
+        .float()
+        .add_scalar(0.5)
+        .div_scalar(width as f32)
+        .reshape([1, width])
+        .repeat_dim(0, height)
+        .reshape([1, n, 1]);
+    let ys = Tensor::<B, 1, Int>::arange(0..height as i64, device)
+        .float()
+        .add_scalar(0.5)
+        .div_scalar(height as f32)
+        .reshape([height, 1])
+        .repeat_dim(1, width)
+        .reshape([1, n, 1]);
+
+    sine_embedding(Tensor::cat(vec![xs, ys], 2), num_feats)
+}
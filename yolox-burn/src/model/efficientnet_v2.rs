@@ -0,0 +1,577 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use super::{
+    blocks::{ActivationType, SeBlock, SeBlockConfig},
+    heads::{ClassificationHead, ClassificationHeadConfig},
+    picodet::{ConvNorm, ConvNormConfig},
+};
+
+/// Number of channels of the last convolution, before the classifier.
+const HEAD_CHANNELS: usize = 1280;
+/// Number of channels of the squeeze-and-excitation gating, relative to the block input channels.
+const SE_RATIO: usize = 4;
+/// Default number of classes (ImageNet-1k).
+const NUM_CLASSES: usize = 1000;
+
+/// Size of the [EfficientNetV2](EfficientNetV2) model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EfficientNetV2Variant {
+    /// EfficientNetV2-S, with 21.5M parameters.
+    #[default]
+    S,
+    /// EfficientNetV2-M, with 54M parameters.
+    M,
+    /// EfficientNetV2-L, with 119M parameters.
+    L,
+    /// EfficientNetV2-XL, with 208M parameters.
+    Xl,
+}
+
+/// Configuration of a stage of [EfficientNetV2](EfficientNetV2) blocks.
+#[derive(Debug, Clone, Copy)]
+struct StageSettings {
+    /// Use [fused MBConv](FusedMbConv) blocks instead of [MBConv](MbConv) blocks.
+    fused: bool,
+    expand_ratio: usize,
+    stride: usize,
+    out_channels: usize,
+    num_blocks: usize,
+}
+
+const fn fused(
+    expand_ratio: usize,
+    stride: usize,
+    out_channels: usize,
+    num_blocks: usize,
+) -> StageSettings {
+    StageSettings {
+        fused: true,
+        expand_ratio,
+        stride,
+        out_channels,
+        num_blocks,
+    }
+}
+
+const fn mbconv(
+    expand_ratio: usize,
+    stride: usize,
+    out_channels: usize,
+    num_blocks: usize,
+) -> StageSettings {
+    StageSettings {
+        fused: false,
+        expand_ratio,
+        stride,
+        out_channels,
+        num_blocks,
+    }
+}
+
+impl EfficientNetV2Variant {
+    /// Number of channels of the stem.
+    fn stem_channels(&self) -> usize {
+        match self {
+            Self::S | Self::M => 24,
+            Self::L | Self::Xl => 32,
+        }
+    }
+
+    /// Settings of each stage, from Table 4 of the paper (and the reference implementation for
+    /// the larger variants). The early stages use fused MBConv blocks.
+    fn stages(&self) -> Vec<StageSettings> {
+        match self {
+            Self::S => vec![
+                fused(1, 1, 24, 2),
+                fused(4, 2, 48, 4),
+                fused(4, 2, 64, 4),
+                mbconv(4, 2, 128, 6),
+                mbconv(6, 1, 160, 9),
+                mbconv(6, 2, 256, 15),
+            ],
+            Self::M => vec![
+                fused(1, 1, 24, 3),
+                fused(4, 2, 48, 5),
+                fused(4, 2, 80, 5),
+                mbconv(4, 2, 160, 7),
+                mbconv(6, 1, 176, 14),
+                mbconv(6, 2, 304, 18),
+                mbconv(6, 1, 512, 5),
+            ],
+            Self::L => vec![
+                fused(1, 1, 32, 4),
+                fused(4, 2, 64, 7),
+                fused(4, 2, 96, 7),
+                mbconv(4, 2, 192, 10),
+                mbconv(6, 1, 224, 19),
+                mbconv(6, 2, 384, 25),
+                mbconv(6, 1, 640, 7),
+            ],
+            Self::Xl => vec![
+                fused(1, 1, 32, 4),
+                fused(4, 2, 64, 8),
+                fused(4, 2, 96, 8),
+                mbconv(4, 2, 192, 16),
+                mbconv(6, 1, 256, 24),
+                mbconv(6, 2, 512, 32),
+                mbconv(6, 1, 640, 8),
+            ],
+        }
+    }
+
+    /// Dropout probability of the classifier.
+    fn dropout(&self) -> f64 {
+        match self {
+            Self::S => 0.2,
+            Self::M => 0.3,
+            Self::L | Self::Xl => 0.4,
+        }
+    }
+}
+
+/// Fused MBConv block: a regular `3x3` expansion convolution replaces the `1x1` expansion and
+/// depthwise convolutions of the [MBConv](MbConv) block.
+///
+/// Conv2d -> BatchNorm -> SiLU -> Conv2d (`1x1` projection) -> BatchNorm, with a residual
+/// connection when the input and output shapes match. Without expansion, the projection is
+/// skipped and the `3x3` convolution directly outputs the block channels.
+#[derive(Module, Debug)]
+pub struct FusedMbConv<B: Backend> {
+    expand: ConvNorm<B>,
+    project: Option<ConvNorm<B>>,
+    shortcut: bool,
+}
+
+impl<B: Backend> FusedMbConv<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let mut out = self.expand.forward(x.clone());
+        if let Some(project) = &self.project {
+            out = project.forward(out);
+        }
+
+        if self.shortcut {
+            out + x
+        } else {
+            out
+        }
+    }
+}
+
+/// [Fused MBConv block](FusedMbConv) configuration.
+pub struct FusedMbConvConfig {
+    in_channels: usize,
+    out_channels: usize,
+    expand_ratio: usize,
+    stride: usize,
+}
+
+impl FusedMbConvConfig {
+    /// Create a new instance of the fused MBConv block [config](FusedMbConvConfig).
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        expand_ratio: usize,
+        stride: usize,
+    ) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+            expand_ratio,
+            stride,
+        }
+    }
+
+    /// Initialize a new [fused MBConv block](FusedMbConv) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> FusedMbConv<B> {
+        let silu = Some(ActivationType::Silu);
+        let shortcut = self.stride == 1 && self.in_channels == self.out_channels;
+
+        let (expand, project) = if self.expand_ratio == 1 {
+            let expand =
+                ConvNormConfig::new(self.in_channels, self.out_channels, 3, self.stride, 1, silu);
+            (expand, None)
+        } else {
+            let hidden_channels = self.in_channels * self.expand_ratio;
+            let expand =
+                ConvNormConfig::new(self.in_channels, hidden_channels, 3, self.stride, 1, silu);
+            let project = ConvNormConfig::new(hidden_channels, self.out_channels, 1, 1, 1, None);
+            (expand, Some(project))
+        };
+
+        FusedMbConv {
+            expand: expand.init(device),
+            project: project.map(|project| project.init(device)),
+            shortcut,
+        }
+    }
+}
+
+/// [MBConv](https://arxiv.org/abs/1801.04381) block: `1x1` expansion -> `3x3` depthwise
+/// convolution -> squeeze-and-excitation -> `1x1` linear projection, with a residual connection
+/// when the input and output shapes match.
+#[derive(Module, Debug)]
+pub struct MbConv<B: Backend> {
+    expand: ConvNorm<B>,
+    dwconv: ConvNorm<B>,
+    se: SeBlock<B>,
+    project: ConvNorm<B>,
+    shortcut: bool,
+}
+
+impl<B: Backend> MbConv<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let out = self.expand.forward(x.clone());
+        let out = self.dwconv.forward(out);
+        let out = self.se.forward(out);
+        let out = self.project.forward(out);
+
+        if self.shortcut {
+            out + x
+        } else {
+            out
+        }
+    }
+}
+
+/// [MBConv block](MbConv) configuration.
+pub struct MbConvConfig {
+    in_channels: usize,
+    out_channels: usize,
+    expand_ratio: usize,
+    stride: usize,
+}
+
+impl MbConvConfig {
+    /// Create a new instance of the MBConv block [config](MbConvConfig).
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        expand_ratio: usize,
+        stride: usize,
+    ) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+            expand_ratio,
+            stride,
+        }
+    }
+
+    /// Initialize a new [MBConv block](MbConv) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> MbConv<B> {
+        let silu = Some(ActivationType::Silu);
+        let hidden_channels = self.in_channels * self.expand_ratio;
+
+        MbConv {
+            expand: ConvNormConfig::new(self.in_channels, hidden_channels, 1, 1, 1, silu)
+                .init(device),
+            dwconv: ConvNormConfig::new(
+                hidden_channels,
+                hidden_channels,
+                3,
+                self.stride,
+                hidden_channels,
+                silu,
+            )
+            .init(device),
+            // The gating channels are relative to the block input channels
+            se: SeBlockConfig::new(hidden_channels, SE_RATIO * self.expand_ratio).init(device),
+            project: ConvNormConfig::new(hidden_channels, self.out_channels, 1, 1, 1, None)
+                .init(device),
+            shortcut: self.stride == 1 && self.in_channels == self.out_channels,
+        }
+    }
+}
+
+/// Block of an [EfficientNetV2](EfficientNetV2) stage.
+#[derive(Module, Debug)]
+pub enum EfficientNetV2Block<B: Backend> {
+    Fused(FusedMbConv<B>),
+    MbConv(MbConv<B>),
+}
+
+impl<B: Backend> EfficientNetV2Block<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        match self {
+            Self::Fused(block) => block.forward(x),
+            Self::MbConv(block) => block.forward(x),
+        }
+    }
+}
+
+/// [EfficientNetV2](https://arxiv.org/abs/2104.00298) image classification model and backbone.
+///
+/// The early stages use [fused MBConv](FusedMbConv) blocks, which are faster to train on
+/// accelerators, and the later stages use [MBConv](MbConv) blocks with squeeze-and-excitation.
+#[derive(Module, Debug)]
+pub struct EfficientNetV2<B: Backend> {
+    stem: ConvNorm<B>,
+    stages: Vec<Vec<EfficientNetV2Block<B>>>,
+    /// Whether the output of each stage is a returned feature map.
+    feature_stages: Vec<bool>,
+    head_conv: Option<ConvNorm<B>>,
+    classifier: Option<ClassificationHead<B>>,
+}
+
+impl<B: Backend> EfficientNetV2<B> {
+    /// Compute the class logits.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, num_classes]`
+    ///
+    /// # Panics
+    ///
+    /// If the model was initialized
+    /// [for feature extraction](EfficientNetV2Config::for_feature_extraction).
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 2> {
+        let (head_conv, classifier) = match (&self.head_conv, &self.classifier) {
+            (Some(head_conv), Some(classifier)) => (head_conv, classifier),
+            _ => panic!("the EfficientNetV2 feature extractor has no classifier head"),
+        };
+
+        let x = self.stem.forward(x);
+        let x = self
+            .stages
+            .iter()
+            .flatten()
+            .fold(x, |x, block| block.forward(x));
+
+        classifier.forward(head_conv.forward(x))
+    }
+
+    /// Compute the feature maps with strides 2, 4, 8, 16 and 32, i.e. the output of the last stage
+    /// of each resolution.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, channels[i], H / 2^(i+1), W / 2^(i+1)]` for each level `i`
+    pub fn forward_features(&self, x: Tensor<B, 4>) -> Vec<Tensor<B, 4>> {
+        let mut x = self.stem.forward(x);
+        let mut features = Vec::with_capacity(5);
+
+        for (stage, &is_feature) in self.stages.iter().zip(self.feature_stages.iter()) {
+            x = stage.iter().fold(x, |x, block| block.forward(x));
+            if is_feature {
+                features.push(x.clone());
+            }
+        }
+
+        features
+    }
+}
+
+/// [EfficientNetV2](EfficientNetV2) configuration.
+pub struct EfficientNetV2Config {
+    variant: EfficientNetV2Variant,
+    num_classes: Option<usize>,
+    dropout: f64,
+}
+
+impl EfficientNetV2Config {
+    /// Create a new instance of the EfficientNetV2 [config](EfficientNetV2Config) for ImageNet
+    /// classification.
+    pub fn new(variant: EfficientNetV2Variant) -> Self {
+        Self {
+            variant,
+            num_classes: Some(NUM_CLASSES),
+            dropout: variant.dropout(),
+        }
+    }
+
+    /// Set the number of classes of the classifier (defaults to 1000).
+    pub fn with_num_classes(mut self, num_classes: usize) -> Self {
+        self.num_classes = Some(num_classes);
+        self
+    }
+
+    /// Set the dropout probability of the classifier (defaults to the variant dropout).
+    pub fn with_dropout(mut self, dropout: f64) -> Self {
+        self.dropout = dropout;
+        self
+    }
+
+    /// Remove the classifier head, to use the model as a backbone with
+    /// [forward_features](EfficientNetV2::forward_features).
+    pub fn for_feature_extraction(mut self) -> Self {
+        self.num_classes = None;
+        self
+    }
+
+    /// Number of channels of each feature map returned by
+    /// [forward_features](EfficientNetV2::forward_features).
+    pub fn feature_channels(&self) -> Vec<usize> {
+        let stages = self.variant.stages();
+        Self::feature_stages(&stages)
+            .into_iter()
+            .zip(stages.iter())
+            .filter(|(is_feature, _)| *is_feature)
+            .map(|(_, stage)| stage.out_channels)
+            .collect()
+    }
+
+    /// Whether each stage is the last one of its resolution.
+    fn feature_stages(stages: &[StageSettings]) -> Vec<bool> {
+        (0..stages.len())
+            .map(|i| i + 1 == stages.len() || stages[i + 1].stride > 1)
+            .collect()
+    }
+
+    /// Initialize a new [EfficientNetV2](EfficientNetV2) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> EfficientNetV2<B> {
+        let silu = Some(ActivationType::Silu);
+        let settings = self.variant.stages();
+        let stem_channels = self.variant.stem_channels();
+
+        let stem = ConvNormConfig::new(3, stem_channels, 3, 2, 1, silu).init(device);
+
+        let mut in_channels = stem_channels;
+        let stages = settings
+            .iter()
+            .map(|stage| {
+                (0..stage.num_blocks)
+                    .map(|i| {
+                        // Only the first block of each stage changes the resolution and channels
+                        let stride = if i == 0 { stage.stride } else { 1 };
+                        let block = if stage.fused {
+                            EfficientNetV2Block::Fused(
+                                FusedMbConvConfig::new(
+                                    in_channels,
+                                    stage.out_channels,
+                                    stage.expand_ratio,
+                                    stride,
+                                )
+                                .init(device),
+                            )
+                        } else {
+                            EfficientNetV2Block::MbConv(
+                                MbConvConfig::new(
+                                    in_channels,
+                                    stage.out_channels,
+                                    stage.expand_ratio,
+                                    stride,
+                                )
+                                .init(device),
+                            )
+                        };
+                        in_channels = stage.out_channels;
+
+                        block
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let (head_conv, classifier) = match self.num_classes {
+            Some(num_classes) => (
+                Some(ConvNormConfig::new(in_channels, HEAD_CHANNELS, 1, 1, 1, silu).init(device)),
+                Some(
+                    ClassificationHeadConfig::new(HEAD_CHANNELS, num_classes, self.dropout)
+                        .init(device),
+                ),
+            ),
+            None => (None, None),
+        };
+
+        EfficientNetV2 {
+            stem,
+            stages,
+            feature_stages: Self::feature_stages(&settings),
+            head_conv,
+            classifier,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn efficientnet_v2_s_num_params() {
+        let device = Default::default();
+        let model =
+            EfficientNetV2Config::new(EfficientNetV2Variant::S).init::<TestBackend>(&device);
+
+        // Same as the reference implementation (21.5M)
+        assert_eq!(model.num_params(), 21_458_488);
+    }
+
+    #[test]
+    fn efficientnet_v2_early_stages_fused() {
+        let device = Default::default();
+        let model = EfficientNetV2Config::new(EfficientNetV2Variant::S)
+            .for_feature_extraction()
+            .init::<TestBackend>(&device);
+
+        for (i, stage) in model.stages.iter().enumerate() {
+            for block in stage {
+                assert_eq!(matches!(block, EfficientNetV2Block::Fused(_)), i < 3);
+            }
+        }
+
+        // Dense 3x3 expansion and 1x1 projection, without depthwise convolution
+        let EfficientNetV2Block::Fused(block) = &model.stages[1][0] else {
+            unreachable!()
+        };
+        let expand = 24 * 96 * 3 * 3 + 2 * 96;
+        let project = 96 * 48 + 2 * 48;
+        assert_eq!(block.num_params(), expand + project);
+
+        // Expansion, depthwise convolution, squeeze-and-excitation and projection
+        let EfficientNetV2Block::MbConv(block) = &model.stages[3][0] else {
+            unreachable!()
+        };
+        let expand = 64 * 256 + 2 * 256;
+        let dwconv = 256 * 3 * 3 + 2 * 256;
+        let se = 256 * 16 + 16 + 16 * 256 + 256;
+        let project = 256 * 128 + 2 * 128;
+        assert_eq!(block.num_params(), expand + dwconv + se + project);
+    }
+
+    #[test]
+    fn efficientnet_v2_feature_maps() {
+        let device = Default::default();
+        let config = EfficientNetV2Config::new(EfficientNetV2Variant::S).for_feature_extraction();
+        let model = config.init::<TestBackend>(&device);
+        let x = Tensor::random([1, 3, 64, 64], Distribution::Default, &device);
+
+        let features = model.forward_features(x);
+
+        assert_eq!(features.len(), 5);
+        assert_eq!(config.feature_channels(), [24, 48, 64, 160, 256]);
+        for (i, (feature, channels)) in features.iter().zip(config.feature_channels()).enumerate() {
+            let size = 64 >> (i + 1);
+            assert_eq!(feature.dims(), [1, channels, size, size]);
+        }
+    }
+
+    #[test]
+    fn efficientnet_v2_classification() {
+        let device = Default::default();
+        let model = EfficientNetV2Config::new(EfficientNetV2Variant::S)
+            .with_num_classes(10)
+            .init::<TestBackend>(&device);
+        let x = Tensor::random([2, 3, 32, 32], Distribution::Default, &device);
+
+        assert_eq!(model.forward(x).dims(), [2, 10]);
+    }
+
+    #[test]
+    #[should_panic = "the EfficientNetV2 feature extractor has no classifier head"]
+    fn efficientnet_v2_feature_extractor_without_head() {
+        let device = Default::default();
+        let model = EfficientNetV2Config::new(EfficientNetV2Variant::S)
+            .for_feature_extraction()
+            .init::<TestBackend>(&device);
+
+        model.forward(Tensor::zeros([1, 3, 32, 32], &device));
+    }
+}
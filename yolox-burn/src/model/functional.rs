@@ -0,0 +1,115 @@
+//! Functional forms of the activations that are not provided by burn.
+use burn::tensor::{
+    activation::{relu, tanh},
+    backend::Backend,
+    Tensor,
+};
+
+/// ReLU6 activation `min(max(x, 0), 6)`, as used by MobileNetV2.
+pub fn relu6<B: Backend, const D: usize>(x: Tensor<B, D>) -> Tensor<B, D> {
+    x.clamp(0., 6.)
+}
+
+/// Hard sigmoid `relu6(x + 3) / 6`, a piecewise linear approximation of the sigmoid used by
+/// [MobileNetV3](https://arxiv.org/abs/1905.02244).
+pub fn hard_sigmoid<B: Backend, const D: usize>(x: Tensor<B, D>) -> Tensor<B, D> {
+    relu6(x.add_scalar(3.)).div_scalar(6.)
+}
+
+/// Hard swish `x * relu6(x + 3) / 6`, a piecewise approximation of [SiLU](super::blocks::Silu)
+/// used by [MobileNetV3](https://arxiv.org/abs/1905.02244).
+pub fn hard_swish<B: Backend, const D: usize>(x: Tensor<B, D>) -> Tensor<B, D> {
+    x.clone() * hard_sigmoid(x)
+}
+
+/// Numerically stable softplus `ln(1 + exp(x))`, computed as `max(x, 0) + ln(1 + exp(-|x|))` so
+/// that neither the value nor the gradient overflows for large inputs.
+pub fn softplus<B: Backend, const D: usize>(x: Tensor<B, D>) -> Tensor<B, D> {
+    relu(x.clone()) + x.abs().neg().exp().log1p()
+}
+
+/// [Mish](https://arxiv.org/abs/1908.08681) activation `x * tanh(softplus(x))`.
+///
+/// For large positive inputs, `tanh` saturates at 1 and the activation is the identity.
+pub fn mish<B: Backend, const D: usize>(x: Tensor<B, D>) -> Tensor<B, D> {
+    x.clone() * tanh(softplus(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        tensor::{activation::silu, Int, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn mish_values() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 1>::from_floats([0., 1., -1., 30.], &device);
+
+        mish(x)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([0., 0.8651, -0.3034, 30.]), 3);
+    }
+
+    #[test]
+    fn mish_finite_gradient() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let x = Tensor::<TrainingBackend, 1, Int>::arange(-100..101, &device)
+            .float()
+            .require_grad();
+
+        let grads = mish(x.clone()).sum().backward();
+        let grad = x.grad(&grads).unwrap().into_data().to_vec::<f32>().unwrap();
+
+        assert_eq!(grad.len(), 201);
+        assert!(grad.iter().all(|g| g.is_finite()));
+        // Identity for large positive inputs, constant for large negative inputs
+        assert!((grad[200] - 1.).abs() < 1e-5);
+        assert!(grad[0].abs() < 1e-5);
+    }
+
+    #[test]
+    fn hard_activations_values() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 1>::from_floats([-3., 0., 3.], &device);
+
+        // -3 * 0 is -0, which is compared approximately
+        hard_swish(x.clone())
+            .into_data()
+            .assert_approx_eq(&TensorData::from([0.0f32, 0., 3.]), 6);
+        hard_sigmoid(x)
+            .into_data()
+            .assert_eq(&TensorData::from([0.0f32, 0.5, 1.]), false);
+    }
+
+    #[test]
+    fn relu6_values() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 1>::from_floats([-1., 2.5, 8.], &device);
+
+        relu6(x)
+            .into_data()
+            .assert_eq(&TensorData::from([0.0f32, 2.5, 6.]), false);
+    }
+
+    #[test]
+    fn hard_swish_approximates_silu() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 1, Int>::arange(-300..301, &device)
+            .float()
+            .div_scalar(100.);
+
+        let expected = silu(x.clone());
+        let range = expected.clone().max() - expected.clone().min();
+        let error = (hard_swish(x) - expected).abs().max() / range;
+
+        // Less than 5% of the range of SiLU on [-3, 3]
+        let error = error.into_scalar();
+        assert!(error < 0.05, "error {error}");
+    }
+}
@@ -5,49 +5,75 @@ use burn::{
         conv::{Conv2d, Conv2dConfig},
         Initializer, PaddingConfig2d,
     },
-    tensor::{activation::sigmoid, backend::Backend, Device, Int, Shape, Tensor},
+    tensor::{
+        activation::{sigmoid, tanh},
+        backend::Backend,
+        Device, Tensor,
+    },
 };
 use itertools::{izip, multiunzip};
 
+use crate::loss::{GroundTruthBoxes, YoloxLoss, YoloxLossOutput};
+
 use super::{
     blocks::{expand, BaseConv, BaseConvConfig, ConvBlock, ConvBlockConfig},
-    pafpn::FpnFeatures,
+    decode_grid::{make_anchor_grid, CachedAnchorGrid},
+    neck::FpnFeatures,
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
 };
 
 const STRIDES: [usize; 3] = [8, 16, 32];
 const IN_CHANNELS: [usize; 3] = [256, 512, 1024];
 const PRIOR_PROB: f64 = 1e-2;
 
-/// Create a 2D coordinate grid for the specified dimensions.
-/// Similar to [`numpy.indices`](https://numpy.org/doc/stable/reference/generated/numpy.indices.html)
-/// but specific to two dimensions.
-fn create_2d_grid<B: Backend>(x: usize, y: usize, device: &Device<B>) -> Tensor<B, 3, Int> {
-    let y_idx = Tensor::arange(0..y as i64, device)
-        .reshape(Shape::new([y, 1]))
-        .repeat_dim(1, x)
-        .reshape(Shape::new([y, x]));
-    let x_idx = Tensor::arange(0..x as i64, device)
-        .reshape(Shape::new([1, x])) // can only repeat with dim=1
-        .repeat_dim(0, y)
-        .reshape(Shape::new([y, x]));
-
-    Tensor::stack(vec![x_idx, y_idx], 2)
+/// Decoding mode of the [detection head](DetectionHead) box predictions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DetectionHeadMode {
+    /// Anchor-free decoding (YOLOX): the box center is an offset from the grid cell and the box
+    /// size is `log` encoded, both in units of the stride.
+    #[default]
+    AnchorFree,
+    /// Anchor-based decoding (YOLOv5): the box center and size are predicted relative to the
+    /// grid cell and the anchor box sizes.
+    ///
+    /// The anchor box `(width, height)` sizes are given in pixels for each level, from the
+    /// highest to the lowest resolution, i.e. `num_levels * num_anchors` sizes.
+    AnchorBased(Vec<(f32, f32)>),
 }
 
-/// YOLOX head.
+/// Decoupled detection head with classification, objectness and regression branches.
+///
+/// With a single anchor per location, this is the anchor-free YOLOX head. The regression branch
+/// can also predict [mask coefficients](DetectionHeadConfig::with_mask_dim) for instance
+/// segmentation.
 #[derive(Module, Debug)]
-pub struct Head<B: Backend> {
+pub struct DetectionHead<B: Backend> {
     stems: Vec<BaseConv<B>>,
     cls_convs: Vec<ConvBlock<B>>,
     reg_convs: Vec<ConvBlock<B>>,
     cls_preds: Vec<Conv2d<B>>,
     reg_preds: Vec<Conv2d<B>>,
     obj_preds: Vec<Conv2d<B>>,
+    /// Mask coefficients predictions, empty without instance segmentation.
+    mask_preds: Vec<Conv2d<B>>,
+    num_anchors: usize,
+    /// Anchor box sizes for anchor-based decoding. Shape: `[num_levels, num_anchors, 2]`.
+    anchors: Option<Tensor<B, 3>>,
+    /// Anchor grid precomputed for a fixed input resolution, if any.
+    grid: Option<CachedAnchorGrid<B>>,
 }
 
-impl<B: Backend> Head<B> {
+impl<B: Backend> DetectionHead<B> {
+    /// Compute the decoded predictions for each location and anchor of the feature maps.
+    ///
+    /// The `mask_dim` mask coefficients (if any) follow the class probabilities, in range
+    /// `(-1, 1)`.
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, num_locations * num_anchors, 5 + num_classes + mask_dim]`
     pub fn forward(&self, x: FpnFeatures<B>) -> Tensor<B, 3> {
         let features: [Tensor<B, 4>; 3] = [x.0, x.1, x.2];
+        let num_anchors = self.num_anchors;
 
         // Outputs for each feature map
         let (outputs, shapes): (Vec<Tensor<B, 3>>, Vec<(usize, usize)>) = izip!(
@@ -58,10 +84,10 @@ impl<B: Backend> Head<B> {
             &self.reg_convs,
             &self.reg_preds,
             &self.obj_preds,
-            &STRIDES
+            0..STRIDES.len()
         )
         .map(
-            |(feat, stem, cls_conv, cls_pred, reg_conv, reg_pred, obj_pred, _stride)| {
+            |(feat, stem, cls_conv, cls_pred, reg_conv, reg_pred, obj_pred, level)| {
                 let feat = stem.forward(feat);
 
                 let cls_feat = cls_conv.forward(feat.clone());
@@ -70,20 +96,110 @@ impl<B: Backend> Head<B> {
                 let reg_feat = reg_conv.forward(feat);
                 let reg_out = reg_pred.forward(reg_feat.clone());
 
-                let obj_out = obj_pred.forward(reg_feat);
+                let obj_out = obj_pred.forward(reg_feat.clone());
 
-                // Output [B, 5 + num_classes, num_anchors]
-                let out = Tensor::cat(vec![reg_out, sigmoid(obj_out), sigmoid(cls_out)], 1);
-                let [_, _, h, w] = out.dims();
-                (out.flatten(2, 3), (h, w))
+                // Split the predictions of each anchor [B, num_anchors, K, H * W]
+                let [b, _, h, w] = reg_out.dims();
+                let split = |x: Tensor<B, 4>| {
+                    let [_, c, _, _] = x.dims();
+                    x.reshape([b, num_anchors, c / num_anchors, h * w])
+                };
+
+                // Output [B, H * W * num_anchors, 5 + num_classes + mask_dim]
+                let mut out = vec![
+                    split(reg_out),
+                    split(sigmoid(obj_out)),
+                    split(sigmoid(cls_out)),
+                ];
+                if let Some(mask_pred) = self.mask_preds.get(level) {
+                    out.push(split(tanh(mask_pred.forward(reg_feat))));
+                }
+                let out = Tensor::cat(out, 2);
+                let [_, _, num_outputs, _] = out.dims();
+                let out = out
+                    .permute([0, 3, 1, 2])
+                    .reshape([b, h * w * num_anchors, num_outputs]);
+                (out, (h, w))
             },
         )
         .unzip();
 
-        // 1. Concat all regression outputs
-        // 2. Permute shape to [B, num_anchors_total, 5 + num_classes]
-        // 3. Decode absolute bounding box values
-        self.decode(Tensor::cat(outputs, 2).swap_dims(2, 1), shapes.as_ref())
+        // 1. Concat all regression outputs [B, num_anchors_total, 5 + num_classes]
+        // 2. Decode absolute bounding box values
+        self.decode(Tensor::cat(outputs, 1), shapes.as_ref())
+    }
+
+    /// Compute the [YOLOX training loss](YoloxLoss) of the predictions, with the anchor points
+    /// of the feature maps.
+    ///
+    /// Only the anchor-free head is supported, and the mask coefficients are not trained.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Feature maps with strides 8, 16 and 32.
+    /// * `targets` - Ground truth boxes of each image.
+    /// * `loss` - Loss and label assignment settings.
+    pub fn forward_training(
+        &self,
+        x: FpnFeatures<B>,
+        targets: &[GroundTruthBoxes<B>],
+        loss: &YoloxLoss,
+    ) -> YoloxLossOutput<B> {
+        assert!(
+            self.anchors.is_none() && self.num_anchors == 1,
+            "training is only supported for the anchor-free head"
+        );
+        let device = x.0.device();
+        let shapes: Vec<(usize, usize)> = [&x.0, &x.1, &x.2]
+            .iter()
+            .map(|feat| {
+                let [_, _, h, w] = feat.dims();
+                (h, w)
+            })
+            .collect();
+
+        // Anchor points and strides [num_anchors, 3]
+        let (points, strides) = self.anchor_grid(&shapes, &device);
+        let [num_anchors, _] = points.dims();
+        let anchors = Tensor::cat(vec![points, strides.reshape([num_anchors, 1])], 1);
+
+        let outputs = self.forward(x);
+        let [b, n, _] = outputs.dims();
+        let outputs = outputs.slice([0..b, 0..n, 0..5 + self.num_classes()]);
+
+        loss.forward(outputs, anchors, targets)
+    }
+
+    /// Number of predicted classes.
+    pub fn num_classes(&self) -> usize {
+        let [num_outputs, _, _, _] = self.cls_preds[0].weight.dims();
+        num_outputs / self.num_anchors
+    }
+
+    /// Number of mask coefficients of each prediction (zero without instance segmentation).
+    pub fn mask_dim(&self) -> usize {
+        self.mask_preds.first().map_or(0, |pred| {
+            let [num_outputs, _, _, _] = pred.weight.dims();
+            num_outputs / self.num_anchors
+        })
+    }
+
+    /// Number of predictions per location.
+    pub fn num_anchors(&self) -> usize {
+        self.num_anchors
+    }
+
+    /// Anchor points and strides of the feature maps, from the [cached grid](CachedAnchorGrid)
+    /// when the feature map sizes match.
+    fn anchor_grid(
+        &self,
+        shapes: &[(usize, usize)],
+        device: &Device<B>,
+    ) -> (Tensor<B, 2>, Tensor<B, 1>) {
+        match &self.grid {
+            Some(grid) => grid.get(shapes, device),
+            None => make_anchor_grid(shapes, &STRIDES, device),
+        }
     }
 
     /// Decode bounding box absolute values from regression output offsets.
@@ -91,30 +207,57 @@ impl<B: Backend> Head<B> {
         let device = outputs.device();
         let [b, num_anchors, num_outputs] = outputs.dims();
 
-        let (grids, strides) = shapes
-            .iter()
-            .zip(STRIDES)
-            .map(|((h, w), stride)| {
-                // Grid (x, y) coordinates
-                let num_anchors = w * h;
-                let grid =
-                    create_2d_grid::<B>(*w, *h, &device).reshape(Shape::new([1, num_anchors, 2]));
-                let strides: Tensor<B, 3, Int> =
-                    Tensor::full(Shape::new([1, num_anchors, 1]), stride as i64, &device);
-
-                (grid, strides)
-            })
-            .unzip();
+        // Anchor points and strides, repeated for each anchor of a location
+        let (points, strides) = self.anchor_grid(shapes, &device);
+        let [num_locations, _] = points.dims();
+        let points = points
+            .reshape([num_locations, 1, 2])
+            .repeat_dim(1, self.num_anchors)
+            .reshape([1, num_anchors, 2]);
+        let strides = strides
+            .reshape([num_locations, 1, 1])
+            .repeat_dim(1, self.num_anchors)
+            .reshape([1, num_anchors, 1]);
+
+        let xy = outputs.clone().slice([0..b, 0..num_anchors, 0..2]);
+        let wh = outputs.clone().slice([0..b, 0..num_anchors, 2..4]);
+
+        let (xy, wh) = match &self.anchors {
+            None => (
+                // Offset from the grid cell corner (i.e., the center minus half a stride)
+                xy.sub_scalar(0.5) * strides.clone() + points,
+                // Decode `log` encoded boxes with `exp`and scale to image dimensions
+                wh.exp() * strides,
+            ),
+            Some(anchors) => {
+                // Anchor sizes for each location [1, num_anchors_total, 2]
+                let anchors = shapes
+                    .iter()
+                    .enumerate()
+                    .map(|(level, (h, w))| {
+                        let [_, n, _] = anchors.dims();
+                        anchors
+                            .clone()
+                            .slice([level..level + 1, 0..n, 0..2])
+                            .repeat_dim(0, h * w)
+                            .reshape([1, h * w * n, 2])
+                    })
+                    .collect();
+                let anchors = Tensor::cat(anchors, 1);
 
-        let grids = Tensor::cat(grids, 1).float();
-        let strides = Tensor::cat(strides, 1).float();
+                (
+                    // Center offset in range (-0.5, 1.5) from the grid cell
+                    (sigmoid(xy) * 2. - 1.) * strides + points,
+                    // Box size in range (0, 4) times the anchor size
+                    sigmoid(wh).mul_scalar(2.).powf_scalar(2.) * anchors,
+                )
+            }
+        };
 
         Tensor::cat(
             vec![
-                // Add grid offset to center coordinates and scale to image dimensions
-                (outputs.clone().slice([0..b, 0..num_anchors, 0..2]) + grids) * strides.clone(),
-                // Decode `log` encoded boxes with `exp`and scale to image dimensions
-                outputs.clone().slice([0..b, 0..num_anchors, 2..4]).exp() * strides,
+                xy,
+                wh,
                 // Classification outputs
                 outputs.slice([0..b, 0..num_anchors, 4..num_outputs]),
             ],
@@ -123,70 +266,375 @@ impl<B: Backend> Head<B> {
     }
 }
 
-/// [YOLOX head](Head) configuration.
-pub struct HeadConfig {
-    stems: Vec<BaseConvConfig>,
-    cls_convs: Vec<ConvBlockConfig>,
-    reg_convs: Vec<ConvBlockConfig>,
-    cls_preds: Vec<Conv2dConfig>,
-    reg_preds: Vec<Conv2dConfig>,
-    obj_preds: Vec<Conv2dConfig>,
+impl<B: Backend> FreezeBatchNorms<B> for DetectionHead<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            stems: self.stems.freeze_batch_norms(),
+            cls_convs: self.cls_convs.freeze_batch_norms(),
+            reg_convs: self.reg_convs.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for DetectionHead<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            stems: self.stems.sync_batch_norms(),
+            cls_convs: self.cls_convs.sync_batch_norms(),
+            reg_convs: self.reg_convs.sync_batch_norms(),
+            ..self
+        }
+    }
 }
 
-impl HeadConfig {
-    /// Create a new instance of the YOLOX head [config](HeadConfig).
-    pub fn new(num_classes: usize, width: f64, depthwise: bool) -> Self {
-        let hidden_channels: usize = 256;
+impl<B: Backend> SetBatchNormMomentum<B> for DetectionHead<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.stems.set_bn_momentum(momentum);
+        self.cls_convs.set_bn_momentum(momentum);
+        self.reg_convs.set_bn_momentum(momentum);
+    }
+}
+
+/// [Detection head](DetectionHead) configuration.
+pub struct DetectionHeadConfig {
+    in_channels: Vec<usize>,
+    hidden_channels: usize,
+    num_classes: usize,
+    num_anchors: usize,
+    mask_dim: usize,
+    depthwise: bool,
+    mode: DetectionHeadMode,
+    input_size: Option<[usize; 2]>,
+}
+
+impl DetectionHeadConfig {
+    /// Create a new instance of the detection head [config](DetectionHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the feature maps with strides 8, 16 and 32.
+    /// * `num_classes` - Number of output classes of the model.
+    /// * `num_anchors` - Number of predictions per location. A single anchor corresponds to the
+    ///   anchor-free YOLOX head.
+    pub fn new(in_channels: Vec<usize>, num_classes: usize, num_anchors: usize) -> Self {
+        assert_eq!(
+            in_channels.len(),
+            STRIDES.len(),
+            "the detection head expects {} feature maps",
+            STRIDES.len()
+        );
+        assert!(num_anchors > 0, "the number of anchors should be positive");
+
+        Self {
+            hidden_channels: in_channels[0],
+            in_channels,
+            num_classes,
+            num_anchors,
+            mask_dim: 0,
+            depthwise: false,
+            mode: DetectionHeadMode::AnchorFree,
+            input_size: None,
+        }
+    }
+
+    /// Create a new instance of the YOLOX head [config](DetectionHeadConfig).
+    pub fn yolox(num_classes: usize, width: f64, depthwise: bool) -> Self {
+        let in_channels = IN_CHANNELS.iter().map(|&c| expand(c, width)).collect();
+
+        Self::new(in_channels, num_classes, 1).with_depthwise(depthwise)
+    }
+
+    /// Set the number of output classes of the model.
+    pub fn with_num_classes(mut self, num_classes: usize) -> Self {
+        self.num_classes = num_classes;
+        self
+    }
+
+    /// Set the number of channels of the prediction branches (defaults to the number of
+    /// channels of the first feature map).
+    pub fn with_hidden_channels(mut self, hidden_channels: usize) -> Self {
+        self.hidden_channels = hidden_channels;
+        self
+    }
+
+    /// Predict `mask_dim` mask coefficients for each prediction from the regression features,
+    /// as in [YOLACT](https://arxiv.org/abs/1904.02689) (defaults to 0, i.e. no coefficients).
+    pub fn with_mask_dim(mut self, mask_dim: usize) -> Self {
+        self.mask_dim = mask_dim;
+        self
+    }
+
+    /// Use depthwise separable convolutions in the prediction branches.
+    pub fn with_depthwise(mut self, depthwise: bool) -> Self {
+        self.depthwise = depthwise;
+        self
+    }
+
+    /// Set the box decoding mode (defaults to [anchor-free](DetectionHeadMode::AnchorFree)).
+    pub fn with_mode(mut self, mode: DetectionHeadMode) -> Self {
+        if let DetectionHeadMode::AnchorBased(anchors) = &mode {
+            assert_eq!(
+                anchors.len(),
+                STRIDES.len() * self.num_anchors,
+                "expected {} anchor sizes per level",
+                self.num_anchors
+            );
+        }
+        self.mode = mode;
+        self
+    }
+
+    /// Precompute the anchor grid of `[height, width]` input images, which is reused by the
+    /// forward passes at this resolution instead of being recomputed (defaults to none, i.e.
+    /// the grid is computed at each forward pass). The height and width should be multiples of
+    /// 32, other resolutions are still supported but do not use the cached grid.
+    pub fn with_input_size(mut self, input_size: Option<[usize; 2]>) -> Self {
+        self.input_size = input_size;
+        self
+    }
+
+    /// Initialize a new [detection head](DetectionHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DetectionHead<B> {
+        let hidden_channels = self.hidden_channels;
+        let num_anchors = self.num_anchors;
         // Initialize conv2d biases for classification and objectness heads
         let bias = -f64::ln((1.0 - PRIOR_PROB) / PRIOR_PROB);
 
-        let (stems, cls_convs, reg_convs, cls_preds, reg_preds, obj_preds) =
-            multiunzip(IN_CHANNELS.into_iter().map(|in_channels| {
-                let stem = BaseConvConfig::new(
-                    expand(in_channels, width),
-                    expand(hidden_channels, width),
-                    1,
-                    1,
-                    1,
-                );
-
-                let cls_conv =
-                    ConvBlockConfig::new(expand(hidden_channels, width), 3, 1, depthwise);
-                let reg_conv =
-                    ConvBlockConfig::new(expand(hidden_channels, width), 3, 1, depthwise);
-
-                let cls_pred =
-                    Conv2dConfig::new([expand(hidden_channels, width), num_classes], [1, 1])
-                        .with_padding(PaddingConfig2d::Explicit(0, 0))
-                        .with_initializer(Initializer::Constant { value: bias });
-                let reg_pred = Conv2dConfig::new([expand(hidden_channels, width), 4], [1, 1])
-                    .with_padding(PaddingConfig2d::Explicit(0, 0));
-                let obj_pred = Conv2dConfig::new([expand(hidden_channels, width), 1], [1, 1])
+        let (stems, cls_convs, reg_convs, cls_preds, reg_preds, obj_preds): (
+            Vec<_>,
+            Vec<_>,
+            Vec<_>,
+            Vec<_>,
+            Vec<_>,
+            Vec<_>,
+        ) = multiunzip(self.in_channels.iter().map(|&in_channels| {
+            let stem = BaseConvConfig::new(in_channels, hidden_channels, 1, 1, 1);
+
+            let cls_conv = ConvBlockConfig::new(hidden_channels, 3, 1, self.depthwise);
+            let reg_conv = ConvBlockConfig::new(hidden_channels, 3, 1, self.depthwise);
+
+            let cls_pred =
+                Conv2dConfig::new([hidden_channels, num_anchors * self.num_classes], [1, 1])
                     .with_padding(PaddingConfig2d::Explicit(0, 0))
                     .with_initializer(Initializer::Constant { value: bias });
+            let reg_pred = Conv2dConfig::new([hidden_channels, num_anchors * 4], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0));
+            let obj_pred = Conv2dConfig::new([hidden_channels, num_anchors], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+                .with_initializer(Initializer::Constant { value: bias });
 
-                (stem, cls_conv, reg_conv, cls_pred, reg_pred, obj_pred)
-            }));
+            (
+                stem.init(device),
+                cls_conv.init(device),
+                reg_conv.init(device),
+                cls_pred.init(device),
+                reg_pred.init(device),
+                obj_pred.init(device),
+            )
+        }));
 
-        Self {
+        let mask_preds = match self.mask_dim {
+            0 => Vec::new(),
+            mask_dim => self
+                .in_channels
+                .iter()
+                .map(|_| {
+                    Conv2dConfig::new([hidden_channels, num_anchors * mask_dim], [1, 1])
+                        .with_padding(PaddingConfig2d::Explicit(0, 0))
+                        .init(device)
+                })
+                .collect(),
+        };
+
+        let anchors = match &self.mode {
+            DetectionHeadMode::AnchorFree => None,
+            DetectionHeadMode::AnchorBased(anchors) => {
+                let sizes: Vec<f32> = anchors.iter().flat_map(|&(w, h)| [w, h]).collect();
+                Some(
+                    Tensor::<B, 1>::from_floats(sizes.as_slice(), device).reshape([
+                        STRIDES.len(),
+                        num_anchors,
+                        2,
+                    ]),
+                )
+            }
+        };
+
+        DetectionHead {
             stems,
             cls_convs,
             reg_convs,
             cls_preds,
             reg_preds,
             obj_preds,
+            mask_preds,
+            num_anchors,
+            anchors,
+            grid: self
+                .input_size
+                .map(|size| CachedAnchorGrid::for_input_size(size, &STRIDES, device)),
         }
     }
+}
 
-    /// Initialize a new [YOLOX head](Head) module.
-    pub fn init<B: Backend>(&self, device: &Device<B>) -> Head<B> {
-        Head {
-            stems: self.stems.iter().map(|m| m.init(device)).collect(),
-            cls_convs: self.cls_convs.iter().map(|m| m.init(device)).collect(),
-            reg_convs: self.reg_convs.iter().map(|m| m.init(device)).collect(),
-            cls_preds: self.cls_preds.iter().map(|m| m.init(device)).collect(),
-            reg_preds: self.reg_preds.iter().map(|m| m.init(device)).collect(),
-            obj_preds: self.obj_preds.iter().map(|m| m.init(device)).collect(),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    /// Random feature maps of two 64x64 images, i.e. 84 locations.
+    fn features(channels: [usize; 3]) -> FpnFeatures<TestBackend> {
+        let device = Default::default();
+        let [x0, x1, x2] = [(channels[0], 8), (channels[1], 4), (channels[2], 2)]
+            .map(|(c, size)| Tensor::random([2, c, size, size], Distribution::Default, &device));
+
+        FpnFeatures(x0, x1, x2)
+    }
+
+    #[test]
+    fn single_anchor_matches_yolox_head() {
+        let device = Default::default();
+        let yolox = DetectionHeadConfig::yolox(80, 0.25, false).init::<TestBackend>(&device);
+        let head = DetectionHeadConfig::new(vec![64, 128, 256], 80, 1).init(&device);
+
+        let expected = yolox.forward(features([64, 128, 256]));
+        let out = head.forward(features([64, 128, 256]));
+
+        assert_eq!(expected.dims(), [2, 84, 85]);
+        assert_eq!(out.dims(), expected.dims());
+        assert_eq!(head.num_params(), yolox.num_params());
+        assert_eq!(head.num_anchors(), 1);
+    }
+
+    #[test]
+    fn multiple_anchors_output_shape() {
+        let anchors = (1..=9).map(|i| (i as f32 * 10., i as f32 * 5.)).collect();
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 3, 3)
+            .with_mode(DetectionHeadMode::AnchorBased(anchors))
+            .init::<TestBackend>(&Default::default());
+
+        let out = head.forward(features([16, 32, 64]));
+
+        assert_eq!(out.dims(), [2, 84 * 3, 5 + 3]);
+        assert_eq!(head.num_anchors(), 3);
+        assert_eq!(head.num_classes(), 3);
+    }
+
+    #[test]
+    fn multiple_anchors_with_mask_coefficients() {
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 3, 2)
+            .with_mask_dim(4)
+            .init::<TestBackend>(&Default::default());
+
+        let out = head.forward(features([16, 32, 64]));
+
+        assert_eq!(out.dims(), [2, 84 * 2, 5 + 3 + 4]);
+        assert_eq!(head.mask_dim(), 4);
+    }
+
+    #[test]
+    fn cached_anchor_grid_same_outputs() {
+        let device = Default::default();
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 3, 1).init::<TestBackend>(&device);
+        let cached = DetectionHead {
+            grid: Some(CachedAnchorGrid::for_input_size(
+                [64, 64],
+                &STRIDES,
+                &device,
+            )),
+            ..head.clone()
+        };
+        let x = features([16, 32, 64]);
+
+        assert!(head.grid.is_none());
+        assert!(cached
+            .grid
+            .as_ref()
+            .unwrap()
+            .matches(&[(8, 8), (4, 4), (2, 2)]));
+        let expected = head.forward(FpnFeatures(x.0.clone(), x.1.clone(), x.2.clone()));
+        cached
+            .forward(x)
+            .into_data()
+            .assert_eq(&expected.into_data(), true);
+    }
+
+    #[test]
+    fn cached_anchor_grid_from_config() {
+        let device = Default::default();
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 3, 1)
+            .with_input_size(Some([64, 64]))
+            .init::<TestBackend>(&device);
+
+        assert!(head.grid.unwrap().matches(&[(8, 8), (4, 4), (2, 2)]));
+    }
+
+    #[test]
+    fn anchor_free_decode() {
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 1, 1)
+            .init::<TestBackend>(&Default::default());
+        let outputs = Tensor::zeros([1, 4, 6], &Default::default());
+
+        // Zero offsets and log sizes: boxes at the grid cell corners with the stride as size
+        let boxes = head.decode(outputs, &[(1, 2), (1, 1), (1, 1)]);
+
+        let boxes = boxes.slice([0..1, 0..4, 0..4]).into_data();
+        boxes.assert_eq(
+            &TensorData::from([[
+                [0f32, 0., 8., 8.],
+                [8., 0., 8., 8.],
+                [0., 0., 16., 16.],
+                [0., 0., 32., 32.],
+            ]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn anchor_based_decode() {
+        let anchors = (1..=6).map(|i| (i as f32 * 10., i as f32 * 20.)).collect();
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 1, 2)
+            .with_mode(DetectionHeadMode::AnchorBased(anchors))
+            .init::<TestBackend>(&Default::default());
+        let outputs = Tensor::zeros([1, 6, 6], &Default::default());
+
+        // Zero outputs: boxes at the grid cell centers with the anchor sizes
+        let boxes = head.decode(outputs, &[(1, 1), (1, 1), (1, 1)]);
+
+        let boxes = boxes.slice([0..1, 0..6, 0..4]).into_data();
+        boxes.assert_eq(
+            &TensorData::from([[
+                [4f32, 4., 10., 20.],
+                [4., 4., 20., 40.],
+                [8., 8., 30., 60.],
+                [8., 8., 40., 80.],
+                [16., 16., 50., 100.],
+                [16., 16., 60., 120.],
+            ]]),
+            false,
+        );
+    }
+
+    #[test]
+    #[should_panic = "expected 2 anchor sizes per level"]
+    fn anchor_based_invalid_sizes() {
+        let anchors = vec![(10., 20.); 3];
+        DetectionHeadConfig::new(vec![16, 32, 64], 1, 2)
+            .with_mode(DetectionHeadMode::AnchorBased(anchors));
+    }
+
+    #[test]
+    #[should_panic = "training is only supported for the anchor-free head"]
+    fn multiple_anchors_training() {
+        let head = DetectionHeadConfig::new(vec![16, 32, 64], 1, 2)
+            .init::<TestBackend>(&Default::default());
+
+        head.forward_training(features([16, 32, 64]), &[], &YoloxLoss::new());
     }
 }
@@ -0,0 +1,810 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        Dropout, DropoutConfig, Initializer, Linear, LinearConfig, PaddingConfig2d,
+    },
+    tensor::{
+        activation::{relu, sigmoid, silu},
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Distribution, Tensor,
+    },
+};
+
+use super::{
+    blocks::{BaseConv, BaseConvConfig, DeformConv2d, DeformConv2dConfig},
+    decode_grid::make_anchor_grid,
+};
+
+/// Initial value of the similarity temperature.
+const TEMPERATURE: f32 = 0.07;
+/// Prior probability of the classification outputs at initialization.
+const PRIOR_PROB: f64 = 1e-2;
+
+/// L2-normalize the rows of a `[num_rows, dim]` tensor.
+pub(crate) fn l2_normalize<B: Backend>(x: Tensor<B, 2>) -> Tensor<B, 2> {
+    let norm = x.clone().powf_scalar(2.).sum_dim(1).sqrt().clamp_min(1e-12);
+    x / norm
+}
+
+/// Classification head based on the cosine similarity between the visual features and a class
+/// embedding table, as used for open-vocabulary detection.
+///
+/// The class embeddings can be replaced at runtime (e.g., with text embeddings of new class
+/// names) for zero-shot classification.
+#[derive(Module, Debug)]
+pub struct CosineSimilarityHead<B: Backend> {
+    /// Class embeddings. Shape: `[num_classes, embed_dim]`.
+    class_embeddings: Param<Tensor<B, 2>>,
+    /// Learnable temperature of the similarity logits.
+    temperature: Param<Tensor<B, 1>>,
+}
+
+impl<B: Backend> CosineSimilarityHead<B> {
+    /// Compute the cosine similarity between the features and each class embedding.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, embed_dim]`
+    ///   - output: `[batch_size, num_classes]`
+    pub fn forward(&self, features: Tensor<B, 2>) -> Tensor<B, 2> {
+        let features = l2_normalize(features);
+        let classes = l2_normalize(self.class_embeddings.val());
+
+        features.matmul(classes.transpose())
+    }
+
+    /// Compute the classification logits, i.e. the cosine similarities scaled by the temperature,
+    /// to be used with a softmax.
+    pub fn logits(&self, features: Tensor<B, 2>) -> Tensor<B, 2> {
+        let temperature = self.temperature.val().clamp_min(1e-4).unsqueeze::<2>();
+        self.forward(features) / temperature
+    }
+
+    /// Replace the class embedding table with shape `[num_classes, embed_dim]`. The number of
+    /// classes can differ from the current table.
+    pub fn update_class_embeddings(&mut self, embeddings: Tensor<B, 2>) {
+        let [_, embed_dim] = self.class_embeddings.dims();
+        let [_, dim] = embeddings.dims();
+        assert_eq!(dim, embed_dim, "class embeddings dimension mismatch");
+
+        self.class_embeddings = Param::from_tensor(embeddings);
+    }
+
+    /// Number of classes of the embedding table.
+    pub fn num_classes(&self) -> usize {
+        let [num_classes, _] = self.class_embeddings.dims();
+        num_classes
+    }
+}
+
+/// [Cosine similarity head](CosineSimilarityHead) configuration.
+pub struct CosineSimilarityHeadConfig {
+    embed_dim: usize,
+    num_classes: usize,
+}
+
+impl CosineSimilarityHeadConfig {
+    /// Create a new instance of the cosine similarity head [config](CosineSimilarityHeadConfig).
+    pub fn new(embed_dim: usize, num_classes: usize) -> Self {
+        Self {
+            embed_dim,
+            num_classes,
+        }
+    }
+
+    /// Initialize a new [cosine similarity head](CosineSimilarityHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> CosineSimilarityHead<B> {
+        let class_embeddings = Tensor::random(
+            [self.num_classes, self.embed_dim],
+            Distribution::Normal(0., 0.02),
+            device,
+        );
+
+        CosineSimilarityHead {
+            class_embeddings: Param::from_tensor(class_embeddings),
+            temperature: Param::from_tensor(Tensor::full([1], TEMPERATURE, device)),
+        }
+    }
+}
+
+/// Global average pooling `[B, C, H, W] -> [B, C]`.
+fn global_pool<B: Backend>(x: Tensor<B, 4>) -> Tensor<B, 2> {
+    let [b, c, _, _] = x.dims();
+    x.flatten::<3>(2, 3).mean_dim(2).reshape([b, c])
+}
+
+/// Global average pooling over the spatial dimensions.
+#[derive(Module, Debug, Clone, Default)]
+pub struct GlobalAvgPool2d {}
+
+impl GlobalAvgPool2d {
+    /// # Shapes
+    ///   - input: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels]`
+    pub fn forward<B: Backend>(&self, x: Tensor<B, 4>) -> Tensor<B, 2> {
+        global_pool(x)
+    }
+}
+
+/// Image classification head: [global average pooling](GlobalAvgPool2d) -> dropout -> Linear.
+#[derive(Module, Debug)]
+pub struct ClassificationHead<B: Backend> {
+    pool: GlobalAvgPool2d,
+    dropout: Dropout,
+    fc: Linear<B>,
+}
+
+impl<B: Backend> ClassificationHead<B> {
+    /// Compute the class logits from the feature map.
+    ///
+    /// Dropout is only applied during training (i.e., with an autodiff backend).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, num_classes]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 2> {
+        let x = self.pool.forward(x);
+        let x = self.dropout.forward(x);
+
+        self.fc.forward(x)
+    }
+}
+
+/// [Classification head](ClassificationHead) configuration.
+pub struct ClassificationHeadConfig {
+    fc: LinearConfig,
+    dropout: DropoutConfig,
+}
+
+impl ClassificationHeadConfig {
+    /// Create a new instance of the classification head [config](ClassificationHeadConfig).
+    pub fn new(in_channels: usize, num_classes: usize, dropout: f64) -> Self {
+        Self {
+            fc: LinearConfig::new(in_channels, num_classes),
+            dropout: DropoutConfig::new(dropout),
+        }
+    }
+
+    /// Initialize a new [classification head](ClassificationHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ClassificationHead<B> {
+        ClassificationHead {
+            pool: GlobalAvgPool2d {},
+            dropout: self.dropout.init(),
+            fc: self.fc.init(device),
+        }
+    }
+}
+
+/// Scale-aware attention of the [dynamic head](DyHead).
+///
+/// Each output level is a weighted sum of all the (resized) levels, where the weight of each
+/// level is a sigmoid gate of its pooled features normalized over the levels. With a single level,
+/// the attention is a pass-through.
+#[derive(Module, Debug)]
+pub struct ScaleAwareAttention<B: Backend> {
+    fc: Conv2d<B>,
+}
+
+impl<B: Backend> ScaleAwareAttention<B> {
+    pub fn forward(&self, features: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 4>> {
+        if features.len() <= 1 {
+            return features;
+        }
+
+        // Level gates [B, 1, 1, 1]
+        let gates: Vec<_> = features
+            .iter()
+            .map(|x| {
+                let [b, c, _, _] = x.dims();
+                let pooled = global_pool(x.clone()).reshape([b, c, 1, 1]);
+                sigmoid(self.fc.forward(pooled))
+            })
+            .collect();
+        let total = gates
+            .iter()
+            .cloned()
+            .reduce(|a, b| a + b)
+            .unwrap()
+            .clamp_min(1e-6);
+
+        features
+            .iter()
+            .map(|target| {
+                let [_, _, h, w] = target.dims();
+                features
+                    .iter()
+                    .zip(&gates)
+                    .map(|(x, gate)| {
+                        let [_, _, xh, xw] = x.dims();
+                        let x = if [xh, xw] == [h, w] {
+                            x.clone()
+                        } else {
+                            interpolate(
+                                x.clone(),
+                                [h, w],
+                                InterpolateOptions::new(InterpolateMode::Bilinear),
+                            )
+                        };
+                        x * (gate.clone() / total.clone())
+                    })
+                    .reduce(|a, b| a + b)
+                    .unwrap()
+            })
+            .collect()
+    }
+}
+
+/// Spatial-aware attention of the [dynamic head](DyHead): a
+/// [deformable convolution](DeformConv2d) attending to sparse locations of each level.
+#[derive(Module, Debug)]
+pub struct SpatialAwareAttention<B: Backend> {
+    conv: DeformConv2d<B>,
+}
+
+impl<B: Backend> SpatialAwareAttention<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.conv.forward(x)
+    }
+}
+
+/// Task-aware attention of the [dynamic head](DyHead): a channel-wise squeeze-excite modulation
+/// conditioned on a learned task embedding (one per class and one for objectness).
+#[derive(Module, Debug)]
+pub struct TaskAwareAttention<B: Backend> {
+    fc1: Linear<B>,
+    fc2: Linear<B>,
+    /// Task embeddings. Shape: `[num_tasks, hidden_channels]`.
+    tasks: Param<Tensor<B, 2>>,
+}
+
+impl<B: Backend> TaskAwareAttention<B> {
+    /// Modulate the channels of the features for a task, or for all the tasks (using the average
+    /// task embedding) when `task` is `None`.
+    pub fn forward(&self, x: Tensor<B, 4>, task: Option<usize>) -> Tensor<B, 4> {
+        let [b, c, _, _] = x.dims();
+        let [_, hidden] = self.tasks.dims();
+
+        // [1, hidden]
+        let task = match task {
+            Some(t) => self.tasks.val().slice([t..t + 1, 0..hidden]),
+            None => self.tasks.val().mean_dim(0),
+        };
+
+        let hidden = relu(self.fc1.forward(global_pool(x.clone())) + task);
+        let gate = sigmoid(self.fc2.forward(hidden)).reshape([b, c, 1, 1]);
+
+        x * gate
+    }
+}
+
+/// [Dynamic head](https://arxiv.org/abs/2106.08322) block, applying the task, scale and spatial
+/// aware attentions in sequence.
+#[derive(Module, Debug)]
+pub struct DyHeadBlock<B: Backend> {
+    task: TaskAwareAttention<B>,
+    scale: ScaleAwareAttention<B>,
+    spatial: SpatialAwareAttention<B>,
+}
+
+impl<B: Backend> DyHeadBlock<B> {
+    pub fn forward(&self, features: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 4>> {
+        let features = features
+            .into_iter()
+            .map(|x| self.task.forward(x, None))
+            .collect();
+
+        self.scale
+            .forward(features)
+            .into_iter()
+            .map(|x| self.spatial.forward(x))
+            .collect()
+    }
+}
+
+/// Dynamic head (DyHead) unifying the scale, spatial and task aware attentions over the levels
+/// of a feature pyramid.
+#[derive(Module, Debug)]
+pub struct DyHead<B: Backend> {
+    blocks: Vec<DyHeadBlock<B>>,
+}
+
+impl<B: Backend> DyHead<B> {
+    /// Refine the feature pyramid levels.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels, H_i, W_i]` for each level
+    ///   - output: `[batch_size, in_channels, H_i, W_i]` for each level
+    pub fn forward(&self, features: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 4>> {
+        self.blocks
+            .iter()
+            .fold(features, |features, block| block.forward(features))
+    }
+}
+
+/// [Dynamic head](DyHead) configuration.
+pub struct DyHeadConfig {
+    in_channels: usize,
+    num_convs: usize,
+    num_classes: usize,
+}
+
+impl DyHeadConfig {
+    /// Create a new instance of the dynamic head [config](DyHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the feature pyramid levels.
+    /// * `num_convs` - Number of dynamic head blocks.
+    /// * `num_classes` - Number of classes (a task embedding is learned for each class and for
+    ///   the objectness).
+    pub fn new(in_channels: usize, num_convs: usize, num_classes: usize) -> Self {
+        Self {
+            in_channels,
+            num_convs,
+            num_classes,
+        }
+    }
+
+    /// Initialize a new [dynamic head](DyHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DyHead<B> {
+        let c = self.in_channels;
+        let hidden = (c / 4).max(1);
+
+        let blocks = (0..self.num_convs)
+            .map(|_| DyHeadBlock {
+                task: TaskAwareAttention {
+                    fc1: LinearConfig::new(c, hidden).init(device),
+                    fc2: LinearConfig::new(hidden, c).init(device),
+                    tasks: Param::from_tensor(Tensor::random(
+                        [self.num_classes + 1, hidden],
+                        Distribution::Normal(0., 0.02),
+                        device,
+                    )),
+                },
+                scale: ScaleAwareAttention {
+                    fc: Conv2dConfig::new([c, 1], [1, 1])
+                        .with_padding(PaddingConfig2d::Explicit(0, 0))
+                        .init(device),
+                },
+                spatial: SpatialAwareAttention {
+                    conv: DeformConv2dConfig::new(c, c, 3).init(device),
+                },
+            })
+            .collect();
+
+        DyHead { blocks }
+    }
+}
+
+/// Task decomposition and prediction branch of the [task-aligned head](TalHead).
+///
+/// The task-specific features are computed from the stacked interactive features with a layer
+/// attention, i.e. a weighted sum of the features of each layer followed by a `1x1` reduction.
+#[derive(Module, Debug)]
+pub struct TaskAlignedPredictor<B: Backend> {
+    attention_fc1: Linear<B>,
+    attention_fc2: Linear<B>,
+    reduce: BaseConv<B>,
+    pred: Conv2d<B>,
+    num_layers: usize,
+}
+
+impl<B: Backend> TaskAlignedPredictor<B> {
+    /// Compute the task-specific features from the stacked interactive features.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, num_layers * channels, H, W]`
+    ///   - output: `[batch_size, channels, H, W]`
+    pub fn decompose(&self, features: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [b, c, h, w] = features.dims();
+        let n = self.num_layers;
+
+        // Layer attention [B, N, 1, 1, 1]
+        let weights = relu(self.attention_fc1.forward(global_pool(features.clone())));
+        let weights = sigmoid(self.attention_fc2.forward(weights)).reshape([b, n, 1, 1, 1]);
+
+        let x = features.reshape([b, n, c / n, h, w]) * weights;
+        self.reduce.forward(x.reshape([b, c, h, w]))
+    }
+
+    /// Predict the task outputs from the task-specific features.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, channels, H, W]`
+    ///   - output: `[batch_size, out_channels, H, W]`
+    pub fn predict(&self, features: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.pred.forward(features)
+    }
+}
+
+/// [Task-aligned predictor](TaskAlignedPredictor) configuration.
+pub struct TaskAlignedPredictorConfig {
+    channels: usize,
+    out_channels: usize,
+    num_layers: usize,
+    bias: f64,
+}
+
+impl TaskAlignedPredictorConfig {
+    /// Create a new instance of the task-aligned predictor [config](TaskAlignedPredictorConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `channels`: Number of channels of each interactive layer.
+    /// * `out_channels` - Number of predicted outputs.
+    /// * `num_layers` - Number of stacked interactive layers.
+    pub fn new(channels: usize, out_channels: usize, num_layers: usize) -> Self {
+        Self {
+            channels,
+            out_channels,
+            num_layers,
+            bias: 0.,
+        }
+    }
+
+    /// Set the initial bias of the predictions (defaults to 0).
+    pub fn with_bias(mut self, bias: f64) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    /// Initialize a new [task-aligned predictor](TaskAlignedPredictor) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> TaskAlignedPredictor<B> {
+        let stacked = self.channels * self.num_layers;
+        let hidden = (stacked / 8).max(1);
+
+        TaskAlignedPredictor {
+            attention_fc1: LinearConfig::new(stacked, hidden).init(device),
+            attention_fc2: LinearConfig::new(hidden, self.num_layers).init(device),
+            reduce: BaseConvConfig::new(stacked, self.channels, 1, 1, 1).init(device),
+            pred: Conv2dConfig::new([self.channels, self.out_channels], [3, 3])
+                .with_padding(PaddingConfig2d::Explicit(1, 1))
+                .with_initializer(Initializer::Constant { value: self.bias })
+                .init(device),
+            num_layers: self.num_layers,
+        }
+    }
+}
+
+/// Task-aligned head (TAL head) from [TOOD](https://arxiv.org/abs/2108.07755), as used in
+/// PP-YOLOE.
+///
+/// A stack of shared convolutions (optionally followed by [deformable
+/// convolutions](DeformConv2d)) computes interactive features, which are decomposed into
+/// classification and localization features by [task-aligned predictors](TaskAlignedPredictor).
+/// The classification features are conditioned on the localization features with an element-wise
+/// product by the task-interactive layer.
+#[derive(Module, Debug)]
+pub struct TalHead<B: Backend> {
+    convs: Vec<BaseConv<B>>,
+    dcns: Vec<DeformConv2d<B>>,
+    interaction: Conv2d<B>,
+    cls: TaskAlignedPredictor<B>,
+    reg: TaskAlignedPredictor<B>,
+}
+
+impl<B: Backend> TalHead<B> {
+    /// Predict the class logits and the box distances at each location.
+    ///
+    /// The boxes are the `(left, top, right, bottom)` distances from each location, in units of
+    /// the feature map stride (see [ltrb_to_cxcywh]).
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, in_channels, H, W]`
+    ///   - output: `([batch_size, H * W, num_classes], [batch_size, H * W, 4])`
+    pub fn forward(&self, x: Tensor<B, 4>) -> (Tensor<B, 3>, Tensor<B, 3>) {
+        let [b, _, h, w] = x.dims();
+
+        // Interactive features of each layer
+        let mut features = Vec::with_capacity(self.convs.len() + self.dcns.len());
+        let mut x = x;
+        for conv in self.convs.iter() {
+            x = conv.forward(x);
+            features.push(x.clone());
+        }
+        for dcn in self.dcns.iter() {
+            x = silu(dcn.forward(x));
+            features.push(x.clone());
+        }
+        let features = Tensor::cat(features, 1);
+
+        // Task decomposition and interaction
+        let reg_features = self.reg.decompose(features.clone());
+        let cls_features =
+            self.cls.decompose(features) * sigmoid(self.interaction.forward(reg_features.clone()));
+
+        let cls = self.cls.predict(cls_features);
+        let reg = relu(self.reg.predict(reg_features));
+        let [_, num_classes, _, _] = cls.dims();
+
+        (
+            cls.reshape([b, num_classes, h * w]).swap_dims(1, 2),
+            reg.reshape([b, 4, h * w]).swap_dims(1, 2),
+        )
+    }
+}
+
+/// Convert the `(left, top, right, bottom)` distances predicted by the [task-aligned
+/// head](TalHead) to `(cx, cy, w, h)` boxes in pixels.
+///
+/// # Shapes
+///   - distances: `[batch_size, H * W, 4]`
+///   - output: `[batch_size, H * W, 4]`
+pub fn ltrb_to_cxcywh<B: Backend>(
+    distances: Tensor<B, 3>,
+    [h, w]: [usize; 2],
+    stride: usize,
+) -> Tensor<B, 3> {
+    let [b, n, _] = distances.dims();
+    let distances = distances.mul_scalar(stride as f32);
+    let col = |i: usize| distances.clone().slice([0..b, 0..n, i..i + 1]);
+
+    // Location centers [1, H * W, 2]
+    let (points, _) = make_anchor_grid::<B>(&[(h, w)], &[stride], &distances.device());
+    let points = points.unsqueeze::<3>();
+
+    let (l, t, r, bottom) = (col(0), col(1), col(2), col(3));
+    let offset = Tensor::cat(vec![r.clone() - l.clone(), bottom.clone() - t.clone()], 2) / 2.;
+
+    Tensor::cat(vec![points + offset, l + r, t + bottom], 2)
+}
+
+/// [Task-aligned head](TalHead) configuration.
+pub struct TalHeadConfig {
+    in_channels: usize,
+    num_classes: usize,
+    num_conv_layers: usize,
+    num_dcn_layers: usize,
+}
+
+impl TalHeadConfig {
+    /// Create a new instance of the task-aligned head [config](TalHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input channels.
+    /// * `num_classes` - Number of classes.
+    /// * `num_conv_layers` - Number of shared convolutions.
+    /// * `num_dcn_layers` - Number of shared deformable convolutions, applied after the
+    ///   convolutions.
+    pub fn new(
+        in_channels: usize,
+        num_classes: usize,
+        num_conv_layers: usize,
+        num_dcn_layers: usize,
+    ) -> Self {
+        assert!(
+            num_conv_layers + num_dcn_layers > 0,
+            "the head should have at least one interactive layer"
+        );
+
+        Self {
+            in_channels,
+            num_classes,
+            num_conv_layers,
+            num_dcn_layers,
+        }
+    }
+
+    /// Initialize a new [task-aligned head](TalHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> TalHead<B> {
+        let c = self.in_channels;
+        let num_layers = self.num_conv_layers + self.num_dcn_layers;
+        // Initialize the classification biases to the prior probability
+        let bias = -f64::ln((1.0 - PRIOR_PROB) / PRIOR_PROB);
+
+        TalHead {
+            convs: (0..self.num_conv_layers)
+                .map(|_| BaseConvConfig::new(c, c, 3, 1, 1).init(device))
+                .collect(),
+            dcns: (0..self.num_dcn_layers)
+                .map(|_| DeformConv2dConfig::new(c, c, 3).init(device))
+                .collect(),
+            interaction: Conv2dConfig::new([c, c], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+                .init(device),
+            cls: TaskAlignedPredictorConfig::new(c, self.num_classes, num_layers)
+                .with_bias(bias)
+                .init(device),
+            reg: TaskAlignedPredictorConfig::new(c, 4, num_layers).init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Int, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn cosine_head(embeddings: [[f32; 3]; 2]) -> CosineSimilarityHead<TestBackend> {
+        let device = Default::default();
+        let mut head = CosineSimilarityHeadConfig::new(3, 2).init(&device);
+        head.update_class_embeddings(Tensor::from_floats(embeddings, &device));
+
+        head
+    }
+
+    #[test]
+    fn cosine_similarity_identical_embeddings() {
+        let head = cosine_head([[1., 2., 3.], [-1., 0., 0.5]]);
+        // Same directions as the class embeddings, with different norms
+        let features = Tensor::from_floats([[2., 4., 6.], [-0.5, 0., 0.25]], &Default::default());
+
+        let scores = head.forward(features).into_data();
+
+        assert_eq!(scores.shape, [2, 2]);
+        let scores = scores.to_vec::<f32>().unwrap();
+        assert!((scores[0] - 1.).abs() < 1e-6);
+        assert!((scores[3] - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cosine_similarity_orthogonal_embeddings() {
+        let head = cosine_head([[1., 0., 0.], [0., 3., 4.]]);
+        let features = Tensor::from_floats([[0., 4., -3.], [0., 0., 2.]], &Default::default());
+
+        let scores = head.forward(features);
+
+        scores
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[0f32, 0.], [0., 0.8]]), 5);
+    }
+
+    #[test]
+    fn cosine_logits_scaled_by_temperature() {
+        let head = cosine_head([[1., 0., 0.], [0., 1., 0.]]);
+        let features = Tensor::from_floats([[1., 0., 0.]], &Default::default());
+
+        let logits = head.logits(features);
+
+        logits
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1. / TEMPERATURE, 0.]]), 3);
+    }
+
+    #[test]
+    fn update_class_embeddings_changes_num_classes() {
+        let device = Default::default();
+        let mut head = CosineSimilarityHeadConfig::new(3, 2).init::<TestBackend>(&device);
+        assert_eq!(head.num_classes(), 2);
+
+        head.update_class_embeddings(Tensor::ones([5, 3], &device));
+
+        assert_eq!(head.num_classes(), 5);
+        let features = Tensor::ones([4, 3], &device);
+        assert_eq!(head.forward(features).dims(), [4, 5]);
+    }
+
+    #[test]
+    #[should_panic = "class embeddings dimension mismatch"]
+    fn update_class_embeddings_dimension_mismatch() {
+        let device = Default::default();
+        let mut head = CosineSimilarityHeadConfig::new(3, 2).init::<TestBackend>(&device);
+
+        head.update_class_embeddings(Tensor::ones([2, 4], &device));
+    }
+
+    fn feature_pyramid(batch_size: usize, channels: usize) -> Vec<Tensor<TestBackend, 4>> {
+        [16, 8, 4]
+            .into_iter()
+            .map(|size| {
+                Tensor::random(
+                    [batch_size, channels, size, size],
+                    Distribution::Default,
+                    &Default::default(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scale_aware_attention_single_level() {
+        let head = DyHeadConfig::new(8, 1, 3).init::<TestBackend>(&Default::default());
+        let x = feature_pyramid(2, 8).remove(0);
+
+        let out = head.blocks[0].scale.forward(vec![x.clone()]);
+
+        assert_eq!(out.len(), 1);
+        out[0].to_data().assert_eq(&x.into_data(), true);
+    }
+
+    #[test]
+    fn scale_aware_attention_identical_levels() {
+        let head = DyHeadConfig::new(8, 1, 3).init::<TestBackend>(&Default::default());
+        let x = feature_pyramid(2, 8).remove(1);
+
+        // The level weights are normalized, so identical levels are unchanged
+        let out = head.blocks[0].scale.forward(vec![x.clone(), x.clone()]);
+
+        for level in out {
+            level.into_data().assert_approx_eq(&x.to_data(), 4);
+        }
+    }
+
+    #[test]
+    fn task_aware_attention_shape() {
+        let head = DyHeadConfig::new(8, 1, 3).init::<TestBackend>(&Default::default());
+        let x = feature_pyramid(2, 8).remove(2);
+        let task = &head.blocks[0].task;
+
+        for t in [None, Some(0), Some(3)] {
+            assert_eq!(task.forward(x.clone(), t).dims(), [2, 8, 4, 4]);
+        }
+    }
+
+    #[test]
+    fn dyhead_output_shapes() {
+        let head = DyHeadConfig::new(8, 2, 3).init::<TestBackend>(&Default::default());
+        let features = feature_pyramid(2, 8);
+        let shapes: Vec<_> = features.iter().map(|x| x.dims()).collect();
+
+        let out = head.forward(features);
+
+        assert_eq!(out.iter().map(|x| x.dims()).collect::<Vec<_>>(), shapes);
+    }
+
+    #[test]
+    fn tal_head_output_shapes() {
+        let device = Default::default();
+        let head = TalHeadConfig::new(8, 3, 1, 1).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 8, 4, 6], Distribution::Default, &device);
+
+        let (cls, reg) = head.forward(x);
+
+        assert_eq!(cls.dims(), [2, 24, 3]);
+        assert_eq!(reg.dims(), [2, 24, 4]);
+        // The distances are non-negative
+        let min: f32 = reg.min().into_scalar();
+        assert!(min >= 0.);
+    }
+
+    #[test]
+    fn ltrb_distances_to_boxes() {
+        let device = Default::default();
+        let distances = Tensor::<TestBackend, 3>::from_floats([[[1., 1., 3., 1.]]], &device);
+
+        // Location center (4, 4), box from (-4, -4) to (28, 12)
+        let boxes = ltrb_to_cxcywh(distances, [1, 1], 8);
+
+        boxes
+            .into_data()
+            .assert_eq(&TensorData::from([[[12f32, 4., 32., 16.]]]), false);
+    }
+
+    #[test]
+    #[should_panic = "at least one interactive layer"]
+    fn tal_head_without_layers() {
+        let _ = TalHeadConfig::new(8, 3, 0, 0);
+    }
+
+    #[test]
+    fn global_avg_pool_shape() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 1, Int>::arange(0..16, &device)
+            .float()
+            .reshape([2, 2, 2, 2]);
+
+        let output = GlobalAvgPool2d::default().forward(x);
+
+        output
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1.5, 5.5], [9.5, 13.5]]), 5);
+    }
+
+    #[test]
+    fn classification_head_shape() {
+        let device = Default::default();
+        let head = ClassificationHeadConfig::new(16, 10, 0.2).init::<TestBackend>(&device);
+        let x = Tensor::random([3, 16, 7, 5], Distribution::Default, &device);
+
+        assert_eq!(head.forward(x).dims(), [3, 10]);
+    }
+}
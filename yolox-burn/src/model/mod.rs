@@ -1,10 +1,284 @@
-mod blocks;
-mod bottleneck;
+pub mod attention;
+pub mod bevfusion;
+pub mod blocks;
+pub mod bottleneck;
 pub mod boxes;
-mod darknet;
-mod head;
+pub mod cascade;
+pub mod clip;
+pub mod darknet;
+pub mod decode_grid;
+pub mod depth_estimation;
+pub mod detr;
+pub mod efficientnet_v2;
+pub mod functional;
+pub mod head;
+pub mod heads;
+pub mod neck;
+pub mod normalizations;
+pub mod owl_vit;
 mod pafpn;
+pub mod picodet;
+pub mod pointcloud;
+pub mod postprocess;
+pub mod raft_lite;
+#[cfg(feature = "pretrained")]
+pub mod registry;
+pub mod rpn;
+pub mod sparse_rcnn;
+pub mod super_resolution;
+pub mod unet;
+#[cfg(feature = "pretrained")]
+pub mod verifier;
+pub mod vit;
 pub mod weights;
 pub mod yolox;
+pub mod yolox_seg;
 
-pub use boxes::BoundingBox;
+use alloc::vec::Vec;
+use burn::tensor::{backend::Backend, Tensor};
+
+use crate::types::{ConfThreshold, Detection, IoUThreshold};
+use postprocess::nms::batch_nms_detections;
+
+/// Default IoU threshold used for [inference](DetectionModel::infer).
+pub const NMS_IOU_THRESHOLD: f32 = 0.65;
+/// Default score threshold used for [inference](DetectionModel::infer).
+pub const SCORE_THRESHOLD: f32 = 0.5;
+/// Maximum number of detections per image.
+pub const MAX_DETECTIONS: usize = 300;
+
+/// Raw detection model outputs, before score filtering and non-maximum suppression.
+///
+/// The variants describe the tensor layout so that the outputs of all models can be decoded by the
+/// same [decode](DetectionModel::decode) path.
+pub enum DetectionRawOutput<B: Backend> {
+    /// Anchor-free predictions (e.g., YOLOX) with absolute `(cx, cy, w, h)` boxes, followed by
+    /// the objectness and class scores. Shape: `[batch_size, num_anchors, 5 + num_classes]`.
+    AnchorFree(Tensor<B, 3>),
+    /// Anchor-based predictions with box offsets relative to the anchor boxes.
+    AnchorBased {
+        /// Box offsets `(dx, dy, dw, dh)`. Shape: `[batch_size, num_anchors, 4]`.
+        deltas: Tensor<B, 3>,
+        /// Anchor boxes `(cx, cy, w, h)`. Shape: `[num_anchors, 4]`.
+        anchors: Tensor<B, 2>,
+        /// Class scores. Shape: `[batch_size, num_anchors, num_classes]`.
+        scores: Tensor<B, 3>,
+    },
+}
+
+impl<B: Backend> DetectionRawOutput<B> {
+    /// Decode the absolute `(cx, cy, w, h)` boxes with shape `[batch_size, num_anchors, 4]` and
+    /// the class scores with shape `[batch_size, num_anchors, num_classes]`.
+    pub fn boxes_and_scores(self) -> (Tensor<B, 3>, Tensor<B, 3>) {
+        match self {
+            Self::AnchorFree(out) => {
+                let [batch_size, num_boxes, num_outputs] = out.dims();
+                let boxes = out.clone().slice([0..batch_size, 0..num_boxes, 0..4]);
+                let obj_scores = out.clone().slice([0..batch_size, 0..num_boxes, 4..5]);
+                let cls_scores = out.slice([0..batch_size, 0..num_boxes, 5..num_outputs]);
+
+                (boxes, cls_scores * obj_scores)
+            }
+            Self::AnchorBased {
+                deltas,
+                anchors,
+                scores,
+            } => {
+                let [batch_size, num_boxes, _] = deltas.dims();
+                let anchors = anchors.unsqueeze::<3>();
+                let anchor_xy = anchors.clone().slice([0..1, 0..num_boxes, 0..2]);
+                let anchor_wh = anchors.slice([0..1, 0..num_boxes, 2..4]);
+
+                let xy = deltas.clone().slice([0..batch_size, 0..num_boxes, 0..2])
+                    * anchor_wh.clone()
+                    + anchor_xy;
+                let wh = deltas.slice([0..batch_size, 0..num_boxes, 2..4]).exp() * anchor_wh;
+
+                (Tensor::cat(alloc::vec![xy, wh], 2), scores)
+            }
+        }
+    }
+}
+
+/// Common interface for object detection models, making them interchangeable for inference and
+/// evaluation.
+///
+/// The trait does not require [Module](burn::module::Module) as a supertrait so that different
+/// models can be used as trait objects (e.g., in an [ensemble](crate::utils::ensemble::ModelEnsemble)).
+pub trait DetectionModel<B: Backend> {
+    /// Compute the raw outputs for a batch of images.
+    ///
+    /// # Arguments
+    ///
+    /// * `images`: Input images. Shape: `[batch_size, channels, height, width]`.
+    fn forward_raw(&self, images: Tensor<B, 4>) -> DetectionRawOutput<B>;
+
+    /// Number of object classes predicted by the model.
+    fn num_classes(&self) -> usize;
+
+    /// Decode the raw outputs into detections for each image.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw`: Raw model outputs.
+    /// * `conf_threshold` - Minimum score of the detections.
+    /// * `nms_iou_threshold` - IoU threshold for non-maximum suppression.
+    fn decode(
+        &self,
+        raw: DetectionRawOutput<B>,
+        conf_threshold: ConfThreshold,
+        nms_iou_threshold: IoUThreshold,
+    ) -> Vec<Vec<Detection>> {
+        let (boxes, scores) = raw.boxes_and_scores();
+        batch_nms_detections(
+            boxes,
+            scores,
+            nms_iou_threshold,
+            conf_threshold,
+            MAX_DETECTIONS,
+        )
+    }
+
+    /// Detect objects in a batch of images with the default thresholds.
+    ///
+    /// # Arguments
+    ///
+    /// * `images`: Input images. Shape: `[batch_size, channels, height, width]`.
+    ///
+    /// # Returns
+    ///
+    /// The detections for each image in the batch.
+    fn infer(&self, images: Tensor<B, 4>) -> Vec<Vec<Detection>> {
+        self.decode(
+            self.forward_raw(images),
+            SCORE_THRESHOLD.into(),
+            NMS_IOU_THRESHOLD.into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics::coco_map, types::GroundTruth};
+    use alloc::{boxed::Box, vec};
+    use burn::{backend::NdArray, tensor::TensorData};
+    use picodet::{PicoDetConfig, PicoDetVariant};
+    use yolox::Yolox;
+
+    type TestBackend = NdArray<f32>;
+
+    /// Ground truth boxes of class 0 and 1 in the first image.
+    fn ground_truths() -> Vec<GroundTruth> {
+        [([40., 40., 60., 60.], 0), ([100., 100., 140., 120.], 1)]
+            .into_iter()
+            .map(|(box_xyxy, class_id)| GroundTruth {
+                image_id: 0,
+                box_xyxy,
+                class_id,
+                area: (box_xyxy[2] - box_xyxy[0]) * (box_xyxy[3] - box_xyxy[1]),
+                is_crowd: false,
+            })
+            .collect()
+    }
+
+    /// Anchor-free outputs matching the ground truth, with a low scoring false positive.
+    fn anchor_free_output(swap_classes: bool) -> DetectionRawOutput<TestBackend> {
+        let (c0, c1) = if swap_classes { (0., 0.9) } else { (0.9, 0.) };
+        DetectionRawOutput::AnchorFree(Tensor::from_floats(
+            [[
+                [50., 50., 20., 20., 1., c0, c1],
+                [120., 110., 40., 20., 0.8, c1, c0],
+                [10., 10., 4., 4., 0.2, 0.5, 0.5],
+            ]],
+            &Default::default(),
+        ))
+    }
+
+    /// Anchor-based outputs matching the ground truth, with zero offsets to the anchor boxes.
+    fn anchor_based_output() -> DetectionRawOutput<TestBackend> {
+        let device = Default::default();
+        DetectionRawOutput::AnchorBased {
+            deltas: Tensor::zeros([1, 3, 4], &device),
+            anchors: Tensor::from_floats(
+                [
+                    [50., 50., 20., 20.],
+                    [120., 110., 40., 20.],
+                    [10., 10., 4., 4.],
+                ],
+                &device,
+            ),
+            scores: Tensor::from_floats([[[0.9, 0.], [0., 0.7], [0.1, 0.1]]], &device),
+        }
+    }
+
+    fn models() -> Vec<Box<dyn DetectionModel<TestBackend>>> {
+        let device = Default::default();
+        let yolox: Box<dyn DetectionModel<TestBackend>> = Box::new(Yolox::yolox_nano(2, &device));
+        vec![
+            yolox,
+            Box::new(
+                PicoDetConfig::new(PicoDetVariant::S)
+                    .with_num_classes(2)
+                    .init(&device),
+            ),
+        ]
+    }
+
+    fn decode(
+        model: &dyn DetectionModel<TestBackend>,
+        raw: DetectionRawOutput<TestBackend>,
+    ) -> Vec<Detection> {
+        let detections = model.decode(
+            raw,
+            ConfThreshold::new(0.5).unwrap(),
+            IoUThreshold::new(0.65).unwrap(),
+        );
+        assert_eq!(detections.len(), 1);
+
+        detections.into_iter().flatten().collect()
+    }
+
+    #[test]
+    fn decode_anchor_free_outputs() {
+        for model in models() {
+            assert_eq!(model.num_classes(), 2);
+
+            let detections = decode(model.as_ref(), anchor_free_output(false));
+            assert_eq!(detections.len(), 2);
+            assert!((coco_map(&detections, &ground_truths()) - 1.).abs() < 1e-6);
+
+            let detections = decode(model.as_ref(), anchor_free_output(true));
+            assert_eq!(coco_map(&detections, &ground_truths()), 0.);
+        }
+    }
+
+    #[test]
+    fn decode_anchor_based_outputs() {
+        for model in models() {
+            let detections = decode(model.as_ref(), anchor_based_output());
+
+            assert_eq!(detections.len(), 2);
+            assert!((coco_map(&detections, &ground_truths()) - 1.).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn anchor_based_boxes_and_scores() {
+        let device = Default::default();
+        let raw = DetectionRawOutput::<TestBackend>::AnchorBased {
+            deltas: Tensor::from_floats([[[0.5, -0.5, 0., 2f32.ln()]]], &device),
+            anchors: Tensor::from_floats([[50., 50., 20., 10.]], &device),
+            scores: Tensor::from_floats([[[0.3, 0.7]]], &device),
+        };
+
+        let (boxes, scores) = raw.boxes_and_scores();
+
+        boxes
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[60., 45., 20., 20.]]]), 4);
+        scores
+            .into_data()
+            .assert_eq(&TensorData::from([[[0.3f32, 0.7]]]), false);
+    }
+}
@@ -0,0 +1,240 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        Initializer, PaddingConfig2d,
+    },
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use crate::model::blocks::deform_sample;
+
+/// Feature alignment before a multi-scale fusion.
+///
+/// A `3x3` convolution predicts a geometric transformation of the sampling grid at each
+/// location: a translation `(dy, dx)` and a log-scale `s`, so that the kernel point `p` samples
+/// the input at `exp(s) * p + (dy, dx)` relative to the location. The input is then convolved
+/// with a [deformable convolution](crate::model::blocks::DeformConv2d) at the transformed
+/// locations, which compensates the scale and spatial mismatches of the resampled features.
+///
+/// The transformation prediction is initialized to zero, so that the module starts as a regular
+/// convolution.
+#[derive(Module, Debug)]
+pub struct FeatureAlignModule<B: Backend> {
+    /// Translation and log-scale prediction (3 channels).
+    transform: Conv2d<B>,
+    /// Projection of the sampled values (equivalent to the convolution kernel).
+    proj: Conv2d<B>,
+    kernel_size: usize,
+}
+
+impl<B: Backend> FeatureAlignModule<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, in_channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.forward_guided(x.clone(), x)
+    }
+
+    /// Align `x`, with the transformation predicted from the `guide` feature map (e.g., the
+    /// concatenation of `x` and the same-scale features it is fused with).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - guide: `[batch_size, guide_channels, height, width]`
+    ///   - output: `[batch_size, in_channels, height, width]`
+    pub fn forward_guided(&self, x: Tensor<B, 4>, guide: Tensor<B, 4>) -> Tensor<B, 4> {
+        let offsets = self.offsets(self.transform.forward(guide));
+        let samples = deform_sample(x, offsets, None, self.kernel_size, 1);
+
+        self.proj.forward(samples)
+    }
+
+    /// Sampling offsets `(dy, dx)` of each kernel point for the predicted transformations.
+    ///
+    /// # Shapes
+    ///   - transform: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, 2 * kernel_size^2, height, width]`
+    fn offsets(&self, transform: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, _, h, w] = transform.dims();
+        let ks = self.kernel_size;
+        let pad = (ks / 2) as f32;
+
+        let dy = transform.clone().slice([0..n, 0..1, 0..h, 0..w]);
+        let dx = transform.clone().slice([0..n, 1..2, 0..h, 0..w]);
+        // Relative scale of the kernel: exp(s) - 1
+        let scale = transform
+            .slice([0..n, 2..3, 0..h, 0..w])
+            .exp()
+            .sub_scalar(1.);
+
+        let offsets: Vec<_> = (0..ks * ks)
+            .flat_map(|i| {
+                let ky = (i / ks) as f32 - pad;
+                let kx = (i % ks) as f32 - pad;
+                [
+                    scale.clone().mul_scalar(ky) + dy.clone(),
+                    scale.clone().mul_scalar(kx) + dx.clone(),
+                ]
+            })
+            .collect();
+
+        Tensor::cat(offsets, 1)
+    }
+}
+
+/// [Feature alignment module](FeatureAlignModule) configuration.
+pub struct FeatureAlignModuleConfig {
+    in_channels: usize,
+    guide_channels: usize,
+    kernel_size: usize,
+}
+
+impl FeatureAlignModuleConfig {
+    /// Create a new instance of the feature alignment module [config](FeatureAlignModuleConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the input (and output) feature map.
+    /// * `kernel_size` - Size of the (odd) kernel of the deformable convolution.
+    pub fn new(in_channels: usize, kernel_size: usize) -> Self {
+        assert!(kernel_size % 2 == 1, "the kernel size should be odd");
+
+        Self {
+            in_channels,
+            guide_channels: in_channels,
+            kernel_size,
+        }
+    }
+
+    /// Set the number of channels of the feature map the transformation is predicted from (see
+    /// [forward_guided](FeatureAlignModule::forward_guided)), which defaults to the input
+    /// channels.
+    pub fn with_guide_channels(mut self, guide_channels: usize) -> Self {
+        self.guide_channels = guide_channels;
+        self
+    }
+
+    /// Initialize a new [feature alignment module](FeatureAlignModule).
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> FeatureAlignModule<B> {
+        let c = self.in_channels;
+        let k = self.kernel_size * self.kernel_size;
+
+        FeatureAlignModule {
+            transform: Conv2dConfig::new([self.guide_channels, 3], [3, 3])
+                .with_padding(PaddingConfig2d::Explicit(1, 1))
+                .with_initializer(Initializer::Zeros)
+                .init(device),
+            proj: Conv2dConfig::new([c * k, c], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+                .init(device),
+            kernel_size: self.kernel_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        module::Param,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    /// Regular convolution with the kernel of the alignment module.
+    fn regular_conv(align: &FeatureAlignModule<TestBackend>) -> Conv2d<TestBackend> {
+        let device = Default::default();
+        let [c, ck, _, _] = align.proj.weight.dims();
+        let (ks, k) = (align.kernel_size, ck / c);
+
+        let mut conv = Conv2dConfig::new([c, c], [ks, ks])
+            .with_padding(PaddingConfig2d::Explicit(ks / 2, ks / 2))
+            .init(&device);
+        // The samples are ordered by kernel point then channel
+        conv.weight = Param::from_tensor(
+            align
+                .proj
+                .weight
+                .val()
+                .reshape([c, k, c])
+                .swap_dims(1, 2)
+                .reshape([c, c, ks, ks]),
+        );
+        conv.bias = align.proj.bias.clone();
+
+        conv
+    }
+
+    #[test]
+    fn zero_transform_is_regular_conv() {
+        let device = Default::default();
+        let align = FeatureAlignModuleConfig::new(4, 3).init::<TestBackend>(&device);
+        let x = Tensor::random([2, 4, 6, 5], Distribution::Default, &device);
+
+        let expected = regular_conv(&align).forward(x.clone());
+        let output = align.forward(x);
+
+        assert_eq!(output.dims(), [2, 4, 6, 5]);
+        output
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 4);
+    }
+
+    #[test]
+    fn non_zero_transform_changes_output() {
+        let device = Default::default();
+        let mut align = FeatureAlignModuleConfig::new(4, 3).init::<TestBackend>(&device);
+        align.transform.bias = Some(Param::from_tensor(Tensor::from_floats(
+            [0.5, -0.5, 0.2],
+            &device,
+        )));
+        let x = Tensor::random([1, 4, 6, 6], Distribution::Default, &device);
+
+        let expected = regular_conv(&align).forward(x.clone());
+        let output = align.forward(x);
+
+        let diff = (output - expected).abs().max().into_scalar();
+        assert!(diff > 1e-3);
+    }
+
+    #[test]
+    fn translation_offsets() {
+        let device = Default::default();
+        let align = FeatureAlignModuleConfig::new(1, 3).init::<TestBackend>(&device);
+        // Translation (1, -2) without scaling
+        let transform =
+            Tensor::<TestBackend, 1>::from_floats([1., -2., 0.], &device).reshape([1, 3, 1, 1]);
+
+        let offsets = align.offsets(transform);
+
+        offsets
+            .reshape([18])
+            .into_data()
+            .assert_approx_eq(&TensorData::new([1f32, -2.].repeat(9), [18]), 5);
+    }
+
+    #[test]
+    fn guided_alignment_shape() {
+        let device = Default::default();
+        let align = FeatureAlignModuleConfig::new(4, 3)
+            .with_guide_channels(12)
+            .init::<TestBackend>(&device);
+
+        let output = align.forward_guided(
+            Tensor::ones([1, 4, 5, 5], &device),
+            Tensor::ones([1, 12, 5, 5], &device),
+        );
+
+        assert_eq!(output.dims(), [1, 4, 5, 5]);
+    }
+
+    #[test]
+    #[should_panic = "the kernel size should be odd"]
+    fn even_kernel_size() {
+        FeatureAlignModuleConfig::new(4, 2);
+    }
+}
@@ -0,0 +1,234 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::Initializer,
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use super::{FpnFeatures, PanNeck, PanNeckConfig};
+use crate::model::{
+    blocks::{expand, DeformConv2d, DeformConv2dConfig},
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
+};
+
+/// Standard deviation of the offset prediction weights at initialization.
+const OFFSET_INIT_STD: f64 = 0.01;
+
+/// [PAN neck](PanNeck) whose top-down and bottom-up merges align the features of the other
+/// scale with a [deformable convolution](DeformConv2d) before the fusion.
+///
+/// The sampling offsets are predicted from the concatenation of the resampled features (from
+/// the coarser scale in the top-down path, or the finer scale in the bottom-up path) and the
+/// same-scale features, so the resampled features can be shifted to match the receptive field
+/// of the same-scale features. The inputs and outputs are the same as the [PAN neck](PanNeck),
+/// which it can replace.
+#[derive(Module, Debug)]
+pub struct DcnNeck<B: Backend> {
+    pan: PanNeck<B>,
+    /// Alignment of the two top-down merges, then of the two bottom-up merges.
+    aligns: Vec<DeformConv2d<B>>,
+}
+
+impl<B: Backend> DcnNeck<B> {
+    /// Fuse the backbone feature maps.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels[i], H / 2^i, W / 2^i]` for each level `i`
+    ///   - output: `[batch_size, out_channels * 2^i, H / 2^i, W / 2^i]` for each level `i`
+    pub fn forward(&self, features: [Tensor<B, 4>; 3]) -> FpnFeatures<B> {
+        self.pan.fuse(features, |i, x, lateral| {
+            let guide = Tensor::cat(vec![x.clone(), lateral], 1);
+            self.aligns[i].forward_guided(x, guide)
+        })
+    }
+
+    /// Deformable alignments of the merges: two top-down then two bottom-up.
+    pub fn aligns(&self) -> &[DeformConv2d<B>] {
+        &self.aligns
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for DcnNeck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            pan: self.pan.freeze_batch_norms(),
+            aligns: self.aligns,
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for DcnNeck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            pan: self.pan.sync_batch_norms(),
+            aligns: self.aligns,
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for DcnNeck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.pan.set_bn_momentum(momentum);
+    }
+}
+
+/// [Deformable convolution neck](DcnNeck) configuration.
+pub struct DcnNeckConfig {
+    in_channels: Vec<usize>,
+    out_channels: usize,
+    deformable_groups: usize,
+    depth_multiple: f64,
+    width_multiple: f64,
+    depthwise: bool,
+}
+
+impl DcnNeckConfig {
+    /// Create a new instance of the deformable convolution neck [config](DcnNeckConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the three input feature maps, from the highest to
+    ///   the lowest resolution, before applying the width multiple.
+    /// * `out_channels` - Number of channels of the highest resolution output feature map, before
+    ///   applying the width multiple. The number of channels doubles for each following level.
+    /// * `deformable_groups` - Number of deformable groups of the alignments, which should
+    ///   divide the number of channels of the aligned feature maps.
+    pub fn new(in_channels: Vec<usize>, out_channels: usize, deformable_groups: usize) -> Self {
+        assert_eq!(
+            in_channels.len(),
+            3,
+            "the DCN neck expects exactly three input feature maps"
+        );
+
+        Self {
+            in_channels,
+            out_channels,
+            deformable_groups,
+            depth_multiple: 1.,
+            width_multiple: 1.,
+            depthwise: false,
+        }
+    }
+
+    /// Set the scaling factors of the number of bottleneck blocks and of the number of channels
+    /// (defaults to 1).
+    pub fn with_multiples(mut self, depth_multiple: f64, width_multiple: f64) -> Self {
+        self.depth_multiple = depth_multiple;
+        self.width_multiple = width_multiple;
+        self
+    }
+
+    /// Use depthwise separable convolutions for the bottom-up path and bottleneck blocks.
+    pub fn with_depthwise(mut self, depthwise: bool) -> Self {
+        self.depthwise = depthwise;
+        self
+    }
+
+    fn pan(&self) -> PanNeckConfig {
+        PanNeckConfig::new(
+            self.in_channels.clone(),
+            self.out_channels,
+            self.depth_multiple,
+            self.width_multiple,
+        )
+        .with_depthwise(self.depthwise)
+    }
+
+    /// Number of channels of each output feature map.
+    pub fn out_channels(&self) -> [usize; 3] {
+        self.pan().out_channels()
+    }
+
+    /// Initialize a new [deformable convolution neck](DcnNeck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DcnNeck<B> {
+        let in_channels: Vec<_> = self
+            .in_channels
+            .iter()
+            .map(|&c| expand(c, self.width_multiple))
+            .collect();
+        let out_channels = self.out_channels();
+
+        // (aligned, same-scale) channels of each merge
+        let merges = [
+            (out_channels[1], in_channels[1]),
+            (out_channels[0], in_channels[0]),
+            (out_channels[0], out_channels[0]),
+            (out_channels[1], out_channels[1]),
+        ];
+        // Small random offsets, so that the alignments are not regular convolutions
+        let initializer = Initializer::Normal {
+            mean: 0.,
+            std: OFFSET_INIT_STD,
+        };
+
+        DcnNeck {
+            pan: self.pan().init(device),
+            aligns: merges
+                .iter()
+                .map(|&(c, lateral)| {
+                    DeformConv2dConfig::new(c, c, 3)
+                        .with_guide_channels(c + lateral)
+                        .with_deformable_groups(self.deformable_groups)
+                        .with_offset_initializer(initializer.clone())
+                        .init(device)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Random feature maps of a 64x64 image, with strides 8, 16 and 32.
+    fn features(channels: [usize; 3]) -> [Tensor<TestBackend, 4>; 3] {
+        let device = Default::default();
+        [(channels[0], 8), (channels[1], 4), (channels[2], 2)]
+            .map(|(c, size)| Tensor::random([2, c, size, size], Distribution::Default, &device))
+    }
+
+    fn dims(features: FpnFeatures<TestBackend>) -> [[usize; 4]; 3] {
+        [features.0.dims(), features.1.dims(), features.2.dims()]
+    }
+
+    #[test]
+    fn matches_pan_neck_shapes() {
+        let device = Default::default();
+        let config = DcnNeckConfig::new(vec![256, 512, 1024], 256, 2).with_multiples(0.33, 0.25);
+        let neck = config.init::<TestBackend>(&device);
+        let pan = PanNeckConfig::new(vec![256, 512, 1024], 256, 0.33, 0.25).init(&device);
+        let x = features([64, 128, 256]);
+
+        let expected = dims(pan.forward(x.clone()));
+        let out = dims(neck.forward(x));
+
+        assert_eq!(out, expected);
+        assert_eq!(config.out_channels(), [64, 128, 256]);
+    }
+
+    #[test]
+    fn non_zero_offsets() {
+        let device = Default::default();
+        let neck = DcnNeckConfig::new(vec![32, 64, 128], 32, 2).init::<TestBackend>(&device);
+        assert_eq!(neck.aligns().len(), 4);
+
+        // First top-down merge: upsampled level 2 outputs (64 channels) and level 1 inputs
+        let guide =
+            Tensor::<TestBackend, 4>::random([2, 128, 4, 4], Distribution::Default, &device);
+        let offsets = neck.aligns()[0].offsets(guide);
+
+        assert_eq!(offsets.dims(), [2, 2 * 9 * 2, 4, 4]);
+        let max_offset = offsets.abs().max().into_scalar();
+        assert!(max_offset > 1e-3);
+    }
+
+    #[test]
+    #[should_panic = "the DCN neck expects exactly three input feature maps"]
+    fn wrong_number_of_inputs() {
+        DcnNeckConfig::new(vec![32, 64], 32, 2);
+    }
+}
@@ -0,0 +1,333 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d, Relu,
+    },
+    tensor::{
+        backend::Backend,
+        module::{interpolate, max_pool2d},
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Tensor,
+    },
+};
+
+/// Source of the first extra (coarser) level of a [feature pyramid](Fpn).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtraLevelSource {
+    /// Strided convolution of the last pyramid level (e.g., P6 from P5).
+    P5,
+    /// Strided convolutions of the second to last pyramid level (e.g., P6 from P4, with an
+    /// intermediate downsampling to the P5 resolution).
+    P4,
+    /// Parameter-free max pooling of the last pyramid level.
+    MaxPool,
+}
+
+/// Extra levels appended to the output of a [feature pyramid](Fpn), for the detection of very
+/// large objects (e.g., P6 and P7 in RetinaNet).
+#[derive(Debug, Clone, Copy)]
+pub struct FpnExtraLevels {
+    num_extra: usize,
+    start_from: ExtraLevelSource,
+}
+
+impl FpnExtraLevels {
+    /// Create a new extra levels configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_extra`: Number of extra levels, each one with half the resolution of the previous.
+    /// * `start_from` - Source of the first extra level. The next ones are computed from the
+    ///   previous extra level.
+    pub fn new(num_extra: usize, start_from: ExtraLevelSource) -> Self {
+        Self {
+            num_extra,
+            start_from,
+        }
+    }
+}
+
+/// [Feature Pyramid Network](https://arxiv.org/abs/1612.03144) neck, which fuses the backbone
+/// feature maps with a top-down path and lateral connections, optionally followed by
+/// [extra levels](FpnExtraLevels).
+#[derive(Module, Debug)]
+pub struct Fpn<B: Backend> {
+    lateral_convs: Vec<Conv2d<B>>,
+    output_convs: Vec<Conv2d<B>>,
+    extra_convs: Vec<Conv2d<B>>,
+    activation: Relu,
+    num_extra: usize,
+    // Offset of the source level of the first extra level from the last pyramid level
+    extra_source_offset: usize,
+}
+
+impl<B: Backend> Fpn<B> {
+    /// Fuse the backbone feature maps.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels[i], H / 2^i, W / 2^i]` for each level `i`, from the
+    ///     highest to the lowest resolution
+    ///   - output: `[batch_size, out_channels, H / 2^i, W / 2^i]` for each level `i`, including
+    ///     the extra levels
+    pub fn forward(&self, features: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 4>> {
+        assert_eq!(
+            features.len(),
+            self.lateral_convs.len(),
+            "the FPN expects one feature map per level"
+        );
+
+        // Top-down path
+        let mut laterals: Vec<_> = features
+            .into_iter()
+            .zip(self.lateral_convs.iter())
+            .map(|(x, conv)| conv.forward(x))
+            .collect();
+        for i in (0..laterals.len() - 1).rev() {
+            let [_, _, h, w] = laterals[i].dims();
+            let upsampled = interpolate(
+                laterals[i + 1].clone(),
+                [h, w],
+                InterpolateOptions::new(InterpolateMode::Nearest),
+            );
+            laterals[i] = laterals[i].clone() + upsampled;
+        }
+
+        let mut outputs: Vec<_> = laterals
+            .into_iter()
+            .zip(self.output_convs.iter())
+            .map(|(x, conv)| conv.forward(x))
+            .collect();
+
+        self.extend(&mut outputs);
+
+        outputs
+    }
+
+    /// Append the extra levels to the pyramid outputs.
+    fn extend(&self, outputs: &mut Vec<Tensor<B, 4>>) {
+        if self.num_extra == 0 {
+            return;
+        }
+
+        if self.extra_convs.is_empty() {
+            for _ in 0..self.num_extra {
+                let x = outputs[outputs.len() - 1].clone();
+                outputs.push(max_pool2d(x, [1, 1], [2, 2], [0, 0], [1, 1]));
+            }
+            return;
+        }
+
+        let mut x = outputs[outputs.len() - 1 - self.extra_source_offset].clone();
+        for (i, conv) in self.extra_convs.iter().enumerate() {
+            // The first level is computed from the pyramid features, the next ones from the
+            // activated previous level
+            if i > 0 {
+                x = self.activation.forward(x);
+            }
+            x = conv.forward(x);
+
+            if i >= self.extra_source_offset {
+                outputs.push(x.clone());
+            }
+        }
+    }
+
+    /// Number of output feature maps, including the extra levels.
+    pub fn num_levels(&self) -> usize {
+        self.lateral_convs.len() + self.num_extra
+    }
+}
+
+/// [FPN](Fpn) configuration.
+pub struct FpnConfig {
+    in_channels: Vec<usize>,
+    out_channels: usize,
+    extra_levels: Option<FpnExtraLevels>,
+}
+
+impl FpnConfig {
+    /// Create a new instance of the FPN [config](FpnConfig), without extra levels.
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the input feature maps, from the highest to the
+    ///   lowest resolution.
+    /// * `out_channels` - Number of channels of the output feature maps.
+    pub fn new(in_channels: Vec<usize>, out_channels: usize) -> Self {
+        assert!(
+            !in_channels.is_empty(),
+            "the FPN expects at least one input feature map"
+        );
+
+        Self {
+            in_channels,
+            out_channels,
+            extra_levels: None,
+        }
+    }
+
+    /// FPN of [RetinaNet](https://arxiv.org/abs/1708.02002), with 256 output channels and two
+    /// extra levels (P6 and P7) computed from P5.
+    pub fn retinanet(in_channels: Vec<usize>) -> Self {
+        Self::new(in_channels, 256).with_extra_levels(FpnExtraLevels::new(2, ExtraLevelSource::P5))
+    }
+
+    /// Append extra levels to the pyramid outputs.
+    pub fn with_extra_levels(mut self, extra_levels: FpnExtraLevels) -> Self {
+        if let ExtraLevelSource::P4 = extra_levels.start_from {
+            assert!(
+                self.in_channels.len() >= 2,
+                "extra levels from P4 require at least two input feature maps"
+            );
+        }
+
+        self.extra_levels = Some(extra_levels);
+        self
+    }
+
+    /// Number of output feature maps, including the extra levels.
+    pub fn num_levels(&self) -> usize {
+        self.in_channels.len() + self.extra_levels.map_or(0, |extra| extra.num_extra)
+    }
+
+    /// Initialize a new [FPN](Fpn) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Fpn<B> {
+        let c = self.out_channels;
+        let conv = |in_channels, kernel_size, stride, padding| {
+            Conv2dConfig::new([in_channels, c], [kernel_size, kernel_size])
+                .with_stride([stride, stride])
+                .with_padding(PaddingConfig2d::Explicit(padding, padding))
+                .init(device)
+        };
+
+        let (num_extra, extra_source_offset) = match self.extra_levels {
+            Some(FpnExtraLevels {
+                num_extra,
+                start_from,
+            }) if num_extra > 0 => match start_from {
+                ExtraLevelSource::P5 | ExtraLevelSource::MaxPool => (num_extra, 0),
+                ExtraLevelSource::P4 => (num_extra, 1),
+            },
+            _ => (0, 0),
+        };
+        let extra_convs = match self.extra_levels {
+            Some(FpnExtraLevels {
+                start_from: ExtraLevelSource::MaxPool,
+                ..
+            }) => Vec::new(),
+            _ => (0..num_extra + extra_source_offset)
+                .map(|_| conv(c, 3, 2, 1))
+                .collect(),
+        };
+
+        Fpn {
+            lateral_convs: self
+                .in_channels
+                .iter()
+                .map(|&in_channels| conv(in_channels, 1, 1, 0))
+                .collect(),
+            output_convs: (0..self.in_channels.len())
+                .map(|_| conv(c, 3, 1, 1))
+                .collect(),
+            extra_convs,
+            activation: Relu::new(),
+            num_extra,
+            extra_source_offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    /// Backbone feature maps P2 to P5 of a `[1, 3, size, size]` input.
+    fn features(size: usize, device: &Device<TestBackend>) -> Vec<Tensor<TestBackend, 4>> {
+        [8, 16, 32, 64]
+            .into_iter()
+            .enumerate()
+            .map(|(i, channels)| {
+                let s = size >> (i + 2);
+                Tensor::ones([1, channels, s, s], device)
+            })
+            .collect()
+    }
+
+    fn check_levels(config: FpnConfig, num_levels: usize) {
+        let device = Default::default();
+        let fpn = config.init::<TestBackend>(&device);
+        assert_eq!(fpn.num_levels(), num_levels);
+
+        let outputs = fpn.forward(features(256, &device));
+        assert_eq!(outputs.len(), num_levels);
+        for (i, output) in outputs.iter().enumerate() {
+            let s = 256 >> (i + 2);
+            assert_eq!(output.dims(), [1, 16, s, s]);
+        }
+    }
+
+    #[test]
+    fn fpn_without_extra_levels() {
+        let config = FpnConfig::new(vec![8, 16, 32, 64], 16);
+        assert_eq!(config.num_levels(), 4);
+
+        check_levels(config, 4);
+    }
+
+    #[test]
+    fn fpn_extra_levels_from_p5() {
+        let config = FpnConfig::new(vec![8, 16, 32, 64], 16)
+            .with_extra_levels(FpnExtraLevels::new(2, ExtraLevelSource::P5));
+        assert_eq!(config.num_levels(), 6);
+
+        // P2 to P7, with P6 and P7 at 1/64 and 1/128 of the input resolution
+        check_levels(config, 6);
+    }
+
+    #[test]
+    fn fpn_extra_levels_from_p4() {
+        let config = FpnConfig::new(vec![8, 16, 32, 64], 16)
+            .with_extra_levels(FpnExtraLevels::new(2, ExtraLevelSource::P4));
+        let fpn = config.init::<TestBackend>(&Default::default());
+        // P4 -> P5 resolution, then P6 and P7
+        assert_eq!(fpn.extra_convs.len(), 3);
+
+        check_levels(config, 6);
+    }
+
+    #[test]
+    fn fpn_extra_levels_max_pool() {
+        let config = FpnConfig::new(vec![8, 16, 32, 64], 16)
+            .with_extra_levels(FpnExtraLevels::new(1, ExtraLevelSource::MaxPool));
+        let fpn = config.init::<TestBackend>(&Default::default());
+        assert!(fpn.extra_convs.is_empty());
+
+        check_levels(config, 5);
+    }
+
+    #[test]
+    fn retinanet_fpn() {
+        let device = Default::default();
+        let fpn = FpnConfig::retinanet(vec![8, 16, 32, 64]).init::<TestBackend>(&device);
+
+        let outputs = fpn.forward(features(256, &device));
+        assert_eq!(outputs.len(), 6);
+        assert_eq!(outputs[4].dims(), [1, 256, 4, 4]);
+        assert_eq!(outputs[5].dims(), [1, 256, 2, 2]);
+    }
+
+    #[test]
+    #[should_panic = "the FPN expects one feature map per level"]
+    fn fpn_wrong_number_of_features() {
+        let device = Default::default();
+        let fpn = FpnConfig::new(vec![8, 16, 32], 16).init::<TestBackend>(&device);
+
+        fpn.forward(features(256, &device));
+    }
+}
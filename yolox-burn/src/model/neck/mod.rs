@@ -0,0 +1,11 @@
+mod align;
+mod dcn;
+mod fpn;
+mod pan;
+mod rfpn;
+
+pub use align::*;
+pub use dcn::*;
+pub use fpn::*;
+pub use pan::*;
+pub use rfpn::*;
@@ -0,0 +1,342 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    tensor::{
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Tensor,
+    },
+};
+
+use super::{FeatureAlignModule, FeatureAlignModuleConfig};
+use crate::model::{
+    blocks::{expand, BaseConv, BaseConvConfig, Conv, ConvConfig},
+    bottleneck::{CspBottleneck, CspBottleneckConfig},
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
+};
+
+/// Feature pyramid maps with strides 8, 16 and 32.
+pub struct FpnFeatures<B: Backend>(pub Tensor<B, 4>, pub Tensor<B, 4>, pub Tensor<B, 4>);
+
+/// [Path Aggregation Network](https://arxiv.org/abs/1803.01534) neck, which fuses three backbone
+/// feature maps with a top-down path followed by a bottom-up path.
+#[derive(Module, Debug)]
+pub struct PanNeck<B: Backend> {
+    lateral_conv0: BaseConv<B>,
+    c3_n3: CspBottleneck<B>,
+    c3_n4: CspBottleneck<B>,
+    c3_p3: CspBottleneck<B>,
+    c3_p4: CspBottleneck<B>,
+    reduce_conv1: BaseConv<B>,
+    bu_conv1: Conv<B>, // bottom-up conv
+    bu_conv2: Conv<B>, // bottom-up conv
+    /// Optional alignment of the two top-down merges, then of the two bottom-up merges.
+    aligns: Vec<FeatureAlignModule<B>>,
+}
+
+impl<B: Backend> PanNeck<B> {
+    /// Fuse the backbone feature maps.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels[i], H / 2^i, W / 2^i]` for each level `i`
+    ///   - output: `[batch_size, out_channels * 2^i, H / 2^i, W / 2^i]` for each level `i`
+    pub fn forward(&self, features: [Tensor<B, 4>; 3]) -> FpnFeatures<B> {
+        self.fuse(features, |i, x, lateral| match self.aligns.get(i) {
+            Some(align) => {
+                let guide = Tensor::cat(vec![x.clone(), lateral], 1);
+                align.forward_guided(x, guide)
+            }
+            None => x,
+        })
+    }
+
+    /// Fuse the backbone feature maps, applying `align(merge_index, x, lateral)` to the upsampled
+    /// (top-down) or downsampled (bottom-up) features `x` before they are concatenated with the
+    /// same-scale `lateral` features. The merges are indexed in order: two top-down then two
+    /// bottom-up.
+    pub(super) fn fuse<F>(&self, features: [Tensor<B, 4>; 3], align: F) -> FpnFeatures<B>
+    where
+        F: Fn(usize, Tensor<B, 4>, Tensor<B, 4>) -> Tensor<B, 4>,
+    {
+        fn upsample<B: Backend>(x_in: Tensor<B, 4>, scale: usize) -> Tensor<B, 4> {
+            let [_, _, h, w] = x_in.dims();
+            interpolate(
+                x_in,
+                [h * scale, w * scale],
+                InterpolateOptions::new(InterpolateMode::Nearest),
+            )
+        }
+
+        let [x2, x1, x0] = features;
+
+        // Top-down path
+        let fpn_out0 = self.lateral_conv0.forward(x0);
+        let f_out0 = upsample(fpn_out0.clone(), 2);
+        let f_out0 = align(0, f_out0, x1.clone());
+        let f_out0 = Tensor::cat(vec![f_out0, x1], 1);
+        let f_out0 = self.c3_p4.forward(f_out0);
+
+        let fpn_out1 = self.reduce_conv1.forward(f_out0);
+        let f_out1 = upsample(fpn_out1.clone(), 2);
+        let f_out1 = align(1, f_out1, x2.clone());
+        let f_out1 = Tensor::cat(vec![f_out1, x2], 1);
+        let pan_out2 = self.c3_p3.forward(f_out1);
+
+        // Bottom-up path
+        let p_out1 = self.bu_conv2.forward(pan_out2.clone());
+        let p_out1 = align(2, p_out1, fpn_out1.clone());
+        let p_out1 = Tensor::cat(vec![p_out1, fpn_out1], 1);
+        let pan_out1 = self.c3_n3.forward(p_out1);
+
+        let p_out0 = self.bu_conv1.forward(pan_out1.clone());
+        let p_out0 = align(3, p_out0, fpn_out0.clone());
+        let p_out0 = Tensor::cat(vec![p_out0, fpn_out0], 1);
+        let pan_out0 = self.c3_n4.forward(p_out0);
+
+        FpnFeatures(pan_out2, pan_out1, pan_out0)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for PanNeck<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            lateral_conv0: self.lateral_conv0.freeze_batch_norms(),
+            c3_n3: self.c3_n3.freeze_batch_norms(),
+            c3_n4: self.c3_n4.freeze_batch_norms(),
+            c3_p3: self.c3_p3.freeze_batch_norms(),
+            c3_p4: self.c3_p4.freeze_batch_norms(),
+            reduce_conv1: self.reduce_conv1.freeze_batch_norms(),
+            bu_conv1: self.bu_conv1.freeze_batch_norms(),
+            bu_conv2: self.bu_conv2.freeze_batch_norms(),
+            aligns: self.aligns,
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for PanNeck<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            lateral_conv0: self.lateral_conv0.sync_batch_norms(),
+            c3_n3: self.c3_n3.sync_batch_norms(),
+            c3_n4: self.c3_n4.sync_batch_norms(),
+            c3_p3: self.c3_p3.sync_batch_norms(),
+            c3_p4: self.c3_p4.sync_batch_norms(),
+            reduce_conv1: self.reduce_conv1.sync_batch_norms(),
+            bu_conv1: self.bu_conv1.sync_batch_norms(),
+            bu_conv2: self.bu_conv2.sync_batch_norms(),
+            aligns: self.aligns,
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for PanNeck<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.lateral_conv0.set_bn_momentum(momentum);
+        self.c3_n3.set_bn_momentum(momentum);
+        self.c3_n4.set_bn_momentum(momentum);
+        self.c3_p3.set_bn_momentum(momentum);
+        self.c3_p4.set_bn_momentum(momentum);
+        self.reduce_conv1.set_bn_momentum(momentum);
+        self.bu_conv1.set_bn_momentum(momentum);
+        self.bu_conv2.set_bn_momentum(momentum);
+    }
+}
+
+/// [PAN neck](PanNeck) configuration.
+pub struct PanNeckConfig {
+    in_channels: Vec<usize>,
+    out_channels: usize,
+    depth_multiple: f64,
+    width_multiple: f64,
+    depthwise: bool,
+    use_feature_align: bool,
+}
+
+impl PanNeckConfig {
+    /// Create a new instance of the PAN neck [config](PanNeckConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the three input feature maps, from the highest to
+    ///   the lowest resolution, before applying the width multiple.
+    /// * `out_channels` - Number of channels of the highest resolution output feature map, before
+    ///   applying the width multiple. The number of channels doubles for each following level.
+    /// * `depth_multiple` - Scaling factor of the number of bottleneck blocks.
+    /// * `width_multiple` - Scaling factor of the number of channels.
+    ///
+    /// With the YOLOX defaults (`in_channels = [256, 512, 1024]` and `out_channels = 256`), the
+    /// output feature maps have the same number of channels as the inputs.
+    pub fn new(
+        in_channels: Vec<usize>,
+        out_channels: usize,
+        depth_multiple: f64,
+        width_multiple: f64,
+    ) -> Self {
+        assert_eq!(
+            in_channels.len(),
+            3,
+            "the PAN neck expects exactly three input feature maps"
+        );
+
+        Self {
+            in_channels,
+            out_channels,
+            depth_multiple,
+            width_multiple,
+            depthwise: false,
+            use_feature_align: false,
+        }
+    }
+
+    /// Use depthwise separable convolutions for the bottom-up path and bottleneck blocks.
+    pub fn with_depthwise(mut self, depthwise: bool) -> Self {
+        self.depthwise = depthwise;
+        self
+    }
+
+    /// Align the resampled features with a [feature alignment module](FeatureAlignModule) before
+    /// each merge (defaults to false).
+    pub fn with_feature_align(mut self, use_feature_align: bool) -> Self {
+        self.use_feature_align = use_feature_align;
+        self
+    }
+
+    /// Number of channels of each output feature map.
+    pub fn out_channels(&self) -> [usize; 3] {
+        let c = expand(self.out_channels, self.width_multiple);
+        [c, c * 2, c * 4]
+    }
+
+    /// Initialize a new [PAN neck](PanNeck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> PanNeck<B> {
+        let width = self.width_multiple;
+        let depthwise = self.depthwise;
+        let in_channels: Vec<_> = self.in_channels.iter().map(|&c| expand(c, width)).collect();
+        let out_channels = self.out_channels();
+        let num_blocks = (3_f64 * self.depth_multiple).round() as usize;
+
+        let csp = |in_channels: usize, out_channels: usize| {
+            CspBottleneckConfig::new(in_channels, out_channels, num_blocks, 0.5, false, depthwise)
+        };
+
+        // Top-down path
+        let lateral_conv0 = BaseConvConfig::new(in_channels[2], out_channels[1], 1, 1, 1);
+        let c3_p4 = csp(in_channels[1] + out_channels[1], out_channels[1]);
+        let reduce_conv1 = BaseConvConfig::new(out_channels[1], out_channels[0], 1, 1, 1);
+        let c3_p3 = csp(in_channels[0] + out_channels[0], out_channels[0]);
+
+        // Bottom-up path
+        let bu_conv2 = ConvConfig::new(out_channels[0], out_channels[0], 3, 2, depthwise);
+        let c3_n3 = csp(2 * out_channels[0], out_channels[1]);
+        let bu_conv1 = ConvConfig::new(out_channels[1], out_channels[1], 3, 2, depthwise);
+        let c3_n4 = csp(2 * out_channels[1], out_channels[2]);
+
+        // (aligned, same-scale) channels of each merge
+        let merges = [
+            (out_channels[1], in_channels[1]),
+            (out_channels[0], in_channels[0]),
+            (out_channels[0], out_channels[0]),
+            (out_channels[1], out_channels[1]),
+        ];
+        let aligns = match self.use_feature_align {
+            true => merges
+                .iter()
+                .map(|&(c, lateral)| {
+                    FeatureAlignModuleConfig::new(c, 3)
+                        .with_guide_channels(c + lateral)
+                        .init(device)
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+
+        PanNeck {
+            lateral_conv0: lateral_conv0.init(device),
+            c3_n3: c3_n3.init(device),
+            c3_n4: c3_n4.init(device),
+            c3_p3: c3_p3.init(device),
+            c3_p4: c3_p4.init(device),
+            reduce_conv1: reduce_conv1.init(device),
+            bu_conv1: bu_conv1.init(device),
+            bu_conv2: bu_conv2.init(device),
+            aligns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::pafpn::PafpnConfig;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Random feature maps of a 64x64 image, with strides 8, 16 and 32.
+    fn features(channels: [usize; 3]) -> [Tensor<TestBackend, 4>; 3] {
+        let device = Default::default();
+        [(channels[0], 8), (channels[1], 4), (channels[2], 2)]
+            .map(|(c, size)| Tensor::random([1, c, size, size], Distribution::Default, &device))
+    }
+
+    fn dims(features: FpnFeatures<TestBackend>) -> [[usize; 4]; 3] {
+        [features.0.dims(), features.1.dims(), features.2.dims()]
+    }
+
+    #[test]
+    fn pan_neck_matches_pafpn_shapes() {
+        let device = Default::default();
+        // YOLOX-Nano, YOLOX-Tiny and YOLOX-S
+        for (depth, width) in [(0.33, 0.25), (0.33, 0.375), (0.33, 0.5)] {
+            let pafpn = PafpnConfig::new(depth, width, false).init::<TestBackend>(&device);
+            let neck_config = PanNeckConfig::new(vec![256, 512, 1024], 256, depth, width);
+            let neck = neck_config.init(&device);
+            let channels = [256, 512, 1024].map(|c| expand(c, width));
+
+            let expected = dims(pafpn.forward(Tensor::zeros([1, 3, 64, 64], &device)));
+            let out = dims(neck.forward(features(channels)));
+
+            assert_eq!(out, expected);
+            assert_eq!(neck_config.out_channels(), channels);
+            assert_eq!(
+                out,
+                [
+                    [1, channels[0], 8, 8],
+                    [1, channels[1], 4, 4],
+                    [1, channels[2], 2, 2]
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn pan_neck_custom_channels() {
+        // EfficientNet-B0 feature maps
+        let neck = PanNeckConfig::new(vec![40, 112, 320], 96, 0.33, 1.)
+            .with_depthwise(true)
+            .init::<TestBackend>(&Default::default());
+
+        let out = dims(neck.forward(features([40, 112, 320])));
+
+        assert_eq!(out, [[1, 96, 8, 8], [1, 192, 4, 4], [1, 384, 2, 2]]);
+    }
+
+    #[test]
+    fn pan_neck_feature_align_shapes() {
+        let neck = PanNeckConfig::new(vec![32, 64, 128], 32, 0.33, 1.)
+            .with_feature_align(true)
+            .init::<TestBackend>(&Default::default());
+
+        let out = dims(neck.forward(features([32, 64, 128])));
+
+        assert_eq!(neck.aligns.len(), 4);
+        assert_eq!(out, [[1, 32, 8, 8], [1, 64, 4, 4], [1, 128, 2, 2]]);
+    }
+
+    #[test]
+    #[should_panic = "the PAN neck expects exactly three input feature maps"]
+    fn pan_neck_invalid_levels() {
+        PanNeckConfig::new(vec![256, 512], 256, 0.33, 0.25);
+    }
+}
@@ -0,0 +1,214 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    tensor::{
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Tensor,
+    },
+};
+
+use crate::model::{
+    blocks::{BaseConv, BaseConvConfig},
+    raft_lite::{ConvGru, ConvGruConfig},
+};
+
+/// Feature pyramid network with a recurrent temporal state, for video object detection.
+///
+/// Each frame goes through a regular top-down feature pyramid. The fused features of each level
+/// then update a per-level hidden state with a [convolutional GRU](ConvGru) step, and the hidden
+/// state is fused back into the output features. The hidden states are carried from one call of
+/// [forward_frame](RecurrentFpn::forward_frame) to the next, and should be
+/// [reset](RecurrentFpn::reset_state) between video clips.
+///
+/// The hidden states are not parameters and are not saved with the module record.
+#[derive(Module, Debug)]
+pub struct RecurrentFpn<B: Backend> {
+    lateral_convs: Vec<BaseConv<B>>,
+    smooth_convs: Vec<BaseConv<B>>,
+    grus: Vec<ConvGru<B>>,
+    output_convs: Vec<BaseConv<B>>,
+    hidden: Vec<Tensor<B, 4>>,
+    temporal_channels: usize,
+}
+
+impl<B: Backend> RecurrentFpn<B> {
+    /// Enhance the feature maps of the current frame with the temporal context, and update the
+    /// hidden states.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels[i], H / 2^i, W / 2^i]` for each level `i`, from the
+    ///     highest to the lowest resolution
+    ///   - output: `[batch_size, hidden_channels, H / 2^i, W / 2^i]` for each level `i`
+    pub fn forward_frame(&mut self, features: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 4>> {
+        assert_eq!(
+            features.len(),
+            self.lateral_convs.len(),
+            "the recurrent FPN expects one feature map per level"
+        );
+
+        // Top-down path
+        let mut laterals: Vec<_> = features
+            .into_iter()
+            .zip(self.lateral_convs.iter())
+            .map(|(x, conv)| conv.forward(x))
+            .collect();
+        for i in (0..laterals.len() - 1).rev() {
+            let [_, _, h, w] = laterals[i].dims();
+            let upsampled = interpolate(
+                laterals[i + 1].clone(),
+                [h, w],
+                InterpolateOptions::new(InterpolateMode::Nearest),
+            );
+            laterals[i] = laterals[i].clone() + upsampled;
+        }
+
+        // Temporal update
+        let hidden = core::mem::take(&mut self.hidden);
+        let mut hidden = hidden.into_iter();
+        let mut outputs = Vec::with_capacity(laterals.len());
+        for (i, x) in laterals.into_iter().enumerate() {
+            let x = self.smooth_convs[i].forward(x);
+            let [n, _, h, w] = x.dims();
+
+            // Start from an empty temporal context on the first frame (or if the input size changed)
+            let h_prev = match hidden.next() {
+                Some(h_prev) if h_prev.dims() == [n, self.temporal_channels, h, w] => h_prev,
+                _ => Tensor::zeros([n, self.temporal_channels, h, w], &x.device()),
+            };
+            let h_t = self.grus[i].forward(h_prev, x.clone());
+
+            outputs.push(self.output_convs[i].forward(Tensor::cat(vec![x, h_t.clone()], 1)));
+            self.hidden.push(h_t);
+        }
+
+        outputs
+    }
+
+    /// Clear the temporal context, so that the next frame is processed as the first frame of a
+    /// video clip.
+    pub fn reset_state(&mut self) {
+        self.hidden.clear();
+    }
+}
+
+/// [Recurrent FPN](RecurrentFpn) configuration.
+pub struct RecurrentFpnConfig {
+    in_channels: Vec<usize>,
+    hidden_channels: usize,
+    temporal_channels: usize,
+}
+
+impl RecurrentFpnConfig {
+    /// Create a new instance of the recurrent FPN [config](RecurrentFpnConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the input feature maps, from the highest to the
+    ///   lowest resolution.
+    /// * `hidden_channels` - Number of channels of the pyramid (and output) feature maps.
+    /// * `temporal_channels` - Number of channels of the hidden state of each level.
+    pub fn new(in_channels: Vec<usize>, hidden_channels: usize, temporal_channels: usize) -> Self {
+        assert!(
+            !in_channels.is_empty(),
+            "the recurrent FPN expects at least one input feature map"
+        );
+
+        Self {
+            in_channels,
+            hidden_channels,
+            temporal_channels,
+        }
+    }
+
+    /// Initialize a new [recurrent FPN](RecurrentFpn) module, with an empty temporal context.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> RecurrentFpn<B> {
+        let c = self.hidden_channels;
+        let t = self.temporal_channels;
+        let num_levels = self.in_channels.len();
+
+        RecurrentFpn {
+            lateral_convs: self
+                .in_channels
+                .iter()
+                .map(|&in_channels| BaseConvConfig::new(in_channels, c, 1, 1, 1).init(device))
+                .collect(),
+            smooth_convs: (0..num_levels)
+                .map(|_| BaseConvConfig::new(c, c, 3, 1, 1).init(device))
+                .collect(),
+            grus: (0..num_levels)
+                .map(|_| ConvGruConfig::new(c, t, 3).init(device))
+                .collect(),
+            output_convs: (0..num_levels)
+                .map(|_| BaseConvConfig::new(c + t, c, 1, 1, 1).init(device))
+                .collect(),
+            hidden: Vec::new(),
+            temporal_channels: t,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    fn features() -> Vec<Tensor<TestBackend, 4>> {
+        let device = Default::default();
+
+        [(8, 16), (16, 8), (32, 4)]
+            .into_iter()
+            .map(|(c, s)| Tensor::random([2, c, s, s], Distribution::Default, &device))
+            .collect()
+    }
+
+    #[test]
+    fn recurrent_fpn_shapes() {
+        let device = Default::default();
+        let mut fpn = RecurrentFpnConfig::new(vec![8, 16, 32], 12, 6).init::<TestBackend>(&device);
+
+        let outputs = fpn.forward_frame(features());
+
+        assert_eq!(outputs.len(), 3);
+        for (output, size) in outputs.iter().zip([16, 8, 4]) {
+            assert_eq!(output.dims(), [2, 12, size, size]);
+        }
+        assert_eq!(fpn.hidden[0].dims(), [2, 6, 16, 16]);
+    }
+
+    #[test]
+    fn recurrent_fpn_temporal_dependency() {
+        let device = Default::default();
+        let mut fpn = RecurrentFpnConfig::new(vec![8, 16, 32], 12, 6).init::<TestBackend>(&device);
+        let frame = features();
+
+        let first = fpn.forward_frame(frame.clone());
+        let second = fpn.forward_frame(frame.clone());
+
+        // The same frame gives a different output with the temporal context of the first frame
+        let difference = (first[0].clone() - second[0].clone())
+            .abs()
+            .max()
+            .into_scalar();
+        assert!(difference > 1e-4);
+
+        // Without the temporal context, the frame is processed as the first frame
+        fpn.reset_state();
+        let reset = fpn.forward_frame(frame);
+        for (reset, first) in reset.into_iter().zip(first) {
+            reset.into_data().assert_approx_eq(&first.into_data(), 5);
+        }
+    }
+
+    #[test]
+    #[should_panic = "the recurrent FPN expects one feature map per level"]
+    fn recurrent_fpn_wrong_levels() {
+        let device = Default::default();
+        let mut fpn = RecurrentFpnConfig::new(vec![8, 16, 32], 12, 6).init::<TestBackend>(&device);
+
+        fpn.forward_frame(features().into_iter().take(2).collect());
+    }
+}
@@ -0,0 +1,862 @@
+use alloc::vec::Vec;
+use burn::{
+    config::Config,
+    module::{Module, Param},
+    nn::{BatchNorm, BatchNormConfig},
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+/// Batch normalization with fixed statistics.
+///
+/// The affine parameters and running statistics are stored as [parameters](Param), but the
+/// running statistics are never updated: the normalization behaves the same during training and
+/// inference, which is preferable when fine-tuning with small batches.
+#[derive(Module, Debug)]
+pub struct FrozenBatchNorm<B: Backend> {
+    pub weight: Param<Tensor<B, 1>>,
+    pub bias: Param<Tensor<B, 1>>,
+    pub running_mean: Param<Tensor<B, 1>>,
+    pub running_var: Param<Tensor<B, 1>>,
+    epsilon: f64,
+}
+
+impl<B: Backend> FrozenBatchNorm<B> {
+    /// Create a frozen batch normalization from the affine parameters and the running statistics
+    /// of a [batch normalization](BatchNorm) module.
+    pub fn from_batch_norm<const D: usize>(bn: BatchNorm<B, D>) -> Self {
+        Self {
+            weight: bn.gamma,
+            bias: bn.beta,
+            running_mean: Param::from_tensor(bn.running_mean.value()).set_require_grad(false),
+            running_var: Param::from_tensor(bn.running_var.value()).set_require_grad(false),
+            epsilon: bn.epsilon,
+        }
+    }
+
+    /// Normalize the input with the running statistics.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, channels, ...]`
+    ///   - output: `[batch_size, channels, ...]`
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let [channels] = self.weight.dims();
+        let mut shape = [1; D];
+        shape[1] = channels;
+
+        let scale = self.weight.val() / self.running_var.val().add_scalar(self.epsilon).sqrt();
+        let shift = self.bias.val() - self.running_mean.val() * scale.clone();
+
+        x * scale.reshape(shape) + shift.reshape(shape)
+    }
+}
+
+/// Normalization layer of the convolution blocks.
+#[derive(Module, Debug)]
+pub enum Normalization<B: Backend> {
+    /// Regular batch normalization, with statistics updated during training.
+    Batch(BatchNorm<B, 2>),
+    /// Batch normalization with [fixed statistics](FrozenBatchNorm).
+    Frozen(FrozenBatchNorm<B>),
+    /// Batch normalization with [statistics synchronized across devices](SyncBatchNorm).
+    Sync(SyncBatchNorm<B>),
+}
+
+impl<B: Backend> Normalization<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        match self {
+            Self::Batch(bn) => bn.forward(x),
+            Self::Frozen(bn) => bn.forward(x),
+            Self::Sync(bn) => bn.forward(x),
+        }
+    }
+}
+
+/// Modules whose [batch normalization](BatchNorm) layers can be replaced by
+/// [frozen batch normalization](FrozenBatchNorm) layers.
+///
+/// Burn modules can only be traversed to map their tensors, not to change the type of their
+/// sub-modules, so each module explicitly forwards the conversion to its children.
+pub trait FreezeBatchNorms<B: Backend>: Module<B> {
+    /// Replace all batch normalization layers with frozen batch normalization layers.
+    fn freeze_batch_norms(self) -> Self;
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for Normalization<B> {
+    fn freeze_batch_norms(self) -> Self {
+        match self {
+            Self::Batch(bn) | Self::Sync(SyncBatchNorm { bn }) => {
+                Self::Frozen(FrozenBatchNorm::from_batch_norm(bn))
+            }
+            frozen => frozen,
+        }
+    }
+}
+
+impl<B: Backend, M: FreezeBatchNorms<B>> FreezeBatchNorms<B> for Vec<M> {
+    fn freeze_batch_norms(self) -> Self {
+        self.into_iter().map(M::freeze_batch_norms).collect()
+    }
+}
+
+impl<B: Backend, M: FreezeBatchNorms<B>> FreezeBatchNorms<B> for Option<M> {
+    fn freeze_batch_norms(self) -> Self {
+        self.map(M::freeze_batch_norms)
+    }
+}
+
+/// Replace all [batch normalization](BatchNorm) layers of a model with
+/// [frozen batch normalization](FrozenBatchNorm) layers, keeping their current running statistics.
+pub fn freeze_batch_norms<B: Backend, M: FreezeBatchNorms<B>>(model: M) -> M {
+    model.freeze_batch_norms()
+}
+
+/// Modules whose [batch normalization](BatchNorm) momentum can be updated in place.
+///
+/// As for [FreezeBatchNorms], each module explicitly forwards the update to its children.
+pub trait SetBatchNormMomentum<B: Backend>: Module<B> {
+    /// Set the momentum of all batch normalization layers.
+    fn set_bn_momentum(&mut self, momentum: f64);
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for Normalization<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        match self {
+            Self::Batch(bn) | Self::Sync(SyncBatchNorm { bn }) => bn.momentum = momentum,
+            // The statistics of frozen batch normalization layers are never updated
+            Self::Frozen(_) => {}
+        }
+    }
+}
+
+impl<B: Backend, M: SetBatchNormMomentum<B>> SetBatchNormMomentum<B> for Vec<M> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.iter_mut().for_each(|m| m.set_bn_momentum(momentum));
+    }
+}
+
+impl<B: Backend, M: SetBatchNormMomentum<B>> SetBatchNormMomentum<B> for Option<M> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        if let Some(m) = self {
+            m.set_bn_momentum(momentum);
+        }
+    }
+}
+
+/// Set the momentum of all [batch normalization](BatchNorm) layers of a model.
+///
+/// The momentum is the weight of the batch statistics when updating the running statistics.
+pub fn set_bn_momentum<B: Backend, M: SetBatchNormMomentum<B>>(model: &mut M, momentum: f64) {
+    model.set_bn_momentum(momentum);
+}
+
+/// Batch normalization whose statistics are computed over the batches of all the devices in
+/// data-parallel training.
+///
+/// Burn does not provide collective operations, so the synchronization happens when the replicas
+/// of a batch split across devices are normalized together with
+/// [forward_replicas](SyncBatchNorm::forward_replicas): the per-channel sums are reduced on the
+/// device of the parameters and the global statistics are sent back to each device. On a single
+/// device, [forward](SyncBatchNorm::forward) is exactly the regular
+/// [batch normalization](BatchNorm).
+#[derive(Module, Debug)]
+pub struct SyncBatchNorm<B: Backend> {
+    pub bn: BatchNorm<B, 2>,
+}
+
+impl<B: Backend> SyncBatchNorm<B> {
+    /// Create a synchronized batch normalization with the parameters and running statistics of a
+    /// [batch normalization](BatchNorm) module.
+    pub fn from_batch_norm(bn: BatchNorm<B, 2>) -> Self {
+        Self { bn }
+    }
+
+    /// Normalize the input of a single device.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.bn.forward(x)
+    }
+
+    /// Normalize the inputs of all the devices with the statistics of the whole batch, updating
+    /// the running statistics during training.
+    ///
+    /// # Shapes
+    ///   - inputs: `[batch_size_i, channels, height_i, width_i]` for each device `i`
+    ///   - output: `[batch_size_i, channels, height_i, width_i]` for each device `i`
+    pub fn forward_replicas(&self, inputs: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 4>> {
+        // The running statistics are used for inference
+        if inputs.len() <= 1 || !B::ad_enabled() {
+            return inputs.into_iter().map(|x| self.forward(x)).collect();
+        }
+
+        let device = self.bn.gamma.device();
+        let [channels] = self.bn.gamma.dims();
+
+        // All-reduce of the per-channel sums
+        let mut count = 0;
+        let mut sum = Tensor::<B, 1>::zeros([channels], &device);
+        let mut sum_sq = Tensor::<B, 1>::zeros([channels], &device);
+        for x in inputs.iter() {
+            let [n, c, h, w] = x.dims();
+            let x = x.clone().swap_dims(0, 1).reshape([c, n * h * w]);
+
+            count += n * h * w;
+            sum = sum + x.clone().sum_dim(1).reshape([c]).to_device(&device);
+            sum_sq = sum_sq + x.powf_scalar(2.).sum_dim(1).reshape([c]).to_device(&device);
+        }
+        let mean = sum.div_scalar(count as f64);
+        let var = sum_sq.div_scalar(count as f64) - mean.clone().powf_scalar(2.);
+
+        let momentum = self.bn.momentum;
+        let running_mean = self.bn.running_mean.value_sync().to_device(&device);
+        let running_var = self.bn.running_var.value_sync().to_device(&device);
+        self.bn.running_mean.update(
+            running_mean.mul_scalar(1. - momentum) + mean.clone().detach().mul_scalar(momentum),
+        );
+        self.bn.running_var.update(
+            running_var.mul_scalar(1. - momentum) + var.clone().detach().mul_scalar(momentum),
+        );
+
+        let scale = self.bn.gamma.val() / var.add_scalar(self.bn.epsilon).sqrt();
+        let shift = self.bn.beta.val() - mean * scale.clone();
+
+        inputs
+            .into_iter()
+            .map(|x| {
+                let device = x.device();
+                let scale = scale
+                    .clone()
+                    .to_device(&device)
+                    .reshape([1, channels, 1, 1]);
+                let shift = shift
+                    .clone()
+                    .to_device(&device)
+                    .reshape([1, channels, 1, 1]);
+
+                x * scale + shift
+            })
+            .collect()
+    }
+}
+
+/// Modules whose [batch normalization](BatchNorm) layers can be replaced by
+/// [synchronized batch normalization](SyncBatchNorm) layers.
+///
+/// As for [FreezeBatchNorms], each module explicitly forwards the conversion to its children.
+pub trait SyncBatchNorms<B: Backend>: Module<B> {
+    /// Replace all batch normalization layers with synchronized batch normalization layers.
+    fn sync_batch_norms(self) -> Self;
+}
+
+impl<B: Backend> SyncBatchNorms<B> for Normalization<B> {
+    fn sync_batch_norms(self) -> Self {
+        match self {
+            Self::Batch(bn) => Self::Sync(SyncBatchNorm::from_batch_norm(bn)),
+            // The statistics of frozen batch normalization layers are never updated
+            other => other,
+        }
+    }
+}
+
+impl<B: Backend, M: SyncBatchNorms<B>> SyncBatchNorms<B> for Vec<M> {
+    fn sync_batch_norms(self) -> Self {
+        self.into_iter().map(M::sync_batch_norms).collect()
+    }
+}
+
+impl<B: Backend, M: SyncBatchNorms<B>> SyncBatchNorms<B> for Option<M> {
+    fn sync_batch_norms(self) -> Self {
+        self.map(M::sync_batch_norms)
+    }
+}
+
+/// Replace all [batch normalization](BatchNorm) layers of a model with
+/// [synchronized batch normalization](SyncBatchNorm) layers, keeping their parameters and running
+/// statistics.
+pub fn convert_sync_batchnorm<B: Backend, M: SyncBatchNorms<B>>(model: M) -> M {
+    model.sync_batch_norms()
+}
+
+/// Batch normalization whose momentum is decayed during the first training steps.
+///
+/// The momentum starts high so that the running statistics quickly move away from their
+/// initialization, and is decayed logarithmically (i.e., geometrically) to the target momentum
+/// over `warmup_steps` steps. [step](MomentumScheduledBatchNorm::step) should be called after each
+/// training iteration.
+#[derive(Module, Debug)]
+pub struct MomentumScheduledBatchNorm<B: Backend> {
+    bn: BatchNorm<B, 2>,
+    initial_momentum: f64,
+    target_momentum: f64,
+    warmup_steps: usize,
+    current_step: usize,
+}
+
+impl<B: Backend> MomentumScheduledBatchNorm<B> {
+    /// Normalize the input, updating the running statistics during training.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.bn.forward(x)
+    }
+
+    /// Advance the momentum schedule by one training step.
+    pub fn step(&mut self) {
+        self.current_step = (self.current_step + 1).min(self.warmup_steps);
+        self.bn.momentum = self.momentum();
+    }
+
+    /// Momentum at the current step.
+    pub fn momentum(&self) -> f64 {
+        if self.current_step >= self.warmup_steps {
+            return self.target_momentum;
+        }
+
+        let progress = self.current_step as f64 / self.warmup_steps as f64;
+        self.initial_momentum * (self.target_momentum / self.initial_momentum).powf(progress)
+    }
+}
+
+/// [Momentum scheduled batch normalization](MomentumScheduledBatchNorm) configuration.
+#[derive(Config)]
+pub struct MomentumScheduledBatchNormConfig {
+    /// Number of features (channels).
+    num_features: usize,
+    /// Number of steps to decay the momentum from the initial to the target value.
+    warmup_steps: usize,
+    /// Momentum at the first step.
+    #[config(default = 0.5)]
+    initial_momentum: f64,
+    /// Momentum after the warmup.
+    #[config(default = 0.03)]
+    target_momentum: f64,
+    /// Value added to the variance for numerical stability.
+    #[config(default = 1e-3)]
+    epsilon: f64,
+}
+
+impl MomentumScheduledBatchNormConfig {
+    /// Initialize a new [momentum scheduled batch normalization](MomentumScheduledBatchNorm)
+    /// module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> MomentumScheduledBatchNorm<B> {
+        assert!(
+            self.initial_momentum > 0. && self.target_momentum > 0.,
+            "batch normalization momentum should be positive"
+        );
+        let bn = BatchNormConfig::new(self.num_features)
+            .with_epsilon(self.epsilon)
+            .with_momentum(self.initial_momentum)
+            .init(device);
+
+        let mut module = MomentumScheduledBatchNorm {
+            bn,
+            initial_momentum: self.initial_momentum,
+            target_momentum: self.target_momentum,
+            warmup_steps: self.warmup_steps,
+            current_step: 0,
+        };
+        // Without warmup, the target momentum is used right away
+        module.bn.momentum = module.momentum();
+
+        module
+    }
+}
+
+/// Normalize each channel of each sample over its spatial (or sequence) dimensions, then apply
+/// the optional per-channel affine transform.
+fn instance_norm<B: Backend, const D: usize>(
+    x: Tensor<B, D>,
+    gamma: Option<&Param<Tensor<B, 1>>>,
+    beta: Option<&Param<Tensor<B, 1>>>,
+    epsilon: f64,
+) -> Tensor<B, D> {
+    let dims = x.dims();
+    let (n, c) = (dims[0], dims[1]);
+    let len = dims[2..].iter().product();
+
+    let flat = x.reshape([n, c, len]);
+    let mean = flat.clone().mean_dim(2);
+    let centered = flat - mean;
+    // Biased variance, as in batch normalization
+    let var = centered.clone().powf_scalar(2.).mean_dim(2);
+    let mut x = centered / var.add_scalar(epsilon).sqrt();
+
+    if let (Some(gamma), Some(beta)) = (gamma, beta) {
+        x = x * gamma.val().reshape([1, c, 1]) + beta.val().reshape([1, c, 1]);
+    }
+
+    x.reshape(dims)
+}
+
+/// [Instance normalization](https://arxiv.org/abs/1607.08022) of sequences.
+///
+/// Each channel of each sample is normalized over the sequence dimension, unlike
+/// [batch normalization](BatchNorm) which normalizes over the batch.
+#[derive(Module, Debug)]
+pub struct InstanceNorm1d<B: Backend> {
+    gamma: Option<Param<Tensor<B, 1>>>,
+    beta: Option<Param<Tensor<B, 1>>>,
+    epsilon: f64,
+}
+
+impl<B: Backend> InstanceNorm1d<B> {
+    /// Normalize the input.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, channels, length]`
+    ///   - output: `[batch_size, channels, length]`
+    pub fn forward(&self, x: Tensor<B, 3>) -> Tensor<B, 3> {
+        instance_norm(x, self.gamma.as_ref(), self.beta.as_ref(), self.epsilon)
+    }
+}
+
+/// [1D instance normalization](InstanceNorm1d) configuration.
+pub struct InstanceNorm1dConfig {
+    num_features: usize,
+    epsilon: f64,
+    affine: bool,
+}
+
+impl InstanceNorm1dConfig {
+    /// Create a new instance of the 1D instance normalization [config](InstanceNorm1dConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `num_features`: Number of features (channels).
+    /// * `epsilon` - Value added to the variance for numerical stability (e.g., `1e-5`).
+    /// * `affine` - Whether to apply a learnable per-channel scale and shift.
+    pub fn new(num_features: usize, epsilon: f64, affine: bool) -> Self {
+        Self {
+            num_features,
+            epsilon,
+            affine,
+        }
+    }
+
+    /// Initialize a new [1D instance normalization](InstanceNorm1d) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> InstanceNorm1d<B> {
+        let (gamma, beta) = affine_params(self.num_features, self.affine, device);
+
+        InstanceNorm1d {
+            gamma,
+            beta,
+            epsilon: self.epsilon,
+        }
+    }
+}
+
+/// [Instance normalization](https://arxiv.org/abs/1607.08022) of feature maps.
+///
+/// Same as the [1D instance normalization](InstanceNorm1d), over the height and width of the
+/// feature maps.
+#[derive(Module, Debug)]
+pub struct InstanceNorm2d<B: Backend> {
+    gamma: Option<Param<Tensor<B, 1>>>,
+    beta: Option<Param<Tensor<B, 1>>>,
+    epsilon: f64,
+}
+
+impl<B: Backend> InstanceNorm2d<B> {
+    /// Normalize the input.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, channels, height, width]`
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        instance_norm(x, self.gamma.as_ref(), self.beta.as_ref(), self.epsilon)
+    }
+}
+
+/// [2D instance normalization](InstanceNorm2d) configuration.
+pub struct InstanceNorm2dConfig {
+    num_features: usize,
+    epsilon: f64,
+    affine: bool,
+}
+
+impl InstanceNorm2dConfig {
+    /// Create a new instance of the 2D instance normalization [config](InstanceNorm2dConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `num_features`: Number of features (channels).
+    /// * `epsilon` - Value added to the variance for numerical stability (e.g., `1e-5`).
+    /// * `affine` - Whether to apply a learnable per-channel scale and shift.
+    pub fn new(num_features: usize, epsilon: f64, affine: bool) -> Self {
+        Self {
+            num_features,
+            epsilon,
+            affine,
+        }
+    }
+
+    /// Initialize a new [2D instance normalization](InstanceNorm2d) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> InstanceNorm2d<B> {
+        let (gamma, beta) = affine_params(self.num_features, self.affine, device);
+
+        InstanceNorm2d {
+            gamma,
+            beta,
+            epsilon: self.epsilon,
+        }
+    }
+}
+
+/// Scale (ones) and shift (zeros) parameters of the affine transform, if enabled.
+#[allow(clippy::type_complexity)]
+fn affine_params<B: Backend>(
+    num_features: usize,
+    affine: bool,
+    device: &Device<B>,
+) -> (Option<Param<Tensor<B, 1>>>, Option<Param<Tensor<B, 1>>>) {
+    if !affine {
+        return (None, None);
+    }
+
+    (
+        Some(Param::from_tensor(Tensor::ones([num_features], device))),
+        Some(Param::from_tensor(Tensor::zeros([num_features], device))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        module::RunningState,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn batch_norm<B: Backend>(device: &Device<B>) -> BatchNorm<B, 2> {
+        let mut bn = BatchNormConfig::new(2).with_epsilon(1e-3).init(device);
+        bn.gamma = Param::from_tensor(Tensor::from_floats([1.5, 0.5], device));
+        bn.beta = Param::from_tensor(Tensor::from_floats([0.25, -0.25], device));
+        bn.running_mean = RunningState::new(Tensor::from_floats([1., 2.], device));
+        bn.running_var = RunningState::new(Tensor::from_floats([4., 0.25], device));
+
+        bn
+    }
+
+    #[test]
+    fn from_batch_norm_copies_statistics() {
+        let frozen =
+            FrozenBatchNorm::from_batch_norm(batch_norm::<TestBackend>(&Default::default()));
+
+        let assert_values = |param: &Param<Tensor<TestBackend, 1>>, values: [f32; 2]| {
+            param
+                .val()
+                .into_data()
+                .assert_eq(&TensorData::from(values), false);
+        };
+        assert_values(&frozen.weight, [1.5, 0.5]);
+        assert_values(&frozen.bias, [0.25, -0.25]);
+        assert_values(&frozen.running_mean, [1., 2.]);
+        assert_values(&frozen.running_var, [4., 0.25]);
+        assert_eq!(frozen.epsilon, 1e-3);
+    }
+
+    #[test]
+    fn frozen_matches_batch_norm_inference() {
+        let device = Default::default();
+        let bn = batch_norm::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 2, 4, 4], Distribution::Default, &device);
+        let expected = bn.forward(x.clone()).into_data();
+
+        let frozen = FrozenBatchNorm::from_batch_norm(bn);
+
+        frozen.forward(x).into_data().assert_approx_eq(&expected, 5);
+    }
+
+    #[test]
+    fn frozen_output_identical_across_batch_statistics() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let frozen = FrozenBatchNorm::from_batch_norm(batch_norm::<TrainingBackend>(&device));
+        let x = Tensor::<TrainingBackend, 4>::random([1, 2, 4, 4], Distribution::Default, &device);
+        let first = |other: Tensor<TrainingBackend, 4>| {
+            frozen
+                .forward(Tensor::cat(alloc::vec![x.clone(), other], 0))
+                .slice([0..1, 0..2, 0..4, 0..4])
+                .into_data()
+        };
+
+        let out = first(Tensor::zeros([3, 2, 4, 4], &device));
+        let out_shifted = first(Tensor::ones([3, 2, 4, 4], &device).mul_scalar(100.));
+
+        out.assert_approx_eq(&out_shifted, 5);
+        // The running statistics are not updated
+        frozen
+            .running_mean
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::from([1f32, 2.]), false);
+    }
+
+    #[test]
+    fn freeze_normalization_layers() {
+        let device = Default::default();
+        let layers = alloc::vec![
+            Normalization::Batch(batch_norm::<TestBackend>(&device)),
+            Normalization::Sync(SyncBatchNorm::from_batch_norm(batch_norm(&device))),
+        ];
+
+        let layers = freeze_batch_norms(layers);
+
+        for layer in layers {
+            let Normalization::Frozen(bn) = layer else {
+                panic!("expected a frozen batch normalization layer");
+            };
+            bn.running_var
+                .val()
+                .into_data()
+                .assert_eq(&TensorData::from([4f32, 0.25]), false);
+        }
+    }
+
+    #[test]
+    fn sync_matches_batch_norm_on_single_device() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let x = Tensor::<TrainingBackend, 4>::random([2, 2, 4, 4], Distribution::Default, &device);
+
+        // Batch statistics during training
+        let bn = batch_norm::<TrainingBackend>(&device);
+        let sync = SyncBatchNorm::from_batch_norm(batch_norm::<TrainingBackend>(&device));
+        let expected = bn.forward(x.clone()).into_data();
+        sync.forward(x.clone())
+            .into_data()
+            .assert_eq(&expected, true);
+        let outputs = sync.forward_replicas(alloc::vec![x.clone()]);
+        assert_eq!(outputs.len(), 1);
+        outputs[0].clone().into_data().assert_eq(&expected, true);
+
+        // Running statistics during inference
+        let x = x.inner();
+        let bn = batch_norm::<TestBackend>(&device);
+        let sync = SyncBatchNorm::from_batch_norm(batch_norm::<TestBackend>(&device));
+        let expected = bn.forward(x.clone()).into_data();
+        sync.forward(x).into_data().assert_eq(&expected, true);
+    }
+
+    #[test]
+    fn sync_replicas_match_whole_batch() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let x = Tensor::<TrainingBackend, 4>::random([3, 2, 4, 4], Distribution::Default, &device);
+        let bn = batch_norm::<TrainingBackend>(&device);
+        let sync = SyncBatchNorm::from_batch_norm(batch_norm::<TrainingBackend>(&device));
+        let expected = bn.forward(x.clone());
+
+        let outputs = sync.forward_replicas(alloc::vec![
+            x.clone().slice([0..1, 0..2, 0..4, 0..4]),
+            x.slice([1..3, 0..2, 0..4, 0..4]),
+        ]);
+
+        Tensor::cat(outputs, 0)
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 4);
+        sync.bn
+            .running_mean
+            .value()
+            .into_data()
+            .assert_approx_eq(&bn.running_mean.value().into_data(), 4);
+    }
+
+    #[test]
+    fn convert_normalization_layers() {
+        let device = Default::default();
+        let layers = alloc::vec![
+            Normalization::Batch(batch_norm::<TestBackend>(&device)),
+            Normalization::Frozen(FrozenBatchNorm::from_batch_norm(batch_norm(&device))),
+        ];
+
+        let layers = convert_sync_batchnorm(layers);
+
+        let Normalization::Sync(bn) = &layers[0] else {
+            panic!("expected a synchronized batch normalization layer");
+        };
+        bn.bn
+            .running_var
+            .value()
+            .into_data()
+            .assert_eq(&TensorData::from([4f32, 0.25]), false);
+        assert!(matches!(layers[1], Normalization::Frozen(_)));
+        assert!(convert_sync_batchnorm(None::<Normalization<TestBackend>>).is_none());
+    }
+
+    #[test]
+    fn convert_model_keeps_outputs() {
+        use crate::model::yolox::Yolox;
+
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::random([1, 3, 64, 64], Distribution::Default, &device);
+        let model = Yolox::<TestBackend>::yolox_nano(2, &device);
+        let expected = model.forward(x.clone()).into_data();
+
+        let model = convert_sync_batchnorm(model);
+
+        model.forward(x).into_data().assert_eq(&expected, true);
+    }
+
+    #[test]
+    fn scheduled_momentum_warmup() {
+        let device = Default::default();
+        let mut bn = MomentumScheduledBatchNormConfig::new(2, 4)
+            .with_initial_momentum(0.5)
+            .with_target_momentum(0.02)
+            .init::<TestBackend>(&device);
+
+        // No step: initial momentum
+        assert_eq!(bn.momentum(), 0.5);
+        assert_eq!(bn.bn.momentum, 0.5);
+
+        // Geometric decay, reaching the target after the warmup steps
+        bn.step();
+        bn.step();
+        assert!((bn.momentum() - 0.1).abs() < 1e-9);
+        bn.step();
+        bn.step();
+        assert_eq!(bn.momentum(), 0.02);
+        assert_eq!(bn.bn.momentum, 0.02);
+
+        // Constant after the warmup
+        bn.step();
+        assert_eq!(bn.momentum(), 0.02);
+    }
+
+    #[test]
+    fn scheduled_momentum_without_warmup() {
+        let bn =
+            MomentumScheduledBatchNormConfig::new(2, 0).init::<TestBackend>(&Default::default());
+
+        assert_eq!(bn.momentum(), 0.03);
+        assert_eq!(bn.bn.momentum, 0.03);
+    }
+
+    #[test]
+    fn set_momentum_of_all_layers() {
+        use crate::model::yolox::Yolox;
+        use burn::module::AutodiffModule;
+        type TrainingBackend = Autodiff<TestBackend>;
+
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 64, 64], Distribution::Default, &device);
+        let mut model = Yolox::<TrainingBackend>::yolox_nano(2, &device);
+
+        // Without momentum, the training forward pass does not update any running statistics
+        set_bn_momentum(&mut model, 0.);
+        let before = model.valid().forward(x.clone());
+        let _ = model.forward(Tensor::from_inner(x.clone()));
+        let after = model.valid().forward(x.clone());
+        after.into_data().assert_eq(&before.into_data(), true);
+
+        set_bn_momentum(&mut model, 0.5);
+        let before = model.valid().forward(x.clone());
+        let _ = model.forward(Tensor::from_inner(x.clone()));
+        let after = model.valid().forward(x);
+        assert_ne!(
+            after.into_data().to_vec::<f32>().unwrap(),
+            before.into_data().to_vec::<f32>().unwrap()
+        );
+    }
+
+    /// Mean and (biased) standard deviation of each vector of the last dimension.
+    fn moments(x: Tensor<TestBackend, 3>) -> (Tensor<TestBackend, 3>, Tensor<TestBackend, 3>) {
+        let mean = x.clone().mean_dim(2);
+        let std = (x - mean.clone()).powf_scalar(2.).mean_dim(2).sqrt();
+        (mean, std)
+    }
+
+    #[test]
+    fn instance_norm_1d_statistics() {
+        let device = Default::default();
+        let norm = InstanceNorm1dConfig::new(4, 1e-5, false).init::<TestBackend>(&device);
+        let x =
+            Tensor::<TestBackend, 3>::random([2, 4, 16], Distribution::Uniform(-5., 20.), &device);
+
+        let (mean, std) = moments(norm.forward(x));
+
+        mean.into_data().assert_approx_eq(
+            &Tensor::<TestBackend, 3>::zeros([2, 4, 1], &device).into_data(),
+            4,
+        );
+        std.into_data().assert_approx_eq(
+            &Tensor::<TestBackend, 3>::ones([2, 4, 1], &device).into_data(),
+            3,
+        );
+    }
+
+    #[test]
+    fn instance_norm_affine_params() {
+        let device = Default::default();
+
+        let norm = InstanceNorm1dConfig::new(4, 1e-5, false).init::<TestBackend>(&device);
+        assert_eq!(norm.num_params(), 0);
+        let norm = InstanceNorm2dConfig::new(4, 1e-5, false).init::<TestBackend>(&device);
+        assert_eq!(norm.num_params(), 0);
+
+        let norm = InstanceNorm1dConfig::new(4, 1e-5, true).init::<TestBackend>(&device);
+        assert_eq!(norm.num_params(), 8);
+        let norm = InstanceNorm2dConfig::new(4, 1e-5, true).init::<TestBackend>(&device);
+        assert_eq!(norm.num_params(), 8);
+    }
+
+    #[test]
+    fn instance_norm_1d_affine() {
+        let device = Default::default();
+        let mut norm = InstanceNorm1dConfig::new(2, 1e-5, true).init::<TestBackend>(&device);
+        norm.gamma = Some(Param::from_tensor(Tensor::from_floats([2., 0.5], &device)));
+        norm.beta = Some(Param::from_tensor(Tensor::from_floats([1., -1.], &device)));
+        let x = Tensor::<TestBackend, 3>::random([3, 2, 8], Distribution::Default, &device);
+
+        let (mean, std) = moments(norm.forward(x));
+
+        mean.into_data().assert_approx_eq(
+            &TensorData::from([[[1f32], [-1.]], [[1.], [-1.]], [[1.], [-1.]]]),
+            4,
+        );
+        std.into_data().assert_approx_eq(
+            &TensorData::from([[[2f32], [0.5]], [[2.], [0.5]], [[2.], [0.5]]]),
+            3,
+        );
+    }
+
+    #[test]
+    fn instance_norm_2d_matches_1d() {
+        let device = Default::default();
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 4, 5], Distribution::Default, &device);
+        let norm_1d = InstanceNorm1dConfig::new(3, 1e-5, false).init::<TestBackend>(&device);
+        let norm_2d = InstanceNorm2dConfig::new(3, 1e-5, false).init::<TestBackend>(&device);
+
+        let expected = norm_1d
+            .forward(x.clone().reshape([2, 3, 20]))
+            .reshape([2, 3, 4, 5]);
+
+        norm_2d
+            .forward(x)
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 5);
+    }
+
+    #[test]
+    fn instance_norm_is_per_sample() {
+        let device = Default::default();
+        let norm = InstanceNorm2dConfig::new(2, 1e-5, false).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([1, 2, 4, 4], Distribution::Default, &device);
+        let expected = norm.forward(x.clone()).into_data();
+
+        // The other samples of the batch have no effect
+        let other = Tensor::random([1, 2, 4, 4], Distribution::Uniform(50., 100.), &device);
+        norm.forward(Tensor::cat(alloc::vec![x, other], 0))
+            .slice([0..1, 0..2, 0..4, 0..4])
+            .into_data()
+            .assert_approx_eq(&expected, 5);
+    }
+}
@@ -0,0 +1,438 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{Gelu, LayerNorm, LayerNormConfig, Linear, LinearConfig},
+    tensor::{activation::sigmoid, backend::Backend, Device, ElementConversion, Int, Tensor},
+};
+
+use super::{
+    heads::l2_normalize,
+    vit::{Vit, VitConfig},
+};
+use crate::types::{ConfThreshold, Detection};
+
+/// Image encoder of [OWL-ViT](OwlVit): a [Vision Transformer](Vit) whose output patch tokens are
+/// used as the per-object embeddings.
+#[derive(Module, Debug)]
+pub struct OwlVitImageEncoder<B: Backend> {
+    vit: Vit<B>,
+    norm: LayerNorm<B>,
+    patch_size: usize,
+}
+
+impl<B: Backend> OwlVitImageEncoder<B> {
+    /// Compute the patch token embeddings, merged with the class token.
+    ///
+    /// # Shapes
+    ///   - images: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, num_patches, embed_dim]`
+    pub fn forward(&self, images: Tensor<B, 4>) -> Tensor<B, 3> {
+        let tokens = self.vit.forward(images);
+        let [b, n, d] = tokens.dims();
+
+        // Merge the class token into the patch tokens
+        let cls = tokens.clone().slice([0..b, 0..1, 0..d]);
+        let patches = tokens.slice([0..b, 1..n, 0..d]);
+
+        self.norm.forward(patches * cls)
+    }
+
+    /// Grid size `[rows, cols]` of the patches for the given image size.
+    pub fn grid_size(&self, [height, width]: [usize; 2]) -> [usize; 2] {
+        [height / self.patch_size, width / self.patch_size]
+    }
+}
+
+/// [OWL-ViT image encoder](OwlVitImageEncoder) configuration.
+pub struct OwlVitImageEncoderConfig {
+    vit: VitConfig,
+    norm: LayerNormConfig,
+}
+
+impl OwlVitImageEncoderConfig {
+    /// Create a new instance of the OWL-ViT image encoder [config](OwlVitImageEncoderConfig).
+    pub fn new(vit: VitConfig) -> Self {
+        let norm = LayerNormConfig::new(vit.embed_dim()).with_epsilon(1e-5);
+
+        Self { vit, norm }
+    }
+
+    /// ViT-B/32 image encoder.
+    pub fn vit_b32(image_size: usize) -> Self {
+        Self::new(VitConfig::new(image_size, 32, 3, 768, 12, 12, 4.))
+    }
+
+    /// ViT-L/14 image encoder.
+    pub fn vit_l14(image_size: usize) -> Self {
+        Self::new(VitConfig::new(image_size, 14, 3, 1024, 24, 16, 4.))
+    }
+
+    /// Embedding dimension of the patch tokens.
+    pub fn embed_dim(&self) -> usize {
+        self.vit.embed_dim()
+    }
+
+    /// Initialize a new [OWL-ViT image encoder](OwlVitImageEncoder) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> OwlVitImageEncoder<B> {
+        OwlVitImageEncoder {
+            vit: self.vit.init(device),
+            norm: self.norm.init(device),
+            patch_size: self.vit.patch_size(),
+        }
+    }
+}
+
+/// Detection head of [OWL-ViT](OwlVit), predicting a box and the similarity to each text query
+/// for every patch token.
+#[derive(Module, Debug)]
+pub struct OwlVitHead<B: Backend> {
+    /// Box predictor MLP.
+    box_mlp: Vec<Linear<B>>,
+    activation: Gelu,
+    /// Projection of the patch tokens to the text embedding space.
+    class_proj: Linear<B>,
+    logit_shift: Linear<B>,
+    logit_scale: Linear<B>,
+}
+
+impl<B: Backend> OwlVitHead<B> {
+    /// Predict the boxes and the class logits of the patch tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens`: Patch token embeddings. Shape: `[batch_size, num_patches, embed_dim]`.
+    /// * `text_embeddings` - Embeddings of the text queries. Shape: `[num_queries, query_dim]`.
+    /// * `grid_size` - Grid size `[rows, cols]` of the patches.
+    ///
+    /// # Returns
+    ///
+    /// The normalized `(cx, cy, w, h)` boxes in the range `[0, 1]` with shape
+    /// `[batch_size, num_patches, 4]` and the class logits with shape
+    /// `[batch_size, num_patches, num_queries]`.
+    pub fn forward(
+        &self,
+        tokens: Tensor<B, 3>,
+        text_embeddings: Tensor<B, 2>,
+        grid_size: [usize; 2],
+    ) -> (Tensor<B, 3>, Tensor<B, 3>) {
+        let [b, n, _] = tokens.dims();
+        let [num_queries, _] = text_embeddings.dims();
+
+        // Boxes, relative to the location of each patch
+        let num_layers = self.box_mlp.len();
+        let boxes = self
+            .box_mlp
+            .iter()
+            .enumerate()
+            .fold(tokens.clone(), |x, (i, layer)| {
+                let x = layer.forward(x);
+                if i + 1 < num_layers {
+                    self.activation.forward(x)
+                } else {
+                    x
+                }
+            });
+        let boxes = sigmoid(boxes + box_bias::<B>(grid_size, &tokens.device()).unsqueeze());
+
+        // Cosine similarity with the text queries, with a learned shift and scale per token
+        let image_embeddings = self.class_proj.forward(tokens.clone());
+        let [_, _, query_dim] = image_embeddings.dims();
+        let image_embeddings = l2_normalize(image_embeddings.reshape([b * n, query_dim]));
+        let similarity = image_embeddings
+            .matmul(l2_normalize(text_embeddings).transpose())
+            .reshape([b, n, num_queries]);
+        let shift = self.logit_shift.forward(tokens.clone());
+        let scale = self.logit_scale.forward(tokens).exp();
+
+        (boxes, (similarity + shift) * scale)
+    }
+}
+
+/// Box bias of each patch, i.e. the inverse sigmoid of the patch box `(cx, cy, w, h)` in
+/// normalized coordinates. Shape: `[num_patches, 4]`.
+fn box_bias<B: Backend>([rows, cols]: [usize; 2], device: &Device<B>) -> Tensor<B, 2> {
+    let n = rows * cols;
+    let cx = Tensor::<B, 1, Int>::arange(0..cols as i64, device)
+        .float()
+        .add_scalar(0.5)
+        .div_scalar(cols as f32)
+        .reshape([1, cols])
+        .repeat_dim(0, rows)
+        .reshape([n, 1]);
+    let cy = Tensor::<B, 1, Int>::arange(0..rows as i64, device)
+        .float()
+        .add_scalar(0.5)
+        .div_scalar(rows as f32)
+        .reshape([rows, 1])
+        .repeat_dim(1, cols)
+        .reshape([n, 1]);
+    let w = Tensor::ones([n, 1], device).div_scalar(cols as f32);
+    let h = Tensor::ones([n, 1], device).div_scalar(rows as f32);
+
+    let boxes = Tensor::cat(vec![cx, cy, w, h], 1).clamp(1e-4, 1. - 1e-4);
+    (boxes.clone() / boxes.neg().add_scalar(1.)).log()
+}
+
+/// [OWL-ViT head](OwlVitHead) configuration.
+pub struct OwlVitHeadConfig {
+    box_mlp: Vec<LinearConfig>,
+    class_proj: LinearConfig,
+    logit_shift: LinearConfig,
+    logit_scale: LinearConfig,
+}
+
+impl OwlVitHeadConfig {
+    /// Create a new instance of the OWL-ViT head [config](OwlVitHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `embed_dim`: Embedding dimension of the patch tokens.
+    /// * `query_dim` - Embedding dimension of the text queries.
+    pub fn new(embed_dim: usize, query_dim: usize) -> Self {
+        let box_mlp = vec![
+            LinearConfig::new(embed_dim, embed_dim),
+            LinearConfig::new(embed_dim, embed_dim),
+            LinearConfig::new(embed_dim, 4),
+        ];
+
+        Self {
+            box_mlp,
+            class_proj: LinearConfig::new(embed_dim, query_dim),
+            logit_shift: LinearConfig::new(embed_dim, 1),
+            logit_scale: LinearConfig::new(embed_dim, 1),
+        }
+    }
+
+    /// Initialize a new [OWL-ViT head](OwlVitHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> OwlVitHead<B> {
+        OwlVitHead {
+            box_mlp: self.box_mlp.iter().map(|l| l.init(device)).collect(),
+            activation: Gelu::new(),
+            class_proj: self.class_proj.init(device),
+            logit_shift: self.logit_shift.init(device),
+            logit_scale: self.logit_scale.init(device),
+        }
+    }
+}
+
+/// [OWL-ViT](https://arxiv.org/abs/2205.06230) open-vocabulary object detector.
+///
+/// The text encoder is not included: the text queries are given as pre-computed embeddings
+/// (e.g., from a CLIP text encoder).
+#[derive(Module, Debug)]
+pub struct OwlVit<B: Backend> {
+    encoder: OwlVitImageEncoder<B>,
+    head: OwlVitHead<B>,
+}
+
+impl<B: Backend> OwlVit<B> {
+    /// Predict the boxes and class logits of each patch.
+    ///
+    /// # Arguments
+    ///
+    /// * `images`: Normalized input images. Shape: `[batch_size, 3, height, width]`.
+    /// * `text_embeddings` - Embeddings of the text queries. Shape: `[num_queries, query_dim]`.
+    ///
+    /// # Shapes
+    ///   - output: `([batch_size, num_patches, 4], [batch_size, num_patches, num_queries])`
+    pub fn forward(
+        &self,
+        images: Tensor<B, 4>,
+        text_embeddings: Tensor<B, 2>,
+    ) -> (Tensor<B, 3>, Tensor<B, 3>) {
+        let [_, _, height, width] = images.dims();
+        let grid_size = self.encoder.grid_size([height, width]);
+        let tokens = self.encoder.forward(images);
+
+        self.head.forward(tokens, text_embeddings, grid_size)
+    }
+
+    /// Detect the objects matching the text queries.
+    ///
+    /// Each patch predicts one detection, whose class is the index of the best matching text
+    /// query. Use [threshold_by_text] to select the detections of a query.
+    ///
+    /// # Arguments
+    ///
+    /// * `images`: Normalized input images. Shape: `[batch_size, 3, height, width]`.
+    /// * `text_embeddings` - Embeddings of the text queries. Shape: `[num_queries, query_dim]`.
+    ///
+    /// # Returns
+    ///
+    /// The detections of all the images, with boxes in pixel coordinates.
+    pub fn predict(&self, images: Tensor<B, 4>, text_embeddings: Tensor<B, 2>) -> Vec<Detection> {
+        let [_, _, height, width] = images.dims();
+        let (boxes, logits) = self.forward(images, text_embeddings);
+        let [b, n, _] = boxes.dims();
+
+        let (scores, queries) = sigmoid(logits).max_dim_with_indices(2);
+        let boxes: Vec<f32> = boxes
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem::<f32>())
+            .collect();
+        let scores: Vec<f32> = scores
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem::<f32>())
+            .collect();
+        let queries: Vec<usize> = queries
+            .into_data()
+            .iter::<B::IntElem>()
+            .map(|v| v.elem::<i64>() as usize)
+            .collect();
+
+        let (width, height) = (width as f32, height as f32);
+        (0..b * n)
+            .map(|i| {
+                let [cx, cy, w, h] = [0, 1, 2, 3].map(|k| boxes[i * 4 + k]);
+                let box_xyxy = [
+                    (cx - w / 2.) * width,
+                    (cy - h / 2.) * height,
+                    (cx + w / 2.) * width,
+                    (cy + h / 2.) * height,
+                ];
+                Detection::new(i / n, box_xyxy, scores[i], queries[i])
+            })
+            .collect()
+    }
+}
+
+/// Select the [OWL-ViT](OwlVit) detections of a text query with a score above the threshold.
+pub fn threshold_by_text(
+    preds: &[Detection],
+    query_idx: usize,
+    threshold: ConfThreshold,
+) -> Vec<Detection> {
+    preds
+        .iter()
+        .filter(|d| d.class_id == query_idx && d.score >= threshold.value())
+        .cloned()
+        .collect()
+}
+
+/// [OWL-ViT](OwlVit) configuration.
+pub struct OwlVitConfig {
+    encoder: OwlVitImageEncoderConfig,
+    head: OwlVitHeadConfig,
+}
+
+impl OwlVitConfig {
+    /// Create a new instance of the OWL-ViT [config](OwlVitConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder`: Image encoder configuration.
+    /// * `query_dim` - Embedding dimension of the text queries.
+    pub fn new(encoder: OwlVitImageEncoderConfig, query_dim: usize) -> Self {
+        let head = OwlVitHeadConfig::new(encoder.embed_dim(), query_dim);
+
+        Self { encoder, head }
+    }
+
+    /// OWL-ViT with a ViT-B/32 image encoder for 768x768 images.
+    pub fn vit_b32() -> Self {
+        Self::new(OwlVitImageEncoderConfig::vit_b32(768), 512)
+    }
+
+    /// OWL-ViT with a ViT-L/14 image encoder for 840x840 images.
+    pub fn vit_l14() -> Self {
+        Self::new(OwlVitImageEncoderConfig::vit_l14(840), 768)
+    }
+
+    /// Initialize a new [OWL-ViT](OwlVit) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> OwlVit<B> {
+        OwlVit {
+            encoder: self.encoder.init(device),
+            head: self.head.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn tiny_owl_vit(device: &Device<TestBackend>) -> OwlVit<TestBackend> {
+        let encoder = OwlVitImageEncoderConfig::new(VitConfig::new(32, 8, 3, 16, 1, 2, 2.));
+        OwlVitConfig::new(encoder, 12).init(device)
+    }
+
+    #[test]
+    fn head_output_shapes() {
+        let device = Default::default();
+        let head = OwlVitHeadConfig::new(16, 12).init::<TestBackend>(&device);
+        let tokens = Tensor::random([2, 6, 16], Distribution::Default, &device);
+        let text_embeddings = Tensor::random([5, 12], Distribution::Default, &device);
+
+        let (boxes, logits) = head.forward(tokens, text_embeddings, [2, 3]);
+
+        assert_eq!(boxes.dims(), [2, 6, 4]);
+        assert_eq!(logits.dims(), [2, 6, 5]);
+    }
+
+    #[test]
+    fn box_bias_patch_boxes() {
+        let bias = box_bias::<TestBackend>([2, 2], &Default::default());
+
+        sigmoid(bias).into_data().assert_approx_eq(
+            &TensorData::from([
+                [0.25f32, 0.25, 0.5, 0.5],
+                [0.75, 0.25, 0.5, 0.5],
+                [0.25, 0.75, 0.5, 0.5],
+                [0.75, 0.75, 0.5, 0.5],
+            ]),
+            4,
+        );
+    }
+
+    #[test]
+    fn owl_vit_forward_shapes() {
+        let device = Default::default();
+        let model = tiny_owl_vit(&device);
+        let images = Tensor::random([2, 3, 32, 32], Distribution::Default, &device);
+        let text_embeddings = Tensor::random([3, 12], Distribution::Default, &device);
+
+        let (boxes, logits) = model.forward(images, text_embeddings);
+
+        assert_eq!(boxes.dims(), [2, 16, 4]);
+        assert_eq!(logits.dims(), [2, 16, 3]);
+    }
+
+    #[test]
+    fn owl_vit_predict_one_detection_per_patch() {
+        let device = Default::default();
+        let model = tiny_owl_vit(&device);
+        let images = Tensor::random([2, 3, 32, 32], Distribution::Default, &device);
+        let text_embeddings = Tensor::random([3, 12], Distribution::Default, &device);
+
+        let detections = model.predict(images, text_embeddings);
+
+        assert_eq!(detections.len(), 2 * 16);
+        assert_eq!(detections.iter().filter(|d| d.image_id == 1).count(), 16);
+        assert!(detections.iter().all(|d| d.class_id < 3));
+        assert!(detections
+            .iter()
+            .all(|d| d.box_xyxy[0] <= d.box_xyxy[2] && d.box_xyxy[1] <= d.box_xyxy[3]));
+    }
+
+    #[test]
+    fn threshold_detections_by_text() {
+        let preds = vec![
+            Detection::new(0, [0., 0., 1., 1.], 0.9, 0),
+            Detection::new(0, [0., 0., 1., 1.], 0.2, 0),
+            Detection::new(1, [0., 0., 1., 1.], 0.8, 1),
+        ];
+
+        let selected = threshold_by_text(&preds, 0, ConfThreshold::new(0.5).unwrap());
+
+        assert_eq!(selected, vec![preds[0].clone()]);
+        assert!(threshold_by_text(&preds, 2, ConfThreshold::new(0.).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,887 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        pool::{MaxPool2d, MaxPool2dConfig},
+        BatchNormConfig, Initializer, PaddingConfig2d,
+    },
+    tensor::{
+        activation::{sigmoid, softmax},
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Int, Tensor,
+    },
+};
+
+use super::{
+    blocks::{Activation, ActivationType, Conv, ConvConfig, SeBlock, SeBlockConfig},
+    bottleneck::{CspBottleneck, CspBottleneckConfig},
+    decode_grid::make_anchor_grid,
+    normalizations::Normalization,
+    DetectionModel, DetectionRawOutput,
+};
+
+#[cfg(feature = "pretrained")]
+use {
+    burn::record::{FullPrecisionSettings, Recorder, RecorderError},
+    burn_import::pytorch::{LoadArgs, PyTorchFileRecorder},
+    std::path::Path,
+};
+
+/// Strides of the detection levels.
+const STRIDES: [usize; 4] = [8, 16, 32, 64];
+/// Number of channels of the backbone stem.
+const STEM_CHANNELS: usize = 24;
+/// Number of blocks of each backbone stage.
+const STAGE_REPEATS: [usize; 3] = [3, 7, 3];
+/// Reduction ratio of the squeeze-and-excitation blocks.
+const SE_REDUCTION: usize = 4;
+/// Maximum value of the discretized box distances (in units of the stride).
+const REG_MAX: usize = 7;
+/// Prior probability of the classification outputs at initialization.
+const PRIOR_PROB: f64 = 1e-2;
+
+/// Size of the [PP-PicoDet](PicoDet) model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PicoDetVariant {
+    /// PP-PicoDet-S, with less than 1M parameters.
+    #[default]
+    S,
+    /// PP-PicoDet-M.
+    M,
+    /// PP-PicoDet-L.
+    L,
+}
+
+impl PicoDetVariant {
+    /// Width multiplier of the backbone.
+    fn backbone_scale(&self) -> f64 {
+        match self {
+            Self::S => 0.75,
+            Self::M => 1.0,
+            Self::L => 1.25,
+        }
+    }
+
+    /// Number of channels of the neck and head.
+    fn channels(&self) -> usize {
+        match self {
+            Self::S => 96,
+            Self::M => 128,
+            Self::L => 160,
+        }
+    }
+
+    /// Number of stacked convolutions of the head.
+    fn num_head_convs(&self) -> usize {
+        match self {
+            Self::S => 2,
+            Self::M | Self::L => 4,
+        }
+    }
+}
+
+/// A Conv2d -> BatchNorm block with an optional activation.
+#[derive(Module, Debug)]
+pub struct ConvNorm<B: Backend> {
+    conv: Conv2d<B>,
+    bn: Normalization<B>,
+    activation: Option<Activation>,
+}
+
+impl<B: Backend> ConvNorm<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.bn.forward(self.conv.forward(x));
+
+        match &self.activation {
+            Some(activation) => activation.forward(x),
+            None => x,
+        }
+    }
+}
+
+/// [Conv-norm block](ConvNorm) configuration.
+pub struct ConvNormConfig {
+    conv: Conv2dConfig,
+    bn: BatchNormConfig,
+    activation: Option<ActivationType>,
+}
+
+impl ConvNormConfig {
+    /// Create a new instance of the conv-norm block [config](ConvNormConfig) with same padding.
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel_size: usize,
+        stride: usize,
+        groups: usize,
+        activation: Option<ActivationType>,
+    ) -> Self {
+        let pad = (kernel_size - 1) / 2;
+        let conv = Conv2dConfig::new([in_channels, out_channels], [kernel_size, kernel_size])
+            .with_stride([stride, stride])
+            .with_padding(PaddingConfig2d::Explicit(pad, pad))
+            .with_groups(groups)
+            .with_bias(false);
+
+        Self {
+            conv,
+            bn: BatchNormConfig::new(out_channels),
+            activation,
+        }
+    }
+
+    /// Initialize a new [conv-norm block](ConvNorm) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ConvNorm<B> {
+        ConvNorm {
+            conv: self.conv.init(device),
+            bn: Normalization::Batch(self.bn.init(device)),
+            activation: self.activation.map(|activation| activation.init()),
+        }
+    }
+}
+
+/// [Ghost module](https://arxiv.org/abs/1911.11907) generating half of the output channels with
+/// a `1x1` convolution and the other half with a cheap depthwise convolution of the first half.
+#[derive(Module, Debug)]
+pub struct GhostModule<B: Backend> {
+    primary: ConvNorm<B>,
+    cheap: ConvNorm<B>,
+}
+
+impl<B: Backend> GhostModule<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.primary.forward(x);
+        let ghost = self.cheap.forward(x.clone());
+
+        Tensor::cat(vec![x, ghost], 1)
+    }
+}
+
+/// [Ghost module](GhostModule) configuration.
+pub struct GhostModuleConfig {
+    primary: ConvNormConfig,
+    cheap: ConvNormConfig,
+}
+
+impl GhostModuleConfig {
+    /// Create a new instance of the ghost module [config](GhostModuleConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input channels.
+    /// * `out_channels` - Number of output channels, which should be even.
+    /// * `activation` - Activation of both convolutions, if any.
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        activation: Option<ActivationType>,
+    ) -> Self {
+        assert!(
+            out_channels % 2 == 0,
+            "the ghost module expects an even number of output channels"
+        );
+        let init_channels = out_channels / 2;
+
+        Self {
+            primary: ConvNormConfig::new(in_channels, init_channels, 1, 1, 1, activation),
+            cheap: ConvNormConfig::new(
+                init_channels,
+                init_channels,
+                3,
+                1,
+                init_channels,
+                activation,
+            ),
+        }
+    }
+
+    /// Initialize a new [ghost module](GhostModule).
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> GhostModule<B> {
+        GhostModule {
+            primary: self.primary.init(device),
+            cheap: self.cheap.init(device),
+        }
+    }
+}
+
+/// Ghost bottleneck block: an expanding [ghost module](GhostModule), an optional strided
+/// depthwise convolution, a [squeeze-and-excitation block](SeBlock) and a linear projecting ghost
+/// module, with a residual connection.
+///
+/// The shortcut is a depthwise separable projection when the resolution or the number of channels
+/// changes, and the identity otherwise.
+#[derive(Module, Debug)]
+pub struct GhostBottleneck<B: Backend> {
+    ghost1: GhostModule<B>,
+    dwconv: Option<ConvNorm<B>>,
+    se: SeBlock<B>,
+    ghost2: GhostModule<B>,
+    shortcut_dw: Option<ConvNorm<B>>,
+    shortcut_pw: Option<ConvNorm<B>>,
+}
+
+impl<B: Backend> GhostBottleneck<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let residual = match (&self.shortcut_dw, &self.shortcut_pw) {
+            (Some(dw), Some(pw)) => pw.forward(dw.forward(x.clone())),
+            _ => x.clone(),
+        };
+
+        let mut x = self.ghost1.forward(x);
+        if let Some(dwconv) = &self.dwconv {
+            x = dwconv.forward(x);
+        }
+        let x = self.se.forward(x);
+
+        self.ghost2.forward(x) + residual
+    }
+}
+
+/// [Ghost bottleneck block](GhostBottleneck) configuration.
+pub struct GhostBottleneckConfig {
+    ghost1: GhostModuleConfig,
+    dwconv: Option<ConvNormConfig>,
+    se: SeBlockConfig,
+    ghost2: GhostModuleConfig,
+    shortcut: Option<(ConvNormConfig, ConvNormConfig)>,
+}
+
+impl GhostBottleneckConfig {
+    /// Create a new instance of the ghost bottleneck block [config](GhostBottleneckConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input channels.
+    /// * `hidden_channels` - Number of channels of the expanded features.
+    /// * `out_channels` - Number of output channels.
+    /// * `stride` - Stride of the block (1 or 2).
+    pub fn new(
+        in_channels: usize,
+        hidden_channels: usize,
+        out_channels: usize,
+        stride: usize,
+    ) -> Self {
+        assert!(stride == 1 || stride == 2, "invalid stride {stride}");
+        let act = Some(ActivationType::Relu);
+
+        let dwconv = (stride > 1).then(|| {
+            ConvNormConfig::new(
+                hidden_channels,
+                hidden_channels,
+                3,
+                stride,
+                hidden_channels,
+                None,
+            )
+        });
+        let shortcut = (stride > 1 || in_channels != out_channels).then(|| {
+            (
+                ConvNormConfig::new(in_channels, in_channels, 3, stride, in_channels, None),
+                ConvNormConfig::new(in_channels, out_channels, 1, 1, 1, None),
+            )
+        });
+
+        Self {
+            ghost1: GhostModuleConfig::new(in_channels, hidden_channels, act),
+            dwconv,
+            se: SeBlockConfig::new(hidden_channels, SE_REDUCTION),
+            ghost2: GhostModuleConfig::new(hidden_channels, out_channels, None),
+            shortcut,
+        }
+    }
+
+    /// Initialize a new [ghost bottleneck block](GhostBottleneck) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> GhostBottleneck<B> {
+        GhostBottleneck {
+            ghost1: self.ghost1.init(device),
+            dwconv: self.dwconv.as_ref().map(|conv| conv.init(device)),
+            se: self.se.init(device),
+            ghost2: self.ghost2.init(device),
+            shortcut_dw: self.shortcut.as_ref().map(|(dw, _)| dw.init(device)),
+            shortcut_pw: self.shortcut.as_ref().map(|(_, pw)| pw.init(device)),
+        }
+    }
+}
+
+/// Enhanced ShuffleNet (ESNet) backbone of [PP-PicoDet](PicoDet), made of stages of
+/// [ghost bottleneck blocks](GhostBottleneck) with squeeze-and-excitation.
+#[derive(Module, Debug)]
+pub struct EsNet<B: Backend> {
+    stem: ConvNorm<B>,
+    pool: MaxPool2d,
+    stages: Vec<Vec<GhostBottleneck<B>>>,
+}
+
+impl<B: Backend> EsNet<B> {
+    /// Extract the feature maps with strides 8, 16 and 32.
+    ///
+    /// # Shapes
+    ///   - input: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, out_channels[i], height / 2^(i + 3), width / 2^(i + 3)]` for
+    ///     each stage `i`
+    pub fn forward(&self, x: Tensor<B, 4>) -> [Tensor<B, 4>; 3] {
+        let mut x = self.pool.forward(self.stem.forward(x));
+
+        let features: Vec<_> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                x = stage.iter().fold(x.clone(), |x, block| block.forward(x));
+                x.clone()
+            })
+            .collect();
+
+        features.try_into().unwrap()
+    }
+}
+
+/// [ESNet backbone](EsNet) configuration.
+pub struct EsNetConfig {
+    stem: ConvNormConfig,
+    stages: Vec<Vec<GhostBottleneckConfig>>,
+    out_channels: [usize; 3],
+}
+
+impl EsNetConfig {
+    /// Create a new instance of the ESNet backbone [config](EsNetConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `scale`: Width multiplier of the stages, whose base output channels are 128, 256 and 512.
+    pub fn new(scale: f64) -> Self {
+        // Channels are kept divisible by 4 so that the ghost modules split them evenly
+        let out_channels = [128, 256, 512].map(|c| ((c as f64 * scale) as usize / 4) * 4);
+
+        let mut in_channels = STEM_CHANNELS;
+        let stages = out_channels
+            .iter()
+            .zip(STAGE_REPEATS)
+            .map(|(&out_channels, repeats)| {
+                (0..repeats)
+                    .map(|i| {
+                        let stride = if i == 0 { 2 } else { 1 };
+                        let block = GhostBottleneckConfig::new(
+                            in_channels,
+                            out_channels / 2,
+                            out_channels,
+                            stride,
+                        );
+                        in_channels = out_channels;
+                        block
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            stem: ConvNormConfig::new(3, STEM_CHANNELS, 3, 2, 1, Some(ActivationType::Relu)),
+            stages,
+            out_channels,
+        }
+    }
+
+    /// Number of channels of the output feature maps.
+    pub fn out_channels(&self) -> [usize; 3] {
+        self.out_channels
+    }
+
+    /// Initialize a new [ESNet backbone](EsNet) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> EsNet<B> {
+        EsNet {
+            stem: self.stem.init(device),
+            pool: MaxPool2dConfig::new([3, 3])
+                .with_strides([2, 2])
+                .with_padding(PaddingConfig2d::Explicit(1, 1))
+                .init(),
+            stages: self
+                .stages
+                .iter()
+                .map(|stage| stage.iter().map(|block| block.init(device)).collect())
+                .collect(),
+        }
+    }
+}
+
+/// CSP-PAN neck of [PP-PicoDet](PicoDet).
+///
+/// The backbone feature maps are reduced to the same number of channels with `1x1` convolutions
+/// and fused by a top-down and a bottom-up path of [CSP bottlenecks](CspBottleneck) with
+/// depthwise separable convolutions. An extra stride 64 level is computed from the reduced
+/// lowest resolution input and output.
+#[derive(Module, Debug)]
+pub struct CspPan<B: Backend> {
+    reduce: Vec<ConvNorm<B>>,
+    top_down: Vec<CspBottleneck<B>>,
+    downsample: Vec<Conv<B>>,
+    bottom_up: Vec<CspBottleneck<B>>,
+    extra_in: Conv<B>,
+    extra_out: Conv<B>,
+}
+
+impl<B: Backend> CspPan<B> {
+    /// Fuse the backbone feature maps.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels[i], H_i, W_i]` for each level `i`
+    ///   - output: `[batch_size, out_channels, H_i, W_i]` for each level `i`, followed by the extra
+    ///     `[batch_size, out_channels, H_2 / 2, W_2 / 2]` level
+    pub fn forward(&self, features: [Tensor<B, 4>; 3]) -> Vec<Tensor<B, 4>> {
+        let inputs: Vec<_> = features
+            .into_iter()
+            .zip(&self.reduce)
+            .map(|(x, conv)| conv.forward(x))
+            .collect();
+
+        // Top-down path, from the lowest resolution
+        let mut inner = vec![inputs[2].clone()];
+        for (i, csp) in (0..2).rev().zip(&self.top_down) {
+            let [_, _, h, w] = inputs[i].dims();
+            let upsampled = interpolate(
+                inner[0].clone(),
+                [h, w],
+                InterpolateOptions::new(InterpolateMode::Nearest),
+            );
+            let x = csp.forward(Tensor::cat(vec![upsampled, inputs[i].clone()], 1));
+            inner.insert(0, x);
+        }
+
+        // Bottom-up path, from the highest resolution
+        let mut outputs = vec![inner[0].clone()];
+        for (i, (down, csp)) in self.downsample.iter().zip(&self.bottom_up).enumerate() {
+            let downsampled = down.forward(outputs[i].clone());
+            let x = csp.forward(Tensor::cat(vec![downsampled, inner[i + 1].clone()], 1));
+            outputs.push(x);
+        }
+
+        // Extra level
+        let extra =
+            self.extra_in.forward(inputs[2].clone()) + self.extra_out.forward(outputs[2].clone());
+        outputs.push(extra);
+
+        outputs
+    }
+}
+
+/// [CSP-PAN neck](CspPan) configuration.
+pub struct CspPanConfig {
+    in_channels: [usize; 3],
+    out_channels: usize,
+}
+
+impl CspPanConfig {
+    /// Create a new instance of the CSP-PAN neck [config](CspPanConfig).
+    pub fn new(in_channels: [usize; 3], out_channels: usize) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+        }
+    }
+
+    /// Initialize a new [CSP-PAN neck](CspPan) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> CspPan<B> {
+        let c = self.out_channels;
+        let csp = || CspBottleneckConfig::new(2 * c, c, 1, 0.5, false, true).init(device);
+        let down = |kernel_size| ConvConfig::new(c, c, kernel_size, 2, true).init(device);
+
+        CspPan {
+            reduce: self
+                .in_channels
+                .iter()
+                .map(|&in_channels| {
+                    ConvNormConfig::new(in_channels, c, 1, 1, 1, Some(ActivationType::Silu))
+                        .init(device)
+                })
+                .collect(),
+            top_down: (0..2).map(|_| csp()).collect(),
+            downsample: (0..2).map(|_| down(3)).collect(),
+            bottom_up: (0..2).map(|_| csp()).collect(),
+            extra_in: down(5),
+            extra_out: down(5),
+        }
+    }
+}
+
+/// Outputs of the [PP-PicoDet head](PicoHead).
+pub struct PicoDetOutput<B: Backend> {
+    /// Classification logits. Shape: `[batch_size, num_locations, num_classes]`.
+    pub cls_logits: Tensor<B, 3>,
+    /// Logits of the discretized `(left, top, right, bottom)` distances distribution. Shape:
+    /// `[batch_size, num_locations, 4 * (reg_max + 1)]`.
+    pub reg_distribution: Tensor<B, 3>,
+    /// Feature map size `(height, width)` of each level.
+    pub feature_shapes: Vec<(usize, usize)>,
+}
+
+/// Decode the box distances from the logits of their discretized distribution, as in
+/// [Generalized Focal Loss](https://arxiv.org/abs/2006.04388).
+///
+/// Each distance is the expectation of its distribution over the integer values `0..=reg_max`.
+///
+/// # Shapes
+///   - logits: `[batch_size, num_locations, 4 * (reg_max + 1)]`
+///   - output: `[batch_size, num_locations, 4]`
+pub fn dfl_decode<B: Backend>(logits: Tensor<B, 3>, reg_max: usize) -> Tensor<B, 3> {
+    let [b, n, _] = logits.dims();
+    let bins = reg_max + 1;
+
+    let probs = softmax(logits.reshape([b, n, 4, bins]), 3);
+    let values = Tensor::<B, 1, Int>::arange(0..bins as i64, &probs.device())
+        .float()
+        .reshape([1, 1, 1, bins]);
+
+    (probs * values).sum_dim(3).reshape([b, n, 4])
+}
+
+/// Convert `(left, top, right, bottom)` distances from the anchor points to `(cx, cy, w, h)`
+/// boxes.
+///
+/// # Shapes
+///   - distances: `[batch_size, num_locations, 4]`
+///   - points: `[num_locations, 2]`
+///   - output: `[batch_size, num_locations, 4]`
+fn distances_to_cxcywh<B: Backend>(distances: Tensor<B, 3>, points: Tensor<B, 2>) -> Tensor<B, 3> {
+    let [b, n, _] = distances.dims();
+    let lt = distances.clone().slice([0..b, 0..n, 0..2]);
+    let rb = distances.slice([0..b, 0..n, 2..4]);
+
+    let centers = points.unsqueeze::<3>() + (rb.clone() - lt.clone()) / 2.;
+
+    Tensor::cat(vec![centers, lt + rb], 2)
+}
+
+/// Prediction head of [PP-PicoDet](PicoDet).
+///
+/// Each level has its own stack of depthwise separable convolutions, shared by the classification
+/// and the box regression predictions. The boxes are regressed as discretized distributions of
+/// the distances from the anchor points (see [dfl_decode]).
+#[derive(Module, Debug)]
+pub struct PicoHead<B: Backend> {
+    convs: Vec<Vec<Conv<B>>>,
+    cls_preds: Vec<Conv2d<B>>,
+    reg_preds: Vec<Conv2d<B>>,
+    reg_max: usize,
+}
+
+impl<B: Backend> PicoHead<B> {
+    /// Predict the class logits and the distance distributions at each location of each level.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, channels, H_i, W_i]` for each level `i`
+    pub fn forward(&self, features: Vec<Tensor<B, 4>>) -> PicoDetOutput<B> {
+        let flatten = |x: Tensor<B, 4>| {
+            let [b, c, h, w] = x.dims();
+            x.reshape([b, c, h * w]).swap_dims(1, 2)
+        };
+
+        let mut feature_shapes = Vec::with_capacity(features.len());
+        let (cls, reg): (Vec<_>, Vec<_>) = features
+            .into_iter()
+            .zip(&self.convs)
+            .zip(self.cls_preds.iter().zip(&self.reg_preds))
+            .map(|((x, convs), (cls_pred, reg_pred))| {
+                let [_, _, h, w] = x.dims();
+                feature_shapes.push((h, w));
+
+                let x = convs.iter().fold(x, |x, conv| conv.forward(x));
+                (
+                    flatten(cls_pred.forward(x.clone())),
+                    flatten(reg_pred.forward(x)),
+                )
+            })
+            .unzip();
+
+        PicoDetOutput {
+            cls_logits: Tensor::cat(cls, 1),
+            reg_distribution: Tensor::cat(reg, 1),
+            feature_shapes,
+        }
+    }
+
+    /// Number of predicted classes.
+    pub fn num_classes(&self) -> usize {
+        let [num_classes, _, _, _] = self.cls_preds[0].weight.dims();
+        num_classes
+    }
+
+    /// Decode the `(cx, cy, w, h)` boxes in pixels.
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, num_locations, 4]`
+    pub fn decode_boxes(&self, output: &PicoDetOutput<B>) -> Tensor<B, 3> {
+        let device = output.reg_distribution.device();
+        let (points, strides) = make_anchor_grid::<B>(&output.feature_shapes, &STRIDES, &device);
+        let [n] = strides.dims();
+
+        let distances =
+            dfl_decode(output.reg_distribution.clone(), self.reg_max) * strides.reshape([1, n, 1]);
+
+        distances_to_cxcywh(distances, points)
+    }
+}
+
+/// [PP-PicoDet head](PicoHead) configuration.
+pub struct PicoHeadConfig {
+    channels: usize,
+    num_classes: usize,
+    num_convs: usize,
+    reg_max: usize,
+}
+
+impl PicoHeadConfig {
+    /// Create a new instance of the PP-PicoDet head [config](PicoHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `channels`: Number of channels of the feature maps.
+    /// * `num_classes` - Number of classes.
+    /// * `num_convs` - Number of stacked convolutions of each level.
+    pub fn new(channels: usize, num_classes: usize, num_convs: usize) -> Self {
+        Self {
+            channels,
+            num_classes,
+            num_convs,
+            reg_max: REG_MAX,
+        }
+    }
+
+    /// Set the maximum value of the discretized distances (defaults to 7).
+    pub fn with_reg_max(mut self, reg_max: usize) -> Self {
+        self.reg_max = reg_max;
+        self
+    }
+
+    /// Initialize a new [PP-PicoDet head](PicoHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> PicoHead<B> {
+        let c = self.channels;
+        // Initialize the classification biases to the prior probability
+        let bias = -f64::ln((1.0 - PRIOR_PROB) / PRIOR_PROB);
+
+        let convs = STRIDES
+            .iter()
+            .map(|_| {
+                (0..self.num_convs)
+                    .map(|_| ConvConfig::new(c, c, 5, 1, true).init(device))
+                    .collect()
+            })
+            .collect();
+        let cls_preds = STRIDES
+            .iter()
+            .map(|_| {
+                Conv2dConfig::new([c, self.num_classes], [1, 1])
+                    .with_padding(PaddingConfig2d::Explicit(0, 0))
+                    .with_initializer(Initializer::Constant { value: bias })
+                    .init(device)
+            })
+            .collect();
+        let reg_preds = STRIDES
+            .iter()
+            .map(|_| {
+                Conv2dConfig::new([c, 4 * (self.reg_max + 1)], [1, 1])
+                    .with_padding(PaddingConfig2d::Explicit(0, 0))
+                    .init(device)
+            })
+            .collect();
+
+        PicoHead {
+            convs,
+            cls_preds,
+            reg_preds,
+            reg_max: self.reg_max,
+        }
+    }
+}
+
+/// [PP-PicoDet](https://arxiv.org/abs/2111.00902) ultra-lightweight mobile object detector.
+#[derive(Module, Debug)]
+pub struct PicoDet<B: Backend> {
+    backbone: EsNet<B>,
+    neck: CspPan<B>,
+    head: PicoHead<B>,
+}
+
+impl<B: Backend> PicoDet<B> {
+    /// # Shapes
+    ///   - input: `[batch_size, 3, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> PicoDetOutput<B> {
+        let features = self.backbone.forward(x);
+        let features = self.neck.forward(features);
+
+        self.head.forward(features)
+    }
+
+    /// Load a record from PaddlePaddle PP-PicoDet weights.
+    ///
+    /// `.pdparams` files pickle NumPy arrays, which cannot be read directly: the state dict
+    /// should first be saved in the PyTorch format, e.g. with
+    /// `torch.save({k: torch.from_numpy(v.numpy()) for k, v in paddle.load(path).items()}, out)`.
+    /// The Paddle `ConvBNLayer` parameter names (`_conv`, `_batch_norm`, `_mean` and `_variance`)
+    /// are mapped to the burn module names.
+    #[cfg(feature = "pretrained")]
+    pub fn load_paddle_weights(
+        path: &Path,
+        device: &Device<B>,
+    ) -> Result<PicoDetRecord<B>, RecorderError> {
+        let load_args = LoadArgs::new(path.to_path_buf())
+            // Map *._batch_norm._mean -> *.bn.running_mean
+            .with_key_remap("(.+)\\._batch_norm\\._mean", "$1.bn.running_mean")
+            // Map *._batch_norm._variance -> *.bn.running_var
+            .with_key_remap("(.+)\\._batch_norm\\._variance", "$1.bn.running_var")
+            // Map *._batch_norm.* -> *.bn.*
+            .with_key_remap("(.+)\\._batch_norm\\.(.+)", "$1.bn.$2")
+            // Map *._conv.* -> *.conv.*
+            .with_key_remap("(.+)\\._conv\\.(.+)", "$1.conv.$2");
+
+        PyTorchFileRecorder::<FullPrecisionSettings>::new().load(load_args, device)
+    }
+}
+
+impl<B: Backend> DetectionModel<B> for PicoDet<B> {
+    fn forward_raw(&self, images: Tensor<B, 4>) -> DetectionRawOutput<B> {
+        let output = self.forward(images);
+        let boxes = self.head.decode_boxes(&output);
+        let scores = sigmoid(output.cls_logits);
+
+        // No objectness is predicted
+        let [b, n, _] = boxes.dims();
+        let objectness = Tensor::ones([b, n, 1], &boxes.device());
+
+        DetectionRawOutput::AnchorFree(Tensor::cat(vec![boxes, objectness, scores], 2))
+    }
+
+    fn num_classes(&self) -> usize {
+        self.head.num_classes()
+    }
+}
+
+/// [PP-PicoDet detector](PicoDet) configuration.
+pub struct PicoDetConfig {
+    variant: PicoDetVariant,
+    num_classes: usize,
+}
+
+impl PicoDetConfig {
+    /// Create a new instance of the PP-PicoDet detector [config](PicoDetConfig) for the 80 COCO
+    /// classes.
+    pub fn new(variant: PicoDetVariant) -> Self {
+        Self {
+            variant,
+            num_classes: 80,
+        }
+    }
+
+    /// Set the number of classes (defaults to 80).
+    pub fn with_num_classes(mut self, num_classes: usize) -> Self {
+        self.num_classes = num_classes;
+        self
+    }
+
+    /// Initialize a new [PP-PicoDet detector](PicoDet) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> PicoDet<B> {
+        let channels = self.variant.channels();
+        let backbone = EsNetConfig::new(self.variant.backbone_scale());
+        let neck = CspPanConfig::new(backbone.out_channels(), channels);
+        let head = PicoHeadConfig::new(channels, self.num_classes, self.variant.num_head_convs());
+
+        PicoDet {
+            backbone: backbone.init(device),
+            neck: neck.init(device),
+            head: head.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    /// Logits of a distribution peaked at each of the `(left, top, right, bottom)` distances.
+    fn peaked_logits(distances: [usize; 4], reg_max: usize) -> Tensor<TestBackend, 3> {
+        let bins = reg_max + 1;
+        let mut logits = vec![0f32; 4 * bins];
+        for (side, distance) in distances.into_iter().enumerate() {
+            logits[side * bins + distance] = 100.;
+        }
+
+        Tensor::<TestBackend, 1>::from_floats(logits.as_slice(), &Default::default()).reshape([
+            1,
+            1,
+            4 * bins,
+        ])
+    }
+
+    #[test]
+    fn picodet_s_num_params() {
+        let device = Default::default();
+        let s = PicoDetConfig::new(PicoDetVariant::S).init::<TestBackend>(&device);
+        let m = PicoDetConfig::new(PicoDetVariant::M).init::<TestBackend>(&device);
+
+        // Less than 1M parameters for the 80 COCO classes
+        let num_params = s.num_params();
+        assert!(
+            (900_000..1_000_000).contains(&num_params),
+            "{num_params} parameters"
+        );
+        assert!(num_params < m.num_params());
+    }
+
+    #[test]
+    fn picodet_forward_shapes() {
+        let device = Default::default();
+        let model = PicoDetConfig::new(PicoDetVariant::S)
+            .with_num_classes(3)
+            .init::<TestBackend>(&device);
+        let images = Tensor::random([1, 3, 320, 320], Distribution::Default, &device);
+
+        let output = model.forward(images);
+
+        // Strides 8, 16, 32 and 64
+        let num_locations = 40 * 40 + 20 * 20 + 10 * 10 + 5 * 5;
+        assert_eq!(
+            output.feature_shapes,
+            [(40, 40), (20, 20), (10, 10), (5, 5)]
+        );
+        assert_eq!(output.cls_logits.dims(), [1, num_locations, 3]);
+        assert_eq!(
+            output.reg_distribution.dims(),
+            [1, num_locations, 4 * (REG_MAX + 1)]
+        );
+        assert_eq!(model.num_classes(), 3);
+    }
+
+    #[test]
+    fn dfl_decode_expectation() {
+        let device = Default::default();
+
+        // A peaked distribution decodes to its peak, a uniform one to the middle of the range
+        dfl_decode(peaked_logits([2, 3, 0, 7], REG_MAX), REG_MAX)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[2., 3., 0., 7.]]]), 4);
+        dfl_decode(Tensor::<TestBackend, 3>::zeros([1, 1, 4 * 5], &device), 4)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[2., 2., 2., 2.]]]), 4);
+    }
+
+    #[test]
+    fn dfl_decode_recovers_boxes() {
+        let device = Default::default();
+        let points = Tensor::<TestBackend, 2>::from_floats([[100., 60.]], &device);
+
+        // Distances (2, 4, 6, 1) in units of a stride of 8
+        let distances = dfl_decode(peaked_logits([2, 4, 6, 1], REG_MAX), REG_MAX).mul_scalar(8.);
+        let boxes = distances_to_cxcywh(distances, points);
+
+        // Box (84, 28, 148, 68)
+        boxes
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[116., 48., 64., 40.]]]), 3);
+    }
+}
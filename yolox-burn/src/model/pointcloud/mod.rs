@@ -0,0 +1,7 @@
+mod ops;
+mod pointnet_pp;
+mod sparse;
+
+pub use ops::*;
+pub use pointnet_pp::*;
+pub use sparse::*;
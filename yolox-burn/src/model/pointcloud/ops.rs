@@ -0,0 +1,231 @@
+use alloc::{vec, vec::Vec};
+use burn::tensor::{backend::Backend, Int, Tensor};
+
+/// Squared euclidean distance between each pair of points.
+///
+/// # Shapes
+///   - src: `[batch_size, N, 3]`
+///   - dst: `[batch_size, M, 3]`
+///   - output: `[batch_size, N, M]`
+pub fn square_distance<B: Backend>(src: Tensor<B, 3>, dst: Tensor<B, 3>) -> Tensor<B, 3> {
+    let src_norm = src.clone().powf_scalar(2.).sum_dim(2);
+    let dst_norm = dst.clone().powf_scalar(2.).sum_dim(2).swap_dims(1, 2);
+
+    (src_norm + dst_norm - src.matmul(dst.swap_dims(1, 2)).mul_scalar(2.)).clamp_min(0.)
+}
+
+/// Gather the points (or point features) at the given indices.
+///
+/// # Shapes
+///   - points: `[batch_size, N, C]`
+///   - indices: `[batch_size, M, K]`
+///   - output: `[batch_size, M, K, C]`
+pub fn gather_points<B: Backend>(points: Tensor<B, 3>, indices: Tensor<B, 3, Int>) -> Tensor<B, 4> {
+    let [b, m, k] = indices.dims();
+    let [_, _, c] = points.dims();
+
+    let indices = indices.reshape([b, m * k, 1]).repeat_dim(2, c);
+    points.gather(1, indices).reshape([b, m, k, c])
+}
+
+/// Iteratively sample the point farthest from the already sampled points, starting from the
+/// first point.
+///
+/// # Shapes
+///   - xyz: `[batch_size, N, 3]`
+///   - output: `[batch_size, num_samples]`
+pub fn farthest_point_sampling<B: Backend>(
+    xyz: Tensor<B, 3>,
+    num_samples: usize,
+) -> Tensor<B, 2, Int> {
+    let device = xyz.device();
+    let [b, n, _] = xyz.dims();
+
+    let mut distance = Tensor::<B, 2>::full([b, n], f32::MAX, &device);
+    let mut farthest = Tensor::<B, 2, Int>::zeros([b, 1], &device);
+    let mut samples = Vec::with_capacity(num_samples);
+
+    for _ in 0..num_samples {
+        samples.push(farthest.clone());
+
+        let centroid = xyz
+            .clone()
+            .gather(1, farthest.reshape([b, 1, 1]).repeat_dim(2, 3));
+        let dist = (xyz.clone() - centroid)
+            .powf_scalar(2.)
+            .sum_dim(2)
+            .reshape([b, n]);
+        distance = distance.min_pair(dist);
+        farthest = distance.clone().argmax(1);
+    }
+
+    Tensor::cat(samples, 1)
+}
+
+/// Find the (at most) `nsample` first points within `radius` of each query point.
+///
+/// When fewer points are within the radius, the first neighbor is repeated so that each query has
+/// exactly `nsample` neighbor indices. Since the query points are usually sampled from the
+/// points, each query has at least itself as neighbor.
+///
+/// # Shapes
+///   - xyz: `[batch_size, N, 3]`
+///   - new_xyz: `[batch_size, M, 3]`
+///   - output: `[batch_size, M, nsample]`
+pub fn ball_query<B: Backend>(
+    radius: f32,
+    nsample: usize,
+    xyz: Tensor<B, 3>,
+    new_xyz: Tensor<B, 3>,
+) -> Tensor<B, 3, Int> {
+    let device = xyz.device();
+    let [b, n, _] = xyz.dims();
+    let [_, m, _] = new_xyz.dims();
+
+    // Index of each point, or N for the points outside of the ball [B, M, N]
+    let outside = square_distance(new_xyz, xyz).greater_elem(radius * radius);
+    let indices = Tensor::<B, 1, Int>::arange(0..n as i64, &device)
+        .reshape([1, 1, n])
+        .repeat_dim(0, b)
+        .repeat_dim(1, m)
+        .mask_fill(outside, n as i64);
+
+    // First neighbors in the ball
+    let k = nsample.min(n);
+    let indices = indices.sort(2).slice([0..b, 0..m, 0..k]);
+    let first = indices.clone().slice([0..b, 0..m, 0..1]);
+    let indices = if k < nsample {
+        Tensor::cat(vec![indices, first.clone().repeat_dim(2, nsample - k)], 2)
+    } else {
+        indices
+    };
+
+    // Pad with the first neighbor
+    let missing = indices.clone().equal_elem(n as i64);
+    let indices = indices.mask_where(missing, first.repeat_dim(2, nsample));
+
+    // Queries without any neighbor fall back to the first point
+    let missing = indices.clone().equal_elem(n as i64);
+    indices.mask_fill(missing, 0)
+}
+
+/// Interpolate the features of the sparse points at the dense points with the inverse distance
+/// weighted average of the three nearest neighbors.
+///
+/// # Shapes
+///   - xyz: `[batch_size, N, 3]` (dense points)
+///   - sparse_xyz: `[batch_size, M, 3]`
+///   - sparse_features: `[batch_size, M, C]`
+///   - output: `[batch_size, N, C]`
+pub fn three_interpolate<B: Backend>(
+    xyz: Tensor<B, 3>,
+    sparse_xyz: Tensor<B, 3>,
+    sparse_features: Tensor<B, 3>,
+) -> Tensor<B, 3> {
+    let [b, n, _] = xyz.dims();
+    let [_, m, c] = sparse_features.dims();
+
+    if m == 1 {
+        return sparse_features.repeat_dim(1, n);
+    }
+
+    // Three nearest neighbors [B, N, k]
+    let k = m.min(3);
+    let (dist, indices) = square_distance(xyz, sparse_xyz).sort_with_indices(2);
+    let dist = dist.slice([0..b, 0..n, 0..k]);
+    let indices = indices.slice([0..b, 0..n, 0..k]);
+
+    let weights = dist.add_scalar(1e-8).recip();
+    let weights = weights.clone() / weights.sum_dim(2);
+
+    // [B, N, k, C]
+    let neighbors = gather_points(sparse_features, indices);
+
+    (neighbors * weights.unsqueeze_dim::<4>(3))
+        .sum_dim(2)
+        .reshape([b, n, c])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, ElementConversion, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn ball_query_pads_with_first_neighbor() {
+        let device = Default::default();
+        let xyz = Tensor::<TestBackend, 1, Int>::arange(0..10, &device)
+            .float()
+            .reshape([1, 10, 1])
+            .pad((0, 2, 0, 0), 0.);
+        let new_xyz = Tensor::from_floats([[[0., 0., 0.], [5.2, 0., 0.]]], &device);
+
+        let indices = ball_query(2.5, 5, xyz, new_xyz);
+
+        indices.into_data().assert_eq(
+            &TensorData::from([[[0i64, 1, 2, 0, 0], [3, 4, 5, 6, 7]]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn ball_query_within_radius() {
+        let device = Default::default();
+        let radius = 0.3;
+        let xyz = Tensor::<TestBackend, 3>::random([2, 64, 3], Distribution::Default, &device);
+        let new_xyz = xyz.clone().slice([0..2, 0..8, 0..3]);
+
+        let indices = ball_query(radius, 16, xyz.clone(), new_xyz.clone());
+        let neighbors = gather_points(xyz, indices);
+        let dist = (neighbors - new_xyz.unsqueeze_dim::<4>(2))
+            .powf_scalar(2.)
+            .sum_dim(3)
+            .sqrt()
+            .max()
+            .into_scalar()
+            .elem::<f32>();
+
+        assert!(
+            dist <= radius + 1e-5,
+            "neighbor at {dist} outside of the ball"
+        );
+    }
+
+    #[test]
+    fn farthest_point_sampling_spreads_samples() {
+        let device = Default::default();
+        let xyz = Tensor::<TestBackend, 3>::from_floats(
+            [[[0., 0., 0.], [0.1, 0., 0.], [10., 0., 0.], [5., 0., 0.]]],
+            &device,
+        );
+
+        let samples = farthest_point_sampling(xyz, 3);
+
+        samples
+            .into_data()
+            .assert_eq(&TensorData::from([[0i64, 2, 3]]), false);
+    }
+
+    #[test]
+    fn three_interpolate_exact_at_sparse_points() {
+        let device = Default::default();
+        let sparse_xyz = Tensor::<TestBackend, 3>::from_floats(
+            [[[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]],
+            &device,
+        );
+        let sparse_features =
+            Tensor::from_floats([[[1., -1.], [2., -2.], [3., -3.], [4., -4.]]], &device);
+
+        let features = three_interpolate(sparse_xyz.clone(), sparse_xyz, sparse_features);
+
+        features.into_data().assert_approx_eq(
+            &TensorData::from([[[1f32, -1.], [2., -2.], [3., -3.], [4., -4.]]]),
+            4,
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{Dropout, DropoutConfig, Linear, LinearConfig},
+    tensor::{activation::relu, backend::Backend, Device, Tensor},
+};
+
+use super::{ball_query, farthest_point_sampling, gather_points, three_interpolate};
+
+/// Shared multi-layer perceptron applied to each point (or neighbor) independently.
+#[derive(Module, Debug)]
+pub struct SharedMlp<B: Backend> {
+    layers: Vec<Linear<B>>,
+}
+
+impl<B: Backend> SharedMlp<B> {
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        self.layers
+            .iter()
+            .fold(x, |x, layer| relu(layer.forward(x)))
+    }
+}
+
+/// [Shared MLP](SharedMlp) configuration.
+pub struct SharedMlpConfig {
+    layers: Vec<LinearConfig>,
+}
+
+impl SharedMlpConfig {
+    /// Create a new instance of the shared MLP [config](SharedMlpConfig).
+    pub fn new(in_channels: usize, channels: &[usize]) -> Self {
+        let layers = channels
+            .iter()
+            .scan(in_channels, |in_channels, &out_channels| {
+                let layer = LinearConfig::new(*in_channels, out_channels);
+                *in_channels = out_channels;
+                Some(layer)
+            })
+            .collect();
+
+        Self { layers }
+    }
+
+    /// Initialize a new [shared MLP](SharedMlp) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> SharedMlp<B> {
+        SharedMlp {
+            layers: self.layers.iter().map(|l| l.init(device)).collect(),
+        }
+    }
+}
+
+/// PointNet++ set abstraction layer, which samples centroids with farthest point sampling,
+/// groups their neighbors with a [ball query](ball_query) and encodes each local region with a
+/// PointNet (shared MLP followed by max pooling).
+#[derive(Module, Debug)]
+pub struct SetAbstractionLayer<B: Backend> {
+    mlp: SharedMlp<B>,
+    num_points: usize,
+    radius: f32,
+    nsample: usize,
+}
+
+impl<B: Backend> SetAbstractionLayer<B> {
+    /// Abstract the point set.
+    ///
+    /// # Shapes
+    ///   - xyz: `[batch_size, N, 3]`
+    ///   - features: `[batch_size, N, in_channels]`
+    ///   - output: `([batch_size, M, 3], [batch_size, M, out_channels])` with
+    ///     `M = min(num_points, N)`
+    pub fn forward(
+        &self,
+        xyz: Tensor<B, 3>,
+        features: Option<Tensor<B, 3>>,
+    ) -> (Tensor<B, 3>, Tensor<B, 3>) {
+        let [b, n, _] = xyz.dims();
+        let m = self.num_points.min(n);
+
+        // Sample the centroids [B, M, 3]
+        let centroids = farthest_point_sampling(xyz.clone(), m);
+        let new_xyz = gather_points(xyz.clone(), centroids.reshape([b, m, 1])).reshape([b, m, 3]);
+
+        // Group the neighbors with coordinates relative to the centroid [B, M, K, 3 + C]
+        let indices = ball_query(self.radius, self.nsample, xyz.clone(), new_xyz.clone());
+        let grouped_xyz =
+            gather_points(xyz, indices.clone()) - new_xyz.clone().unsqueeze_dim::<4>(2);
+        let grouped = match features {
+            Some(features) => Tensor::cat(vec![grouped_xyz, gather_points(features, indices)], 3),
+            None => grouped_xyz,
+        };
+
+        // PointNet on each local region
+        let new_features = self.mlp.forward(grouped).max_dim(2);
+        let [_, _, _, c] = new_features.dims();
+
+        (new_xyz, new_features.reshape([b, m, c]))
+    }
+}
+
+/// [Set abstraction layer](SetAbstractionLayer) configuration.
+pub struct SetAbstractionLayerConfig {
+    mlp: SharedMlpConfig,
+    num_points: usize,
+    radius: f32,
+    nsample: usize,
+}
+
+impl SetAbstractionLayerConfig {
+    /// Create a new instance of the set abstraction layer [config](SetAbstractionLayerConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `num_points`: Number of sampled centroids.
+    /// * `radius` - Radius of the local regions.
+    /// * `nsample` - Number of neighbors of each local region.
+    /// * `in_channels` - Number of input point features (excluding the coordinates).
+    /// * `mlp` - Number of channels of each layer of the PointNet.
+    pub fn new(
+        num_points: usize,
+        radius: f32,
+        nsample: usize,
+        in_channels: usize,
+        mlp: &[usize],
+    ) -> Self {
+        Self {
+            mlp: SharedMlpConfig::new(in_channels + 3, mlp),
+            num_points,
+            radius,
+            nsample,
+        }
+    }
+
+    /// Initialize a new [set abstraction layer](SetAbstractionLayer) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> SetAbstractionLayer<B> {
+        SetAbstractionLayer {
+            mlp: self.mlp.init(device),
+            num_points: self.num_points,
+            radius: self.radius,
+            nsample: self.nsample,
+        }
+    }
+}
+
+/// PointNet++ feature propagation layer, which upsamples the features of a sparse point set to a
+/// dense point set with [inverse distance weighted interpolation](three_interpolate).
+#[derive(Module, Debug)]
+pub struct FeaturePropagationLayer<B: Backend> {
+    mlp: SharedMlp<B>,
+}
+
+impl<B: Backend> FeaturePropagationLayer<B> {
+    /// Propagate the sparse features to the dense points.
+    ///
+    /// # Shapes
+    ///   - xyz: `[batch_size, N, 3]`
+    ///   - sparse_xyz: `[batch_size, M, 3]`
+    ///   - features: `[batch_size, N, skip_channels]` (skip connection of the dense points)
+    ///   - sparse_features: `[batch_size, M, in_channels]`
+    ///   - output: `[batch_size, N, out_channels]`
+    pub fn forward(
+        &self,
+        xyz: Tensor<B, 3>,
+        sparse_xyz: Tensor<B, 3>,
+        features: Option<Tensor<B, 3>>,
+        sparse_features: Tensor<B, 3>,
+    ) -> Tensor<B, 3> {
+        let interpolated = three_interpolate(xyz, sparse_xyz, sparse_features);
+
+        let x = match features {
+            Some(features) => Tensor::cat(vec![interpolated, features], 2),
+            None => interpolated,
+        };
+
+        self.mlp.forward(x)
+    }
+}
+
+/// [Feature propagation layer](FeaturePropagationLayer) configuration.
+pub struct FeaturePropagationLayerConfig {
+    mlp: SharedMlpConfig,
+}
+
+impl FeaturePropagationLayerConfig {
+    /// Create a new instance of the feature propagation layer
+    /// [config](FeaturePropagationLayerConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of interpolated and skip connection features.
+    /// * `mlp` - Number of channels of each layer of the shared MLP.
+    pub fn new(in_channels: usize, mlp: &[usize]) -> Self {
+        Self {
+            mlp: SharedMlpConfig::new(in_channels, mlp),
+        }
+    }
+
+    /// Initialize a new [feature propagation layer](FeaturePropagationLayer) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> FeaturePropagationLayer<B> {
+        FeaturePropagationLayer {
+            mlp: self.mlp.init(device),
+        }
+    }
+}
+
+/// [PointNet++](https://arxiv.org/abs/1706.02413) segmentation network (single-scale grouping).
+#[derive(Module, Debug)]
+pub struct PointNetPp<B: Backend> {
+    sa: Vec<SetAbstractionLayer<B>>,
+    fp: Vec<FeaturePropagationLayer<B>>,
+    fc: Linear<B>,
+    dropout: Dropout,
+    cls: Linear<B>,
+}
+
+impl<B: Backend> PointNetPp<B> {
+    /// Compute the per-point class scores.
+    ///
+    /// # Shapes
+    ///   - xyz: `[batch_size, N, 3]`
+    ///   - features: `[batch_size, N, input_dim]`
+    ///   - output: `[batch_size, N, num_classes]`
+    pub fn forward(&self, xyz: Tensor<B, 3>, features: Option<Tensor<B, 3>>) -> Tensor<B, 3> {
+        // Encoder
+        let mut levels = vec![(xyz, features)];
+        for sa in self.sa.iter() {
+            let (xyz, features) = levels.last().unwrap().clone();
+            let (xyz, features) = sa.forward(xyz, features);
+            levels.push((xyz, Some(features)));
+        }
+
+        // Decoder, from the sparsest to the densest level
+        let (mut sparse_xyz, sparse_features) = levels.pop().unwrap();
+        let mut sparse_features = sparse_features.unwrap();
+        for fp in self.fp.iter() {
+            let (xyz, features) = levels.pop().unwrap();
+            sparse_features = fp.forward(xyz.clone(), sparse_xyz, features, sparse_features);
+            sparse_xyz = xyz;
+        }
+
+        let x = relu(self.fc.forward(sparse_features));
+        let x = self.dropout.forward(x);
+
+        self.cls.forward(x)
+    }
+}
+
+/// [PointNet++](PointNetPp) configuration.
+pub struct PointNetPpConfig {
+    sa: Vec<SetAbstractionLayerConfig>,
+    fp: Vec<FeaturePropagationLayerConfig>,
+    fc: LinearConfig,
+    dropout: DropoutConfig,
+    cls: LinearConfig,
+}
+
+impl PointNetPpConfig {
+    /// Create a new instance of the PointNet++ [config](PointNetPpConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `num_classes`: Number of output classes of the model.
+    /// * `input_dim` - Number of input point features, excluding the coordinates (e.g., 3 for
+    ///   RGB colors or 0 for coordinates only).
+    pub fn new(num_classes: usize, input_dim: usize) -> Self {
+        // (num_points, radius, nsample, mlp)
+        let sa_params: [(usize, f32, usize, [usize; 3]); 4] = [
+            (1024, 0.1, 32, [32, 32, 64]),
+            (256, 0.2, 32, [64, 64, 128]),
+            (64, 0.4, 32, [128, 128, 256]),
+            (16, 0.8, 32, [256, 256, 512]),
+        ];
+
+        let mut in_channels = input_dim;
+        let mut skip_channels = vec![];
+        let sa = sa_params
+            .iter()
+            .map(|(num_points, radius, nsample, mlp)| {
+                skip_channels.push(in_channels);
+                let sa = SetAbstractionLayerConfig::new(
+                    *num_points,
+                    *radius,
+                    *nsample,
+                    in_channels,
+                    mlp,
+                );
+                in_channels = mlp[2];
+                sa
+            })
+            .collect();
+
+        let fp_mlps: [&[usize]; 4] = [&[256, 256], &[256, 256], &[256, 128], &[128, 128, 128]];
+        let fp = fp_mlps
+            .iter()
+            .zip(skip_channels.iter().rev())
+            .map(|(mlp, skip)| {
+                let fp = FeaturePropagationLayerConfig::new(in_channels + skip, mlp);
+                in_channels = mlp[mlp.len() - 1];
+                fp
+            })
+            .collect();
+
+        Self {
+            sa,
+            fp,
+            fc: LinearConfig::new(in_channels, 128),
+            dropout: DropoutConfig::new(0.5),
+            cls: LinearConfig::new(128, num_classes),
+        }
+    }
+
+    /// Initialize a new [PointNet++](PointNetPp) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> PointNetPp<B> {
+        PointNetPp {
+            sa: self.sa.iter().map(|l| l.init(device)).collect(),
+            fp: self.fp.iter().map(|l| l.init(device)).collect(),
+            fc: self.fc.init(device),
+            dropout: self.dropout.init(),
+            cls: self.cls.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn set_abstraction_reduces_points() {
+        let device = Default::default();
+        let sa =
+            SetAbstractionLayerConfig::new(16, 0.4, 8, 3, &[8, 16]).init::<TestBackend>(&device);
+        let xyz = Tensor::random([2, 64, 3], Distribution::Default, &device);
+        let features = Tensor::random([2, 64, 3], Distribution::Default, &device);
+
+        let (new_xyz, new_features) = sa.forward(xyz, Some(features));
+
+        assert_eq!(new_xyz.dims(), [2, 16, 3]);
+        assert_eq!(new_features.dims(), [2, 16, 16]);
+    }
+
+    #[test]
+    fn feature_propagation_restores_points() {
+        let device = Default::default();
+        let sa =
+            SetAbstractionLayerConfig::new(16, 0.4, 8, 0, &[8, 16]).init::<TestBackend>(&device);
+        let fp = FeaturePropagationLayerConfig::new(16, &[16, 8]).init(&device);
+        let xyz = Tensor::random([2, 64, 3], Distribution::Default, &device);
+
+        let (sparse_xyz, sparse_features) = sa.forward(xyz.clone(), None);
+        let features = fp.forward(xyz, sparse_xyz, None, sparse_features);
+
+        assert_eq!(features.dims(), [2, 64, 8]);
+    }
+
+    #[test]
+    fn pointnet_pp_per_point_scores() {
+        let device = Default::default();
+        let model = PointNetPpConfig::new(5, 3).init::<TestBackend>(&device);
+        let xyz = Tensor::random([1, 128, 3], Distribution::Default, &device);
+        let colors = Tensor::random([1, 128, 3], Distribution::Default, &device);
+
+        let scores = model.forward(xyz, Some(colors));
+
+        assert_eq!(scores.dims(), [1, 128, 5]);
+    }
+}
@@ -0,0 +1,327 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d,
+    },
+    tensor::{
+        backend::Backend, module::max_pool2d, Device, ElementConversion, Int, Tensor, TensorData,
+    },
+};
+
+/// Sparse 2D feature map in coordinate (COO) format, e.g., LiDAR points projected to a
+/// bird's-eye view grid.
+///
+/// Only the active sites are stored: the sites which are not listed have zero features.
+#[derive(Debug, Clone)]
+pub struct SparseTensor<B: Backend> {
+    /// Features of the active sites. Shape: `[num_active, channels]`.
+    pub features: Tensor<B, 2>,
+    /// Coordinates `(batch_index, y, x)` of the active sites, without duplicates.
+    /// Shape: `[num_active, 3]`.
+    pub indices: Tensor<B, 2, Int>,
+    /// Spatial shape `[height, width]` of the feature map.
+    pub spatial_shape: Vec<usize>,
+    /// Number of samples of the batch.
+    pub batch_size: usize,
+}
+
+impl<B: Backend> SparseTensor<B> {
+    /// Create a new sparse tensor.
+    pub fn new(
+        features: Tensor<B, 2>,
+        indices: Tensor<B, 2, Int>,
+        spatial_shape: Vec<usize>,
+        batch_size: usize,
+    ) -> Self {
+        assert_eq!(spatial_shape.len(), 2, "expected a 2D spatial shape");
+
+        Self {
+            features,
+            indices,
+            spatial_shape,
+            batch_size,
+        }
+    }
+
+    /// Number of active sites.
+    pub fn num_active(&self) -> usize {
+        let [num_active, _] = self.features.dims();
+        num_active
+    }
+
+    /// Indices of the active sites in the flattened `[batch_size * height * width]` feature map.
+    fn flat_indices(&self) -> Tensor<B, 1, Int> {
+        let [h, w] = [self.spatial_shape[0], self.spatial_shape[1]];
+        let n = self.num_active();
+        let column = |i: usize| self.indices.clone().slice([0..n, i..i + 1]).reshape([n]);
+
+        column(0).mul_scalar((h * w) as i64) + column(1).mul_scalar(w as i64) + column(2)
+    }
+
+    /// Convert to a dense feature map.
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, channels, height, width]`
+    pub fn to_dense(&self) -> Tensor<B, 4> {
+        let [h, w] = [self.spatial_shape[0], self.spatial_shape[1]];
+        let [_, c] = self.features.dims();
+        let b = self.batch_size;
+
+        Tensor::zeros([b * h * w, c], &self.features.device())
+            .select_assign(0, self.flat_indices(), self.features.clone())
+            .reshape([b, h, w, c])
+            .permute([0, 3, 1, 2])
+    }
+
+    /// Dense mask of the active sites (1 for active sites, 0 otherwise).
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, 1, height, width]`
+    pub fn active_mask(&self) -> Tensor<B, 4> {
+        let [h, w] = [self.spatial_shape[0], self.spatial_shape[1]];
+        let b = self.batch_size;
+        let device = self.features.device();
+
+        Tensor::zeros([b * h * w], &device)
+            .select_assign(
+                0,
+                self.flat_indices(),
+                Tensor::ones([self.num_active()], &device),
+            )
+            .reshape([b, 1, h, w])
+    }
+
+    /// Convert a dense feature map to a sparse tensor, keeping the sites where the mask is
+    /// positive.
+    ///
+    /// # Shapes
+    ///   - dense: `[batch_size, channels, height, width]`
+    ///   - mask: `[batch_size, 1, height, width]`
+    pub fn from_dense(dense: Tensor<B, 4>, mask: Tensor<B, 4>) -> Self {
+        let [b, c, h, w] = dense.dims();
+        let device = dense.device();
+
+        // Coordinates of the active sites on the host
+        let flat: Vec<i64> = mask
+            .into_data()
+            .iter::<B::FloatElem>()
+            .enumerate()
+            .filter(|(_, v)| v.elem::<f32>() > 0.)
+            .map(|(i, _)| i as i64)
+            .collect();
+        let num_active = flat.len();
+        let coords: Vec<i64> = flat
+            .iter()
+            .flat_map(|&i| {
+                let (batch, cell) = (i / (h * w) as i64, i % (h * w) as i64);
+                [batch, cell / w as i64, cell % w as i64]
+            })
+            .collect();
+
+        let int_tensor = |data: Vec<i64>, shape: Vec<usize>| {
+            Tensor::<B, 1, Int>::from_data(
+                TensorData::new(data, shape).convert::<B::IntElem>(),
+                &device,
+            )
+        };
+        let flat = int_tensor(flat, vec![num_active]);
+        let indices = int_tensor(coords, vec![num_active * 3]).reshape([num_active, 3]);
+
+        let features = dense
+            .permute([0, 2, 3, 1])
+            .reshape([b * h * w, c])
+            .select(0, flat);
+
+        Self::new(features, indices, vec![h, w], b)
+    }
+}
+
+/// Sparse 2D convolution, computed on the dense feature map and masked to the output active
+/// sites, as burn has no native sparse convolution.
+///
+/// A regular sparse convolution activates every output site whose receptive field contains an
+/// active input site, which dilates the active sites. A submanifold sparse convolution only
+/// computes the outputs at the input active sites, so the sparsity is preserved through the
+/// network.
+#[derive(Module, Debug)]
+pub struct SparseConv2d<B: Backend> {
+    conv: Conv2d<B>,
+    kernel_size: usize,
+    stride: usize,
+    submanifold: bool,
+}
+
+impl<B: Backend> SparseConv2d<B> {
+    /// # Shapes
+    ///   - sparse_input: `[num_active, in_channels]` features on a `[height, width]` grid
+    ///   - output: `[num_active_out, out_channels]` features on a
+    ///     `[height / stride, width / stride]` grid
+    pub fn forward(&self, sparse_input: SparseTensor<B>) -> SparseTensor<B> {
+        let mask = sparse_input.active_mask();
+        let out = self.conv.forward(sparse_input.to_dense());
+
+        let out_mask = if self.submanifold {
+            mask
+        } else {
+            let (k, s, p) = (self.kernel_size, self.stride, self.kernel_size / 2);
+            max_pool2d(mask, [k, k], [s, s], [p, p], [1, 1])
+        };
+
+        SparseTensor::from_dense(out, out_mask)
+    }
+
+    /// Whether the output active sites are restricted to the input active sites.
+    pub fn is_submanifold(&self) -> bool {
+        self.submanifold
+    }
+}
+
+/// [Sparse convolution](SparseConv2d) configuration.
+pub struct SparseConv2dConfig {
+    conv: Conv2dConfig,
+    kernel_size: usize,
+    stride: usize,
+    submanifold: bool,
+}
+
+impl SparseConv2dConfig {
+    /// Create a new instance of the sparse convolution [config](SparseConv2dConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input channels.
+    /// * `out_channels` - Number of output channels.
+    /// * `kernel_size` - Size of the (square, odd) kernel.
+    /// * `stride` - Stride of the convolution, which should be 1 for submanifold convolutions.
+    /// * `submanifold` - Restrict the output active sites to the input active sites.
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel_size: usize,
+        stride: usize,
+        submanifold: bool,
+    ) -> Self {
+        assert!(kernel_size % 2 == 1, "kernel size should be odd");
+        assert!(
+            !submanifold || stride == 1,
+            "submanifold convolutions should have a stride of 1"
+        );
+
+        let pad = kernel_size / 2;
+        let conv = Conv2dConfig::new([in_channels, out_channels], [kernel_size, kernel_size])
+            .with_stride([stride, stride])
+            .with_padding(PaddingConfig2d::Explicit(pad, pad));
+
+        Self {
+            conv,
+            kernel_size,
+            stride,
+            submanifold,
+        }
+    }
+
+    /// Initialize a new [sparse convolution](SparseConv2d) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> SparseConv2d<B> {
+        SparseConv2d {
+            conv: self.conv.init(device),
+            kernel_size: self.kernel_size,
+            stride: self.stride,
+            submanifold: self.submanifold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    /// A single active site at `(y, x) = (2, 3)` of the second sample on a 5x6 grid.
+    fn single_site(device: &Device<TestBackend>) -> SparseTensor<TestBackend> {
+        SparseTensor::new(
+            Tensor::from_floats([[1., -2.]], device),
+            Tensor::from_ints([[1, 2, 3]], device),
+            vec![5, 6],
+            2,
+        )
+    }
+
+    #[test]
+    fn dense_round_trip() {
+        let device = Default::default();
+        let sparse = single_site(&device);
+
+        let dense = sparse.to_dense();
+        assert_eq!(dense.dims(), [2, 2, 5, 6]);
+        let mask = sparse.active_mask();
+        assert_eq!(mask.clone().sum().into_scalar(), 1.);
+
+        let sparse = SparseTensor::from_dense(dense, mask);
+        assert_eq!(sparse.spatial_shape, [5, 6]);
+        assert_eq!(sparse.batch_size, 2);
+        sparse
+            .features
+            .into_data()
+            .assert_eq(&TensorData::from([[1f32, -2.]]), false);
+        sparse
+            .indices
+            .into_data()
+            .assert_eq(&TensorData::from([[1i64, 2, 3]]), false);
+    }
+
+    #[test]
+    fn regular_conv_dilates_active_sites() {
+        let device = Default::default();
+        let conv = SparseConv2dConfig::new(2, 4, 3, 1, false).init::<TestBackend>(&device);
+
+        let out = conv.forward(single_site(&device));
+
+        // 3x3 neighborhood of the input active site
+        let expected: Vec<i64> = (1..4)
+            .flat_map(|y| (2..5).flat_map(move |x| [1, y, x]))
+            .collect();
+        assert_eq!(out.num_active(), 9);
+        assert_eq!(out.features.dims(), [9, 4]);
+        out.indices
+            .into_data()
+            .assert_eq(&TensorData::new(expected, [9, 3]), false);
+    }
+
+    #[test]
+    fn submanifold_conv_keeps_active_sites() {
+        let device = Default::default();
+        let conv = SparseConv2dConfig::new(2, 4, 3, 1, true).init::<TestBackend>(&device);
+        assert!(conv.is_submanifold());
+
+        let out = conv.forward(single_site(&device));
+
+        assert_eq!(out.num_active(), 1);
+        out.indices
+            .into_data()
+            .assert_eq(&TensorData::from([[1i64, 2, 3]]), false);
+    }
+
+    #[test]
+    fn strided_conv_spatial_shape() {
+        let device = Default::default();
+        let conv = SparseConv2dConfig::new(2, 4, 3, 2, false).init::<TestBackend>(&device);
+
+        let out = conv.forward(single_site(&device));
+
+        assert_eq!(out.spatial_shape, [3, 3]);
+        // Output sites whose receptive field contains (2, 3)
+        out.indices
+            .into_data()
+            .assert_eq(&TensorData::from([[1i64, 1, 1], [1, 1, 2]]), false);
+    }
+
+    #[test]
+    #[should_panic = "submanifold convolutions should have a stride of 1"]
+    fn strided_submanifold_conv() {
+        SparseConv2dConfig::new(2, 4, 3, 2, true);
+    }
+}
@@ -0,0 +1,287 @@
+use alloc::vec::Vec;
+use burn::tensor::{backend::Backend, module::max_pool2d, ElementConversion, Tensor};
+
+use crate::types::ConfThreshold;
+
+/// Minimum heatmap value before the log transform of [DARK](gaussian_heatmap_to_coords).
+const LOG_EPSILON: f32 = 1e-10;
+
+/// Peak of a heatmap (e.g., a CenterNet object center or a pose keypoint).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keypoint {
+    /// Column of the peak, in heatmap pixels.
+    pub x: f32,
+    /// Row of the peak, in heatmap pixels.
+    pub y: f32,
+    /// Heatmap value at the peak.
+    pub score: f32,
+    /// Heatmap channel (e.g., class or keypoint type).
+    pub channel: usize,
+}
+
+/// Extract the highest peaks of the heatmaps of each image.
+///
+/// The local maxima are the points equal to the max-pooled heatmap, which replaces non-maximum
+/// suppression in [CenterNet](https://arxiv.org/abs/1904.07850).
+///
+/// # Arguments
+///
+/// * `heatmap`: Heatmaps with shape `[batch_size, channels, height, width]`.
+/// * `num_peaks` - Maximum number of peaks per image, over all the channels.
+/// * `kernel_size` - Size of the max-pooling window (odd), i.e., the neighborhood of each peak.
+/// * `score_threshold` - Minimum heatmap value of the peaks.
+///
+/// # Returns
+///
+/// The peaks of each image, sorted by decreasing score.
+pub fn heatmap_to_peaks<B: Backend>(
+    heatmap: Tensor<B, 4>,
+    num_peaks: usize,
+    kernel_size: usize,
+    score_threshold: ConfThreshold,
+) -> Vec<Vec<Keypoint>> {
+    assert!(kernel_size % 2 == 1, "kernel size should be odd");
+    let [b, c, h, w] = heatmap.dims();
+    let pad = kernel_size / 2;
+
+    // Keep the local maxima only
+    let pooled = max_pool2d(
+        heatmap.clone(),
+        [kernel_size, kernel_size],
+        [1, 1],
+        [pad, pad],
+        [1, 1],
+    );
+    let keep = heatmap.clone().equal(pooled).float();
+    let scores: Vec<f32> = (heatmap * keep)
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>())
+        .collect();
+
+    let num_cells = h * w;
+    scores
+        .chunks(c * num_cells)
+        .take(b)
+        .map(|scores| {
+            let mut peaks: Vec<_> = scores
+                .iter()
+                .enumerate()
+                .filter(|(_, &score)| score > 0. && score >= score_threshold.value())
+                .map(|(idx, &score)| {
+                    let (channel, cell) = (idx / num_cells, idx % num_cells);
+                    Keypoint {
+                        x: (cell % w) as f32,
+                        y: (cell / w) as f32,
+                        score,
+                        channel,
+                    }
+                })
+                .collect();
+            peaks.sort_unstable_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+            peaks.truncate(num_peaks);
+            peaks
+        })
+        .collect()
+}
+
+/// Locate the maximum of each heatmap (e.g., of each keypoint of top-down pose estimation).
+///
+/// With `use_dark`, the integer location of the maximum is refined to sub-pixel accuracy with
+/// [DARK](https://arxiv.org/abs/1910.06278) (Distribution-Aware coordinate Representation of
+/// Keypoints): the log-heatmap is approximated by its second order Taylor expansion at the
+/// maximum, whose extremum is the center of a Gaussian heatmap. The heatmaps are expected to be
+/// smooth (e.g., predicted from Gaussian targets), as the modulation of the paper is not applied.
+///
+/// # Shapes
+///   - heatmap: `[batch_size, num_keypoints, height, width]`
+///   - output: `[batch_size, num_keypoints, 2]` coordinates `(x, y)` in heatmap pixels
+pub fn gaussian_heatmap_to_coords<B: Backend>(
+    heatmap: Tensor<B, 4>,
+    use_dark: bool,
+) -> Tensor<B, 3> {
+    let [b, k, h, w] = heatmap.dims();
+    let device = heatmap.device();
+    let values: Vec<f32> = heatmap
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>())
+        .collect();
+
+    let coords: Vec<f32> = values
+        .chunks(h * w)
+        .take(b * k)
+        .flat_map(|hm| {
+            let (idx, _) =
+                hm.iter()
+                    .enumerate()
+                    .fold((0, f32::NEG_INFINITY), |(best, max), (i, &v)| {
+                        if v > max {
+                            (i, v)
+                        } else {
+                            (best, max)
+                        }
+                    });
+            let (x, y) = (idx % w, idx / w);
+
+            let (dx, dy) = if use_dark {
+                dark_offset(hm, [h, w], x, y)
+            } else {
+                (0., 0.)
+            };
+
+            [x as f32 + dx, y as f32 + dy]
+        })
+        .collect();
+
+    Tensor::<B, 1>::from_floats(coords.as_slice(), &device).reshape([b, k, 2])
+}
+
+/// Sub-pixel offset `(dx, dy)` of the maximum at `(x, y)` of a heatmap, i.e., `-H^-1 * g` with
+/// the gradient `g` and the hessian `H` of the log-heatmap.
+fn dark_offset(heatmap: &[f32], [h, w]: [usize; 2], x: usize, y: usize) -> (f32, f32) {
+    // The second derivatives require two pixels on each side
+    if x < 2 || x + 2 >= w || y < 2 || y + 2 >= h {
+        return (0., 0.);
+    }
+
+    let at = |x: usize, y: usize| heatmap[y * w + x].max(LOG_EPSILON).ln();
+    let center = at(x, y);
+
+    let dx = 0.5 * (at(x + 1, y) - at(x - 1, y));
+    let dy = 0.5 * (at(x, y + 1) - at(x, y - 1));
+    let dxx = 0.25 * (at(x + 2, y) - 2. * center + at(x - 2, y));
+    let dyy = 0.25 * (at(x, y + 2) - 2. * center + at(x, y - 2));
+    let dxy = 0.25 * (at(x + 1, y + 1) - at(x + 1, y - 1) - at(x - 1, y + 1) + at(x - 1, y - 1));
+
+    let det = dxx * dyy - dxy * dxy;
+    if det.abs() < f32::EPSILON {
+        return (0., 0.);
+    }
+
+    (-(dyy * dx - dxy * dy) / det, -(dxx * dy - dxy * dx) / det)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    fn threshold() -> ConfThreshold {
+        ConfThreshold::new(0.1).unwrap()
+    }
+
+    /// Gaussian heatmap centered on `(cx, cy)`.
+    fn gaussian([h, w]: [usize; 2], (cx, cy): (f32, f32), sigma: f32) -> Vec<f32> {
+        (0..h * w)
+            .map(|i| {
+                let (x, y) = ((i % w) as f32, (i / w) as f32);
+                let d2 = (x - cx).powi(2) + (y - cy).powi(2);
+                (-d2 / (2. * sigma * sigma)).exp()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_peak() {
+        let device = Default::default();
+        let mut values = alloc::vec![0.; 9 * 11];
+        values.extend(gaussian([9, 11], (5., 3.), 1.5));
+        let heatmap =
+            Tensor::<TestBackend, 4>::from_data(TensorData::new(values, [1, 2, 9, 11]), &device);
+
+        let peaks = heatmap_to_peaks(heatmap, 10, 3, threshold());
+
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(
+            peaks[0],
+            [Keypoint {
+                x: 5.,
+                y: 3.,
+                score: 1.,
+                channel: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn peaks_sorted_and_truncated() {
+        let device = Default::default();
+        let mut values = alloc::vec![0f32; 2 * 8 * 8];
+        values[8 + 1] = 0.9;
+        values[4 * 8 + 6] = 0.5;
+        values[64 + 7 * 8 + 2] = 0.7;
+        values[64 + 3 * 8 + 3] = 0.05;
+        let heatmap = Tensor::<TestBackend, 4>::from_data(
+            TensorData::new(values.clone(), [2, 1, 8, 8]),
+            &device,
+        );
+
+        let peaks = heatmap_to_peaks(heatmap, 2, 3, threshold());
+
+        assert_eq!(peaks.len(), 2);
+        let keypoint = |x, y, score| Keypoint {
+            x,
+            y,
+            score,
+            channel: 0,
+        };
+        assert_eq!(peaks[0], [keypoint(1., 1., 0.9), keypoint(6., 4., 0.5)]);
+        // The peak below the threshold is ignored
+        assert_eq!(peaks[1], [keypoint(2., 7., 0.7)]);
+
+        let peaks = heatmap_to_peaks(
+            Tensor::<TestBackend, 4>::from_data(TensorData::new(values, [2, 1, 8, 8]), &device),
+            1,
+            3,
+            threshold(),
+        );
+        assert_eq!(peaks[0], [keypoint(1., 1., 0.9)]);
+    }
+
+    #[test]
+    fn dark_gaussian_center() {
+        let device = Default::default();
+        let values = gaussian([16, 16], (5.3, 4.6), 2.);
+        let heatmap =
+            Tensor::<TestBackend, 4>::from_data(TensorData::new(values, [1, 1, 16, 16]), &device);
+
+        let coords = gaussian_heatmap_to_coords(heatmap.clone(), false);
+        coords
+            .into_data()
+            .assert_eq(&TensorData::from([[[5f32, 5.]]]), false);
+
+        // Sub-pixel location of the maximum
+        let coords = gaussian_heatmap_to_coords(heatmap, true);
+        coords
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[5.3f32, 4.6]]]), 3);
+    }
+
+    #[test]
+    fn dark_at_border() {
+        let device = Default::default();
+        let values = gaussian([8, 8], (0.4, 7.), 1.);
+        let heatmap =
+            Tensor::<TestBackend, 4>::from_data(TensorData::new(values, [1, 1, 8, 8]), &device);
+
+        // Not enough pixels to refine the location
+        let coords = gaussian_heatmap_to_coords(heatmap, true);
+        coords
+            .into_data()
+            .assert_eq(&TensorData::from([[[0f32, 7.]]]), false);
+    }
+
+    #[test]
+    #[should_panic = "kernel size should be odd"]
+    fn even_kernel_size() {
+        heatmap_to_peaks(
+            Tensor::<TestBackend, 4>::zeros([1, 1, 4, 4], &Default::default()),
+            1,
+            2,
+            threshold(),
+        );
+    }
+}
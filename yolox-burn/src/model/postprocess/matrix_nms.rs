@@ -0,0 +1,229 @@
+use alloc::vec::Vec;
+use burn::tensor::{backend::Backend, Device, ElementConversion, Int, Tensor, TensorData};
+
+use crate::types::{ConfThreshold, IoUThreshold};
+
+/// Small value to avoid divisions by zero.
+const EPSILON: f32 = 1e-6;
+
+/// Score decay function of [matrix NMS](matrix_nms).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MatrixNmsMethod {
+    /// `exp(-(iou² - compensate_iou²) / sigma)`
+    #[default]
+    Gaussian,
+    /// `(1 - iou) / (1 - compensate_iou)`
+    Linear,
+}
+
+/// Matrix non-maximum suppression (NMS), as introduced in [SOLOv2](https://arxiv.org/abs/2003.10152).
+///
+/// Instead of greedily removing the overlapping masks one at a time, the score of each mask is
+/// decayed in parallel according to its maximum IoU with the higher scoring masks, compensated by
+/// how much these masks are themselves suppressed.
+///
+/// # Arguments
+///
+/// * `masks`: Instance masks (probabilities or binary masks, binarized at 0.5). Shape:
+///   `[num_masks, height, width]`.
+/// * `scores` - Score of each mask. Shape: `[num_masks]`.
+/// * `iou_threshold` - Minimum IoU for a higher scoring mask to decay the score of another mask.
+/// * `score_threshold` - Masks with a decayed score lower than or equal to this threshold are
+///   removed.
+/// * `method` - Score decay function.
+/// * `sigma` - Spread of the [gaussian](MatrixNmsMethod::Gaussian) decay.
+///
+/// # Returns
+///
+/// The kept masks with shape `[num_kept, height, width]` and their decayed scores with shape
+/// `[num_kept]`, sorted in decreasing order of the original scores.
+pub fn matrix_nms<B: Backend>(
+    masks: Tensor<B, 3>,
+    scores: Tensor<B, 1>,
+    iou_threshold: IoUThreshold,
+    score_threshold: ConfThreshold,
+    method: MatrixNmsMethod,
+    sigma: f32,
+) -> (Tensor<B, 3>, Tensor<B, 1>) {
+    let device = masks.device();
+    let [n, height, width] = masks.dims();
+    if n == 0 {
+        return (masks, scores);
+    }
+
+    // Sort the masks in decreasing order of scores
+    let values: Vec<f32> = scores
+        .clone()
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>())
+        .collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| values[b].partial_cmp(&values[a]).unwrap());
+    let order = indices_tensor::<B>(order, &device);
+    let masks = masks.select(0, order.clone());
+    let scores = scores.select(0, order);
+
+    // Pairwise IoU of the flattened binary masks [N, N]
+    let flat = masks
+        .clone()
+        .greater_elem(0.5)
+        .float()
+        .reshape([n, height * width]);
+    let areas = flat.clone().sum_dim(1);
+    let intersection = flat.clone().matmul(flat.transpose());
+    let union = areas.clone() + areas.transpose() - intersection.clone();
+    let iou = intersection / union.clamp_min(EPSILON);
+
+    // Only keep the IoU with the higher scoring masks (i.e., row i < column j)
+    let iou = iou.triu(1);
+    let iou = iou
+        .clone()
+        .mask_fill(iou.clone().lower_elem(iou_threshold.value()), 0.);
+
+    // Maximum IoU of each mask with a higher scoring mask, broadcast along the rows [N, 1]
+    let compensate_iou = iou.clone().max_dim(0).transpose();
+
+    let decay = match method {
+        MatrixNmsMethod::Gaussian => {
+            ((compensate_iou.powf_scalar(2.) - iou.powf_scalar(2.)) / sigma).exp()
+        }
+        MatrixNmsMethod::Linear => {
+            iou.neg().add_scalar(1.) / compensate_iou.neg().add_scalar(1.).clamp_min(EPSILON)
+        }
+    };
+    let decay = decay.min_dim(0).reshape([n]);
+    let scores = scores * decay;
+
+    // Remove the suppressed masks
+    let keep: Vec<usize> = scores
+        .clone()
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>())
+        .enumerate()
+        .filter(|(_, score)| *score > score_threshold.value())
+        .map(|(i, _)| i)
+        .collect();
+    let keep = indices_tensor::<B>(keep, &device);
+
+    (masks.select(0, keep.clone()), scores.select(0, keep))
+}
+
+fn indices_tensor<B: Backend>(indices: Vec<usize>, device: &Device<B>) -> Tensor<B, 1, Int> {
+    let num_indices = indices.len();
+    let indices: Vec<i64> = indices.into_iter().map(|i| i as i64).collect();
+
+    Tensor::from_data(
+        TensorData::new(indices, [num_indices]).convert::<B::IntElem>(),
+        device,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn scores_of(scores: Tensor<TestBackend, 1>) -> Vec<f32> {
+        scores.into_data().to_vec::<f32>().unwrap()
+    }
+
+    #[test]
+    fn identical_masks_suppressed() {
+        let device = Default::default();
+        let masks = Tensor::<TestBackend, 3>::from_floats(
+            [[[1., 1.], [0., 0.]], [[1., 1.], [0., 0.]]],
+            &device,
+        );
+        let scores = Tensor::from_floats([0.8, 0.9], &device);
+        let iou_threshold = IoUThreshold::new(0.5).unwrap();
+
+        // Gaussian decay of the lower scoring mask: exp(-1 / 0.5)
+        let (kept, decayed) = matrix_nms(
+            masks.clone(),
+            scores.clone(),
+            iou_threshold,
+            ConfThreshold::new(0.).unwrap(),
+            MatrixNmsMethod::Gaussian,
+            0.5,
+        );
+        assert_eq!(kept.dims(), [2, 2, 2]);
+        let decayed = scores_of(decayed);
+        assert!((decayed[0] - 0.9).abs() < 1e-5);
+        assert!((decayed[1] - 0.8 * (-2f32).exp()).abs() < 1e-5);
+
+        // Linear decay of the lower scoring mask to zero
+        let (kept, decayed) = matrix_nms(
+            masks,
+            scores,
+            iou_threshold,
+            ConfThreshold::new(0.).unwrap(),
+            MatrixNmsMethod::Linear,
+            0.5,
+        );
+        assert_eq!(kept.dims(), [1, 2, 2]);
+        assert_eq!(scores_of(decayed), vec![0.9]);
+    }
+
+    #[test]
+    fn zero_threshold_keeps_non_overlapping_masks() {
+        let device = Default::default();
+        let masks = Tensor::<TestBackend, 3>::from_floats(
+            [
+                [[1., 0.], [0., 0.]],
+                [[0., 1.], [0., 0.]],
+                [[0., 0.], [1., 1.]],
+            ],
+            &device,
+        );
+        let scores = Tensor::from_floats([0.1, 0.7, 0.4], &device);
+
+        let (kept, decayed) = matrix_nms(
+            masks,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.).unwrap(),
+            MatrixNmsMethod::Gaussian,
+            2.,
+        );
+
+        assert_eq!(kept.dims(), [3, 2, 2]);
+        // Sorted by scores, without any decay
+        assert_eq!(scores_of(decayed), vec![0.7, 0.4, 0.1]);
+        assert_eq!(
+            kept.slice([0..1, 0..2, 0..2])
+                .into_data()
+                .to_vec::<f32>()
+                .unwrap(),
+            vec![0., 1., 0., 0.]
+        );
+    }
+
+    #[test]
+    fn score_threshold_removes_decayed_masks() {
+        let device = Default::default();
+        // The second mask overlaps the first with an IoU of 2/3
+        let masks = Tensor::<TestBackend, 3>::from_floats(
+            [[[1., 1.], [1., 0.]], [[1., 1.], [0., 0.]]],
+            &device,
+        );
+        let scores = Tensor::from_floats([0.9, 0.6], &device);
+
+        let (kept, decayed) = matrix_nms(
+            masks,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.3).unwrap(),
+            MatrixNmsMethod::Linear,
+            2.,
+        );
+
+        // Decayed score 0.6 * (1 - 2/3) = 0.2
+        assert_eq!(kept.dims(), [1, 2, 2]);
+        assert_eq!(scores_of(decayed), vec![0.9]);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod heatmap;
+pub mod matrix_nms;
+#[cfg(feature = "std")]
+pub mod mono3d;
+pub mod nms;
+pub mod yolox;
@@ -0,0 +1,228 @@
+use alloc::vec::Vec;
+use burn::tensor::{backend::Backend, ElementConversion, Tensor};
+use core::f32::consts::PI;
+
+/// Default minimum depth of the decoded boxes in meters.
+const MIN_DEPTH: f32 = 0.1;
+
+/// 3D bounding box in the camera frame (`x` right, `y` down, `z` forward).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Box3d {
+    /// Box center `(x, y, z)` in meters.
+    pub center_3d: [f32; 3],
+    /// Box size `(height, width, length)` in meters.
+    pub size_3d: [f32; 3],
+    /// Rotation around the `y` axis in radians, in the range `[-pi, pi)`.
+    pub yaw: f32,
+}
+
+/// Decoder of the predictions of monocular 3D detectors (e.g.,
+/// [Deep3DBox](https://arxiv.org/abs/1612.00496)) into [3D boxes](Box3d) in the camera frame.
+///
+/// For each object:
+///   - the depth is decoded as `exp(pred_depth)`, and clamped to the minimum depth,
+///   - the 3D center is the center of the 2D box back-projected at the decoded depth,
+///   - the size is decoded as `anchor_size * exp(pred_size)`,
+///   - the local (observation) angle uses the MultiBin representation: the bin with the highest
+///     confidence is selected and its residual is added to the bin center. The yaw is the local
+///     angle plus the angle of the viewing ray of the center.
+#[derive(Debug, Clone)]
+pub struct Anchor3dBoxDecoder {
+    anchor_size: [f32; 3],
+    min_depth: f32,
+}
+
+impl Anchor3dBoxDecoder {
+    /// Create a new 3D box decoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `anchor_size`: Reference box size `(height, width, length)` in meters (e.g., the mean
+    ///   size of the objects of the dataset).
+    pub fn new(anchor_size: [f32; 3]) -> Self {
+        Self {
+            anchor_size,
+            min_depth: MIN_DEPTH,
+        }
+    }
+
+    /// Set the minimum depth of the decoded boxes in meters (defaults to 0.1).
+    pub fn with_min_depth(mut self, min_depth: f32) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    /// Decode the 3D boxes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred_boxes`: 2D boxes `(xmin, ymin, xmax, ymax)` in pixels. Shape: `[num_boxes, 4]`.
+    /// * `pred_depth` - Log depth of the box centers. Shape: `[num_boxes, 1]`.
+    /// * `pred_size` - Log size of the boxes relative to the anchor size. Shape: `[num_boxes, 3]`.
+    /// * `pred_rotation` - Confidence logits of the `num_bins` angle bins, followed by the
+    ///   residual angle of each bin in radians. Shape: `[num_boxes, 2 * num_bins]`.
+    /// * `camera_intrinsics` - Camera intrinsic matrix `K`. Shape: `[3, 3]`.
+    pub fn decode<B: Backend>(
+        &self,
+        pred_boxes: Tensor<B, 2>,
+        pred_depth: Tensor<B, 2>,
+        pred_size: Tensor<B, 2>,
+        pred_rotation: Tensor<B, 2>,
+        camera_intrinsics: Tensor<B, 2>,
+    ) -> Vec<Box3d> {
+        let [num_boxes, _] = pred_boxes.dims();
+        let [_, num_rotation] = pred_rotation.dims();
+        assert_eq!(
+            num_rotation % 2,
+            0,
+            "expected a confidence and a residual per angle bin"
+        );
+        assert_eq!(camera_intrinsics.dims(), [3, 3], "expected a 3x3 matrix");
+        let num_bins = num_rotation / 2;
+
+        let to_vec = |x: Tensor<B, 2>| -> Vec<f32> {
+            x.into_data()
+                .iter::<B::FloatElem>()
+                .map(|v| v.elem::<f32>())
+                .collect()
+        };
+        let boxes = to_vec(pred_boxes);
+        let depth = to_vec(pred_depth);
+        let size = to_vec(pred_size);
+        let rotation = to_vec(pred_rotation);
+        let k = to_vec(camera_intrinsics);
+        let intrinsics = [[k[0], k[1], k[2]], [k[3], k[4], k[5]], [k[6], k[7], k[8]]];
+
+        (0..num_boxes)
+            .map(|i| {
+                let b = &boxes[i * 4..i * 4 + 4];
+                let center_2d = [(b[0] + b[2]) / 2., (b[1] + b[3]) / 2.];
+                let depth = depth[i].exp().max(self.min_depth);
+                let center_3d = back_project(center_2d, depth, &intrinsics);
+
+                let size = &size[i * 3..i * 3 + 3];
+                let size_3d = [0, 1, 2].map(|j| self.anchor_size[j] * size[j].exp());
+
+                let rotation = &rotation[i * num_rotation..(i + 1) * num_rotation];
+                let (bin, _) = rotation[..num_bins]
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .unwrap();
+                let alpha = bin as f32 * 2. * PI / num_bins as f32 + rotation[num_bins + bin];
+                let yaw = wrap_angle(alpha + center_3d[0].atan2(center_3d[2]));
+
+                Box3d {
+                    center_3d,
+                    size_3d,
+                    yaw,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Back-project an image point `(u, v)` in pixels to the 3D point at the given depth in the
+/// camera frame, with the camera intrinsic matrix `K`.
+pub fn back_project(point: [f32; 2], depth: f32, intrinsics: &[[f32; 3]; 3]) -> [f32; 3] {
+    let [[fx, skew, cx], [_, fy, cy], _] = *intrinsics;
+    let y = (point[1] - cy) * depth / fy;
+    let x = ((point[0] - cx) * depth - skew * y) / fx;
+
+    [x, y, depth]
+}
+
+/// Wrap an angle to the range `[-pi, pi)`.
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2. * PI) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    const INTRINSICS: [[f32; 3]; 3] = [[720., 0., 640.], [0., 720., 360.], [0., 0., 1.]];
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}");
+        }
+    }
+
+    /// Decode a single box centered at `(1000, 540)` in pixels.
+    fn decode(log_depth: f32, rotation: [f32; 8]) -> Box3d {
+        let device = Default::default();
+        let decoder = Anchor3dBoxDecoder::new([1.5, 1.6, 3.9]);
+
+        let mut boxes = decoder.decode(
+            Tensor::<TestBackend, 2>::from_floats([[980., 520., 1020., 560.]], &device),
+            Tensor::from_floats([[log_depth]], &device),
+            Tensor::zeros([1, 3], &device),
+            Tensor::from_floats([rotation], &device),
+            Tensor::from_floats(INTRINSICS, &device),
+        );
+        assert_eq!(boxes.len(), 1);
+
+        boxes.remove(0)
+    }
+
+    #[test]
+    fn back_project_known_point() {
+        assert_close(
+            &back_project([1000., 540.], 20., &INTRINSICS),
+            &[10., 5., 20.],
+        );
+        // The principal point is on the optical axis
+        assert_close(&back_project([640., 360.], 7., &INTRINSICS), &[0., 0., 7.]);
+    }
+
+    #[test]
+    fn decode_center_and_size() {
+        let decoded = decode(20f32.ln(), [1., 0., 0., 0., 0., 0., 0., 0.]);
+
+        assert_close(&decoded.center_3d, &[10., 5., 20.]);
+        assert_close(&decoded.size_3d, &[1.5, 1.6, 3.9]);
+    }
+
+    #[test]
+    fn decode_clamps_depth() {
+        let decoded = decode(-10., [1., 0., 0., 0., 0., 0., 0., 0.]);
+
+        assert_eq!(decoded.center_3d[2], MIN_DEPTH);
+        assert_close(&decoded.center_3d, &[0.05, 0.025, 0.1]);
+    }
+
+    #[test]
+    fn decode_multibin_rotation() {
+        // Second of 4 bins (centered at pi / 2) with a residual of 0.1
+        let decoded = decode(20f32.ln(), [0., 2., 1., -1., 0.5, 0.1, 0.3, 0.]);
+
+        // Plus the angle of the viewing ray
+        let ray = 10f32.atan2(20.);
+        assert_close(&[decoded.yaw], &[PI / 2. + 0.1 + ray]);
+    }
+
+    #[test]
+    fn wrapped_angles() {
+        assert_close(&[wrap_angle(PI / 2.)], &[PI / 2.]);
+        assert_close(&[wrap_angle(3. * PI / 2.)], &[-PI / 2.]);
+        assert_close(&[wrap_angle(-5. * PI / 2.)], &[-PI / 2.]);
+    }
+
+    #[test]
+    #[should_panic = "expected a confidence and a residual per angle bin"]
+    fn odd_rotation_predictions() {
+        let device = Default::default();
+        Anchor3dBoxDecoder::new([1., 1., 1.]).decode(
+            Tensor::<TestBackend, 2>::zeros([1, 4], &device),
+            Tensor::zeros([1, 1], &device),
+            Tensor::zeros([1, 3], &device),
+            Tensor::zeros([1, 3], &device),
+            Tensor::from_floats(INTRINSICS, &device),
+        );
+    }
+}
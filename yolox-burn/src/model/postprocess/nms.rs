@@ -0,0 +1,392 @@
+use alloc::vec::Vec;
+use burn::tensor::{backend::Backend, BasicOps, Bool, ElementConversion, Int, Tensor};
+
+#[cfg(feature = "std")]
+use rayon::prelude::*;
+
+use crate::{
+    model::boxes::{candidates, nms_candidates, Candidates},
+    types::{ConfThreshold, Detection, IoUThreshold},
+};
+
+/// Batched non-maximum suppression (NMS).
+///
+/// Equivalent to applying [nms](crate::model::boxes::nms) to each image, except that the
+/// detections of all classes are merged and only the `max_detections` highest scoring boxes are
+/// kept for each image. With the `std` feature enabled, the images are processed in parallel.
+///
+/// # Arguments
+///
+/// * `boxes`: Bounding box coordinates `(cx, cy, w, h)`. Shape: `[batch_size, num_boxes, 4]`.
+/// * `scores` - Classification scores for each box. Shape: `[batch_size, num_boxes, num_classes]`.
+/// * `iou_threshold` - Scalar threshold for IoU.
+/// * `score_threshold` - Scalar threshold for scores.
+/// * `max_detections` - Maximum number of detections kept for each image.
+///
+/// # Returns
+///
+/// For each image in the batch, the `(xmin, ymin, xmax, ymax)` bounding boxes with shape
+/// `[num_detections, 4]`, the scores with shape `[num_detections]` and the class indices with
+/// shape `[num_detections]`. Detections are sorted in decreasing order of scores.
+#[allow(clippy::type_complexity)]
+pub fn batch_nms<B: Backend>(
+    boxes: Tensor<B, 3>,
+    scores: Tensor<B, 3>,
+    iou_threshold: IoUThreshold,
+    score_threshold: ConfThreshold,
+    max_detections: usize,
+) -> (Vec<Tensor<B, 2>>, Vec<Tensor<B, 1>>, Vec<Tensor<B, 1>>) {
+    let device = boxes.device();
+    let detections = batch_nms_detections(
+        boxes,
+        scores,
+        iou_threshold,
+        score_threshold,
+        max_detections,
+    );
+
+    let mut out_boxes = Vec::with_capacity(detections.len());
+    let mut out_scores = Vec::with_capacity(detections.len());
+    let mut out_classes = Vec::with_capacity(detections.len());
+    for detections in detections {
+        let num_detections = detections.len();
+        let (coords, (confidences, classes)): (Vec<_>, (Vec<_>, Vec<_>)) = detections
+            .into_iter()
+            .map(|det| (det.box_xyxy, (det.score, det.class_id as f32)))
+            .unzip();
+
+        out_boxes.push(
+            Tensor::<B, 1>::from_floats(coords.concat().as_slice(), &device)
+                .reshape([num_detections, 4]),
+        );
+        out_scores.push(Tensor::from_floats(confidences.as_slice(), &device));
+        out_classes.push(Tensor::from_floats(classes.as_slice(), &device));
+    }
+
+    (out_boxes, out_scores, out_classes)
+}
+
+/// Batched non-maximum suppression (NMS) returning the [detections](Detection) for each image.
+///
+/// See [batch_nms] for details.
+pub fn batch_nms_detections<B: Backend>(
+    boxes: Tensor<B, 3>,
+    scores: Tensor<B, 3>,
+    iou_threshold: IoUThreshold,
+    score_threshold: ConfThreshold,
+    max_detections: usize,
+) -> Vec<Vec<Detection>> {
+    let [_, _, num_classes] = scores.dims();
+
+    let per_image = |(image_id, (candidate_boxes, cls_score, cls_idx)): (usize, Candidates)| {
+        let bboxes = nms_candidates(
+            image_id,
+            &candidate_boxes,
+            &cls_score,
+            &cls_idx,
+            num_classes,
+            iou_threshold,
+            score_threshold,
+        );
+        top_detections(bboxes, max_detections)
+    };
+
+    let candidates = candidates(boxes, scores).into_iter().enumerate();
+    #[cfg(feature = "std")]
+    let detections = candidates
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(per_image)
+        .collect();
+    #[cfg(not(feature = "std"))]
+    let detections = candidates.map(per_image).collect();
+
+    detections
+}
+
+/// Merge the per-class detections and keep the `max_detections` highest scoring boxes.
+fn top_detections(bboxes: Vec<Vec<Detection>>, max_detections: usize) -> Vec<Detection> {
+    let mut detections: Vec<_> = bboxes.into_iter().flatten().collect();
+    detections.sort_by(|d1, d2| d2.score.total_cmp(&d1.score));
+    detections.truncate(max_detections);
+
+    detections
+}
+
+/// Greedy class-aware non-maximum suppression (NMS) computed on the device.
+///
+/// A box is kept if no kept box of the same class with a higher score has an IoU greater than
+/// `iou_threshold` with it. The IoU is the same as [iou](crate::model::boxes::iou) (inclusive
+/// pixel coordinates), so that the results match [nms](crate::model::boxes::nms).
+///
+/// The kept boxes are computed from the pairwise IoU matrix by fixed-point iteration, which gives
+/// the same result as the sequential greedy algorithm: the `i`-th box is final after `i`
+/// iterations, and a few iterations are usually enough. Only a scalar is read back from the
+/// device at each iteration.
+///
+/// # Arguments
+///
+/// * `boxes`: Bounding box coordinates `(xmin, ymin, xmax, ymax)`, sorted in decreasing order
+///   of scores. Shape: `[num_boxes, 4]`.
+/// * `class_ids` - Class index of each box. Shape: `[num_boxes]`.
+/// * `iou_threshold` - Scalar threshold for IoU.
+///
+/// # Returns
+///
+/// The mask of the kept boxes. Shape: `[num_boxes]`.
+pub fn tensor_nms<B: Backend>(
+    boxes: Tensor<B, 2>,
+    class_ids: Tensor<B, 1, Int>,
+    iou_threshold: IoUThreshold,
+) -> Tensor<B, 1, Bool> {
+    let device = boxes.device();
+    let [n, _] = boxes.dims();
+    if n == 0 {
+        return Tensor::<B, 1>::zeros([0], &device).greater_elem(0.);
+    }
+
+    // Element [i, j] of the pairwise matrices relates box i to box j
+    let rows = |x| repeat_rows(x, n);
+    let cols = |x| repeat_cols(x, n);
+    let [xmin, ymin, xmax, ymax]: [Tensor<B, 2>; 4] =
+        core::array::from_fn(|i| boxes.clone().slice([0..n, i..i + 1]));
+    let area =
+        (xmax.clone() - xmin.clone()).add_scalar(1.) * (ymax.clone() - ymin.clone()).add_scalar(1.);
+
+    let inter_w = (rows(xmax.clone()).min_pair(cols(xmax))
+        - rows(xmin.clone()).max_pair(cols(xmin)))
+    .add_scalar(1.)
+    .clamp_min(0.);
+    let inter_h = (rows(ymax.clone()).min_pair(cols(ymax))
+        - rows(ymin.clone()).max_pair(cols(ymin)))
+    .add_scalar(1.)
+    .clamp_min(0.);
+    let inter = inter_w * inter_h;
+    let union = rows(area.clone()) + cols(area) - inter.clone();
+    let iou = inter / union.clamp_min(f32::EPSILON);
+
+    // Overlapping boxes of the same class with a higher score (j < i)
+    let class_ids = class_ids.reshape([n, 1]);
+    let same_class = repeat_rows(class_ids.clone(), n).equal(repeat_cols(class_ids, n));
+    let index = Tensor::<B, 1, Int>::arange(0..n as i64, &device).reshape([n, 1]);
+    let higher = repeat_cols(index.clone(), n).lower(repeat_rows(index, n));
+    let overlaps =
+        iou.greater_elem(iou_threshold.value()).float() * same_class.float() * higher.float();
+
+    let mut keep = Tensor::<B, 2>::ones([n, 1], &device);
+    for _ in 0..n {
+        // Kept if none of the kept higher scoring boxes overlaps
+        let next = overlaps
+            .clone()
+            .matmul(keep.clone())
+            .lower_elem(0.5)
+            .float();
+        let changes: f32 = (next.clone() - keep).abs().sum().into_scalar().elem();
+        keep = next;
+        if changes == 0. {
+            break;
+        }
+    }
+
+    keep.reshape([n]).greater_elem(0.5)
+}
+
+/// Repeat a column `[n, 1]` along the rows of a `[n, n]` matrix (element `[i, j]` is `x[i]`).
+fn repeat_rows<B: Backend, K: BasicOps<B>>(x: Tensor<B, 2, K>, n: usize) -> Tensor<B, 2, K> {
+    x.repeat_dim(1, n)
+}
+
+/// Repeat a column `[n, 1]` along the columns of a `[n, n]` matrix (element `[i, j]` is `x[j]`).
+fn repeat_cols<B: Backend, K: BasicOps<B>>(x: Tensor<B, 2, K>, n: usize) -> Tensor<B, 2, K> {
+    x.transpose().repeat_dim(0, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::boxes::nms;
+    use alloc::vec;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Boxes `[3, 4, 4]` and scores `[3, 4, 2]` with overlapping boxes of the same class.
+    fn inputs() -> (Tensor<TestBackend, 3>, Tensor<TestBackend, 3>) {
+        let device = Default::default();
+        let boxes = Tensor::from_floats(
+            [
+                [
+                    [50., 50., 20., 20.],
+                    [52., 50., 20., 20.],
+                    [100., 100., 30., 30.],
+                    [10., 10., 4., 4.],
+                ],
+                [
+                    [20., 20., 10., 10.],
+                    [60., 60., 10., 10.],
+                    [61., 61., 10., 10.],
+                    [90., 20., 8., 8.],
+                ],
+                [
+                    [5., 5., 2., 2.],
+                    [15., 15., 2., 2.],
+                    [25., 25., 2., 2.],
+                    [35., 35., 2., 2.],
+                ],
+            ],
+            &device,
+        );
+        let scores = Tensor::from_floats(
+            [
+                [[0.1, 0.9], [0.2, 0.7], [0.8, 0.1], [0.3, 0.2]],
+                [[0.6, 0.2], [0.1, 0.95], [0.05, 0.85], [0.55, 0.5]],
+                [[0.1, 0.2], [0.2, 0.1], [0.3, 0.1], [0.1, 0.4]],
+            ],
+            &device,
+        );
+
+        (boxes, scores)
+    }
+
+    /// Per-image NMS results flattened and sorted by decreasing scores.
+    fn looped_nms(max_detections: usize) -> Vec<Vec<Detection>> {
+        let (boxes, scores) = inputs();
+        nms(
+            boxes,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.5).unwrap(),
+        )
+        .into_iter()
+        .map(|per_class| top_detections(per_class, max_detections))
+        .collect()
+    }
+
+    #[test]
+    fn batch_nms_per_image_outputs() {
+        let (boxes, scores) = inputs();
+        let (out_boxes, out_scores, out_classes) = batch_nms(
+            boxes,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.5).unwrap(),
+            10,
+        );
+
+        assert_eq!(out_boxes.len(), 3);
+        assert_eq!(out_scores.len(), 3);
+        assert_eq!(out_classes.len(), 3);
+        assert_eq!(out_boxes[2].dims(), [0, 4]);
+        assert_eq!(out_scores[2].dims(), [0]);
+    }
+
+    #[test]
+    fn batch_nms_matches_looped_nms() {
+        let (boxes, scores) = inputs();
+        let (out_boxes, out_scores, out_classes) = batch_nms(
+            boxes,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.5).unwrap(),
+            10,
+        );
+
+        for (i, expected) in looped_nms(10).into_iter().enumerate() {
+            let coords: Vec<f32> = expected.iter().flat_map(|d| d.box_xyxy).collect();
+            let confidences: Vec<f32> = expected.iter().map(|d| d.score).collect();
+            let classes: Vec<f32> = expected.iter().map(|d| d.class_id as f32).collect();
+
+            assert_eq!(out_boxes[i].to_data().to_vec::<f32>().unwrap(), coords);
+            assert_eq!(
+                out_scores[i].to_data().to_vec::<f32>().unwrap(),
+                confidences
+            );
+            assert_eq!(out_classes[i].to_data().to_vec::<f32>().unwrap(), classes);
+        }
+    }
+
+    #[test]
+    fn batch_nms_detections_matches_looped_nms() {
+        let (boxes, scores) = inputs();
+        let detections = batch_nms_detections(
+            boxes,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.5).unwrap(),
+            10,
+        );
+
+        assert_eq!(detections, looped_nms(10));
+        // Overlapping boxes of the same class are suppressed, other classes are kept
+        assert_eq!(detections[0].len(), 2);
+        assert_eq!(detections[1].len(), 3);
+        assert!(detections[2].is_empty());
+    }
+
+    #[test]
+    fn batch_nms_max_detections() {
+        let (boxes, scores) = inputs();
+        let detections = batch_nms_detections(
+            boxes,
+            scores,
+            IoUThreshold::new(0.5).unwrap(),
+            ConfThreshold::new(0.5).unwrap(),
+            1,
+        );
+
+        assert_eq!(detections[0], looped_nms(1)[0]);
+        assert_eq!(detections[0].len(), 1);
+        assert_eq!(detections[0][0].score, 0.9);
+        assert_eq!(detections[1][0].score, 0.95);
+    }
+
+    #[test]
+    fn tensor_nms_matches_looped_nms() {
+        let device = Default::default();
+        let n = 64;
+        let centers =
+            Tensor::<TestBackend, 3>::random([1, n, 2], Distribution::Uniform(0., 100.), &device);
+        let sizes = Tensor::random([1, n, 2], Distribution::Uniform(5., 40.), &device);
+        let boxes = Tensor::cat(vec![centers, sizes], 2);
+        let scores = Tensor::<TestBackend, 3>::random([1, n, 3], Distribution::Default, &device);
+
+        for threshold in [0.3, 0.5, 0.7] {
+            let iou_threshold = IoUThreshold::new(threshold).unwrap();
+            let expected = top_detections(
+                nms(
+                    boxes.clone(),
+                    scores.clone(),
+                    iou_threshold,
+                    ConfThreshold::new(0.).unwrap(),
+                )
+                .remove(0),
+                n,
+            );
+
+            // Candidates sorted by decreasing scores, with (xmin, ymin, xmax, ymax) boxes
+            let (max_scores, class_ids) = scores.clone().squeeze::<2>(0).max_dim_with_indices(1);
+            let (max_scores, order) = max_scores.reshape([n]).sort_descending_with_indices(0);
+            let class_ids = class_ids.reshape([n]).select(0, order.clone());
+            let cxcywh = boxes.clone().squeeze::<2>(0).select(0, order);
+            let xy = cxcywh.clone().slice([0..n, 0..2]);
+            let half_wh = cxcywh.slice([0..n, 2..4]).mul_scalar(0.5);
+            let xyxy = Tensor::cat(vec![xy.clone() - half_wh.clone(), xy + half_wh], 1);
+
+            let keep = tensor_nms(xyxy.clone(), class_ids.clone(), iou_threshold).into_data();
+            let xyxy = xyxy.into_data().to_vec::<f32>().unwrap();
+            let detections: Vec<_> = xyxy
+                .chunks(4)
+                .zip(max_scores.into_data().iter::<f32>())
+                .zip(class_ids.into_data().iter::<i64>())
+                .zip(keep.iter::<bool>())
+                .filter(|(_, keep)| *keep)
+                .map(|(((b, score), class_id), _)| {
+                    Detection::new(0, [b[0], b[1], b[2], b[3]], score, class_id as usize)
+                })
+                .collect();
+
+            assert!(detections.len() < n, "no box suppressed");
+            assert_eq!(detections, expected, "IoU threshold {threshold}");
+        }
+    }
+}
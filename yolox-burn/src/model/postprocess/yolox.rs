@@ -0,0 +1,240 @@
+use alloc::{vec, vec::Vec};
+use burn::tensor::{backend::Backend, ElementConversion, Tensor};
+
+use super::nms::tensor_nms;
+use crate::{
+    model::{MAX_DETECTIONS, NMS_IOU_THRESHOLD, SCORE_THRESHOLD},
+    types::{ConfThreshold, Detection, IoUThreshold},
+};
+
+/// Default maximum number of candidate boxes of each image before non-maximum suppression.
+const PRE_NMS_TOP_K: usize = 1000;
+
+/// Post-processing of the [YOLOX](crate::model::yolox::Yolox) outputs into
+/// [detections](Detection).
+///
+/// The score of each box is its objectness multiplied by its highest class probability. The
+/// boxes with a score of at least the confidence threshold are sorted, converted to
+/// `(xmin, ymin, xmax, ymax)` and filtered by class-aware [non-maximum suppression](tensor_nms),
+/// all on the device. Only the candidate boxes are read back.
+#[derive(Debug, Clone)]
+pub struct YoloxPostprocessor {
+    conf_threshold: ConfThreshold,
+    iou_threshold: IoUThreshold,
+    pre_nms_top_k: usize,
+    max_detections: usize,
+}
+
+impl Default for YoloxPostprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl YoloxPostprocessor {
+    /// Create a new post-processor with the default
+    /// [inference](crate::model::DetectionModel::infer) thresholds.
+    pub fn new() -> Self {
+        Self {
+            conf_threshold: SCORE_THRESHOLD.into(),
+            iou_threshold: NMS_IOU_THRESHOLD.into(),
+            pre_nms_top_k: PRE_NMS_TOP_K,
+            max_detections: MAX_DETECTIONS,
+        }
+    }
+
+    /// Set the minimum score of the detections (defaults to 0.5).
+    pub fn with_conf_threshold(mut self, conf_threshold: ConfThreshold) -> Self {
+        self.conf_threshold = conf_threshold;
+        self
+    }
+
+    /// Set the IoU threshold of the non-maximum suppression (defaults to 0.65).
+    pub fn with_iou_threshold(mut self, iou_threshold: IoUThreshold) -> Self {
+        self.iou_threshold = iou_threshold;
+        self
+    }
+
+    /// Set the maximum number of highest scoring candidates of each image before non-maximum
+    /// suppression, which bounds the size of the pairwise IoU matrix (defaults to 1000).
+    pub fn with_pre_nms_top_k(mut self, pre_nms_top_k: usize) -> Self {
+        self.pre_nms_top_k = pre_nms_top_k;
+        self
+    }
+
+    /// Set the maximum number of detections of each image (defaults to 300).
+    pub fn with_max_detections(mut self, max_detections: usize) -> Self {
+        self.max_detections = max_detections;
+        self
+    }
+
+    /// Decode the outputs of the model into detections.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs`: Absolute `(cx, cy, w, h)` boxes, followed by the objectness and class
+    ///   probabilities. Shape: `[batch_size, num_boxes, 5 + num_classes]`.
+    ///
+    /// # Returns
+    ///
+    /// The detections of each image, with the batch index as image id, sorted in decreasing order
+    /// of scores.
+    pub fn postprocess<B: Backend>(&self, outputs: Tensor<B, 3>) -> Vec<Vec<Detection>> {
+        self.postprocess_with_indices(outputs)
+            .into_iter()
+            .map(|detections| detections.into_iter().map(|(_, det)| det).collect())
+            .collect()
+    }
+
+    /// Decode the outputs of the model into detections, along with the index of the prediction
+    /// (anchor) of each detection, e.g., to gather other per-prediction outputs.
+    ///
+    /// See [postprocess](Self::postprocess) for details.
+    pub fn postprocess_with_indices<B: Backend>(
+        &self,
+        outputs: Tensor<B, 3>,
+    ) -> Vec<Vec<(usize, Detection)>> {
+        outputs
+            .iter_dim(0)
+            .enumerate()
+            .map(|(image_id, out)| self.postprocess_image(image_id, out.squeeze(0)))
+            .collect()
+    }
+
+    /// Decode the outputs `[num_boxes, 5 + num_classes]` of a single image, with the index of
+    /// the prediction of each detection.
+    fn postprocess_image<B: Backend>(
+        &self,
+        image_id: usize,
+        outputs: Tensor<B, 2>,
+    ) -> Vec<(usize, Detection)> {
+        let [n, num_outputs] = outputs.dims();
+        let boxes = outputs.clone().slice([0..n, 0..4]);
+        let obj_scores = outputs.clone().slice([0..n, 4..5]);
+        let cls_scores = outputs.slice([0..n, 5..num_outputs]);
+
+        // Highest scoring class of each box, and candidates above the confidence threshold
+        let (scores, class_ids) = (cls_scores * obj_scores).max_dim_with_indices(1);
+        let (scores, order) = scores.reshape([n]).sort_descending_with_indices(0);
+        let num_candidates: i64 = scores
+            .clone()
+            .greater_equal_elem(self.conf_threshold.value())
+            .int()
+            .sum()
+            .into_scalar()
+            .elem();
+        let k = (num_candidates as usize).min(self.pre_nms_top_k);
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let order = order.slice([0..k]);
+        let scores = scores.slice([0..k]);
+        let class_ids = class_ids.reshape([n]).select(0, order.clone());
+        let boxes = boxes.select(0, order.clone());
+        let xy = boxes.clone().slice([0..k, 0..2]);
+        let half_wh = boxes.slice([0..k, 2..4]).mul_scalar(0.5);
+        let boxes = Tensor::cat(vec![xy.clone() - half_wh.clone(), xy + half_wh], 1);
+
+        let keep = tensor_nms(boxes.clone(), class_ids.clone(), self.iou_threshold).into_data();
+        let boxes = boxes.into_data();
+        let scores = scores.into_data();
+        let class_ids = class_ids.into_data();
+        let order = order.into_data();
+
+        boxes
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem::<f32>())
+            .collect::<Vec<_>>()
+            .chunks(4)
+            .zip(scores.iter::<B::FloatElem>())
+            .zip(class_ids.iter::<B::IntElem>())
+            .zip(order.iter::<B::IntElem>())
+            .zip(keep.iter::<bool>())
+            .filter(|(_, keep)| *keep)
+            .take(self.max_detections)
+            .map(|((((b, score), class_id), index), _)| {
+                let detection = Detection::new(
+                    image_id,
+                    [b[0], b[1], b[2], b[3]],
+                    score.elem(),
+                    class_id.elem::<i64>() as usize,
+                );
+                (index.elem::<i64>() as usize, detection)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    /// Outputs of a single image with disjoint `(cx, cy, w, h)` boxes of class 1 and the given
+    /// scores.
+    fn outputs(scores: &[f32]) -> Tensor<TestBackend, 3> {
+        let values: Vec<f32> = scores
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &score)| [20. * i as f32 + 10., 10., 10., 10., 1., 0., score])
+            .collect();
+
+        Tensor::<TestBackend, 1>::from_floats(values.as_slice(), &Default::default()).reshape([
+            1,
+            scores.len(),
+            7,
+        ])
+    }
+
+    #[test]
+    fn boxes_converted_to_xyxy() {
+        let outputs = Tensor::<TestBackend, 1>::from_floats(
+            [50., 40., 20., 10., 1., 0.2, 0.8],
+            &Default::default(),
+        )
+        .reshape([1, 1, 7]);
+
+        let detections = YoloxPostprocessor::new().postprocess(outputs);
+
+        assert_eq!(
+            detections,
+            [[Detection::new(0, [40., 35., 60., 45.], 0.8, 1)]]
+        );
+    }
+
+    #[test]
+    fn conf_threshold_filters_boxes() {
+        let postprocessor =
+            YoloxPostprocessor::new().with_conf_threshold(ConfThreshold::new(0.6).unwrap());
+
+        let detections = postprocessor.postprocess(outputs(&[0.5, 0.9, 0.6]));
+
+        let scores: Vec<f32> = detections[0].iter().map(|det| det.score).collect();
+        assert_eq!(scores, [0.9, 0.6]);
+        assert!(postprocessor.postprocess(outputs(&[0.3]))[0].is_empty());
+    }
+
+    #[test]
+    fn pre_nms_top_k_keeps_highest_scores() {
+        let postprocessor = YoloxPostprocessor::new().with_pre_nms_top_k(2);
+
+        let detections = postprocessor.postprocess_with_indices(outputs(&[0.7, 0.9, 0.6, 0.8]));
+
+        let indices: Vec<usize> = detections[0].iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, [1, 3]);
+        assert_eq!(detections[0][0].1.box_xyxy, [25., 5., 35., 15.]);
+    }
+
+    #[test]
+    fn max_detections_per_image() {
+        let postprocessor = YoloxPostprocessor::new().with_max_detections(3);
+
+        let detections = postprocessor.postprocess(outputs(&[0.7, 0.9, 0.6, 0.8, 0.95]));
+
+        let scores: Vec<f32> = detections[0].iter().map(|det| det.score).collect();
+        assert_eq!(scores, [0.95, 0.9, 0.8]);
+    }
+}
@@ -0,0 +1,556 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d,
+    },
+    tensor::{
+        activation::{relu, sigmoid, tanh},
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Int, Tensor,
+    },
+};
+
+use super::blocks::{
+    bilinear_sample_points, ActivationType, BaseConv, BaseConvConfig, ResidualConnection,
+    ResidualConnectionConfig,
+};
+
+/// Output stride of the [feature encoder](FeatureEncoder).
+const FEATURE_STRIDE: usize = 8;
+/// Number of channels of the encoder features.
+const FEATURE_CHANNELS: usize = 256;
+/// Number of channels of the GRU hidden state (the remaining encoder channels are the context).
+const HIDDEN_CHANNELS: usize = 128;
+/// Number of channels of the encoded motion features (including the flow).
+const MOTION_CHANNELS: usize = 82;
+/// Default radius of the correlation lookup window.
+const DEFAULT_RADIUS: usize = 4;
+
+/// Convolution with same padding and bias, without normalization.
+fn conv_config(in_channels: usize, out_channels: usize, kernel_size: usize) -> Conv2dConfig {
+    let pad = kernel_size / 2;
+    Conv2dConfig::new([in_channels, out_channels], [kernel_size, kernel_size])
+        .with_padding(PaddingConfig2d::Explicit(pad, pad))
+}
+
+/// Pixel coordinates `(x, y)` of each cell of a `height x width` feature map.
+///
+/// # Shapes
+///   - output: `[batch_size, 2, height, width]`
+fn coords_grid<B: Backend>(
+    batch_size: usize,
+    [height, width]: [usize; 2],
+    device: &Device<B>,
+) -> Tensor<B, 4> {
+    let xs = Tensor::<B, 1, Int>::arange(0..width as i64, device)
+        .float()
+        .reshape([1, 1, 1, width])
+        .repeat_dim(2, height);
+    let ys = Tensor::<B, 1, Int>::arange(0..height as i64, device)
+        .float()
+        .reshape([1, 1, height, 1])
+        .repeat_dim(3, width);
+
+    Tensor::cat(vec![xs, ys], 1).repeat_dim(0, batch_size)
+}
+
+/// Residual block of the [feature encoder](FeatureEncoder): two `3x3` convolutions with a
+/// residual connection.
+#[derive(Module, Debug)]
+pub struct ResidualBlock<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: BaseConv<B>,
+    residual: ResidualConnection<B>,
+}
+
+impl<B: Backend> ResidualBlock<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let y = self.conv2.forward(self.conv1.forward(x.clone()));
+        relu(self.residual.apply(x, y))
+    }
+}
+
+/// [Residual block](ResidualBlock) configuration.
+pub struct ResidualBlockConfig {
+    conv1: BaseConvConfig,
+    conv2: BaseConvConfig,
+    residual: ResidualConnectionConfig,
+}
+
+impl ResidualBlockConfig {
+    /// Create a new instance of the residual block [config](ResidualBlockConfig).
+    pub fn new(in_channels: usize, out_channels: usize, stride: usize) -> Self {
+        Self {
+            conv1: BaseConvConfig::new(in_channels, out_channels, 3, stride, 1)
+                .with_activation(ActivationType::Relu),
+            conv2: BaseConvConfig::new(out_channels, out_channels, 3, 1, 1)
+                .with_activation(ActivationType::Relu),
+            residual: ResidualConnectionConfig::new(in_channels, out_channels, stride),
+        }
+    }
+
+    /// Initialize a new [residual block](ResidualBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ResidualBlock<B> {
+        ResidualBlock {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+            residual: self.residual.init(device),
+        }
+    }
+}
+
+/// Small ResNet extracting 256-channel features at `1/8` of the input resolution.
+#[derive(Module, Debug)]
+pub struct FeatureEncoder<B: Backend> {
+    stem: BaseConv<B>,
+    layers: Vec<ResidualBlock<B>>,
+    output: Conv2d<B>,
+}
+
+impl<B: Backend> FeatureEncoder<B> {
+    /// # Shapes
+    ///   - input: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, 256, height / 8, width / 8]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.stem.forward(x);
+        let x = self.layers.iter().fold(x, |x, layer| layer.forward(x));
+
+        self.output.forward(x)
+    }
+}
+
+/// [Feature encoder](FeatureEncoder) configuration.
+pub struct FeatureEncoderConfig {
+    stem: BaseConvConfig,
+    layers: Vec<ResidualBlockConfig>,
+    output: Conv2dConfig,
+}
+
+impl FeatureEncoderConfig {
+    /// Create a new instance of the feature encoder [config](FeatureEncoderConfig).
+    pub fn new() -> Self {
+        Self {
+            stem: BaseConvConfig::new(3, 64, 7, 2, 1).with_activation(ActivationType::Relu),
+            layers: vec![
+                ResidualBlockConfig::new(64, 64, 1),
+                ResidualBlockConfig::new(64, 96, 2),
+                ResidualBlockConfig::new(96, 128, 2),
+            ],
+            output: conv_config(128, FEATURE_CHANNELS, 1),
+        }
+    }
+
+    /// Initialize a new [feature encoder](FeatureEncoder) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> FeatureEncoder<B> {
+        FeatureEncoder {
+            stem: self.stem.init(device),
+            layers: self.layers.iter().map(|l| l.init(device)).collect(),
+            output: self.output.init(device),
+        }
+    }
+}
+
+impl Default for FeatureEncoderConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// All-pairs correlation volume between two feature maps.
+///
+/// The correlation of each pixel of the first feature map with all the pixels of the second
+/// feature map is computed once, and looked up in a `(2 * radius + 1)^2` window around the
+/// current correspondence estimates at each refinement iteration.
+pub struct CorrelationVolume<B: Backend> {
+    /// Correlations of each pixel of the first feature map. Shape: `[N * H * W, 1, H * W]`.
+    corr: Tensor<B, 3>,
+    shape: [usize; 3],
+    radius: usize,
+}
+
+impl<B: Backend> CorrelationVolume<B> {
+    /// Compute the correlation volume, with the dot products scaled by `1 / sqrt(channels)`.
+    ///
+    /// # Shapes
+    ///   - fmap1: `[batch_size, channels, height, width]`
+    ///   - fmap2: `[batch_size, channels, height, width]`
+    pub fn new(fmap1: Tensor<B, 4>, fmap2: Tensor<B, 4>, radius: usize) -> Self {
+        let [n, c, h, w] = fmap1.dims();
+        let fmap1 = fmap1.reshape([n, c, h * w]).swap_dims(1, 2);
+        let fmap2 = fmap2.reshape([n, c, h * w]);
+
+        let scale = Tensor::<B, 1>::from_floats([c as f32], &fmap1.device())
+            .sqrt()
+            .reshape([1, 1, 1]);
+        let corr = (fmap1.matmul(fmap2) / scale).reshape([n * h * w, 1, h * w]);
+
+        Self {
+            corr,
+            shape: [n, h, w],
+            radius,
+        }
+    }
+
+    /// Look up the correlations in a window around the given coordinates in the second feature
+    /// map. Correlations outside of the feature map are zero.
+    ///
+    /// # Shapes
+    ///   - coords: `[batch_size, 2, height, width]` with `(x, y)` pixel coordinates
+    ///   - output: `[batch_size, height, width, (2 * radius + 1)^2]`
+    pub fn lookup(&self, coords: Tensor<B, 4>) -> Tensor<B, 4> {
+        let [n, h, w] = self.shape;
+        let r = self.radius as i64;
+        let d = 2 * self.radius + 1;
+        let device = coords.device();
+
+        // Window offsets [1, D * D], with the y offset varying the slowest
+        let range = Tensor::<B, 1, Int>::arange(-r..r + 1, &device).float();
+        let dx = range
+            .clone()
+            .reshape([1, d])
+            .repeat_dim(0, d)
+            .reshape([1, d * d]);
+        let dy = range.reshape([d, 1]).repeat_dim(1, d).reshape([1, d * d]);
+
+        let coords = coords
+            .swap_dims(1, 2)
+            .swap_dims(2, 3)
+            .reshape([n * h * w, 2]);
+        let px = coords.clone().slice([0..n * h * w, 0..1]) + dx;
+        let py = coords.slice([0..n * h * w, 1..2]) + dy;
+
+        bilinear_sample_points(self.corr.clone(), py, px, [h, w]).reshape([n, h, w, d * d])
+    }
+}
+
+/// Convolutional GRU cell.
+#[derive(Module, Debug)]
+pub struct ConvGru<B: Backend> {
+    convz: Conv2d<B>,
+    convr: Conv2d<B>,
+    convq: Conv2d<B>,
+}
+
+impl<B: Backend> ConvGru<B> {
+    /// Update the hidden state `[N, hidden, H, W]` with the input `[N, input, H, W]`.
+    pub fn forward(&self, h: Tensor<B, 4>, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let hx = Tensor::cat(vec![h.clone(), x.clone()], 1);
+        let z = sigmoid(self.convz.forward(hx.clone()));
+        let r = sigmoid(self.convr.forward(hx));
+        let q = tanh(self.convq.forward(Tensor::cat(vec![r * h.clone(), x], 1)));
+
+        h.clone() + z * (q - h)
+    }
+}
+
+/// [Convolutional GRU](ConvGru) configuration.
+pub struct ConvGruConfig {
+    input_channels: usize,
+    hidden_channels: usize,
+    kernel_size: usize,
+}
+
+impl ConvGruConfig {
+    /// Create a new instance of the convolutional GRU [config](ConvGruConfig).
+    pub fn new(input_channels: usize, hidden_channels: usize, kernel_size: usize) -> Self {
+        Self {
+            input_channels,
+            hidden_channels,
+            kernel_size,
+        }
+    }
+
+    /// Initialize a new [convolutional GRU](ConvGru) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ConvGru<B> {
+        let gate_conv = || {
+            conv_config(
+                self.hidden_channels + self.input_channels,
+                self.hidden_channels,
+                self.kernel_size,
+            )
+            .init(device)
+        };
+
+        ConvGru {
+            convz: gate_conv(),
+            convr: gate_conv(),
+            convq: gate_conv(),
+        }
+    }
+}
+
+/// Update block of [RAFT-lite](RaftLite), which encodes the correlations and the current flow
+/// and predicts a flow update with a [convolutional GRU](ConvGru).
+#[derive(Module, Debug)]
+pub struct GruUpdateBlock<B: Backend> {
+    convc: Conv2d<B>,
+    convf1: Conv2d<B>,
+    convf2: Conv2d<B>,
+    motion: Conv2d<B>,
+    gru: ConvGru<B>,
+    flow_head1: Conv2d<B>,
+    flow_head2: Conv2d<B>,
+}
+
+impl<B: Backend> GruUpdateBlock<B> {
+    /// Refine the hidden state and predict the flow update.
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden`: GRU hidden state. Shape: `[batch_size, 128, height, width]`.
+    /// * `context` - Context features. Shape: `[batch_size, 128, height, width]`.
+    /// * `corr` - Looked up correlations. Shape: `[batch_size, (2r + 1)^2, height, width]`.
+    /// * `flow` - Current flow. Shape: `[batch_size, 2, height, width]`.
+    ///
+    /// # Returns
+    ///
+    /// The updated hidden state and the flow update with shape `[batch_size, 2, height, width]`.
+    pub fn forward(
+        &self,
+        hidden: Tensor<B, 4>,
+        context: Tensor<B, 4>,
+        corr: Tensor<B, 4>,
+        flow: Tensor<B, 4>,
+    ) -> (Tensor<B, 4>, Tensor<B, 4>) {
+        // Motion features
+        let corr = relu(self.convc.forward(corr));
+        let flow_features = relu(self.convf2.forward(relu(self.convf1.forward(flow.clone()))));
+        let motion = relu(
+            self.motion
+                .forward(Tensor::cat(vec![corr, flow_features], 1)),
+        );
+        let motion = Tensor::cat(vec![motion, flow], 1);
+
+        let hidden = self
+            .gru
+            .forward(hidden, Tensor::cat(vec![context, motion], 1));
+        let delta = self
+            .flow_head2
+            .forward(relu(self.flow_head1.forward(hidden.clone())));
+
+        (hidden, delta)
+    }
+}
+
+/// [GRU update block](GruUpdateBlock) configuration.
+pub struct GruUpdateBlockConfig {
+    corr_channels: usize,
+}
+
+impl GruUpdateBlockConfig {
+    /// Create a new instance of the GRU update block [config](GruUpdateBlockConfig), for a
+    /// correlation lookup radius `radius`.
+    pub fn new(radius: usize) -> Self {
+        let d = 2 * radius + 1;
+        Self {
+            corr_channels: d * d,
+        }
+    }
+
+    /// Initialize a new [GRU update block](GruUpdateBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> GruUpdateBlock<B> {
+        let context_channels = FEATURE_CHANNELS - HIDDEN_CHANNELS;
+
+        GruUpdateBlock {
+            convc: conv_config(self.corr_channels, 96, 1).init(device),
+            convf1: conv_config(2, 64, 7).init(device),
+            convf2: conv_config(64, 32, 3).init(device),
+            motion: conv_config(96 + 32, MOTION_CHANNELS - 2, 3).init(device),
+            gru: ConvGruConfig::new(context_channels + MOTION_CHANNELS, HIDDEN_CHANNELS, 3)
+                .init(device),
+            flow_head1: conv_config(HIDDEN_CHANNELS, 256, 3).init(device),
+            flow_head2: conv_config(256, 2, 3).init(device),
+        }
+    }
+}
+
+/// Simplified [RAFT](https://arxiv.org/abs/2003.12039) optical flow model.
+///
+/// Compared to RAFT, the context features are computed by the feature encoder (shared weights),
+/// the correlation volume has a single level and the flow is upsampled bilinearly.
+#[derive(Module, Debug)]
+pub struct RaftLite<B: Backend> {
+    encoder: FeatureEncoder<B>,
+    update: GruUpdateBlock<B>,
+    radius: usize,
+}
+
+impl<B: Backend> RaftLite<B> {
+    /// Estimate the optical flow from the first to the second image.
+    ///
+    /// # Arguments
+    ///
+    /// * `image1`: First images, with pixel values in the range `[0, 255]`.
+    ///   Shape: `[batch_size, 3, height, width]`.
+    /// * `image2` - Second images. Shape: `[batch_size, 3, height, width]`.
+    /// * `num_iters` - Number of refinement iterations.
+    ///
+    /// # Returns
+    ///
+    /// The flow `(dx, dy)` in pixels estimated at each iteration, with shape
+    /// `[batch_size, 2, height, width]`. The image size should be a multiple of 8.
+    pub fn forward(
+        &self,
+        image1: Tensor<B, 4>,
+        image2: Tensor<B, 4>,
+        num_iters: usize,
+    ) -> Vec<Tensor<B, 4>> {
+        let [n, _, height, width] = image1.dims();
+        let normalize = |x: Tensor<B, 4>| x.div_scalar(255. / 2.).sub_scalar(1.);
+
+        // Both images are encoded in a single batch
+        let features = self
+            .encoder
+            .forward(Tensor::cat(vec![normalize(image1), normalize(image2)], 0));
+        let [_, c, h, w] = features.dims();
+        let fmap1 = features.clone().slice([0..n, 0..c, 0..h, 0..w]);
+        let fmap2 = features.slice([n..2 * n, 0..c, 0..h, 0..w]);
+
+        // Hidden state and context from the features of the first image
+        let mut hidden = tanh(fmap1.clone().slice([0..n, 0..HIDDEN_CHANNELS, 0..h, 0..w]));
+        let context = relu(fmap1.clone().slice([0..n, HIDDEN_CHANNELS..c, 0..h, 0..w]));
+
+        let volume = CorrelationVolume::new(fmap1, fmap2, self.radius);
+        let coords0 = coords_grid::<B>(n, [h, w], &context.device());
+        let mut coords1 = coords0.clone();
+
+        let mut flows = Vec::with_capacity(num_iters);
+        for _ in 0..num_iters {
+            let coords = coords1.detach();
+            let corr = volume
+                .lookup(coords.clone())
+                .swap_dims(2, 3)
+                .swap_dims(1, 2);
+            let flow = coords.clone() - coords0.clone();
+
+            let (next_hidden, delta) = self.update.forward(hidden, context.clone(), corr, flow);
+            hidden = next_hidden;
+            coords1 = coords + delta;
+
+            // Upsample the flow to the image resolution
+            let flow = interpolate(
+                coords1.clone() - coords0.clone(),
+                [height, width],
+                InterpolateOptions::new(InterpolateMode::Bilinear),
+            );
+            flows.push(flow.mul_scalar(FEATURE_STRIDE as f32));
+        }
+
+        flows
+    }
+}
+
+/// [RAFT-lite](RaftLite) configuration.
+pub struct RaftLiteConfig {
+    encoder: FeatureEncoderConfig,
+    update: GruUpdateBlockConfig,
+    radius: usize,
+}
+
+impl RaftLiteConfig {
+    /// Create a new instance of the RAFT-lite [config](RaftLiteConfig).
+    pub fn new() -> Self {
+        Self {
+            encoder: FeatureEncoderConfig::new(),
+            update: GruUpdateBlockConfig::new(DEFAULT_RADIUS),
+            radius: DEFAULT_RADIUS,
+        }
+    }
+
+    /// Set the radius of the correlation lookup window (defaults to 4).
+    pub fn with_radius(mut self, radius: usize) -> Self {
+        self.update = GruUpdateBlockConfig::new(radius);
+        self.radius = radius;
+        self
+    }
+
+    /// Initialize a new [RAFT-lite](RaftLite) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> RaftLite<B> {
+        RaftLite {
+            encoder: self.encoder.init(device),
+            update: self.update.init(device),
+            radius: self.radius,
+        }
+    }
+}
+
+impl Default for RaftLiteConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn coords_grid_pixels() {
+        let device = Default::default();
+
+        let coords = coords_grid::<TestBackend>(1, [2, 3], &device);
+
+        coords.into_data().assert_eq(
+            &TensorData::from([[
+                [[0.0f32, 1., 2.], [0., 1., 2.]],
+                [[0., 0., 0.], [1., 1., 1.]],
+            ]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn correlation_volume_shape() {
+        let device = Default::default();
+        let fmap1 = Tensor::<TestBackend, 4>::random([2, 8, 4, 6], Distribution::Default, &device);
+        let fmap2 = Tensor::random([2, 8, 4, 6], Distribution::Default, &device);
+        let volume = CorrelationVolume::new(fmap1, fmap2, 2);
+
+        let corr = volume.lookup(coords_grid(2, [4, 6], &device));
+
+        assert_eq!(corr.dims(), [2, 4, 6, 5 * 5]);
+    }
+
+    #[test]
+    fn correlation_volume_lookup() {
+        let device = Default::default();
+        let fmap = Tensor::<TestBackend, 4>::ones([1, 4, 3, 3], &device);
+        let volume = CorrelationVolume::new(fmap.clone(), fmap, 1);
+
+        let corr = volume.lookup(coords_grid(1, [3, 3], &device));
+
+        // Dot products of 4 ones scaled by 1 / sqrt(4), zero outside of the feature map
+        let corr = corr.slice([0..1, 0..1, 0..1, 0..9]).reshape([9]);
+        corr.into_data().assert_approx_eq(
+            &TensorData::from([0.0f32, 0., 0., 0., 2., 2., 0., 2., 2.]),
+            5,
+        );
+    }
+
+    #[test]
+    fn raft_lite_flow_shape() {
+        let device = Default::default();
+        let model = RaftLiteConfig::new()
+            .with_radius(2)
+            .init::<TestBackend>(&device);
+        let image1 = Tensor::random([2, 3, 32, 48], Distribution::Uniform(0., 255.), &device);
+        let image2 = Tensor::random([2, 3, 32, 48], Distribution::Uniform(0., 255.), &device);
+
+        let flows = model.forward(image1, image2, 3);
+
+        assert_eq!(flows.len(), 3);
+        for flow in flows {
+            assert_eq!(flow.dims(), [2, 2, 32, 48]);
+        }
+    }
+}
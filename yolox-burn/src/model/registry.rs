@@ -0,0 +1,143 @@
+//! Registry of the pre-trained models that can be initialized from a model ID.
+use core::fmt;
+use std::path::PathBuf;
+
+use burn::record::RecorderError;
+
+use super::{weights::Weights, yolox::YoloxConfig};
+
+/// Pre-trained model registry entry.
+#[derive(Debug, Clone, Copy)]
+pub struct RegistryEntry {
+    /// Model identifier (e.g., `"yolox-s/coco"`).
+    pub id: &'static str,
+    /// Architecture preset, with its pre-trained [weights](Weights).
+    config: fn() -> YoloxConfig,
+}
+
+/// Supported model IDs.
+pub const MODELS_REGISTRY: [RegistryEntry; 6] = [
+    RegistryEntry {
+        id: "yolox-nano/coco",
+        config: YoloxConfig::yolox_nano,
+    },
+    RegistryEntry {
+        id: "yolox-tiny/coco",
+        config: YoloxConfig::yolox_tiny,
+    },
+    RegistryEntry {
+        id: "yolox-s/coco",
+        config: YoloxConfig::yolox_s,
+    },
+    RegistryEntry {
+        id: "yolox-m/coco",
+        config: YoloxConfig::yolox_m,
+    },
+    RegistryEntry {
+        id: "yolox-l/coco",
+        config: YoloxConfig::yolox_l,
+    },
+    RegistryEntry {
+        id: "yolox-x/coco",
+        config: YoloxConfig::yolox_x,
+    },
+];
+
+/// Error type for pre-trained model downloads.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The model ID is not part of the [registry](MODELS_REGISTRY).
+    UnsupportedModel(String),
+    /// The remote file could not be downloaded.
+    Network(String),
+    /// The downloaded file does not match the expected checksum.
+    ChecksumMismatch { expected: String, got: String },
+    /// Failed to read or write the cached file.
+    Io(std::io::Error),
+    /// Failed to load the record from the downloaded file.
+    Record(RecorderError),
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedModel(id) => write!(f, "Unsupported model ID '{id}'"),
+            Self::Network(reason) => write!(f, "Failed to download file: {reason}"),
+            Self::ChecksumMismatch { expected, got } => {
+                write!(f, "Checksum mismatch (expected {expected}, got {got})")
+            }
+            Self::Io(err) => write!(f, "Failed to access cached file: {err}"),
+            Self::Record(err) => write!(f, "Failed to load record: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<RecorderError> for DownloadError {
+    fn from(err: RecorderError) -> Self {
+        Self::Record(err)
+    }
+}
+
+/// Find the registry entry for the given model ID.
+pub fn find(model_id: &str) -> Result<&'static RegistryEntry, DownloadError> {
+    MODELS_REGISTRY
+        .iter()
+        .find(|entry| entry.id == model_id)
+        .ok_or_else(|| DownloadError::UnsupportedModel(model_id.to_string()))
+}
+
+impl RegistryEntry {
+    /// Configuration of the registered model.
+    pub fn config(&self) -> YoloxConfig {
+        (self.config)()
+    }
+
+    /// Pre-trained weights of the registered model.
+    pub fn weights(&self) -> Weights {
+        *self
+            .config()
+            .weights()
+            .expect("Registered models should have pre-trained weights")
+    }
+
+    /// Download the checkpoint to the local cache directory (if not already cached) and return
+    /// the path to the cached file.
+    pub fn download(&self) -> Result<PathBuf, DownloadError> {
+        self.weights().download()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::weights::{WeightsMeta, YoloxS};
+
+    #[test]
+    fn registered_models_have_weights() {
+        for entry in MODELS_REGISTRY {
+            let weights = entry.weights();
+            assert_eq!(weights.num_classes, 80);
+            assert!(weights.url.ends_with(".pth"));
+        }
+        assert_eq!(
+            find("yolox-s/coco").unwrap().weights().url,
+            YoloxS::Coco.weights().url
+        );
+    }
+
+    #[test]
+    fn unsupported_model() {
+        assert!(matches!(
+            find("yolox-s/voc"),
+            Err(DownloadError::UnsupportedModel(id)) if id == "yolox-s/voc"
+        ));
+    }
+}
@@ -0,0 +1,384 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d, Relu,
+    },
+    tensor::{activation::softmax, backend::Backend, Device, ElementConversion, Int, Tensor},
+};
+
+use super::boxes::non_maximum_suppression;
+use crate::types::{Detection, IoUThreshold};
+
+/// Maximum log-scale box size update, to avoid overflows of the exponential.
+const MAX_LOG_SCALE: f32 = 4.135; // ln(1000 / 16)
+
+/// [Region Proposal Network](https://arxiv.org/abs/1506.01497) (RPN) head of two-stage
+/// detectors (e.g., Faster R-CNN).
+///
+/// For each anchor of each location, the head predicts the background and foreground logits and
+/// the box deltas `(dx, dy, dw, dh)` relative to the anchor.
+#[derive(Module, Debug)]
+pub struct Rpn<B: Backend> {
+    convs: Vec<Conv2d<B>>,
+    activation: Relu,
+    objectness_head: Conv2d<B>,
+    bbox_head: Conv2d<B>,
+    anchor_sizes: Vec<usize>,
+    anchor_ratios: Vec<f32>,
+}
+
+impl<B: Backend> Rpn<B> {
+    /// Compute the objectness logits and box deltas.
+    ///
+    /// # Shapes
+    ///   - features: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, 2 * num_anchors, height, width]` objectness logits, with the
+    ///     `(background, foreground)` logits of each anchor, and
+    ///     `[batch_size, 4 * num_anchors, height, width]` box deltas
+    pub fn forward(&self, features: Tensor<B, 4>) -> (Tensor<B, 4>, Tensor<B, 4>) {
+        let x = self
+            .convs
+            .iter()
+            .fold(features, |x, conv| self.activation.forward(conv.forward(x)));
+
+        (
+            self.objectness_head.forward(x.clone()),
+            self.bbox_head.forward(x),
+        )
+    }
+
+    /// Number of anchors at each location.
+    pub fn num_anchors(&self) -> usize {
+        self.anchor_sizes.len() * self.anchor_ratios.len()
+    }
+
+    /// Compute the anchor boxes `(x1, y1, x2, y2)` of a feature map, centered on the grid cells.
+    ///
+    /// The anchors are ordered by location (row-major) then by anchor, as expected by the
+    /// [decoder](RpnDecoder).
+    ///
+    /// # Shapes
+    ///   - output: `[height * width * num_anchors, 4]`
+    pub fn anchors(
+        &self,
+        [height, width]: [usize; 2],
+        stride: usize,
+        device: &Device<B>,
+    ) -> Tensor<B, 2> {
+        // Anchor half sizes [A, 2]
+        let half_sizes: Vec<f32> = self
+            .anchor_ratios
+            .iter()
+            .flat_map(|&ratio| {
+                self.anchor_sizes.iter().flat_map(move |&size| {
+                    // ratio = height / width with the same area as a size x size square
+                    let w = size as f32 / ratio.sqrt();
+                    let h = size as f32 * ratio.sqrt();
+                    [w / 2., h / 2.]
+                })
+            })
+            .collect();
+        let num_anchors = self.num_anchors();
+        let half_sizes =
+            Tensor::<B, 1>::from_floats(half_sizes.as_slice(), device).reshape([1, num_anchors, 2]);
+
+        // Cell centers [H * W, 1, 2]
+        let xs = Tensor::<B, 1, Int>::arange(0..width as i64, device)
+            .float()
+            .reshape([1, width])
+            .repeat_dim(0, height);
+        let ys = Tensor::<B, 1, Int>::arange(0..height as i64, device)
+            .float()
+            .reshape([height, 1])
+            .repeat_dim(1, width);
+        let centers = Tensor::stack::<3>(vec![xs, ys], 2)
+            .add_scalar(0.5)
+            .mul_scalar(stride as f32)
+            .reshape([height * width, 1, 2]);
+
+        Tensor::cat(
+            vec![centers.clone() - half_sizes.clone(), centers + half_sizes],
+            2,
+        )
+        .reshape([height * width * num_anchors, 4])
+    }
+}
+
+/// [RPN](Rpn) configuration.
+pub struct RpnConfig {
+    in_channels: usize,
+    anchor_sizes: Vec<usize>,
+    anchor_ratios: Vec<f32>,
+    num_conv_layers: usize,
+}
+
+impl RpnConfig {
+    /// Create a new instance of the RPN [config](RpnConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the feature map.
+    /// * `anchor_sizes` - Sizes of the anchors in pixels (e.g., `[32, 64, 128, 256, 512]`).
+    /// * `anchor_ratios` - Aspect ratios (height / width) of the anchors (e.g., `[0.5, 1.0, 2.0]`).
+    /// * `num_conv_layers` - Number of `3x3` convolutions before the prediction heads.
+    pub fn new(
+        in_channels: usize,
+        anchor_sizes: Vec<usize>,
+        anchor_ratios: Vec<f32>,
+        num_conv_layers: usize,
+    ) -> Self {
+        assert!(
+            !anchor_sizes.is_empty() && !anchor_ratios.is_empty(),
+            "at least one anchor size and one anchor ratio are required"
+        );
+
+        Self {
+            in_channels,
+            anchor_sizes,
+            anchor_ratios,
+            num_conv_layers,
+        }
+    }
+
+    /// Initialize a new [RPN](Rpn) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Rpn<B> {
+        let c = self.in_channels;
+        let num_anchors = self.anchor_sizes.len() * self.anchor_ratios.len();
+        let head = |out_channels| {
+            Conv2dConfig::new([c, out_channels], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+                .init(device)
+        };
+
+        Rpn {
+            convs: (0..self.num_conv_layers)
+                .map(|_| {
+                    Conv2dConfig::new([c, c], [3, 3])
+                        .with_padding(PaddingConfig2d::Explicit(1, 1))
+                        .init(device)
+                })
+                .collect(),
+            activation: Relu::new(),
+            objectness_head: head(2 * num_anchors),
+            bbox_head: head(4 * num_anchors),
+            anchor_sizes: self.anchor_sizes.clone(),
+            anchor_ratios: self.anchor_ratios.clone(),
+        }
+    }
+}
+
+/// Decoder of the [RPN](Rpn) outputs into region proposals.
+pub struct RpnDecoder;
+
+impl RpnDecoder {
+    /// Decode the region proposals of each image.
+    ///
+    /// For each image, the `pre_nms_top_n` anchors with the highest foreground probability are
+    /// decoded, clipped to the image and filtered by size, then non-maximum suppression is applied
+    /// and the `post_nms_top_n` highest scoring proposals are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `objectness`: Objectness logits. Shape: `[batch_size, 2 * num_anchors, height, width]`.
+    /// * `bbox_deltas` - Box deltas. Shape: `[batch_size, 4 * num_anchors, height, width]`.
+    /// * `anchors` - [Anchor boxes](Rpn::anchors). Shape: `[height * width * num_anchors, 4]`.
+    /// * `image_size` - Input image size `(height, width)`.
+    /// * `min_size` - Minimum width and height of the proposals in pixels.
+    /// * `nms_threshold` - IoU threshold for non-maximum suppression.
+    /// * `pre_nms_top_n` - Number of proposals kept before non-maximum suppression.
+    /// * `post_nms_top_n` - Maximum number of proposals kept after non-maximum suppression.
+    ///
+    /// # Returns
+    ///
+    /// The proposals `(batch_index, x1, y1, x2, y2)` of all the images with shape
+    /// `[num_proposals, 5]`, with at most `post_nms_top_n` proposals per image.
+    #[allow(clippy::too_many_arguments)]
+    pub fn decode<B: Backend>(
+        objectness: Tensor<B, 4>,
+        bbox_deltas: Tensor<B, 4>,
+        anchors: Tensor<B, 2>,
+        image_size: [usize; 2],
+        min_size: f32,
+        nms_threshold: IoUThreshold,
+        pre_nms_top_n: usize,
+        post_nms_top_n: usize,
+    ) -> Tensor<B, 2> {
+        let [n, c, h, w] = objectness.dims();
+        let num_anchors = c / 2;
+        let num_boxes = h * w * num_anchors;
+        let device = objectness.device();
+
+        // Foreground probability [N, H * W * A]
+        let scores = softmax(objectness.reshape([n, num_anchors, 2, h, w]), 2)
+            .slice([0..n, 0..num_anchors, 1..2, 0..h, 0..w])
+            .reshape([n, num_anchors, h * w])
+            .swap_dims(1, 2)
+            .reshape([n, num_boxes]);
+        // Box deltas [N, H * W * A, 4]
+        let deltas = bbox_deltas
+            .reshape([n, num_anchors, 4, h * w])
+            .permute([0, 3, 1, 2])
+            .reshape([n, num_boxes, 4]);
+
+        let k = pre_nms_top_n.min(num_boxes);
+        let [img_h, img_w] = image_size;
+        let mut proposals = Vec::new();
+        for i in 0..n {
+            let (scores, indices) = scores
+                .clone()
+                .slice([i..i + 1, 0..num_boxes])
+                .reshape([num_boxes])
+                .topk_with_indices(k, 0);
+            let deltas = deltas
+                .clone()
+                .slice([i..i + 1, 0..num_boxes, 0..4])
+                .reshape([num_boxes, 4])
+                .select(0, indices.clone());
+            let boxes = apply_deltas(anchors.clone().select(0, indices), deltas)
+                .clamp_min(0.)
+                .into_data();
+            let scores = scores.into_data();
+
+            // Size filtering and suppression on the host
+            let mut candidates = vec![boxes
+                .iter::<B::FloatElem>()
+                .map(|v| v.elem::<f32>())
+                .collect::<Vec<_>>()
+                .chunks(4)
+                .zip(scores.iter::<B::FloatElem>())
+                .map(|(b, score)| {
+                    let box_xyxy = [b[0], b[1], b[2].min(img_w as f32), b[3].min(img_h as f32)];
+                    Detection::new(i, box_xyxy, score.elem(), 0)
+                })
+                .filter(|det| det.width() >= min_size && det.height() >= min_size)
+                .collect::<Vec<_>>()];
+            non_maximum_suppression(&mut candidates, nms_threshold);
+
+            proposals.extend(
+                candidates
+                    .remove(0)
+                    .into_iter()
+                    .take(post_nms_top_n)
+                    .flat_map(|det| {
+                        let [xmin, ymin, xmax, ymax] = det.box_xyxy;
+                        [i as f32, xmin, ymin, xmax, ymax]
+                    }),
+            );
+        }
+
+        let num_proposals = proposals.len() / 5;
+        Tensor::<B, 1>::from_floats(proposals.as_slice(), &device).reshape([num_proposals, 5])
+    }
+}
+
+/// Apply the box deltas `(dx, dy, dw, dh)` to the anchor boxes `(x1, y1, x2, y2)`.
+///
+/// # Shapes
+///   - anchors: `[num_boxes, 4]`
+///   - deltas: `[num_boxes, 4]`
+///   - output: `[num_boxes, 4]`
+fn apply_deltas<B: Backend>(anchors: Tensor<B, 2>, deltas: Tensor<B, 2>) -> Tensor<B, 2> {
+    let [m, _] = anchors.dims();
+
+    let x1y1 = anchors.clone().slice([0..m, 0..2]);
+    let wh = anchors.slice([0..m, 2..4]) - x1y1.clone();
+    let center = x1y1 + wh.clone().mul_scalar(0.5);
+
+    let center = center + deltas.clone().slice([0..m, 0..2]) * wh.clone();
+    let half_wh = deltas.slice([0..m, 2..4]).clamp_max(MAX_LOG_SCALE).exp() * wh.mul_scalar(0.5);
+
+    Tensor::cat(vec![center.clone() - half_wh.clone(), center + half_wh], 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn rpn(device: &Device<TestBackend>) -> Rpn<TestBackend> {
+        RpnConfig::new(8, vec![16, 32], vec![0.5, 1.0, 2.0], 1).init(device)
+    }
+
+    #[test]
+    fn output_channels_per_anchor() {
+        let device = Default::default();
+        let rpn = rpn(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 8, 8, 10], Distribution::Default, &device);
+
+        let (objectness, bbox_deltas) = rpn.forward(x);
+
+        assert_eq!(rpn.num_anchors(), 6);
+        // Background and foreground logits of each anchor
+        assert_eq!(objectness.dims(), [2, 2 * 6, 8, 10]);
+        assert_eq!(bbox_deltas.dims(), [2, 4 * 6, 8, 10]);
+    }
+
+    #[test]
+    fn anchors_centered_on_cells() {
+        let device = Default::default();
+        let rpn = RpnConfig::new(8, vec![16], vec![1.0, 4.0], 0).init::<TestBackend>(&device);
+
+        let anchors = rpn.anchors([2, 3], 8, &device);
+
+        assert_eq!(anchors.dims(), [2 * 3 * 2, 4]);
+        // First cell centered on (4, 4), second cell on (12, 4)
+        anchors.slice([0..3, 0..4]).into_data().assert_approx_eq(
+            &TensorData::from([
+                [-4f32, -4., 12., 12.],
+                [0., -12., 8., 20.],
+                [4., -4., 20., 12.],
+            ]),
+            5,
+        );
+    }
+
+    #[test]
+    fn decoded_proposals_count() {
+        let device = Default::default();
+        let rpn = rpn(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 8, 8, 8], Distribution::Default, &device);
+        let (objectness, bbox_deltas) = rpn.forward(x);
+        let anchors = rpn.anchors([8, 8], 8, &device);
+
+        let proposals = RpnDecoder::decode(
+            objectness,
+            bbox_deltas,
+            anchors,
+            [64, 64],
+            1.,
+            IoUThreshold::from(0.7),
+            100,
+            10,
+        );
+
+        let [num_proposals, num_values] = proposals.dims();
+        assert_eq!(num_values, 5);
+        assert!(num_proposals > 0);
+        let values = proposals.into_data().to_vec::<f32>().unwrap();
+        for image in [0., 1.] {
+            let count = values.chunks(5).filter(|p| p[0] == image).count();
+            assert!(count <= 10);
+        }
+        // Clipped to the image
+        for p in values.chunks(5) {
+            assert!(p[1] >= 0. && p[2] >= 0. && p[3] <= 64. && p[4] <= 64.);
+        }
+    }
+
+    #[test]
+    fn zero_deltas_keep_anchors() {
+        let device = Default::default();
+        let anchors = Tensor::<TestBackend, 2>::from_floats([[4., 8., 20., 16.]], &device);
+
+        let boxes = apply_deltas(anchors.clone(), Tensor::zeros([1, 4], &device));
+
+        boxes.into_data().assert_approx_eq(&anchors.into_data(), 5);
+    }
+}
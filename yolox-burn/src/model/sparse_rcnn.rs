@@ -0,0 +1,270 @@
+//! Dynamic instance interaction of [Sparse R-CNN](https://arxiv.org/abs/2011.12450).
+use burn::{
+    module::Module,
+    nn::{LayerNorm, LayerNormConfig, Linear, LinearConfig},
+    tensor::{activation::relu, backend::Backend, Device, Tensor},
+};
+
+/// Default number of channels of the dynamic convolution between the two interactions.
+const DYNAMIC_DIM: usize = 64;
+
+/// Convolution whose `1x1` kernel is generated from the feature vector of each proposal, and
+/// applied to the RoI features of the same proposal.
+///
+/// The kernel `[in_channels, out_channels]` is predicted by a linear layer from the instance
+/// feature, and applied to the RoI features with a batched matrix multiplication followed by a
+/// layer normalization.
+#[derive(Module, Debug)]
+pub struct DynamicConv<B: Backend> {
+    params: Linear<B>,
+    norm: LayerNorm<B>,
+    in_channels: usize,
+    out_channels: usize,
+}
+
+impl<B: Backend> DynamicConv<B> {
+    /// Apply the dynamic convolution of each proposal to its RoI features.
+    ///
+    /// # Shapes
+    ///   - roi_features: `[batch_size, num_proposals, num_points, in_channels]`, where
+    ///     `num_points` is the number of positions of the pooled RoI (e.g., 49 for `7x7`)
+    ///   - instance_features: `[batch_size, num_proposals, feat_channels]`
+    ///   - output: `[batch_size, num_proposals, num_points, out_channels]`
+    pub fn forward(
+        &self,
+        roi_features: Tensor<B, 4>,
+        instance_features: Tensor<B, 3>,
+    ) -> Tensor<B, 4> {
+        let [n, p, s, _] = roi_features.dims();
+
+        let params = self.dynamic_params(instance_features);
+        let x = roi_features
+            .reshape([n * p, s, self.in_channels])
+            .matmul(params);
+
+        self.norm.forward(x).reshape([n, p, s, self.out_channels])
+    }
+
+    /// Generate the dynamic kernel of each proposal.
+    ///
+    /// # Shapes
+    ///   - instance_features: `[batch_size, num_proposals, feat_channels]`
+    ///   - output: `[batch_size * num_proposals, in_channels, out_channels]`
+    pub fn dynamic_params(&self, instance_features: Tensor<B, 3>) -> Tensor<B, 3> {
+        let [n, p, _] = instance_features.dims();
+
+        self.params
+            .forward(instance_features)
+            .reshape([n * p, self.in_channels, self.out_channels])
+    }
+}
+
+/// [Dynamic convolution](DynamicConv) configuration.
+pub struct DynamicConvConfig {
+    in_channels: usize,
+    feat_channels: usize,
+    out_channels: usize,
+}
+
+impl DynamicConvConfig {
+    /// Create a new instance of the dynamic convolution [config](DynamicConvConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the RoI features.
+    /// * `feat_channels` - Number of channels of the instance features.
+    /// * `out_channels` - Number of output channels.
+    pub fn new(in_channels: usize, feat_channels: usize, out_channels: usize) -> Self {
+        Self {
+            in_channels,
+            feat_channels,
+            out_channels,
+        }
+    }
+
+    /// Number of dynamic parameters generated for each proposal.
+    pub fn num_params(&self) -> usize {
+        self.in_channels * self.out_channels
+    }
+
+    /// Initialize a new [dynamic convolution](DynamicConv) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DynamicConv<B> {
+        DynamicConv {
+            params: LinearConfig::new(self.feat_channels, self.num_params()).init(device),
+            norm: LayerNormConfig::new(self.out_channels).init(device),
+            in_channels: self.in_channels,
+            out_channels: self.out_channels,
+        }
+    }
+}
+
+/// Outputs of the [dynamic instance interaction head](DynamicConvHead).
+pub struct DynamicConvHeadOutput<B: Backend> {
+    /// Updated instance features. Shape: `[batch_size, num_proposals, hidden_dim]`.
+    pub features: Tensor<B, 3>,
+    /// Class logits. Shape: `[batch_size, num_proposals, num_classes]`.
+    pub cls_logits: Tensor<B, 3>,
+    /// Box deltas of the proposals. Shape: `[batch_size, num_proposals, 4]`.
+    pub box_deltas: Tensor<B, 3>,
+}
+
+/// Dynamic instance interaction head of Sparse R-CNN.
+///
+/// The RoI features of each proposal go through two [dynamic convolutions](DynamicConv)
+/// (`hidden_dim -> dynamic_dim -> hidden_dim`) generated from the proposal feature, with ReLU
+/// activations. The result is flattened and projected to the updated instance feature, from which
+/// the class logits and box deltas are predicted.
+#[derive(Module, Debug)]
+pub struct DynamicConvHead<B: Backend> {
+    conv1: DynamicConv<B>,
+    conv2: DynamicConv<B>,
+    out_proj: Linear<B>,
+    out_norm: LayerNorm<B>,
+    cls_head: Linear<B>,
+    reg_head: Linear<B>,
+}
+
+impl<B: Backend> DynamicConvHead<B> {
+    /// Compute the instance interaction and the predictions of each proposal.
+    ///
+    /// # Shapes
+    ///   - roi_features: `[batch_size, num_proposals, num_points, hidden_dim]`
+    ///   - instance_features: `[batch_size, num_proposals, hidden_dim]`
+    pub fn forward(
+        &self,
+        roi_features: Tensor<B, 4>,
+        instance_features: Tensor<B, 3>,
+    ) -> DynamicConvHeadOutput<B> {
+        let [n, p, s, d] = roi_features.dims();
+
+        let x = relu(self.conv1.forward(roi_features, instance_features.clone()));
+        let x = relu(self.conv2.forward(x, instance_features));
+
+        let x = self.out_proj.forward(x.reshape([n, p, s * d]));
+        let features = relu(self.out_norm.forward(x));
+
+        DynamicConvHeadOutput {
+            cls_logits: self.cls_head.forward(features.clone()),
+            box_deltas: self.reg_head.forward(features.clone()),
+            features,
+        }
+    }
+}
+
+/// [Dynamic instance interaction head](DynamicConvHead) configuration.
+pub struct DynamicConvHeadConfig {
+    hidden_dim: usize,
+    dynamic_dim: usize,
+    num_points: usize,
+    num_classes: usize,
+}
+
+impl DynamicConvHeadConfig {
+    /// Create a new instance of the dynamic instance interaction head
+    /// [config](DynamicConvHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden_dim`: Number of channels of the RoI and instance features (e.g., 256).
+    /// * `num_points` - Number of positions of the pooled RoI features (e.g., 49 for `7x7`).
+    /// * `num_classes` - Number of object classes.
+    pub fn new(hidden_dim: usize, num_points: usize, num_classes: usize) -> Self {
+        Self {
+            hidden_dim,
+            dynamic_dim: DYNAMIC_DIM,
+            num_points,
+            num_classes,
+        }
+    }
+
+    /// Set the number of channels between the two dynamic convolutions (defaults to 64).
+    pub fn with_dynamic_dim(mut self, dynamic_dim: usize) -> Self {
+        self.dynamic_dim = dynamic_dim;
+        self
+    }
+
+    /// Initialize a new [dynamic instance interaction head](DynamicConvHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DynamicConvHead<B> {
+        let (d, dynamic_dim) = (self.hidden_dim, self.dynamic_dim);
+
+        DynamicConvHead {
+            conv1: DynamicConvConfig::new(d, d, dynamic_dim).init(device),
+            conv2: DynamicConvConfig::new(dynamic_dim, d, d).init(device),
+            out_proj: LinearConfig::new(self.num_points * d, d).init(device),
+            out_norm: LayerNormConfig::new(d).init(device),
+            cls_head: LinearConfig::new(d, self.num_classes).init(device),
+            reg_head: LinearConfig::new(d, 4).init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn dynamic_conv_shape() {
+        let device = Default::default();
+        let conv = DynamicConvConfig::new(16, 32, 8).init::<TestBackend>(&device);
+
+        let roi_features = Tensor::random([2, 5, 49, 16], Distribution::Default, &device);
+        let instance_features = Tensor::random([2, 5, 32], Distribution::Default, &device);
+        let output = conv.forward(roi_features, instance_features);
+
+        assert_eq!(output.dims(), [2, 5, 49, 8]);
+    }
+
+    #[test]
+    fn dynamic_conv_num_params() {
+        let config = DynamicConvConfig::new(16, 32, 8);
+        assert_eq!(config.num_params(), 128);
+
+        let device = Default::default();
+        let conv = config.init::<TestBackend>(&device);
+        assert_eq!(conv.params.weight.dims(), [32, 128]);
+    }
+
+    #[test]
+    fn dynamic_params_depend_on_instance_features() {
+        let device = Default::default();
+        let conv = DynamicConvConfig::new(4, 8, 4).init::<TestBackend>(&device);
+
+        let instance_features = Tensor::cat(
+            vec![
+                Tensor::<TestBackend, 3>::ones([1, 1, 8], &device),
+                Tensor::full([1, 1, 8], -2., &device),
+            ],
+            1,
+        );
+        let params = conv.dynamic_params(instance_features);
+        assert_eq!(params.dims(), [2, 4, 4]);
+
+        let diff = (params.clone().slice([0..1]) - params.slice([1..2]))
+            .abs()
+            .max()
+            .into_scalar();
+        assert!(
+            diff > 1e-3,
+            "same dynamic parameters for different proposals"
+        );
+    }
+
+    #[test]
+    fn dynamic_conv_head_shapes() {
+        let device = Default::default();
+        let head = DynamicConvHeadConfig::new(32, 49, 80)
+            .with_dynamic_dim(16)
+            .init::<TestBackend>(&device);
+
+        let roi_features = Tensor::random([2, 10, 49, 32], Distribution::Default, &device);
+        let instance_features = Tensor::random([2, 10, 32], Distribution::Default, &device);
+        let output = head.forward(roi_features, instance_features);
+
+        assert_eq!(output.features.dims(), [2, 10, 32]);
+        assert_eq!(output.cls_logits.dims(), [2, 10, 80]);
+        assert_eq!(output.box_deltas.dims(), [2, 10, 4]);
+    }
+}
@@ -0,0 +1,227 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d,
+    },
+    tensor::{activation::leaky_relu, backend::Backend, Device, Tensor},
+};
+
+/// Negative slope of the leaky ReLU activations.
+const NEGATIVE_SLOPE: f64 = 0.2;
+/// Number of dense layers of each [dense block](DenseBlock).
+const NUM_DENSE_LAYERS: usize = 5;
+
+/// Layer of a [dense block](DenseBlock): `3x3` Conv2d -> LeakyReLU.
+///
+/// The last layer of a block has no activation.
+#[derive(Module, Debug)]
+pub struct DenseLayer<B: Backend> {
+    conv: Conv2d<B>,
+    activation: bool,
+}
+
+impl<B: Backend> DenseLayer<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.conv.forward(x);
+
+        if self.activation {
+            leaky_relu(x, NEGATIVE_SLOPE)
+        } else {
+            x
+        }
+    }
+}
+
+/// Residual dense block of [ESRGAN](https://arxiv.org/abs/1809.00219).
+///
+/// Each of the first four layers receives the concatenation of the block input and the outputs of
+/// all the previous layers, and adds `num_grow_ch` channels to the dense feature map. The last
+/// layer projects the dense feature map back to `num_features` channels, and its output is scaled
+/// by `beta` and added to the block input.
+#[derive(Module, Debug)]
+pub struct DenseBlock<B: Backend> {
+    layers: Vec<DenseLayer<B>>,
+    beta: f64,
+}
+
+impl<B: Backend> DenseBlock<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, num_features, height, width]`
+    ///   - output: `[batch_size, num_features, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let (last, layers) = self.layers.split_last().unwrap();
+
+        // The dense feature map is extended in place of keeping the outputs of all the layers
+        let mut features = x.clone();
+        for layer in layers {
+            let out = layer.forward(features.clone());
+            features = Tensor::cat(alloc::vec![features, out], 1);
+        }
+
+        last.forward(features).mul_scalar(self.beta) + x
+    }
+}
+
+/// [Dense block](DenseBlock) configuration.
+pub struct DenseBlockConfig {
+    num_features: usize,
+    num_grow_ch: usize,
+    beta: f64,
+}
+
+impl DenseBlockConfig {
+    /// Create a new instance of the dense block [config](DenseBlockConfig).
+    pub fn new(num_features: usize, num_grow_ch: usize, beta: f64) -> Self {
+        Self {
+            num_features,
+            num_grow_ch,
+            beta,
+        }
+    }
+
+    /// Number of channels of the dense feature map at the input of each layer.
+    pub fn layer_in_channels(&self) -> Vec<usize> {
+        (0..NUM_DENSE_LAYERS)
+            .map(|i| self.num_features + i * self.num_grow_ch)
+            .collect()
+    }
+
+    /// Initialize a new [dense block](DenseBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DenseBlock<B> {
+        let layers = self
+            .layer_in_channels()
+            .into_iter()
+            .enumerate()
+            .map(|(i, in_channels)| {
+                let is_last = i + 1 == NUM_DENSE_LAYERS;
+                let out_channels = if is_last {
+                    self.num_features
+                } else {
+                    self.num_grow_ch
+                };
+
+                DenseLayer {
+                    conv: Conv2dConfig::new([in_channels, out_channels], [3, 3])
+                        .with_padding(PaddingConfig2d::Explicit(1, 1))
+                        .init(device),
+                    activation: !is_last,
+                }
+            })
+            .collect();
+
+        DenseBlock {
+            layers,
+            beta: self.beta,
+        }
+    }
+}
+
+/// Residual-in-residual dense block (RRDB) of [ESRGAN](https://arxiv.org/abs/1809.00219).
+///
+/// A sequence of [dense blocks](DenseBlock), whose output is scaled by `beta` and added to the
+/// block input. With `beta = 0`, the block is the identity.
+#[derive(Module, Debug)]
+pub struct Rrdb<B: Backend> {
+    blocks: Vec<DenseBlock<B>>,
+    beta: f64,
+}
+
+impl<B: Backend> Rrdb<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, num_features, height, width]`
+    ///   - output: `[batch_size, num_features, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let out = self
+            .blocks
+            .iter()
+            .fold(x.clone(), |out, block| block.forward(out));
+
+        out.mul_scalar(self.beta) + x
+    }
+}
+
+/// [RRDB](Rrdb) configuration.
+pub struct RrdbConfig {
+    block: DenseBlockConfig,
+    num_dense_blocks: usize,
+    beta: f64,
+}
+
+impl RrdbConfig {
+    /// Create a new instance of the RRDB [config](RrdbConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `num_features`: Number of input and output channels (64 for ESRGAN).
+    /// * `num_grow_ch` - Number of channels added by each dense layer (32 for ESRGAN).
+    /// * `num_dense_blocks` - Number of dense blocks (3 for ESRGAN).
+    /// * `beta` - Scaling factor of the residuals of the dense blocks and of the RRDB (0.2 for
+    ///   ESRGAN).
+    pub fn new(
+        num_features: usize,
+        num_grow_ch: usize,
+        num_dense_blocks: usize,
+        beta: f64,
+    ) -> Self {
+        Self {
+            block: DenseBlockConfig::new(num_features, num_grow_ch, beta),
+            num_dense_blocks,
+            beta,
+        }
+    }
+
+    /// Initialize a new [RRDB](Rrdb) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Rrdb<B> {
+        Rrdb {
+            blocks: (0..self.num_dense_blocks)
+                .map(|_| self.block.init(device))
+                .collect(),
+            beta: self.beta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn rrdb_zero_beta_identity() {
+        let device = Default::default();
+        let rrdb = RrdbConfig::new(8, 4, 3, 0.).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 8, 6, 5], Distribution::Default, &device);
+
+        rrdb.forward(x.clone())
+            .into_data()
+            .assert_eq(&x.into_data(), true);
+    }
+
+    #[test]
+    fn dense_feature_map_growth() {
+        let config = DenseBlockConfig::new(8, 4, 0.2);
+        assert_eq!(config.layer_in_channels(), [8, 12, 16, 20, 24]);
+
+        let block = config.init::<TestBackend>(&Default::default());
+        assert_eq!(block.layers.len(), NUM_DENSE_LAYERS);
+        for (i, layer) in block.layers.iter().enumerate() {
+            let [out_channels, in_channels, _, _] = layer.conv.weight.dims();
+            assert_eq!(in_channels, 8 + i * 4);
+            assert_eq!(out_channels, if i == 4 { 8 } else { 4 });
+        }
+    }
+
+    #[test]
+    fn rrdb_output_channels() {
+        let device = Default::default();
+        let rrdb = RrdbConfig::new(8, 4, 3, 0.2).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 8, 6, 5], Distribution::Default, &device);
+
+        assert_eq!(rrdb.blocks.len(), 3);
+        assert_eq!(rrdb.forward(x).dims(), [2, 8, 6, 5]);
+    }
+}
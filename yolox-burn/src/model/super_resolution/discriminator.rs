@@ -0,0 +1,168 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        BatchNorm, BatchNormConfig, Linear, LinearConfig, PaddingConfig2d,
+    },
+    tensor::{activation::leaky_relu, backend::Backend, Device, Tensor},
+};
+
+/// Negative slope of the leaky ReLU activations.
+const NEGATIVE_SLOPE: f64 = 0.2;
+/// Maximum number of features, relative to the base number of features.
+const MAX_FEATURES_MULTIPLIER: usize = 8;
+
+/// Downsampling block of the [VGG-style discriminator](VggDiscriminator):
+/// Conv2d (stride 2) -> BatchNorm -> LeakyReLU.
+#[derive(Module, Debug)]
+pub struct DiscriminatorBlock<B: Backend> {
+    conv: Conv2d<B>,
+    bn: BatchNorm<B, 2>,
+}
+
+impl<B: Backend> DiscriminatorBlock<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.conv.forward(x);
+        let x = self.bn.forward(x);
+
+        leaky_relu(x, NEGATIVE_SLOPE)
+    }
+}
+
+/// [Discriminator block](DiscriminatorBlock) configuration.
+pub struct DiscriminatorBlockConfig {
+    conv: Conv2dConfig,
+    bn: BatchNormConfig,
+}
+
+impl DiscriminatorBlockConfig {
+    /// Create a new instance of the discriminator block [config](DiscriminatorBlockConfig).
+    pub fn new(in_channels: usize, out_channels: usize) -> Self {
+        let conv = Conv2dConfig::new([in_channels, out_channels], [3, 3])
+            .with_stride([2, 2])
+            .with_padding(PaddingConfig2d::Explicit(1, 1))
+            .with_bias(false);
+        let bn = BatchNormConfig::new(out_channels);
+
+        Self { conv, bn }
+    }
+
+    /// Initialize a new [discriminator block](DiscriminatorBlock) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DiscriminatorBlock<B> {
+        DiscriminatorBlock {
+            conv: self.conv.init(device),
+            bn: self.bn.init(device),
+        }
+    }
+}
+
+/// VGG-style discriminator, as used to train [ESRGAN](https://arxiv.org/abs/1809.00219) and
+/// similar super-resolution models.
+///
+/// A stem convolution is followed by blocks which halve the resolution and double the number of
+/// features (up to 8 times the base number of features). The features are averaged over the
+/// spatial dimensions and a final linear layer predicts the real/fake score, so the input images
+/// can have any size.
+#[derive(Module, Debug)]
+pub struct VggDiscriminator<B: Backend> {
+    stem: Conv2d<B>,
+    blocks: Vec<DiscriminatorBlock<B>>,
+    classifier: Linear<B>,
+}
+
+impl<B: Backend> VggDiscriminator<B> {
+    /// Predict the real/fake score (logit) of each image.
+    ///
+    /// # Shapes
+    ///   - images: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, 1]`
+    pub fn forward(&self, images: Tensor<B, 4>) -> Tensor<B, 2> {
+        let x = leaky_relu(self.stem.forward(images), NEGATIVE_SLOPE);
+        let x = self.blocks.iter().fold(x, |x, block| block.forward(x));
+
+        let [batch_size, channels, height, width] = x.dims();
+        let x = x
+            .reshape([batch_size, channels, height * width])
+            .mean_dim(2)
+            .reshape([batch_size, channels]);
+
+        self.classifier.forward(x)
+    }
+}
+
+/// [VGG-style discriminator](VggDiscriminator) configuration.
+pub struct VggDiscriminatorConfig {
+    stem: Conv2dConfig,
+    blocks: Vec<DiscriminatorBlockConfig>,
+    classifier: LinearConfig,
+}
+
+impl VggDiscriminatorConfig {
+    /// Create a new instance of the VGG-style discriminator [config](VggDiscriminatorConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of channels of the input images.
+    /// * `base_features` - Number of features of the stem convolution.
+    /// * `num_blocks` - Number of downsampling blocks.
+    pub fn new(in_channels: usize, base_features: usize, num_blocks: usize) -> Self {
+        let stem = Conv2dConfig::new([in_channels, base_features], [3, 3])
+            .with_padding(PaddingConfig2d::Explicit(1, 1));
+
+        let max_features = base_features * MAX_FEATURES_MULTIPLIER;
+        let mut features = base_features;
+        let blocks = (0..num_blocks)
+            .map(|_| {
+                let out_features = (features * 2).min(max_features);
+                let block = DiscriminatorBlockConfig::new(features, out_features);
+                features = out_features;
+                block
+            })
+            .collect();
+        let classifier = LinearConfig::new(features, 1);
+
+        Self {
+            stem,
+            blocks,
+            classifier,
+        }
+    }
+
+    /// Initialize a new [VGG-style discriminator](VggDiscriminator) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> VggDiscriminator<B> {
+        VggDiscriminator {
+            stem: self.stem.init(device),
+            blocks: self.blocks.iter().map(|b| b.init(device)).collect(),
+            classifier: self.classifier.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn discriminator_scores() {
+        let device = Default::default();
+        let discriminator = VggDiscriminatorConfig::new(3, 8, 3).init::<TestBackend>(&device);
+        let images = Tensor::random([2, 3, 32, 48], Distribution::Default, &device);
+
+        assert_eq!(discriminator.forward(images).dims(), [2, 1]);
+    }
+
+    #[test]
+    fn discriminator_max_features() {
+        let device = Default::default();
+        let discriminator = VggDiscriminatorConfig::new(3, 4, 5).init::<TestBackend>(&device);
+
+        // 8, 16, 32, 32 and 32 features
+        let [d_input, _] = discriminator.classifier.weight.dims();
+        assert_eq!(d_input, 4 * MAX_FEATURES_MULTIPLIER);
+        assert_eq!(discriminator.blocks.len(), 5);
+    }
+}
@@ -0,0 +1,464 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig, ConvTranspose2d, ConvTranspose2dConfig},
+        pool::{MaxPool2d, MaxPool2dConfig},
+        PaddingConfig2d,
+    },
+    tensor::{
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, Tensor,
+    },
+};
+
+use super::blocks::{ActivationType, AttentionGate, AttentionGateConfig, BaseConv, BaseConvConfig};
+
+/// Default number of channels of each encoder level.
+const FEATURES: [usize; 5] = [64, 128, 256, 512, 1024];
+
+/// Two `3x3` Conv2d -> BatchNorm -> ReLU blocks.
+#[derive(Module, Debug)]
+pub struct DoubleConv<B: Backend> {
+    conv1: BaseConv<B>,
+    conv2: BaseConv<B>,
+}
+
+impl<B: Backend> DoubleConv<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        self.conv2.forward(self.conv1.forward(x))
+    }
+}
+
+/// [Double convolution block](DoubleConv) configuration.
+pub struct DoubleConvConfig {
+    conv1: BaseConvConfig,
+    conv2: BaseConvConfig,
+}
+
+impl DoubleConvConfig {
+    /// Create a new instance of the double convolution block [config](DoubleConvConfig).
+    pub fn new(in_channels: usize, out_channels: usize) -> Self {
+        Self {
+            conv1: BaseConvConfig::new(in_channels, out_channels, 3, 1, 1)
+                .with_activation(ActivationType::Relu),
+            conv2: BaseConvConfig::new(out_channels, out_channels, 3, 1, 1)
+                .with_activation(ActivationType::Relu),
+        }
+    }
+
+    /// Initialize a new [double convolution block](DoubleConv) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DoubleConv<B> {
+        DoubleConv {
+            conv1: self.conv1.init(device),
+            conv2: self.conv2.init(device),
+        }
+    }
+}
+
+/// [U-Net](https://arxiv.org/abs/1505.04597) segmentation model, optionally with the
+/// [attention gates](AttentionGate) of [Attention U-Net](https://arxiv.org/abs/1804.03999) on the
+/// skip connections.
+///
+/// The input height and width should be divisible by `2^(num_levels - 1)`.
+#[derive(Module, Debug)]
+pub struct UNet<B: Backend> {
+    encoder: Vec<DoubleConv<B>>,
+    pool: MaxPool2d,
+    upsample: Vec<ConvTranspose2d<B>>,
+    /// Attention gate of each skip connection (empty without attention).
+    gates: Vec<AttentionGate<B>>,
+    decoder: Vec<DoubleConv<B>>,
+    output: Conv2d<B>,
+}
+
+impl<B: Backend> UNet<B> {
+    /// Compute the segmentation logits.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, num_classes, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let num_levels = self.encoder.len();
+
+        // Encoder, keeping the skip connections
+        let mut skips = Vec::with_capacity(num_levels - 1);
+        let mut x = x;
+        for (i, block) in self.encoder.iter().enumerate() {
+            if i > 0 {
+                x = self.pool.forward(x);
+            }
+            x = block.forward(x);
+            if i + 1 < num_levels {
+                skips.push(x.clone());
+            }
+        }
+
+        // Decoder, from the lowest resolution
+        for (i, (upsample, block)) in self.upsample.iter().zip(self.decoder.iter()).enumerate() {
+            let skip = skips.pop().unwrap();
+            let skip = match self.gates.get(i) {
+                // The gating signal is the decoder features before upsampling
+                Some(gate) => gate.forward(x.clone(), skip),
+                None => skip,
+            };
+
+            x = upsample.forward(x);
+            x = block.forward(Tensor::cat(vec![skip, x], 1));
+        }
+
+        self.output.forward(x)
+    }
+
+    /// Whether the skip connections are gated by [attention gates](AttentionGate).
+    pub fn has_attention(&self) -> bool {
+        !self.gates.is_empty()
+    }
+}
+
+/// [U-Net](UNet) configuration.
+pub struct UNetConfig {
+    in_channels: usize,
+    num_classes: usize,
+    features: Vec<usize>,
+    attention: bool,
+}
+
+impl UNetConfig {
+    /// Create a new instance of the U-Net [config](UNetConfig).
+    pub fn new(in_channels: usize, num_classes: usize) -> Self {
+        Self {
+            in_channels,
+            num_classes,
+            features: FEATURES.to_vec(),
+            attention: false,
+        }
+    }
+
+    /// Set the number of channels of each encoder level, from the highest to the lowest
+    /// resolution (defaults to `[64, 128, 256, 512, 1024]`).
+    pub fn with_features(mut self, features: Vec<usize>) -> Self {
+        assert!(features.len() >= 2, "at least two levels are required");
+        self.features = features;
+        self
+    }
+
+    /// Gate the skip connections with [attention gates](AttentionGate) (Attention U-Net).
+    pub fn with_attention(mut self, attention: bool) -> Self {
+        self.attention = attention;
+        self
+    }
+
+    /// Initialize a new [U-Net](UNet) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> UNet<B> {
+        let features = &self.features;
+        let num_levels = features.len();
+
+        let encoder = (0..num_levels)
+            .map(|i| {
+                let in_channels = if i == 0 {
+                    self.in_channels
+                } else {
+                    features[i - 1]
+                };
+                DoubleConvConfig::new(in_channels, features[i]).init(device)
+            })
+            .collect();
+
+        // Decoder levels, from the lowest resolution
+        let levels: Vec<_> = (0..num_levels - 1).rev().collect();
+        let upsample = levels
+            .iter()
+            .map(|&i| {
+                ConvTranspose2dConfig::new([features[i + 1], features[i]], [2, 2])
+                    .with_stride([2, 2])
+                    .init(device)
+            })
+            .collect();
+        let gates = if self.attention {
+            levels
+                .iter()
+                .map(|&i| {
+                    let inter_channels = (features[i] / 2).max(1);
+                    AttentionGateConfig::new(features[i + 1], features[i], inter_channels)
+                        .init(device)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let decoder = levels
+            .iter()
+            .map(|&i| DoubleConvConfig::new(2 * features[i], features[i]).init(device))
+            .collect();
+
+        UNet {
+            encoder,
+            pool: MaxPool2dConfig::new([2, 2]).with_strides([2, 2]).init(),
+            upsample,
+            gates,
+            decoder,
+            output: Conv2dConfig::new([features[0], self.num_classes], [1, 1])
+                .with_padding(PaddingConfig2d::Explicit(0, 0))
+                .init(device),
+        }
+    }
+}
+
+/// [UNet++](https://arxiv.org/abs/1807.10165) (nested U-Net) segmentation model.
+///
+/// The skip connections are replaced by dense convolution blocks: the node `X(i, j)` at
+/// downsampling level `i` and dense block index `j` fuses all the previous nodes `X(i, 0..j)` of
+/// its level with the upsampled node `X(i + 1, j - 1)`. The encoder nodes are `X(i, 0)`.
+///
+/// With deep supervision, a prediction is made from each of the top level nodes `X(0, 1..depth)`
+/// (i.e., from the output of each nested U-Net). The input height and width should be divisible
+/// by `2^(depth - 1)`.
+#[derive(Module, Debug)]
+pub struct UNetPlusPlus<B: Backend> {
+    /// Nodes `X(i, j)` of each level `i`, with `j < depth - i`.
+    nodes: Vec<Vec<DoubleConv<B>>>,
+    pool: MaxPool2d,
+    /// Prediction heads of the nodes `X(0, 1..depth)` with deep supervision, or of the last node
+    /// only.
+    outputs: Vec<Conv2d<B>>,
+}
+
+impl<B: Backend> UNetPlusPlus<B> {
+    /// Compute the segmentation logits of each supervised output.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, num_classes, height, width]` for each of the `depth - 1`
+    ///     decoder paths with deep supervision, or for the full network only
+    pub fn forward(&self, x: Tensor<B, 4>) -> Vec<Tensor<B, 4>> {
+        let depth = self.nodes.len();
+        let mut grid: Vec<Vec<Tensor<B, 4>>> = vec![Vec::new(); depth];
+
+        // Encoder nodes X(i, 0)
+        let mut x = x;
+        for (i, nodes) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                x = self.pool.forward(x);
+            }
+            x = nodes[0].forward(x);
+            grid[i].push(x.clone());
+        }
+
+        // Dense nodes X(i, j), column by column
+        for j in 1..depth {
+            for i in 0..depth - j {
+                let below = grid[i + 1][j - 1].clone();
+                let [_, _, h, w] = below.dims();
+                let up = interpolate(
+                    below,
+                    [h * 2, w * 2],
+                    InterpolateOptions::new(InterpolateMode::Bilinear),
+                );
+
+                let mut inputs = grid[i].clone();
+                inputs.push(up);
+                let node = self.nodes[i][j].forward(Tensor::cat(inputs, 1));
+                grid[i].push(node);
+            }
+        }
+
+        let top = &grid[0];
+        let first = top.len() - self.outputs.len();
+        self.outputs
+            .iter()
+            .zip(&top[first..])
+            .map(|(output, x)| output.forward(x.clone()))
+            .collect()
+    }
+
+    /// Compute the segmentation logits, averaged over the supervised outputs.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, num_classes, height, width]`
+    pub fn predict(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let outputs = self.forward(x);
+        let num_outputs = outputs.len();
+
+        outputs
+            .into_iter()
+            .reduce(|sum, x| sum + x)
+            .unwrap()
+            .div_scalar(num_outputs as f32)
+    }
+
+    /// Whether a prediction is made from each decoder path.
+    pub fn has_deep_supervision(&self) -> bool {
+        self.outputs.len() > 1
+    }
+}
+
+/// [UNet++](UNetPlusPlus) configuration.
+pub struct UNetPlusPlusConfig {
+    in_channels: usize,
+    num_classes: usize,
+    base_features: usize,
+    depth: usize,
+    deep_supervision: bool,
+}
+
+impl UNetPlusPlusConfig {
+    /// Create a new instance of the UNet++ [config](UNetPlusPlusConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_channels`: Number of input image channels.
+    /// * `num_classes` - Number of segmentation classes.
+    /// * `base_features` - Number of channels of the first level, doubled at each level (32 in
+    ///   the original paper).
+    /// * `depth` - Number of levels (5 in the original paper).
+    /// * `deep_supervision` - Predict from each of the `depth - 1` decoder paths.
+    pub fn new(
+        in_channels: usize,
+        num_classes: usize,
+        base_features: usize,
+        depth: usize,
+        deep_supervision: bool,
+    ) -> Self {
+        assert!(depth >= 2, "at least two levels are required");
+
+        Self {
+            in_channels,
+            num_classes,
+            base_features,
+            depth,
+            deep_supervision,
+        }
+    }
+
+    /// Number of channels of each level, from the highest to the lowest resolution.
+    pub fn features(&self) -> Vec<usize> {
+        (0..self.depth).map(|i| self.base_features << i).collect()
+    }
+
+    /// Initialize a new [UNet++](UNetPlusPlus) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> UNetPlusPlus<B> {
+        let features = self.features();
+        let depth = self.depth;
+
+        let nodes = (0..depth)
+            .map(|i| {
+                (0..depth - i)
+                    .map(|j| {
+                        let in_channels = match (i, j) {
+                            (0, 0) => self.in_channels,
+                            (_, 0) => features[i - 1],
+                            // X(i, 0..j) and the upsampled X(i + 1, j - 1)
+                            _ => j * features[i] + features[i + 1],
+                        };
+                        DoubleConvConfig::new(in_channels, features[i]).init(device)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let num_outputs = if self.deep_supervision { depth - 1 } else { 1 };
+        let outputs = (0..num_outputs)
+            .map(|_| {
+                Conv2dConfig::new([features[0], self.num_classes], [1, 1])
+                    .with_padding(PaddingConfig2d::Explicit(0, 0))
+                    .init(device)
+            })
+            .collect();
+
+        UNetPlusPlus {
+            nodes,
+            pool: MaxPool2dConfig::new([2, 2]).with_strides([2, 2]).init(),
+            outputs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn attention_gate_per_skip_connection() {
+        let device = Default::default();
+        let config = UNetConfig::new(3, 2).with_features(vec![4, 8, 16]);
+
+        let unet = config.init::<TestBackend>(&device);
+        assert!(!unet.has_attention());
+        assert!(unet.gates.is_empty());
+
+        let unet = config.with_attention(true).init::<TestBackend>(&device);
+        assert!(unet.has_attention());
+        assert_eq!(unet.gates.len(), 2);
+    }
+
+    #[test]
+    fn attention_output_shape() {
+        let device = Default::default();
+        let unet = UNetConfig::new(3, 2)
+            .with_features(vec![4, 8, 16])
+            .with_attention(true)
+            .init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 16, 24], Distribution::Default, &device);
+
+        assert_eq!(unet.forward(x).dims(), [2, 2, 16, 24]);
+    }
+
+    #[test]
+    fn nested_output_shape() {
+        let device = Default::default();
+        let unet = UNetPlusPlusConfig::new(3, 2, 4, 5, false).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 32, 48], Distribution::Default, &device);
+
+        let outputs = unet.forward(x.clone());
+
+        assert!(!unet.has_deep_supervision());
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].dims(), [2, 2, 32, 48]);
+        unet.predict(x)
+            .into_data()
+            .assert_eq(&outputs[0].clone().into_data(), true);
+    }
+
+    #[test]
+    fn nested_deep_supervision() {
+        let device = Default::default();
+        let unet = UNetPlusPlusConfig::new(3, 2, 4, 5, true).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 32, 48], Distribution::Default, &device);
+
+        let outputs = unet.forward(x.clone());
+
+        assert!(unet.has_deep_supervision());
+        assert_eq!(outputs.len(), 4);
+        for output in outputs.iter() {
+            assert_eq!(output.dims(), [2, 2, 32, 48]);
+        }
+        let mean = outputs
+            .into_iter()
+            .reduce(|sum, x| sum + x)
+            .unwrap()
+            .div_scalar(4.);
+        unet.predict(x)
+            .into_data()
+            .assert_approx_eq(&mean.into_data(), 5);
+    }
+
+    #[test]
+    fn nested_more_params_than_unet() {
+        let device = Default::default();
+        let config = UNetPlusPlusConfig::new(3, 2, 4, 5, false);
+        let nested = config.init::<TestBackend>(&device);
+        let unet = UNetConfig::new(3, 2)
+            .with_features(config.features())
+            .init::<TestBackend>(&device);
+
+        assert_eq!(config.features(), [4, 8, 16, 32, 64]);
+        assert!(nested.num_params() > unet.num_params());
+    }
+}
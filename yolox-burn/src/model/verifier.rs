@@ -0,0 +1,368 @@
+//! Compatibility checks between a record (e.g., a loaded checkpoint) and a model configuration.
+use core::fmt;
+use std::collections::BTreeMap;
+
+use burn::{
+    module::Module,
+    record::{
+        serde::{data::NestedValue, ser::Serializer},
+        FullPrecisionSettings, Record,
+    },
+    tensor::{backend::Backend, Device},
+};
+use serde::Serialize;
+
+use super::{
+    efficientnet_v2::{EfficientNetV2, EfficientNetV2Config},
+    picodet::{PicoDet, PicoDetConfig},
+    yolox::{Yolox, YoloxConfig},
+};
+
+/// Configurations which initialize a [module](Module), whose record can be
+/// [verified](ModelVerifier::verify).
+pub trait ModuleConfig<B: Backend> {
+    /// Type of the initialized module.
+    type Module: Module<B>;
+
+    /// Initialize a new module.
+    fn init_module(&self, device: &Device<B>) -> Self::Module;
+}
+
+impl<B: Backend> ModuleConfig<B> for YoloxConfig {
+    type Module = Yolox<B>;
+
+    fn init_module(&self, device: &Device<B>) -> Self::Module {
+        self.init(device)
+    }
+}
+
+impl<B: Backend> ModuleConfig<B> for PicoDetConfig {
+    type Module = PicoDet<B>;
+
+    fn init_module(&self, device: &Device<B>) -> Self::Module {
+        self.init(device)
+    }
+}
+
+impl<B: Backend> ModuleConfig<B> for EfficientNetV2Config {
+    type Module = EfficientNetV2<B>;
+
+    fn init_module(&self, device: &Device<B>) -> Self::Module {
+        self.init(device)
+    }
+}
+
+/// Error type for [record verification](ModelVerifier).
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// A tensor of the model is missing from the record.
+    MissingKey(String),
+    /// A tensor of the record does not have the shape expected by the model.
+    ShapeMismatch {
+        key: String,
+        expected: Vec<usize>,
+        got: Vec<usize>,
+    },
+    /// A tensor of the record cannot be converted to the data type of the model (e.g., an integer
+    /// tensor for a float parameter).
+    DTypeMismatch {
+        key: String,
+        expected: String,
+        got: String,
+    },
+    /// The record contains tensors that do not belong to the model (strict mode only).
+    ExtraKeys(Vec<String>),
+    /// The record could not be serialized to list its tensors.
+    Record(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKey(key) => write!(f, "Missing key '{key}' in record"),
+            Self::ShapeMismatch { key, expected, got } => write!(
+                f,
+                "Shape mismatch for key '{key}' (expected {expected:?}, got {got:?})"
+            ),
+            Self::DTypeMismatch { key, expected, got } => write!(
+                f,
+                "Incompatible data type for key '{key}' (expected {expected}, got {got})"
+            ),
+            Self::ExtraKeys(keys) => write!(f, "Unexpected keys in record: {}", keys.join(", ")),
+            Self::Record(reason) => write!(f, "Failed to read record: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Shape and data type of a tensor of a record.
+#[derive(Debug, Clone, PartialEq)]
+struct TensorSpec {
+    shape: Vec<usize>,
+    dtype: String,
+}
+
+impl TensorSpec {
+    /// Whether the tensor data can be loaded into a tensor with the `other` data type.
+    ///
+    /// Floating point tensors are converted to the precision of the model when loaded, so only
+    /// the kind of the data type (float, integer or boolean) has to match.
+    fn is_dtype_compatible(&self, other: &Self) -> bool {
+        fn kind(dtype: &str) -> char {
+            match dtype {
+                "BF16" => 'F',
+                "Bool" => 'B',
+                // F64, F32, F16, I64, ..., U8
+                _ => dtype.chars().next().unwrap_or_default(),
+            }
+        }
+
+        let (expected, got) = (kind(&other.dtype), kind(&self.dtype));
+        expected == got || (matches!(expected, 'I' | 'U') && matches!(got, 'I' | 'U'))
+    }
+}
+
+/// Verify that a record can be loaded into a model, before initializing the model with it.
+///
+/// Loading a checkpoint of a different model variant usually fails with a panic deep inside the
+/// model initialization. The verifier instead initializes the model described by the config,
+/// and compares the keys, shapes and data types of its tensors with the ones of the record.
+pub struct ModelVerifier;
+
+impl ModelVerifier {
+    /// Verify that all the tensors expected by the model are present in the record, with the
+    /// expected shapes and compatible data types. Extra keys in the record are ignored.
+    pub fn verify<B: Backend, R: Record<B> + Clone>(
+        config: &impl ModuleConfig<B>,
+        record: &R,
+    ) -> Result<(), VerifyError> {
+        Self::verify_with(config, record, false)
+    }
+
+    /// Same as [verify](ModelVerifier::verify), but also reject the records with tensors that do
+    /// not belong to the model.
+    pub fn verify_strict<B: Backend, R: Record<B> + Clone>(
+        config: &impl ModuleConfig<B>,
+        record: &R,
+    ) -> Result<(), VerifyError> {
+        Self::verify_with(config, record, true)
+    }
+
+    fn verify_with<B: Backend, R: Record<B> + Clone>(
+        config: &impl ModuleConfig<B>,
+        record: &R,
+        strict: bool,
+    ) -> Result<(), VerifyError> {
+        let device = Default::default();
+        let expected = tensor_specs::<B, _>(config.init_module(&device).into_record())?;
+        let mut actual = tensor_specs::<B, _>(record.clone())?;
+
+        for (key, expected) in expected.iter() {
+            let got = actual
+                .remove(key)
+                .ok_or_else(|| VerifyError::MissingKey(key.clone()))?;
+
+            if got.shape != expected.shape {
+                return Err(VerifyError::ShapeMismatch {
+                    key: key.clone(),
+                    expected: expected.shape.clone(),
+                    got: got.shape,
+                });
+            }
+
+            if !got.is_dtype_compatible(expected) {
+                return Err(VerifyError::DTypeMismatch {
+                    key: key.clone(),
+                    expected: expected.dtype.clone(),
+                    got: got.dtype,
+                });
+            }
+        }
+
+        // The remaining tensors are not used by the model
+        if strict && !actual.is_empty() {
+            return Err(VerifyError::ExtraKeys(actual.into_keys().collect()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Collect the tensors of a record, indexed by their path (e.g., `backbone.stem.conv.weight`).
+fn tensor_specs<B: Backend, R: Record<B>>(
+    record: R,
+) -> Result<BTreeMap<String, TensorSpec>, VerifyError> {
+    let value = record
+        .into_item::<FullPrecisionSettings>()
+        .serialize(Serializer::new())
+        .map_err(|err| VerifyError::Record(err.to_string()))?;
+
+    let mut specs = BTreeMap::new();
+    collect_tensor_specs(&value, "", &mut specs);
+
+    Ok(specs)
+}
+
+fn collect_tensor_specs(value: &NestedValue, path: &str, specs: &mut BTreeMap<String, TensorSpec>) {
+    let join = |name: &str| {
+        if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{path}.{name}")
+        }
+    };
+
+    match value {
+        NestedValue::Map(map) => {
+            // Tensor data
+            if let (Some(shape), Some(NestedValue::String(dtype))) =
+                (map.get("shape"), map.get("dtype"))
+            {
+                if let Some(shape) = to_shape(shape) {
+                    specs.insert(
+                        path.to_string(),
+                        TensorSpec {
+                            shape,
+                            dtype: dtype.clone(),
+                        },
+                    );
+                    return;
+                }
+            }
+
+            // Parameters are serialized with their ID, which is not part of the path
+            if let (Some(param), true) = (map.get("param"), map.contains_key("id")) {
+                return collect_tensor_specs(param, path, specs);
+            }
+
+            for (name, value) in map.iter() {
+                collect_tensor_specs(value, &join(name), specs);
+            }
+        }
+        NestedValue::Vec(values) => {
+            for (i, value) in values.iter().enumerate() {
+                collect_tensor_specs(value, &join(&i.to_string()), specs);
+            }
+        }
+        // Constants (e.g., activation modules or hyper-parameters)
+        _ => {}
+    }
+}
+
+fn to_shape(value: &NestedValue) -> Option<Vec<usize>> {
+    match value {
+        NestedValue::Vec(dims) => dims
+            .iter()
+            .map(|dim| match dim {
+                NestedValue::U64(d) => Some(*d as usize),
+                NestedValue::I64(d) => Some(*d as usize),
+                NestedValue::U16(d) => Some(*d as usize),
+                NestedValue::I32(d) => Some(*d as usize),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        nn::{Linear, LinearConfig},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    impl<B: Backend> ModuleConfig<B> for LinearConfig {
+        type Module = Linear<B>;
+
+        fn init_module(&self, device: &Device<B>) -> Self::Module {
+            self.init(device)
+        }
+    }
+
+    fn linear_record(config: LinearConfig) -> <Linear<TestBackend> as Module<TestBackend>>::Record {
+        config
+            .init::<TestBackend>(&Default::default())
+            .into_record()
+    }
+
+    #[test]
+    fn verify_compatible_record() {
+        let config = YoloxConfig::yolox_nano();
+        let record = config
+            .init::<TestBackend>(&Default::default())
+            .into_record();
+
+        assert_eq!(
+            ModelVerifier::verify::<TestBackend, _>(&config, &record),
+            Ok(())
+        );
+        assert_eq!(
+            ModelVerifier::verify_strict::<TestBackend, _>(&config, &record),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn verify_detects_shape_mismatch() {
+        let config = YoloxConfig::yolox_nano();
+        // Checkpoint of a model fine-tuned on 3 classes
+        let record = YoloxConfig::yolox_nano()
+            .with_num_classes(3)
+            .init::<TestBackend>(&Default::default())
+            .into_record();
+
+        match ModelVerifier::verify::<TestBackend, _>(&config, &record) {
+            Err(VerifyError::ShapeMismatch { key, expected, got }) => {
+                assert!(key.starts_with("head.cls_preds."), "{key}");
+                assert_eq!(expected[0], 80);
+                assert_eq!(got[0], 3);
+            }
+            result => panic!("expected a shape mismatch, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn verify_linear_shape_mismatch() {
+        let record = linear_record(LinearConfig::new(4, 3));
+
+        assert_eq!(
+            ModelVerifier::verify::<TestBackend, _>(&LinearConfig::new(4, 2), &record),
+            Err(VerifyError::ShapeMismatch {
+                key: "bias".into(),
+                expected: vec![2],
+                got: vec![3],
+            })
+        );
+    }
+
+    #[test]
+    fn verify_missing_key() {
+        let record = linear_record(LinearConfig::new(4, 2).with_bias(false));
+
+        assert_eq!(
+            ModelVerifier::verify::<TestBackend, _>(&LinearConfig::new(4, 2), &record),
+            Err(VerifyError::MissingKey("bias".into()))
+        );
+    }
+
+    #[test]
+    fn verify_extra_keys() {
+        let config = LinearConfig::new(4, 2).with_bias(false);
+        let record = linear_record(LinearConfig::new(4, 2));
+
+        // Extra keys are only rejected in strict mode
+        assert_eq!(
+            ModelVerifier::verify::<TestBackend, _>(&config, &record),
+            Ok(())
+        );
+        assert_eq!(
+            ModelVerifier::verify_strict::<TestBackend, _>(&config, &record),
+            Err(VerifyError::ExtraKeys(vec!["bias".into()]))
+        );
+    }
+}
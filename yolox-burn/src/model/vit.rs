@@ -0,0 +1,246 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{
+        transformer::{TransformerEncoder, TransformerEncoderConfig, TransformerEncoderInput},
+        LayerNorm, LayerNormConfig,
+    },
+    tensor::{backend::Backend, Device, Distribution, Tensor},
+};
+
+use super::blocks::{PatchEmbedding, PatchEmbeddingConfig};
+
+/// [Vision Transformer](https://arxiv.org/abs/2010.11929) (ViT) backbone.
+#[derive(Module, Debug)]
+pub struct Vit<B: Backend> {
+    patch_embed: PatchEmbedding<B>,
+    cls_token: Param<Tensor<B, 3>>,
+    pos_embed: Param<Tensor<B, 3>>,
+    blocks: Vec<TransformerEncoder<B>>,
+    norm: LayerNorm<B>,
+}
+
+impl<B: Backend> Vit<B> {
+    /// Compute the output tokens, with the class token first.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, 1 + num_patches, embed_dim]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 3> {
+        let x = self.embed_patches(x);
+        let [batch_size, _, _] = x.dims();
+
+        let cls = self.cls_token().repeat_dim(0, batch_size);
+        self.encode(Tensor::cat(vec![cls, x], 1))
+    }
+
+    /// Embed the image patches and add the position embeddings.
+    ///
+    /// # Shapes
+    ///   - output: `[batch_size, num_patches, embed_dim]`
+    pub(crate) fn embed_patches(&self, x: Tensor<B, 4>) -> Tensor<B, 3> {
+        let x = self.patch_embed.forward(x);
+        let [_, num_patches, embed_dim] = x.dims();
+
+        x + self
+            .pos_embed
+            .val()
+            .slice([0..1, 1..num_patches + 1, 0..embed_dim])
+    }
+
+    /// Class token with its position embedding. Shape: `[1, 1, embed_dim]`.
+    pub(crate) fn cls_token(&self) -> Tensor<B, 3> {
+        let [_, _, embed_dim] = self.cls_token.dims();
+        self.cls_token.val() + self.pos_embed.val().slice([0..1, 0..1, 0..embed_dim])
+    }
+
+    /// Apply the transformer encoder to the (embedded) tokens.
+    pub(crate) fn encode(&self, tokens: Tensor<B, 3>) -> Tensor<B, 3> {
+        let x = self.blocks.iter().fold(tokens, |x, block| {
+            block.forward(TransformerEncoderInput::new(x))
+        });
+        self.norm.forward(x)
+    }
+
+    /// Compute the normalized patch tokens at the output of the given transformer blocks (e.g.,
+    /// for dense prediction heads), without the class token.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input images.
+    /// * `hooks` - Increasing indices of the transformer blocks (0-based).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, num_patches, embed_dim]` for each hook
+    pub fn forward_hooks(&self, x: Tensor<B, 4>, hooks: &[usize]) -> Vec<Tensor<B, 3>> {
+        assert!(
+            hooks.iter().all(|&i| i < self.blocks.len()),
+            "invalid transformer block index"
+        );
+        let x = self.embed_patches(x);
+        let [batch_size, num_patches, embed_dim] = x.dims();
+
+        let cls = self.cls_token().repeat_dim(0, batch_size);
+        let mut x = Tensor::cat(vec![cls, x], 1);
+        let mut outputs = Vec::with_capacity(hooks.len());
+        for (i, block) in self.blocks.iter().enumerate() {
+            x = block.forward(TransformerEncoderInput::new(x));
+            if hooks.contains(&i) {
+                let patches = x
+                    .clone()
+                    .slice([0..batch_size, 1..num_patches + 1, 0..embed_dim]);
+                outputs.push(self.norm.forward(patches));
+            }
+        }
+
+        outputs
+    }
+
+    /// Number of transformer blocks.
+    pub fn depth(&self) -> usize {
+        self.blocks.len()
+    }
+}
+
+/// Size and patch size of the [Vision Transformer](Vit).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VitVariant {
+    /// ViT-S/14.
+    S14,
+    /// ViT-B/14.
+    B14,
+    /// ViT-B/32.
+    B32,
+    /// ViT-B/16.
+    #[default]
+    B16,
+    /// ViT-L/14.
+    L14,
+}
+
+impl VitVariant {
+    /// Patch size, embedding dimension, depth and number of attention heads.
+    fn dims(&self) -> (usize, usize, usize, usize) {
+        match self {
+            Self::S14 => (14, 384, 12, 6),
+            Self::B14 => (14, 768, 12, 12),
+            Self::B32 => (32, 768, 12, 12),
+            Self::B16 => (16, 768, 12, 12),
+            Self::L14 => (14, 1024, 24, 16),
+        }
+    }
+}
+
+/// [Vision Transformer](Vit) configuration.
+pub struct VitConfig {
+    patch_embed: PatchEmbeddingConfig,
+    encoder: TransformerEncoderConfig,
+    norm: LayerNormConfig,
+    patch_size: usize,
+    in_channels: usize,
+    embed_dim: usize,
+    depth: usize,
+}
+
+impl VitConfig {
+    /// Create a new instance of the ViT [config](VitConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `image_size`: Input image size (square).
+    /// * `patch_size` - Patch size (square).
+    /// * `in_channels` - Number of input image channels.
+    /// * `embed_dim` - Embedding dimension.
+    /// * `depth` - Number of transformer blocks.
+    /// * `num_heads` - Number of attention heads.
+    /// * `mlp_ratio` - Ratio of the MLP hidden dimension to the embedding dimension.
+    pub fn new(
+        image_size: usize,
+        patch_size: usize,
+        in_channels: usize,
+        embed_dim: usize,
+        depth: usize,
+        num_heads: usize,
+        mlp_ratio: f64,
+    ) -> Self {
+        let patch_embed = PatchEmbeddingConfig::new(
+            (image_size, image_size),
+            (patch_size, patch_size),
+            in_channels,
+            embed_dim,
+            false,
+        );
+        let d_ff = (embed_dim as f64 * mlp_ratio) as usize;
+        // Single block encoders, so that the intermediate tokens can be retrieved
+        let encoder = TransformerEncoderConfig::new(embed_dim, d_ff, num_heads, 1)
+            .with_dropout(0.)
+            .with_norm_first(true);
+        let norm = LayerNormConfig::new(embed_dim).with_epsilon(1e-6);
+
+        Self {
+            patch_embed,
+            encoder,
+            norm,
+            patch_size,
+            in_channels,
+            embed_dim,
+            depth,
+        }
+    }
+
+    /// ViT-B/16 for 224x224 images.
+    pub fn vit_base(image_size: usize) -> Self {
+        Self::new(image_size, 16, 3, 768, 12, 12, 4.)
+    }
+
+    /// ViT variant for RGB images of the given size.
+    pub fn from_variant(variant: VitVariant, image_size: usize) -> Self {
+        let (patch_size, embed_dim, depth, num_heads) = variant.dims();
+        Self::new(image_size, patch_size, 3, embed_dim, depth, num_heads, 4.)
+    }
+
+    /// Number of image patches.
+    pub fn num_patches(&self) -> usize {
+        self.patch_embed.num_patches()
+    }
+
+    /// Patch size.
+    pub fn patch_size(&self) -> usize {
+        self.patch_size
+    }
+
+    /// Number of input image channels.
+    pub fn in_channels(&self) -> usize {
+        self.in_channels
+    }
+
+    /// Embedding dimension.
+    pub fn embed_dim(&self) -> usize {
+        self.embed_dim
+    }
+
+    /// Number of transformer blocks.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Initialize a new [Vision Transformer](Vit) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Vit<B> {
+        let init = |shape: [usize; 3]| {
+            Param::from_tensor(Tensor::random(
+                shape,
+                Distribution::Normal(0., 0.02),
+                device,
+            ))
+        };
+
+        Vit {
+            patch_embed: self.patch_embed.init(device),
+            cls_token: init([1, 1, self.embed_dim]),
+            pos_embed: init([1, self.num_patches() + 1, self.embed_dim]),
+            blocks: (0..self.depth).map(|_| self.encoder.init(device)).collect(),
+            norm: self.norm.init(device),
+        }
+    }
+}
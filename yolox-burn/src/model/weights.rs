@@ -1,50 +1,118 @@
+#[cfg(feature = "pretrained")]
+pub mod pytorch;
+
 /// Pre-trained weights metadata.
+#[derive(Debug, Clone, Copy)]
 pub struct Weights {
     pub(super) url: &'static str,
     pub(super) num_classes: usize,
+    /// Expected SHA256 checksum of the file, verified on download. Files without a checksum are
+    /// rejected.
+    pub(super) sha256: Option<&'static str>,
 }
 
 #[cfg(feature = "pretrained")]
 mod downloader {
     use super::*;
+    use crate::model::registry::DownloadError;
     use burn::data::network::downloader;
-    use std::fs::{create_dir_all, File};
+    use sha2::{Digest, Sha256};
+    use std::fs::{self, create_dir_all, File};
     use std::io::Write;
-    use std::path::PathBuf;
+    use std::panic::{self, UnwindSafe};
+    use std::path::{Path, PathBuf};
 
     impl Weights {
-        /// Download the pre-trained weights to the local cache directory.
-        pub fn download(&self) -> Result<PathBuf, std::io::Error> {
+        /// Download the pre-trained weights to the local cache directory
+        /// (`~/.cache/burn-models`).
+        pub fn download(&self) -> Result<PathBuf, DownloadError> {
             // Model cache directory
             let model_dir = dirs::home_dir()
                 .expect("Should be able to get home directory")
                 .join(".cache")
-                .join("yolox-burn");
+                .join("burn-models");
+
+            self.download_to(&model_dir)
+        }
+
+        /// Download the pre-trained weights to the specified directory (if not already cached)
+        /// and return the path to the cached file.
+        ///
+        /// Local files are supported with the `file://` scheme.
+        pub fn download_to(&self, model_dir: &Path) -> Result<PathBuf, DownloadError> {
+            self.download_with(model_dir, downloader::download_file_as_bytes)
+        }
 
+        /// Same as [download_to](Self::download_to), with the function fetching the content of
+        /// a remote `url` (displayed as `file_name` in the progress bar), which panics on
+        /// failure.
+        pub(super) fn download_with<F>(
+            &self,
+            model_dir: &Path,
+            fetch: F,
+        ) -> Result<PathBuf, DownloadError>
+        where
+            F: FnOnce(&str, &str) -> Vec<u8> + UnwindSafe,
+        {
             if !model_dir.exists() {
-                create_dir_all(&model_dir)?;
+                create_dir_all(model_dir)?;
             }
 
             let file_base_name = self.url.rsplit_once('/').unwrap().1;
             let file_name = model_dir.join(file_base_name);
-            if !file_name.exists() {
-                // Download file content
-                let bytes = downloader::download_file_as_bytes(self.url, file_base_name);
-
-                // Write content to file
-                let mut output_file = File::create(&file_name)?;
-                let bytes_written = output_file.write(&bytes)?;
-
-                if bytes_written != bytes.len() {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Failed to write the whole model weights file.",
-                    ));
+            if file_name.exists() {
+                self.verify(&fs::read(&file_name)?)?;
+                return Ok(file_name);
+            }
+
+            // Download file content
+            let bytes = match self.url.strip_prefix("file://") {
+                Some(path) => fs::read(path)?,
+                None => {
+                    // The downloader panics on network failures
+                    panic::catch_unwind(move || fetch(self.url, file_base_name)).map_err(|err| {
+                        let reason = err
+                            .downcast_ref::<&str>()
+                            .map(|s| s.to_string())
+                            .or_else(|| err.downcast_ref::<String>().cloned())
+                            .unwrap_or_else(|| "unknown error".to_string());
+                        DownloadError::Network(format!("{} ({reason})", self.url))
+                    })?
                 }
+            };
+            if bytes.is_empty() {
+                return Err(DownloadError::Network(format!(
+                    "{} (empty response)",
+                    self.url
+                )));
             }
+            self.verify(&bytes)?;
+
+            // Write content to file
+            let mut output_file = File::create(&file_name)?;
+            output_file.write_all(&bytes)?;
 
             Ok(file_name)
         }
+
+        /// Verify the file content against the expected checksum, which is required.
+        fn verify(&self, bytes: &[u8]) -> Result<(), DownloadError> {
+            let got: String = Sha256::digest(bytes)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            match self.sha256 {
+                Some(expected) if got == expected => {}
+                expected => {
+                    return Err(DownloadError::ChecksumMismatch {
+                        expected: expected.unwrap_or("<none>").to_string(),
+                        got,
+                    })
+                }
+            }
+
+            Ok(())
+        }
     }
 }
 
@@ -63,6 +131,7 @@ impl WeightsMeta for YoloxNano {
         Weights {
             url: "https://github.com/Megvii-BaseDetection/YOLOX/releases/download/0.1.1rc0/yolox_nano.pth",
             num_classes: 80,
+            sha256: None,
         }
     }
 }
@@ -78,6 +147,7 @@ impl WeightsMeta for YoloxTiny {
         Weights {
             url: "https://github.com/Megvii-BaseDetection/YOLOX/releases/download/0.1.1rc0/yolox_tiny.pth",
             num_classes: 80,
+            sha256: None,
         }
     }
 }
@@ -93,6 +163,7 @@ impl WeightsMeta for YoloxS {
         Weights {
             url: "https://github.com/Megvii-BaseDetection/YOLOX/releases/download/0.1.1rc0/yolox_s.pth",
             num_classes: 80,
+            sha256: None,
         }
     }
 }
@@ -108,6 +179,7 @@ impl WeightsMeta for YoloxM {
         Weights {
             url: "https://github.com/Megvii-BaseDetection/YOLOX/releases/download/0.1.1rc0/yolox_m.pth",
             num_classes: 80,
+            sha256: None,
         }
     }
 }
@@ -123,6 +195,7 @@ impl WeightsMeta for YoloxL {
         Weights {
             url: "https://github.com/Megvii-BaseDetection/YOLOX/releases/download/0.1.1rc0/yolox_l.pth",
             num_classes: 80,
+            sha256: None,
         }
     }
 }
@@ -138,6 +211,128 @@ impl WeightsMeta for YoloxX {
         Weights {
             url: "https://github.com/Megvii-BaseDetection/YOLOX/releases/download/0.1.1rc0/yolox_x.pth",
             num_classes: 80,
+            sha256: None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pretrained"))]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::model::registry::DownloadError;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yolox-burn-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Hexadecimal SHA256 checksum of the content.
+    fn sha256(bytes: &[u8]) -> &'static str {
+        let digest: String = Sha256::digest(bytes)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Box::leak(digest.into_boxed_str())
+    }
+
+    /// Weights downloaded from a local file, with an optional checksum.
+    fn local_weights(path: &std::path::Path, sha256: Option<&'static str>) -> Weights {
+        Weights {
+            url: Box::leak(format!("file://{}", path.display()).into_boxed_str()),
+            num_classes: 80,
+            sha256,
         }
     }
+
+    #[test]
+    fn download_verifies_checksum() {
+        let dir = temp_dir("download");
+        let source = dir.join("weights.pth");
+        fs::write(&source, b"checkpoint content").unwrap();
+
+        let weights = local_weights(&source, Some(sha256(b"checkpoint content")));
+        let cached = weights.download_to(&dir.join("cache")).unwrap();
+        assert_eq!(cached, dir.join("cache").join("weights.pth"));
+        assert_eq!(fs::read(&cached).unwrap(), b"checkpoint content");
+
+        let weights = local_weights(&source, Some("0000"));
+        let result = weights.download_to(&dir.join("other"));
+        assert!(matches!(
+            result,
+            Err(DownloadError::ChecksumMismatch { .. })
+        ));
+        assert!(!dir.join("other").join("weights.pth").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn download_requires_checksum() {
+        let dir = temp_dir("no-checksum");
+        let source = dir.join("weights.pth");
+        fs::write(&source, b"checkpoint content").unwrap();
+
+        let weights = local_weights(&source, None);
+        let result = weights.download_to(&dir.join("cache"));
+        assert!(matches!(
+            result,
+            Err(DownloadError::ChecksumMismatch { expected, .. }) if expected == "<none>"
+        ));
+        assert!(!dir.join("cache").join("weights.pth").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn download_uses_cached_file() {
+        let dir = temp_dir("cached");
+        fs::write(dir.join("weights.pth"), b"cached").unwrap();
+
+        // The source does not exist, so only the cached file can be returned
+        let weights = local_weights(
+            &dir.join("missing").join("weights.pth"),
+            Some(sha256(b"cached")),
+        );
+        let cached = weights.download_to(&dir).unwrap();
+        assert_eq!(fs::read(cached).unwrap(), b"cached");
+
+        let result = weights.download_to(&dir.join("empty"));
+        assert!(matches!(result, Err(DownloadError::Io(_))));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn download_network_error() {
+        let dir = temp_dir("network");
+        let weights = Weights {
+            url: "https://example.com/yolox_s.pth",
+            num_classes: 80,
+            sha256: Some("0000"),
+        };
+
+        // Downloader failing as on a refused connection
+        let result = weights.download_with(&dir, |_url, _file_name| {
+            panic!("connection refused");
+        });
+        assert!(matches!(
+            result,
+            Err(DownloadError::Network(reason)) if reason.contains("connection refused")
+        ));
+
+        let result = weights.download_with(&dir, |_url, _file_name| Vec::new());
+        assert!(matches!(
+            result,
+            Err(DownloadError::Network(reason)) if reason.contains("empty response")
+        ));
+        assert!(!dir.join("yolox_s.pth").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
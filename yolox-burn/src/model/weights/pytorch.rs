@@ -0,0 +1,267 @@
+//! Raw access to the official YOLOX PyTorch `.pth` checkpoints.
+use core::fmt;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use burn::tensor::{backend::Backend, Device, Tensor, TensorData};
+use burn_import::pytorch::LoadArgs;
+use candle_core::{pickle, DType};
+use regex::Regex;
+
+/// Error type for PyTorch checkpoint loading.
+#[derive(Debug)]
+pub enum PytorchLoadError {
+    /// Failed to read or unpickle the checkpoint file.
+    Pickle(String),
+    /// A tensor could not be converted.
+    Tensor { name: String, reason: String },
+}
+
+impl fmt::Display for PytorchLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pickle(reason) => write!(f, "Failed to read checkpoint: {reason}"),
+            Self::Tensor { name, reason } => {
+                write!(f, "Failed to convert tensor '{name}': {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PytorchLoadError {}
+
+/// Translate the parameter names of a source checkpoint to the burn record paths.
+pub trait NameMapper {
+    /// Target record path for the source parameter name, or `None` to skip the parameter.
+    fn map(&self, name: &str) -> Option<String>;
+}
+
+/// Remapping `(pattern, replacement)` of the official YOLOX PyTorch parameter names to the
+/// [YOLOX](crate::model::yolox::Yolox) record paths, applied in order.
+pub(crate) const YOLOX_KEY_REMAP: [(&str, &str); 7] = [
+    // Map backbone.C3_* -> backbone.neck.c3_*
+    ("backbone\\.C3_(.+)", "backbone.neck.c3_$1"),
+    // Map backbone.{lateral_conv0 | reduce_conv1 | bu_conv[i]}.* -> backbone.neck.*
+    (
+        "backbone\\.(lateral_conv0|reduce_conv1|bu_conv[12])\\.(.+)",
+        "backbone.neck.$1.$2",
+    ),
+    // Map backbone.backbone.dark[i].0.* -> backbone.backbone.dark[i].conv.*
+    ("(backbone\\.backbone\\.dark[2-5])\\.0\\.(.+)", "$1.conv.$2"),
+    // Map backbone.backbone.dark[i].1.* -> backbone.backbone.dark[i].c3.*
+    ("(backbone\\.backbone\\.dark[2-4])\\.1\\.(.+)", "$1.c3.$2"),
+    // Map backbone.backbone.dark5.1.* -> backbone.backbone.dark5.spp.*
+    ("(backbone\\.backbone\\.dark5)\\.1\\.(.+)", "$1.spp.$2"),
+    // Map backbone.backbone.dark5.2.* -> backbone.backbone.dark5.c3.*
+    ("(backbone\\.backbone\\.dark5)\\.2\\.(.+)", "$1.c3.$2"),
+    // Map head.{cls | reg}_convs.x.[i].* -> head.{cls | reg}_convs.x.conv[i].*
+    (
+        "(head\\.(cls|reg)_convs\\.[0-9]+)\\.([0-9]+)\\.(.+)",
+        "$1.conv$3.$4",
+    ),
+];
+
+/// [PyTorch recorder](burn_import::pytorch::PyTorchFileRecorder) arguments to load an official
+/// YOLOX checkpoint into a [YOLOX](crate::model::yolox::Yolox) record, with the same
+/// [remapping](PyTorchToYoloxMapping) of the parameter names.
+pub(crate) fn yolox_load_args(checkpoint: PathBuf) -> LoadArgs {
+    YOLOX_KEY_REMAP.iter().fold(
+        // State dict contains "model", "amp", "optimizer", "start_epoch"
+        LoadArgs::new(checkpoint).with_top_level_key("model"),
+        |args, (pattern, replacement)| args.with_key_remap(pattern, replacement),
+    )
+}
+
+/// Map the official YOLOX PyTorch parameter names to the [YOLOX](crate::model::yolox::Yolox)
+/// record paths (e.g., `backbone.backbone.dark3.0.bn.weight` ->
+/// `backbone.backbone.dark3.conv.bn.weight`).
+///
+/// The names are remapped as when [loading the pre-trained weights](crate::model::yolox::Yolox).
+#[derive(Debug, Clone)]
+pub struct PyTorchToYoloxMapping {
+    rules: Vec<(Regex, &'static str)>,
+}
+
+impl Default for PyTorchToYoloxMapping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PyTorchToYoloxMapping {
+    /// Create a new YOLOX name mapper.
+    pub fn new() -> Self {
+        let rules = YOLOX_KEY_REMAP
+            .iter()
+            .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), *replacement))
+            .collect();
+
+        Self { rules }
+    }
+}
+
+impl NameMapper for PyTorchToYoloxMapping {
+    fn map(&self, name: &str) -> Option<String> {
+        let name = self
+            .rules
+            .iter()
+            .fold(name.to_string(), |name, (pattern, replacement)| {
+                pattern.replace_all(&name, *replacement).into_owned()
+            });
+
+        Some(name)
+    }
+}
+
+/// Load the tensors of rank `D` of a PyTorch `.pth` checkpoint.
+///
+/// The official YOLOX checkpoints store the state dict under the `"model"` key (along with the
+/// optimizer state), otherwise the file is expected to contain the state dict directly.
+///
+/// Parameters have different ranks (e.g., 4 for the convolution weights and 1 for the biases and
+/// batch norms), so only the tensors of rank `D` are returned and the others are skipped. The
+/// values are converted to the floating point element type of the backend.
+///
+/// # Arguments
+///
+/// * `path`: Path to the checkpoint file.
+/// * `device` - Device to create the tensors on.
+pub fn load_pytorch_checkpoint<B: Backend, const D: usize>(
+    path: &Path,
+    device: &Device<B>,
+) -> Result<HashMap<String, Tensor<B, D>>, PytorchLoadError> {
+    let tensors = pickle::read_all_with_key(path, Some("model"))
+        .or_else(|_| pickle::read_all(path))
+        .map_err(|err| PytorchLoadError::Pickle(err.to_string()))?;
+
+    tensors
+        .into_iter()
+        .filter(|(_, tensor)| tensor.rank() == D)
+        .map(|(name, tensor)| {
+            let shape = tensor.dims().to_vec();
+            let values = tensor
+                .to_dtype(DType::F32)
+                .and_then(|t| t.flatten_all())
+                .and_then(|t| t.to_vec1::<f32>())
+                .map_err(|err| PytorchLoadError::Tensor {
+                    name: name.clone(),
+                    reason: err.to_string(),
+                })?;
+            let data = TensorData::new(values, shape).convert::<B::FloatElem>();
+
+            Ok((name, Tensor::from_data(data, device)))
+        })
+        .collect()
+}
+
+/// Rename the checkpoint tensors with the name mapper. Tensors without a target are dropped.
+pub fn remap<T, M: NameMapper>(tensors: HashMap<String, T>, mapper: &M) -> HashMap<String, T> {
+    tensors
+        .into_iter()
+        .filter_map(|(name, tensor)| mapper.map(&name).map(|name| (name, tensor)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/conv2d.pth")
+    }
+
+    fn values<const D: usize>(tensor: &Tensor<TestBackend, D>) -> Vec<f32> {
+        tensor.to_data().to_vec().unwrap()
+    }
+
+    #[test]
+    fn load_conv2d_weight() {
+        let device = Default::default();
+        let tensors = load_pytorch_checkpoint::<TestBackend, 4>(&fixture(), &device).unwrap();
+
+        assert_eq!(tensors.len(), 1);
+        let weight = &tensors["conv.weight"];
+        assert_eq!(weight.dims(), [2, 1, 2, 2]);
+        let expected: Vec<f32> = (0..8).map(|i| (0.1 * i as f64) as f32).collect();
+        assert_eq!(values(weight), expected);
+    }
+
+    #[test]
+    fn load_conv2d_bias() {
+        let device = Default::default();
+        let tensors = load_pytorch_checkpoint::<TestBackend, 1>(&fixture(), &device).unwrap();
+
+        assert_eq!(tensors.len(), 1);
+        assert_eq!(values(&tensors["conv.bias"]), [0.5, -0.5]);
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let device = Default::default();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/missing.pth");
+        let result = load_pytorch_checkpoint::<TestBackend, 1>(&path, &device);
+
+        assert!(matches!(result, Err(PytorchLoadError::Pickle(_))));
+    }
+
+    #[test]
+    fn yolox_names() {
+        let mapper = PyTorchToYoloxMapping::new();
+        let cases = [
+            (
+                "backbone.backbone.dark3.0.bn.weight",
+                "backbone.backbone.dark3.conv.bn.weight",
+            ),
+            (
+                "backbone.backbone.dark3.1.m.0.conv1.conv.weight",
+                "backbone.backbone.dark3.c3.m.0.conv1.conv.weight",
+            ),
+            (
+                "backbone.backbone.dark5.1.conv1.conv.weight",
+                "backbone.backbone.dark5.spp.conv1.conv.weight",
+            ),
+            (
+                "backbone.backbone.dark5.2.conv3.bn.bias",
+                "backbone.backbone.dark5.c3.conv3.bn.bias",
+            ),
+            (
+                "backbone.C3_p4.conv1.conv.weight",
+                "backbone.neck.c3_p4.conv1.conv.weight",
+            ),
+            (
+                "backbone.lateral_conv0.bn.running_mean",
+                "backbone.neck.lateral_conv0.bn.running_mean",
+            ),
+            (
+                "head.cls_convs.2.1.conv.weight",
+                "head.cls_convs.2.conv1.conv.weight",
+            ),
+            ("head.stems.0.conv.weight", "head.stems.0.conv.weight"),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(mapper.map(name).as_deref(), Some(expected), "{name}");
+        }
+    }
+
+    #[test]
+    fn remap_checkpoint() {
+        let device = Default::default();
+        let tensors = load_pytorch_checkpoint::<TestBackend, 1>(&fixture(), &device).unwrap();
+
+        struct Prefix;
+        impl NameMapper for Prefix {
+            fn map(&self, name: &str) -> Option<String> {
+                Some(format!("stem.{name}"))
+            }
+        }
+
+        let tensors = remap(tensors, &Prefix);
+        assert_eq!(values(&tensors["stem.conv.bias"]), [0.5, -0.5]);
+    }
+}
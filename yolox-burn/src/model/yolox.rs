@@ -1,27 +1,40 @@
+use alloc::vec::Vec;
 use burn::{
     module::{ConstantRecord, Module},
     tensor::{backend::Backend, Device, Tensor},
 };
 
-use crate::model::bottleneck::SPP_POOLING;
+use crate::{
+    loss::{GroundTruthBoxes, YoloxLoss, YoloxLossOutput},
+    model::bottleneck::SPP_POOLING,
+    types::{ConfThreshold, Detection, IoUThreshold},
+};
 
 use super::{
-    head::{Head, HeadConfig},
+    darknet::{CspDarknetConfig, StemType},
+    head::{DetectionHead, DetectionHeadConfig},
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
     pafpn::{Pafpn, PafpnConfig},
+    postprocess::{nms::batch_nms_detections, yolox::YoloxPostprocessor},
+    weights::{self, WeightsMeta},
+    DetectionModel, DetectionRawOutput, MAX_DETECTIONS,
 };
 
 #[cfg(feature = "pretrained")]
 use {
-    super::weights::{self, WeightsMeta},
+    super::weights::pytorch,
     burn::record::{FullPrecisionSettings, Recorder, RecorderError},
-    burn_import::pytorch::{LoadArgs, PyTorchFileRecorder},
+    burn_import::pytorch::PyTorchFileRecorder,
 };
 
+/// Number of classes of the COCO pre-trained weights.
+const COCO_CLASSES: usize = 80;
+
 /// [YOLOX](https://paperswithcode.com/method/yolox) object detection architecture.
 #[derive(Module, Debug)]
 pub struct Yolox<B: Backend> {
     backbone: Pafpn<B>,
-    head: Head<B>,
+    head: DetectionHead<B>,
 }
 
 impl<B: Backend> Yolox<B> {
@@ -30,6 +43,41 @@ impl<B: Backend> Yolox<B> {
         self.head.forward(features)
     }
 
+    /// Compute the [YOLOX training loss](YoloxLoss) for a batch of images.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input images. Shape: `[batch_size, channels, height, width]`.
+    /// * `targets` - Ground truth boxes of each image.
+    /// * `loss` - Loss and label assignment settings.
+    pub fn forward_training(
+        &self,
+        x: Tensor<B, 4>,
+        targets: &[GroundTruthBoxes<B>],
+        loss: &YoloxLoss,
+    ) -> YoloxLossOutput<B> {
+        let features = self.backbone.forward(x);
+        self.head.forward_training(features, targets, loss)
+    }
+
+    /// Detect objects in a batch of images.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input images. Shape: `[batch_size, channels, height, width]`.
+    /// * `postprocessor` - Score filtering and non-maximum suppression of the outputs.
+    ///
+    /// # Returns
+    ///
+    /// The detections of each image, sorted in decreasing order of scores.
+    pub fn detect(
+        &self,
+        x: Tensor<B, 4>,
+        postprocessor: &YoloxPostprocessor,
+    ) -> Vec<Vec<Detection>> {
+        postprocessor.postprocess(self.forward(x))
+    }
+
     /// YOLOX-Nano from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430).
     ///
     /// # Arguments
@@ -41,7 +89,9 @@ impl<B: Backend> Yolox<B> {
     ///
     /// A YOLOX-Nano module.
     pub fn yolox_nano(num_classes: usize, device: &Device<B>) -> Self {
-        YoloxConfig::new(0.33, 0.25, num_classes, true).init(device)
+        YoloxConfig::yolox_nano()
+            .with_num_classes(num_classes)
+            .init(device)
     }
 
     /// YOLOX-Nano from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430)
@@ -79,7 +129,9 @@ impl<B: Backend> Yolox<B> {
     ///
     /// A YOLOX-Tiny module.
     pub fn yolox_tiny(num_classes: usize, device: &Device<B>) -> Self {
-        YoloxConfig::new(0.33, 0.375, num_classes, false).init(device)
+        YoloxConfig::yolox_tiny()
+            .with_num_classes(num_classes)
+            .init(device)
     }
 
     /// YOLOX-Tiny from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430)
@@ -117,7 +169,9 @@ impl<B: Backend> Yolox<B> {
     ///
     /// A YOLOX-S module.
     pub fn yolox_s(num_classes: usize, device: &Device<B>) -> Self {
-        YoloxConfig::new(0.33, 0.50, num_classes, false).init(device)
+        YoloxConfig::yolox_s()
+            .with_num_classes(num_classes)
+            .init(device)
     }
 
     /// YOLOX-S from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430)
@@ -155,7 +209,9 @@ impl<B: Backend> Yolox<B> {
     ///
     /// A YOLOX-M module.
     pub fn yolox_m(num_classes: usize, device: &Device<B>) -> Self {
-        YoloxConfig::new(0.67, 0.75, num_classes, false).init(device)
+        YoloxConfig::yolox_m()
+            .with_num_classes(num_classes)
+            .init(device)
     }
 
     /// YOLOX-M from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430)
@@ -193,7 +249,9 @@ impl<B: Backend> Yolox<B> {
     ///
     /// A YOLOX-L module.
     pub fn yolox_l(num_classes: usize, device: &Device<B>) -> Self {
-        YoloxConfig::new(1., 1., num_classes, false).init(device)
+        YoloxConfig::yolox_l()
+            .with_num_classes(num_classes)
+            .init(device)
     }
 
     /// YOLOX-L from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430)
@@ -231,7 +289,9 @@ impl<B: Backend> Yolox<B> {
     ///
     /// A YOLOX-X module.
     pub fn yolox_x(num_classes: usize, device: &Device<B>) -> Self {
-        YoloxConfig::new(1.33, 1.25, num_classes, false).init(device)
+        YoloxConfig::yolox_x()
+            .with_num_classes(num_classes)
+            .init(device)
     }
 
     /// YOLOX-X from [`YOLOX: Exceeding YOLO Series in 2021`](https://arxiv.org/abs/2107.08430)
@@ -269,24 +329,7 @@ impl<B: Backend> Yolox<B> {
         })?;
 
         // Load weights from torch state_dict
-        let load_args = LoadArgs::new(torch_weights)
-            // State dict contains "model", "amp", "optimizer", "start_epoch"
-            .with_top_level_key("model")
-            // Map backbone.C3_* -> backbone.c3_*
-            .with_key_remap("backbone\\.C3_(.+)", "backbone.c3_$1")
-            // Map backbone.backbone.dark[i].0.* -> backbone.backbone.dark[i].conv.*
-            .with_key_remap("(backbone\\.backbone\\.dark[2-5])\\.0\\.(.+)", "$1.conv.$2")
-            // Map backbone.backbone.dark[i].1.* -> backbone.backbone.dark[i].c3.*
-            .with_key_remap("(backbone\\.backbone\\.dark[2-4])\\.1\\.(.+)", "$1.c3.$2")
-            // Map backbone.backbone.dark5.1.* -> backbone.backbone.dark5.spp.*
-            .with_key_remap("(backbone\\.backbone\\.dark5)\\.1\\.(.+)", "$1.spp.$2")
-            // Map backbone.backbone.dark5.2.* -> backbone.backbone.dark5.c3.*
-            .with_key_remap("(backbone\\.backbone\\.dark5)\\.2\\.(.+)", "$1.c3.$2")
-            // Map head.{cls | reg}_convs.x.[i].* -> head.{cls | reg}_convs.x.conv[i].*
-            .with_key_remap(
-                "(head\\.(cls|reg)_convs\\.[0-9]+)\\.([0-9]+)\\.(.+)",
-                "$1.conv$3.$4",
-            );
+        let load_args = pytorch::yolox_load_args(torch_weights);
 
         let mut record: YoloxRecord<B> =
             PyTorchFileRecorder::<FullPrecisionSettings>::new().load(load_args, device)?;
@@ -304,19 +347,152 @@ impl<B: Backend> Yolox<B> {
     }
 }
 
+impl<B: Backend> FreezeBatchNorms<B> for Yolox<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            backbone: self.backbone.freeze_batch_norms(),
+            head: self.head.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for Yolox<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            backbone: self.backbone.sync_batch_norms(),
+            head: self.head.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for Yolox<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.backbone.set_bn_momentum(momentum);
+        self.head.set_bn_momentum(momentum);
+    }
+}
+
+impl<B: Backend> DetectionModel<B> for Yolox<B> {
+    fn forward_raw(&self, images: Tensor<B, 4>) -> DetectionRawOutput<B> {
+        DetectionRawOutput::AnchorFree(self.forward(images))
+    }
+
+    fn num_classes(&self) -> usize {
+        self.head.num_classes()
+    }
+
+    fn decode(
+        &self,
+        raw: DetectionRawOutput<B>,
+        conf_threshold: ConfThreshold,
+        nms_iou_threshold: IoUThreshold,
+    ) -> Vec<Vec<Detection>> {
+        match raw {
+            // Class-aware NMS on the device
+            DetectionRawOutput::AnchorFree(outputs) => YoloxPostprocessor::new()
+                .with_conf_threshold(conf_threshold)
+                .with_iou_threshold(nms_iou_threshold)
+                .postprocess(outputs),
+            raw => {
+                let (boxes, scores) = raw.boxes_and_scores();
+                batch_nms_detections(
+                    boxes,
+                    scores,
+                    nms_iou_threshold,
+                    conf_threshold,
+                    MAX_DETECTIONS,
+                )
+            }
+        }
+    }
+}
+
 /// [YOLOX detector](Yolox) configuration.
 pub struct YoloxConfig {
     backbone: PafpnConfig,
-    head: HeadConfig,
+    head: DetectionHeadConfig,
+    weights: Option<weights::Weights>,
 }
 
 impl YoloxConfig {
     /// Create a new instance of the YOLOX detector [config](YoloxConfig).
     pub fn new(depth: f64, width: f64, num_classes: usize, depthwise: bool) -> Self {
         let backbone = PafpnConfig::new(depth, width, depthwise);
-        let head = HeadConfig::new(num_classes, width, depthwise);
+        let head = DetectionHeadConfig::yolox(num_classes, width, depthwise);
+
+        Self {
+            backbone,
+            head,
+            weights: None,
+        }
+    }
+
+    /// YOLOX-Nano configuration for the 80 COCO classes, with the official pre-trained weights.
+    pub fn yolox_nano() -> Self {
+        Self::new(0.33, 0.25, COCO_CLASSES, true).with_weights(weights::YoloxNano::Coco)
+    }
+
+    /// YOLOX-Tiny configuration for the 80 COCO classes, with the official pre-trained weights.
+    pub fn yolox_tiny() -> Self {
+        Self::new(0.33, 0.375, COCO_CLASSES, false).with_weights(weights::YoloxTiny::Coco)
+    }
+
+    /// YOLOX-S configuration for the 80 COCO classes, with the official pre-trained weights.
+    pub fn yolox_s() -> Self {
+        Self::new(0.33, 0.50, COCO_CLASSES, false).with_weights(weights::YoloxS::Coco)
+    }
+
+    /// YOLOX-M configuration for the 80 COCO classes, with the official pre-trained weights.
+    pub fn yolox_m() -> Self {
+        Self::new(0.67, 0.75, COCO_CLASSES, false).with_weights(weights::YoloxM::Coco)
+    }
+
+    /// YOLOX-L configuration for the 80 COCO classes, with the official pre-trained weights.
+    pub fn yolox_l() -> Self {
+        Self::new(1., 1., COCO_CLASSES, false).with_weights(weights::YoloxL::Coco)
+    }
+
+    /// YOLOX-X configuration for the 80 COCO classes, with the official pre-trained weights.
+    pub fn yolox_x() -> Self {
+        Self::new(1.33, 1.25, COCO_CLASSES, false).with_weights(weights::YoloxX::Coco)
+    }
+
+    /// Set the type of stem block of the backbone.
+    ///
+    /// The [focus-free](StemType::FocusFree) stem replaces the slicing operations of the focus
+    /// block by an equivalent strided convolution.
+    pub fn with_stem_type(mut self, stem_type: StemType) -> Self {
+        self.backbone = self.backbone.with_stem_type(stem_type);
+        self
+    }
+
+    /// Set the number of output classes of the detection head.
+    pub fn with_num_classes(mut self, num_classes: usize) -> Self {
+        self.head = self.head.with_num_classes(num_classes);
+        self
+    }
+
+    /// Precompute the anchor grid of the detection head for `[height, width]` input images
+    /// (see [DetectionHeadConfig::with_input_size]).
+    pub fn with_input_size(mut self, input_size: Option<[usize; 2]>) -> Self {
+        self.head = self.head.with_input_size(input_size);
+        self
+    }
+
+    /// Set the pre-trained weights loaded by [init_pretrained](Self::init_pretrained).
+    pub fn with_weights(mut self, weights: impl WeightsMeta) -> Self {
+        self.weights = Some(weights.weights());
+        self
+    }
+
+    /// The [darknet backbone](CspDarknetConfig) configuration.
+    pub fn darknet(&self) -> &CspDarknetConfig {
+        self.backbone.backbone()
+    }
 
-        Self { backbone, head }
+    /// The pre-trained weights of the config, if any.
+    pub fn weights(&self) -> Option<&weights::Weights> {
+        self.weights.as_ref()
     }
 
     /// Initialize a new [YOLOX detector](Yolox) module.
@@ -326,4 +502,77 @@ impl YoloxConfig {
             head: self.head.init(device),
         }
     }
+
+    /// Initialize a new [YOLOX detector](Yolox) module with the pre-trained weights of the
+    /// config, downloaded and imported from the official PyTorch checkpoint.
+    ///
+    /// The architecture and number of classes of the config should match the weights. In
+    /// particular, the [stem type](Self::with_stem_type) should be the default focus block.
+    #[cfg(feature = "pretrained")]
+    pub fn init_pretrained<B: Backend>(
+        &self,
+        device: &Device<B>,
+    ) -> Result<Yolox<B>, RecorderError> {
+        let weights = self.weights.as_ref().ok_or_else(|| {
+            RecorderError::Unknown("No pre-trained weights for this configuration.".into())
+        })?;
+
+        let model = self.init(device);
+        if model.head.num_classes() != weights.num_classes {
+            return Err(RecorderError::Unknown(format!(
+                "The pre-trained weights have {} classes, but the model has {}.",
+                weights.num_classes,
+                model.head.num_classes()
+            )));
+        }
+        let record = Yolox::load_weights_record(weights, device)?;
+
+        Ok(model.load_record(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        optim::GradientsParams,
+        tensor::{Distribution, ElementConversion},
+    };
+
+    type TestBackend = Autodiff<NdArray<f32>>;
+
+    #[test]
+    fn forward_training_loss() {
+        let device = Default::default();
+        let model = Yolox::<TestBackend>::yolox_nano(2, &device);
+        let x = Tensor::random([2, 3, 64, 64], Distribution::Default, &device);
+        let targets = [
+            GroundTruthBoxes {
+                boxes: Tensor::from_floats([[32., 32., 24., 16.], [12., 40., 8., 8.]], &device),
+                labels: Tensor::from_ints([0, 1], &device),
+            },
+            GroundTruthBoxes {
+                boxes: Tensor::zeros([0, 4], &device),
+                labels: Tensor::zeros([0], &device),
+            },
+        ];
+
+        let output = model.forward_training(x, &targets, &YoloxLoss::new());
+
+        assert!(output.num_foreground >= 2);
+        for term in [
+            &output.loss,
+            &output.iou_loss,
+            &output.obj_loss,
+            &output.cls_loss,
+        ] {
+            assert_eq!(term.dims(), [1]);
+            let value = term.clone().into_scalar().elem::<f32>();
+            assert!(value.is_finite() && value >= 0.);
+        }
+
+        let grads = GradientsParams::from_grads(output.loss.backward(), &model);
+        assert!(!grads.is_empty());
+    }
 }
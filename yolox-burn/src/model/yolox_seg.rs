@@ -0,0 +1,488 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        PaddingConfig2d,
+    },
+    tensor::{
+        activation::sigmoid,
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, ElementConversion, Int, Tensor, TensorData,
+    },
+};
+
+use crate::types::Detection;
+
+use super::{
+    blocks::{expand, BaseConv, BaseConvConfig},
+    head::{DetectionHead, DetectionHeadConfig},
+    neck::FpnFeatures,
+    normalizations::{FreezeBatchNorms, SetBatchNormMomentum, SyncBatchNorms},
+    pafpn::{Pafpn, PafpnConfig},
+    postprocess::yolox::YoloxPostprocessor,
+};
+
+/// Output stride of the prototype masks.
+const PROTO_STRIDE: usize = 4;
+const IN_CHANNELS: [usize; 3] = [256, 512, 1024];
+
+/// Binary instance mask.
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major mask values (1 for foreground, 0 for background).
+    pub data: Vec<u8>,
+}
+
+/// [YOLOX-Seg](YoloxSeg) outputs.
+pub struct YoloxSegOutput<B: Backend> {
+    /// Decoded detections. Shape: `[batch_size, num_anchors, 5 + num_classes]`.
+    pub detections: Tensor<B, 3>,
+    /// Mask coefficients for each prediction. Shape: `[batch_size, num_anchors, mask_dim]`.
+    pub coefficients: Tensor<B, 3>,
+    /// Prototype masks. Shape: `[batch_size, mask_dim, H / 4, W / 4]`.
+    pub protos: Tensor<B, 4>,
+}
+
+/// Prototype mask generation network from [YOLACT](https://arxiv.org/abs/1904.02689).
+#[derive(Module, Debug)]
+pub struct ProtoNet<B: Backend> {
+    convs: Vec<BaseConv<B>>,
+    upsample_conv: BaseConv<B>,
+    proj: Conv2d<B>,
+}
+
+impl<B: Backend> ProtoNet<B> {
+    /// Generate the prototype masks from the stride 8 feature map.
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.convs.iter().fold(x, |x, conv| conv.forward(x));
+
+        // Upsample to stride 4
+        let [_, _, h, w] = x.dims();
+        let x = interpolate(
+            x,
+            [h * 2, w * 2],
+            InterpolateOptions::new(InterpolateMode::Bilinear),
+        );
+
+        let x = self.upsample_conv.forward(x);
+        self.proj.forward(x)
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for ProtoNet<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            convs: self.convs.freeze_batch_norms(),
+            upsample_conv: self.upsample_conv.freeze_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for ProtoNet<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            convs: self.convs.sync_batch_norms(),
+            upsample_conv: self.upsample_conv.sync_batch_norms(),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for ProtoNet<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.convs.set_bn_momentum(momentum);
+        self.upsample_conv.set_bn_momentum(momentum);
+    }
+}
+
+/// [ProtoNet](ProtoNet) configuration.
+pub struct ProtoNetConfig {
+    convs: Vec<BaseConvConfig>,
+    upsample_conv: BaseConvConfig,
+    proj: Conv2dConfig,
+}
+
+impl ProtoNetConfig {
+    /// Create a new instance of the ProtoNet [config](ProtoNetConfig).
+    pub fn new(in_channels: usize, hidden_channels: usize, mask_dim: usize) -> Self {
+        let convs = (0..3)
+            .map(|i| {
+                let in_channels = if i == 0 { in_channels } else { hidden_channels };
+                BaseConvConfig::new(in_channels, hidden_channels, 3, 1, 1)
+            })
+            .collect();
+        let upsample_conv = BaseConvConfig::new(hidden_channels, hidden_channels, 3, 1, 1);
+        let proj = Conv2dConfig::new([hidden_channels, mask_dim], [1, 1])
+            .with_padding(PaddingConfig2d::Explicit(0, 0));
+
+        Self {
+            convs,
+            upsample_conv,
+            proj,
+        }
+    }
+
+    /// Initialize a new [ProtoNet](ProtoNet) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ProtoNet<B> {
+        ProtoNet {
+            convs: self.convs.iter().map(|m| m.init(device)).collect(),
+            upsample_conv: self.upsample_conv.init(device),
+            proj: self.proj.init(device),
+        }
+    }
+}
+
+/// YOLOX-Seg instance segmentation architecture.
+///
+/// Extends the [YOLOX](super::yolox::Yolox) detector with a
+/// [YOLACT](https://arxiv.org/abs/1904.02689) style mask branch: a [ProtoNet](ProtoNet) generates
+/// prototype masks shared across all instances and the head predicts `mask_dim` additional
+/// coefficients for each prediction. The final instance masks are given by the linear
+/// combination of the prototypes with the coefficients.
+#[derive(Module, Debug)]
+pub struct YoloxSeg<B: Backend> {
+    backbone: Pafpn<B>,
+    head: DetectionHead<B>,
+    protonet: ProtoNet<B>,
+}
+
+impl<B: Backend> YoloxSeg<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> YoloxSegOutput<B> {
+        let FpnFeatures(f1, f2, f3) = self.backbone.forward(x);
+
+        let protos = self.protonet.forward(f1.clone());
+        let outputs = self.head.forward(FpnFeatures(f1, f2, f3));
+
+        // The mask coefficients follow the detection outputs
+        let [b, n, num_outputs] = outputs.dims();
+        let num_detection_outputs = num_outputs - self.head.mask_dim();
+        let detections = outputs
+            .clone()
+            .slice([0..b, 0..n, 0..num_detection_outputs]);
+        let coefficients = outputs.slice([0..b, 0..n, num_detection_outputs..num_outputs]);
+
+        YoloxSegOutput {
+            detections,
+            coefficients,
+            protos,
+        }
+    }
+
+    /// Detect and segment the instances of a batch of images.
+    ///
+    /// The coefficients of the predictions kept by the post-processing are combined with the
+    /// prototypes into the [mask](Detection::mask) of each detection, with the spatial dimensions
+    /// of the prototype masks (`H / 4`, `W / 4`).
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input images. Shape: `[batch_size, channels, height, width]`.
+    /// * `postprocessor` - Score filtering and non-maximum suppression of the detections.
+    ///
+    /// # Returns
+    ///
+    /// The detections of each image with their mask, sorted in decreasing order of scores.
+    pub fn segment(
+        &self,
+        x: Tensor<B, 4>,
+        postprocessor: &YoloxPostprocessor,
+    ) -> Vec<Vec<Detection>> {
+        let YoloxSegOutput {
+            detections,
+            coefficients,
+            protos,
+        } = self.forward(x);
+        let [_, num_anchors, mask_dim] = coefficients.dims();
+        let [_, _, proto_h, proto_w] = protos.dims();
+        let device = protos.device();
+
+        postprocessor
+            .postprocess_with_indices(detections)
+            .into_iter()
+            .enumerate()
+            .map(|(i, kept)| {
+                let (indices, detections): (Vec<_>, Vec<_>) = kept
+                    .into_iter()
+                    .map(|(index, det)| (index as i64, det))
+                    .unzip();
+                if detections.is_empty() {
+                    return detections;
+                }
+
+                // Coefficients of the kept predictions [num_detections, mask_dim]
+                let num_detections = indices.len();
+                let indices = Tensor::<B, 1, Int>::from_data(
+                    TensorData::new(indices, [num_detections]).convert::<B::IntElem>(),
+                    &device,
+                );
+                let coefficients = coefficients
+                    .clone()
+                    .slice([i..i + 1, 0..num_anchors, 0..mask_dim])
+                    .reshape([num_anchors, mask_dim])
+                    .select(0, indices);
+                let protos = protos
+                    .clone()
+                    .slice([i..i + 1, 0..mask_dim, 0..proto_h, 0..proto_w]);
+
+                let masks = Self::decode_masks(detections.clone(), coefficients, protos);
+                detections
+                    .into_iter()
+                    .zip(masks)
+                    .map(|(mut det, mask)| {
+                        det.mask = Some(mask.data);
+                        det
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Compute the binary masks of the detected instances for a single image.
+    ///
+    /// # Arguments
+    ///
+    /// * `detections`: Detections of the image (boxes in input image coordinates).
+    /// * `coefficients` - Mask coefficients for each detection.
+    ///   Shape: `[num_detections, mask_dim]`.
+    /// * `protos` - Prototype masks of the image. Shape: `[1, mask_dim, H / 4, W / 4]`.
+    ///
+    /// # Returns
+    ///
+    /// The binary mask of each detection, with the spatial dimensions of the prototype masks.
+    /// Masks are cropped to their bounding box.
+    pub fn decode_masks(
+        detections: Vec<Detection>,
+        coefficients: Tensor<B, 2>,
+        protos: Tensor<B, 4>,
+    ) -> Vec<Mask> {
+        let [_, mask_dim, height, width] = protos.dims();
+        let [num_detections, num_coefficients] = coefficients.dims();
+        assert_eq!(
+            num_detections,
+            detections.len(),
+            "expected one coefficients vector per detection"
+        );
+        assert_eq!(
+            num_coefficients, mask_dim,
+            "number of coefficients should be equal to the prototype masks dimension"
+        );
+
+        if detections.is_empty() {
+            return vec![];
+        }
+
+        // [num_detections, mask_dim] @ [mask_dim, H * W]
+        let masks = sigmoid(coefficients.matmul(protos.reshape([mask_dim, height * width])));
+        let masks: Vec<f32> = masks
+            .greater_elem(0.5)
+            .float()
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem::<f32>())
+            .collect();
+
+        detections
+            .iter()
+            .zip(masks.chunks(height * width))
+            .map(|(detection, mask)| {
+                // Crop to the (downscaled) bounding box
+                let scale = PROTO_STRIDE as f32;
+                let [xmin, ymin, xmax, ymax] = detection.box_xyxy.map(|v| v / scale);
+                let xmin = xmin.floor().max(0.) as usize;
+                let ymin = ymin.floor().max(0.) as usize;
+                let xmax = (xmax.ceil().max(0.) as usize).min(width);
+                let ymax = (ymax.ceil().max(0.) as usize).min(height);
+
+                let data = mask
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        let (y, x) = (i / width, i % width);
+                        let inside = x >= xmin && x < xmax && y >= ymin && y < ymax;
+                        (inside && *v > 0.5) as u8
+                    })
+                    .collect();
+
+                Mask {
+                    width,
+                    height,
+                    data,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<B: Backend> FreezeBatchNorms<B> for YoloxSeg<B> {
+    fn freeze_batch_norms(self) -> Self {
+        Self {
+            backbone: self.backbone.freeze_batch_norms(),
+            head: self.head.freeze_batch_norms(),
+            protonet: self.protonet.freeze_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SyncBatchNorms<B> for YoloxSeg<B> {
+    fn sync_batch_norms(self) -> Self {
+        Self {
+            backbone: self.backbone.sync_batch_norms(),
+            head: self.head.sync_batch_norms(),
+            protonet: self.protonet.sync_batch_norms(),
+        }
+    }
+}
+
+impl<B: Backend> SetBatchNormMomentum<B> for YoloxSeg<B> {
+    fn set_bn_momentum(&mut self, momentum: f64) {
+        self.backbone.set_bn_momentum(momentum);
+        self.head.set_bn_momentum(momentum);
+        self.protonet.set_bn_momentum(momentum);
+    }
+}
+
+/// [YOLOX-Seg](YoloxSeg) configuration.
+pub struct YoloxSegConfig {
+    backbone: PafpnConfig,
+    head: DetectionHeadConfig,
+    protonet: ProtoNetConfig,
+}
+
+impl YoloxSegConfig {
+    /// Create a new instance of the YOLOX-Seg [config](YoloxSegConfig).
+    pub fn new(depth: f64, width: f64, num_classes: usize, mask_dim: usize) -> Self {
+        assert!(mask_dim > 0, "the mask dimension should be positive");
+        let depthwise = false;
+        let backbone = PafpnConfig::new(depth, width, depthwise);
+        let head =
+            DetectionHeadConfig::yolox(num_classes, width, depthwise).with_mask_dim(mask_dim);
+        let protonet =
+            ProtoNetConfig::new(expand(IN_CHANNELS[0], width), expand(256, width), mask_dim);
+
+        Self {
+            backbone,
+            head,
+            protonet,
+        }
+    }
+
+    /// Initialize a new [YOLOX-Seg](YoloxSeg) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> YoloxSeg<B> {
+        YoloxSeg {
+            backbone: self.backbone.init(device),
+            head: self.head.init(device),
+            protonet: self.protonet.init(device),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    use super::*;
+    use crate::types::ConfThreshold;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn protonet_output_shape() {
+        let device = Default::default();
+        let protonet = ProtoNetConfig::new(16, 8, 4).init::<TestBackend>(&device);
+
+        let protos = protonet.forward(Tensor::ones([2, 16, 8, 8], &device));
+
+        assert_eq!(protos.dims(), [2, 4, 16, 16]);
+    }
+
+    #[test]
+    fn coefficients_per_prediction() {
+        let device = Default::default();
+        let model = YoloxSegConfig::new(0.33, 0.25, 3, 4).init::<TestBackend>(&device);
+
+        let output = model.forward(Tensor::ones([1, 3, 64, 64], &device));
+
+        // 8x8 + 4x4 + 2x2 predictions
+        assert_eq!(output.detections.dims(), [1, 84, 8]);
+        assert_eq!(output.coefficients.dims(), [1, 84, 4]);
+        assert_eq!(output.protos.dims(), [1, 4, 16, 16]);
+    }
+
+    #[test]
+    fn decode_masks_with_proto_size() {
+        let device = Default::default();
+        let protos = Tensor::<TestBackend, 4>::ones([1, 2, 4, 6], &device);
+        let coefficients = Tensor::from_data(
+            TensorData::from([[1f32, 1.], [-1., -1.], [2., 0.]]),
+            &device,
+        );
+        let detections = vec![
+            Detection::new(0, [0., 0., 8., 8.], 0.9, 0),
+            Detection::new(0, [0., 0., 24., 16.], 0.8, 1),
+            // Partially outside of the image
+            Detection::new(0, [14., 6., 40., 40.], 0.7, 2),
+        ];
+
+        let masks = YoloxSeg::decode_masks(detections, coefficients, protos);
+
+        assert_eq!(masks.len(), 3);
+        for mask in &masks {
+            assert_eq!((mask.height, mask.width), (4, 6));
+            assert_eq!(mask.data.len(), 4 * 6);
+        }
+        // Cropped to the 2x2 box at stride 4
+        let foreground = |mask: &Mask| -> Vec<usize> {
+            (0..mask.data.len())
+                .filter(|&i| mask.data[i] == 1)
+                .collect()
+        };
+        assert_eq!(foreground(&masks[0]), vec![0, 1, 6, 7]);
+        // Negative logits everywhere
+        assert!(foreground(&masks[1]).is_empty());
+        // Columns 3..6 and rows 1..4
+        assert_eq!(
+            foreground(&masks[2]),
+            vec![9, 10, 11, 15, 16, 17, 21, 22, 23]
+        );
+    }
+
+    #[test]
+    fn segment_fills_detection_masks() {
+        let device = Default::default();
+        let model = YoloxSegConfig::new(0.33, 0.25, 3, 4).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 64, 64], Distribution::Default, &device);
+        // Keep the (low scoring) predictions of the randomly initialized model
+        let postprocessor = YoloxPostprocessor::new()
+            .with_conf_threshold(ConfThreshold::new(0.).unwrap())
+            .with_max_detections(5);
+
+        let segmented = model.segment(x.clone(), &postprocessor);
+
+        assert_eq!(segmented.len(), 2);
+        let output = model.forward(x);
+        let kept = postprocessor.postprocess_with_indices(output.detections);
+        for (i, (detections, kept)) in segmented.iter().zip(kept).enumerate() {
+            assert_eq!(detections.len(), 5);
+            for (det, (index, expected)) in detections.iter().zip(kept) {
+                assert_eq!(det.box_xyxy, expected.box_xyxy);
+                assert_eq!(det.image_id, i);
+
+                // Same mask as decoded from the coefficients of the kept prediction
+                let coefficients = output
+                    .coefficients
+                    .clone()
+                    .slice([i..i + 1, index..index + 1, 0..4])
+                    .reshape([1, 4]);
+                let protos = output.protos.clone().slice([i..i + 1, 0..4, 0..16, 0..16]);
+                let mask = YoloxSeg::decode_masks(vec![expected], coefficients, protos).remove(0);
+                assert_eq!(det.mask.as_ref(), Some(&mask.data));
+                assert_eq!(mask.data.len(), 16 * 16);
+            }
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Image preprocessing utilities.
+//!
+//! Images have shape `[C, H, W]` and boxes are `(xmin, ymin, xmax, ymax)` in pixel coordinates.
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    tensor::{
+        backend::Backend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, ElementConversion, Tensor,
+    },
+};
+
+/// Per-channel mean of the ImageNet images, for pixel values in `[0, 1]`.
+pub const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+/// Per-channel standard deviation of the ImageNet images, for pixel values in `[0, 1]`.
+pub const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// Per-channel image normalization with the dataset statistics: `(x - mean) / std`.
+///
+/// As a module, the normalization can be included in an end-to-end model which takes the raw
+/// images as input.
+#[derive(Module, Debug)]
+pub struct ImageNormalization<B: Backend> {
+    /// Shape: `[1, 3, 1, 1]`.
+    mean: Tensor<B, 4>,
+    /// Shape: `[1, 3, 1, 1]`.
+    std: Tensor<B, 4>,
+}
+
+impl<B: Backend> ImageNormalization<B> {
+    /// Normalize the images.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, 3, height, width]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        (x - self.mean.clone()) / self.std.clone()
+    }
+
+    /// Reverse the normalization (e.g., to visualize the normalized images).
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, 3, height, width]`
+    ///   - output: `[batch_size, 3, height, width]`
+    pub fn denormalize(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        x * self.std.clone() + self.mean.clone()
+    }
+}
+
+/// [Image normalization](ImageNormalization) configuration.
+pub struct ImageNormalizationConfig {
+    mean: [f32; 3],
+    std: [f32; 3],
+    max_value: f32,
+}
+
+impl ImageNormalizationConfig {
+    /// Create a new instance of the image normalization [config](ImageNormalizationConfig), with
+    /// the statistics of the pixel values in `[0, 1]`.
+    pub fn new(mean: [f32; 3], std: [f32; 3]) -> Self {
+        assert!(
+            std.iter().all(|&s| s > 0.),
+            "standard deviations should be positive"
+        );
+
+        Self {
+            mean,
+            std,
+            max_value: 1.,
+        }
+    }
+
+    /// ImageNet statistics.
+    pub fn imagenet() -> Self {
+        Self::new(IMAGENET_MEAN, IMAGENET_STD)
+    }
+
+    /// COCO statistics, which are the ImageNet statistics by convention (the backbones are
+    /// pre-trained on ImageNet).
+    pub fn coco() -> Self {
+        Self::imagenet()
+    }
+
+    /// Set the maximum pixel value of the input images (defaults to 1), e.g., 255 for the images
+    /// of the [datasets](crate::datasets).
+    pub fn with_max_value(mut self, max_value: f32) -> Self {
+        self.max_value = max_value;
+        self
+    }
+
+    /// Initialize a new [image normalization](ImageNormalization) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> ImageNormalization<B> {
+        let stats = |values: [f32; 3]| {
+            Tensor::<B, 1>::from_floats(values, device)
+                .mul_scalar(self.max_value)
+                .reshape([1, 3, 1, 1])
+        };
+
+        ImageNormalization {
+            mean: stats(self.mean),
+            std: stats(self.std),
+        }
+    }
+}
+
+/// Crop a box from an image with a margin, and resize the crop to the target size.
+///
+/// The box is expanded by `margin * max(box_w, box_h)` on each side. The regions of the expanded
+/// box outside of the image are filled with `fill_value`, so the aspect ratio of the object is
+/// preserved near the image borders.
+///
+/// # Arguments
+///
+/// * `image`: Image with shape `[channels, height, width]`.
+/// * `box_xyxy` - Box `(xmin, ymin, xmax, ymax)` in pixel coordinates.
+/// * `target_size` - Output size `(height, width)`.
+/// * `margin` - Margin on each side, relative to the largest side of the box.
+/// * `fill_value` - Value of the pixels outside of the image.
+///
+/// # Returns
+///
+/// The resized crop with shape `[channels, target_height, target_width]`.
+pub fn crop_resize_pad<B: Backend>(
+    image: Tensor<B, 3>,
+    box_xyxy: [f32; 4],
+    target_size: (usize, usize),
+    margin: f32,
+    fill_value: f32,
+) -> Tensor<B, 3> {
+    let [channels, height, width] = image.dims();
+    let [xmin, ymin, xmax, ymax] = box_xyxy;
+
+    // Expanded crop region, in (possibly out of bounds) pixel coordinates
+    let pad = margin * (xmax - xmin).max(ymax - ymin);
+    let left = floor(xmin - pad);
+    let top = floor(ymin - pad);
+    let right = ceil(xmax + pad).max(left + 1);
+    let bottom = ceil(ymax + pad).max(top + 1);
+    let (crop_w, crop_h) = ((right - left) as usize, (bottom - top) as usize);
+
+    // Region of the crop inside the image
+    let (x0, x1) = (left.max(0), right.min(width as i64));
+    let (y0, y1) = (top.max(0), bottom.min(height as i64));
+
+    let crop = if left == 0 && top == 0 && crop_w == width && crop_h == height {
+        image
+    } else {
+        let canvas = Tensor::full([channels, crop_h, crop_w], fill_value, &image.device());
+        if x0 < x1 && y0 < y1 {
+            let (x0, x1, y0, y1) = (x0 as usize, x1 as usize, y0 as usize, y1 as usize);
+            let (dx, dy) = ((x0 as i64 - left) as usize, (y0 as i64 - top) as usize);
+            canvas.slice_assign(
+                [0..channels, dy..dy + y1 - y0, dx..dx + x1 - x0],
+                image.slice([0..channels, y0..y1, x0..x1]),
+            )
+        } else {
+            // The crop does not overlap the image
+            canvas
+        }
+    };
+
+    let (target_h, target_w) = target_size;
+    interpolate(
+        crop.unsqueeze::<4>(),
+        [target_h, target_w],
+        InterpolateOptions::new(InterpolateMode::Bilinear),
+    )
+    .squeeze(0)
+}
+
+/// Apply [crop_resize_pad] to all the boxes of an image.
+///
+/// # Arguments
+///
+/// * `image`: Image with shape `[channels, height, width]`.
+/// * `boxes` - Boxes `(xmin, ymin, xmax, ymax)` with shape `[num_boxes, 4]`.
+/// * `target_size` - Output size `(height, width)`.
+/// * `margin` - Margin on each side, relative to the largest side of each box.
+/// * `fill_value` - Value of the pixels outside of the image.
+///
+/// # Returns
+///
+/// The resized crops with shape `[num_boxes, channels, target_height, target_width]`.
+pub fn batch_crop_resize_pad<B: Backend>(
+    image: Tensor<B, 3>,
+    boxes: Tensor<B, 2>,
+    target_size: (usize, usize),
+    margin: f32,
+    fill_value: f32,
+) -> Tensor<B, 4> {
+    let [channels, _, _] = image.dims();
+    let (target_h, target_w) = target_size;
+
+    let coords: Vec<f32> = boxes
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>())
+        .collect();
+    if coords.is_empty() {
+        return Tensor::zeros([0, channels, target_h, target_w], &image.device());
+    }
+
+    let crops = coords
+        .chunks(4)
+        .map(|b| {
+            crop_resize_pad(
+                image.clone(),
+                [b[0], b[1], b[2], b[3]],
+                target_size,
+                margin,
+                fill_value,
+            )
+        })
+        .collect();
+
+    Tensor::stack(crops, 0)
+}
+
+/// Largest integer less than or equal to `x` (`f32::floor` requires the standard library).
+fn floor(x: f32) -> i64 {
+    let i = x as i64;
+    if (i as f32) > x {
+        i - 1
+    } else {
+        i
+    }
+}
+
+/// Smallest integer greater than or equal to `x`.
+fn ceil(x: f32) -> i64 {
+    -floor(-x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn normalization_round_trip() {
+        let device = Default::default();
+        let normalization = ImageNormalizationConfig::imagenet().init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::random([2, 3, 8, 6], Distribution::Default, &device);
+
+        let restored = normalization.denormalize(normalization.forward(x.clone()));
+
+        restored.into_data().assert_approx_eq(&x.into_data(), 5);
+    }
+
+    #[test]
+    fn normalization_per_channel() {
+        let device = Default::default();
+        let normalization = ImageNormalizationConfig::new([0.5, 0.25, 0.], [0.5, 0.25, 2.])
+            .with_max_value(255.)
+            .init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 4>::full([1, 3, 1, 1], 127.5, &device);
+
+        normalization
+            .forward(x)
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[[[0f32]], [[1.]], [[0.25]]]]), 5);
+    }
+
+    #[test]
+    fn normalization_gradient() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let normalization = ImageNormalizationConfig::coco().init::<TrainingBackend>(&device);
+        let x = Tensor::<TrainingBackend, 4>::random([1, 3, 2, 2], Distribution::Default, &device)
+            .require_grad();
+
+        let grads = normalization.forward(x.clone()).sum().backward();
+
+        // Gradient of the division by the standard deviation
+        let expected = Tensor::<TestBackend, 1>::from_floats(IMAGENET_STD, &device)
+            .recip()
+            .reshape([1, 3, 1, 1])
+            .repeat_dim(2, 2)
+            .repeat_dim(3, 2);
+        x.grad(&grads)
+            .unwrap()
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 4);
+    }
+
+    #[test]
+    #[should_panic = "standard deviations should be positive"]
+    fn normalization_zero_std() {
+        ImageNormalizationConfig::new([0.5; 3], [0.2, 0., 0.2]);
+    }
+
+    #[test]
+    fn full_image_box_without_margin() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::random([3, 12, 16], Distribution::Default, &device);
+
+        let crop = crop_resize_pad(image.clone(), [0., 0., 16., 12.], (12, 16), 0., 0.);
+
+        crop.into_data().assert_approx_eq(&image.into_data(), 4);
+    }
+
+    #[test]
+    fn out_of_bounds_fill_value() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::ones([1, 8, 8], &device);
+
+        // Expanded to (-2, -2, 6, 6)
+        let crop = crop_resize_pad(image, [0., 0., 4., 4.], (8, 8), 0.5, -1.);
+
+        let expected: Vec<f32> = (0..8)
+            .flat_map(|y| (0..8).map(move |x| if x < 2 || y < 2 { -1. } else { 1. }))
+            .collect();
+        crop.into_data()
+            .assert_approx_eq(&TensorData::new(expected, [1, 8, 8]), 4);
+    }
+
+    #[test]
+    fn crop_output_shape() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::random([3, 20, 30], Distribution::Default, &device);
+
+        let crop = crop_resize_pad(image, [5.5, 3., 12., 18.2], (24, 16), 0.1, 0.5);
+
+        assert_eq!(crop.dims(), [3, 24, 16]);
+    }
+
+    #[test]
+    fn batch_crops() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::random([3, 20, 30], Distribution::Default, &device);
+        let boxes = Tensor::<TestBackend, 2>::from_floats(
+            [
+                [5., 3., 12., 18.],
+                [-10., -10., 5., 5.],
+                [25., 15., 40., 30.],
+            ],
+            &device,
+        );
+
+        let crops = batch_crop_resize_pad(image.clone(), boxes, (8, 8), 0.2, 0.);
+        assert_eq!(crops.dims(), [3, 3, 8, 8]);
+
+        let crops = batch_crop_resize_pad(image, Tensor::zeros([0, 4], &device), (8, 8), 0.2, 0.);
+        assert_eq!(crops.dims(), [0, 3, 8, 8]);
+    }
+
+    #[test]
+    fn floor_and_ceil() {
+        assert_eq!(floor(1.5), 1);
+        assert_eq!(floor(-1.5), -2);
+        assert_eq!(floor(-2.), -2);
+        assert_eq!(ceil(1.2), 2);
+        assert_eq!(ceil(-1.2), -1);
+        assert_eq!(ceil(3.), 3);
+    }
+}
@@ -0,0 +1,474 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, ModuleMapper, ModuleVisitor, Param, ParamId},
+    nn::{Gelu, Linear, LinearConfig},
+    tensor::{backend::Backend, Device, Distribution, Tensor},
+};
+
+use crate::model::{
+    heads::l2_normalize,
+    vit::{Vit, VitConfig},
+};
+
+/// Linear layer without bias, whose weight is [weight normalized](https://arxiv.org/abs/1602.07868)
+/// with a frozen magnitude of 1 (i.e., each output unit has a unit norm weight vector).
+#[derive(Module, Debug)]
+pub struct WeightNormLinear<B: Backend> {
+    /// Weight direction. Shape: `[d_input, d_output]`.
+    weight: Param<Tensor<B, 2>>,
+}
+
+impl<B: Backend> WeightNormLinear<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, d_input]`
+    ///   - output: `[batch_size, d_output]`
+    pub fn forward(&self, x: Tensor<B, 2>) -> Tensor<B, 2> {
+        x.matmul(self.normalized_weight())
+    }
+
+    /// Effective weight, with unit norm columns. Shape: `[d_input, d_output]`.
+    pub fn normalized_weight(&self) -> Tensor<B, 2> {
+        let weight = self.weight.val();
+        let norm = weight
+            .clone()
+            .powf_scalar(2.)
+            .sum_dim(0)
+            .sqrt()
+            .clamp_min(1e-12);
+        weight / norm
+    }
+}
+
+/// Projection head of [DINO](Dino): a 3-layer MLP with GELU activations, whose output is L2
+/// normalized and projected by a [weight normalized](WeightNormLinear) linear layer.
+#[derive(Module, Debug)]
+pub struct DinoHead<B: Backend> {
+    mlp: Vec<Linear<B>>,
+    activation: Gelu,
+    last_layer: WeightNormLinear<B>,
+}
+
+impl<B: Backend> DinoHead<B> {
+    /// # Shapes
+    ///   - x: `[batch_size, in_dim]`
+    ///   - output: `[batch_size, out_dim]`
+    pub fn forward(&self, x: Tensor<B, 2>) -> Tensor<B, 2> {
+        let num_layers = self.mlp.len();
+        let x = self.mlp.iter().enumerate().fold(x, |x, (i, linear)| {
+            let x = linear.forward(x);
+            if i + 1 < num_layers {
+                self.activation.forward(x)
+            } else {
+                x
+            }
+        });
+
+        self.last_layer.forward(l2_normalize(x))
+    }
+
+    /// Last (weight normalized) layer of the head.
+    pub fn last_layer(&self) -> &WeightNormLinear<B> {
+        &self.last_layer
+    }
+}
+
+/// [DINO projection head](DinoHead) configuration.
+pub struct DinoHeadConfig {
+    in_dim: usize,
+    hidden_dim: usize,
+    bottleneck_dim: usize,
+    out_dim: usize,
+}
+
+impl DinoHeadConfig {
+    /// Create a new instance of the DINO projection head [config](DinoHeadConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `in_dim`: Dimension of the backbone features.
+    /// * `hidden_dim` - Hidden dimension of the MLP (2048 in the original paper).
+    /// * `bottleneck_dim` - Output dimension of the MLP (256 in the original paper).
+    /// * `out_dim` - Output dimension of the head (65536 in the original paper).
+    pub fn new(in_dim: usize, hidden_dim: usize, bottleneck_dim: usize, out_dim: usize) -> Self {
+        Self {
+            in_dim,
+            hidden_dim,
+            bottleneck_dim,
+            out_dim,
+        }
+    }
+
+    /// Initialize a new [DINO projection head](DinoHead) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> DinoHead<B> {
+        let dims = [
+            self.in_dim,
+            self.hidden_dim,
+            self.hidden_dim,
+            self.bottleneck_dim,
+        ];
+
+        DinoHead {
+            mlp: dims
+                .windows(2)
+                .map(|d| LinearConfig::new(d[0], d[1]).init(device))
+                .collect(),
+            activation: Gelu::new(),
+            last_layer: WeightNormLinear {
+                weight: Param::from_tensor(Tensor::random(
+                    [self.bottleneck_dim, self.out_dim],
+                    Distribution::Normal(0., 0.02),
+                    device,
+                )),
+            },
+        }
+    }
+}
+
+/// [ViT](Vit) backbone followed by a [projection head](DinoHead).
+#[derive(Module, Debug)]
+pub struct DinoBranch<B: Backend> {
+    backbone: Vit<B>,
+    head: DinoHead<B>,
+}
+
+impl<B: Backend> DinoBranch<B> {
+    /// Project the class token of the backbone.
+    ///
+    /// # Shapes
+    ///   - x: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, out_dim]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 2> {
+        let tokens = self.backbone.forward(x);
+        let [batch_size, _, embed_dim] = tokens.dims();
+        let cls = tokens
+            .slice([0..batch_size, 0..1, 0..embed_dim])
+            .reshape([batch_size, embed_dim]);
+
+        self.head.forward(cls)
+    }
+
+    /// Apply the branch to multiple crops of the same size in a single forward pass.
+    fn forward_crops(&self, crops: Vec<Tensor<B, 4>>) -> Vec<Tensor<B, 2>> {
+        if crops.is_empty() {
+            return Vec::new();
+        }
+
+        let num_crops = crops.len();
+        self.forward(Tensor::cat(crops, 0)).chunk(num_crops, 0)
+    }
+}
+
+/// Outputs of the [DINO](Dino) student and teacher.
+pub struct DinoOutput<B: Backend> {
+    /// Student outputs of the global crops, then of the local crops.
+    /// Shape: `[batch_size, out_dim]`.
+    pub student: Vec<Tensor<B, 2>>,
+    /// Teacher outputs of the global crops (without gradient). Shape: `[batch_size, out_dim]`.
+    pub teacher: Vec<Tensor<B, 2>>,
+}
+
+/// [DINO](https://arxiv.org/abs/2104.14294) self-distillation for self-supervised pre-training of a
+/// [ViT](Vit) backbone.
+///
+/// The student sees the global and local crops of an image and is trained (with
+/// [DinoLoss](crate::loss::DinoLoss)) to match the output distribution of the teacher on the
+/// global crops. The teacher is not trained by the optimizer: its weights are an exponential
+/// moving average of the student weights, updated with [update_teacher](Dino::update_teacher)
+/// after each optimizer step.
+///
+/// The local crops are embedded with the position embeddings of the first patches, so their size
+/// should be a multiple of the patch size no larger than the backbone image size.
+#[derive(Module, Debug)]
+pub struct Dino<B: Backend> {
+    student: DinoBranch<B>,
+    teacher: DinoBranch<B>,
+    teacher_momentum: f64,
+}
+
+impl<B: Backend> Dino<B> {
+    /// Compute the student and teacher outputs of the multi-crop views of a batch of images.
+    ///
+    /// # Shapes
+    ///   - global_crops: `[batch_size, in_channels, global_size, global_size]` each
+    ///   - local_crops: `[batch_size, in_channels, local_size, local_size]` each
+    pub fn forward(
+        &self,
+        global_crops: Vec<Tensor<B, 4>>,
+        local_crops: Vec<Tensor<B, 4>>,
+    ) -> DinoOutput<B> {
+        let teacher = self
+            .teacher
+            .forward_crops(global_crops.clone())
+            .into_iter()
+            .map(|x| x.detach())
+            .collect();
+
+        let mut student = self.student.forward_crops(global_crops);
+        student.extend(self.student.forward_crops(local_crops));
+
+        DinoOutput { student, teacher }
+    }
+
+    /// Update the teacher weights with the exponential moving average of the student weights:
+    /// `teacher = momentum * teacher + (1 - momentum) * student`.
+    pub fn update_teacher(&mut self) {
+        self.teacher = ema_update(self.teacher.clone(), &self.student, self.teacher_momentum);
+    }
+
+    /// Set the momentum of the teacher update (e.g., to follow a cosine schedule to 1).
+    pub fn set_teacher_momentum(&mut self, momentum: f64) {
+        self.teacher_momentum = momentum;
+    }
+
+    /// Momentum of the teacher update.
+    pub fn teacher_momentum(&self) -> f64 {
+        self.teacher_momentum
+    }
+
+    /// The teacher branch.
+    pub fn teacher(&self) -> &DinoBranch<B> {
+        &self.teacher
+    }
+
+    /// Consume DINO and return the pre-trained backbone of the teacher, which usually performs
+    /// better than the student one.
+    pub fn into_backbone(self) -> Vit<B> {
+        self.teacher.backbone
+    }
+}
+
+/// Blend the parameters of the teacher with the ones of the student.
+///
+/// The student and the teacher have different parameter IDs, so their parameters are matched by
+/// their order in the module, which requires the two branches to have the same architecture.
+fn ema_update<B: Backend>(
+    teacher: DinoBranch<B>,
+    student: &DinoBranch<B>,
+    momentum: f64,
+) -> DinoBranch<B> {
+    let mut collector = ParamCollector { params: Vec::new() };
+    student.visit(&mut collector);
+
+    let mut mapper = EmaMapper {
+        params: collector.params.into_iter(),
+        momentum,
+    };
+    teacher.map(&mut mapper)
+}
+
+/// Collect the (flattened and detached) float parameters of a module, in order.
+struct ParamCollector<B: Backend> {
+    params: Vec<Tensor<B, 1>>,
+}
+
+impl<B: Backend> ModuleVisitor<B> for ParamCollector<B> {
+    fn visit_float<const D: usize>(&mut self, _id: &ParamId, tensor: &Tensor<B, D>) {
+        let numel = tensor.shape().num_elements();
+        self.params.push(tensor.clone().detach().reshape([numel]));
+    }
+}
+
+/// Blend the module parameters with the collected parameters, in order.
+struct EmaMapper<B: Backend> {
+    params: vec::IntoIter<Tensor<B, 1>>,
+    momentum: f64,
+}
+
+impl<B: Backend> ModuleMapper<B> for EmaMapper<B> {
+    fn map_float<const D: usize>(&mut self, _id: &ParamId, tensor: Tensor<B, D>) -> Tensor<B, D> {
+        let param = self
+            .params
+            .next()
+            .expect("the student and teacher should have the same architecture");
+        assert_eq!(
+            param.dims()[0],
+            tensor.shape().num_elements(),
+            "the student and teacher should have the same architecture"
+        );
+
+        let param = param.reshape(tensor.shape());
+        tensor.mul_scalar(self.momentum) + param.mul_scalar(1. - self.momentum)
+    }
+}
+
+/// [DINO](Dino) configuration.
+pub struct DinoConfig {
+    student: VitConfig,
+    teacher: VitConfig,
+    student_head: DinoHeadConfig,
+    teacher_head: DinoHeadConfig,
+    teacher_momentum: f64,
+}
+
+impl DinoConfig {
+    /// Create a new instance of the DINO [config](DinoConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `student_config`: ViT configuration of the student.
+    /// * `teacher_config` - ViT configuration of the teacher, which should have the same
+    ///   architecture as the student.
+    /// * `projection_dim` - Bottleneck dimension of the projection head (256 in the original
+    ///   paper).
+    /// * `hidden_dim` - Hidden dimension of the projection head (2048 in the original paper).
+    /// * `out_dim` - Output dimension of the projection head (65536 in the original paper).
+    /// * `teacher_momentum` - Momentum of the teacher update (0.996 in the original paper).
+    pub fn new(
+        student_config: VitConfig,
+        teacher_config: VitConfig,
+        projection_dim: usize,
+        hidden_dim: usize,
+        out_dim: usize,
+        teacher_momentum: f64,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&teacher_momentum),
+            "teacher momentum should be in range [0, 1]"
+        );
+
+        let head = |backbone: &VitConfig| {
+            DinoHeadConfig::new(backbone.embed_dim(), hidden_dim, projection_dim, out_dim)
+        };
+
+        Self {
+            student_head: head(&student_config),
+            teacher_head: head(&teacher_config),
+            student: student_config,
+            teacher: teacher_config,
+            teacher_momentum,
+        }
+    }
+
+    /// Initialize a new [DINO](Dino) module, with the teacher initialized with the student
+    /// weights.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Dino<B> {
+        let student = DinoBranch {
+            backbone: self.student.init(device),
+            head: self.student_head.init(device),
+        };
+        let teacher = DinoBranch {
+            backbone: self.teacher.init(device),
+            head: self.teacher_head.init(device),
+        };
+        let teacher = ema_update(teacher, &student, 0.).no_grad();
+
+        Dino {
+            student,
+            teacher,
+            teacher_momentum: self.teacher_momentum,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    /// DINO with a tiny ViT on 32x32 images, i.e. 16 patches of 8x8 pixels.
+    fn config(teacher_momentum: f64) -> DinoConfig {
+        let backbone = || VitConfig::new(32, 8, 3, 16, 1, 2, 2.);
+        DinoConfig::new(backbone(), backbone(), 8, 32, 64, teacher_momentum)
+    }
+
+    fn dino(teacher_momentum: f64) -> Dino<TestBackend> {
+        config(teacher_momentum).init(&Default::default())
+    }
+
+    fn params(branch: &DinoBranch<TestBackend>) -> Vec<Tensor<TestBackend, 1>> {
+        let mut collector = ParamCollector { params: Vec::new() };
+        branch.visit(&mut collector);
+        collector.params
+    }
+
+    #[test]
+    fn teacher_initialized_with_student() {
+        let dino = dino(0.996);
+
+        for (teacher, student) in params(&dino.teacher).into_iter().zip(params(&dino.student)) {
+            teacher.into_data().assert_eq(&student.into_data(), true);
+        }
+    }
+
+    #[test]
+    fn teacher_ema_update() {
+        let device = Default::default();
+        let mut dino = dino(0.75);
+        // Student weights different from the teacher ones, as after an optimizer step
+        let other = config(0.75);
+        dino.student = DinoBranch {
+            backbone: other.student.init(&device),
+            head: other.student_head.init(&device),
+        };
+        let teacher = params(&dino.teacher);
+        let student = params(&dino.student);
+
+        dino.update_teacher();
+
+        let mut num_changed = 0;
+        for ((updated, teacher), student) in
+            params(&dino.teacher).into_iter().zip(teacher).zip(student)
+        {
+            let expected = teacher.clone().mul_scalar(0.75) + student.mul_scalar(0.25);
+            updated
+                .clone()
+                .into_data()
+                .assert_approx_eq(&expected.into_data(), 5);
+            if updated.into_data().to_vec::<f32>().unwrap()
+                != teacher.into_data().to_vec::<f32>().unwrap()
+            {
+                num_changed += 1;
+            }
+        }
+        assert!(num_changed > 0);
+    }
+
+    #[test]
+    fn last_layer_weight_normalization() {
+        let device = Default::default();
+        let mut head = DinoHeadConfig::new(16, 32, 8, 64).init::<TestBackend>(&device);
+
+        // The magnitude is frozen: only the weight direction is learned
+        assert_eq!(head.last_layer().num_params(), 8 * 64);
+        let norms = head
+            .last_layer()
+            .normalized_weight()
+            .powf_scalar(2.)
+            .sum_dim(0)
+            .sqrt();
+        norms
+            .into_data()
+            .assert_approx_eq(&TensorData::from([[1f32; 64]]), 4);
+
+        // Scaling the weight does not change the output
+        let x = Tensor::<TestBackend, 2>::random([2, 16], Distribution::Default, &device);
+        let expected = head.forward(x.clone()).into_data();
+        head.last_layer.weight = Param::from_tensor(head.last_layer.weight.val().mul_scalar(3.));
+        head.forward(x).into_data().assert_approx_eq(&expected, 4);
+    }
+
+    #[test]
+    fn multi_crop_outputs() {
+        let device = Default::default();
+        let dino = dino(0.996);
+        let global = |_| Tensor::random([2, 3, 32, 32], Distribution::Default, &device);
+        let local = |_| Tensor::random([2, 3, 16, 16], Distribution::Default, &device);
+
+        let output = dino.forward((0..2).map(global).collect(), (0..3).map(local).collect());
+
+        // The teacher only sees the global crops
+        assert_eq!(output.teacher.len(), 2);
+        assert_eq!(output.student.len(), 5);
+        for x in output.teacher.iter().chain(output.student.iter()) {
+            assert_eq!(x.dims(), [2, 64]);
+        }
+    }
+
+    #[test]
+    #[should_panic = "teacher momentum should be in range [0, 1]"]
+    fn invalid_teacher_momentum() {
+        dino(1.5);
+    }
+}
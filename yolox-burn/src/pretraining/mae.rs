@@ -0,0 +1,336 @@
+use alloc::{vec, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{
+        transformer::{TransformerEncoder, TransformerEncoderConfig, TransformerEncoderInput},
+        LayerNorm, LayerNormConfig, Linear, LinearConfig,
+    },
+    tensor::{backend::Backend, Device, Distribution, Int, Tensor, TensorData},
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::model::vit::{Vit, VitConfig};
+
+/// [Masked Autoencoder](https://arxiv.org/abs/2111.06377) (MAE) for self-supervised pre-training
+/// of a [ViT](Vit) backbone.
+///
+/// A random subset of the image patches is masked. The encoder is only applied to the visible
+/// patches and a lightweight decoder reconstructs the pixels of the masked patches from the
+/// encoded visible patches and a learned mask token.
+#[derive(Module, Debug)]
+pub struct Mae<B: Backend> {
+    encoder: Vit<B>,
+    decoder_embed: Linear<B>,
+    mask_token: Param<Tensor<B, 3>>,
+    decoder_pos_embed: Param<Tensor<B, 3>>,
+    decoder: TransformerEncoder<B>,
+    decoder_norm: LayerNorm<B>,
+    decoder_pred: Linear<B>,
+    patch_size: usize,
+    mask_ratio: f64,
+}
+
+impl<B: Backend> Mae<B> {
+    /// Reconstruct the image patches.
+    ///
+    /// # Returns
+    ///
+    /// The predicted pixels of each patch (in the [patchify](patchify) layout) with shape
+    /// `[batch_size, num_patches, patch_size^2 * in_channels]` and the binary mask with shape
+    /// `[batch_size, num_patches]` (1 for masked patches, 0 for visible patches).
+    pub fn forward(
+        &self,
+        images: Tensor<B, 4>,
+        rng: &mut impl Rng,
+    ) -> (Tensor<B, 3>, Tensor<B, 2>) {
+        let device = images.device();
+        let x = self.encoder.embed_patches(images);
+        let [batch_size, num_patches, embed_dim] = x.dims();
+        let num_keep = ((num_patches as f64) * (1. - self.mask_ratio)) as usize;
+
+        // Random shuffle of the patches for each sample
+        let mut ids_shuffle = Vec::with_capacity(batch_size * num_patches);
+        let mut ids_restore = vec![0i64; batch_size * num_patches];
+        let mut mask = vec![1f32; batch_size * num_patches];
+        for b in 0..batch_size {
+            let mut ids: Vec<usize> = (0..num_patches).collect();
+            ids.shuffle(rng);
+            for (pos, &id) in ids.iter().enumerate() {
+                ids_restore[b * num_patches + id] = pos as i64;
+                if pos < num_keep {
+                    mask[b * num_patches + id] = 0.;
+                }
+            }
+            ids_shuffle.extend(ids.into_iter().map(|id| id as i64));
+        }
+        let int_tensor = |data: Vec<i64>| {
+            Tensor::<B, 2, Int>::from_data(
+                TensorData::new(data, [batch_size, num_patches]).convert::<B::IntElem>(),
+                &device,
+            )
+        };
+        let ids_shuffle = int_tensor(ids_shuffle);
+        let ids_restore = int_tensor(ids_restore);
+        let mask = Tensor::from_data(
+            TensorData::new(mask, [batch_size, num_patches]).convert::<B::FloatElem>(),
+            &device,
+        );
+
+        // Encode the visible patches only
+        let ids_keep = ids_shuffle.slice([0..batch_size, 0..num_keep]);
+        let x = x.gather(1, expand_ids(ids_keep, embed_dim));
+        let cls = self.encoder.cls_token().repeat_dim(0, batch_size);
+        let latent = self.encoder.encode(Tensor::cat(vec![cls, x], 1));
+
+        // Append the mask tokens and restore the original order
+        let x = self.decoder_embed.forward(latent);
+        let [_, num_tokens, decoder_dim] = x.dims();
+        let cls = x.clone().slice([0..batch_size, 0..1, 0..decoder_dim]);
+        let x = x.slice([0..batch_size, 1..num_tokens, 0..decoder_dim]);
+        let mask_tokens = self
+            .mask_token
+            .val()
+            .repeat_dim(0, batch_size)
+            .repeat_dim(1, num_patches - num_keep);
+        let x =
+            Tensor::cat(vec![x, mask_tokens], 1).gather(1, expand_ids(ids_restore, decoder_dim));
+        let x = Tensor::cat(vec![cls, x], 1) + self.decoder_pos_embed.val();
+
+        // Decode and remove the class token
+        let x = self.decoder.forward(TransformerEncoderInput::new(x));
+        let x = self.decoder_pred.forward(self.decoder_norm.forward(x));
+        let [_, _, patch_dim] = x.dims();
+        let pred = x.slice([0..batch_size, 1..num_patches + 1, 0..patch_dim]);
+
+        (pred, mask)
+    }
+
+    /// Patch size of the encoder.
+    pub fn patch_size(&self) -> usize {
+        self.patch_size
+    }
+
+    /// Consume the MAE and return the pre-trained encoder.
+    pub fn into_encoder(self) -> Vit<B> {
+        self.encoder
+    }
+}
+
+/// Expand the token indices `[batch_size, num_tokens]` along the embedding dimension for
+/// [gather](Tensor::gather).
+fn expand_ids<B: Backend>(ids: Tensor<B, 2, Int>, dim: usize) -> Tensor<B, 3, Int> {
+    ids.unsqueeze_dim::<3>(2).repeat_dim(2, dim)
+}
+
+/// [Masked Autoencoder](Mae) configuration.
+pub struct MaeConfig {
+    backbone: VitConfig,
+    decoder_embed: LinearConfig,
+    decoder: TransformerEncoderConfig,
+    decoder_norm: LayerNormConfig,
+    decoder_pred: LinearConfig,
+    decoder_embed_dim: usize,
+    mask_ratio: f64,
+}
+
+impl MaeConfig {
+    /// Create a new instance of the MAE [config](MaeConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `backbone_config`: ViT encoder configuration.
+    /// * `decoder_embed_dim` - Embedding dimension of the decoder.
+    /// * `decoder_depth` - Number of decoder transformer blocks.
+    /// * `decoder_num_heads` - Number of decoder attention heads.
+    /// * `mask_ratio` - Ratio of masked patches in `[0, 1)` (0.75 in the original paper).
+    pub fn new(
+        backbone_config: VitConfig,
+        decoder_embed_dim: usize,
+        decoder_depth: usize,
+        decoder_num_heads: usize,
+        mask_ratio: f64,
+    ) -> Self {
+        assert!(
+            (0.0..1.0).contains(&mask_ratio),
+            "mask ratio should be in range [0, 1)"
+        );
+
+        let patch_dim = backbone_config.patch_size().pow(2) * backbone_config.in_channels();
+        let decoder_embed = LinearConfig::new(backbone_config.embed_dim(), decoder_embed_dim);
+        let decoder = TransformerEncoderConfig::new(
+            decoder_embed_dim,
+            decoder_embed_dim * 4,
+            decoder_num_heads,
+            decoder_depth,
+        )
+        .with_dropout(0.)
+        .with_norm_first(true);
+        let decoder_norm = LayerNormConfig::new(decoder_embed_dim).with_epsilon(1e-6);
+        let decoder_pred = LinearConfig::new(decoder_embed_dim, patch_dim);
+
+        Self {
+            backbone: backbone_config,
+            decoder_embed,
+            decoder,
+            decoder_norm,
+            decoder_pred,
+            decoder_embed_dim,
+            mask_ratio,
+        }
+    }
+
+    /// Initialize a new [Masked Autoencoder](Mae) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> Mae<B> {
+        let init = |shape: [usize; 3]| {
+            Param::from_tensor(Tensor::random(
+                shape,
+                Distribution::Normal(0., 0.02),
+                device,
+            ))
+        };
+
+        Mae {
+            encoder: self.backbone.init(device),
+            decoder_embed: self.decoder_embed.init(device),
+            mask_token: init([1, 1, self.decoder_embed_dim]),
+            decoder_pos_embed: init([1, self.backbone.num_patches() + 1, self.decoder_embed_dim]),
+            decoder: self.decoder.init(device),
+            decoder_norm: self.decoder_norm.init(device),
+            decoder_pred: self.decoder_pred.init(device),
+            patch_size: self.backbone.patch_size(),
+            mask_ratio: self.mask_ratio,
+        }
+    }
+}
+
+/// Split the images into flattened patches.
+///
+/// # Shapes
+///   - images: `[batch_size, channels, height, width]`
+///   - output: `[batch_size, num_patches, patch_size^2 * channels]`
+pub fn patchify<B: Backend>(images: Tensor<B, 4>, patch_size: usize) -> Tensor<B, 3> {
+    let [b, c, h, w] = images.dims();
+    let (nh, nw) = (h / patch_size, w / patch_size);
+
+    images
+        .reshape([b, c, nh, patch_size, nw, patch_size])
+        // [B, nh, nw, p, p, C]
+        .permute([0, 2, 4, 3, 5, 1])
+        .reshape([b, nh * nw, patch_size * patch_size * c])
+}
+
+/// Mean squared error of the reconstructed patches, only computed on the masked patches.
+///
+/// # Arguments
+///
+/// * `pred`: Predicted patches. Shape: `[batch_size, num_patches, patch_dim]`.
+/// * `target` - Target patches (see [patchify]). Shape: `[batch_size, num_patches, patch_dim]`.
+/// * `mask` - Binary mask (1 for masked patches). Shape: `[batch_size, num_patches]`.
+pub fn patch_mse_loss<B: Backend>(
+    pred: Tensor<B, 3>,
+    target: Tensor<B, 3>,
+    mask: Tensor<B, 2>,
+) -> Tensor<B, 1> {
+    // Mean loss per patch [B, L]
+    let loss = (pred - target).powf_scalar(2.).mean_dim(2).squeeze::<2>(2);
+    let num_masked = mask.clone().sum().clamp_min(1.);
+
+    (loss * mask).sum() / num_masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    type TestBackend = NdArray<f32>;
+
+    /// MAE with a tiny ViT encoder on 32x32 images, i.e. 16 patches of 8x8 pixels.
+    fn mae(mask_ratio: f64) -> Mae<TestBackend> {
+        let backbone = VitConfig::new(32, 8, 3, 16, 1, 2, 2.);
+        MaeConfig::new(backbone, 8, 1, 2, mask_ratio).init(&Default::default())
+    }
+
+    #[test]
+    fn mask_ratio() {
+        let device = Default::default();
+        let images =
+            Tensor::<TestBackend, 4>::random([2, 3, 32, 32], Distribution::Default, &device);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let (pred, mask) = mae(0.75).forward(images, &mut rng);
+
+        assert_eq!(pred.dims(), [2, 16, 8 * 8 * 3]);
+        assert_eq!(mask.dims(), [2, 16]);
+        // 12 out of 16 patches are masked in each image
+        let masked = mask.sum_dim(1).into_data().to_vec::<f32>().unwrap();
+        assert_eq!(masked, [12., 12.]);
+    }
+
+    #[test]
+    fn masks_are_random() {
+        let device = Default::default();
+        let images = Tensor::<TestBackend, 4>::zeros([2, 3, 32, 32], &device);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let (_, mask) = mae(0.5).forward(images, &mut rng);
+
+        let mask = mask.into_data().to_vec::<f32>().unwrap();
+        assert!(mask.iter().all(|&m| m == 0. || m == 1.));
+        assert_ne!(mask[..16], mask[16..]);
+    }
+
+    #[test]
+    fn no_masking() {
+        let device = Default::default();
+        let images =
+            Tensor::<TestBackend, 4>::random([1, 3, 32, 32], Distribution::Default, &device);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let (pred, mask) = mae(0.).forward(images.clone(), &mut rng);
+
+        assert_eq!(pred.dims(), patchify(images, 8).dims());
+        mask.into_data()
+            .assert_eq(&TensorData::zeros::<f32, _>([1, 16]), false);
+    }
+
+    #[test]
+    #[should_panic = "mask ratio should be in range [0, 1)"]
+    fn full_mask_ratio() {
+        mae(1.);
+    }
+
+    #[test]
+    fn patchify_layout() {
+        let device = Default::default();
+        let images = Tensor::<TestBackend, 1>::arange(0..32, &device)
+            .float()
+            .reshape([1, 2, 4, 4]);
+
+        let patches = patchify(images, 2);
+
+        assert_eq!(patches.dims(), [1, 4, 8]);
+        // First patch, with the channels last
+        let first = patches.slice([0..1, 0..1, 0..8]).into_data();
+        first.assert_eq(
+            &TensorData::from([[[0f32, 16., 1., 17., 4., 20., 5., 21.]]]),
+            false,
+        );
+    }
+
+    #[test]
+    fn loss_on_masked_patches_only() {
+        let device = Default::default();
+        let target = Tensor::<TestBackend, 3>::zeros([1, 2, 4], &device);
+        // Error of 1 on the masked patch and 3 on the visible patch
+        let pred = Tensor::from_floats([[[1., 1., 1., 1.], [3., 3., 3., 3.]]], &device);
+        let mask = Tensor::from_floats([[1., 0.]], &device);
+
+        let loss = patch_mse_loss(pred.clone(), target.clone(), mask);
+        assert_eq!(loss.into_scalar(), 1.);
+
+        let loss = patch_mse_loss(target.clone(), target, Tensor::ones([1, 2], &device));
+        assert_eq!(loss.into_scalar(), 0.);
+    }
+}
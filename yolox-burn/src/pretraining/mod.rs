@@ -0,0 +1,5 @@
+mod dino;
+mod mae;
+
+pub use dino::*;
+pub use mae::*;
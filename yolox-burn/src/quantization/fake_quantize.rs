@@ -0,0 +1,286 @@
+use burn::{
+    module::{Module, RunningState},
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use super::{ObserverType, QatConfig};
+
+/// Momentum of the [moving average](ObserverType::MovingAverage) observer.
+const OBSERVER_MOMENTUM: f32 = 0.01;
+/// Minimum quantization scale, to avoid divisions by zero.
+const MIN_SCALE: f32 = 1e-8;
+
+/// Simulated quantization: the values are quantized to the integer grid and immediately
+/// dequantized, so that the rest of the model runs with floating point values.
+///
+/// The rounding has a zero gradient almost everywhere, so the backward pass uses the
+/// straight-through estimator (STE): the gradient is passed through unchanged for the values
+/// within the quantization range, and is zero for the clipped values.
+///
+/// The quantization range is tracked per tensor, or per channel along `channel_axis`. It is only
+/// updated when autodiff is enabled (i.e., during training).
+#[derive(Module, Debug)]
+pub struct FakeQuantize<B: Backend> {
+    min: RunningState<Tensor<B, 1>>,
+    max: RunningState<Tensor<B, 1>>,
+    /// `1` once the range has been observed, `0` before.
+    observed: RunningState<Tensor<B, 1>>,
+    channel_axis: Option<usize>,
+    num_bits: usize,
+    symmetric: bool,
+    moving_average: bool,
+}
+
+impl<B: Backend> FakeQuantize<B> {
+    /// Create a new fake quantization module.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: Quantization configuration.
+    /// * `channel_axis` - Axis of the channels for per-channel quantization, or `None` for
+    ///   per-tensor quantization.
+    /// * `num_channels` - Number of channels (ignored for per-tensor quantization).
+    /// * `device` - Device on which to store the observed range.
+    pub fn new(
+        config: &QatConfig,
+        channel_axis: Option<usize>,
+        num_channels: usize,
+        device: &Device<B>,
+    ) -> Self {
+        let size = if channel_axis.is_some() {
+            num_channels
+        } else {
+            1
+        };
+
+        Self {
+            min: RunningState::new(Tensor::zeros([size], device)),
+            max: RunningState::new(Tensor::zeros([size], device)),
+            observed: RunningState::new(Tensor::zeros([1], device)),
+            channel_axis,
+            num_bits: config.num_bits,
+            symmetric: config.symmetric,
+            moving_average: config.observer == ObserverType::MovingAverage,
+        }
+    }
+
+    /// Integer range `(qmin, qmax)` of the quantized values.
+    fn quant_range(&self) -> (f32, f32) {
+        if self.symmetric {
+            let qmax = ((1 << (self.num_bits - 1)) - 1) as f32;
+            (-qmax, qmax)
+        } else {
+            (0., ((1 << self.num_bits) - 1) as f32)
+        }
+    }
+
+    /// Minimum and maximum of the values, per channel or per tensor. Shape: `[num_channels]` or
+    /// `[1]`.
+    fn batch_range<const D: usize>(&self, x: Tensor<B, D>) -> (Tensor<B, 1>, Tensor<B, 1>) {
+        match self.channel_axis {
+            Some(axis) => {
+                let [size] = self.min.value().dims();
+                let x = x.swap_dims(0, axis).reshape([size as i32, -1]);
+                (
+                    x.clone().min_dim(1).reshape([size]),
+                    x.max_dim(1).reshape([size]),
+                )
+            }
+            None => (x.clone().min(), x.max()),
+        }
+    }
+
+    /// Update the observed range with the values of the current batch.
+    fn observe<const D: usize>(&self, x: Tensor<B, D>) -> (Tensor<B, 1>, Tensor<B, 1>) {
+        let (batch_min, batch_max) = self.batch_range(x.detach());
+        let (min, max) = (self.min.value(), self.max.value());
+        let observed = self.observed.value();
+
+        let (new_min, new_max) = if self.moving_average {
+            (
+                min.clone() + (batch_min.clone() - min.clone()).mul_scalar(OBSERVER_MOMENTUM),
+                max.clone() + (batch_max.clone() - max.clone()).mul_scalar(OBSERVER_MOMENTUM),
+            )
+        } else {
+            (
+                min.clone()
+                    .mask_where(batch_min.clone().lower(min.clone()), batch_min.clone()),
+                max.clone()
+                    .mask_where(batch_max.clone().greater(max.clone()), batch_max.clone()),
+            )
+        };
+
+        // The first batch initializes the range
+        let not_observed = observed.clone().neg().add_scalar(1.);
+        let min = new_min * observed.clone() + batch_min * not_observed.clone();
+        let max = new_max * observed.clone() + batch_max * not_observed;
+
+        self.min.update(min.clone());
+        self.max.update(max.clone());
+        self.observed.update(observed.ones_like());
+
+        (min, max)
+    }
+
+    /// Quantization scale and zero point computed from the observed range.
+    fn quant_params(&self, min: Tensor<B, 1>, max: Tensor<B, 1>) -> (Tensor<B, 1>, Tensor<B, 1>) {
+        let (qmin, qmax) = self.quant_range();
+        // The range always includes zero, so that zero is exactly representable
+        let min = min.clamp_max(0.);
+        let max = max.clamp_min(0.);
+
+        if self.symmetric {
+            let scale = min
+                .abs()
+                .max_pair(max)
+                .div_scalar(qmax)
+                .clamp_min(MIN_SCALE);
+            let zero_point = scale.zeros_like();
+            (scale, zero_point)
+        } else {
+            let scale = (max - min.clone())
+                .div_scalar(qmax - qmin)
+                .clamp_min(MIN_SCALE);
+            let zero_point = round((min / scale.clone()).neg().add_scalar(qmin), qmax);
+            (scale, zero_point)
+        }
+    }
+
+    /// Reshape per-channel quantization parameters to broadcast along the channel axis.
+    fn broadcast<const D: usize>(&self, params: Tensor<B, 1>) -> Tensor<B, D> {
+        let [size] = params.dims();
+        let mut shape = [1; D];
+        if let Some(axis) = self.channel_axis {
+            shape[axis] = size;
+        }
+
+        params.reshape(shape)
+    }
+
+    /// Quantize and dequantize the values.
+    ///
+    /// # Shapes
+    ///   - input: `[...]`, with the channels along `channel_axis` for per-channel quantization
+    ///   - output: `[...]`
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let (min, max) = if B::ad_enabled() {
+            self.observe(x.clone())
+        } else {
+            (self.min.value(), self.max.value())
+        };
+        let (qmin, qmax) = self.quant_range();
+        let (scale, zero_point) = self.quant_params(min, max);
+        let scale = self.broadcast::<D>(scale);
+        let zero_point = self.broadcast::<D>(zero_point);
+
+        // Quantize to the integer grid and dequantize
+        let q = x.clone().detach() / scale.clone() + zero_point.clone();
+        let in_range = q
+            .clone()
+            .greater_equal_elem(qmin - 0.5)
+            .float()
+            .mul(q.clone().lower_equal_elem(qmax + 0.5).float());
+        let q = round(q.clamp(qmin, qmax), qmax);
+        let dequantized = (q - zero_point) * scale;
+
+        // Straight-through estimator: identity gradient within the quantization range
+        let x = x * in_range;
+        x.clone() + (dequantized - x).detach()
+    }
+}
+
+/// Round to the nearest integer the values in the range `[-bound, bound]`.
+fn round<B: Backend, const D: usize>(x: Tensor<B, D>, bound: f32) -> Tensor<B, D> {
+    // Floor through the truncation of positive values
+    let shift = 2. * bound + 1.;
+    x.add_scalar(shift + 0.5).int().float().sub_scalar(shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::{Autodiff, NdArray},
+        tensor::TensorData,
+    };
+
+    type TestBackend = NdArray<f32>;
+    type TrainingBackend = Autodiff<TestBackend>;
+
+    /// Check that each value is a multiple of its quantization step.
+    fn assert_multiples(values: &[f32], steps: &[f32]) {
+        for (value, step) in values.iter().zip(steps) {
+            let q = value / step;
+            assert!(
+                (q - q.round()).abs() < 1e-3,
+                "{value} is not a multiple of {step}"
+            );
+        }
+    }
+
+    #[test]
+    fn fake_quantize_straight_through_gradient() {
+        let device = Default::default();
+        let quantizer = FakeQuantize::<TrainingBackend>::new(&QatConfig::new(8), None, 1, &device);
+        let x = Tensor::<TrainingBackend, 1>::from_floats([-1.0, -0.3, 0.2, 0.7, 1.0], &device)
+            .require_grad();
+
+        let grads = quantizer.forward(x.clone()).sum().backward();
+
+        x.grad(&grads)
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([1.0f32, 1.0, 1.0, 1.0, 1.0]), false);
+    }
+
+    #[test]
+    fn fake_quantize_clipped_gradient() {
+        let device = Default::default();
+        let config = QatConfig::new(8).with_observer(ObserverType::MovingAverage);
+        let quantizer = FakeQuantize::<TrainingBackend>::new(&config, None, 1, &device);
+        quantizer.forward(Tensor::<TrainingBackend, 1>::from_floats(
+            [-1.0, 1.0],
+            &device,
+        ));
+
+        // The range only moves to [-1.03, 1.03], so the outliers are clipped
+        let x = Tensor::<TrainingBackend, 1>::from_floats([-4.0, 0.5, 4.0], &device).require_grad();
+        let grads = quantizer.forward(x.clone()).sum().backward();
+
+        x.grad(&grads)
+            .unwrap()
+            .into_data()
+            .assert_eq(&TensorData::from([0.0f32, 1.0, 0.0]), false);
+    }
+
+    #[test]
+    fn fake_quantize_scale_multiples() {
+        let device = Default::default();
+        let quantizer = FakeQuantize::<TrainingBackend>::new(&QatConfig::new(8), None, 1, &device);
+        let x = Tensor::<TrainingBackend, 1>::from_floats([-2.0, -0.37, 0.11, 0.93, 1.5], &device);
+
+        let output = quantizer.forward(x).into_data().to_vec::<f32>().unwrap();
+
+        // Symmetric 8-bit quantization of [-2, 2]
+        let step = 2. / 127.;
+        assert_multiples(&output, &[step; 5]);
+        assert!((output[0] + 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fake_quantize_per_channel_scale_multiples() {
+        let device = Default::default();
+        let quantizer =
+            FakeQuantize::<TrainingBackend>::new(&QatConfig::new(4), Some(0), 2, &device);
+        let x = Tensor::<TrainingBackend, 2>::from_floats(
+            [[0.1, -0.7, 0.35], [2.0, -5.0, 3.3]],
+            &device,
+        );
+
+        let output = quantizer.forward(x).into_data().to_vec::<f32>().unwrap();
+
+        // Symmetric 4-bit quantization of [-0.7, 0.7] and [-5, 5]
+        let (step_0, step_1) = (0.7 / 7., 5. / 7.);
+        assert_multiples(&output, &[step_0, step_0, step_0, step_1, step_1, step_1]);
+    }
+}
@@ -0,0 +1,138 @@
+use alloc::vec::Vec;
+use burn::{
+    module::{Module, Param, ParamId},
+    nn::{conv::Conv2d, Linear},
+    tensor::{backend::Backend, Tensor},
+};
+
+use super::{FakeQuantize, QatConfig};
+
+/// [2D convolution](Conv2d) with [fake quantization](FakeQuantize) of the kernel (per output
+/// channel) and of the input activations (per tensor).
+#[derive(Module, Debug)]
+pub struct QatConv2d<B: Backend> {
+    conv: Conv2d<B>,
+    weight_quantizer: FakeQuantize<B>,
+    activation_quantizer: FakeQuantize<B>,
+}
+
+impl<B: Backend> QatConv2d<B> {
+    /// Wrap a convolution for quantization-aware training.
+    pub fn from_conv(conv: Conv2d<B>, config: &QatConfig) -> Self {
+        let device = conv.weight.device();
+        let [out_channels, _, _, _] = conv.weight.dims();
+
+        Self {
+            weight_quantizer: FakeQuantize::new(config, Some(0), out_channels, &device),
+            activation_quantizer: FakeQuantize::new(config, None, 1, &device),
+            conv,
+        }
+    }
+
+    /// # Shapes
+    ///   - input: `[batch_size, in_channels, height, width]`
+    ///   - output: `[batch_size, out_channels, height_out, width_out]`
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.activation_quantizer.forward(x);
+        let weight = self.weight_quantizer.forward(self.conv.weight.val());
+
+        // Same convolution with the fake quantized kernel. The parameter is not registered as
+        // a leaf, so the gradient flows back to the original kernel.
+        let mut conv = self.conv.clone();
+        conv.weight = Param::initialized(ParamId::new(), weight);
+        conv.forward(x)
+    }
+
+    /// Unwrap the convolution, with its (not quantized) trained parameters.
+    pub fn into_conv(self) -> Conv2d<B> {
+        self.conv
+    }
+}
+
+/// [Linear layer](Linear) with [fake quantization](FakeQuantize) of the weight (per output
+/// feature) and of the input activations (per tensor).
+#[derive(Module, Debug)]
+pub struct QatLinear<B: Backend> {
+    linear: Linear<B>,
+    weight_quantizer: FakeQuantize<B>,
+    activation_quantizer: FakeQuantize<B>,
+}
+
+impl<B: Backend> QatLinear<B> {
+    /// Wrap a linear layer for quantization-aware training.
+    pub fn from_linear(linear: Linear<B>, config: &QatConfig) -> Self {
+        let device = linear.weight.device();
+        // The weight has shape [d_input, d_output]
+        let [_, d_output] = linear.weight.dims();
+
+        Self {
+            weight_quantizer: FakeQuantize::new(config, Some(1), d_output, &device),
+            activation_quantizer: FakeQuantize::new(config, None, 1, &device),
+            linear,
+        }
+    }
+
+    /// # Shapes
+    ///   - input: `[..., d_input]`
+    ///   - output: `[..., d_output]`
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let x = self.activation_quantizer.forward(x);
+        let weight = self.weight_quantizer.forward(self.linear.weight.val());
+
+        let mut linear = self.linear.clone();
+        linear.weight = Param::initialized(ParamId::new(), weight);
+        linear.forward(x)
+    }
+
+    /// Unwrap the linear layer, with its (not quantized) trained parameters.
+    pub fn into_linear(self) -> Linear<B> {
+        self.linear
+    }
+}
+
+/// Layers which can be converted to their quantization-aware training variant.
+///
+/// Burn modules can only be traversed to map their tensors, not to change the type of their
+/// sub-modules, so the conversion is only implemented for the [convolution](Conv2d) and
+/// [linear](Linear) layers (and their collections) and returns a different module type. The
+/// blocks and models of the crate are not converted: models have to be built from the converted
+/// layers to be trained with quantization.
+pub trait PrepareQat<B: Backend>: Module<B> {
+    /// Quantization-aware training variant of the module.
+    type Qat: Module<B>;
+
+    /// Convert the module to its quantization-aware training variant.
+    fn prepare_qat(self, config: &QatConfig) -> Self::Qat;
+}
+
+impl<B: Backend> PrepareQat<B> for Conv2d<B> {
+    type Qat = QatConv2d<B>;
+
+    fn prepare_qat(self, config: &QatConfig) -> Self::Qat {
+        QatConv2d::from_conv(self, config)
+    }
+}
+
+impl<B: Backend> PrepareQat<B> for Linear<B> {
+    type Qat = QatLinear<B>;
+
+    fn prepare_qat(self, config: &QatConfig) -> Self::Qat {
+        QatLinear::from_linear(self, config)
+    }
+}
+
+impl<B: Backend, M: PrepareQat<B>> PrepareQat<B> for Vec<M> {
+    type Qat = Vec<M::Qat>;
+
+    fn prepare_qat(self, config: &QatConfig) -> Self::Qat {
+        self.into_iter().map(|m| m.prepare_qat(config)).collect()
+    }
+}
+
+impl<B: Backend, M: PrepareQat<B>> PrepareQat<B> for Option<M> {
+    type Qat = Option<M::Qat>;
+
+    fn prepare_qat(self, config: &QatConfig) -> Self::Qat {
+        self.map(|m| m.prepare_qat(config))
+    }
+}
@@ -0,0 +1,59 @@
+//! Quantization-aware training (QAT), which simulates the quantization of the weights and
+//! activations during training so that the model learns to be robust to the quantization error.
+mod fake_quantize;
+mod layers;
+
+pub use fake_quantize::*;
+pub use layers::*;
+
+/// Observer used to track the range of the quantized values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObserverType {
+    /// Running minimum and maximum of all the observed values.
+    #[default]
+    MinMax,
+    /// Exponential moving average of the minimum and maximum of each batch, which is less
+    /// sensitive to outliers.
+    MovingAverage,
+}
+
+/// Quantization-aware training configuration.
+#[derive(Debug, Clone)]
+pub struct QatConfig {
+    num_bits: usize,
+    symmetric: bool,
+    observer: ObserverType,
+}
+
+impl QatConfig {
+    /// Create a new instance of the quantization-aware training [config](QatConfig), with
+    /// symmetric quantization and a [min-max](ObserverType::MinMax) observer.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_bits`: Bit-width of the quantized values (4 or 8).
+    pub fn new(num_bits: usize) -> Self {
+        assert!(
+            num_bits == 4 || num_bits == 8,
+            "only 4-bit and 8-bit quantization are supported"
+        );
+
+        Self {
+            num_bits,
+            symmetric: true,
+            observer: ObserverType::MinMax,
+        }
+    }
+
+    /// Use symmetric quantization (zero point fixed to 0) or asymmetric quantization.
+    pub fn with_symmetric(mut self, symmetric: bool) -> Self {
+        self.symmetric = symmetric;
+        self
+    }
+
+    /// Set the observer used to track the range of the quantized values.
+    pub fn with_observer(mut self, observer: ObserverType) -> Self {
+        self.observer = observer;
+        self
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use burn::{
+    module::Module,
+    record::{FullPrecisionSettings, NamedMpkFileRecorder, Record, Recorder, RecorderError},
+    tensor::{backend::Backend, Device},
+};
+
+/// Extension added to the checkpoint files by the [recorder](NamedMpkFileRecorder).
+const CHECKPOINT_EXTENSION: &str = "mpk";
+
+/// Validation metric used to select the best checkpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckpointMetric {
+    /// Metric to maximize (e.g., mAP), identified by its name.
+    HigherIsBetter(String),
+    /// Metric to minimize (e.g., the validation loss), identified by its name.
+    LowerIsBetter(String),
+}
+
+impl CheckpointMetric {
+    /// Name of the metric.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::HigherIsBetter(name) | Self::LowerIsBetter(name) => name,
+        }
+    }
+
+    /// Whether `value` is better than `best`.
+    fn is_better(&self, value: f64, best: f64) -> bool {
+        match self {
+            Self::HigherIsBetter(_) => value > best,
+            Self::LowerIsBetter(_) => value < best,
+        }
+    }
+}
+
+/// Error type for checkpoint saving and loading.
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// Failed to create the checkpoint directory or to remove an old checkpoint.
+    Io(std::io::Error),
+    /// Failed to save or load a record.
+    Record(RecorderError),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Failed to access checkpoint file: {err}"),
+            Self::Record(err) => write!(f, "Failed to save or load record: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<RecorderError> for CheckpointError {
+    fn from(err: RecorderError) -> Self {
+        Self::Record(err)
+    }
+}
+
+/// Saved checkpoint.
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    epoch: usize,
+    metric_value: f64,
+}
+
+/// Save the model (and optimizer) checkpoints whenever the validation metric improves.
+///
+/// Only the `keep_last_n` most recent checkpoints are kept on disk. Since a checkpoint is only
+/// saved when the metric improves, the most recent checkpoint is always the best one.
+///
+/// The checkpoints of epoch `e` are saved as `model-{e}.mpk` and `optimizer-{e}.mpk` in the save
+/// directory.
+#[derive(Debug)]
+pub struct CheckpointManager {
+    save_dir: PathBuf,
+    metric: CheckpointMetric,
+    keep_last_n: usize,
+    checkpoints: VecDeque<Checkpoint>,
+    recorder: NamedMpkFileRecorder<FullPrecisionSettings>,
+}
+
+impl CheckpointManager {
+    /// Create a new checkpoint manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `save_dir`: Directory of the checkpoints, created if needed.
+    /// * `metric` - Validation metric used to select the best checkpoint.
+    /// * `keep_last_n` - Number of checkpoints kept on disk (at least 1).
+    pub fn new(save_dir: &Path, metric: CheckpointMetric, keep_last_n: usize) -> Self {
+        assert!(keep_last_n > 0, "at least one checkpoint should be kept");
+
+        Self {
+            save_dir: save_dir.to_path_buf(),
+            metric,
+            keep_last_n,
+            checkpoints: VecDeque::new(),
+            recorder: NamedMpkFileRecorder::new(),
+        }
+    }
+
+    /// Validation metric used to select the best checkpoint.
+    pub fn metric(&self) -> &CheckpointMetric {
+        &self.metric
+    }
+
+    /// Epoch and metric value of the best checkpoint, if any.
+    pub fn best(&self) -> Option<(usize, f64)> {
+        self.checkpoints
+            .back()
+            .map(|checkpoint| (checkpoint.epoch, checkpoint.metric_value))
+    }
+
+    fn model_path(&self, epoch: usize) -> PathBuf {
+        self.save_dir.join(format!("model-{epoch}"))
+    }
+
+    fn optimizer_path(&self, epoch: usize) -> PathBuf {
+        self.save_dir.join(format!("optimizer-{epoch}"))
+    }
+
+    /// Save the model and the optimizer state if the metric improved, and remove the oldest
+    /// checkpoints beyond `keep_last_n`.
+    ///
+    /// # Arguments
+    ///
+    /// * `model`: Model to save.
+    /// * `optimizer_record` - Optimizer state (e.g., [Optimizer::to_record]).
+    /// * `epoch` - Current epoch.
+    /// * `metric_value` - Value of the validation metric at the current epoch.
+    ///
+    /// # Returns
+    ///
+    /// Whether a checkpoint was saved.
+    ///
+    /// [Optimizer::to_record]: burn::optim::Optimizer::to_record
+    pub fn maybe_save<B: Backend, M: Module<B>, R: Record<B>>(
+        &mut self,
+        model: &M,
+        optimizer_record: R,
+        epoch: usize,
+        metric_value: f64,
+    ) -> Result<bool, CheckpointError> {
+        if let Some((_, best)) = self.best() {
+            if !self.metric.is_better(metric_value, best) {
+                return Ok(false);
+            }
+        }
+
+        fs::create_dir_all(&self.save_dir)?;
+        model
+            .clone()
+            .save_file(self.model_path(epoch), &self.recorder)?;
+        Recorder::<B>::record(&self.recorder, optimizer_record, self.optimizer_path(epoch))?;
+        self.checkpoints.push_back(Checkpoint {
+            epoch,
+            metric_value,
+        });
+
+        // Remove the oldest checkpoints
+        while self.checkpoints.len() > self.keep_last_n {
+            let checkpoint = self.checkpoints.pop_front().unwrap();
+            for path in [
+                self.model_path(checkpoint.epoch),
+                self.optimizer_path(checkpoint.epoch),
+            ] {
+                fs::remove_file(path.with_extension(CHECKPOINT_EXTENSION))?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Load the parameters of the best checkpoint into the model.
+    ///
+    /// The model should be initialized from the same configuration as the saved model.
+    ///
+    /// # Returns
+    ///
+    /// The model with the loaded parameters, or `None` if no checkpoint has been saved.
+    pub fn load_best<B: Backend, M: Module<B>>(
+        &self,
+        model: M,
+        device: &Device<B>,
+    ) -> Option<Result<M, CheckpointError>> {
+        let (epoch, _) = self.best()?;
+
+        Some(
+            model
+                .load_file(self.model_path(epoch), &self.recorder, device)
+                .map_err(CheckpointError::from),
+        )
+    }
+
+    /// Load the optimizer state of the best checkpoint.
+    ///
+    /// # Returns
+    ///
+    /// The optimizer record, or `None` if no checkpoint has been saved.
+    pub fn load_best_optimizer<B: Backend, R: Record<B>>(
+        &self,
+        device: &Device<B>,
+    ) -> Option<Result<R, CheckpointError>> {
+        let (epoch, _) = self.best()?;
+
+        Some(
+            Recorder::<B>::load(&self.recorder, self.optimizer_path(epoch), device)
+                .map_err(CheckpointError::from),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{
+        backend::NdArray,
+        nn::{Linear, LinearConfig, LinearRecord},
+        tensor::Tensor,
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yolox-burn-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn checkpoint_files(dir: &Path) -> Vec<String> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn checkpoint_manager_keeps_best() {
+        let device = Default::default();
+        let dir = temp_dir("checkpoints");
+        let metric = CheckpointMetric::HigherIsBetter("mAP".into());
+        let mut manager = CheckpointManager::new(&dir, metric, 1);
+
+        let models: Vec<Linear<TestBackend>> = (0..3)
+            .map(|_| LinearConfig::new(4, 2).init(&device))
+            .collect();
+        let mut saved = Vec::new();
+        for (epoch, (model, value)) in models.iter().zip([0.5, 0.8, 0.7]).enumerate() {
+            let record = model.clone().into_record();
+            saved.push(manager.maybe_save(model, record, epoch, value).unwrap());
+        }
+
+        assert_eq!(saved, [true, true, false]);
+        assert_eq!(manager.best(), Some((1, 0.8)));
+        assert_eq!(checkpoint_files(&dir), ["model-1.mpk", "optimizer-1.mpk"]);
+
+        // The parameters of the best model are restored
+        let model = LinearConfig::new(4, 2).init::<TestBackend>(&device);
+        let model = manager.load_best(model, &device).unwrap().unwrap();
+        let x = Tensor::<TestBackend, 2>::ones([1, 4], &device);
+        model
+            .forward(x.clone())
+            .into_data()
+            .assert_approx_eq(&models[1].forward(x).into_data(), 5);
+
+        let record: LinearRecord<TestBackend> =
+            manager.load_best_optimizer(&device).unwrap().unwrap();
+        let [d_input, d_output] = record.weight.val().dims();
+        assert_eq!([d_input, d_output], [4, 2]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checkpoint_manager_keeps_last_n() {
+        let device = Default::default();
+        let dir = temp_dir("checkpoints-last-n");
+        let metric = CheckpointMetric::LowerIsBetter("loss".into());
+        let mut manager = CheckpointManager::new(&dir, metric, 2);
+        let model = LinearConfig::new(4, 2).init::<TestBackend>(&device);
+
+        for (epoch, value) in [1.0, 0.9, 0.95, 0.6].into_iter().enumerate() {
+            let record = model.clone().into_record();
+            manager.maybe_save(&model, record, epoch, value).unwrap();
+        }
+
+        assert_eq!(manager.best(), Some((3, 0.6)));
+        assert_eq!(
+            checkpoint_files(&dir),
+            [
+                "model-1.mpk",
+                "model-3.mpk",
+                "optimizer-1.mpk",
+                "optimizer-3.mpk"
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checkpoint_manager_without_checkpoint() {
+        let device = Default::default();
+        let metric = CheckpointMetric::HigherIsBetter("mAP".into());
+        let manager = CheckpointManager::new(&temp_dir("checkpoints-empty"), metric, 1);
+        let model = LinearConfig::new(4, 2).init::<TestBackend>(&device);
+
+        assert_eq!(manager.best(), None);
+        assert!(manager.load_best(model, &device).is_none());
+    }
+
+    #[test]
+    #[should_panic = "at least one checkpoint should be kept"]
+    fn checkpoint_manager_keep_none() {
+        let metric = CheckpointMetric::HigherIsBetter("mAP".into());
+        CheckpointManager::new(&temp_dir("checkpoints-none"), metric, 0);
+    }
+}
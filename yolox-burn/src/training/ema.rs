@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use burn::{
+    module::{AutodiffModule, Module, ModuleMapper, ModuleVisitor, ParamId},
+    tensor::{
+        backend::{AutodiffBackend, Backend},
+        Tensor,
+    },
+};
+
+/// Exponential moving average (EMA) of the model parameters.
+///
+/// The averaged parameters usually generalize better than the raw parameters and are used for
+/// evaluation. As in YOLOX, the decay ramps up during the first updates:
+/// `decay * (1 - exp(-updates / 2000))`.
+#[derive(Debug, Clone)]
+pub struct EmaModel<M> {
+    model: M,
+    decay: f64,
+    updates: usize,
+}
+
+impl<M> EmaModel<M> {
+    /// Create a new EMA from the (inference) model.
+    pub fn new(model: M, decay: f64) -> Self {
+        Self {
+            model,
+            decay,
+            updates: 0,
+        }
+    }
+
+    /// The averaged model.
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Consume the EMA and return the averaged model.
+    pub fn into_model(self) -> M {
+        self.model
+    }
+
+    /// Move the averaged model to the specified device.
+    pub fn to_device<B: Backend>(mut self, device: &B::Device) -> Self
+    where
+        M: Module<B>,
+    {
+        self.model = crate::utils::device::to_device(self.model, device);
+        self
+    }
+
+    /// Current decay value, accounting for the ramp-up.
+    fn current_decay(&self) -> f64 {
+        self.decay * (1. - (-(self.updates as f64) / 2000.).exp())
+    }
+
+    /// Update the averaged parameters with the current parameters of the trained model.
+    pub fn update<B, T>(&mut self, model: &T)
+    where
+        B: AutodiffBackend,
+        T: AutodiffModule<B, InnerModule = M>,
+        M: Module<B::InnerBackend>,
+    {
+        self.updates += 1;
+
+        let mut collector = ParamCollector::default();
+        model.valid().visit(&mut collector);
+
+        let mut mapper = EmaMapper {
+            params: collector.params,
+            decay: self.current_decay(),
+        };
+        self.model = self.model.clone().map(&mut mapper);
+    }
+}
+
+/// Collect the (flattened) float parameters of a module.
+struct ParamCollector<B: Backend> {
+    params: HashMap<ParamId, Tensor<B, 1>>,
+}
+
+impl<B: Backend> Default for ParamCollector<B> {
+    fn default() -> Self {
+        Self {
+            params: HashMap::new(),
+        }
+    }
+}
+
+impl<B: Backend> ModuleVisitor<B> for ParamCollector<B> {
+    fn visit_float<const D: usize>(&mut self, id: &ParamId, tensor: &Tensor<B, D>) {
+        let numel = tensor.shape().num_elements();
+        self.params
+            .insert(id.clone(), tensor.clone().reshape([numel]));
+    }
+}
+
+/// Blend the module parameters with the collected parameters.
+struct EmaMapper<B: Backend> {
+    params: HashMap<ParamId, Tensor<B, 1>>,
+    decay: f64,
+}
+
+impl<B: Backend> ModuleMapper<B> for EmaMapper<B> {
+    fn map_float<const D: usize>(&mut self, id: &ParamId, tensor: Tensor<B, D>) -> Tensor<B, D> {
+        match self.params.remove(id) {
+            Some(param) => {
+                let param = param.reshape(tensor.shape());
+                tensor.mul_scalar(self.decay) + param.mul_scalar(1. - self.decay)
+            }
+            None => tensor,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod checkpoint;
+pub mod ema;
+pub mod schedulers;
+#[cfg(feature = "dataset")]
+mod trainer;
+
+#[cfg(feature = "dataset")]
+pub use trainer::*;
@@ -0,0 +1,354 @@
+use core::{f64::consts::PI, marker::PhantomData};
+
+use burn::{
+    module::AutodiffModule,
+    optim::{GradientsParams, Optimizer},
+    tensor::backend::AutodiffBackend,
+};
+
+/// Learning rate scheduler.
+pub trait LRScheduler {
+    /// Learning rate for the given epoch (starting at 0).
+    fn step(&self, epoch: usize) -> f64;
+}
+
+/// Linear learning rate warmup from `start_lr` to `base_lr` over `warmup_epochs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearWarmup {
+    start_lr: f64,
+    base_lr: f64,
+    warmup_epochs: usize,
+}
+
+impl LinearWarmup {
+    /// Create a new linear warmup scheduler.
+    pub fn new(start_lr: f64, base_lr: f64, warmup_epochs: usize) -> Self {
+        Self {
+            start_lr,
+            base_lr,
+            warmup_epochs,
+        }
+    }
+}
+
+impl LRScheduler for LinearWarmup {
+    fn step(&self, epoch: usize) -> f64 {
+        if epoch >= self.warmup_epochs {
+            return self.base_lr;
+        }
+
+        let progress = epoch as f64 / self.warmup_epochs as f64;
+        self.start_lr + (self.base_lr - self.start_lr) * progress
+    }
+}
+
+/// Cosine annealing of the learning rate from `base_lr` to `min_lr` over `num_epochs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CosineAnnealing {
+    base_lr: f64,
+    min_lr: f64,
+    num_epochs: usize,
+}
+
+impl CosineAnnealing {
+    /// Create a new cosine annealing scheduler.
+    pub fn new(base_lr: f64, min_lr: f64, num_epochs: usize) -> Self {
+        Self {
+            base_lr,
+            min_lr,
+            num_epochs,
+        }
+    }
+}
+
+impl LRScheduler for CosineAnnealing {
+    fn step(&self, epoch: usize) -> f64 {
+        if self.num_epochs == 0 || epoch >= self.num_epochs {
+            return self.min_lr;
+        }
+
+        let progress = epoch as f64 / self.num_epochs as f64;
+        self.min_lr + 0.5 * (self.base_lr - self.min_lr) * (1. + f64::cos(PI * progress))
+    }
+}
+
+/// Cosine annealing with linear warmup, as used to train YOLOX.
+///
+/// The learning rate increases linearly from `warmup_start_lr` to `base_lr` during the first
+/// `warmup_epochs`, then decays to `min_lr` following a cosine schedule until `num_epochs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CosineAnnealingWithWarmup {
+    warmup: LinearWarmup,
+    cosine: CosineAnnealing,
+    warmup_epochs: usize,
+}
+
+impl CosineAnnealingWithWarmup {
+    /// Create a new cosine annealing scheduler with linear warmup.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_lr`: Learning rate reached at the end of the warmup.
+    /// * `min_lr` - Learning rate at the end of the schedule.
+    /// * `num_epochs` - Total number of epochs (including warmup).
+    /// * `warmup_epochs` - Number of warmup epochs.
+    /// * `warmup_start_lr` - Initial learning rate of the warmup.
+    pub fn new(
+        base_lr: f64,
+        min_lr: f64,
+        num_epochs: usize,
+        warmup_epochs: usize,
+        warmup_start_lr: f64,
+    ) -> Self {
+        assert!(
+            warmup_epochs <= num_epochs,
+            "warmup epochs should not exceed the total number of epochs"
+        );
+
+        Self {
+            warmup: LinearWarmup::new(warmup_start_lr, base_lr, warmup_epochs),
+            cosine: CosineAnnealing::new(base_lr, min_lr, num_epochs - warmup_epochs),
+            warmup_epochs,
+        }
+    }
+}
+
+impl LRScheduler for CosineAnnealingWithWarmup {
+    fn step(&self, epoch: usize) -> f64 {
+        if epoch < self.warmup_epochs {
+            self.warmup.step(epoch)
+        } else {
+            self.cosine.step(epoch - self.warmup_epochs)
+        }
+    }
+}
+
+/// Decay the learning rate by `gamma` once the epoch reaches one of the milestones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiStepLR {
+    base_lr: f64,
+    milestones: Vec<usize>,
+    gamma: f64,
+}
+
+impl MultiStepLR {
+    /// Create a new multi-step scheduler.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_lr`: Initial learning rate.
+    /// * `milestones` - Epochs at which the learning rate is decayed.
+    /// * `gamma` - Multiplicative decay factor.
+    pub fn new(base_lr: f64, mut milestones: Vec<usize>, gamma: f64) -> Self {
+        milestones.sort_unstable();
+
+        Self {
+            base_lr,
+            milestones,
+            gamma,
+        }
+    }
+}
+
+impl LRScheduler for MultiStepLR {
+    fn step(&self, epoch: usize) -> f64 {
+        let num_decays = self.milestones.iter().filter(|&&m| epoch >= m).count();
+        self.base_lr * self.gamma.powi(num_decays as i32)
+    }
+}
+
+/// [Cosine annealing with warm restarts](https://arxiv.org/abs/1608.03983) (SGDR).
+///
+/// The learning rate follows a cosine decay from `base_lr` to `min_lr` over a cycle of `t_0`
+/// iterations, then restarts at `base_lr`. The length of each cycle is multiplied by `t_mult`
+/// after each restart. The step is a global iteration (or epoch) count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CosineAnnealingWarmRestarts {
+    base_lr: f64,
+    min_lr: f64,
+    t_0: usize,
+    t_mult: usize,
+}
+
+impl CosineAnnealingWarmRestarts {
+    /// Create a new cosine annealing scheduler with warm restarts.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_lr`: Learning rate at the start of each cycle.
+    /// * `min_lr` - Learning rate at the end of each cycle.
+    /// * `t_0` - Number of iterations of the first cycle.
+    /// * `t_mult` - Multiplicative factor of the cycle length after each restart.
+    pub fn new(base_lr: f64, min_lr: f64, t_0: usize, t_mult: usize) -> Self {
+        assert!(
+            t_0 > 0,
+            "the first cycle should have at least one iteration"
+        );
+        assert!(t_mult > 0, "the cycle length factor should be positive");
+
+        Self {
+            base_lr,
+            min_lr,
+            t_0,
+            t_mult,
+        }
+    }
+
+    /// Position within the current cycle and length of the current cycle for a global iteration.
+    pub fn cycle_position(&self, iteration: usize) -> (usize, usize) {
+        if self.t_mult == 1 {
+            return (iteration % self.t_0, self.t_0);
+        }
+
+        let mut t_cur = iteration;
+        let mut t_i = self.t_0;
+        while t_cur >= t_i {
+            t_cur -= t_i;
+            t_i *= self.t_mult;
+        }
+
+        (t_cur, t_i)
+    }
+}
+
+impl LRScheduler for CosineAnnealingWarmRestarts {
+    fn step(&self, iteration: usize) -> f64 {
+        let (t_cur, t_i) = self.cycle_position(iteration);
+        let progress = t_cur as f64 / t_i as f64;
+
+        self.min_lr + 0.5 * (self.base_lr - self.min_lr) * (1. + f64::cos(PI * progress))
+    }
+}
+
+/// Optimizer whose learning rate is set by a [scheduler](LRScheduler) at each step.
+///
+/// Burn optimizers receive the learning rate at each step, so the wrapper keeps track of the
+/// global iteration count and computes the learning rate of the current iteration.
+pub struct SchedulerBurner<B: AutodiffBackend, S: LRScheduler, O> {
+    scheduler: S,
+    optimizer: O,
+    iteration: usize,
+    _backend: PhantomData<B>,
+}
+
+impl<B: AutodiffBackend, S: LRScheduler, O> SchedulerBurner<B, S, O> {
+    /// Wrap an optimizer, starting the schedule at the first iteration.
+    pub fn new(scheduler: S, optimizer: O) -> Self {
+        Self {
+            scheduler,
+            optimizer,
+            iteration: 0,
+            _backend: PhantomData,
+        }
+    }
+
+    /// Learning rate of the current iteration.
+    pub fn lr(&self) -> f64 {
+        self.scheduler.step(self.iteration)
+    }
+
+    /// Number of optimization steps performed so far.
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    /// Set the global iteration count (e.g., when resuming training).
+    pub fn set_iteration(&mut self, iteration: usize) {
+        self.iteration = iteration;
+    }
+
+    /// Update the model parameters with the learning rate of the current iteration, then advance
+    /// the schedule.
+    pub fn step<M>(&mut self, model: M, grads: GradientsParams) -> M
+    where
+        M: AutodiffModule<B>,
+        O: Optimizer<M, B>,
+    {
+        let lr = self.lr();
+        self.iteration += 1;
+
+        self.optimizer.step(lr, model, grads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yolox_schedule() -> CosineAnnealingWithWarmup {
+        CosineAnnealingWithWarmup::new(0.01, 0.0005, 300, 5, 0.)
+    }
+
+    #[test]
+    fn warmup_starts_at_warmup_start_lr() {
+        assert_eq!(yolox_schedule().step(0), 0.);
+        assert_eq!(LinearWarmup::new(0.001, 0.01, 5).step(0), 0.001);
+    }
+
+    #[test]
+    fn warmup_approaches_base_lr() {
+        let scheduler = yolox_schedule();
+
+        let last_warmup = scheduler.step(4);
+        assert!(last_warmup < 0.01);
+        assert!((last_warmup - 0.008).abs() < 1e-12);
+        assert_eq!(scheduler.step(5), 0.01);
+    }
+
+    #[test]
+    fn cosine_ends_at_min_lr() {
+        assert_eq!(yolox_schedule().step(300), 0.0005);
+        assert_eq!(yolox_schedule().step(400), 0.0005);
+        assert_eq!(CosineAnnealing::new(0.01, 0.0005, 10).step(10), 0.0005);
+    }
+
+    #[test]
+    fn cosine_phase_is_monotonic() {
+        let scheduler = yolox_schedule();
+        let lrs: Vec<f64> = (5..=300).map(|epoch| scheduler.step(epoch)).collect();
+
+        assert!(lrs.windows(2).all(|w| w[1] <= w[0]));
+    }
+
+    #[test]
+    fn multi_step_decays_at_milestones() {
+        let scheduler = MultiStepLR::new(0.1, vec![20, 10], 0.1);
+
+        assert_eq!(scheduler.step(0), 0.1);
+        assert_eq!(scheduler.step(9), 0.1);
+        assert!((scheduler.step(10) - 0.01).abs() < 1e-12);
+        assert!((scheduler.step(20) - 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn warm_restarts_periods() {
+        let scheduler = CosineAnnealingWarmRestarts::new(0.1, 0.001, 10, 2);
+
+        let restarts: Vec<usize> = (1..200)
+            .filter(|&iteration| scheduler.cycle_position(iteration).0 == 0)
+            .collect();
+        assert_eq!(restarts, [10, 30, 70, 150]);
+        assert_eq!(scheduler.cycle_position(75), (5, 80));
+    }
+
+    #[test]
+    fn warm_restarts_base_lr_at_restart() {
+        let scheduler = CosineAnnealingWarmRestarts::new(0.1, 0.001, 10, 2);
+
+        for restart in [0, 10, 30, 70, 150] {
+            assert!((scheduler.step(restart) - 0.1).abs() < 1e-12);
+            // Halfway through the cycle, and close to the minimum at the end of the cycle
+            let (_, length) = scheduler.cycle_position(restart);
+            assert!((scheduler.step(restart + length / 2) - 0.0505).abs() < 1e-12);
+            assert!(scheduler.step(restart + length - 1) < 0.005);
+        }
+    }
+
+    #[test]
+    fn warm_restarts_constant_period() {
+        let scheduler = CosineAnnealingWarmRestarts::new(0.1, 0., 4, 1);
+
+        assert_eq!(scheduler.step(8), 0.1);
+        assert!((scheduler.step(2) - scheduler.step(14)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,456 @@
+use burn::{
+    config::Config,
+    grad_clipping::GradientClippingConfig,
+    module::AutodiffModule,
+    optim::{
+        adaptor::OptimizerAdaptor, decay::WeightDecayConfig, momentum::MomentumConfig,
+        GradientsParams, Optimizer, Sgd, SgdConfig,
+    },
+    tensor::{
+        backend::AutodiffBackend,
+        module::interpolate,
+        ops::{InterpolateMode, InterpolateOptions},
+        Device, ElementConversion, Tensor, TensorData,
+    },
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    datasets::coco::{Annotation, CocoDataset},
+    loss::{GroundTruthBoxes, YoloxLoss},
+    metrics::coco_map,
+    model::{postprocess::nms::batch_nms_detections, yolox::Yolox},
+    types::GroundTruth,
+};
+
+use super::{
+    ema::EmaModel,
+    schedulers::{CosineAnnealingWithWarmup, LRScheduler},
+};
+
+/// Thresholds used to compute the detections for evaluation.
+const EVAL_IOU_THRESHOLD: f32 = 0.65;
+const EVAL_SCORE_THRESHOLD: f32 = 0.001;
+const EVAL_MAX_DETECTIONS: usize = 100;
+
+/// [YOLOX trainer](YoloxTrainer) configuration.
+#[derive(Config, Debug)]
+pub struct TrainerConfig {
+    /// Total number of training epochs.
+    #[config(default = 300)]
+    pub num_epochs: usize,
+    /// Learning rate after warmup.
+    #[config(default = 0.01)]
+    pub base_lr: f64,
+    /// Weight decay (L2 penalty).
+    #[config(default = 5e-4)]
+    pub weight_decay: f64,
+    /// Number of images per batch.
+    #[config(default = 16)]
+    pub batch_size: usize,
+    /// Probability to use mosaic augmentation for each sample.
+    #[config(default = 1.0)]
+    pub mosaic_prob: f64,
+    /// Decay of the parameters exponential moving average.
+    #[config(default = 0.9998)]
+    pub ema_decay: f64,
+    /// Number of learning rate warmup epochs.
+    #[config(default = 5)]
+    pub warm_epochs: usize,
+    /// Maximum gradient norm. Gradients are not clipped when `None`.
+    #[config(default = "Some(10.0)")]
+    pub grad_clip: Option<f32>,
+    /// Number of epochs between evaluations on the validation dataset.
+    #[config(default = 10)]
+    pub eval_interval: usize,
+    /// Random seed for shuffling and augmentations.
+    #[config(default = 42)]
+    pub seed: u64,
+}
+
+/// Metrics of a training epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochMetrics {
+    /// Epoch index (starting at 0).
+    pub epoch: usize,
+    /// Learning rate used for the last iteration of the epoch.
+    pub lr: f64,
+    /// Mean training loss.
+    pub loss: f64,
+    /// COCO mAP of the EMA model on the validation dataset, only computed every
+    /// [eval_interval](TrainerConfig::eval_interval) epochs.
+    pub map: Option<f32>,
+}
+
+/// YOLOX training recipe on [COCO](CocoDataset) formatted datasets.
+///
+/// Follows the official recipe: SGD with Nesterov momentum, cosine learning rate schedule with
+/// warmup stepped at every iteration, mosaic augmentation and an
+/// [exponential moving average](EmaModel) of the parameters. The predictions are assigned to the
+/// objects with SimOTA and trained with the [YOLOX loss](YoloxLoss).
+pub struct YoloxTrainer<B: AutodiffBackend> {
+    config: TrainerConfig,
+    model: Option<Yolox<B>>,
+    optimizer: OptimizerAdaptor<Sgd<B::InnerBackend>, Yolox<B>, B>,
+    scheduler: Option<CosineAnnealingWithWarmup>,
+    ema: EmaModel<Yolox<B::InnerBackend>>,
+    validation: Option<CocoDataset>,
+    loss: YoloxLoss,
+    device: Device<B>,
+    epoch: usize,
+    iteration: usize,
+    rng: StdRng,
+}
+
+impl<B: AutodiffBackend> YoloxTrainer<B> {
+    /// Create a new trainer for the model.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: Training settings.
+    /// * `model` - Model to train.
+    /// * `device` - Device on which the model was created.
+    pub fn new(config: TrainerConfig, model: Yolox<B>, device: Device<B>) -> Self {
+        let optimizer = SgdConfig::new()
+            .with_momentum(Some(
+                MomentumConfig::new().with_momentum(0.9).with_nesterov(true),
+            ))
+            .with_weight_decay(Some(WeightDecayConfig::new(config.weight_decay as f32)))
+            .with_gradient_clipping(config.grad_clip.map(GradientClippingConfig::Norm))
+            .init();
+        let ema = EmaModel::new(model.valid(), config.ema_decay);
+        let rng = StdRng::seed_from_u64(config.seed);
+
+        Self {
+            config,
+            model: Some(model),
+            optimizer,
+            scheduler: None,
+            ema,
+            validation: None,
+            loss: YoloxLoss::new(),
+            device,
+            epoch: 0,
+            iteration: 0,
+            rng,
+        }
+    }
+
+    /// Evaluate the model on the validation dataset every
+    /// [eval_interval](TrainerConfig::eval_interval) epochs.
+    pub fn with_validation(mut self, dataset: CocoDataset) -> Self {
+        self.validation = Some(dataset);
+        self
+    }
+
+    /// The trained model.
+    pub fn model(&self) -> &Yolox<B> {
+        self.model.as_ref().unwrap()
+    }
+
+    /// The exponential moving average of the trained model, used for evaluation.
+    pub fn ema_model(&self) -> &Yolox<B::InnerBackend> {
+        self.ema.model()
+    }
+
+    /// Train the model for one epoch over the dataset.
+    ///
+    /// The learning rate schedule is stepped at every iteration, its length is computed from the
+    /// number of batches of the dataset passed to the first call.
+    pub fn train_epoch(&mut self, dataset: &CocoDataset) -> EpochMetrics {
+        let iters_per_epoch = dataset.num_images().div_ceil(self.config.batch_size).max(1);
+        let scheduler = self
+            .scheduler
+            .get_or_insert_with(|| {
+                CosineAnnealingWithWarmup::new(
+                    self.config.base_lr,
+                    self.config.base_lr * 0.05,
+                    self.config.num_epochs * iters_per_epoch,
+                    self.config.warm_epochs * iters_per_epoch,
+                    0.,
+                )
+            })
+            .clone();
+        let mut lr = scheduler.step(self.iteration);
+
+        let mut indices: Vec<usize> = (0..dataset.num_images()).collect();
+        indices.shuffle(&mut self.rng);
+
+        let mut total_loss = 0.;
+        let mut num_batches = 0;
+        for batch in indices.chunks(self.config.batch_size) {
+            lr = scheduler.step(self.iteration);
+            self.iteration += 1;
+
+            let (images, targets): (Vec<_>, Vec<_>) = batch
+                .iter()
+                .map(|&index| self.sample(dataset, index))
+                .unzip();
+            let images: Tensor<B, 4> = Tensor::stack(images, 0);
+            let targets: Vec<_> = targets.iter().map(|anns| self.targets(anns)).collect();
+
+            let model = self.model.take().unwrap();
+            let loss = model.forward_training(images, &targets, &self.loss).loss;
+            total_loss += loss.clone().into_scalar().elem::<f64>();
+            num_batches += 1;
+
+            let grads = GradientsParams::from_grads(loss.backward(), &model);
+            let model = self.optimizer.step(lr, model, grads);
+
+            self.ema.update::<B, _>(&model);
+            self.model = Some(model);
+        }
+
+        let map = match &self.validation {
+            Some(dataset) if (self.epoch + 1) % self.config.eval_interval == 0 => {
+                Some(self.evaluate(dataset))
+            }
+            _ => None,
+        };
+
+        let metrics = EpochMetrics {
+            epoch: self.epoch,
+            lr,
+            loss: total_loss / num_batches.max(1) as f64,
+            map,
+        };
+        self.epoch += 1;
+
+        metrics
+    }
+
+    /// Compute the COCO mAP of the [EMA model](Self::ema_model) on the dataset.
+    pub fn evaluate(&self, dataset: &CocoDataset) -> f32 {
+        let model = self.ema.model();
+        let device = self.device.clone();
+
+        let mut detections = Vec::new();
+        let mut ground_truths = Vec::new();
+        let indices: Vec<usize> = (0..dataset.num_images()).collect();
+        for batch in indices.chunks(self.config.batch_size) {
+            let (images, annotations): (Vec<_>, Vec<_>) = batch
+                .iter()
+                .map(|&index| dataset.get::<B::InnerBackend>(index, &device))
+                .unzip();
+
+            let out = model.forward(Tensor::stack(images, 0));
+            let [batch_size, num_boxes, num_outputs] = out.dims();
+            let boxes = out.clone().slice([0..batch_size, 0..num_boxes, 0..4]);
+            let obj_scores = out.clone().slice([0..batch_size, 0..num_boxes, 4..5]);
+            let cls_scores = out.slice([0..batch_size, 0..num_boxes, 5..num_outputs]);
+
+            let batch_detections = batch_nms_detections(
+                boxes,
+                cls_scores * obj_scores,
+                EVAL_IOU_THRESHOLD.into(),
+                EVAL_SCORE_THRESHOLD.into(),
+                EVAL_MAX_DETECTIONS,
+            );
+
+            for ((&image_id, dets), anns) in batch.iter().zip(batch_detections).zip(annotations) {
+                detections.extend(dets.into_iter().map(|mut det| {
+                    det.image_id = image_id;
+                    det
+                }));
+                ground_truths.extend(anns.into_iter().map(|ann| {
+                    let [x, y, w, h] = ann.box_xywh;
+                    GroundTruth {
+                        image_id,
+                        box_xyxy: [x, y, x + w, y + h],
+                        class_id: ann.category_id,
+                        area: w * h,
+                        is_crowd: false,
+                    }
+                }));
+            }
+        }
+
+        coco_map(&detections, &ground_truths)
+    }
+
+    /// Load a training sample, with mosaic augmentation.
+    ///
+    /// The mosaic has the size of the dataset images. For odd sizes, the tiles of the first row
+    /// and column are one pixel larger than the others.
+    fn sample(&mut self, dataset: &CocoDataset, index: usize) -> (Tensor<B, 3>, Vec<Annotation>) {
+        if self.rng.gen::<f64>() >= self.config.mosaic_prob {
+            return dataset.get(index, &self.device);
+        }
+
+        // Combine 4 images downscaled by about half in a 2x2 grid
+        let [height, width] = dataset.image_size();
+        let tile_heights = [height.div_ceil(2), height / 2];
+        let tile_widths = [width.div_ceil(2), width / 2];
+        let others: Vec<usize> = (0..3)
+            .map(|_| self.rng.gen_range(0..dataset.num_images()))
+            .collect();
+        let mut tiles = Vec::with_capacity(4);
+        let mut annotations = Vec::new();
+        for (i, index) in [index].into_iter().chain(others).enumerate() {
+            let (image, anns) = dataset.get::<B>(index, &self.device);
+            let [channels, image_h, image_w] = image.dims();
+            let (h, w) = (tile_heights[i / 2], tile_widths[i % 2]);
+            let image = interpolate(
+                image.reshape([1, channels, image_h, image_w]),
+                [h, w],
+                InterpolateOptions::new(InterpolateMode::Bilinear),
+            )
+            .reshape([channels, h, w]);
+            tiles.push(image);
+
+            let (scale_x, scale_y) = (w as f32 / image_w as f32, h as f32 / image_h as f32);
+            let offset_x = (i % 2 * tile_widths[0]) as f32;
+            let offset_y = (i / 2 * tile_heights[0]) as f32;
+            annotations.extend(anns.into_iter().map(|ann| {
+                let [x, y, bw, bh] = ann.box_xywh;
+                Annotation {
+                    box_xywh: [
+                        x * scale_x + offset_x,
+                        y * scale_y + offset_y,
+                        bw * scale_x,
+                        bh * scale_y,
+                    ],
+                    ..ann
+                }
+            }));
+        }
+
+        let bottom = Tensor::cat(tiles.split_off(2), 2);
+        let top = Tensor::cat(tiles, 2);
+
+        (Tensor::cat(vec![top, bottom], 1), annotations)
+    }
+
+    /// Convert the annotations of an image to ground truth boxes `(cx, cy, w, h)`.
+    fn targets(&self, annotations: &[Annotation]) -> GroundTruthBoxes<B> {
+        let num_boxes = annotations.len();
+        let (boxes, labels): (Vec<_>, Vec<_>) = annotations
+            .iter()
+            .map(|ann| {
+                let [x, y, w, h] = ann.box_xywh;
+                ([x + w / 2., y + h / 2., w, h], ann.category_id as i64)
+            })
+            .unzip();
+
+        GroundTruthBoxes {
+            boxes: Tensor::from_data(
+                TensorData::new(boxes.concat(), [num_boxes, 4]).convert::<B::FloatElem>(),
+                &self.device,
+            ),
+            labels: Tensor::from_data(
+                TensorData::new(labels, [num_boxes]).convert::<B::IntElem>(),
+                &self.device,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::coco::{CocoAnnotation, CocoCategory, CocoImage};
+    use burn::backend::{Autodiff, NdArray};
+    use std::path::PathBuf;
+
+    type TestBackend = Autodiff<NdArray<f32>>;
+
+    /// Synthetic dataset of 32x32 images with a bright square object.
+    fn synthetic_dataset(name: &str, num_images: usize) -> CocoDataset {
+        let dir: PathBuf = std::env::temp_dir().join(format!("yolox-burn-trainer-{name}"));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut images = Vec::new();
+        let mut annotations = Vec::new();
+        for id in 0..num_images {
+            let file_name = format!("{id}.png");
+            image::RgbImage::from_fn(32, 32, |x, y| {
+                let inside = (8..20).contains(&x) && (8..20).contains(&y);
+                image::Rgb(if inside { [255, 255, 255] } else { [0, 0, 0] })
+            })
+            .save(dir.join(&file_name))
+            .unwrap();
+
+            images.push(CocoImage {
+                id,
+                file_name,
+                width: 32,
+                height: 32,
+            });
+            annotations.push(CocoAnnotation {
+                image_id: id,
+                category_id: 1,
+                bbox: [8., 8., 12., 12.],
+                iscrowd: 0,
+            });
+        }
+        let categories = vec![CocoCategory {
+            id: 1,
+            name: "square".into(),
+        }];
+
+        CocoDataset::from_parts(&dir, images, annotations, categories).with_image_size(32, 32)
+    }
+
+    fn trainer(config: TrainerConfig) -> YoloxTrainer<TestBackend> {
+        let device = Default::default();
+        let model = Yolox::yolox_nano(1, &device);
+        YoloxTrainer::new(config, model, device)
+    }
+
+    #[test]
+    fn loss_decreases() {
+        let dataset = synthetic_dataset("loss", 1);
+        let config = TrainerConfig::new()
+            .with_num_epochs(5)
+            .with_warm_epochs(0)
+            .with_batch_size(1)
+            .with_mosaic_prob(0.);
+        let mut trainer = trainer(config);
+
+        let losses: Vec<f64> = (0..5).map(|_| trainer.train_epoch(&dataset).loss).collect();
+
+        assert!(losses.iter().all(|loss| loss.is_finite()));
+        assert!(
+            losses[4] < losses[0],
+            "loss should decrease, got {losses:?}"
+        );
+    }
+
+    #[test]
+    fn epoch_metrics() {
+        let dataset = synthetic_dataset("metrics", 2);
+        let config = TrainerConfig::new()
+            .with_num_epochs(2)
+            .with_warm_epochs(1)
+            .with_batch_size(1)
+            .with_eval_interval(1);
+        let mut trainer = trainer(config).with_validation(dataset.clone());
+
+        let first = trainer.train_epoch(&dataset);
+        let second = trainer.train_epoch(&dataset);
+
+        // Two iterations per epoch: warmup over the first epoch, then cosine decay
+        assert_eq!((first.epoch, second.epoch), (0, 1));
+        assert!((first.lr - 0.005).abs() < 1e-9, "{}", first.lr);
+        assert!((second.lr - 0.00525).abs() < 1e-9, "{}", second.lr);
+        assert!(second.map.is_some());
+    }
+
+    #[test]
+    fn mosaic_odd_image_size() {
+        let dataset = synthetic_dataset("mosaic", 1).with_image_size(33, 31);
+        let mut trainer = trainer(TrainerConfig::new().with_mosaic_prob(1.));
+
+        let (image, annotations) = trainer.sample(&dataset, 0);
+
+        assert_eq!(image.dims(), [3, 33, 31]);
+        assert_eq!(annotations.len(), 4);
+        // Top-left tile of 17x16 pixels and bottom-right tile of 16x15 pixels
+        let expected = [[4., 4.25, 6., 6.375], [19.75, 21., 5.625, 6.]];
+        for (ann, expected) in [&annotations[0], &annotations[3]].into_iter().zip(expected) {
+            for (a, e) in ann.box_xywh.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-4, "{:?} != {expected:?}", ann.box_xywh);
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Geometric transforms of bounding boxes, matching the transforms of their image.
+//!
+//! Bounding boxes are `(xmin, ymin, xmax, ymax)` in pixel coordinates with shape
+//! `[num_boxes, 4]`.
+use alloc::{vec, vec::Vec};
+use burn::tensor::{backend::Backend, Bool, Int, Tensor, TensorData};
+
+/// Transform the bounding boxes of an image along with the image.
+pub struct BoxTransformer;
+
+impl BoxTransformer {
+    /// Flip the boxes of an image flipped horizontally.
+    pub fn horizontal_flip<B: Backend>(boxes: Tensor<B, 2>, image_width: f32) -> Tensor<B, 2> {
+        let [xmin, ymin, xmax, ymax] = columns(boxes);
+        Tensor::cat(
+            vec![
+                xmax.neg().add_scalar(image_width),
+                ymin,
+                xmin.neg().add_scalar(image_width),
+                ymax,
+            ],
+            1,
+        )
+    }
+
+    /// Flip the boxes of an image flipped vertically.
+    pub fn vertical_flip<B: Backend>(boxes: Tensor<B, 2>, image_height: f32) -> Tensor<B, 2> {
+        let [xmin, ymin, xmax, ymax] = columns(boxes);
+        Tensor::cat(
+            vec![
+                xmin,
+                ymax.neg().add_scalar(image_height),
+                xmax,
+                ymin.neg().add_scalar(image_height),
+            ],
+            1,
+        )
+    }
+
+    /// Scale the boxes of a resized image.
+    pub fn scale<B: Backend>(boxes: Tensor<B, 2>, scale_x: f32, scale_y: f32) -> Tensor<B, 2> {
+        let device = boxes.device();
+        boxes
+            * Tensor::<B, 1>::from_floats([scale_x, scale_y, scale_x, scale_y], &device).unsqueeze()
+    }
+
+    /// Translate the boxes of a shifted (e.g., padded or cropped) image.
+    pub fn translate<B: Backend>(boxes: Tensor<B, 2>, tx: f32, ty: f32) -> Tensor<B, 2> {
+        let device = boxes.device();
+        boxes + Tensor::<B, 1>::from_floats([tx, ty, tx, ty], &device).unsqueeze()
+    }
+
+    /// Clip the boxes to the image and remove the degenerate boxes (i.e., with an empty area after
+    /// clipping, such as the boxes outside of the image).
+    ///
+    /// # Arguments
+    ///
+    /// * `boxes`: Bounding boxes. Shape: `[num_boxes, 4]`.
+    /// * `image_hw` - Image size `(height, width)`.
+    ///
+    /// # Returns
+    ///
+    /// The clipped boxes which are kept with shape `[num_kept, 4]`, and the mask of the kept
+    /// boxes with shape `[num_boxes]` (e.g., to filter their labels).
+    pub fn clip_to_image<B: Backend>(
+        boxes: Tensor<B, 2>,
+        image_hw: (f32, f32),
+    ) -> (Tensor<B, 2>, Tensor<B, 1, Bool>) {
+        let (height, width) = image_hw;
+        let [n, _] = boxes.dims();
+        let device = boxes.device();
+
+        let [xmin, ymin, xmax, ymax] = columns(boxes);
+        let (xmin, xmax) = (xmin.clamp(0., width), xmax.clamp(0., width));
+        let (ymin, ymax) = (ymin.clamp(0., height), ymax.clamp(0., height));
+
+        let keep = (xmax.clone() - xmin.clone())
+            .greater_elem(0.)
+            .float()
+            .mul((ymax.clone() - ymin.clone()).greater_elem(0.).float())
+            .reshape([n])
+            .greater_elem(0.5);
+
+        // Select the kept boxes on the host
+        let indices: Vec<i64> = keep
+            .clone()
+            .into_data()
+            .iter::<bool>()
+            .enumerate()
+            .filter_map(|(i, keep)| keep.then_some(i as i64))
+            .collect();
+        let num_kept = indices.len();
+        let indices = Tensor::<B, 1, Int>::from_data(
+            TensorData::new(indices, [num_kept]).convert::<B::IntElem>(),
+            &device,
+        );
+        let boxes = Tensor::cat(vec![xmin, ymin, xmax, ymax], 1).select(0, indices);
+
+        (boxes, keep)
+    }
+
+    /// Rotate the boxes of an image rotated counter-clockwise by `num_rotations` quarter turns
+    /// (as with `numpy.rot90`), e.g., to switch between portrait and landscape orientations.
+    ///
+    /// # Arguments
+    ///
+    /// * `boxes`: Bounding boxes. Shape: `[num_boxes, 4]`.
+    /// * `image_hw` - Size `(height, width)` of the image before the rotation.
+    /// * `num_rotations` - Number of counter-clockwise quarter turns.
+    pub fn rotate_90<B: Backend>(
+        boxes: Tensor<B, 2>,
+        image_hw: (f32, f32),
+        num_rotations: usize,
+    ) -> Tensor<B, 2> {
+        let (mut height, mut width) = image_hw;
+        let mut boxes = boxes;
+
+        for _ in 0..num_rotations % 4 {
+            // A point (x, y) is moved to (y, width - x)
+            let [xmin, ymin, xmax, ymax] = columns(boxes);
+            boxes = Tensor::cat(
+                vec![
+                    ymin,
+                    xmax.neg().add_scalar(width),
+                    ymax,
+                    xmin.neg().add_scalar(width),
+                ],
+                1,
+            );
+            (height, width) = (width, height);
+        }
+
+        boxes
+    }
+}
+
+/// Split the bounding boxes into their `xmin`, `ymin`, `xmax` and `ymax` columns.
+fn columns<B: Backend>(boxes: Tensor<B, 2>) -> [Tensor<B, 2>; 4] {
+    let [n, _] = boxes.dims();
+    core::array::from_fn(|i| boxes.clone().slice([0..n, i..i + 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn boxes(values: &[[f32; 4]]) -> Tensor<TestBackend, 2> {
+        let values: Vec<f32> = values.iter().flatten().copied().collect();
+        let n = values.len() / 4;
+        Tensor::<TestBackend, 1>::from_floats(values.as_slice(), &Default::default())
+            .reshape([n, 4])
+    }
+
+    fn assert_boxes(actual: Tensor<TestBackend, 2>, expected: &[[f32; 4]]) {
+        actual
+            .into_data()
+            .assert_approx_eq(&boxes(expected).into_data(), 5);
+    }
+
+    #[test]
+    fn horizontal_flip() {
+        let flipped = BoxTransformer::horizontal_flip(
+            boxes(&[[10., 20., 30., 60.], [0., 0., 100., 50.]]),
+            100.,
+        );
+        assert_boxes(
+            flipped.clone(),
+            &[[70., 20., 90., 60.], [0., 0., 100., 50.]],
+        );
+
+        // Flipping twice restores the boxes
+        let restored = BoxTransformer::horizontal_flip(flipped, 100.);
+        assert_boxes(restored, &[[10., 20., 30., 60.], [0., 0., 100., 50.]]);
+    }
+
+    #[test]
+    fn vertical_flip() {
+        let flipped =
+            BoxTransformer::vertical_flip(boxes(&[[10., 5., 30., 25.], [0., 0., 100., 50.]]), 80.);
+
+        assert_boxes(flipped, &[[10., 55., 30., 75.], [0., 30., 100., 80.]]);
+    }
+
+    #[test]
+    fn scale() {
+        let scaled = BoxTransformer::scale(boxes(&[[10., 20., 30., 60.]]), 0.5, 2.);
+
+        assert_boxes(scaled, &[[5., 40., 15., 120.]]);
+    }
+
+    #[test]
+    fn translate() {
+        let translated = BoxTransformer::translate(boxes(&[[10., 20., 30., 60.]]), 5., -10.);
+
+        assert_boxes(translated, &[[15., 10., 35., 50.]]);
+    }
+
+    #[test]
+    fn clip_to_image() {
+        let (clipped, keep) = BoxTransformer::clip_to_image(
+            boxes(&[
+                [-10., -5., 30., 40.],
+                [90., 70., 120., 100.],
+                // Outside of the image
+                [110., 10., 130., 20.],
+                // Degenerate
+                [20., 30., 20., 50.],
+            ]),
+            (80., 100.),
+        );
+
+        assert_boxes(clipped, &[[0., 0., 30., 40.], [90., 70., 100., 80.]]);
+        assert_eq!(
+            keep.into_data().to_vec::<bool>().unwrap(),
+            [true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn clip_all_boxes_outside() {
+        let (clipped, keep) =
+            BoxTransformer::clip_to_image(boxes(&[[-20., 0., -10., 10.]]), (80., 100.));
+
+        assert_eq!(clipped.dims(), [0, 4]);
+        assert_eq!(keep.into_data().to_vec::<bool>().unwrap(), [false]);
+    }
+
+    #[test]
+    fn rotate_90() {
+        let original = [[10., 20., 30., 60.]];
+
+        // (x, y) is moved to (y, 100 - x)
+        let rotated = BoxTransformer::rotate_90(boxes(&original), (80., 100.), 1);
+        assert_boxes(rotated, &[[20., 70., 60., 90.]]);
+
+        // Half turn, as when flipping in both directions
+        let rotated = BoxTransformer::rotate_90(boxes(&original), (80., 100.), 2);
+        assert_boxes(rotated, &[[70., 20., 90., 60.]]);
+
+        let rotated = BoxTransformer::rotate_90(boxes(&original), (80., 100.), 4);
+        assert_boxes(rotated, &original);
+        let rotated = BoxTransformer::rotate_90(boxes(&original), (80., 100.), 5);
+        assert_boxes(rotated, &[[20., 70., 60., 90.]]);
+    }
+}
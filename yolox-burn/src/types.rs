@@ -0,0 +1,499 @@
+//! Detection types shared across models.
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use burn::tensor::{backend::Backend, Tensor};
+use serde::{Deserialize, Serialize};
+
+/// A detected object.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Index of the image in the batch (or dataset).
+    pub image_id: usize,
+    /// Bounding box `[xmin, ymin, xmax, ymax]` in image coordinates.
+    pub box_xyxy: [f32; 4],
+    /// Confidence score.
+    pub score: f32,
+    /// Predicted class index.
+    pub class_id: usize,
+    /// Optional row-major binary mask (1 for foreground, 0 for background).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<Vec<u8>>,
+    /// Optional keypoints `(x, y, score)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypoints: Option<Vec<(f32, f32, f32)>>,
+}
+
+impl Detection {
+    /// Create a new detection without mask or keypoints.
+    pub fn new(image_id: usize, box_xyxy: [f32; 4], score: f32, class_id: usize) -> Self {
+        Self {
+            image_id,
+            box_xyxy,
+            score,
+            class_id,
+            mask: None,
+            keypoints: None,
+        }
+    }
+
+    /// Bounding box width.
+    pub fn width(&self) -> f32 {
+        self.box_xyxy[2] - self.box_xyxy[0]
+    }
+
+    /// Bounding box height.
+    pub fn height(&self) -> f32 {
+        self.box_xyxy[3] - self.box_xyxy[1]
+    }
+
+    /// Bounding box area.
+    pub fn area(&self) -> f32 {
+        self.width().max(0.) * self.height().max(0.)
+    }
+}
+
+/// A ground-truth object annotation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GroundTruth {
+    /// Index of the image in the dataset.
+    pub image_id: usize,
+    /// Bounding box `[xmin, ymin, xmax, ymax]` in image coordinates.
+    pub box_xyxy: [f32; 4],
+    /// Object class index.
+    pub class_id: usize,
+    /// Object area (used for small / medium / large object evaluation).
+    pub area: f32,
+    /// Crowd annotations are ignored during evaluation.
+    pub is_crowd: bool,
+}
+
+/// Error of the [IoU](IoUThreshold) and [confidence](ConfThreshold) threshold validation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdError {
+    /// The threshold is not in the range `[0, 1]`.
+    OutOfRange { got: f32 },
+}
+
+impl fmt::Display for ThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange { got } => write!(f, "Threshold {got} is not in range [0, 1]"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ThresholdError {}
+
+/// Validate that the threshold is in the range `[0, 1]`.
+fn check_threshold(value: f32) -> Result<f32, ThresholdError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(ThresholdError::OutOfRange { got: value })
+    }
+}
+
+/// Intersection over union threshold (e.g., of non-maximum suppression or of the matching of
+/// the detections for evaluation), in the range `[0, 1]`.
+///
+/// Distinct from the [confidence thresholds](ConfThreshold), so that the two cannot be swapped:
+///
+/// ```compile_fail
+/// use yolox_burn::types::{ConfThreshold, IoUThreshold};
+///
+/// fn filter_scores(threshold: ConfThreshold) {}
+///
+/// filter_scores(IoUThreshold::default());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct IoUThreshold(f32);
+
+impl IoUThreshold {
+    /// Create a new IoU threshold, if the value is in the range `[0, 1]`.
+    pub fn new(value: f32) -> Result<Self, ThresholdError> {
+        check_threshold(value).map(Self)
+    }
+
+    /// Threshold value.
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+impl Default for IoUThreshold {
+    fn default() -> Self {
+        Self(0.5)
+    }
+}
+
+impl From<f32> for IoUThreshold {
+    /// Create a new IoU threshold.
+    ///
+    /// # Panics
+    ///
+    /// If the value is not in the range `[0, 1]`.
+    fn from(value: f32) -> Self {
+        Self::new(value).unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+/// Confidence (score) threshold, in the range `[0, 1]`.
+///
+/// Distinct from the [IoU thresholds](IoUThreshold), so that the two cannot be swapped.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct ConfThreshold(f32);
+
+impl ConfThreshold {
+    /// Create a new confidence threshold, if the value is in the range `[0, 1]`.
+    pub fn new(value: f32) -> Result<Self, ThresholdError> {
+        check_threshold(value).map(Self)
+    }
+
+    /// Threshold value.
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+impl Default for ConfThreshold {
+    fn default() -> Self {
+        Self(0.3)
+    }
+}
+
+impl From<f32> for ConfThreshold {
+    /// Create a new confidence threshold.
+    ///
+    /// # Panics
+    ///
+    /// If the value is not in the range `[0, 1]`.
+    fn from(value: f32) -> Self {
+        Self::new(value).unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+/// Encoding of the raw box predictions of a model (see [decode_boxes] and [encode_boxes]).
+#[derive(Debug, Clone)]
+pub enum Box2dDecoder<B: Backend> {
+    /// `(xmin, ymin, width, height)`, as in the COCO annotations.
+    Xywh,
+    /// `(xmin, ymin, xmax, ymax)`.
+    Xyxy,
+    /// `(center_x, center_y, width, height)`, as predicted by YOLOX.
+    CenterWh,
+    /// Offsets `(dx, dy, dw, dh)` relative to anchor boxes, as predicted by Faster R-CNN: the
+    /// center offsets are scaled by the anchor size and the size offsets are log-scale ratios.
+    /// The offsets are normalized by their standard deviation.
+    DeltaXywh {
+        /// Anchor boxes `(xmin, ymin, xmax, ymax)`. Shape: `[num_boxes, 4]`.
+        anchor_boxes: Tensor<B, 2>,
+        /// Standard deviation of each offset (e.g., `[0.1, 0.1, 0.2, 0.2]`).
+        std_dev: [f32; 4],
+    },
+}
+
+/// Convert the raw box predictions to `(xmin, ymin, xmax, ymax)` boxes.
+///
+/// # Arguments
+///
+/// * `raw`: Raw box predictions. Shape: `[num_boxes, 4]`.
+/// * `format` - Encoding of the predictions.
+/// * `clip` - Optional image size `(width, height)` to clip the boxes to.
+///
+/// # Returns
+///
+/// The boxes `(xmin, ymin, xmax, ymax)` with shape `[num_boxes, 4]`.
+pub fn decode_boxes<B: Backend>(
+    raw: Tensor<B, 2>,
+    format: &Box2dDecoder<B>,
+    clip: Option<(f32, f32)>,
+) -> Tensor<B, 2> {
+    let [a, b, c, d] = box_columns(raw);
+    let [xmin, ymin, xmax, ymax] = match format {
+        Box2dDecoder::Xywh => [a.clone(), b.clone(), a + c, b + d],
+        Box2dDecoder::Xyxy => [a, b, c, d],
+        Box2dDecoder::CenterWh => {
+            let (half_w, half_h) = (c.mul_scalar(0.5), d.mul_scalar(0.5));
+            [
+                a.clone() - half_w.clone(),
+                b.clone() - half_h.clone(),
+                a + half_w,
+                b + half_h,
+            ]
+        }
+        Box2dDecoder::DeltaXywh {
+            anchor_boxes,
+            std_dev,
+        } => {
+            let [acx, acy, aw, ah] = center_size(anchor_boxes.clone());
+            let cx = acx + a.mul_scalar(std_dev[0]) * aw.clone();
+            let cy = acy + b.mul_scalar(std_dev[1]) * ah.clone();
+            let half_w = c.mul_scalar(std_dev[2]).exp() * aw.mul_scalar(0.5);
+            let half_h = d.mul_scalar(std_dev[3]).exp() * ah.mul_scalar(0.5);
+            [
+                cx.clone() - half_w.clone(),
+                cy.clone() - half_h.clone(),
+                cx + half_w,
+                cy + half_h,
+            ]
+        }
+    };
+
+    let [xmin, ymin, xmax, ymax] = match clip {
+        Some((width, height)) => [
+            xmin.clamp(0., width),
+            ymin.clamp(0., height),
+            xmax.clamp(0., width),
+            ymax.clamp(0., height),
+        ],
+        None => [xmin, ymin, xmax, ymax],
+    };
+
+    Tensor::cat(vec![xmin, ymin, xmax, ymax], 1)
+}
+
+/// Convert `(xmin, ymin, xmax, ymax)` boxes to the given encoding, i.e., the inverse of
+/// [decode_boxes] without clipping.
+///
+/// # Shapes
+///   - boxes_xyxy: `[num_boxes, 4]`
+///   - output: `[num_boxes, 4]`
+pub fn encode_boxes<B: Backend>(
+    boxes_xyxy: Tensor<B, 2>,
+    format: &Box2dDecoder<B>,
+) -> Tensor<B, 2> {
+    let columns = match format {
+        Box2dDecoder::Xyxy => return boxes_xyxy,
+        Box2dDecoder::Xywh => {
+            let [xmin, ymin, xmax, ymax] = box_columns(boxes_xyxy);
+            [xmin.clone(), ymin.clone(), xmax - xmin, ymax - ymin]
+        }
+        Box2dDecoder::CenterWh => center_size(boxes_xyxy),
+        Box2dDecoder::DeltaXywh {
+            anchor_boxes,
+            std_dev,
+        } => {
+            let [cx, cy, w, h] = center_size(boxes_xyxy);
+            let [acx, acy, aw, ah] = center_size(anchor_boxes.clone());
+            [
+                ((cx - acx) / aw.clone()).div_scalar(std_dev[0]),
+                ((cy - acy) / ah.clone()).div_scalar(std_dev[1]),
+                (w / aw).log().div_scalar(std_dev[2]),
+                (h / ah).log().div_scalar(std_dev[3]),
+            ]
+        }
+    };
+
+    Tensor::cat(columns.to_vec(), 1)
+}
+
+/// Split the boxes into their four columns with shape `[num_boxes, 1]`.
+fn box_columns<B: Backend>(boxes: Tensor<B, 2>) -> [Tensor<B, 2>; 4] {
+    let [n, _] = boxes.dims();
+    core::array::from_fn(|i| boxes.clone().slice([0..n, i..i + 1]))
+}
+
+/// Center and size columns `(center_x, center_y, width, height)` of `(xmin, ymin, xmax, ymax)`
+/// boxes.
+fn center_size<B: Backend>(boxes_xyxy: Tensor<B, 2>) -> [Tensor<B, 2>; 4] {
+    let [xmin, ymin, xmax, ymax] = box_columns(boxes_xyxy);
+    let (w, h) = (xmax - xmin.clone(), ymax - ymin.clone());
+    [
+        xmin + w.clone().mul_scalar(0.5),
+        ymin + h.clone().mul_scalar(0.5),
+        w,
+        h,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::{backend::NdArray, tensor::TensorData};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    #[cfg(feature = "dataset")]
+    fn detection_serde_round_trip() {
+        let mut detection = Detection::new(3, [10., 20., 50., 60.], 0.75, 16);
+        detection.mask = Some(vec![0, 1, 1, 0]);
+        detection.keypoints = Some(vec![(12., 24., 0.5), (40., 55., 0.25)]);
+
+        let json = serde_json::to_string(&detection).unwrap();
+        let decoded: Detection = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, detection);
+    }
+
+    #[test]
+    #[cfg(feature = "dataset")]
+    fn detection_mask_serializes_as_bytes() {
+        let mut detection = Detection::new(0, [0., 0., 2., 2.], 0.5, 1);
+        detection.mask = Some(vec![0, 1, 255, 0]);
+
+        let value = serde_json::to_value(&detection).unwrap();
+
+        assert_eq!(value["mask"], serde_json::json!([0, 1, 255, 0]));
+        assert!(value.get("keypoints").is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "dataset")]
+    fn detection_without_mask_or_keypoints() {
+        let detection = Detection::new(1, [1., 2., 3., 4.], 0.25, 7);
+
+        let value = serde_json::to_value(&detection).unwrap();
+        assert!(value.get("mask").is_none());
+        assert!(value.get("keypoints").is_none());
+
+        let json = r#"{"image_id":1,"box_xyxy":[1.0,2.0,3.0,4.0],"score":0.25,"class_id":7}"#;
+        let decoded: Detection = serde_json::from_str(json).unwrap();
+        assert_eq!(decoded, detection);
+    }
+
+    #[test]
+    #[cfg(feature = "dataset")]
+    fn ground_truth_serde_round_trip() {
+        let ground_truth = GroundTruth {
+            image_id: 139,
+            box_xyxy: [64., 48., 192., 144.],
+            class_id: 0,
+            area: 12288.,
+            is_crowd: true,
+        };
+
+        let json = serde_json::to_string(&ground_truth).unwrap();
+        let decoded: GroundTruth = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, ground_truth);
+    }
+
+    fn boxes(values: [[f32; 4]; 3]) -> Tensor<TestBackend, 2> {
+        Tensor::from_floats(values, &Default::default())
+    }
+
+    fn formats() -> [Box2dDecoder<TestBackend>; 4] {
+        let anchor_boxes = boxes([[0., 0., 16., 16.], [20., 10., 60., 30.], [5., 5., 15., 45.]]);
+
+        [
+            Box2dDecoder::Xywh,
+            Box2dDecoder::Xyxy,
+            Box2dDecoder::CenterWh,
+            Box2dDecoder::DeltaXywh {
+                anchor_boxes,
+                std_dev: [0.1, 0.1, 0.2, 0.2],
+            },
+        ]
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let xyxy = boxes([[2., 4., 10., 20.], [30., 12., 50., 40.], [0., 0., 64., 48.]]);
+
+        for format in formats() {
+            let encoded = encode_boxes(xyxy.clone(), &format);
+            let decoded = decode_boxes(encoded, &format, None);
+
+            decoded
+                .into_data()
+                .assert_approx_eq(&xyxy.clone().into_data(), 3);
+        }
+    }
+
+    #[test]
+    fn decode_known_formats() {
+        let raw = boxes([[10., 20., 4., 6.], [0., 0., 2., 2.], [5., 5., 0., 0.]]);
+
+        decode_boxes(raw.clone(), &Box2dDecoder::Xywh, None)
+            .into_data()
+            .assert_approx_eq(
+                &TensorData::from([[10f32, 20., 14., 26.], [0., 0., 2., 2.], [5., 5., 5., 5.]]),
+                5,
+            );
+        decode_boxes(raw, &Box2dDecoder::CenterWh, None)
+            .into_data()
+            .assert_approx_eq(
+                &TensorData::from([[8f32, 17., 12., 23.], [-1., -1., 1., 1.], [5., 5., 5., 5.]]),
+                5,
+            );
+
+        // Zero offsets decode to the anchors
+        let [_, _, _, anchors] = formats();
+        let Box2dDecoder::DeltaXywh { anchor_boxes, .. } = &anchors else {
+            unreachable!()
+        };
+        decode_boxes(Tensor::zeros([3, 4], &Default::default()), &anchors, None)
+            .into_data()
+            .assert_approx_eq(&anchor_boxes.clone().into_data(), 5);
+    }
+
+    #[test]
+    fn decode_clipped() {
+        let raw = boxes([
+            [-10., 5., 30., 40.],
+            [50., -20., 120., 90.],
+            [10., 10., 20., 20.],
+        ]);
+
+        let decoded = decode_boxes(raw, &Box2dDecoder::Xyxy, Some((64., 48.)));
+
+        decoded.clone().into_data().assert_approx_eq(
+            &TensorData::from([
+                [0f32, 5., 30., 40.],
+                [50., 0., 64., 48.],
+                [10., 10., 20., 20.],
+            ]),
+            5,
+        );
+        let [n, _] = decoded.dims();
+        for x in [0, 2] {
+            let x = decoded.clone().slice([0..n, x..x + 1]);
+            assert!(x.clone().min().into_scalar() >= 0.);
+            assert!(x.max().into_scalar() <= 64.);
+        }
+    }
+
+    #[test]
+    fn threshold_range() {
+        for value in [0., 0.5, 1.] {
+            assert_eq!(IoUThreshold::new(value).unwrap().value(), value);
+            assert_eq!(ConfThreshold::new(value).unwrap().value(), value);
+        }
+
+        for value in [-0.1, 1.1, f32::NAN, f32::INFINITY] {
+            assert!(IoUThreshold::new(value).is_err());
+            assert!(ConfThreshold::new(value).is_err());
+        }
+        assert_eq!(
+            IoUThreshold::new(1.5),
+            Err(ThresholdError::OutOfRange { got: 1.5 })
+        );
+        assert_eq!(
+            ConfThreshold::new(-1.),
+            Err(ThresholdError::OutOfRange { got: -1. })
+        );
+    }
+
+    #[test]
+    fn threshold_defaults() {
+        assert_eq!(IoUThreshold::default().value(), 0.5);
+        assert_eq!(ConfThreshold::default().value(), 0.3);
+        assert_eq!(IoUThreshold::from(0.7), IoUThreshold::new(0.7).unwrap());
+        assert!(ConfThreshold::from(0.2) < ConfThreshold::default());
+    }
+
+    #[test]
+    #[should_panic = "Threshold 2 is not in range [0, 1]"]
+    fn iou_threshold_from_out_of_range() {
+        let _ = IoUThreshold::from(2.);
+    }
+
+    #[test]
+    #[should_panic = "Threshold -0.5 is not in range [0, 1]"]
+    fn conf_threshold_from_out_of_range() {
+        let _ = ConfThreshold::from(-0.5);
+    }
+}
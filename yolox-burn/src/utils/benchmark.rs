@@ -0,0 +1,293 @@
+use core::fmt;
+use std::time::Instant;
+
+use burn::tensor::{
+    backend::{Backend, SyncType},
+    Device, Distribution, Tensor,
+};
+
+use crate::model::yolox::Yolox;
+
+/// A model that can be benchmarked on a batch of images.
+pub trait Forward<B: Backend> {
+    type Output;
+
+    fn forward(&self, input: Tensor<B, 4>) -> Self::Output;
+}
+
+impl<B: Backend> Forward<B> for Yolox<B> {
+    type Output = Tensor<B, 3>;
+
+    fn forward(&self, input: Tensor<B, 4>) -> Self::Output {
+        Yolox::forward(self, input)
+    }
+}
+
+impl<B, F, O> Forward<B> for F
+where
+    B: Backend,
+    F: Fn(Tensor<B, 4>) -> O,
+{
+    type Output = O;
+
+    fn forward(&self, input: Tensor<B, 4>) -> Self::Output {
+        self(input)
+    }
+}
+
+/// Inference latency and throughput statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkResult {
+    /// Number of measured iterations.
+    pub num_iters: usize,
+    /// Batch size of the input.
+    pub batch_size: usize,
+    /// Mean latency (ms).
+    pub mean: f64,
+    /// Median latency (ms).
+    pub median: f64,
+    /// 95th percentile latency (ms).
+    pub p95: f64,
+    /// 99th percentile latency (ms).
+    pub p99: f64,
+    /// Additional requested percentiles `(percentile, latency in ms)`.
+    pub percentiles: Vec<(f64, f64)>,
+    /// Throughput (images/second).
+    pub throughput: f64,
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "| {:<18} | {:>12} |", "Metric", "Value")?;
+        writeln!(f, "|{:-<20}|{:->14}|", "", "")?;
+        writeln!(f, "| {:<18} | {:>12} |", "Iterations", self.num_iters)?;
+        writeln!(f, "| {:<18} | {:>12} |", "Batch size", self.batch_size)?;
+        writeln!(f, "| {:<18} | {:>9.3} ms |", "Mean", self.mean)?;
+        writeln!(f, "| {:<18} | {:>9.3} ms |", "Median", self.median)?;
+        writeln!(f, "| {:<18} | {:>9.3} ms |", "P95", self.p95)?;
+        writeln!(f, "| {:<18} | {:>9.3} ms |", "P99", self.p99)?;
+        for (p, latency) in self.percentiles.iter() {
+            writeln!(f, "| {:<18} | {:>9.3} ms |", format!("P{p}"), latency)?;
+        }
+        write!(
+            f,
+            "| {:<18} | {:>8.2} im/s |",
+            "Throughput", self.throughput
+        )
+    }
+}
+
+/// Side-by-side comparison of two [benchmark results](BenchmarkResult).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkComparison {
+    pub first: BenchmarkResult,
+    pub second: BenchmarkResult,
+}
+
+impl BenchmarkComparison {
+    /// Mean latency speedup of the first model over the second one.
+    pub fn speedup(&self) -> f64 {
+        self.second.mean / self.first.mean
+    }
+}
+
+impl fmt::Display for BenchmarkComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("Mean", self.first.mean, self.second.mean),
+            ("Median", self.first.median, self.second.median),
+            ("P95", self.first.p95, self.second.p95),
+            ("P99", self.first.p99, self.second.p99),
+        ];
+
+        writeln!(
+            f,
+            "| {:<12} | {:>12} | {:>12} |",
+            "Metric", "Model 1", "Model 2"
+        )?;
+        writeln!(f, "|{:-<14}|{:->14}|{:->14}|", "", "", "")?;
+        for (name, first, second) in rows {
+            writeln!(f, "| {name:<12} | {first:>9.3} ms | {second:>9.3} ms |")?;
+        }
+        writeln!(
+            f,
+            "| {:<12} | {:>7.2} im/s | {:>7.2} im/s |",
+            "Throughput", self.first.throughput, self.second.throughput
+        )?;
+        write!(f, "Speedup (mean latency): {:.2}x", self.speedup())
+    }
+}
+
+/// Inference benchmark measuring the end-to-end latency and throughput of a model.
+///
+/// The device is synchronized after each forward pass so that the measured latency includes the
+/// actual computation and not only the kernel dispatch time.
+pub struct ModelBenchmark<B: Backend, M> {
+    model: M,
+    input_shape: [usize; 4],
+    device: Device<B>,
+}
+
+impl<B: Backend, M: Forward<B>> ModelBenchmark<B, M> {
+    /// Create a new benchmark for the model.
+    ///
+    /// # Arguments
+    ///
+    /// * `model`: Model to benchmark.
+    /// * `input_shape` - Input shape `[batch_size, channels, height, width]`.
+    /// * `device` - Device on which the model was created.
+    pub fn new(model: M, input_shape: [usize; 4], device: Device<B>) -> Self {
+        Self {
+            model,
+            input_shape,
+            device,
+        }
+    }
+
+    /// Random input batch.
+    fn input(&self) -> Tensor<B, 4> {
+        Tensor::random(
+            self.input_shape,
+            Distribution::Uniform(0., 255.),
+            &self.device,
+        )
+    }
+
+    /// Run a single forward pass and return the elapsed time in milliseconds.
+    fn step(&self) -> f64 {
+        let input = self.input();
+        // Make sure the input is ready before starting the timer
+        B::sync(&self.device, SyncType::Wait);
+
+        let start = Instant::now();
+        let _output = self.model.forward(input);
+        B::sync(&self.device, SyncType::Wait);
+
+        start.elapsed().as_secs_f64() * 1e3
+    }
+
+    /// Run the model for a number of iterations without measuring (e.g., for kernel compilation
+    /// and autotuning).
+    pub fn warmup(&self, num_iters: usize) {
+        for _ in 0..num_iters {
+            self.step();
+        }
+    }
+
+    /// Run the benchmark.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_iters`: Number of measured iterations.
+    /// * `percentiles` - Additional latency percentiles to report, in the range `[0, 100]`.
+    pub fn run(&self, num_iters: usize, percentiles: &[f64]) -> BenchmarkResult {
+        assert!(num_iters > 0, "at least one iteration is required");
+
+        let mut latencies: Vec<f64> = (0..num_iters).map(|_| self.step()).collect();
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mean = latencies.iter().sum::<f64>() / num_iters as f64;
+        let batch_size = self.input_shape[0];
+
+        BenchmarkResult {
+            num_iters,
+            batch_size,
+            mean,
+            median: percentile(&latencies, 50.),
+            p95: percentile(&latencies, 95.),
+            p99: percentile(&latencies, 99.),
+            percentiles: percentiles
+                .iter()
+                .map(|&p| (p, percentile(&latencies, p)))
+                .collect(),
+            throughput: batch_size as f64 / (mean / 1e3),
+        }
+    }
+}
+
+/// Nearest-rank percentile of the sorted values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    assert!(
+        (0. ..=100.).contains(&p),
+        "percentile should be in [0, 100]"
+    );
+    let rank = ((p / 100.) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+}
+
+/// Benchmark two models with the same number of warmup and measured iterations.
+pub fn compare<B: Backend, M1: Forward<B>, M2: Forward<B>>(
+    m1: &ModelBenchmark<B, M1>,
+    m2: &ModelBenchmark<B, M2>,
+    warmup_iters: usize,
+    num_iters: usize,
+) -> BenchmarkComparison {
+    m1.warmup(warmup_iters);
+    let first = m1.run(num_iters, &[]);
+
+    m2.warmup(warmup_iters);
+    let second = m2.run(num_iters, &[]);
+
+    BenchmarkComparison { first, second }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn benchmark() -> ModelBenchmark<TestBackend, impl Forward<TestBackend>> {
+        let model = |x: Tensor<TestBackend, 4>| x.mul_scalar(2.).sum();
+        ModelBenchmark::new(model, [2, 3, 8, 8], Default::default())
+    }
+
+    #[test]
+    fn warmup_zero_iterations() {
+        benchmark().warmup(0);
+    }
+
+    #[test]
+    fn run_completes() {
+        let bench = benchmark();
+        bench.warmup(1);
+        let result = bench.run(5, &[10., 90.]);
+
+        assert_eq!(result.num_iters, 5);
+        assert_eq!(result.batch_size, 2);
+        assert!(result.mean >= 0.);
+        assert!(result.median <= result.p95 && result.p95 <= result.p99);
+        assert_eq!(result.percentiles.len(), 2);
+        assert_eq!(result.percentiles[0].0, 10.);
+        assert!(result.throughput > 0.);
+        assert!(result.to_string().contains("Throughput"));
+    }
+
+    #[test]
+    fn yolox_benchmark_completes() {
+        let device = Default::default();
+        let model = Yolox::<TestBackend>::yolox_nano(2, &device);
+        let result = ModelBenchmark::new(model, [1, 3, 32, 32], device).run(1, &[]);
+
+        assert_eq!(result.num_iters, 1);
+    }
+
+    #[test]
+    fn compare_models() {
+        let comparison = compare(&benchmark(), &benchmark(), 0, 2);
+
+        assert_eq!(comparison.first.num_iters, 2);
+        assert_eq!(comparison.second.num_iters, 2);
+        assert!(comparison.to_string().contains("Speedup"));
+    }
+
+    #[test]
+    fn nearest_rank_percentile() {
+        let sorted = [1., 2., 3., 4.];
+
+        assert_eq!(percentile(&sorted, 0.), 1.);
+        assert_eq!(percentile(&sorted, 50.), 2.);
+        assert_eq!(percentile(&sorted, 100.), 4.);
+    }
+}
@@ -0,0 +1,525 @@
+use burn::tensor::{backend::Backend, Tensor};
+
+use crate::{
+    metrics::box_iou,
+    model::{DetectionModel, DetectionRawOutput, NMS_IOU_THRESHOLD},
+    types::{ConfThreshold, Detection, GroundTruth, IoUThreshold},
+};
+
+/// Minimum IoU for a detection to be considered correct.
+const IOU_THRESHOLD: f32 = 0.5;
+/// Scores are clamped to avoid infinite logits.
+const EPS: f64 = 1e-6;
+/// Minimum raw score of the detections, low enough to calibrate the whole score range.
+const MIN_SCORE_THRESHOLD: f32 = 0.001;
+
+/// Logit (inverse sigmoid) of a score.
+fn logit(score: f32) -> f64 {
+    let p = (score as f64).clamp(EPS, 1. - EPS);
+    (p / (1. - p)).ln()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1. / (1. + (-x).exp())
+}
+
+/// Binary negative log-likelihood of the predicted probabilities.
+fn nll(samples: &[(f32, bool)], calibrate: impl Fn(f32) -> f64) -> f64 {
+    samples
+        .iter()
+        .map(|&(score, correct)| {
+            let p = calibrate(score).clamp(EPS, 1. - EPS);
+            if correct {
+                -p.ln()
+            } else {
+                -(1. - p).ln()
+            }
+        })
+        .sum::<f64>()
+        / samples.len().max(1) as f64
+}
+
+/// Run the model on the validation batches and match the detections with the ground-truth
+/// objects.
+///
+/// All the detections with a score of at least 0.001 are collected, not only the ones above the
+/// default [inference](DetectionModel::infer) threshold.
+///
+/// # Returns
+///
+/// The `(score, correct)` pair of each detection, where a detection is correct when it matches a
+/// ground-truth object of the same class with an IoU of at least 0.5.
+pub fn calibration_samples<B, M, I>(model: &M, val_loader: I) -> Vec<(f32, bool)>
+where
+    B: Backend,
+    M: DetectionModel<B> + ?Sized,
+    I: IntoIterator<Item = (Tensor<B, 4>, Vec<Vec<GroundTruth>>)>,
+{
+    let mut samples = Vec::new();
+    for (images, targets) in val_loader {
+        let detections = model.decode(
+            model.forward_raw(images),
+            MIN_SCORE_THRESHOLD.into(),
+            NMS_IOU_THRESHOLD.into(),
+        );
+        for (mut detections, targets) in detections.into_iter().zip(targets) {
+            detections.sort_by(|a, b| b.score.total_cmp(&a.score));
+            let mut matched = vec![false; targets.len()];
+            for det in detections {
+                let best = targets
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, gt)| !matched[*i] && gt.class_id == det.class_id)
+                    .map(|(i, gt)| (i, box_iou(&det.box_xyxy, &gt.box_xyxy)))
+                    .filter(|(_, iou)| *iou >= IOU_THRESHOLD)
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+
+                if let Some((i, _)) = best {
+                    matched[i] = true;
+                }
+                samples.push((det.score, best.is_some()));
+            }
+        }
+    }
+
+    samples
+}
+
+/// Expected calibration error (ECE).
+///
+/// Scores are grouped into equal-width bins and the ECE is the weighted average of the absolute
+/// difference between the mean score and the accuracy of each bin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ece {
+    num_bins: usize,
+}
+
+impl Ece {
+    /// Create a new ECE metric with the given number of bins.
+    pub fn new(num_bins: usize) -> Self {
+        assert!(num_bins > 0, "number of bins should be positive");
+        Self { num_bins }
+    }
+
+    /// Compute the ECE of the `(score, correct)` samples.
+    pub fn compute(&self, samples: &[(f32, bool)]) -> f64 {
+        let mut bins = vec![(0usize, 0f64, 0usize); self.num_bins];
+        for &(score, correct) in samples {
+            let bin = ((score as f64 * self.num_bins as f64) as usize).min(self.num_bins - 1);
+            bins[bin].0 += 1;
+            bins[bin].1 += score as f64;
+            bins[bin].2 += correct as usize;
+        }
+
+        bins.iter()
+            .filter(|(count, _, _)| *count > 0)
+            .map(|&(count, score_sum, num_correct)| {
+                let confidence = score_sum / count as f64;
+                let accuracy = num_correct as f64 / count as f64;
+                count as f64 * (confidence - accuracy).abs()
+            })
+            .sum::<f64>()
+            / samples.len().max(1) as f64
+    }
+}
+
+/// Temperature scaling calibration of the detection scores: `sigmoid(logit(score) / T)`.
+///
+/// A single temperature preserves the ranking of the detections, so the mAP is unchanged.
+pub struct TemperatureScaling<M> {
+    model: M,
+    temperature: f64,
+}
+
+impl<M> TemperatureScaling<M> {
+    /// Create a new temperature scaling wrapper around the model.
+    pub fn new(model: M, temperature: f64) -> Self {
+        Self { model, temperature }
+    }
+
+    /// Current temperature.
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Calibrated score.
+    pub fn calibrate_score(&self, score: f32) -> f32 {
+        sigmoid(logit(score) / self.temperature) as f32
+    }
+
+    /// Find the temperature minimizing the negative log-likelihood of the detections on the
+    /// validation set.
+    ///
+    /// The NLL is convex in `1 / T`, so a 1D grid search over `T` in `[0.05, 10]` is used.
+    pub fn calibrate<B, I>(&mut self, val_loader: I) -> f64
+    where
+        B: Backend,
+        M: DetectionModel<B>,
+        I: IntoIterator<Item = (Tensor<B, 4>, Vec<Vec<GroundTruth>>)>,
+    {
+        let samples = calibration_samples(&self.model, val_loader);
+        self.temperature = fit_temperature(&samples);
+        self.temperature
+    }
+}
+
+/// Grid search of the temperature minimizing the NLL.
+fn fit_temperature(samples: &[(f32, bool)]) -> f64 {
+    let loss = |t: f64| nll(samples, |s| sigmoid(logit(s) / t));
+
+    // Coarse logarithmic grid followed by a finer linear search around the best value
+    let coarse = (0..=200).map(|i| 0.05 * (200f64).powf(i as f64 / 200.));
+    let best = coarse.min_by(|a, b| loss(*a).total_cmp(&loss(*b))).unwrap();
+    let fine = (0..=200).map(|i| best * (0.95 + 0.1 * i as f64 / 200.));
+
+    fine.min_by(|a, b| loss(*a).total_cmp(&loss(*b))).unwrap()
+}
+
+/// Platt scaling calibration of the detection scores: `sigmoid(a * logit(score) + b)`.
+pub struct PlattScaling<M> {
+    model: M,
+    a: f64,
+    b: f64,
+}
+
+impl<M> PlattScaling<M> {
+    /// Create a new Platt scaling wrapper around the model (initialized to the identity mapping).
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            a: 1.,
+            b: 0.,
+        }
+    }
+
+    /// Fitted `(a, b)` parameters of the sigmoid.
+    pub fn params(&self) -> (f64, f64) {
+        (self.a, self.b)
+    }
+
+    /// Calibrated score.
+    pub fn calibrate_score(&self, score: f32) -> f32 {
+        sigmoid(self.a * logit(score) + self.b) as f32
+    }
+
+    /// Fit the sigmoid parameters by minimizing the negative log-likelihood of the detections on
+    /// the validation set (Newton's method with a backtracking line search).
+    pub fn calibrate<B, I>(&mut self, val_loader: I) -> (f64, f64)
+    where
+        B: Backend,
+        M: DetectionModel<B>,
+        I: IntoIterator<Item = (Tensor<B, 4>, Vec<Vec<GroundTruth>>)>,
+    {
+        let samples = calibration_samples(&self.model, val_loader);
+        (self.a, self.b) = fit_platt(&samples);
+        (self.a, self.b)
+    }
+}
+
+/// Fit the Platt scaling parameters with Newton's method.
+fn fit_platt(samples: &[(f32, bool)]) -> (f64, f64) {
+    let (mut a, mut b) = (1., 0.);
+    if samples.is_empty() {
+        return (a, b);
+    }
+    let loss = |a: f64, b: f64| nll(samples, |s| sigmoid(a * logit(s) + b));
+
+    for _ in 0..100 {
+        // Gradient and Hessian of the NLL
+        let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0., 0., 0., 0., 0.);
+        for &(score, correct) in samples {
+            let x = logit(score);
+            let p = sigmoid(a * x + b);
+            let d = p - correct as u8 as f64;
+            let w = (p * (1. - p)).max(EPS);
+            ga += d * x;
+            gb += d;
+            haa += w * x * x;
+            hab += w * x;
+            hbb += w;
+        }
+
+        // Small regularization to keep the Hessian invertible
+        haa += EPS;
+        hbb += EPS;
+        let det = haa * hbb - hab * hab;
+        if det.abs() < f64::EPSILON {
+            break;
+        }
+        let da = (hbb * ga - hab * gb) / det;
+        let db = (haa * gb - hab * ga) / det;
+
+        // Backtracking line search, as the full Newton step can overshoot
+        let current = loss(a, b);
+        let mut step = 1.;
+        while step > 1e-8 && loss(a - step * da, b - step * db) > current {
+            step /= 2.;
+        }
+        a -= step * da;
+        b -= step * db;
+
+        if (step * da).abs() < 1e-8 && (step * db).abs() < 1e-8 {
+            break;
+        }
+    }
+
+    (a, b)
+}
+
+/// Decode the detections of the model and rescale their scores.
+///
+/// The confidence threshold applies to the calibrated scores, so the model detections are decoded
+/// with a minimal threshold.
+fn decode_calibrated<B: Backend, M: DetectionModel<B>>(
+    model: &M,
+    raw: DetectionRawOutput<B>,
+    conf_threshold: ConfThreshold,
+    nms_iou_threshold: IoUThreshold,
+    calibrate: impl Fn(f32) -> f32,
+) -> Vec<Vec<Detection>> {
+    model
+        .decode(raw, MIN_SCORE_THRESHOLD.into(), nms_iou_threshold)
+        .into_iter()
+        .map(|detections| {
+            detections
+                .into_iter()
+                .map(|mut det| {
+                    det.score = calibrate(det.score);
+                    det
+                })
+                .filter(|det| det.score >= conf_threshold.value())
+                .collect()
+        })
+        .collect()
+}
+
+impl<B: Backend, M: DetectionModel<B>> DetectionModel<B> for TemperatureScaling<M> {
+    fn forward_raw(&self, images: Tensor<B, 4>) -> DetectionRawOutput<B> {
+        self.model.forward_raw(images)
+    }
+
+    fn num_classes(&self) -> usize {
+        self.model.num_classes()
+    }
+
+    fn decode(
+        &self,
+        raw: DetectionRawOutput<B>,
+        conf_threshold: ConfThreshold,
+        nms_iou_threshold: IoUThreshold,
+    ) -> Vec<Vec<Detection>> {
+        decode_calibrated(&self.model, raw, conf_threshold, nms_iou_threshold, |s| {
+            self.calibrate_score(s)
+        })
+    }
+}
+
+impl<B: Backend, M: DetectionModel<B>> DetectionModel<B> for PlattScaling<M> {
+    fn forward_raw(&self, images: Tensor<B, 4>) -> DetectionRawOutput<B> {
+        self.model.forward_raw(images)
+    }
+
+    fn num_classes(&self) -> usize {
+        self.model.num_classes()
+    }
+
+    fn decode(
+        &self,
+        raw: DetectionRawOutput<B>,
+        conf_threshold: ConfThreshold,
+        nms_iou_threshold: IoUThreshold,
+    ) -> Vec<Vec<Detection>> {
+        decode_calibrated(&self.model, raw, conf_threshold, nms_iou_threshold, |s| {
+            self.calibrate_score(s)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    /// Model predicting 10 disjoint boxes of class 0 with the same score.
+    struct FixedModel {
+        score: f32,
+    }
+
+    impl DetectionModel<TestBackend> for FixedModel {
+        fn forward_raw(&self, images: Tensor<TestBackend, 4>) -> DetectionRawOutput<TestBackend> {
+            let [batch_size, _, _, _] = images.dims();
+            let out: Vec<_> = (0..10)
+                .flat_map(|i| [20. * i as f32 + 10., 10., 10., 10., 1., self.score])
+                .collect();
+            let out = Tensor::<TestBackend, 1>::from_floats(out.as_slice(), &images.device())
+                .reshape([1, 10, 6])
+                .repeat_dim(0, batch_size);
+
+            DetectionRawOutput::AnchorFree(out)
+        }
+
+        fn num_classes(&self) -> usize {
+            1
+        }
+    }
+
+    /// Validation batch of a single image, where only the first `num_objects` boxes of the
+    /// [model](FixedModel) are ground-truth objects.
+    fn val_loader(num_objects: usize) -> Vec<(Tensor<TestBackend, 4>, Vec<Vec<GroundTruth>>)> {
+        let targets = (0..num_objects)
+            .map(|i| GroundTruth {
+                image_id: 0,
+                box_xyxy: [20. * i as f32 + 5., 5., 20. * i as f32 + 15., 15.],
+                class_id: 0,
+                area: 100.,
+                is_crowd: false,
+            })
+            .collect();
+
+        vec![(
+            Tensor::zeros([1, 3, 8, 8], &Default::default()),
+            vec![targets],
+        )]
+    }
+
+    #[test]
+    fn samples_match_ground_truth() {
+        let samples = calibration_samples(&FixedModel { score: 0.9 }, val_loader(4));
+
+        assert_eq!(samples.len(), 10);
+        assert_eq!(samples.iter().filter(|(_, correct)| *correct).count(), 4);
+        assert!(samples.iter().all(|(score, _)| *score == 0.9));
+    }
+
+    #[test]
+    fn samples_below_inference_threshold() {
+        let samples = calibration_samples(&FixedModel { score: 0.1 }, val_loader(4));
+
+        assert_eq!(samples.len(), 10);
+        assert!(samples.iter().all(|(score, _)| *score == 0.1));
+    }
+
+    #[test]
+    fn temperature_of_calibrated_model() {
+        // 90% of the detections with a score of 0.9 are correct
+        let mut calibration = TemperatureScaling::new(FixedModel { score: 0.9 }, 2.);
+
+        let temperature = calibration.calibrate(val_loader(9));
+
+        assert!((temperature - 1.).abs() < 1e-2, "temperature {temperature}");
+        assert_eq!(calibration.temperature(), temperature);
+        assert!((calibration.calibrate_score(0.9) - 0.9).abs() < 1e-3);
+    }
+
+    #[test]
+    fn temperature_reduces_ece_of_overconfident_model() {
+        // Only half of the detections with a score of 0.9 are correct
+        let samples = calibration_samples(&FixedModel { score: 0.9 }, val_loader(5));
+        let mut calibration = TemperatureScaling::new(FixedModel { score: 0.9 }, 1.);
+        let ece = Ece::new(10);
+
+        let temperature = calibration.calibrate(val_loader(5));
+
+        assert!(temperature > 1.);
+        let calibrated: Vec<_> = samples
+            .iter()
+            .map(|&(score, correct)| (calibration.calibrate_score(score), correct))
+            .collect();
+        assert!(ece.compute(&calibrated) < ece.compute(&samples));
+    }
+
+    #[test]
+    fn temperature_preserves_ranking() {
+        let calibration = TemperatureScaling::new(FixedModel { score: 0.9 }, 3.);
+
+        let scores = [0.1, 0.5, 0.7, 0.99].map(|s| calibration.calibrate_score(s));
+
+        assert!(scores.windows(2).all(|w| w[0] < w[1]));
+        assert!((scores[1] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn calibrated_detections_are_rescaled() {
+        let calibration = TemperatureScaling::new(FixedModel { score: 0.9 }, 2.);
+        let images = Tensor::zeros([1, 3, 8, 8], &Default::default());
+
+        let detections = calibration.infer(images);
+
+        assert_eq!(detections[0].len(), 10);
+        let expected = calibration.calibrate_score(0.9);
+        assert!(detections[0].iter().all(|d| d.score == expected));
+    }
+
+    #[test]
+    fn threshold_applies_to_calibrated_scores() {
+        let mut calibration = PlattScaling::new(FixedModel { score: 0.45 });
+        (calibration.a, calibration.b) = (1., 1.);
+        let images = Tensor::<TestBackend, 4>::zeros([1, 3, 8, 8], &Default::default());
+
+        // The raw score is below the threshold but not the calibrated score
+        let detections = calibration.infer(images.clone());
+        assert_eq!(detections[0].len(), 10);
+        assert!(detections[0].iter().all(|d| d.score > 0.5));
+
+        calibration.b = -1.;
+        assert!(calibration.infer(images)[0].is_empty());
+    }
+
+    #[test]
+    fn platt_scaling_fits_accuracy() {
+        // 50% correct at a score of 0.9 and 20% correct at a score of 0.6
+        let samples: Vec<_> = (0..10)
+            .map(|i| (0.9, i < 5))
+            .chain((0..10).map(|i| (0.6, i < 2)))
+            .collect();
+        let (a, b) = fit_platt(&samples);
+        let mut calibration = PlattScaling::new(FixedModel { score: 0.9 });
+        (calibration.a, calibration.b) = (a, b);
+
+        assert!((calibration.calibrate_score(0.9) - 0.5).abs() < 1e-3);
+        assert!((calibration.calibrate_score(0.6) - 0.2).abs() < 1e-3);
+        let ece = Ece::new(10);
+        let calibrated: Vec<_> = samples
+            .iter()
+            .map(|&(score, correct)| (calibration.calibrate_score(score), correct))
+            .collect();
+        assert!(ece.compute(&calibrated) < 1e-3);
+    }
+
+    #[test]
+    fn platt_scaling_calibrate() {
+        let mut calibration = PlattScaling::new(FixedModel { score: 0.9 });
+        assert_eq!(calibration.params(), (1., 0.));
+
+        calibration.calibrate(val_loader(5));
+
+        assert!((calibration.calibrate_score(0.9) - 0.5).abs() < 1e-2);
+    }
+
+    #[test]
+    fn expected_calibration_error() {
+        // Bin [0.8, 0.9): mean score 0.85 with accuracy 0.5, bin [0.2, 0.3): perfectly calibrated
+        let samples = [
+            (0.8, true),
+            (0.9 - 1e-3, false),
+            (0.25, true),
+            (0.25, false),
+            (0.25, false),
+            (0.25, false),
+        ];
+
+        let ece = Ece::new(10).compute(&samples);
+
+        let expected = (2. * (0.8495 - 0.5)) / 6.;
+        assert!((ece - expected).abs() < 1e-6, "ece {ece}");
+        assert_eq!(Ece::new(5).compute(&[]), 0.);
+    }
+
+    #[test]
+    #[should_panic = "number of bins should be positive"]
+    fn ece_without_bins() {
+        Ece::new(0);
+    }
+}
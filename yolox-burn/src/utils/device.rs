@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    tensor::{
+        backend::{Backend, SyncType},
+        Device,
+    },
+};
+
+/// Move all the parameters and buffers of a model to the specified device.
+///
+/// # Example
+///
+/// ```ignore
+/// let model = to_device(model, &gpu_device);
+/// let out = model.forward(input.to_device(&gpu_device));
+/// ```
+pub fn to_device<B: Backend, M: Module<B>>(model: M, device: &Device<B>) -> M {
+    DeviceMover::new(device).move_module(model)
+}
+
+/// Moves the tensors of one or more modules to a device.
+///
+/// All the tensors are transferred in a single pass over the module tree before synchronizing
+/// the target device once, which avoids a host-device round-trip per module.
+#[derive(Debug, Clone)]
+pub struct DeviceMover<B: Backend> {
+    device: Device<B>,
+}
+
+impl<B: Backend> DeviceMover<B> {
+    /// Create a new mover to the target device.
+    pub fn new(device: &Device<B>) -> Self {
+        Self {
+            device: device.clone(),
+        }
+    }
+
+    /// Move a single module.
+    pub fn move_module<M: Module<B>>(&self, module: M) -> M {
+        let module = module.to_device(&self.device);
+        B::sync(&self.device, SyncType::Wait);
+        module
+    }
+
+    /// Move several modules.
+    pub fn move_all<M: Module<B>>(&self, modules: Vec<M>) -> Vec<M> {
+        let modules = modules
+            .into_iter()
+            .map(|m| m.to_device(&self.device))
+            .collect();
+        B::sync(&self.device, SyncType::Wait);
+        modules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::{
+        backend::NdArray,
+        nn::{Linear, LinearConfig},
+        tensor::Tensor,
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn linear() -> Linear<TestBackend> {
+        LinearConfig::new(4, 2).init(&Default::default())
+    }
+
+    #[test]
+    fn forward_after_to_device() {
+        let device = Default::default();
+        let model = linear();
+        let expected = model.forward(Tensor::ones([3, 4], &device)).into_data();
+
+        let model = to_device(model, &device);
+        let out = model.forward(Tensor::ones([3, 4], &device));
+
+        assert_eq!(model.devices(), vec![device]);
+        out.into_data().assert_eq(&expected, true);
+    }
+
+    #[test]
+    fn move_all_modules() {
+        let device = Default::default();
+        let modules = vec![linear(), linear()];
+        let weights: Vec<_> = modules.iter().map(|m| m.weight.val().into_data()).collect();
+
+        let modules = DeviceMover::new(&device).move_all(modules);
+
+        assert_eq!(modules.len(), 2);
+        for (module, weight) in modules.iter().zip(weights) {
+            assert_eq!(module.devices(), vec![device]);
+            module.weight.val().into_data().assert_eq(&weight, true);
+            let out = module.forward(Tensor::ones([1, 4], &device));
+            assert_eq!(out.dims(), [1, 2]);
+        }
+    }
+
+    #[test]
+    fn move_keeps_params() {
+        let device = Default::default();
+        let model = DeviceMover::new(&device).move_module(linear());
+
+        assert_eq!(model.num_params(), 4 * 2 + 2);
+        assert!(model.bias.is_some());
+    }
+}
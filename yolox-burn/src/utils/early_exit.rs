@@ -0,0 +1,269 @@
+use alloc::vec::Vec;
+use burn::{
+    module::Module,
+    nn::{loss::CrossEntropyLossConfig, Linear, LinearConfig},
+    tensor::{activation::softmax, backend::Backend, Device, ElementConversion, Int, Tensor},
+};
+
+use crate::model::darknet::{CspDarknet, CspDarknetConfig};
+
+/// A backbone that can be applied stage by stage.
+pub trait StagedBackbone<B: Backend> {
+    /// Number of stages.
+    fn num_stages(&self) -> usize;
+
+    /// Apply the stage at the given index to the output of the previous stage.
+    fn forward_stage(&self, stage: usize, x: Tensor<B, 4>) -> Tensor<B, 4>;
+}
+
+/// Configuration of a [staged backbone](StagedBackbone).
+pub trait StagedBackboneConfig {
+    type Backbone<B: Backend>: StagedBackbone<B> + Module<B>;
+
+    /// Number of output channels of each stage.
+    fn stage_channels(&self) -> Vec<usize>;
+
+    /// Initialize a new backbone module.
+    fn init_backbone<B: Backend>(&self, device: &Device<B>) -> Self::Backbone<B>;
+}
+
+impl<B: Backend> StagedBackbone<B> for CspDarknet<B> {
+    fn num_stages(&self) -> usize {
+        CspDarknet::num_stages(self)
+    }
+
+    fn forward_stage(&self, stage: usize, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        CspDarknet::forward_stage(self, stage, x)
+    }
+}
+
+impl StagedBackboneConfig for CspDarknetConfig {
+    type Backbone<B: Backend> = CspDarknet<B>;
+
+    fn stage_channels(&self) -> Vec<usize> {
+        CspDarknetConfig::stage_channels(self).to_vec()
+    }
+
+    fn init_backbone<B: Backend>(&self, device: &Device<B>) -> CspDarknet<B> {
+        self.init(device)
+    }
+}
+
+/// Backbone with lightweight classification exits at intermediate stages.
+///
+/// Inference stops at the first exit where the prediction is confident enough, so that easy
+/// images skip the deeper stages of the backbone.
+#[derive(Module, Debug)]
+pub struct EarlyExitBackbone<B: Backend, M: Module<B>> {
+    backbone: M,
+    /// Exit heads (global average pooling followed by a linear classifier).
+    heads: Vec<Linear<B>>,
+    exit_points: Vec<usize>,
+    exit_threshold: f64,
+}
+
+impl<B: Backend, M: Module<B> + StagedBackbone<B>> EarlyExitBackbone<B, M> {
+    /// Apply the backbone until an exit is taken.
+    ///
+    /// An exit is taken when the uncertainty `1 - max(softmax(logits))` of all the images in the
+    /// batch is lower than the exit threshold. With a threshold of 1, inference always stops at
+    /// the first exit. With a threshold of 0, the final stage is always reached.
+    ///
+    /// # Returns
+    ///
+    /// The features of the last computed stage and the index of this stage.
+    pub fn forward(&self, x: Tensor<B, 4>) -> (Tensor<B, 4>, usize) {
+        let num_stages = self.backbone.num_stages();
+        let mut x = x;
+        for stage in 0..num_stages {
+            x = self.backbone.forward_stage(stage, x);
+
+            if let Some(head) = self.exit_head(stage) {
+                let confidence = softmax(head.forward(pool(x.clone())), 1)
+                    .max_dim(1)
+                    .min()
+                    .into_scalar()
+                    .elem::<f64>();
+                if 1. - confidence < self.exit_threshold {
+                    return (x, stage);
+                }
+            }
+        }
+
+        (x, num_stages - 1)
+    }
+
+    /// Compute the classification loss of all the exit heads, averaged over the exits.
+    ///
+    /// All the stages are applied, so that each exit head is trained.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input images.
+    /// * `targets` - Class index of each image. Shape: `[batch_size]`.
+    pub fn train_with_aux_losses(
+        &self,
+        x: Tensor<B, 4>,
+        targets: Tensor<B, 1, Int>,
+    ) -> Tensor<B, 1> {
+        let loss_fn = CrossEntropyLossConfig::new().init(&targets.device());
+
+        let mut x = x;
+        let mut losses = Vec::with_capacity(self.heads.len());
+        for stage in 0..self.backbone.num_stages() {
+            x = self.backbone.forward_stage(stage, x);
+
+            if let Some(head) = self.exit_head(stage) {
+                let logits = head.forward(pool(x.clone()));
+                losses.push(loss_fn.forward(logits, targets.clone()));
+            }
+        }
+
+        let num_exits = losses.len();
+        Tensor::cat(losses, 0).sum().div_scalar(num_exits as f32)
+    }
+
+    /// The wrapped backbone.
+    pub fn backbone(&self) -> &M {
+        &self.backbone
+    }
+
+    fn exit_head(&self, stage: usize) -> Option<&Linear<B>> {
+        self.exit_points
+            .iter()
+            .position(|&p| p == stage)
+            .map(|i| &self.heads[i])
+    }
+}
+
+/// Global average pooling `[B, C, H, W] -> [B, C]`.
+fn pool<B: Backend>(x: Tensor<B, 4>) -> Tensor<B, 2> {
+    let [b, c, _, _] = x.dims();
+    x.flatten::<3>(2, 3).mean_dim(2).reshape([b, c])
+}
+
+/// [Early exit backbone](EarlyExitBackbone) configuration.
+pub struct EarlyExitConfig<C> {
+    backbone: C,
+    exit_points: Vec<usize>,
+    exit_threshold: f64,
+    num_classes: usize,
+}
+
+impl<C: StagedBackboneConfig> EarlyExitConfig<C> {
+    /// Create a new instance of the early exit backbone [config](EarlyExitConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `backbone_config`: Backbone configuration.
+    /// * `exit_points` - Indices of the stages followed by an exit head.
+    /// * `exit_threshold` - Maximum uncertainty `1 - confidence` to take an exit, in `[0, 1]`.
+    pub fn new(backbone_config: C, exit_points: Vec<usize>, exit_threshold: f64) -> Self {
+        let num_stages = backbone_config.stage_channels().len();
+        assert!(
+            !exit_points.is_empty(),
+            "at least one exit point is required"
+        );
+        assert!(
+            exit_points.iter().all(|&p| p < num_stages),
+            "exit points should be valid stage indices (< {num_stages})"
+        );
+        assert!(
+            (0.0..=1.0).contains(&exit_threshold),
+            "exit threshold should be in range [0, 1]"
+        );
+
+        Self {
+            backbone: backbone_config,
+            exit_points,
+            exit_threshold,
+            num_classes: 1000,
+        }
+    }
+
+    /// Set the number of classes of the exit heads (defaults to 1000).
+    pub fn with_num_classes(mut self, num_classes: usize) -> Self {
+        self.num_classes = num_classes;
+        self
+    }
+
+    /// Initialize a new [early exit backbone](EarlyExitBackbone) module.
+    pub fn init<B: Backend>(&self, device: &Device<B>) -> EarlyExitBackbone<B, C::Backbone<B>> {
+        let channels = self.backbone.stage_channels();
+
+        EarlyExitBackbone {
+            backbone: self.backbone.init_backbone(device),
+            heads: self
+                .exit_points
+                .iter()
+                .map(|&p| LinearConfig::new(channels[p], self.num_classes).init(device))
+                .collect(),
+            exit_points: self.exit_points.clone(),
+            exit_threshold: self.exit_threshold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    /// Early exit CSPDarknet (with base channels 16) after dark3 and dark5.
+    fn early_exit(exit_threshold: f64) -> EarlyExitBackbone<TestBackend, CspDarknet<TestBackend>> {
+        let backbone = CspDarknetConfig::new(0.33, 0.25, false);
+        EarlyExitConfig::new(backbone, vec![1, 3], exit_threshold)
+            .with_num_classes(10)
+            .init(&Default::default())
+    }
+
+    fn images() -> Tensor<TestBackend, 4> {
+        Tensor::random([2, 3, 64, 64], Distribution::Default, &Default::default())
+    }
+
+    #[test]
+    fn always_exit_at_first_exit() {
+        let (features, exit_stage) = early_exit(1.).forward(images());
+
+        assert_eq!(exit_stage, 1);
+        // dark3 features (stride 8)
+        assert_eq!(features.dims(), [2, 64, 8, 8]);
+    }
+
+    #[test]
+    fn never_exit_early() {
+        let (features, exit_stage) = early_exit(0.).forward(images());
+
+        assert_eq!(exit_stage, 3);
+        // dark5 features (stride 32)
+        assert_eq!(features.dims(), [2, 256, 2, 2]);
+    }
+
+    #[test]
+    fn aux_losses() {
+        let model = early_exit(0.5);
+        let targets = Tensor::from_ints([3, 7], &Default::default());
+
+        let loss = model.train_with_aux_losses(images(), targets);
+
+        assert_eq!(loss.dims(), [1]);
+        // Near uniform predictions of the freshly initialized exits over 10 classes
+        let loss = loss.into_scalar();
+        assert!(loss.is_finite() && loss > 0.);
+    }
+
+    #[test]
+    #[should_panic = "exit points should be valid stage indices (< 4)"]
+    fn invalid_exit_point() {
+        EarlyExitConfig::new(CspDarknetConfig::new(0.33, 0.25, false), vec![4], 0.5);
+    }
+
+    #[test]
+    #[should_panic = "at least one exit point is required"]
+    fn no_exit_point() {
+        EarlyExitConfig::new(CspDarknetConfig::new(0.33, 0.25, false), vec![], 0.5);
+    }
+}
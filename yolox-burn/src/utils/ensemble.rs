@@ -0,0 +1,299 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::cmp::Ordering;
+
+use burn::tensor::{backend::Backend, Tensor};
+
+use crate::{
+    metrics::box_iou,
+    model::{boxes::non_maximum_suppression, DetectionModel},
+    types::{Detection, IoUThreshold},
+};
+
+/// Strategy to merge the detections of the [ensemble](ModelEnsemble) models.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FusionStrategy {
+    /// Class-wise non-maximum suppression with the given IoU threshold: overlapping boxes are
+    /// removed in favor of the highest scoring one.
+    Nms(IoUThreshold),
+    /// [Weighted boxes fusion](https://arxiv.org/abs/1910.13302) with the given IoU threshold and
+    /// model weights: overlapping boxes are merged into a single box with coordinates averaged by
+    /// confidence.
+    Wbf(IoUThreshold, Vec<f32>),
+}
+
+/// Ensemble of (possibly heterogeneous) detection models.
+pub struct ModelEnsemble<B: Backend> {
+    models: Vec<Box<dyn DetectionModel<B>>>,
+    fusion: FusionStrategy,
+}
+
+impl<B: Backend> ModelEnsemble<B> {
+    /// Create a new ensemble.
+    ///
+    /// # Panics
+    ///
+    /// If the number of [weighted boxes fusion](FusionStrategy::Wbf) weights does not match the
+    /// number of models.
+    pub fn new(models: Vec<Box<dyn DetectionModel<B>>>, fusion: FusionStrategy) -> Self {
+        if let FusionStrategy::Wbf(_, weights) = &fusion {
+            assert_eq!(
+                weights.len(),
+                models.len(),
+                "expected one fusion weight per model"
+            );
+        }
+
+        Self { models, fusion }
+    }
+
+    /// Run all the models and merge their detections for each image.
+    pub fn forward(&self, x: Tensor<B, 4>) -> Vec<Vec<Detection>> {
+        let [batch_size, _, _, _] = x.dims();
+
+        // Detections per image, with the index of the model
+        let mut per_image: Vec<Vec<(usize, Detection)>> = vec![Vec::new(); batch_size];
+        for (m, model) in self.models.iter().enumerate() {
+            for (image, detections) in per_image.iter_mut().zip(model.infer(x.clone())) {
+                image.extend(detections.into_iter().map(|det| (m, det)));
+            }
+        }
+
+        per_image
+            .into_iter()
+            .map(|detections| match &self.fusion {
+                FusionStrategy::Nms(iou_threshold) => nms(detections, *iou_threshold),
+                FusionStrategy::Wbf(iou_threshold, weights) => {
+                    wbf(detections, *iou_threshold, weights)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sort detections by decreasing score.
+fn sort_by_score(detections: &mut [(usize, Detection)]) {
+    detections.sort_by(|(_, a), (_, b)| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+}
+
+/// Class-wise non-maximum suppression of the detections of all models.
+pub(crate) fn nms(
+    detections: Vec<(usize, Detection)>,
+    iou_threshold: IoUThreshold,
+) -> Vec<Detection> {
+    let num_classes = detections
+        .iter()
+        .map(|(_, det)| det.class_id + 1)
+        .max()
+        .unwrap_or(0);
+    let mut per_class: Vec<Vec<Detection>> = vec![Vec::new(); num_classes];
+    for (_, det) in detections {
+        per_class[det.class_id].push(det);
+    }
+
+    non_maximum_suppression(&mut per_class, iou_threshold);
+
+    let mut kept: Vec<Detection> = per_class.into_iter().flatten().collect();
+    kept.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    kept
+}
+
+/// Weighted boxes fusion of the detections of all models.
+pub(crate) fn wbf(
+    mut detections: Vec<(usize, Detection)>,
+    iou_threshold: IoUThreshold,
+    weights: &[f32],
+) -> Vec<Detection> {
+    sort_by_score(&mut detections);
+
+    // Clusters of matching boxes, with the current fused box
+    let mut clusters: Vec<(Detection, Vec<(usize, Detection)>)> = Vec::new();
+    for (m, det) in detections {
+        let matched = clusters.iter_mut().find(|(fused, _)| {
+            fused.class_id == det.class_id
+                && box_iou(&fused.box_xyxy, &det.box_xyxy) > iou_threshold.value()
+        });
+
+        match matched {
+            Some((fused, members)) => {
+                members.push((m, det));
+                *fused = fuse(members, weights);
+            }
+            None => clusters.push((det.clone(), vec![(m, det)])),
+        }
+    }
+
+    // Rescale confidence by the (weighted) number of models that agree
+    let total_weight: f32 = weights.iter().sum();
+    clusters
+        .into_iter()
+        .map(|(mut fused, members)| {
+            let weighted_score: f32 = members.iter().map(|(m, d)| d.score * weights[*m]).sum();
+            fused.score = weighted_score / total_weight;
+            fused
+        })
+        .collect()
+}
+
+/// Average the box coordinates of the cluster members, weighted by confidence.
+fn fuse(members: &[(usize, Detection)], weights: &[f32]) -> Detection {
+    let mut fused = members[0].1.clone();
+    let mut box_xyxy = [0.; 4];
+    let mut weight_sum = 0.;
+    for (m, det) in members {
+        let weight = det.score * weights[*m];
+        for (v, coord) in box_xyxy.iter_mut().zip(det.box_xyxy) {
+            *v += weight * coord;
+        }
+        weight_sum += weight;
+    }
+
+    if weight_sum > 0. {
+        fused.box_xyxy = box_xyxy.map(|v| v / weight_sum);
+    }
+    fused.score = members.iter().map(|(_, d)| d.score).sum::<f32>() / members.len() as f32;
+
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::DetectionRawOutput;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    /// Model predicting a single `(cx, cy, w, h)` box of class 0 with the given score.
+    struct FixedModel {
+        box_cxcywh: [f32; 4],
+        score: f32,
+    }
+
+    impl DetectionModel<TestBackend> for FixedModel {
+        fn forward_raw(&self, images: Tensor<TestBackend, 4>) -> DetectionRawOutput<TestBackend> {
+            let [batch_size, _, _, _] = images.dims();
+            let [cx, cy, w, h] = self.box_cxcywh;
+            let out = Tensor::<TestBackend, 1>::from_floats(
+                [cx, cy, w, h, 1., self.score, 0.],
+                &images.device(),
+            )
+            .reshape([1, 1, 7])
+            .repeat_dim(0, batch_size);
+
+            DetectionRawOutput::AnchorFree(out)
+        }
+
+        fn num_classes(&self) -> usize {
+            2
+        }
+    }
+
+    fn ensemble(models: [FixedModel; 2], fusion: FusionStrategy) -> Vec<Vec<Detection>> {
+        let models = models
+            .into_iter()
+            .map(|m| Box::new(m) as Box<dyn DetectionModel<TestBackend>>)
+            .collect();
+        let x = Tensor::zeros([2, 3, 8, 8], &Default::default());
+
+        ModelEnsemble::new(models, fusion).forward(x)
+    }
+
+    fn identical_models() -> [FixedModel; 2] {
+        [
+            FixedModel {
+                box_cxcywh: [50., 50., 20., 20.],
+                score: 0.9,
+            },
+            FixedModel {
+                box_cxcywh: [50., 50., 20., 20.],
+                score: 0.9,
+            },
+        ]
+    }
+
+    #[test]
+    fn nms_merges_duplicates() {
+        let detections = ensemble(
+            identical_models(),
+            FusionStrategy::Nms(IoUThreshold::new(0.5).unwrap()),
+        );
+
+        assert_eq!(detections.len(), 2);
+        for (image_id, image) in detections.iter().enumerate() {
+            assert_eq!(
+                image,
+                &[Detection::new(image_id, [40., 40., 60., 60.], 0.9, 0)]
+            );
+        }
+    }
+
+    #[test]
+    fn wbf_merges_duplicates() {
+        let detections = ensemble(
+            identical_models(),
+            FusionStrategy::Wbf(IoUThreshold::new(0.5).unwrap(), vec![1., 1.]),
+        );
+
+        for image in detections.iter() {
+            assert_eq!(image.len(), 1);
+            assert_eq!(image[0].box_xyxy, [40., 40., 60., 60.]);
+            assert!((image[0].score - 0.9).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn nms_keeps_high_confidence_box() {
+        let models = [
+            FixedModel {
+                box_cxcywh: [52., 51., 20., 20.],
+                score: 0.6,
+            },
+            FixedModel {
+                box_cxcywh: [50., 50., 20., 20.],
+                score: 0.95,
+            },
+        ];
+
+        let detections = ensemble(models, FusionStrategy::Nms(IoUThreshold::new(0.5).unwrap()));
+
+        assert_eq!(
+            detections[0],
+            [Detection::new(0, [40., 40., 60., 60.], 0.95, 0)]
+        );
+    }
+
+    #[test]
+    fn wbf_favors_high_confidence_box() {
+        let models = [
+            FixedModel {
+                box_cxcywh: [60., 50., 20., 20.],
+                score: 0.6,
+            },
+            FixedModel {
+                box_cxcywh: [50., 50., 20., 20.],
+                score: 0.95,
+            },
+        ];
+
+        let detections = ensemble(
+            models,
+            FusionStrategy::Wbf(IoUThreshold::new(0.3).unwrap(), vec![1., 1.]),
+        );
+
+        assert_eq!(detections[0].len(), 1);
+        let xmin = detections[0][0].box_xyxy[0];
+        // Confidence weighted average of 40 and 50
+        assert!((xmin - (0.95 * 40. + 0.6 * 50.) / 1.55).abs() < 1e-4);
+        assert!(xmin < 45.);
+    }
+
+    #[test]
+    #[should_panic = "expected one fusion weight per model"]
+    fn wbf_weights_per_model() {
+        ensemble(
+            identical_models(),
+            FusionStrategy::Wbf(IoUThreshold::new(0.5).unwrap(), vec![1.]),
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use alloc::vec::Vec;
+use burn::{
+    module::{Module, ModuleMapper, ModuleVisitor, ParamId},
+    nn::Initializer,
+    tensor::{backend::Backend, Tensor},
+};
+
+/// Weight initialization strategy of the [Conv2d](burn::nn::conv::Conv2d) and
+/// [Linear](burn::nn::Linear) layers of a model (see [apply_weight_init]).
+///
+/// Unless stated otherwise, the biases are initialized to zero.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WeightInitStrategy {
+    /// Uniform [He initialization](https://arxiv.org/abs/1502.01852) for ReLU networks.
+    KaimingUniform,
+    /// Normal [He initialization](https://arxiv.org/abs/1502.01852) for ReLU networks.
+    KaimingNormal,
+    /// Uniform [Glorot initialization](https://proceedings.mlr.press/v9/glorot10a).
+    XavierUniform,
+    /// Normal [Glorot initialization](https://proceedings.mlr.press/v9/glorot10a).
+    XavierNormal,
+    /// Initialization of the classification layers trained with the
+    /// [focal loss](https://arxiv.org/abs/1708.02002): normal He initialization of the weights
+    /// and biases of `-log((1 - pi) / pi)`, so that the initial probability of each class is
+    /// `pi` (e.g., 0.01) and the loss is not dominated by the easy negatives.
+    FocalLossPrior { pi: f64 },
+    /// Constant weights.
+    Constant(f64),
+    /// Zero weights.
+    Zero,
+}
+
+impl WeightInitStrategy {
+    /// Initializer of the weights.
+    fn weight_initializer(&self) -> Initializer {
+        let gain = core::f64::consts::SQRT_2;
+        match self {
+            Self::KaimingUniform => Initializer::KaimingUniform {
+                gain,
+                fan_out_only: false,
+            },
+            Self::KaimingNormal | Self::FocalLossPrior { .. } => Initializer::KaimingNormal {
+                gain,
+                fan_out_only: false,
+            },
+            Self::XavierUniform => Initializer::XavierUniform { gain: 1. },
+            Self::XavierNormal => Initializer::XavierNormal { gain: 1. },
+            Self::Constant(value) => Initializer::Constant { value: *value },
+            Self::Zero => Initializer::Zeros,
+        }
+    }
+
+    /// Initial value of the biases.
+    fn bias_value(&self) -> f64 {
+        match self {
+            Self::FocalLossPrior { pi } => -((1. - pi) / pi).ln(),
+            _ => 0.,
+        }
+    }
+}
+
+/// Modules which declare their preferred [weight initialization](WeightInitStrategy), e.g., the
+/// classification layers trained with the focal loss.
+pub trait PreferredWeightInit {
+    /// Preferred weight initialization of the module.
+    fn preferred_weight_init(&self) -> WeightInitStrategy;
+}
+
+/// Initialize a module with its [preferred weight initialization](PreferredWeightInit).
+pub fn apply_preferred_weight_init<B: Backend, M: Module<B> + PreferredWeightInit>(model: &mut M) {
+    let strategy = model.preferred_weight_init();
+    apply_weight_init(model, &strategy);
+}
+
+/// Initialize the weights and biases of all the [Conv2d](burn::nn::conv::Conv2d) and
+/// [Linear](burn::nn::Linear) layers of a model.
+///
+/// The layers are identified by their parameters: a weight of rank 4 (convolution), optionally
+/// followed by a bias of its output size, or a weight of rank 2 followed by its bias (linear). The
+/// biases are told apart from the parameters of a following normalization layer (e.g., the scale
+/// and shift of a batch norm after a convolution without bias) as normalization layers have an
+/// even number of rank 1 parameters.
+///
+/// The other parameters are left unchanged. This includes the rank 2 parameters without bias,
+/// which are not told apart from the embedding tables, the relative position bias tables, the
+/// query embeddings or the LoRA updates, so linear layers without bias are not initialized.
+///
+/// To initialize a single layer or head (e.g., the classification layer with
+/// [FocalLossPrior](WeightInitStrategy::FocalLossPrior)), apply the initialization to that
+/// submodule only.
+pub fn apply_weight_init<B: Backend, M: Module<B>>(model: &mut M, strategy: &WeightInitStrategy) {
+    let mut collector = ShapeCollector { shapes: Vec::new() };
+    model.visit(&mut collector);
+
+    let mut mapper = WeightInitMapper {
+        roles: param_roles(&collector.shapes),
+        index: 0,
+        initializer: strategy.weight_initializer(),
+        bias: strategy.bias_value(),
+    };
+    *model = model.clone().map(&mut mapper);
+}
+
+/// Role of a parameter for the weight initialization.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParamRole {
+    /// Weight of a convolution or linear layer.
+    Weight { fan_in: usize, fan_out: usize },
+    /// Bias of a convolution or linear layer.
+    Bias,
+    /// Any other parameter, which is left unchanged.
+    Other,
+}
+
+/// Identify the weights and biases from the parameter shapes, in visiting order.
+fn param_roles(shapes: &[Vec<usize>]) -> Vec<ParamRole> {
+    let mut roles = Vec::with_capacity(shapes.len());
+
+    for (i, shape) in shapes.iter().enumerate() {
+        let role = match shape.as_slice() {
+            // Linear weight [d_input, d_output], only identified by its bias
+            &[d_input, d_output] if is_bias_at(shapes, i + 1, i) => ParamRole::Weight {
+                fan_in: d_input,
+                fan_out: d_output,
+            },
+            // Conv2d weight [out_channels, in_channels / groups, kernel_h, kernel_w]
+            &[out_channels, in_channels, kh, kw] => ParamRole::Weight {
+                fan_in: in_channels * kh * kw,
+                fan_out: out_channels * kh * kw,
+            },
+            &[size] if i > 0 && is_bias(shapes, i - 1, size) => ParamRole::Bias,
+            _ => ParamRole::Other,
+        };
+        roles.push(role);
+    }
+
+    roles
+}
+
+/// Whether the parameter at `index` is the bias of the parameter `prev`.
+fn is_bias_at(shapes: &[Vec<usize>], index: usize, prev: usize) -> bool {
+    match shapes.get(index).map(Vec::as_slice) {
+        Some(&[size]) => is_bias(shapes, prev, size),
+        _ => false,
+    }
+}
+
+/// Whether the rank 1 parameter of the given size following the parameter `prev` is its bias.
+pub(crate) fn is_bias(shapes: &[Vec<usize>], prev: usize, size: usize) -> bool {
+    let out_size = match shapes[prev].as_slice() {
+        &[_, d_output] => d_output,
+        &[out_channels, _, _, _] => out_channels,
+        _ => return false,
+    };
+    let run = shapes[prev + 1..]
+        .iter()
+        .take_while(|shape| shape.len() == 1)
+        .count();
+
+    size == out_size && run % 2 == 1
+}
+
+/// Collect the shapes of the float parameters of a module, in order.
+struct ShapeCollector {
+    shapes: Vec<Vec<usize>>,
+}
+
+impl<B: Backend> ModuleVisitor<B> for ShapeCollector {
+    fn visit_float<const D: usize>(&mut self, _id: &ParamId, tensor: &Tensor<B, D>) {
+        self.shapes.push(tensor.dims().to_vec());
+    }
+}
+
+/// Re-initialize the weights and biases, in the same order as the [collected](ShapeCollector)
+/// shapes.
+struct WeightInitMapper {
+    roles: Vec<ParamRole>,
+    index: usize,
+    initializer: Initializer,
+    bias: f64,
+}
+
+impl<B: Backend> ModuleMapper<B> for WeightInitMapper {
+    fn map_float<const D: usize>(&mut self, _id: &ParamId, tensor: Tensor<B, D>) -> Tensor<B, D> {
+        let role = self.roles[self.index];
+        self.index += 1;
+
+        let device = tensor.device();
+        let require_grad = tensor.is_require_grad();
+        let value = match role {
+            ParamRole::Weight { fan_in, fan_out } => self
+                .initializer
+                .init_with(tensor.shape(), Some(fan_in), Some(fan_out), &device)
+                .val(),
+            ParamRole::Bias => Tensor::full(tensor.shape(), self.bias, &device),
+            ParamRole::Other => return tensor,
+        };
+
+        value.set_require_grad(require_grad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::attention::{RelativePositionBias, RelativePositionBiasConfig},
+        utils::lora::{LoraConfig, LoraLinear},
+    };
+    use alloc::vec;
+    use burn::{
+        backend::NdArray,
+        module::Param,
+        nn::{
+            conv::{Conv2d, Conv2dConfig},
+            Embedding, EmbeddingConfig, Linear, LinearConfig,
+        },
+        tensor::TensorData,
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    #[derive(Module, Debug)]
+    struct Head<B: Backend> {
+        conv: Conv2d<B>,
+        cls: Linear<B>,
+    }
+
+    fn head() -> Head<TestBackend> {
+        let device = Default::default();
+        Head {
+            conv: Conv2dConfig::new([4, 8], [3, 3]).init(&device),
+            cls: LinearConfig::new(8, 3).init(&device),
+        }
+    }
+
+    #[test]
+    fn focal_loss_prior_bias() {
+        let mut head = head();
+
+        apply_weight_init(
+            &mut head.cls,
+            &WeightInitStrategy::FocalLossPrior { pi: 0.01 },
+        );
+
+        // -log(0.99 / 0.01)
+        head.cls
+            .bias
+            .unwrap()
+            .val()
+            .into_data()
+            .assert_approx_eq(&TensorData::from([-4.59512f32; 3]), 4);
+        // The other layers are unchanged
+        let conv_bias = head.conv.bias.unwrap().val().into_data().to_vec::<f32>();
+        assert!(conv_bias.unwrap().iter().any(|&b| b != -4.59512));
+    }
+
+    #[test]
+    fn constant_zero_weights() {
+        let mut head = head();
+
+        apply_weight_init(&mut head, &WeightInitStrategy::Constant(0.));
+
+        head.conv
+            .weight
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::zeros::<f32, _>([8, 4, 3, 3]), false);
+        head.cls
+            .weight
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::zeros::<f32, _>([8, 3]), false);
+        head.cls
+            .bias
+            .unwrap()
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::zeros::<f32, _>([3]), false);
+    }
+
+    #[test]
+    fn constant_weights() {
+        let mut head = head();
+
+        apply_weight_init(&mut head, &WeightInitStrategy::Constant(0.5));
+
+        head.cls
+            .weight
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::from([[0.5f32; 3]; 8]), false);
+    }
+
+    #[derive(Module, Debug)]
+    struct Transformer<B: Backend> {
+        embedding: Embedding<B>,
+        queries: Param<Tensor<B, 2>>,
+        position_bias: RelativePositionBias<B>,
+        qkv: LoraLinear<B>,
+    }
+
+    #[test]
+    fn embeddings_left_unchanged() {
+        let device = Default::default();
+        let lora = LoraConfig::new(2, 4., vec!["qkv".into()]);
+        let mut model = Transformer::<TestBackend> {
+            embedding: EmbeddingConfig::new(10, 8).init(&device),
+            queries: Initializer::Ones.init([5, 8], &device),
+            position_bias: RelativePositionBiasConfig::new(3, 8).init(&device),
+            qkv: lora.init_linear(LinearConfig::new(8, 24).init(&device)),
+        };
+        let embedding = model.embedding.weight.val();
+        let position_bias = model.position_bias.forward();
+
+        apply_weight_init(&mut model, &WeightInitStrategy::Constant(0.5));
+
+        model
+            .embedding
+            .weight
+            .val()
+            .into_data()
+            .assert_eq(&embedding.into_data(), false);
+        model
+            .queries
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::from([[1f32; 8]; 5]), false);
+        model
+            .position_bias
+            .forward()
+            .into_data()
+            .assert_eq(&position_bias.into_data(), false);
+        // The LoRA update is still zero, only the base layer is initialized
+        model
+            .qkv
+            .delta_weight()
+            .into_data()
+            .assert_eq(&TensorData::zeros::<f32, _>([8, 24]), false);
+        model
+            .qkv
+            .merge()
+            .weight
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::from([[0.5f32; 24]; 8]), false);
+    }
+
+    #[test]
+    fn linear_weight_needs_bias() {
+        let roles = param_roles(&[vec![8, 3], vec![3], vec![4, 3], vec![6, 3]]);
+
+        assert_eq!(
+            roles,
+            [
+                ParamRole::Weight {
+                    fan_in: 8,
+                    fan_out: 3
+                },
+                ParamRole::Bias,
+                ParamRole::Other,
+                ParamRole::Other
+            ]
+        );
+    }
+
+    #[test]
+    fn biases_told_apart_from_normalization() {
+        let conv = vec![8, 4, 3, 3];
+        let weight = ParamRole::Weight {
+            fan_in: 4 * 9,
+            fan_out: 8 * 9,
+        };
+
+        // Convolution with bias
+        let roles = param_roles(&[conv.clone(), vec![8]]);
+        assert_eq!(roles, [weight, ParamRole::Bias]);
+
+        // Convolution without bias, followed by a batch norm
+        let roles = param_roles(&[conv.clone(), vec![8], vec![8], vec![8], vec![8]]);
+        assert_eq!(
+            roles,
+            [
+                weight,
+                ParamRole::Other,
+                ParamRole::Other,
+                ParamRole::Other,
+                ParamRole::Other
+            ]
+        );
+
+        // Convolution with bias, followed by a batch norm
+        let roles = param_roles(&[conv, vec![8], vec![8], vec![8], vec![8], vec![8]]);
+        assert_eq!(roles[1], ParamRole::Bias);
+        assert_eq!(roles[2], ParamRole::Other);
+    }
+}
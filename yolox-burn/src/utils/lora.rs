@@ -0,0 +1,291 @@
+//! [Low-Rank Adaptation](https://arxiv.org/abs/2106.09685) (LoRA) of linear layers for
+//! fine-tuning.
+//!
+//! The adaptation is applied to individual layers. Burn modules can only be traversed to map
+//! their tensors, not to change the type of their sub-modules, so there is no model-level
+//! conversion: a model is adapted by building its linear layers with [LoraConfig::apply] (which
+//! returns an [AdaptedLinear] for each layer path) and merged for deployment with
+//! [merge_lora_weights]. The transformer models of the crate (e.g., [ViT](crate::model::vit::Vit))
+//! use the Burn transformer encoder and cannot be adapted.
+use alloc::{string::String, vec::Vec};
+use burn::{
+    module::{Module, Param},
+    nn::{Initializer, Linear},
+    tensor::{backend::Backend, Tensor},
+};
+
+/// Linear layer with a frozen base weight and a trainable low-rank update.
+///
+/// The output is `base(x) + alpha / rank * x @ A @ B`, where `A: [d_input, rank]` is initialized
+/// randomly and `B: [rank, d_output]` is initialized to zero, so that the layer initially computes
+/// the same output as the base layer.
+#[derive(Module, Debug)]
+pub struct LoraLinear<B: Backend> {
+    base: Linear<B>,
+    lora_a: Param<Tensor<B, 2>>,
+    lora_b: Param<Tensor<B, 2>>,
+    scaling: f64,
+}
+
+impl<B: Backend> LoraLinear<B> {
+    /// # Shapes
+    ///   - x: `[..., d_input]`
+    ///   - output: `[..., d_output]`
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        let update = x
+            .clone()
+            .matmul(self.lora_a.val().unsqueeze())
+            .matmul(self.lora_b.val().unsqueeze());
+
+        self.base.forward(x) + update.mul_scalar(self.scaling)
+    }
+
+    /// Low-rank update `alpha / rank * A @ B` of the base weight. Shape: `[d_input, d_output]`.
+    pub fn delta_weight(&self) -> Tensor<B, 2> {
+        self.lora_a
+            .val()
+            .matmul(self.lora_b.val())
+            .mul_scalar(self.scaling)
+    }
+
+    /// Fold the low-rank update into the base weight, for deployment.
+    pub fn merge(self) -> Linear<B> {
+        let weight = self.base.weight.val() + self.delta_weight();
+
+        Linear {
+            weight: Param::from_tensor(weight),
+            bias: self.base.bias,
+        }
+    }
+}
+
+/// [LoRA](LoraLinear) configuration.
+#[derive(Debug, Clone)]
+pub struct LoraConfig {
+    rank: usize,
+    alpha: f64,
+    target_modules: Vec<String>,
+}
+
+impl LoraConfig {
+    /// Create a new instance of the LoRA [config](LoraConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `rank`: Rank of the weight update.
+    /// * `alpha` - Scale of the weight update, divided by the rank.
+    /// * `target_modules` - Paths (or path suffixes) of the adapted linear layers, e.g.,
+    ///   `attn.qkv` for all the `qkv` projections of the `attn` modules.
+    pub fn new(rank: usize, alpha: f64, target_modules: Vec<String>) -> Self {
+        assert!(rank > 0, "the rank should be positive");
+
+        Self {
+            rank,
+            alpha,
+            target_modules,
+        }
+    }
+
+    /// Whether the linear layer with the given path (e.g., `encoder.blocks.0.attn.qkv`) should
+    /// be adapted, i.e. it matches one of the target modules up to a path separator.
+    pub fn is_target(&self, path: &str) -> bool {
+        self.target_modules.iter().any(|target| {
+            path.strip_suffix(target.as_str())
+                .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.'))
+        })
+    }
+
+    /// Wrap a linear layer into a [LoRA layer](LoraLinear), freezing its weights.
+    pub fn init_linear<B: Backend>(&self, linear: Linear<B>) -> LoraLinear<B> {
+        let [d_input, d_output] = linear.weight.dims();
+        let device = linear.weight.device();
+
+        // Uniform initialization in [-1/sqrt(d_input), 1/sqrt(d_input)], B being zero
+        let lora_a = Initializer::KaimingUniform {
+            gain: 1. / 3f64.sqrt(),
+            fan_out_only: false,
+        }
+        .init_with([d_input, self.rank], Some(d_input), None, &device);
+        let lora_b = Initializer::Zeros.init([self.rank, d_output], &device);
+
+        LoraLinear {
+            base: linear.no_grad(),
+            lora_a,
+            lora_b,
+            scaling: self.alpha / self.rank as f64,
+        }
+    }
+
+    /// Wrap the linear layer with the given path if it is [targeted](Self::is_target), or
+    /// return it unchanged.
+    pub fn apply<B: Backend>(&self, path: &str, linear: Linear<B>) -> AdaptedLinear<B> {
+        match self.is_target(path) {
+            true => AdaptedLinear::Lora(self.init_linear(linear)),
+            false => AdaptedLinear::Base(linear),
+        }
+    }
+}
+
+/// Linear layer which may be [adapted](LoraConfig::apply) with LoRA.
+#[derive(Module, Debug)]
+pub enum AdaptedLinear<B: Backend> {
+    /// Linear layer without adaptation.
+    Base(Linear<B>),
+    /// Linear layer with a low-rank update.
+    Lora(LoraLinear<B>),
+}
+
+impl<B: Backend> AdaptedLinear<B> {
+    pub fn forward<const D: usize>(&self, x: Tensor<B, D>) -> Tensor<B, D> {
+        match self {
+            Self::Base(linear) => linear.forward(x),
+            Self::Lora(lora) => lora.forward(x),
+        }
+    }
+
+    /// Fold the low-rank update (if any) into the base weight, for deployment.
+    pub fn merge(self) -> Linear<B> {
+        match self {
+            Self::Base(linear) => linear,
+            Self::Lora(lora) => lora.merge(),
+        }
+    }
+}
+
+/// Fold the low-rank updates of [adapted layers](AdaptedLinear) into their base weights.
+///
+/// The layers are merged individually, in order, to be loaded in the linear layers of the model
+/// used for deployment.
+pub fn merge_lora_weights<B: Backend>(layers: Vec<AdaptedLinear<B>>) -> Vec<Linear<B>> {
+    layers.into_iter().map(AdaptedLinear::merge).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::ToString, vec};
+    use burn::{
+        backend::{Autodiff, NdArray},
+        nn::LinearConfig,
+        tensor::{Distribution, TensorData},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    fn config() -> LoraConfig {
+        LoraConfig::new(4, 8., vec!["attn.qkv".to_string()])
+    }
+
+    #[test]
+    fn init_a_random_b_zero() {
+        let device = Default::default();
+        let linear = LinearConfig::new(16, 8).init::<TestBackend>(&device);
+
+        let lora = config().init_linear(linear);
+
+        let a = lora.lora_a.val();
+        assert_eq!(a.dims(), [16, 4]);
+        assert!(a.clone().abs().max().into_scalar() > 0.);
+        assert!(a.abs().max().into_scalar() <= 0.25);
+        lora.lora_b
+            .val()
+            .into_data()
+            .assert_eq(&TensorData::zeros::<f32, _>([4, 8]), false);
+        assert_eq!(lora.scaling, 2.);
+    }
+
+    #[test]
+    fn initial_output_equals_base() {
+        let device = Default::default();
+        let linear = LinearConfig::new(16, 8).init::<TestBackend>(&device);
+        let x = Tensor::<TestBackend, 3>::random([2, 5, 16], Distribution::Default, &device);
+        let expected = linear.forward(x.clone()).into_data();
+
+        let lora = config().init_linear(linear);
+
+        lora.forward(x).into_data().assert_eq(&expected, false);
+    }
+
+    #[test]
+    fn base_weights_frozen() {
+        type TrainingBackend = Autodiff<TestBackend>;
+        let device = Default::default();
+        let linear = LinearConfig::new(16, 8).init::<TrainingBackend>(&device);
+        let lora = config().init_linear(linear);
+        let x = Tensor::<TrainingBackend, 2>::random([3, 16], Distribution::Default, &device);
+
+        let grads = lora.forward(x).sum().backward();
+
+        assert!(lora.base.weight.grad(&grads).is_none());
+        assert!(lora.lora_a.grad(&grads).is_some());
+        assert!(lora.lora_b.grad(&grads).is_some());
+    }
+
+    #[test]
+    fn merge_matches_forward() {
+        let device = Default::default();
+        let linear = LinearConfig::new(16, 8).init::<TestBackend>(&device);
+        let mut lora = config().init_linear(linear);
+        lora.lora_b = Param::from_tensor(Tensor::random([4, 8], Distribution::Default, &device));
+        let x = Tensor::<TestBackend, 2>::random([3, 16], Distribution::Default, &device);
+        let expected = lora.forward(x.clone()).into_data();
+
+        let merged = merge_lora_weights(vec![AdaptedLinear::Lora(lora)]);
+
+        assert_eq!(merged.len(), 1);
+        merged[0]
+            .forward(x)
+            .into_data()
+            .assert_approx_eq(&expected, 4);
+    }
+
+    #[test]
+    fn merge_adapted_layers() {
+        let device = Default::default();
+        let config = LoraConfig::new(2, 2., vec!["qkv".to_string()]);
+        let qkv = LinearConfig::new(8, 24).init::<TestBackend>(&device);
+        let proj = LinearConfig::new(8, 8).init::<TestBackend>(&device);
+        let proj_weight = proj.weight.val().into_data();
+        let layers = vec![
+            config.apply("attn.qkv", qkv),
+            config.apply("attn.proj", proj),
+        ];
+        assert!(matches!(layers[0], AdaptedLinear::Lora(_)));
+
+        let merged = merge_lora_weights(layers);
+
+        assert_eq!(merged[0].weight.dims(), [8, 24]);
+        merged[1]
+            .weight
+            .val()
+            .into_data()
+            .assert_eq(&proj_weight, true);
+    }
+
+    #[test]
+    fn target_modules() {
+        let config = config();
+
+        assert!(config.is_target("attn.qkv"));
+        assert!(config.is_target("encoder.blocks.0.attn.qkv"));
+        assert!(!config.is_target("encoder.blocks.0.self_attn.qkv"));
+        assert!(!config.is_target("encoder.blocks.0.attn.proj"));
+
+        let device = Default::default();
+        let linear = || LinearConfig::new(4, 4).init::<TestBackend>(&device);
+        assert!(matches!(
+            config.apply("blocks.1.attn.qkv", linear()),
+            AdaptedLinear::Lora(_)
+        ));
+        assert!(matches!(
+            config.apply("blocks.1.mlp.fc1", linear()),
+            AdaptedLinear::Base(_)
+        ));
+    }
+
+    #[test]
+    #[should_panic = "the rank should be positive"]
+    fn zero_rank() {
+        let _ = LoraConfig::new(0, 1., Vec::new());
+    }
+}
@@ -0,0 +1,14 @@
+#[cfg(feature = "std")]
+pub mod benchmark;
+#[cfg(feature = "std")]
+pub mod calibration;
+pub mod device;
+pub mod early_exit;
+pub mod ensemble;
+pub mod init;
+pub mod lora;
+pub mod pruning;
+pub mod pyramid;
+pub mod transfer_learning;
+#[cfg(feature = "dataset")]
+pub mod visualize;
@@ -0,0 +1,599 @@
+//! Structured pruning of the convolution filters of a model.
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use burn::{
+    module::{Module, ModuleMapper, ModuleVisitor, ParamId},
+    tensor::{backend::Backend, Distribution, ElementConversion, Int, Tensor, TensorData},
+};
+
+use super::init::is_bias;
+
+/// Criterion ranking the convolution filters by importance, the least important filters being
+/// [pruned](structured_prune) first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PruningCriterion {
+    /// L1 norm of the filter weights.
+    L1Norm,
+    /// L2 norm of the filter weights.
+    L2Norm,
+    /// Random importance, as a baseline.
+    RandomPrune,
+}
+
+/// Pruned filters of a convolution layer.
+#[derive(Debug, Clone)]
+pub struct PrunedLayer {
+    /// ID of the convolution weight.
+    pub param_id: ParamId,
+    /// Number of output filters of the layer before pruning.
+    pub num_filters: usize,
+    /// Sorted indices of the pruned output filters.
+    pub pruned_filters: Vec<usize>,
+}
+
+impl PrunedLayer {
+    /// Sorted indices of the output filters which are kept.
+    pub fn kept_filters(&self) -> Vec<usize> {
+        (0..self.num_filters)
+            .filter(|i| self.pruned_filters.binary_search(i).is_err())
+            .collect()
+    }
+}
+
+/// Error of the [hard pruning](compact_model) of a layer which is not applied sequentially.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruningError {
+    /// The next convolution does not take the filters of the pruned layer as input channels,
+    /// e.g., after a concatenation or for a grouped convolution.
+    InputMismatch {
+        layer: usize,
+        num_filters: usize,
+        in_channels: usize,
+    },
+    /// The pruned layer is a depthwise convolution, whose number of groups cannot be updated.
+    Depthwise { layer: usize },
+    /// The outputs of the pruned layer have the width of its inputs, so they may be added to a
+    /// residual connection.
+    Residual { layer: usize },
+}
+
+impl fmt::Display for PruningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InputMismatch {
+                layer,
+                num_filters,
+                in_channels,
+            } => write!(
+                f,
+                "Layer {layer} has {num_filters} filters but the next convolution expects \
+                 {in_channels} input channels"
+            ),
+            Self::Depthwise { layer } => write!(f, "Layer {layer} is a depthwise convolution"),
+            Self::Residual { layer } => {
+                write!(f, "Layer {layer} may be part of a residual connection")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PruningError {}
+
+/// Filters removed from each convolution layer by [structured pruning](structured_prune).
+#[derive(Debug, Clone, Default)]
+pub struct PruningMask {
+    layers: Vec<PrunedLayer>,
+}
+
+impl PruningMask {
+    /// Pruned filters of each convolution layer, in visiting order.
+    pub fn layers(&self) -> &[PrunedLayer] {
+        &self.layers
+    }
+
+    /// Total number of pruned filters.
+    pub fn num_pruned(&self) -> usize {
+        self.layers.iter().map(|l| l.pruned_filters.len()).sum()
+    }
+
+    fn find(&self, id: &ParamId) -> Option<&PrunedLayer> {
+        self.layers.iter().find(|l| &l.param_id == id)
+    }
+}
+
+/// Prune the least important output filters of each [Conv2d](burn::nn::conv::Conv2d) layer of a
+/// model.
+///
+/// The convolution layers are identified by their rank 4 weights. For each layer, the
+/// `floor(pruning_ratio * num_filters)` filters with the lowest importance are selected (at
+/// least one filter is always kept), then [zeroed](apply_mask) without changing the tensor shapes.
+/// Use [compact_model] to actually remove them.
+///
+/// # Returns
+///
+/// The soft-pruned model and the pruned filters of each layer.
+pub fn structured_prune<B: Backend, M: Module<B>>(
+    model: M,
+    pruning_ratio: f32,
+    criterion: PruningCriterion,
+) -> (M, PruningMask) {
+    assert!(
+        (0.0..1.0).contains(&pruning_ratio),
+        "the pruning ratio should be in range [0, 1)"
+    );
+
+    let mut collector = ParamCollector::<B>::default();
+    model.visit(&mut collector);
+
+    let mut mask = PruningMask::default();
+    for (id, weights) in collector.conv_weights {
+        let [num_filters, _] = weights.dims();
+        let num_pruned = ((pruning_ratio * num_filters as f32) as usize).min(num_filters - 1);
+        if num_pruned == 0 {
+            continue;
+        }
+
+        let importance = match criterion {
+            PruningCriterion::L1Norm => weights.abs().sum_dim(1),
+            PruningCriterion::L2Norm => weights.powf_scalar(2.).sum_dim(1).sqrt(),
+            PruningCriterion::RandomPrune => {
+                Tensor::random([num_filters, 1], Distribution::Default, &weights.device())
+            }
+        };
+        let importance: Vec<f32> = importance
+            .into_data()
+            .iter::<B::FloatElem>()
+            .map(|v| v.elem())
+            .collect();
+
+        let mut order: Vec<usize> = (0..num_filters).collect();
+        order.sort_by(|&a, &b| importance[a].total_cmp(&importance[b]));
+        let mut pruned_filters = order[..num_pruned].to_vec();
+        pruned_filters.sort_unstable();
+
+        mask.layers.push(PrunedLayer {
+            param_id: id,
+            num_filters,
+            pruned_filters,
+        });
+    }
+
+    let mut model = model;
+    apply_mask(&mut model, &mask);
+
+    (model, mask)
+}
+
+/// Zero the pruned filters of the convolution layers of a model (soft pruning), along with their
+/// biases. The tensor shapes are unchanged.
+///
+/// The parameters of the normalization layers following the convolutions are left unchanged.
+pub fn apply_mask<B: Backend, M: Module<B>>(model: &mut M, mask: &PruningMask) {
+    let mut collector = ParamCollector::<B>::default();
+    model.visit(&mut collector);
+
+    let mut plan = vec![SlicePlan::default(); collector.ids.len()];
+    for (i, id) in collector.ids.iter().enumerate() {
+        if let Some(layer) = mask.find(id) {
+            plan[i].dim0 = Some(layer.kept_filters());
+            // Bias of the convolution
+            if i + 1 < plan.len() && is_bias(&collector.shapes, i, layer.num_filters) {
+                plan[i + 1].dim0 = Some(layer.kept_filters());
+            }
+        }
+    }
+
+    let mut mapper = MaskMapper {
+        plan,
+        index: 0,
+        hard: false,
+    };
+    *model = model.clone().map(&mut mapper);
+}
+
+/// Remove the pruned filters of the convolution layers of a model (hard pruning).
+///
+/// For each pruned convolution, the output filters are removed from its weight, and the
+/// corresponding channels from the rank 1 parameters which directly follow it (its bias and the
+/// parameters of a normalization layer) and from the input channels of the next convolution in
+/// visiting order.
+///
+/// # Errors
+///
+/// The convolutions have to be applied sequentially. As the model structure is only known from
+/// the parameter shapes, a [PruningError] is returned for the pruned layers (indexed in visiting
+/// order in the [mask](PruningMask::layers)) which:
+/// - are not followed by a convolution taking their filters as input (concatenations, grouped
+///   and depthwise convolutions);
+/// - are depthwise convolutions;
+/// - have as many filters as the input channels of the layer or of the previous convolution,
+///   as their outputs may be added to a residual connection.
+pub fn compact_model<B: Backend, M: Module<B>>(
+    model: M,
+    mask: &PruningMask,
+) -> Result<M, PruningError> {
+    let mut collector = ParamCollector::<B>::default();
+    model.visit(&mut collector);
+
+    let shapes = &collector.shapes;
+    let mut plan = vec![SlicePlan::default(); shapes.len()];
+    let mut prev_conv: Option<usize> = None;
+    for (i, id) in collector.ids.iter().enumerate() {
+        if shapes[i].len() != 4 {
+            continue;
+        }
+        let prev = prev_conv.replace(i);
+        let Some(index) = mask.layers.iter().position(|l| &l.param_id == id) else {
+            continue;
+        };
+        let layer = &mask.layers[index];
+        validate_layer(shapes, i, prev, index)?;
+        let kept = layer.kept_filters();
+        plan[i].dim0 = Some(kept.clone());
+
+        // Bias and normalization parameters
+        let mut j = i + 1;
+        while j < shapes.len() && shapes[j].as_slice() == [layer.num_filters] {
+            plan[j].dim0 = Some(kept.clone());
+            j += 1;
+        }
+
+        // Input channels of the next convolution
+        if let Some(k) = (j..shapes.len()).find(|&k| shapes[k].len() == 4) {
+            if shapes[k][1] != layer.num_filters {
+                return Err(PruningError::InputMismatch {
+                    layer: index,
+                    num_filters: layer.num_filters,
+                    in_channels: shapes[k][1],
+                });
+            }
+            plan[k].dim1 = Some(kept);
+        }
+    }
+
+    let mut mapper = MaskMapper {
+        plan,
+        index: 0,
+        hard: true,
+    };
+    Ok(model.map(&mut mapper))
+}
+
+/// Check that the pruned convolution weight `i` (following the convolution weight `prev`) is
+/// applied sequentially.
+fn validate_layer(
+    shapes: &[Vec<usize>],
+    i: usize,
+    prev: Option<usize>,
+    layer: usize,
+) -> Result<(), PruningError> {
+    let (num_filters, in_channels) = (shapes[i][0], shapes[i][1]);
+    let prev_out = prev.map(|prev| shapes[prev][0]);
+    let prev_in = prev.map(|prev| shapes[prev][1]);
+
+    if in_channels == 1 && num_filters > 1 && prev_out == Some(num_filters) {
+        return Err(PruningError::Depthwise { layer });
+    }
+    if in_channels == num_filters || prev_in == Some(num_filters) {
+        return Err(PruningError::Residual { layer });
+    }
+
+    Ok(())
+}
+
+/// Collect the IDs and shapes of the float parameters of a module in visiting order, and the
+/// flattened filters `[num_filters, fan_in]` of the convolution weights.
+struct ParamCollector<B: Backend> {
+    ids: Vec<ParamId>,
+    shapes: Vec<Vec<usize>>,
+    conv_weights: Vec<(ParamId, Tensor<B, 2>)>,
+}
+
+impl<B: Backend> Default for ParamCollector<B> {
+    fn default() -> Self {
+        Self {
+            ids: Vec::new(),
+            shapes: Vec::new(),
+            conv_weights: Vec::new(),
+        }
+    }
+}
+
+impl<B: Backend> ModuleVisitor<B> for ParamCollector<B> {
+    fn visit_float<const D: usize>(&mut self, id: &ParamId, tensor: &Tensor<B, D>) {
+        let shape = tensor.dims().to_vec();
+        // Conv2d weight [out_channels, in_channels / groups, kernel_h, kernel_w]
+        if let &[num_filters, c, kh, kw] = shape.as_slice() {
+            let weights = tensor.clone().reshape([num_filters, c * kh * kw]);
+            self.conv_weights.push((id.clone(), weights));
+        }
+        self.ids.push(id.clone());
+        self.shapes.push(shape);
+    }
+}
+
+/// Indices of the channels kept along the first two dimensions of a parameter.
+#[derive(Debug, Clone, Default)]
+struct SlicePlan {
+    dim0: Option<Vec<usize>>,
+    dim1: Option<Vec<usize>>,
+}
+
+/// Zero (soft pruning) or remove (hard pruning) the pruned channels of the parameters, in the
+/// same order as the [collected](ParamCollector) parameters.
+struct MaskMapper {
+    plan: Vec<SlicePlan>,
+    index: usize,
+    hard: bool,
+}
+
+impl<B: Backend> ModuleMapper<B> for MaskMapper {
+    fn map_float<const D: usize>(&mut self, _id: &ParamId, tensor: Tensor<B, D>) -> Tensor<B, D> {
+        let plan = self.plan[self.index].clone();
+        self.index += 1;
+
+        let require_grad = tensor.is_require_grad();
+        let mut tensor = tensor;
+        for (dim, kept) in [(0, plan.dim0), (1, plan.dim1)] {
+            let Some(kept) = kept else {
+                continue;
+            };
+            tensor = if self.hard {
+                keep_channels(tensor, dim, kept)
+            } else {
+                zero_channels(tensor, dim, &kept)
+            };
+        }
+
+        tensor.set_require_grad(require_grad)
+    }
+}
+
+/// Select the kept channels along a dimension.
+fn keep_channels<B: Backend, const D: usize>(
+    tensor: Tensor<B, D>,
+    dim: usize,
+    kept: Vec<usize>,
+) -> Tensor<B, D> {
+    let device = tensor.device();
+    let num_kept = kept.len();
+    let indices = kept.into_iter().map(|i| i as i64).collect::<Vec<_>>();
+    let indices = Tensor::<B, 1, Int>::from_data(
+        TensorData::new(indices, [num_kept]).convert::<B::IntElem>(),
+        &device,
+    );
+
+    tensor.select(dim, indices)
+}
+
+/// Zero the channels which are not kept along a dimension.
+fn zero_channels<B: Backend, const D: usize>(
+    tensor: Tensor<B, D>,
+    dim: usize,
+    kept: &[usize],
+) -> Tensor<B, D> {
+    let num_channels = tensor.dims()[dim];
+    let values = (0..num_channels)
+        .map(|i| {
+            if kept.binary_search(&i).is_ok() {
+                1.
+            } else {
+                0.
+            }
+        })
+        .collect::<Vec<f32>>();
+    let mut shape = [1; D];
+    shape[dim] = num_channels;
+    let mask = Tensor::<B, 1>::from_floats(values.as_slice(), &tensor.device()).reshape(shape);
+
+    tensor * mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::blocks::BaseConvConfig;
+    use burn::{
+        backend::NdArray,
+        module::Param,
+        nn::conv::{Conv2d, Conv2dConfig},
+    };
+
+    type TestBackend = NdArray<f32>;
+
+    /// Sequential convolutions.
+    #[derive(Module, Debug)]
+    struct TwoConvs<B: Backend> {
+        first: Conv2d<B>,
+        second: Conv2d<B>,
+    }
+
+    impl<B: Backend> TwoConvs<B> {
+        fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+            self.second.forward(self.first.forward(x))
+        }
+    }
+
+    fn two_convs(device: &<TestBackend as Backend>::Device) -> TwoConvs<TestBackend> {
+        TwoConvs {
+            first: Conv2dConfig::new([3, 8], [3, 3]).init(device),
+            second: Conv2dConfig::new([8, 4], [1, 1]).init(device),
+        }
+    }
+
+    /// Number of all-zero filters of each convolution weight.
+    fn zero_filters<M: Module<TestBackend>>(model: &M) -> Vec<usize> {
+        let mut collector = ParamCollector::<TestBackend>::default();
+        model.visit(&mut collector);
+
+        collector
+            .conv_weights
+            .into_iter()
+            .map(|(_, weights)| {
+                let [num_filters, _] = weights.dims();
+                let norms: Vec<f32> = weights.abs().sum_dim(1).into_data().to_vec().unwrap();
+                assert_eq!(norms.len(), num_filters);
+                norms.iter().filter(|&&n| n == 0.).count()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn soft_pruning_zeros_half_filters() {
+        let device = Default::default();
+        let conv = BaseConvConfig::new(8, 16, 3, 1, 1).init::<TestBackend>(&device);
+        let num_params = conv.num_params();
+
+        let (conv, mask) = structured_prune(conv, 0.5, PruningCriterion::L1Norm);
+
+        assert_eq!(mask.layers().len(), 1);
+        assert_eq!(mask.layers()[0].num_filters, 16);
+        assert_eq!(mask.num_pruned(), 8);
+        assert_eq!(zero_filters(&conv), [8]);
+        // The shapes are unchanged
+        assert_eq!(conv.num_params(), num_params);
+    }
+
+    #[test]
+    fn l1_norm_prunes_smallest_filters() {
+        let device = Default::default();
+        let mut conv = Conv2dConfig::new([1, 4], [1, 1]).init::<TestBackend>(&device);
+        conv.weight = Param::from_tensor(
+            Tensor::from_floats([3., -0.5, 2., 1.], &device).reshape([4, 1, 1, 1]),
+        );
+
+        let (_, mask) = structured_prune(conv.clone(), 0.5, PruningCriterion::L1Norm);
+        assert_eq!(mask.layers()[0].pruned_filters, [1, 3]);
+        assert_eq!(mask.layers()[0].kept_filters(), [0, 2]);
+
+        let (_, mask) = structured_prune(conv, 0.25, PruningCriterion::L2Norm);
+        assert_eq!(mask.layers()[0].pruned_filters, [1]);
+    }
+
+    #[test]
+    fn hard_pruning_removes_filters() {
+        let device = Default::default();
+        let model = two_convs(&device);
+
+        let (soft, mask) = structured_prune(model, 0.5, PruningCriterion::L2Norm);
+        let x = Tensor::<TestBackend, 4>::ones([1, 3, 6, 6], &device);
+        let expected = soft.forward(x.clone());
+        let model = compact_model(soft, &mask).unwrap();
+
+        assert_eq!(model.first.weight.dims(), [4, 3, 3, 3]);
+        assert_eq!(model.first.bias.as_ref().unwrap().dims(), [4]);
+        // The input channels of the next convolution are removed too
+        assert_eq!(model.second.weight.dims(), [2, 4, 1, 1]);
+
+        // Same outputs for the kept filters
+        let kept = mask.layers()[1].kept_filters();
+        let output = model.forward(x);
+        assert_eq!(output.dims(), [1, 2, 4, 4]);
+        output
+            .into_data()
+            .assert_approx_eq(&keep_channels(expected, 1, kept).into_data(), 4);
+    }
+
+    fn convs(
+        first: Conv2dConfig,
+        second: Conv2dConfig,
+        device: &<TestBackend as Backend>::Device,
+    ) -> TwoConvs<TestBackend> {
+        TwoConvs {
+            first: first.init(device),
+            second: second.init(device),
+        }
+    }
+
+    #[test]
+    fn hard_pruning_concatenation() {
+        let device = Default::default();
+        // The second convolution takes the outputs of the first one concatenated with the input
+        let model = convs(
+            Conv2dConfig::new([3, 8], [3, 3]),
+            Conv2dConfig::new([11, 4], [1, 1]),
+            &device,
+        );
+
+        let (model, mask) = structured_prune(model, 0.5, PruningCriterion::L1Norm);
+
+        assert_eq!(
+            compact_model(model, &mask).unwrap_err(),
+            PruningError::InputMismatch {
+                layer: 0,
+                num_filters: 8,
+                in_channels: 11
+            }
+        );
+    }
+
+    #[test]
+    fn hard_pruning_depthwise() {
+        let device = Default::default();
+        let model = convs(
+            Conv2dConfig::new([3, 8], [1, 1]),
+            Conv2dConfig::new([8, 8], [3, 3]).with_groups(8),
+            &device,
+        );
+
+        // The depthwise convolution does not take all the filters as input
+        let (soft, mask) = structured_prune(model.clone(), 0.5, PruningCriterion::L1Norm);
+        assert_eq!(
+            compact_model(soft, &mask).unwrap_err(),
+            PruningError::InputMismatch {
+                layer: 0,
+                num_filters: 8,
+                in_channels: 1
+            }
+        );
+
+        // Its number of groups cannot be changed
+        let mask = PruningMask {
+            layers: vec![PrunedLayer {
+                param_id: model.second.weight.id.clone(),
+                num_filters: 8,
+                pruned_filters: vec![0, 1],
+            }],
+        };
+        assert_eq!(
+            compact_model(model, &mask).unwrap_err(),
+            PruningError::Depthwise { layer: 0 }
+        );
+    }
+
+    #[test]
+    fn hard_pruning_residual() {
+        let device = Default::default();
+        // The second convolution may be added to its input
+        let model = convs(
+            Conv2dConfig::new([3, 8], [3, 3]),
+            Conv2dConfig::new([8, 8], [3, 3]),
+            &device,
+        );
+
+        let (model, mask) = structured_prune(model, 0.5, PruningCriterion::L1Norm);
+
+        assert_eq!(
+            compact_model(model, &mask).unwrap_err(),
+            PruningError::Residual { layer: 1 }
+        );
+    }
+
+    #[test]
+    fn random_pruning_ratio() {
+        let device = Default::default();
+        let (model, mask) =
+            structured_prune(two_convs(&device), 0.25, PruningCriterion::RandomPrune);
+
+        assert_eq!(mask.num_pruned(), 2 + 1);
+        assert_eq!(zero_filters(&model), [2, 1]);
+    }
+
+    #[test]
+    #[should_panic = "the pruning ratio should be in range [0, 1)"]
+    fn invalid_pruning_ratio() {
+        structured_prune(two_convs(&Default::default()), 1., PruningCriterion::L1Norm);
+    }
+}
@@ -0,0 +1,305 @@
+use alloc::vec::Vec;
+
+use burn::tensor::{
+    backend::Backend,
+    module::interpolate,
+    ops::{InterpolateMode, InterpolateOptions},
+    Tensor,
+};
+
+use super::ensemble::{nms, wbf, FusionStrategy};
+use crate::{
+    model::{
+        darknet::{CspDarknet, DarknetFeatures},
+        DetectionModel,
+    },
+    types::Detection,
+};
+
+/// Image pyramid, which runs a model on the image resized to multiple scales.
+///
+/// Unlike a feature pyramid network, which computes multi-scale features in a single forward
+/// pass, each scale requires a forward pass of the model. This is usually only done at inference,
+/// to improve the detection of small and large objects.
+#[derive(Debug, Clone)]
+pub struct ImagePyramid<M> {
+    model: M,
+    scales: Vec<f32>,
+}
+
+impl<M> ImagePyramid<M> {
+    /// The wrapped model.
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Scale factors of the pyramid levels.
+    pub fn scales(&self) -> &[f32] {
+        &self.scales
+    }
+
+    /// Run the given function of the model on the image resized to each scale.
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: Input image. Shape: `[channels, height, width]`.
+    /// * `f` - Function of the model and of the resized image with shape
+    ///   `[1, channels, height * scale, width * scale]`.
+    ///
+    /// # Returns
+    ///
+    /// The scale factor and the output of each pyramid level.
+    pub fn forward_with<B: Backend, O>(
+        &self,
+        image: Tensor<B, 3>,
+        f: impl Fn(&M, Tensor<B, 4>) -> O,
+    ) -> Vec<(f32, O)> {
+        let [_, height, width] = image.dims();
+        let image = image.unsqueeze::<4>();
+
+        self.scales
+            .iter()
+            .map(|&scale| {
+                // The original image is used as is, so that the outputs match a regular forward
+                let resized = if scale == 1. {
+                    image.clone()
+                } else {
+                    let size = [
+                        ((height as f32 * scale).round() as usize).max(1),
+                        ((width as f32 * scale).round() as usize).max(1),
+                    ];
+                    interpolate(
+                        image.clone(),
+                        size,
+                        InterpolateOptions::new(InterpolateMode::Bilinear),
+                    )
+                };
+
+                (scale, f(&self.model, resized))
+            })
+            .collect()
+    }
+
+    /// Detect the objects at each scale and merge the detections in the original image
+    /// coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: Input image. Shape: `[channels, height, width]`.
+    /// * `merger` - Strategy to merge the detections of the different scales.
+    pub fn detect<B: Backend>(&self, image: Tensor<B, 3>, merger: FusionStrategy) -> Vec<Detection>
+    where
+        M: DetectionModel<B>,
+    {
+        let detections = self.forward_with(image, |model, x| {
+            model.infer(x).into_iter().next().unwrap_or_default()
+        });
+
+        merge_pyramid_detections(detections, merger)
+    }
+}
+
+impl<B: Backend> ImagePyramid<CspDarknet<B>> {
+    /// Extract the backbone features of the image resized to each scale.
+    ///
+    /// # Shapes
+    ///   - image: `[channels, height, width]`
+    pub fn forward(&self, image: Tensor<B, 3>) -> Vec<(f32, DarknetFeatures<B>)> {
+        self.forward_with(image, |backbone, x| backbone.forward(x))
+    }
+}
+
+/// [Image pyramid](ImagePyramid) configuration.
+#[derive(Debug, Clone)]
+pub struct ImagePyramidConfig {
+    scales: Vec<f32>,
+    max_scale: f32,
+}
+
+impl ImagePyramidConfig {
+    /// Create a new instance of the image pyramid [config](ImagePyramidConfig).
+    ///
+    /// # Arguments
+    ///
+    /// * `scales`: Scale factors of the pyramid levels.
+    /// * `max_scale` - Maximum scale factor, to bound the memory usage. Larger scales are clamped
+    ///   to this value.
+    pub fn new(scales: Vec<f32>, max_scale: f32) -> Self {
+        assert!(
+            !scales.is_empty(),
+            "the pyramid should have at least one scale"
+        );
+        assert!(
+            max_scale > 0. && scales.iter().all(|&s| s > 0.),
+            "the scales should be positive"
+        );
+
+        Self { scales, max_scale }
+    }
+
+    /// Initialize a new [image pyramid](ImagePyramid) wrapping the model.
+    pub fn init<M>(&self, model: M) -> ImagePyramid<M> {
+        ImagePyramid {
+            model,
+            scales: self
+                .scales
+                .iter()
+                .map(|&scale| scale.min(self.max_scale))
+                .collect(),
+        }
+    }
+}
+
+/// Merge the detections of the levels of an [image pyramid](ImagePyramid).
+///
+/// The boxes and keypoints are rescaled to the original image coordinates (i.e., divided by the
+/// scale factor) before being merged. The masks are left at the resolution of their level.
+///
+/// # Arguments
+///
+/// * `detections_per_scale`: Scale factor and detections of each pyramid level.
+/// * `merger` - Strategy to merge the detections. The [weighted boxes fusion](FusionStrategy::Wbf)
+///   weights are the weights of the pyramid levels.
+///
+/// # Panics
+///
+/// If the number of weighted boxes fusion weights does not match the number of levels.
+pub fn merge_pyramid_detections(
+    detections_per_scale: Vec<(f32, Vec<Detection>)>,
+    merger: FusionStrategy,
+) -> Vec<Detection> {
+    if let FusionStrategy::Wbf(_, weights) = &merger {
+        assert_eq!(
+            weights.len(),
+            detections_per_scale.len(),
+            "expected one fusion weight per pyramid level"
+        );
+    }
+
+    let detections: Vec<_> = detections_per_scale
+        .into_iter()
+        .enumerate()
+        .flat_map(|(level, (scale, detections))| {
+            detections.into_iter().map(move |mut det| {
+                det.box_xyxy = det.box_xyxy.map(|v| v / scale);
+                if let Some(keypoints) = det.keypoints.as_mut() {
+                    for (x, y, _) in keypoints.iter_mut() {
+                        *x /= scale;
+                        *y /= scale;
+                    }
+                }
+                (level, det)
+            })
+        })
+        .collect();
+
+    match &merger {
+        FusionStrategy::Nms(iou_threshold) => nms(detections, *iou_threshold),
+        FusionStrategy::Wbf(iou_threshold, weights) => wbf(detections, *iou_threshold, weights),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::darknet::CspDarknetConfig, types::IoUThreshold};
+    use alloc::vec;
+    use burn::{backend::NdArray, tensor::Distribution};
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn pyramid_one_output_per_scale() {
+        let device = Default::default();
+        let backbone = CspDarknetConfig::new(0.33, 0.25, false).init::<TestBackend>(&device);
+        let pyramid = ImagePyramidConfig::new(vec![0.5, 1., 2.], 2.).init(backbone);
+        let image = Tensor::random([3, 32, 32], Distribution::Default, &device);
+
+        let outputs = pyramid.forward(image);
+
+        assert_eq!(outputs.len(), pyramid.scales().len());
+        for ((scale, DarknetFeatures(_, _, dark5)), size) in outputs.into_iter().zip([1, 1, 2]) {
+            let [batch_size, _, height, width] = dark5.dims();
+            assert_eq!(
+                [batch_size, height, width],
+                [1, size, size],
+                "scale {scale}"
+            );
+        }
+    }
+
+    #[test]
+    fn pyramid_unit_scale_matches_forward() {
+        let device = Default::default();
+        let backbone = CspDarknetConfig::new(0.33, 0.25, false).init::<TestBackend>(&device);
+        let image = Tensor::<TestBackend, 3>::random([3, 32, 32], Distribution::Default, &device);
+        let DarknetFeatures(_, _, expected) = backbone.forward(image.clone().unsqueeze());
+        let pyramid = ImagePyramidConfig::new(vec![0.5, 1.], 1.).init(backbone);
+
+        let mut outputs = pyramid.forward(image);
+        let (scale, DarknetFeatures(_, _, dark5)) = outputs.pop().unwrap();
+
+        assert_eq!(scale, 1.);
+        dark5.into_data().assert_approx_eq(&expected.into_data(), 5);
+    }
+
+    #[test]
+    fn pyramid_max_scale() {
+        let pyramid = ImagePyramidConfig::new(vec![0.5, 1., 4.], 2.).init(());
+
+        assert_eq!(pyramid.scales(), [0.5, 1., 2.]);
+    }
+
+    #[test]
+    fn merge_pyramid_unscales_boxes() {
+        let mut small = Detection::new(0, [10., 10., 20., 20.], 0.9, 0);
+        small.keypoints = Some(vec![(15., 12., 1.)]);
+        let detections = vec![
+            (0.5, vec![small]),
+            (1., vec![Detection::new(0, [60., 60., 80., 80.], 0.8, 1)]),
+        ];
+
+        let merged = merge_pyramid_detections(
+            detections,
+            FusionStrategy::Nms(IoUThreshold::new(0.5).unwrap()),
+        );
+
+        // The half-size boxes are doubled
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].box_xyxy, [20., 20., 40., 40.]);
+        assert_eq!(merged[0].keypoints, Some(vec![(30., 24., 1.)]));
+        assert_eq!(merged[1].box_xyxy, [60., 60., 80., 80.]);
+    }
+
+    #[test]
+    fn merge_pyramid_duplicates() {
+        let detections = vec![
+            (0.5, vec![Detection::new(0, [10., 10., 20., 20.], 0.9, 0)]),
+            (1., vec![Detection::new(0, [20., 20., 40., 40.], 0.7, 0)]),
+        ];
+
+        let merged = merge_pyramid_detections(
+            detections,
+            FusionStrategy::Nms(IoUThreshold::new(0.5).unwrap()),
+        );
+
+        assert_eq!(merged, [Detection::new(0, [20., 20., 40., 40.], 0.9, 0)]);
+    }
+
+    #[test]
+    #[should_panic = "expected one fusion weight per pyramid level"]
+    fn merge_pyramid_wbf_weights() {
+        let detections = vec![(0.5, vec![]), (1., vec![])];
+
+        merge_pyramid_detections(
+            detections,
+            FusionStrategy::Wbf(IoUThreshold::new(0.5).unwrap(), vec![1.]),
+        );
+    }
+
+    #[test]
+    #[should_panic = "the scales should be positive"]
+    fn pyramid_negative_scale() {
+        ImagePyramidConfig::new(vec![1., -0.5], 2.);
+    }
+}
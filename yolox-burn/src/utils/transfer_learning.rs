@@ -0,0 +1,346 @@
+//! Weight transfer between detection architectures.
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+use burn::{
+    module::{Module, Param},
+    nn::conv::Conv2dRecord,
+    tensor::{backend::Backend, Device, Tensor},
+};
+
+use crate::model::yolox::{YoloxConfig, YoloxRecord};
+
+/// Outcome of a [weight conversion](AnchorFreeConverter::convert), listing the converted
+/// layers by path (e.g., `head.cls_preds.0`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferReport {
+    /// Layers initialized from the source weights.
+    pub transferred: Vec<String>,
+    /// Layers of the target model whose weights could not be mapped, and were set to zero.
+    pub zeroed: Vec<String>,
+    /// Layers of the source model without counterpart in the target model, which were not used.
+    pub skipped: Vec<String>,
+}
+
+impl fmt::Display for TransferReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transferred: {}", self.transferred.join(", "))?;
+        writeln!(f, "Zeroed: {}", self.zeroed.join(", "))?;
+        write!(f, "Skipped: {}", self.skipped.join(", "))
+    }
+}
+
+/// Convert the weights of an anchor-based (YOLOv5-style) detector to the anchor-free YOLOX
+/// detector, for transfer learning.
+///
+/// The source is a [YOLOX](crate::model::yolox::Yolox) record whose detection head uses
+/// [anchor-based decoding](crate::model::head::DetectionHeadMode::AnchorBased), i.e. with
+/// several predictions per location. The backbone, neck and the shared convolutions of the head
+/// are structurally equivalent and copied directly. The classification and objectness
+/// predictions of the anchors are averaged into the single prediction of the anchor-free head
+/// when the number of classes matches, and zeroed otherwise. The number of anchors is given by
+/// the outputs of the source objectness predictions. The box regression predictions use
+/// a different encoding and are always zeroed.
+///
+/// Zeroed layers keep their initial biases, so that the initial predictions follow the prior
+/// probability of the head. The transferred, zeroed and skipped layers are also reported as
+/// `tracing` events.
+pub struct AnchorFreeConverter;
+
+impl AnchorFreeConverter {
+    /// Convert an anchor-based detector record to a YOLOX record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record`: Record of the anchor-based detector.
+    /// * `config` - Configuration of the target YOLOX detector, with the same depth and width as
+    ///   the source detector.
+    /// * `num_classes` - Number of classes of the target detector.
+    /// * `device` - Device of the initialized target weights.
+    pub fn convert<B: Backend>(
+        record: YoloxRecord<B>,
+        config: YoloxConfig,
+        num_classes: usize,
+        device: &Device<B>,
+    ) -> (YoloxRecord<B>, TransferReport) {
+        let mut target = config
+            .with_num_classes(num_classes)
+            .init::<B>(device)
+            .into_record();
+        let mut report = TransferReport::default();
+
+        // Backbone and neck
+        target.backbone = record.backbone;
+        report.transferred("backbone".into());
+
+        // Shared head convolutions
+        let source = record.head;
+        target.head.stems = source.stems;
+        target.head.cls_convs = source.cls_convs;
+        target.head.reg_convs = source.reg_convs;
+        for name in ["stems", "cls_convs", "reg_convs"] {
+            report.transferred(format!("head.{name}"));
+        }
+
+        // Predictions, with one objectness output per anchor
+        let num_anchors = source
+            .obj_preds
+            .first()
+            .map_or(1, |obj_preds| obj_preds.weight.dims()[0]);
+        let branches = [
+            (
+                "cls_preds",
+                source.cls_preds,
+                &mut target.head.cls_preds,
+                true,
+            ),
+            (
+                "obj_preds",
+                source.obj_preds,
+                &mut target.head.obj_preds,
+                true,
+            ),
+            (
+                "reg_preds",
+                source.reg_preds,
+                &mut target.head.reg_preds,
+                false,
+            ),
+        ];
+        for (name, sources, targets, transferable) in branches {
+            let num_levels = sources.len().max(targets.len());
+            let mut sources = sources.into_iter();
+            for i in 0..num_levels {
+                let path = format!("head.{name}.{i}");
+                let source = sources.next();
+                let Some(target) = targets.get_mut(i) else {
+                    report.skipped(path);
+                    continue;
+                };
+                let weights = match (transferable, &source) {
+                    (true, Some(source)) => merge_anchors(source, target, num_anchors),
+                    _ => None,
+                };
+
+                match weights {
+                    Some((weight, bias)) => {
+                        target.weight = Param::from_tensor(weight);
+                        target.bias = bias.map(Param::from_tensor);
+                        report.transferred(path);
+                    }
+                    None => {
+                        target.weight = Param::from_tensor(target.weight.val().zeros_like());
+                        report.zeroed(path);
+                    }
+                }
+            }
+        }
+
+        (target, report)
+    }
+}
+
+impl TransferReport {
+    /// Record a layer initialized from the source weights.
+    fn transferred(&mut self, path: String) {
+        tracing::debug!("transferred {path}");
+        self.transferred.push(path);
+    }
+
+    /// Record a target layer set to zero.
+    fn zeroed(&mut self, path: String) {
+        tracing::warn!("zeroed {path}: no matching source weights");
+        self.zeroed.push(path);
+    }
+
+    /// Record an unused source layer.
+    fn skipped(&mut self, path: String) {
+        tracing::warn!("skipped {path}: no matching target layer");
+        self.skipped.push(path);
+    }
+}
+
+/// Average the per-anchor predictions of an anchor-based prediction layer, if the number of
+/// outputs per anchor matches the outputs of the anchor-free `target` layer.
+fn merge_anchors<B: Backend>(
+    source: &Conv2dRecord<B>,
+    target: &Conv2dRecord<B>,
+    num_anchors: usize,
+) -> Option<(Tensor<B, 4>, Option<Tensor<B, 1>>)> {
+    let [source_out, source_in, k1, k2] = source.weight.dims();
+    let [target_out, target_in, ..] = target.weight.dims();
+    if source_in != target_in || source_out != num_anchors * target_out {
+        return None;
+    }
+
+    // The outputs are grouped by anchor [num_anchors, num_outputs]
+    let weight = source
+        .weight
+        .val()
+        .reshape([num_anchors, target_out * source_in * k1 * k2])
+        .mean_dim(0)
+        .reshape([target_out, source_in, k1, k2]);
+    let bias = source.bias.as_ref().map(|bias| {
+        bias.val()
+            .reshape([num_anchors, target_out])
+            .mean_dim(0)
+            .reshape([target_out])
+    });
+
+    Some((weight, bias))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        head::{DetectionHead, DetectionHeadConfig, DetectionHeadMode},
+        yolox::Yolox,
+    };
+    use alloc::vec;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn nano(num_classes: usize) -> YoloxConfig {
+        YoloxConfig::new(0.33, 0.25, num_classes, true)
+    }
+
+    /// Anchor-based YOLOX-Nano head, with 3 anchors per location.
+    fn anchor_based_head(
+        num_classes: usize,
+        device: &Device<TestBackend>,
+    ) -> DetectionHead<TestBackend> {
+        DetectionHeadConfig::new(vec![64, 128, 256], num_classes, 3)
+            .with_depthwise(true)
+            .with_mode(DetectionHeadMode::AnchorBased(vec![(16., 16.); 9]))
+            .init(device)
+    }
+
+    /// Convert the backbone of `source` with an anchor-based head to a YOLOX-Nano record with 4
+    /// classes.
+    fn convert(
+        source: &Yolox<TestBackend>,
+        head: &DetectionHead<TestBackend>,
+        device: &Device<TestBackend>,
+    ) -> (YoloxRecord<TestBackend>, TransferReport) {
+        let mut record = source.clone().into_record();
+        record.head = head.clone().into_record();
+
+        AnchorFreeConverter::convert(record, nano(80), 4, device)
+    }
+
+    fn is_zero(tensor: Tensor<TestBackend, 4>) -> bool {
+        tensor.abs().sum().into_scalar() == 0.
+    }
+
+    #[test]
+    fn converted_backbone_parameters() {
+        let device = Default::default();
+        let source = nano(4).init(&device);
+        let (record, _) = convert(&source, &anchor_based_head(4, &device), &device);
+
+        let head_params = DetectionHeadConfig::yolox(4, 0.25, true)
+            .init::<TestBackend>(&device)
+            .num_params();
+        let expected = nano(4).init::<TestBackend>(&device).num_params() - head_params;
+        let model = nano(4).init(&device).load_record(record);
+        assert_eq!(model.num_params() - head_params, expected);
+
+        // The backbone weights are copied: replacing the converted backbone by the source one
+        // does not change the outputs
+        let mut record = model.clone().into_record();
+        record.backbone = source.into_record().backbone;
+        let reference = nano(4).init(&device).load_record(record);
+        let x = Tensor::<TestBackend, 4>::ones([1, 3, 64, 64], &device);
+        model
+            .forward(x.clone())
+            .into_data()
+            .assert_eq(&reference.forward(x).into_data(), true);
+    }
+
+    #[test]
+    fn converted_record_loads_in_yolox() {
+        let device = Default::default();
+        let source = nano(4).init(&device);
+        let (record, _) = convert(&source, &anchor_based_head(4, &device), &device);
+
+        let expected = nano(4).init::<TestBackend>(&device).num_params();
+        let model = nano(4).init(&device).load_record(record);
+        assert_eq!(model.num_params(), expected);
+
+        let output = model.forward(Tensor::ones([1, 3, 64, 64], &device));
+        assert_eq!(output.dims(), [1, 84, 9]);
+    }
+
+    #[test]
+    fn matching_classes_merge_anchors() {
+        let device = Default::default();
+        let head = anchor_based_head(4, &device);
+        let (record, report) = convert(&nano(4).init(&device), &head, &device);
+
+        // Mean of the predictions of the 3 anchors
+        let expected = head.into_record().cls_preds[0]
+            .weight
+            .val()
+            .reshape([3, 4 * 64])
+            .mean_dim(0)
+            .reshape([4, 64, 1, 1]);
+        record.head.cls_preds[0]
+            .weight
+            .val()
+            .into_data()
+            .assert_approx_eq(&expected.into_data(), 5);
+
+        assert!(report.transferred.contains(&"head.cls_preds.0".into()));
+        assert!(report.transferred.contains(&"head.obj_preds.2".into()));
+        assert!(report.zeroed.contains(&"head.reg_preds.1".into()));
+        assert!(is_zero(record.head.reg_preds[1].weight.val()));
+    }
+
+    #[test]
+    fn mismatched_classes_are_zeroed() {
+        let device = Default::default();
+        let source = nano(4).init(&device);
+        let (record, report) = convert(&source, &anchor_based_head(5, &device), &device);
+
+        for i in 0..3 {
+            assert_eq!(record.head.cls_preds[i].weight.dims(), [4, 64, 1, 1]);
+            assert!(is_zero(record.head.cls_preds[i].weight.val()));
+            assert!(report.zeroed.contains(&format!("head.cls_preds.{i}")));
+        }
+        // The objectness has a single output per anchor
+        assert!(report.transferred.contains(&"head.obj_preds.0".into()));
+        assert!(report.transferred.contains(&"backbone".into()));
+        // The zeroed layers have a source counterpart
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn anchors_from_objectness_outputs() {
+        let device = Default::default();
+        let source = nano(4).init(&device);
+        // 3 anchors with 8 classes: the 24 outputs are also divisible by the 4 target classes
+        let (record, report) = convert(&source, &anchor_based_head(8, &device), &device);
+
+        assert!(is_zero(record.head.cls_preds[0].weight.val()));
+        assert!(report.zeroed.contains(&"head.cls_preds.0".into()));
+        assert!(report.transferred.contains(&"head.obj_preds.0".into()));
+    }
+
+    #[test]
+    fn extra_source_levels_are_skipped() {
+        let device = Default::default();
+        let mut record = nano(4).init::<TestBackend>(&device).into_record();
+        record.head = anchor_based_head(4, &device).into_record();
+        // Additional prediction level of the source head
+        let extra = anchor_based_head(4, &device).into_record().cls_preds.pop();
+        record.head.cls_preds.extend(extra);
+
+        let (_, report) = AnchorFreeConverter::convert(record, nano(80), 4, &device);
+
+        assert_eq!(report.skipped, ["head.cls_preds.3"]);
+        assert!(!report.zeroed.contains(&"head.cls_preds.3".into()));
+        assert!(report.transferred.contains(&"head.cls_preds.2".into()));
+    }
+}
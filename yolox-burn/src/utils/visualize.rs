@@ -0,0 +1,371 @@
+//! Drawing of the detected boxes and labels on images.
+use burn::tensor::{backend::Backend, ElementConversion, Tensor, TensorData};
+use image::{codecs::jpeg::JpegEncoder, ColorType, ImageError, Rgb, RgbImage};
+
+use crate::types::{ConfThreshold, Detection};
+
+/// Default colors of the classes, cycled when there are more classes than colors.
+pub const DEFAULT_PALETTE: [[u8; 3]; 20] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+    [210, 245, 60],
+    [250, 190, 212],
+    [0, 128, 128],
+    [220, 190, 255],
+    [170, 110, 40],
+    [255, 250, 200],
+    [128, 0, 0],
+    [170, 255, 195],
+    [128, 128, 0],
+    [255, 215, 180],
+    [0, 0, 128],
+    [128, 128, 128],
+];
+
+/// Default width of the box outlines in pixels.
+const LINE_WIDTH: u32 = 2;
+/// Scale of the `3x5` pixel font of the labels.
+const FONT_SCALE: u32 = 2;
+/// JPEG encoding quality.
+const JPEG_QUALITY: u8 = 90;
+
+/// Draw the detected boxes and their labels (class name and score) on images.
+///
+/// The drawing is done on the CPU: the image tensor is copied to a pixel buffer, and the result
+/// is copied back to a tensor on the same device.
+#[derive(Debug, Clone)]
+pub struct DetectionVisualizer {
+    class_names: Vec<String>,
+    palette: Vec<[u8; 3]>,
+    line_width: u32,
+}
+
+impl DetectionVisualizer {
+    /// Create a new detection visualizer.
+    ///
+    /// # Arguments
+    ///
+    /// * `class_names`: Name of each class. The class index is displayed for the other classes.
+    /// * `palette` - Color of each class, cycled when there are more classes than colors
+    ///   (defaults to [DEFAULT_PALETTE]).
+    pub fn new(class_names: Vec<String>, palette: Option<Vec<[u8; 3]>>) -> Self {
+        let palette = palette.unwrap_or_else(|| DEFAULT_PALETTE.to_vec());
+        assert!(!palette.is_empty(), "the palette should not be empty");
+
+        Self {
+            class_names,
+            palette,
+            line_width: LINE_WIDTH,
+        }
+    }
+
+    /// Set the width of the box outlines in pixels (defaults to 2).
+    pub fn with_line_width(mut self, line_width: u32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Draw the detections with a score of at least `score_threshold` on an image.
+    ///
+    /// # Shapes
+    ///   - image: `[3, height, width]` with pixel values in the range `[0, 255]`
+    ///   - output: `[3, height, width]`
+    pub fn draw_detections<B: Backend>(
+        &self,
+        image: Tensor<B, 3>,
+        detections: &[Detection],
+        score_threshold: ConfThreshold,
+    ) -> Tensor<B, 3> {
+        let device = image.device();
+        let mut buffer = to_rgb_image(image);
+
+        for det in detections
+            .iter()
+            .filter(|d| d.score >= score_threshold.value())
+        {
+            let color = Rgb(self.palette[det.class_id % self.palette.len()]);
+            let [xmin, ymin, xmax, ymax] = det.box_xyxy;
+            let (w, h) = (buffer.width() as f32, buffer.height() as f32);
+            let (x0, y0) = (xmin.clamp(0., w - 1.) as u32, ymin.clamp(0., h - 1.) as u32);
+            let (x1, y1) = (xmax.clamp(0., w - 1.) as u32, ymax.clamp(0., h - 1.) as u32);
+
+            for i in 0..self.line_width {
+                draw_rect(
+                    &mut buffer,
+                    [x0 + i, y0 + i, x1.saturating_sub(i), y1.saturating_sub(i)],
+                    color,
+                );
+            }
+
+            let name = match self.class_names.get(det.class_id) {
+                Some(name) => name.clone(),
+                None => det.class_id.to_string(),
+            };
+            draw_label(
+                &mut buffer,
+                &format!("{name} {:.2}", det.score),
+                [x0, y0],
+                color,
+            );
+        }
+
+        let (w, h) = (buffer.width() as usize, buffer.height() as usize);
+        let data = TensorData::new(buffer.into_raw(), [h, w, 3]);
+        Tensor::<B, 3>::from_data(data.convert::<B::FloatElem>(), &device)
+            // [H, W, C] -> [C, H, W]
+            .permute([2, 0, 1])
+    }
+}
+
+/// Encode an image to JPEG.
+///
+/// # Shapes
+///   - image: `[3, height, width]` with pixel values in the range `[0, 255]`
+pub fn encode_jpeg<B: Backend>(image: Tensor<B, 3>) -> Result<Vec<u8>, ImageError> {
+    let buffer = to_rgb_image(image);
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).encode(
+        buffer.as_raw(),
+        buffer.width(),
+        buffer.height(),
+        ColorType::Rgb8,
+    )?;
+
+    Ok(bytes)
+}
+
+/// Copy a `[3, height, width]` image tensor to an RGB pixel buffer.
+fn to_rgb_image<B: Backend>(image: Tensor<B, 3>) -> RgbImage {
+    let [c, h, w] = image.dims();
+    assert_eq!(c, 3, "expected an RGB image");
+
+    let pixels = image
+        // [C, H, W] -> [H, W, C]
+        .permute([1, 2, 0])
+        .into_data()
+        .iter::<B::FloatElem>()
+        .map(|v| v.elem::<f32>().round().clamp(0., 255.) as u8)
+        .collect();
+
+    RgbImage::from_raw(w as u32, h as u32, pixels).unwrap()
+}
+
+/// Draw the outline of a box `[x0, y0, x1, y1]`, which is skipped if empty.
+fn draw_rect(image: &mut RgbImage, [x0, y0, x1, y1]: [u32; 4], color: Rgb<u8>) {
+    if x0 > x1 || y0 > y1 {
+        return;
+    }
+    for x in x0..=x1 {
+        image.put_pixel(x, y0, color);
+        image.put_pixel(x, y1, color);
+    }
+    for y in y0..=y1 {
+        image.put_pixel(x0, y, color);
+        image.put_pixel(x1, y, color);
+    }
+}
+
+/// Draw a label on a filled background of the box color, above the top-left corner of the box
+/// (or below it at the top of the image).
+fn draw_label(image: &mut RgbImage, text: &str, [x, y]: [u32; 2], color: Rgb<u8>) {
+    let (char_w, char_h) = (4 * FONT_SCALE, 5 * FONT_SCALE);
+    let label_w = text.chars().count() as u32 * char_w + FONT_SCALE;
+    let label_h = char_h + 2 * FONT_SCALE;
+    let top = if y >= label_h { y - label_h } else { y };
+
+    // Black or white text, depending on the brightness of the background
+    let [r, g, b] = color.0;
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    let text_color = if luma > 128. {
+        Rgb([0, 0, 0])
+    } else {
+        Rgb([255, 255, 255])
+    };
+
+    let (w, h) = image.dimensions();
+    let mut put = |px: u32, py: u32, c: Rgb<u8>| {
+        if px < w && py < h {
+            image.put_pixel(px, py, c);
+        }
+    };
+    for py in top..top + label_h {
+        for px in x..x + label_w {
+            put(px, py, color);
+        }
+    }
+
+    for (i, ch) in text.chars().enumerate() {
+        let x0 = x + FONT_SCALE + i as u32 * char_w;
+        let y0 = top + FONT_SCALE;
+        for (row, bits) in glyph(ch).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..FONT_SCALE {
+                    for dx in 0..FONT_SCALE {
+                        put(
+                            x0 + col * FONT_SCALE + dx,
+                            y0 + row as u32 * FONT_SCALE + dy,
+                            text_color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rows of the `3x5` pixel glyph of a character, with the leftmost pixel as the highest bit.
+/// Lowercase letters are drawn as uppercase and unsupported characters as blanks.
+fn glyph(ch: char) -> [u8; 5] {
+    const DIGITS: [[u8; 5]; 10] = [
+        [7, 5, 5, 5, 7],
+        [2, 6, 2, 2, 7],
+        [7, 1, 7, 4, 7],
+        [7, 1, 7, 1, 7],
+        [5, 5, 7, 1, 1],
+        [7, 4, 7, 1, 7],
+        [7, 4, 7, 5, 7],
+        [7, 1, 1, 1, 1],
+        [7, 5, 7, 5, 7],
+        [7, 5, 7, 1, 7],
+    ];
+    const LETTERS: [[u8; 5]; 26] = [
+        [2, 5, 7, 5, 5],
+        [6, 5, 6, 5, 6],
+        [3, 4, 4, 4, 3],
+        [6, 5, 5, 5, 6],
+        [7, 4, 6, 4, 7],
+        [7, 4, 6, 4, 4],
+        [3, 4, 5, 5, 3],
+        [5, 5, 7, 5, 5],
+        [7, 2, 2, 2, 7],
+        [1, 1, 1, 5, 2],
+        [5, 5, 6, 5, 5],
+        [4, 4, 4, 4, 7],
+        [5, 7, 7, 5, 5],
+        [6, 5, 5, 5, 5],
+        [2, 5, 5, 5, 2],
+        [6, 5, 6, 4, 4],
+        [2, 5, 5, 6, 3],
+        [6, 5, 6, 5, 5],
+        [3, 4, 2, 1, 6],
+        [7, 2, 2, 2, 2],
+        [5, 5, 5, 5, 7],
+        [5, 5, 5, 5, 2],
+        [5, 5, 7, 7, 5],
+        [5, 5, 2, 5, 5],
+        [5, 5, 2, 2, 2],
+        [7, 1, 2, 4, 7],
+    ];
+
+    match ch.to_ascii_uppercase() {
+        c @ '0'..='9' => DIGITS[c as usize - '0' as usize],
+        c @ 'A'..='Z' => LETTERS[c as usize - 'A' as usize],
+        '.' => [0, 0, 0, 0, 2],
+        '-' => [0, 0, 7, 0, 0],
+        '_' => [0, 0, 0, 0, 7],
+        ':' => [0, 2, 0, 2, 0],
+        '%' => [5, 1, 2, 4, 5],
+        _ => [0; 5],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    fn pixel(image: &Tensor<TestBackend, 3>, x: usize, y: usize) -> Vec<f32> {
+        image
+            .clone()
+            .slice([0..3, y..y + 1, x..x + 1])
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap()
+    }
+
+    #[test]
+    fn draw_detections_shape() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::zeros([3, 48, 64], &device);
+        let visualizer = DetectionVisualizer::new(vec!["person".to_string()], None);
+
+        let output = visualizer.draw_detections(
+            image,
+            &[Detection::new(0, [10., 20., 40., 40.], 0.9, 0)],
+            ConfThreshold::default(),
+        );
+
+        assert_eq!(output.dims(), [3, 48, 64]);
+    }
+
+    #[test]
+    fn draw_detections_box_corners() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::zeros([3, 64, 64], &device);
+        let visualizer = DetectionVisualizer::new(vec!["person".to_string()], None);
+
+        let output = visualizer.draw_detections(
+            image,
+            &[Detection::new(0, [10., 30., 40., 50.], 0.9, 0)],
+            ConfThreshold::default(),
+        );
+
+        let color = DEFAULT_PALETTE[0].map(|c| c as f32).to_vec();
+        for (x, y) in [(10, 30), (40, 30), (10, 50), (40, 50)] {
+            assert_eq!(pixel(&output, x, y), color, "corner ({x}, {y})");
+        }
+        // Inside of the box is not filled
+        assert_eq!(pixel(&output, 25, 40), vec![0.; 3]);
+    }
+
+    #[test]
+    fn draw_detections_below_threshold() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::zeros([3, 64, 64], &device);
+        let visualizer = DetectionVisualizer::new(Vec::new(), None);
+
+        let output = visualizer.draw_detections(
+            image,
+            &[Detection::new(0, [10., 30., 40., 50.], 0.1, 0)],
+            ConfThreshold::default(),
+        );
+
+        assert_eq!(output.max().into_scalar(), 0.);
+    }
+
+    #[test]
+    fn encode_jpeg_magic_bytes() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::full([3, 16, 16], 128., &device);
+
+        let bytes = encode_jpeg(image).unwrap();
+
+        assert_eq!(bytes[..2], [0xFF, 0xD8]);
+    }
+
+    #[test]
+    #[should_panic = "the palette should not be empty"]
+    fn empty_palette() {
+        let _ = DetectionVisualizer::new(Vec::new(), Some(Vec::new()));
+    }
+
+    #[test]
+    #[should_panic = "expected an RGB image"]
+    fn encode_jpeg_not_rgb() {
+        let device = Default::default();
+        let image = Tensor::<TestBackend, 3>::zeros([1, 16, 16], &device);
+
+        let _ = encode_jpeg(image);
+    }
+}
@@ -0,0 +1,173 @@
+"""Generate the PyTorch checkpoint fixtures without a PyTorch install.
+
+The files follow the `torch.save` zip format: a pickled (protocol 2) state dict whose tensors are
+rebuilt with `torch._utils._rebuild_tensor_v2` from persistent storages stored as raw little-endian
+data files in the archive.
+
+Usage: python3 make_pth.py
+"""
+
+import collections
+import os
+import pickle
+import struct
+import sys
+import types
+import zipfile
+
+# Stand-ins for the torch globals referenced by the pickle stream
+torch = types.ModuleType("torch")
+torch_utils = types.ModuleType("torch._utils")
+
+
+class FloatStorage:
+    pass
+
+
+class LongStorage:
+    pass
+
+
+def _rebuild_tensor_v2(*args):
+    raise NotImplementedError
+
+
+for cls in (FloatStorage, LongStorage):
+    cls.__module__ = "torch"
+    setattr(torch, cls.__name__, cls)
+_rebuild_tensor_v2.__module__ = "torch._utils"
+torch_utils._rebuild_tensor_v2 = _rebuild_tensor_v2
+torch._utils = torch_utils
+sys.modules["torch"] = torch
+sys.modules["torch._utils"] = torch_utils
+
+
+class Storage:
+    def __init__(self, key, storage_type, numel):
+        self.key = key
+        self.storage_type = storage_type
+        self.numel = numel
+
+
+class Tensor:
+    def __init__(self, storage, shape):
+        self.storage = storage
+        self.shape = tuple(shape)
+
+    def __reduce__(self):
+        stride, step = [], 1
+        for dim in reversed(self.shape):
+            stride.insert(0, step)
+            step *= dim
+        args = (self.storage, 0, self.shape, tuple(stride), False, collections.OrderedDict())
+        return (_rebuild_tensor_v2, args)
+
+
+class Pickler(pickle.Pickler):
+    def persistent_id(self, obj):
+        if isinstance(obj, Storage):
+            return ("storage", obj.storage_type, obj.key, "cpu", obj.numel)
+        return None
+
+
+def save(path, state_dict, top_level_key=None):
+    """Save a state dict of `name -> (shape, values)`, with integer values for `LongStorage`."""
+    name = os.path.splitext(os.path.basename(path))[0]
+    tensors = collections.OrderedDict()
+    data = []
+    for i, (key, (shape, values)) in enumerate(state_dict.items()):
+        is_int = all(isinstance(v, int) for v in values)
+        storage_type, fmt = (LongStorage, "q") if is_int else (FloatStorage, "f")
+        tensors[key] = Tensor(Storage(str(i), storage_type, len(values)), shape)
+        data.append(struct.pack(f"<{len(values)}{fmt}", *values))
+
+    obj = tensors
+    if top_level_key is not None:
+        obj = {"start_epoch": 300, top_level_key: tensors}
+
+    with zipfile.ZipFile(path, "w", zipfile.ZIP_STORED) as archive:
+        with archive.open(f"{name}/data.pkl", "w") as f:
+            Pickler(f, protocol=2).dump(obj)
+        for i, values in enumerate(data):
+            archive.writestr(f"{name}/data/{i}", values)
+        archive.writestr(f"{name}/version", "3\n")
+
+
+def base_conv(state_dict, prefix, c_in, c_out, k, groups=1, value=0.01):
+    """Parameters of a YOLOX `BaseConv` (bias-free convolution and batch norm)."""
+    shape = [c_out, c_in // groups, k, k]
+    state_dict[f"{prefix}.conv.weight"] = (shape, [value] * (c_out * c_in // groups * k * k))
+    for name in ("weight", "bias", "running_mean", "running_var"):
+        state_dict[f"{prefix}.bn.{name}"] = ([c_out], [value] * c_out)
+
+
+def dw_conv(state_dict, prefix, c_in, c_out):
+    """Parameters of a YOLOX `DWConv`."""
+    base_conv(state_dict, f"{prefix}.dconv", c_in, c_in, 3, groups=c_in)
+    base_conv(state_dict, f"{prefix}.pconv", c_in, c_out, 1)
+
+
+def csp_layer(state_dict, prefix, channels, n):
+    """Parameters of a YOLOX `CSPLayer` with depthwise bottlenecks."""
+    hidden = channels // 2
+    base_conv(state_dict, f"{prefix}.conv1", channels, hidden, 1)
+    base_conv(state_dict, f"{prefix}.conv2", channels, hidden, 1)
+    base_conv(state_dict, f"{prefix}.conv3", 2 * hidden, channels, 1)
+    for i in range(n):
+        base_conv(state_dict, f"{prefix}.m.{i}.conv1", hidden, hidden, 1)
+        dw_conv(state_dict, f"{prefix}.m.{i}.conv2", hidden, hidden)
+
+
+def yolox_nano_backbone():
+    """Official parameter names and shapes of the YOLOX-Nano backbone, filled with 0.01."""
+    state_dict = collections.OrderedDict()
+    p = "backbone.backbone"
+    base_conv(state_dict, f"{p}.stem.conv", 12, 16, 3)
+    for i, (c, n) in enumerate([(32, 1), (64, 3), (128, 3), (256, 1)]):
+        dark = f"{p}.dark{i + 2}"
+        dw_conv(state_dict, f"{dark}.0", c // 2, c)
+        if i == 3:
+            base_conv(state_dict, f"{dark}.1.conv1", c, c // 2, 1)
+            base_conv(state_dict, f"{dark}.1.conv2", c * 2, c, 1)
+            csp_layer(state_dict, f"{dark}.2", c, n)
+        else:
+            csp_layer(state_dict, f"{dark}.1", c, n)
+    return state_dict
+
+
+def main():
+    here = os.path.dirname(os.path.abspath(__file__))
+
+    # nn.Conv2d(1, 2, kernel_size=2) in a YOLOX-like checkpoint
+    save(
+        os.path.join(here, "conv2d.pth"),
+        {
+            "conv.weight": ([2, 1, 2, 2], [0.1 * i for i in range(8)]),
+            "conv.bias": ([2], [0.5, -0.5]),
+        },
+        top_level_key="model",
+    )
+
+    # YOLOX BaseConv(1, 2, ksize=1, stride=1): bias-free convolution and batch norm
+    save(
+        os.path.join(here, "base_conv.pth"),
+        {
+            "conv.weight": ([2, 1, 1, 1], [2.0, -1.0]),
+            "bn.weight": ([2], [1.5, 0.5]),
+            "bn.bias": ([2], [0.25, -0.25]),
+            "bn.running_mean": ([2], [1.0, 2.0]),
+            "bn.running_var": ([2], [4.0, 0.25]),
+            "bn.num_batches_tracked": ([], [1000]),
+        },
+    )
+
+    # Backbone of a YOLOX-Nano checkpoint
+    save(
+        os.path.join(here, "yolox_nano_backbone.pth"),
+        yolox_nano_backbone(),
+        top_level_key="model",
+    )
+
+
+if __name__ == "__main__":
+    main()